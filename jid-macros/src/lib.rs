@@ -0,0 +1,61 @@
+// Copyright (c) 2024 xmpp-rs contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#![deny(missing_docs)]
+
+//! Macros to build Jabber IDs from string literals.
+//!
+//! The literal is parsed and stringprepped while compiling, so an invalid
+//! JID is a compile error and the macros expand to an infallible
+//! construction, without any `.unwrap()` at runtime.
+//!
+//! ```
+//! use jid_macros::{bare_jid, full_jid, jid};
+//!
+//! let jid: jid::Jid = jid!("user@example.com/res");
+//! assert_eq!(jid.resource().map(|x| x.as_str()), Some("res"));
+//!
+//! let bare: jid::BareJid = bare_jid!("user@EXAMPLE.com");
+//! assert_eq!(bare.as_str(), "user@example.com");
+//!
+//! let full: jid::FullJid = full_jid!("example.com/res");
+//! assert_eq!(full.node(), None);
+//! ```
+//!
+//! ```compile_fail
+//! let jid = jid_macros::jid!("@example.com");
+//! ```
+
+use proc_macro::TokenStream;
+use quote::ToTokens;
+use syn::{parse_macro_input, LitStr};
+
+fn expand<J: ToTokens>(input: LitStr, parse: fn(&str) -> Result<J, jid::Error>) -> TokenStream {
+    match parse(&input.value()) {
+        Ok(jid) => jid.into_token_stream().into(),
+        Err(err) => syn::Error::new(input.span(), format!("invalid JID: {}", err))
+            .into_compile_error()
+            .into(),
+    }
+}
+
+/// Builds a [`jid::Jid`] from a string literal.
+#[proc_macro]
+pub fn jid(input: TokenStream) -> TokenStream {
+    expand(parse_macro_input!(input as LitStr), jid::Jid::new)
+}
+
+/// Builds a [`jid::BareJid`] from a string literal.
+#[proc_macro]
+pub fn bare_jid(input: TokenStream) -> TokenStream {
+    expand(parse_macro_input!(input as LitStr), jid::BareJid::new)
+}
+
+/// Builds a [`jid::FullJid`] from a string literal.
+#[proc_macro]
+pub fn full_jid(input: TokenStream) -> TokenStream {
+    expand(parse_macro_input!(input as LitStr), jid::FullJid::new)
+}
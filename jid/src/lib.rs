@@ -625,12 +625,35 @@ impl<'de> Deserialize<'de> for BareJid {
     }
 }
 
+#[cfg(feature = "quote")]
+fn node_to_tokens(node: Option<&NodeRef>) -> TokenStream {
+    match node {
+        Some(node) => {
+            let node = node.as_str();
+            quote!(Some(::jid::NodeRef::from_str_unchecked(#node)))
+        }
+        None => quote!(None),
+    }
+}
+
 #[cfg(feature = "quote")]
 impl ToTokens for Jid {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let s = &self.normalized;
+        let node = node_to_tokens(self.node());
+        let domain = self.domain().as_str();
+        let resource = match self.resource() {
+            Some(resource) => {
+                let resource = resource.as_str();
+                quote!(Some(::jid::ResourceRef::from_str_unchecked(#resource)))
+            }
+            None => quote!(None),
+        };
         tokens.extend(quote! {
-            ::jid::Jid::new(#s).unwrap()
+            ::jid::Jid::from_parts(
+                #node,
+                ::jid::DomainRef::from_str_unchecked(#domain),
+                #resource,
+            )
         });
     }
 }
@@ -638,9 +661,15 @@ impl ToTokens for Jid {
 #[cfg(feature = "quote")]
 impl ToTokens for FullJid {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let s = &self.inner.normalized;
+        let node = node_to_tokens(self.node());
+        let domain = self.domain().as_str();
+        let resource = self.resource().as_str();
         tokens.extend(quote! {
-            ::jid::FullJid::new(#s).unwrap()
+            ::jid::FullJid::from_parts(
+                #node,
+                ::jid::DomainRef::from_str_unchecked(#domain),
+                ::jid::ResourceRef::from_str_unchecked(#resource),
+            )
         });
     }
 }
@@ -648,9 +677,13 @@ impl ToTokens for FullJid {
 #[cfg(feature = "quote")]
 impl ToTokens for BareJid {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let s = &self.inner.normalized;
+        let node = node_to_tokens(self.node());
+        let domain = self.domain().as_str();
         tokens.extend(quote! {
-            ::jid::BareJid::new(#s).unwrap()
+            ::jid::BareJid::from_parts(
+                #node,
+                ::jid::DomainRef::from_str_unchecked(#domain),
+            )
         });
     }
 }
@@ -162,7 +162,10 @@ macro_rules! def_part_types {
         pub struct $borrowed(pub(crate) str);
 
         impl $borrowed {
-            pub(crate) fn from_str_unchecked(s: &str) -> &Self {
+            // Used by the code generated with quote, which has already
+            // been validated when it got generated.
+            #[doc(hidden)]
+            pub fn from_str_unchecked(s: &str) -> &Self {
                 // SAFETY: repr(transparent) thing can be transmuted to/from
                 // its inner.
                 unsafe { mem::transmute(s) }
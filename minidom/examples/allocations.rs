@@ -0,0 +1,58 @@
+// Copyright (c) 2024 xmpp-rs contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Counts the allocations done while parsing a stream of stanzas, and the
+//! memory the resulting elements keep alive.
+//!
+//! Run with `cargo run --release --example allocations`.
+
+use minidom::Element;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const STANZA: &str = "<message xmlns='jabber:client' to='juliet@capulet.example/balcony' from='romeo@montague.example/garden' type='chat' id='abcd'><body>Art thou not Romeo, and a Montague?</body><active xmlns='http://jabber.org/protocol/chatstates'/><request xmlns='urn:xmpp:receipts'/><origin-id xmlns='urn:xmpp:sid:0' id='abcd'/><thread>e0ffe42b28561960c6b12b944a092794b9683a38</thread></message>";
+
+const COUNT: usize = 10_000;
+
+fn main() {
+    let mut document = String::from("<stream xmlns='jabber:client'>");
+    for _ in 0..COUNT {
+        document.push_str(STANZA);
+    }
+    document.push_str("</stream>");
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let live_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+    let root: Element = document.parse().unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let live_bytes = LIVE_BYTES.load(Ordering::Relaxed) - live_bytes;
+
+    assert_eq!(root.children().count(), COUNT);
+    println!("size of Element: {} bytes", std::mem::size_of::<Element>());
+    println!("allocations per stanza: {}", allocations / COUNT);
+    println!("bytes kept per stanza: {}", live_bytes / COUNT);
+}
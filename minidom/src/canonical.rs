@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Canonical serialisation of elements, stable across versions of this crate and of rxml.
+//!
+//! The output is modelled after Canonical XML (C14N):
+//! - elements are never prefixed, and `xmlns` is only emitted when the namespace differs from
+//!   the parent’s,
+//! - prefixes are only declared for the attributes which use them, on the element where they
+//!   get used first,
+//! - attributes are sorted by namespace then local name, unqualified ones first, and namespace
+//!   declarations by prefix,
+//! - empty elements are written as a start and end tag pair,
+//! - attribute values are always double-quoted, and text and attribute values escaped the same
+//!   way as in C14N,
+//! - adjacent text nodes are merged, and there is no XML declaration.
+
+use crate::element::Element;
+use crate::error::{Error, Result};
+use crate::node::Node;
+use crate::prefixes::Prefixes;
+
+use std::io::Write;
+
+/// Namespace bound to the `xml` prefix.
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+impl Element {
+    /// Output this element in a canonical form, meant to be hashed or compared byte for byte.
+    ///
+    /// Two elements with the same names, namespaces, attributes and text produce the same
+    /// output, regardless of how they were built or parsed (prefixes, attribute order, etc.).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use minidom::Element;
+    ///
+    /// let a: Element = "<p:a xmlns:p='ns' y='2' x='1'><p:b/></p:a>".parse().unwrap();
+    /// let b: Element = "<a xmlns='ns' x='1' y='2'><b></b></a>".parse().unwrap();
+    ///
+    /// let canonical = a.to_canonical_string().unwrap();
+    /// assert_eq!(canonical, r#"<a xmlns="ns" x="1" y="2"><b></b></a>"#);
+    /// assert_eq!(canonical, b.to_canonical_string().unwrap());
+    /// ```
+    pub fn write_canonical_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let canonical = self.to_canonical_string()?;
+        writer
+            .write_all(canonical.as_bytes())
+            .map_err(|err| Error::XmlError(err.into()))
+    }
+
+    /// Returns this element in the canonical form of [`Element::write_canonical_to`].
+    pub fn to_canonical_string(&self) -> Result<String> {
+        let mut out = String::new();
+        canonicalize(self, None, &mut Vec::new(), &mut Vec::new(), &mut out)?;
+        Ok(out)
+    }
+}
+
+fn canonicalize<'a>(
+    elem: &'a Element,
+    parent_ns: Option<&str>,
+    scope: &mut Vec<&'a Prefixes>,
+    declared: &mut Vec<(&'a str, &'a str)>,
+    out: &mut String,
+) -> Result<()> {
+    scope.push(&elem.prefixes);
+    let declared_len = declared.len();
+    let ns = elem.ns_str();
+
+    out.push('<');
+    out.push_str(elem.name());
+    if parent_ns != Some(ns) && !(parent_ns.is_none() && ns.is_empty()) {
+        out.push_str(" xmlns=\"");
+        escape_attr(ns, out);
+        out.push('"');
+    }
+
+    // (namespace, local name, name, value), unqualified attributes having no namespace.
+    let mut attrs: Vec<(&str, &str, &str, &str)> = Vec::new();
+    let mut new_prefixes: Vec<(&str, &str)> = Vec::new();
+    for (name, value) in elem.attrs() {
+        let (prefix, local) = match name.split_once(':') {
+            Some(("xml", local)) => {
+                attrs.push((XML_NS, local, name, value));
+                continue;
+            }
+            None => {
+                attrs.push(("", name, name, value));
+                continue;
+            }
+            Some(split) => split,
+        };
+        let prefix_ns = lookup_prefix(scope, prefix).ok_or(Error::InvalidPrefix)?;
+        attrs.push((prefix_ns, local, name, value));
+        let in_scope = declared.iter().rev().find(|(p, _)| *p == prefix);
+        if in_scope.map(|(_, ns)| *ns) != Some(prefix_ns)
+            && !new_prefixes.iter().any(|(p, _)| *p == prefix)
+        {
+            new_prefixes.push((prefix, prefix_ns));
+        }
+    }
+    new_prefixes.sort_unstable();
+    for &(prefix, prefix_ns) in &new_prefixes {
+        out.push_str(" xmlns:");
+        out.push_str(prefix);
+        out.push_str("=\"");
+        escape_attr(prefix_ns, out);
+        out.push('"');
+    }
+    declared.extend(new_prefixes);
+
+    attrs.sort_unstable();
+    for (_, _, name, value) in attrs {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        escape_attr(value, out);
+        out.push('"');
+    }
+    out.push('>');
+
+    for node in elem.nodes() {
+        match node {
+            Node::Element(child) => canonicalize(child, Some(ns), scope, declared, out)?,
+            Node::Text(text) => escape_text(text, out),
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(elem.name());
+    out.push('>');
+
+    declared.truncate(declared_len);
+    scope.pop();
+    Ok(())
+}
+
+fn lookup_prefix<'a>(scope: &[&'a Prefixes], prefix: &str) -> Option<&'a str> {
+    scope.iter().rev().find_map(|prefixes| {
+        prefixes
+            .declared_prefixes()
+            .iter()
+            .find(|(p, _)| p.as_deref() == Some(prefix))
+            .map(|(_, ns)| ns.as_str())
+    })
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn escape_attr(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -28,6 +28,7 @@ use std::str;
 
 use rxml::writer::{Encoder, Item, TrackNamespace};
 use rxml::{RawParser, Reader, XmlVersion};
+use smartstring::{LazyCompact, SmartString};
 
 use std::str::FromStr;
 
@@ -114,14 +115,18 @@ pub fn escape(raw: &[u8]) -> Cow<[u8]> {
     }
 }
 
+pub(crate) type Attributes = BTreeMap<SmartString<LazyCompact>, String>;
+
 #[derive(Clone, Eq, Debug)]
 /// A struct representing a DOM Element.
 pub struct Element {
-    name: String,
-    namespace: String,
+    // Short names are stored inline, and namespaces are shared with the parent element when
+    // parsing, as most elements have no other data on the heap.
+    name: SmartString<LazyCompact>,
+    namespace: Arc<str>,
     /// Namespace declarations
     pub prefixes: Prefixes,
-    attributes: BTreeMap<String, String>,
+    attributes: Attributes,
     children: Vec<Node>,
 }
 
@@ -159,10 +164,10 @@ impl PartialEq for Element {
 
 impl Element {
     pub(crate) fn new<P: Into<Prefixes>>(
-        name: String,
-        namespace: String,
+        name: SmartString<LazyCompact>,
+        namespace: Arc<str>,
         prefixes: P,
-        attributes: BTreeMap<String, String>,
+        attributes: Attributes,
         children: Vec<Node>,
     ) -> Element {
         Element {
@@ -195,8 +200,8 @@ impl Element {
     pub fn builder<S: AsRef<str>, NS: Into<String>>(name: S, namespace: NS) -> ElementBuilder {
         ElementBuilder {
             root: Element::new(
-                name.as_ref().to_string(),
-                namespace.into(),
+                name.as_ref().into(),
+                namespace.into().into(),
                 None,
                 BTreeMap::new(),
                 Vec::new(),
@@ -220,8 +225,8 @@ impl Element {
     /// ```
     pub fn bare<S: Into<String>, NS: Into<String>>(name: S, namespace: NS) -> Element {
         Element::new(
-            name.into(),
-            namespace.into(),
+            name.into().into(),
+            namespace.into().into(),
             None,
             BTreeMap::new(),
             Vec::new(),
@@ -233,9 +238,18 @@ impl Element {
         &self.name
     }
 
+    #[cfg(test)]
+    pub(crate) fn namespace_arc(&self) -> Arc<str> {
+        Arc::clone(&self.namespace)
+    }
+
+    pub(crate) fn ns_str(&self) -> &str {
+        &self.namespace
+    }
+
     /// Returns a reference to the namespace of this element.
     pub fn ns(&self) -> String {
-        self.namespace.clone()
+        self.namespace.to_string()
     }
 
     /// Returns a reference to the value of the given attribute, if it exists, else `None`.
@@ -279,14 +293,14 @@ impl Element {
         let name = name.into();
         let val = val.into_attribute_value();
 
-        if let Some(value) = self.attributes.get_mut(&name) {
+        if let Some(value) = self.attributes.get_mut(name.as_str()) {
             *value = val
                 .expect("removing existing value via set_attr, this is not yet supported (TODO)"); // TODO
             return;
         }
 
         if let Some(val) = val {
-            self.attributes.insert(name, val);
+            self.attributes.insert(name.into(), val);
         }
     }
 
@@ -402,7 +416,7 @@ impl Element {
         let namespace = if self.namespace.is_empty() {
             None
         } else {
-            Some(Arc::new(self.namespace.clone().try_into()?))
+            Some(Arc::new((*self.namespace).try_into()?))
         };
         writer.write(Item::ElementHeadStart(namespace, (*self.name).try_into()?))?;
 
@@ -863,7 +877,7 @@ pub type NodesMut<'a> = slice::IterMut<'a, Node>;
 
 /// An iterator over the attributes of an `Element`.
 pub struct Attrs<'a> {
-    iter: btree_map::Iter<'a, String, String>,
+    iter: btree_map::Iter<'a, SmartString<LazyCompact>, String>,
 }
 
 impl<'a> Iterator for Attrs<'a> {
@@ -876,7 +890,7 @@ impl<'a> Iterator for Attrs<'a> {
 
 /// An iterator over the attributes of an `Element`, with the values mutable.
 pub struct AttrsMut<'a> {
-    iter: btree_map::IterMut<'a, String, String>,
+    iter: btree_map::IterMut<'a, SmartString<LazyCompact>, String>,
 }
 
 impl<'a> Iterator for AttrsMut<'a> {
@@ -946,10 +960,10 @@ mod tests {
     #[test]
     fn test_element_new() {
         let elem = Element::new(
-            "name".to_owned(),
-            "namespace".to_owned(),
+            "name".into(),
+            "namespace".into(),
             (None, "namespace".to_owned()),
-            BTreeMap::from_iter(vec![("name".to_string(), "value".to_string())].into_iter()),
+            BTreeMap::from_iter(vec![("name".into(), "value".to_string())].into_iter()),
             Vec::new(),
         );
 
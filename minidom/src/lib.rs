@@ -75,6 +75,7 @@
 //! minidom = "*"
 //! ```
 
+mod canonical;
 pub mod convert;
 pub mod element;
 pub mod error;
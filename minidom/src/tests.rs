@@ -464,3 +464,98 @@ fn misserialisation() {
     let data = String::from(&elem);
     assert_eq!(xml, data);
 }
+
+#[test]
+fn shared_namespaces() {
+    let elem: Element = "<a xmlns='ns1'><b><c xmlns:p='ns2'><p:d><e/></p:d></c></b></a>"
+        .parse()
+        .unwrap();
+    let b = elem.get_child("b", "ns1").unwrap();
+    let c = b.get_child("c", "ns1").unwrap();
+    let d = c.get_child("d", "ns2").unwrap();
+    let e = d.get_child("e", "ns1").unwrap();
+    assert!(std::sync::Arc::ptr_eq(
+        &elem.namespace_arc(),
+        &e.namespace_arc()
+    ));
+
+    // Not only with ancestors, but also between unrelated elements.
+    let elem: Element = "<a xmlns='ns1'><b xmlns='ns2'/><c><d xmlns='ns2'/></c></a>"
+        .parse()
+        .unwrap();
+    let b = elem.get_child("b", "ns2").unwrap();
+    let d = elem
+        .get_child("c", "ns1")
+        .unwrap()
+        .get_child("d", "ns2")
+        .unwrap();
+    assert!(std::sync::Arc::ptr_eq(
+        &b.namespace_arc(),
+        &d.namespace_arc()
+    ));
+}
+
+#[test]
+fn canonical_serialisation() {
+    let a: Element = "<p:a xmlns:p='ns1' z='3' a='1'><p:b/>text<c xmlns='ns2'/></p:a>"
+        .parse()
+        .unwrap();
+    let b = Element::builder("a", "ns1")
+        .attr("a", "1")
+        .attr("z", "3")
+        .append(Element::bare("b", "ns1"))
+        .append("te")
+        .append("xt")
+        .append(Element::bare("c", "ns2"))
+        .build();
+    let canonical = a.to_canonical_string().unwrap();
+    assert_eq!(
+        canonical,
+        r#"<a xmlns="ns1" a="1" z="3"><b></b>text<c xmlns="ns2"></c></a>"#
+    );
+    assert_eq!(canonical, b.to_canonical_string().unwrap());
+
+    let mut writer = Vec::new();
+    b.write_canonical_to(&mut writer).unwrap();
+    assert_eq!(writer, canonical.as_bytes());
+}
+
+#[test]
+fn canonical_prefixed_attributes() {
+    let elem: Element =
+        "<a xmlns='ns1' xmlns:unused='ns3' xmlns:p='ns2' xml:lang='en'><b p:y='1' p:x='2'><c p:z='3'/></b></a>"
+            .parse()
+            .unwrap();
+    assert_eq!(
+        elem.to_canonical_string().unwrap(),
+        r#"<a xmlns="ns1" xml:lang="en"><b xmlns:p="ns2" p:x="2" p:y="1"><c p:z="3"></c></b></a>"#
+    );
+
+    // Sorted by namespace, not by prefix.
+    let elem: Element =
+        "<a xmlns='ns1' xmlns:z='ns2' xmlns:y='ns3' y:b='1' z:c='2' d='3' z:a='4'/>"
+            .parse()
+            .unwrap();
+    assert_eq!(
+        elem.to_canonical_string().unwrap(),
+        r#"<a xmlns="ns1" xmlns:y="ns3" xmlns:z="ns2" d="3" z:a="4" z:c="2" y:b="1"></a>"#
+    );
+
+    let elem = Element::builder("a", "ns1").attr("p:x", "1").build();
+    match elem.to_canonical_string() {
+        Err(crate::error::Error::InvalidPrefix) => (),
+        err => panic!("No or wrong error: {:?}", err),
+    }
+}
+
+#[test]
+fn canonical_escaping() {
+    let elem = Element::builder("a", "ns1")
+        .attr("b", "<\"&'>\t\n\r")
+        .append("<\"&'>\t\n\r")
+        .build();
+    assert_eq!(
+        elem.to_canonical_string().unwrap(),
+        "<a xmlns=\"ns1\" b=\"&lt;&quot;&amp;'>&#x9;&#xA;&#xD;\">&lt;\"&amp;'&gt;\t\n&#xD;</a>"
+    );
+}
@@ -2,20 +2,30 @@
 
 //! SAX events to DOM tree conversion
 
+use crate::element::Attributes;
 use crate::prefixes::{Prefix, Prefixes};
-use crate::{Element, Error};
+use crate::{Element, Error, Node};
 use rxml::RawEvent;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// Number of distinct namespaces shared between the elements of a document, past which new ones
+/// are allocated per element so that a peer can't make the builder grow without bound
+const MAX_SHARED_NAMESPACES: usize = 256;
 
 /// Tree-building parser state
 pub struct TreeBuilder {
-    next_tag: Option<(Prefix, String, Prefixes, BTreeMap<String, String>)>,
+    next_tag: Option<(Prefix, String, Prefixes, Attributes)>,
     /// Parsing stack
     stack: Vec<Element>,
-    /// Namespace set stack by prefix
+    /// Namespace set stack by prefix, declared outside of the parsed elements
     prefixes_stack: Vec<Prefixes>,
     /// Document root element if finished
     pub root: Option<Element>,
+    /// Size past which text goes to a new text node instead of growing the last one
+    text_chunk_size: Option<usize>,
+    /// Namespaces seen so far, shared by all the elements which have them
+    namespaces: HashSet<Arc<str>>,
 }
 
 impl Default for TreeBuilder {
@@ -32,6 +42,8 @@ impl TreeBuilder {
             stack: vec![],
             prefixes_stack: vec![],
             root: None,
+            text_chunk_size: None,
+            namespaces: HashSet::new(),
         }
     }
 
@@ -44,6 +56,15 @@ impl TreeBuilder {
         self
     }
 
+    /// Split text longer than `size` bytes into several consecutive text nodes, instead of
+    /// reallocating a single growing one, e.g. for base64 payloads of several megabytes.
+    ///
+    /// `Element::text()` and serialisation are unaffected, but such an element doesn’t compare
+    /// equal to one holding the same text in a single node.
+    pub fn set_text_chunk_size(&mut self, size: Option<usize>) {
+        self.text_chunk_size = size;
+    }
+
     /// Stack depth
     pub fn depth(&self) -> usize {
         self.stack.len()
@@ -56,7 +77,6 @@ impl TreeBuilder {
 
     /// Pop the top-most element from the stack
     fn pop(&mut self) -> Option<Element> {
-        self.prefixes_stack.pop();
         self.stack.pop()
     }
 
@@ -70,8 +90,17 @@ impl TreeBuilder {
         }
     }
 
-    /// Lookup XML namespace declaration for given prefix (or no prefix)
-    fn lookup_prefix(&self, prefix: &Option<String>) -> Option<&str> {
+    /// Lookup XML namespace declaration for given prefix (or no prefix), starting with the
+    /// declarations of the element being opened
+    fn lookup_prefix<'a>(&'a self, own: &'a Prefixes, prefix: &Prefix) -> Option<&'a str> {
+        if let Some(ns) = own.get(prefix) {
+            return Some(ns);
+        }
+        for el in self.stack.iter().rev() {
+            if let Some(ns) = el.prefixes.get(prefix) {
+                return Some(ns);
+            }
+        }
         for nss in self.prefixes_stack.iter().rev() {
             if let Some(ns) = nss.get(prefix) {
                 return Some(ns);
@@ -97,7 +126,17 @@ impl TreeBuilder {
     fn process_text(&mut self, text: String) {
         if self.depth() > 0 {
             let top = self.stack.len() - 1;
-            self.stack[top].append_text(text);
+            let top = &mut self.stack[top];
+            let last_len = match top.nodes().next_back() {
+                Some(Node::Text(last)) => last.len(),
+                _ => 0,
+            };
+            match self.text_chunk_size {
+                Some(size) if last_len > 0 && last_len + text.len() > size => {
+                    top.append_text_node(text)
+                }
+                _ => top.append_text(text),
+            }
         }
     }
 
@@ -123,10 +162,13 @@ impl TreeBuilder {
                             prefixes.insert(Some(prefix.as_str().to_owned()), value);
                         }
                         (Some(prefix), name) => {
-                            attrs.insert(format!("{}:{}", prefix, name), value.as_str().to_owned());
+                            attrs.insert(
+                                format!("{}:{}", prefix, name).into(),
+                                value.as_str().to_owned(),
+                            );
                         }
                         (None, name) => {
-                            attrs.insert(name.as_str().to_owned(), value.as_str().to_owned());
+                            attrs.insert(name.as_str().into(), value.as_str().to_owned());
                         }
                     }
                 }
@@ -134,14 +176,22 @@ impl TreeBuilder {
 
             RawEvent::ElementHeadClose(_) => {
                 if let Some((prefix, name, prefixes, attrs)) = self.next_tag.take() {
-                    self.prefixes_stack.push(prefixes.clone());
-
                     let namespace = self
-                        .lookup_prefix(&prefix.clone().map(|prefix| prefix.as_str().to_owned()))
-                        .ok_or(Error::MissingNamespace)?
-                        .to_owned();
-                    let el =
-                        Element::new(name.as_str().to_owned(), namespace, prefixes, attrs, vec![]);
+                        .lookup_prefix(&prefixes, &prefix)
+                        .ok_or(Error::MissingNamespace)?;
+                    // Share the namespace with every element of the document which has it, most
+                    // stanzas only use a handful of them.
+                    let namespace = match self.namespaces.get(namespace) {
+                        Some(shared) => Arc::clone(shared),
+                        None => {
+                            let namespace: Arc<str> = namespace.into();
+                            if self.namespaces.len() < MAX_SHARED_NAMESPACES {
+                                self.namespaces.insert(Arc::clone(&namespace));
+                            }
+                            namespace
+                        }
+                    };
+                    let el = Element::new(name.as_str().into(), namespace, prefixes, attrs, vec![]);
                     self.stack.push(el);
                 }
             }
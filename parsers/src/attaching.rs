@@ -0,0 +1,80 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::message::MessagePayload;
+
+generate_element!(
+    /// Attaches the payloads of this message to a previous message, e.g. a
+    /// receipt or a file uploaded as a follow-up.
+    AttachTo, "attach-to", MESSAGE_ATTACHING,
+    attributes: [
+        /// The id of the message this one is attached to, its stanza-id in a
+        /// groupchat.
+        id: Required<String> = "id",
+    ]
+);
+
+impl AttachTo {
+    /// Attach to the message of this id.
+    pub fn new<I: Into<String>>(id: I) -> AttachTo {
+        AttachTo { id: id.into() }
+    }
+}
+
+impl MessagePayload for AttachTo {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(AttachTo, 12);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(AttachTo, 24);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<message xmlns='jabber:client'
+                  to='juliet@capulet.net/balcony'
+                  from='romeo@montague.net/garden'
+                  id='d3d4bc6e-8f3d-4ba9-ba6c-e1e3ad0ecf8c'>
+              <body>Also, you've got to see this!</body>
+              <attach-to xmlns='urn:xmpp:message-attaching:1'
+                  id='288b83a9-ab0e-4dd4-b5cc-e6d9ddbaf5e6'/>
+            </message>"
+            .parse()
+            .unwrap();
+        let message = Message::try_from(elem).unwrap();
+        let attach_to = message.get_payload::<AttachTo>().unwrap().unwrap();
+        assert_eq!(
+            attach_to,
+            AttachTo::new("288b83a9-ab0e-4dd4-b5cc-e6d9ddbaf5e6")
+        );
+    }
+
+    #[test]
+    fn test_missing_id() {
+        let elem: Element = "<attach-to xmlns='urn:xmpp:message-attaching:1'/>"
+            .parse()
+            .unwrap();
+        let error = AttachTo::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Required attribute 'id' missing.");
+    }
+}
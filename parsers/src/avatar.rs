@@ -19,6 +19,29 @@ generate_element!(
 
 impl PubSubPayload for Metadata {}
 
+impl Metadata {
+    /// Picks the version of this avatar closest to a square of `size`
+    /// pixels: the smallest one at least that large, else the largest one,
+    /// those of unknown dimensions coming last.  Among equally good versions,
+    /// those stored in the data node are preferred over those hosted at a URL.
+    pub fn best_info(&self, size: u16) -> Option<&Info> {
+        self.infos.iter().min_by_key(|info| {
+            let distance = match (info.width, info.height) {
+                (Some(width), Some(height)) => {
+                    let side = width.min(height);
+                    if side >= size {
+                        (0, side - size)
+                    } else {
+                        (1, size - side)
+                    }
+                }
+                _ => (2, 0),
+            };
+            (distance, info.url.is_some())
+        })
+    }
+}
+
 generate_element!(
     /// Communicates avatar metadata.
     Info, "info", AVATAR_METADATA,
@@ -111,6 +134,37 @@ mod tests {
         assert_eq!(data.data, b"\0\0\0");
     }
 
+    #[test]
+    fn test_best_info() {
+        let elem: Element = "<metadata xmlns='urn:xmpp:avatar:metadata'>
+                                 <info bytes='12345' width='64' height='64'
+                                       id='111f4b3c50d7b0df729d299bc6f8e9ef9066971f'
+                                       type='image/png'/>
+                                 <info bytes='12345' width='64' height='64'
+                                       id='e279f80c38f99c1e7e53e262b440993b2f7eea57'
+                                       type='image/png'
+                                       url='https://avatars.example.org/happy.png'/>
+                                 <info bytes='23456' width='128' height='128'
+                                       id='357a8123a30844a3aa99861b6349264ba67a5694'
+                                       type='image/gif'
+                                       url='https://avatars.example.org/happy.gif'/>
+                                 <info bytes='78912'
+                                       id='03a179fe37bd5d6bf9c2e1e592a14ae7814e31da'
+                                       type='image/mng'/>
+                             </metadata>"
+            .parse()
+            .unwrap();
+        let metadata = Metadata::try_from(elem).unwrap();
+        let best = |size| metadata.best_info(size).unwrap().id.to_hex();
+        assert_eq!(best(64), "111f4b3c50d7b0df729d299bc6f8e9ef9066971f");
+        assert_eq!(best(32), "111f4b3c50d7b0df729d299bc6f8e9ef9066971f");
+        assert_eq!(best(96), "357a8123a30844a3aa99861b6349264ba67a5694");
+        assert_eq!(best(256), "357a8123a30844a3aa99861b6349264ba67a5694");
+
+        let metadata = Metadata { infos: Vec::new() };
+        assert!(metadata.best_info(64).is_none());
+    }
+
     #[cfg(not(feature = "disable-validation"))]
     #[test]
     fn test_invalid() {
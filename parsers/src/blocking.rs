@@ -6,6 +6,7 @@
 
 use crate::iq::{IqGetPayload, IqResultPayload, IqSetPayload};
 use crate::ns;
+use crate::reporting::Report;
 use crate::util::error::Error;
 use crate::Element;
 use jid::Jid;
@@ -68,12 +69,103 @@ generate_blocking_element!(
 
 impl IqResultPayload for BlocklistResult {}
 
+/// A JID to block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockItem {
+    /// The JID to block.
+    pub jid: Jid,
+
+    /// Why this JID gets blocked, reported to the server as per
+    /// [XEP-0377](https://xmpp.org/extensions/xep-0377.html).
+    pub report: Option<Report>,
+}
+
+impl BlockItem {
+    /// Create an item blocking this JID, without reporting it.
+    pub fn new(jid: Jid) -> BlockItem {
+        BlockItem { jid, report: None }
+    }
+
+    /// Report this JID to the server while blocking it.
+    pub fn with_report(mut self, report: Report) -> BlockItem {
+        self.report = Some(report);
+        self
+    }
+}
+
+impl TryFrom<Element> for BlockItem {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<BlockItem, Error> {
+        check_self!(elem, "item", BLOCKING);
+        check_no_unknown_attributes!(elem, "item", ["jid"]);
+        let mut item = BlockItem::new(get_attr!(elem, "jid", Required));
+        for child in elem.children() {
+            if item.report.is_some() {
+                return Err(Error::ParseError("More than one report in item element."));
+            }
+            item.report = Some(Report::try_from(child.clone())?);
+        }
+        Ok(item)
+    }
+}
+
+impl From<BlockItem> for Element {
+    fn from(item: BlockItem) -> Element {
+        Element::builder("item", ns::BLOCKING)
+            .attr("jid", item.jid)
+            .append_all(item.report)
+            .build()
+    }
+}
+
+/// A query to block one or more JIDs.
 // TODO: Prevent zero elements from being allowed.
-generate_blocking_element!(
-    /// A query to block one or more JIDs.
-    Block,
-    "block"
-);
+#[derive(Debug, Clone)]
+pub struct Block {
+    /// List of JIDs affected by this command, each with its own report.
+    pub items: Vec<BlockItem>,
+}
+
+impl Block {
+    /// Create a command blocking these JIDs, without reporting them.
+    pub fn new(items: Vec<Jid>) -> Block {
+        Block {
+            items: items.into_iter().map(BlockItem::new).collect(),
+        }
+    }
+
+    /// Report every JID of this command to the server while blocking them.
+    pub fn with_report(mut self, report: Report) -> Block {
+        for item in &mut self.items {
+            item.report = Some(report.clone());
+        }
+        self
+    }
+}
+
+impl TryFrom<Element> for Block {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<Block, Error> {
+        check_self!(elem, "block", BLOCKING);
+        check_no_attributes!(elem, "block");
+        let items = elem
+            .children()
+            .cloned()
+            .map(BlockItem::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(Block { items })
+    }
+}
+
+impl From<Block> for Element {
+    fn from(block: Block) -> Element {
+        Element::builder("block", ns::BLOCKING)
+            .append_all(block.items)
+            .build()
+    }
+}
 
 impl IqSetPayload for Block {}
 
@@ -97,12 +189,14 @@ generate_empty_element!(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::reporting::Reason;
 
     #[cfg(target_pointer_width = "32")]
     #[test]
     fn test_size() {
         assert_size!(BlocklistRequest, 0);
         assert_size!(BlocklistResult, 12);
+        assert_size!(BlockItem, 44);
         assert_size!(Block, 12);
         assert_size!(Unblock, 12);
     }
@@ -112,6 +206,7 @@ mod tests {
     fn test_size() {
         assert_size!(BlocklistRequest, 0);
         assert_size!(BlocklistResult, 24);
+        assert_size!(BlockItem, 88);
         assert_size!(Block, 24);
         assert_size!(Unblock, 24);
     }
@@ -149,13 +244,65 @@ mod tests {
 
         let elem: Element = "<block xmlns='urn:xmpp:blocking'><item jid='coucou@coucou'/><item jid='domain'/></block>".parse().unwrap();
         let block = Block::try_from(elem).unwrap();
-        assert_eq!(block.items, two_items);
+        let jids: Vec<_> = block.items.into_iter().map(|item| item.jid).collect();
+        assert_eq!(jids, two_items);
 
         let elem: Element = "<unblock xmlns='urn:xmpp:blocking'><item jid='coucou@coucou'/><item jid='domain'/></unblock>".parse().unwrap();
         let unblock = Unblock::try_from(elem).unwrap();
         assert_eq!(unblock.items, two_items);
     }
 
+    #[test]
+    fn test_report() {
+        let elem: Element = "<block xmlns='urn:xmpp:blocking'>
+              <item jid='romeo@montague.net'>
+                <report xmlns='urn:xmpp:reporting:1' reason='urn:xmpp:reporting:spam'/>
+              </item>
+            </block>"
+            .parse()
+            .unwrap();
+        let block = Block::try_from(elem).unwrap();
+        assert_eq!(
+            block.items,
+            [BlockItem::new(Jid::new("romeo@montague.net").unwrap())
+                .with_report(Report::new(Reason::Spam))]
+        );
+
+        let block = Block::new(vec![Jid::new("romeo@montague.net").unwrap()])
+            .with_report(Report::new(Reason::Abuse));
+        let elem = Element::from(block);
+        let item = elem.get_child("item", ns::BLOCKING).unwrap();
+        assert!(item.has_child("report", ns::REPORTING));
+    }
+
+    #[test]
+    fn test_different_reports() {
+        let elem: Element = "<block xmlns='urn:xmpp:blocking'>
+              <item jid='romeo@montague.net'>
+                <report xmlns='urn:xmpp:reporting:1' reason='urn:xmpp:reporting:spam'/>
+              </item>
+              <item jid='tybalt@capulet.net'>
+                <report xmlns='urn:xmpp:reporting:1' reason='urn:xmpp:reporting:abuse'/>
+              </item>
+              <item jid='nurse@capulet.net'/>
+            </block>"
+            .parse()
+            .unwrap();
+        let block = Block::try_from(elem).unwrap();
+        let reports: Vec<_> = block.items.iter().map(|item| item.report.clone()).collect();
+        assert_eq!(
+            reports,
+            [
+                Some(Report::new(Reason::Spam)),
+                Some(Report::new(Reason::Abuse)),
+                None
+            ]
+        );
+
+        let elem = Element::from(block.clone());
+        assert_eq!(Block::try_from(elem).unwrap().items, block.items);
+    }
+
     #[cfg(not(feature = "disable-validation"))]
     #[test]
     fn test_invalid() {
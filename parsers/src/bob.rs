@@ -4,21 +4,36 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::caps::hash_caps;
 use crate::hashes::{Algo, Hash};
+use crate::iq::{IqGetPayload, IqResultPayload};
+use crate::message::MessagePayload;
 use crate::util::error::Error;
 use crate::util::text_node_codecs::{Base64, Codec};
 use minidom::IntoAttributeValue;
+use sha1::{Digest, Sha1};
+use std::fmt;
 use std::str::FromStr;
 
 /// A Content-ID, as defined in RFC2111.
 ///
 /// The text value SHOULD be of the form algo+hash@bob.xmpp.org, this struct
 /// enforces that format.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ContentId {
     hash: Hash,
 }
 
+impl ContentId {
+    /// Computes the cid of this data, using SHA-1 as recommended.
+    pub fn from_data(data: &[u8]) -> ContentId {
+        let hash = Sha1::digest(data).to_vec();
+        ContentId {
+            hash: Hash::new(Algo::Sha_1, hash),
+        }
+    }
+}
+
 impl FromStr for ContentId {
     type Err = Error;
 
@@ -38,7 +53,7 @@ impl FromStr for ContentId {
                 let algo = match lhs {
                     "sha1" => Algo::Sha_1,
                     "sha256" => Algo::Sha_256,
-                    _ => unimplemented!(),
+                    _ => return Err(Error::ParseError("Unknown algorithm in cid URI.")),
                 };
                 (algo, rhs)
             }
@@ -49,14 +64,21 @@ impl FromStr for ContentId {
     }
 }
 
-impl IntoAttributeValue for ContentId {
-    fn into_attribute_value(self) -> Option<String> {
+impl fmt::Display for ContentId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        // The hash is private, and only ever built with one of these two algorithms.
         let algo = match self.hash.algo {
             Algo::Sha_1 => "sha1",
             Algo::Sha_256 => "sha256",
-            _ => unimplemented!(),
+            ref algo => unreachable!("ContentId built with unsupported algorithm {:?}", algo),
         };
-        Some(format!("{}+{}@bob.xmpp.org", algo, self.hash.to_hex()))
+        write!(fmt, "{}+{}@bob.xmpp.org", algo, self.hash.to_hex())
+    }
+}
+
+impl IntoAttributeValue for ContentId {
+    fn into_attribute_value(self) -> Option<String> {
+        Some(self.to_string())
     }
 }
 
@@ -85,6 +107,39 @@ generate_element!(
     )
 );
 
+impl Data {
+    /// Wraps this data along with its cid, so that it can be referenced by
+    /// this cid and sent to whoever requests it.
+    pub fn new(type_: Option<String>, data: Vec<u8>) -> Data {
+        Data {
+            cid: ContentId::from_data(&data),
+            max_age: None,
+            type_,
+            data,
+        }
+    }
+
+    /// Whether the cid is the hash of this data, with the algorithm it names,
+    /// which is never the case with an unknown algorithm.
+    pub fn matches_cid(&self) -> bool {
+        hash_caps(&self.data, self.cid.hash.algo.clone()).is_ok_and(|hash| hash == self.cid.hash)
+    }
+
+    /// Requests the data of this cid, in an iq get.
+    pub fn request(cid: ContentId) -> Data {
+        Data {
+            cid,
+            max_age: None,
+            type_: None,
+            data: Vec::new(),
+        }
+    }
+}
+
+impl IqGetPayload for Data {}
+impl IqResultPayload for Data {}
+impl MessagePayload for Data {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,6 +186,29 @@ mod tests {
         assert!(data.data.is_empty());
     }
 
+    #[test]
+    fn test_new() {
+        let data = Data::new(Some(String::from("image/png")), b"Hello world!".to_vec());
+        assert_eq!(
+            data.cid,
+            "sha1+d3486ae9136e7856bc42212385ea797094475802@bob.xmpp.org"
+                .parse()
+                .unwrap()
+        );
+        let elem = Element::from(data.clone());
+        assert_eq!(elem.text(), "SGVsbG8gd29ybGQh");
+        assert_eq!(Data::try_from(elem).unwrap(), data);
+
+        assert!(data.matches_cid());
+        let mut forged = data.clone();
+        forged.data = b"Goodbye world!".to_vec();
+        assert!(!forged.matches_cid());
+
+        let request = Element::from(Data::request(data.cid));
+        assert_eq!(request.attr("type"), None);
+        assert_eq!(request.text(), "");
+    }
+
     #[test]
     fn invalid_cid() {
         let error = "Hello world!".parse::<ContentId>().unwrap_err();
@@ -156,6 +234,13 @@ mod tests {
         };
         assert_eq!(message, "Wrong domain for cid URI.");
 
+        let error = "md5+1234@bob.xmpp.org".parse::<ContentId>().unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Unknown algorithm in cid URI.");
+
         let error = "sha1+invalid@bob.xmpp.org"
             .parse::<ContentId>()
             .unwrap_err();
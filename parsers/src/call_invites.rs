@@ -0,0 +1,207 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::jingle::SessionId;
+use crate::message::MessagePayload;
+use jid::FullJid;
+
+generate_attribute!(
+    /// Whether the call should start with video.
+    Video,
+    "video",
+    bool
+);
+
+generate_element!(
+    /// Join the call through a Jingle session with this resource.
+    JingleMethod, "jingle", CALL_INVITES,
+    attributes: [
+        /// The id of the Jingle session.
+        sid: Required<SessionId> = "sid",
+
+        /// The resource to start the Jingle session with.
+        jid: Required<FullJid> = "jid",
+    ]
+);
+
+generate_element!(
+    /// Join the call outside of XMPP, e.g. on a web page.
+    External, "external", CALL_INVITES,
+    attributes: [
+        /// Where to join the call.
+        uri: Required<String> = "uri",
+    ]
+);
+
+generate_element!(
+    /// Invites the recipients to a call, or everyone in a room if sent there,
+    /// offering the ways to join it.
+    Invite, "invite", CALL_INVITES,
+    attributes: [
+        /// Whether the call should start with video.
+        video: Default<Video> = "video",
+    ],
+    children: [
+        /// The Jingle sessions to join the call with.
+        jingle: Vec<JingleMethod> = ("jingle", CALL_INVITES) => JingleMethod,
+
+        /// The URIs to join the call with outside of XMPP.
+        external: Vec<External> = ("external", CALL_INVITES) => External
+    ]
+);
+
+generate_element!(
+    /// Cancels the invite of this message id, e.g. because the call ended
+    /// before anyone joined.
+    Retract, "retract", CALL_INVITES,
+    attributes: [
+        /// The id of the message containing the invite.
+        id: Required<String> = "id",
+    ]
+);
+
+generate_element!(
+    /// Accepts the invite of this message id, also telling our other devices
+    /// to stop ringing.
+    Accept, "accept", CALL_INVITES,
+    attributes: [
+        /// The id of the message containing the invite.
+        id: Required<String> = "id",
+    ],
+    children: [
+        /// The Jingle session used to join the call, if any.
+        jingle: Option<JingleMethod> = ("jingle", CALL_INVITES) => JingleMethod,
+
+        /// The URI used to join the call, if any.
+        external: Option<External> = ("external", CALL_INVITES) => External
+    ]
+);
+
+generate_element!(
+    /// Declines the invite of this message id.
+    Reject, "reject", CALL_INVITES,
+    attributes: [
+        /// The id of the message containing the invite.
+        id: Required<String> = "id",
+    ]
+);
+
+generate_element!(
+    /// Tells the others that we left the call of the invite of this message id.
+    Left, "left", CALL_INVITES,
+    attributes: [
+        /// The id of the message containing the invite.
+        id: Required<String> = "id",
+    ]
+);
+
+impl MessagePayload for Invite {}
+impl MessagePayload for Retract {}
+impl MessagePayload for Accept {}
+impl MessagePayload for Reject {}
+impl MessagePayload for Left {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Video, 1);
+        assert_size!(JingleMethod, 28);
+        assert_size!(External, 12);
+        assert_size!(Invite, 28);
+        assert_size!(Retract, 12);
+        assert_size!(Accept, 52);
+        assert_size!(Reject, 12);
+        assert_size!(Left, 12);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Video, 1);
+        assert_size!(JingleMethod, 56);
+        assert_size!(External, 24);
+        assert_size!(Invite, 56);
+        assert_size!(Retract, 24);
+        assert_size!(Accept, 104);
+        assert_size!(Reject, 24);
+        assert_size!(Left, 24);
+    }
+
+    #[test]
+    fn test_invite() {
+        let elem: Element = "<message xmlns='jabber:client'
+                  from='romeo@montague.lit/orchard'
+                  to='juliet@capulet.lit'
+                  type='chat'
+                  id='4bfe5a49-d6ca-4bb5-9d29-dc5b5cbb8e91'>
+              <invite xmlns='urn:xmpp:call-invites:0' video='true'>
+                <jingle sid='a73sjjvkla37jfea' jid='romeo@montague.lit/orchard'/>
+                <external uri='https://meet.jit.si/ProjectRomeoAndJuliet'/>
+              </invite>
+            </message>"
+            .parse()
+            .unwrap();
+        let message = Message::try_from(elem).unwrap();
+        let invite = message.get_payload::<Invite>().unwrap().unwrap();
+        assert_eq!(invite.video, Video::True);
+        assert_eq!(
+            invite.jingle[0].sid,
+            SessionId(String::from("a73sjjvkla37jfea"))
+        );
+        assert_eq!(invite.jingle[0].jid.resource().as_str(), "orchard");
+        assert_eq!(
+            invite.external[0].uri,
+            "https://meet.jit.si/ProjectRomeoAndJuliet"
+        );
+
+        let elem = Element::from(invite.clone());
+        assert_eq!(Invite::try_from(elem).unwrap(), invite);
+    }
+
+    #[test]
+    fn test_answers() {
+        let elem: Element = "<accept xmlns='urn:xmpp:call-invites:0'
+                  id='4bfe5a49-d6ca-4bb5-9d29-dc5b5cbb8e91'>
+              <jingle sid='a73sjjvkla37jfea' jid='romeo@montague.lit/orchard'/>
+            </accept>"
+            .parse()
+            .unwrap();
+        let accept = Accept::try_from(elem).unwrap();
+        assert_eq!(accept.id, "4bfe5a49-d6ca-4bb5-9d29-dc5b5cbb8e91");
+        assert!(accept.jingle.is_some());
+        assert!(accept.external.is_none());
+
+        for name in ["retract", "reject", "left"] {
+            let elem: Element = format!("<{name} xmlns='urn:xmpp:call-invites:0' id='invite1'/>")
+                .parse()
+                .unwrap();
+            let id = match name {
+                "retract" => Retract::try_from(elem).unwrap().id,
+                "reject" => Reject::try_from(elem).unwrap().id,
+                _ => Left::try_from(elem).unwrap().id,
+            };
+            assert_eq!(id, "invite1");
+        }
+    }
+
+    #[test]
+    fn test_missing_id() {
+        let elem: Element = "<reject xmlns='urn:xmpp:call-invites:0'/>".parse().unwrap();
+        let error = Reject::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Required attribute 'id' missing.");
+    }
+}
@@ -17,6 +17,9 @@ use digest::{Digest, Update, VariableOutput};
 use sha1::Sha1;
 use sha2::{Sha256, Sha512};
 use sha3::{Sha3_256, Sha3_512};
+use std::collections::HashSet;
+use std::error::Error as StdError;
+use std::fmt;
 
 /// Represents a capability hash for a given client.
 #[derive(Debug, Clone)]
@@ -119,9 +122,13 @@ fn compute_identities(identities: &[Identity]) -> Vec<u8> {
 }
 
 fn compute_extensions(extensions: &[DataForm]) -> Vec<u8> {
-    compute_items(extensions, |extension| {
+    // Forms without a FORM_TYPE are ignored, as per XEP-0115 §5.4.
+    let extensions: Vec<&DataForm> = extensions
+        .iter()
+        .filter(|extension| extension.form_type.is_some())
+        .collect();
+    compute_items(&extensions, |extension| {
         let mut bytes = vec![];
-        // TODO: maybe handle the error case?
         if let Some(ref form_type) = extension.form_type {
             bytes.extend_from_slice(form_type.as_bytes());
         }
@@ -214,6 +221,93 @@ pub fn query_caps(caps: Caps) -> DiscoInfoQuery {
     }
 }
 
+/// The reasons a disco#info result can fail to match a caps hash, see
+/// [verify_caps()].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapsError {
+    /// The caps element uses a hash algorithm this crate doesn’t implement.
+    UnknownAlgorithm(String),
+
+    /// The disco#info result contains the same identity more than once.
+    DuplicateIdentity,
+
+    /// The disco#info result contains the same feature more than once.
+    DuplicateFeature,
+
+    /// The disco#info result contains more than one extension with the same
+    /// FORM_TYPE.
+    DuplicateExtension,
+
+    /// The hash of the disco#info result isn’t the one advertised.
+    HashMismatch,
+}
+
+impl StdError for CapsError {}
+
+impl fmt::Display for CapsError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CapsError::UnknownAlgorithm(algo) => write!(fmt, "unknown hash algorithm: {}", algo),
+            CapsError::DuplicateIdentity => write!(fmt, "duplicate identity in disco#info"),
+            CapsError::DuplicateFeature => write!(fmt, "duplicate feature in disco#info"),
+            CapsError::DuplicateExtension => {
+                write!(fmt, "duplicate FORM_TYPE in disco#info extensions")
+            }
+            CapsError::HashMismatch => write!(fmt, "caps hash mismatch"),
+        }
+    }
+}
+
+/// Checks that a disco#info result matches a received caps element.
+///
+/// As per the [security
+/// considerations](https://xmpp.org/extensions/xep-0115.html#security), a
+/// result containing duplicate identities, features or extension FORM_TYPEs is
+/// rejected as ill-formed, before its hash gets compared to the advertised
+/// one.  Only once this succeeds can the result be cached for this hash.
+pub fn verify_caps(caps: &Caps, disco: &DiscoInfoResult) -> Result<(), CapsError> {
+    let mut identities = HashSet::new();
+    for identity in &disco.identities {
+        if !identities.insert((
+            &identity.category,
+            &identity.type_,
+            &identity.lang,
+            &identity.name,
+        )) {
+            return Err(CapsError::DuplicateIdentity);
+        }
+    }
+
+    let mut features = HashSet::new();
+    for feature in &disco.features {
+        if !features.insert(&feature.var) {
+            return Err(CapsError::DuplicateFeature);
+        }
+    }
+
+    let mut form_types = HashSet::new();
+    for extension in &disco.extensions {
+        // Forms without a FORM_TYPE are ignored, and don't take part in the hash.
+        let Some(ref form_type) = extension.form_type else {
+            continue;
+        };
+        if !form_types.insert(form_type) {
+            return Err(CapsError::DuplicateExtension);
+        }
+    }
+
+    if let Algo::Unknown(ref algo) = caps.hash.algo {
+        return Err(CapsError::UnknownAlgorithm(algo.clone()));
+    }
+    let data = compute_disco(disco);
+    let hash = hash_caps(&data, caps.hash.algo.clone())
+        .map_err(|_| CapsError::UnknownAlgorithm(String::from(caps.hash.algo.clone())))?;
+    if hash.hash != caps.hash.hash {
+        return Err(CapsError::HashMismatch);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -339,4 +433,86 @@ mod tests {
             Base64.decode("q07IKJEyjvHSyhy//CH0CxmKi8w=").unwrap()
         );
     }
+
+    #[test]
+    fn test_verify() {
+        let elem: Element = r#"<query xmlns='http://jabber.org/protocol/disco#info'>
+  <identity category='client' name='Exodus 0.9.1' type='pc'/>
+  <feature var='http://jabber.org/protocol/caps'/>
+  <feature var='http://jabber.org/protocol/disco#info'/>
+  <feature var='http://jabber.org/protocol/disco#items'/>
+  <feature var='http://jabber.org/protocol/muc'/>
+</query>
+"#
+        .parse()
+        .unwrap();
+        let disco = DiscoInfoResult::try_from(elem).unwrap();
+        let hash = Hash {
+            algo: Algo::Sha_1,
+            hash: Base64.decode("QgayPKawpkPSDYmwT/WM94uAlu0=").unwrap(),
+        };
+        let caps = Caps::new("http://code.google.com/p/exodus", hash);
+        caps::verify_caps(&caps, &disco).unwrap();
+
+        let mut wrong = caps.clone();
+        wrong.hash.hash[0] ^= 1;
+        assert_eq!(
+            caps::verify_caps(&wrong, &disco),
+            Err(CapsError::HashMismatch)
+        );
+
+        let mut unknown = caps.clone();
+        unknown.hash.algo = Algo::Unknown(String::from("md5"));
+        assert_eq!(
+            caps::verify_caps(&unknown, &disco),
+            Err(CapsError::UnknownAlgorithm(String::from("md5")))
+        );
+    }
+
+    #[test]
+    fn test_verify_duplicates() {
+        let hash = Hash {
+            algo: Algo::Sha_1,
+            hash: vec![0; 20],
+        };
+        let caps = Caps::new("coucou", hash);
+
+        let elem: Element = "<query xmlns='http://jabber.org/protocol/disco#info'><identity category='client' type='pc'/><identity category='client' type='pc'/><feature var='http://jabber.org/protocol/disco#info'/></query>".parse().unwrap();
+        let disco = DiscoInfoResult::try_from(elem).unwrap();
+        assert_eq!(
+            caps::verify_caps(&caps, &disco),
+            Err(CapsError::DuplicateIdentity)
+        );
+
+        let elem: Element = "<query xmlns='http://jabber.org/protocol/disco#info'><identity category='client' type='pc'/><feature var='http://jabber.org/protocol/disco#info'/><feature var='http://jabber.org/protocol/disco#info'/></query>".parse().unwrap();
+        let disco = DiscoInfoResult::try_from(elem).unwrap();
+        assert_eq!(
+            caps::verify_caps(&caps, &disco),
+            Err(CapsError::DuplicateFeature)
+        );
+
+        let elem: Element = "<query xmlns='http://jabber.org/protocol/disco#info'><identity category='client' type='pc'/><feature var='http://jabber.org/protocol/disco#info'/><x xmlns='jabber:x:data' type='result'><field var='FORM_TYPE' type='hidden'><value>coucou</value></field></x><x xmlns='jabber:x:data' type='result'><field var='FORM_TYPE' type='hidden'><value>coucou</value></field></x></query>".parse().unwrap();
+        let disco = DiscoInfoResult::try_from(elem).unwrap();
+        assert_eq!(
+            caps::verify_caps(&caps, &disco),
+            Err(CapsError::DuplicateExtension)
+        );
+    }
+
+    #[test]
+    fn test_verify_ignores_untyped_extensions() {
+        let elem: Element = "<query xmlns='http://jabber.org/protocol/disco#info'><identity category='client' type='pc'/><feature var='http://jabber.org/protocol/disco#info'/></query>".parse().unwrap();
+        let plain = DiscoInfoResult::try_from(elem).unwrap();
+        let hash = caps::hash_caps(&caps::compute_disco(&plain), Algo::Sha_1).unwrap();
+        let caps = Caps::new("coucou", hash);
+
+        // The disco#info parser rejects such forms, but they can still be built by hand.
+        let elem: Element = "<x xmlns='jabber:x:data' type='result'><field var='foo'><value>bar</value></field></x>".parse().unwrap();
+        let form = DataForm::try_from(elem).unwrap();
+        assert_eq!(form.form_type, None);
+        let mut disco = plain;
+        disco.extensions.push(form.clone());
+        disco.extensions.push(form);
+        caps::verify_caps(&caps, &disco).unwrap();
+    }
 }
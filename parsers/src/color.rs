@@ -0,0 +1,163 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Generates the color of a nickname or a contact from its text, so that
+//! every client displays the same color for the same person.
+
+use sha1::{Digest, Sha1};
+use std::f64::consts::PI;
+
+/// The color vision deficiency of the user, which the generated colors are
+/// corrected for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorVisionDeficiency {
+    /// No correction.
+    #[default]
+    None,
+
+    /// Protanopia and deuteranopia, keeping the hues which can still be told
+    /// apart.
+    RedGreen,
+
+    /// Tritanopia, keeping the hues which can still be told apart.
+    Blue,
+}
+
+/// The hue angle of this text, in degrees, in the `[0, 360)` range.
+pub fn hue_angle(text: &str, cvd: ColorVisionDeficiency) -> f64 {
+    let hash = Sha1::digest(text.as_bytes());
+    let value = u16::from_le_bytes([hash[0], hash[1]]);
+    let angle = f64::from(value) / 65536. * 360.;
+    match cvd {
+        ColorVisionDeficiency::None => angle,
+        ColorVisionDeficiency::RedGreen => angle % 180.,
+        ColorVisionDeficiency::Blue => angle % 180. + 90.,
+    }
+}
+
+/// The color of this text, as red, green and blue components in the
+/// `[0, 1]` range.
+pub fn rgb(text: &str, cvd: ColorVisionDeficiency) -> [f64; 3] {
+    hsluv_to_rgb(hue_angle(text, cvd), 100., 50.)
+}
+
+/// The color of this text, as 8-bit red, green and blue components.
+pub fn rgb8(text: &str, cvd: ColorVisionDeficiency) -> [u8; 3] {
+    rgb(text, cvd).map(|component| (component * 255.).round() as u8)
+}
+
+// The HSLuv color space, see https://www.hsluv.org/ and its reference
+// implementation.
+
+const M: [[f64; 3]; 3] = [
+    [3.240969941904521, -1.537383177570093, -0.498610760293],
+    [-0.96924363628087, 1.87596750150772, 0.041555057407175],
+    [0.055630079696993, -0.20397695888897, 1.056971514242878],
+];
+const REF_U: f64 = 0.19783000664283;
+const REF_V: f64 = 0.46831999493879;
+const KAPPA: f64 = 903.2962962;
+const EPSILON: f64 = 0.0088564516;
+
+/// The lines bounding the RGB gamut at this lightness, as slopes and
+/// intercepts.
+fn bounds(l: f64) -> impl Iterator<Item = (f64, f64)> {
+    let sub1 = (l + 16.).powi(3) / 1560896.;
+    let sub2 = if sub1 > EPSILON { sub1 } else { l / KAPPA };
+    M.into_iter().flat_map(move |[m1, m2, m3]| {
+        [0., 1.].map(|t| {
+            let top1 = (284517. * m1 - 94839. * m3) * sub2;
+            let top2 = (838422. * m3 + 769860. * m2 + 731718. * m1) * l * sub2 - 769860. * t * l;
+            let bottom = (632260. * m3 - 126452. * m2) * sub2 + 126452. * t;
+            (top1 / bottom, top2 / bottom)
+        })
+    })
+}
+
+fn max_chroma(l: f64, h: f64) -> f64 {
+    let theta = h / 360. * 2. * PI;
+    bounds(l)
+        .map(|(slope, intercept)| intercept / (theta.sin() - slope * theta.cos()))
+        .filter(|length| *length >= 0.)
+        .fold(f64::MAX, f64::min)
+}
+
+fn from_linear(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn hsluv_to_rgb(h: f64, s: f64, l: f64) -> [f64; 3] {
+    if l > 99.9999999 {
+        return [1., 1., 1.];
+    }
+    if l < 0.00000001 {
+        return [0., 0., 0.];
+    }
+
+    // HSLuv to LCh to Luv.
+    let c = max_chroma(l, h) / 100. * s;
+    let theta = h / 360. * 2. * PI;
+    let (u, v) = (theta.cos() * c, theta.sin() * c);
+
+    // Luv to XYZ.
+    let var_u = u / (13. * l) + REF_U;
+    let var_v = v / (13. * l) + REF_V;
+    let y = if l <= 8. {
+        l / KAPPA
+    } else {
+        ((l + 16.) / 116.).powi(3)
+    };
+    let x = -(9. * y * var_u) / ((var_u - 4.) * var_v - var_u * var_v);
+    let z = (9. * y - 15. * var_v * y - var_v * x) / (3. * var_v);
+
+    // XYZ to RGB.
+    M.map(|[m1, m2, m3]| from_linear(m1 * x + m2 * y + m3 * z).clamp(0., 1.))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64, precision: f64) {
+        assert!((a - b).abs() < precision, "{} != {}", a, b);
+    }
+
+    // The test vectors of XEP-0392 §A.
+    const VECTORS: [(&str, f64, [f64; 3]); 4] = [
+        ("Romeo", 327.255249, [0.865, 0.000, 0.686]),
+        ("juliet@capulet.lit", 209.410400, [0.000, 0.515, 0.573]),
+        ("\u{1f63a}", 331.199341, [0.872, 0.000, 0.659]),
+        ("council", 359.994507, [0.918, 0.000, 0.394]),
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for (text, angle, color) in VECTORS {
+            assert_close(hue_angle(text, ColorVisionDeficiency::None), angle, 1e-6);
+            let generated = rgb(text, ColorVisionDeficiency::None);
+            for (generated, expected) in generated.into_iter().zip(color) {
+                assert_close(generated, expected, 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrections() {
+        for (text, angle, _) in VECTORS {
+            let red_green = hue_angle(text, ColorVisionDeficiency::RedGreen);
+            assert_close(red_green, angle % 180., 1e-6);
+            assert!(red_green < 180.);
+            let blue = hue_angle(text, ColorVisionDeficiency::Blue);
+            assert_close(blue, angle % 180. + 90., 1e-6);
+            assert!((90. ..270.).contains(&blue));
+        }
+        assert_eq!(rgb8("Romeo", ColorVisionDeficiency::None), [221, 0, 175]);
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::iq::{Iq, IqResultPayload, IqSetPayload};
+use crate::message::MessagePayload;
+use crate::ns;
+use crate::stanza::with_namespace;
+use crate::util::error::Error;
+use crate::Element;
+
+generate_element!(
+    /// An attribute the delegation of a namespace is restricted to, e.g. a
+    /// node of pubsub.
+    Attribute, "attribute", DELEGATION,
+    attributes: [
+        /// The name of this attribute.
+        name: Required<String> = "name",
+    ]
+);
+
+generate_element!(
+    /// A namespace the server delegates to this component.
+    Delegated, "delegated", DELEGATION,
+    attributes: [
+        /// The delegated namespace.
+        namespace: Required<String> = "namespace",
+    ],
+    children: [
+        /// Attributes the delegation is restricted to, if any.
+        attributes: Vec<Attribute> = ("attribute", DELEGATION) => Attribute
+    ]
+);
+
+generate_element!(
+    /// Sent by the server in a message, to advertise the namespaces it
+    /// delegates to this component.
+    Delegation, "delegation", DELEGATION,
+    children: [
+        /// The delegated namespaces.
+        delegated: Vec<Delegated> = ("delegated", DELEGATION) => Delegated
+    ]
+);
+
+impl MessagePayload for Delegation {}
+
+/// An iq forwarded between the server and the component, in both
+/// directions: wrapped in an iq set for the request, and in an iq result
+/// for the response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelegationForward {
+    /// The forwarded iq.
+    pub iq: Iq,
+}
+
+impl IqSetPayload for DelegationForward {}
+impl IqResultPayload for DelegationForward {}
+
+impl TryFrom<Element> for DelegationForward {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<DelegationForward, Error> {
+        check_self!(elem, "delegation", DELEGATION);
+        check_no_attributes!(elem, "delegation");
+        let mut iq = None;
+        for child in elem.children() {
+            if !child.is("forwarded", ns::FORWARD) || iq.is_some() {
+                return Err(Error::ParseError(
+                    "Delegation must contain exactly one forwarded element.",
+                ));
+            }
+            check_no_attributes!(child, "forwarded");
+            // Forwarded iqs are in the jabber:client namespace, even on a
+            // component stream.
+            let mut inner = child.children();
+            match (inner.next(), inner.next()) {
+                (Some(forwarded), None)
+                    if forwarded.is("iq", ns::JABBER_CLIENT)
+                        || forwarded.is("iq", ns::COMPONENT_ACCEPT) =>
+                {
+                    iq = Some(Iq::try_from(with_namespace(
+                        forwarded.clone(),
+                        ns::DEFAULT_NS,
+                    ))?);
+                }
+                _ => {
+                    return Err(Error::ParseError(
+                        "Forwarded element must contain exactly one iq.",
+                    ))
+                }
+            }
+        }
+        let iq = iq.ok_or(Error::ParseError(
+            "Delegation must contain exactly one forwarded element.",
+        ))?;
+        Ok(DelegationForward { iq })
+    }
+}
+
+impl From<DelegationForward> for Element {
+    fn from(forward: DelegationForward) -> Element {
+        let iq = with_namespace(forward.iq.into(), ns::JABBER_CLIENT);
+        Element::builder("delegation", ns::DELEGATION)
+            .append(Element::builder("forwarded", ns::FORWARD).append(iq))
+            .build()
+    }
+}
+
+/// Returns the disco#info feature the server advertises for a delegated
+/// namespace.
+pub fn delegated_feature(namespace: &str) -> String {
+    format!("{}::{}", ns::DELEGATION, namespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iq::IqType;
+    use crate::ping::Ping;
+
+    #[test]
+    fn test_advertisement() {
+        let elem: Element = "<delegation xmlns='urn:xmpp:delegation:2'>
+            <delegated namespace='urn:xmpp:mam:2'/>
+            <delegated namespace='http://jabber.org/protocol/pubsub'>
+              <attribute name='node'/>
+            </delegated>
+          </delegation>"
+            .parse()
+            .unwrap();
+        let delegation = Delegation::try_from(elem).unwrap();
+        assert_eq!(delegation.delegated.len(), 2);
+        assert_eq!(delegation.delegated[0].namespace, ns::MAM);
+        assert!(delegation.delegated[0].attributes.is_empty());
+        assert_eq!(delegation.delegated[1].attributes[0].name, "node");
+    }
+
+    #[test]
+    fn test_forward() {
+        let elem: Element = "<delegation xmlns='urn:xmpp:delegation:2'>
+            <forwarded xmlns='urn:xmpp:forward:0'>
+              <iq xmlns='jabber:client' from='juliet@capulet.lit/balcony' to='capulet.lit' type='get' id='ping1'>
+                <ping xmlns='urn:xmpp:ping'/>
+              </iq>
+            </forwarded>
+          </delegation>"
+            .parse()
+            .unwrap();
+        let forward = DelegationForward::try_from(elem).unwrap();
+        assert_eq!(forward.iq.id, "ping1");
+        assert!(matches!(forward.iq.payload, IqType::Get(_)));
+        assert!(forward.iq.get_payload::<Ping>().unwrap().is_ok());
+
+        let elem: Element = forward.clone().into();
+        let forwarded = elem.get_child("forwarded", ns::FORWARD).unwrap();
+        assert!(forwarded.get_child("iq", ns::JABBER_CLIENT).is_some());
+        assert_eq!(DelegationForward::try_from(elem).unwrap(), forward);
+    }
+
+    #[test]
+    fn test_invalid_forward() {
+        let elem: Element = "<delegation xmlns='urn:xmpp:delegation:2'/>"
+            .parse()
+            .unwrap();
+        let error = DelegationForward::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(
+            message,
+            "Delegation must contain exactly one forwarded element."
+        );
+
+        let elem: Element = "<delegation xmlns='urn:xmpp:delegation:2'>
+            <forwarded xmlns='urn:xmpp:forward:0'>
+              <message xmlns='jabber:client'/>
+            </forwarded>
+          </delegation>"
+            .parse()
+            .unwrap();
+        let error = DelegationForward::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Forwarded element must contain exactly one iq.");
+    }
+
+    #[test]
+    fn test_feature() {
+        assert_eq!(
+            delegated_feature(ns::MAM),
+            "urn:xmpp:delegation:2::urn:xmpp:mam:2"
+        );
+    }
+}
@@ -39,6 +39,12 @@ impl Feature {
     pub fn new<S: Into<String>>(var: S) -> Feature {
         Feature { var: var.into() }
     }
+
+    /// Create a new `<feature/>` advertising a namespace defined outside of
+    /// this crate.
+    pub fn from_namespace<N: ns::Namespace>() -> Feature {
+        Feature::new(N::NS)
+    }
 }
 
 generate_element!(
@@ -459,4 +465,15 @@ mod tests {
         assert_eq!(query.items[1].node, Some(String::from("test")));
         assert_eq!(query.items[1].name, Some(String::from("A component")));
     }
+
+    #[test]
+    fn test_feature_from_namespace() {
+        struct Example;
+        impl ns::Namespace for Example {
+            const NS: &'static str = "urn:example:0";
+        }
+
+        let feature = Feature::from_namespace::<Example>();
+        assert_eq!(feature.var, "urn:example:0");
+    }
 }
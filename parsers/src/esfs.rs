@@ -0,0 +1,237 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::hashes::Hash;
+use crate::ns;
+use crate::url_data::UrlData;
+use crate::util::error::Error;
+use crate::util::text_node_codecs::{Base64, Codec};
+use crate::Element;
+
+generate_attribute!(
+    /// The cipher a shared file got encrypted with.
+    Cipher, "cipher", {
+        /// AES-128 in GCM mode, with the authentication tag appended.
+        Aes128GcmNoPadding => "urn:xmpp:ciphers:aes-128-gcm-nopadding:0",
+
+        /// AES-256 in GCM mode, with the authentication tag appended, as used
+        /// by encrypted uploads (XEP-0454).
+        Aes256GcmNoPadding => "urn:xmpp:ciphers:aes-256-gcm-nopadding:0",
+
+        /// AES-256 in CBC mode, with PKCS#7 padding.
+        Aes256CbcPkcs7 => "urn:xmpp:ciphers:aes-256-cbc-pkcs7:0",
+    }
+);
+
+generate_element!(
+    /// The key to decrypt a shared file with.
+    Key, "key", ESFS,
+    text: (
+        /// The raw key.
+        data: Base64
+    )
+);
+
+generate_element!(
+    /// The initialisation vector of the cipher.
+    Iv, "iv", ESFS,
+    text: (
+        /// The raw initialisation vector.
+        data: Base64
+    )
+);
+
+/// Where to download a file from, as in Stateless File Sharing
+/// (XEP-0447).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Sources {
+    /// The URLs the file can be downloaded from.
+    pub urls: Vec<UrlData>,
+
+    /// The other sources, e.g. a Jingle one (`<jinglepub/>`), kept as is
+    /// since they may be offered by newer clients.
+    pub others: Vec<Element>,
+}
+
+impl TryFrom<Element> for Sources {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<Sources, Error> {
+        check_self!(elem, "sources", SFS);
+        check_no_attributes!(elem, "sources");
+        let mut sources = Sources::default();
+        for child in elem.children() {
+            if child.is("url-data", ns::URL_DATA) {
+                sources.urls.push(UrlData::try_from(child.clone())?);
+            } else {
+                sources.others.push(child.clone());
+            }
+        }
+        Ok(sources)
+    }
+}
+
+impl From<Sources> for Element {
+    fn from(sources: Sources) -> Element {
+        Element::builder("sources", ns::SFS)
+            .append_all(sources.urls)
+            .append_all(sources.others)
+            .build()
+    }
+}
+
+generate_element!(
+    /// A source of a shared file which got encrypted before being uploaded,
+    /// along with what is needed to decrypt it.
+    EncryptedSource, "encrypted", ESFS,
+    attributes: [
+        /// The cipher the file got encrypted with.
+        cipher: Required<Cipher> = "cipher",
+    ],
+    children: [
+        /// The key to decrypt the file with.
+        key: Required<Key> = ("key", ESFS) => Key,
+
+        /// The initialisation vector of the cipher.
+        iv: Required<Iv> = ("iv", ESFS) => Iv,
+
+        /// Hashes of the encrypted file, to check it once downloaded.
+        hashes: Vec<Hash> = ("hash", HASHES) => Hash,
+
+        /// Where to download the encrypted file from.
+        sources: Required<Sources> = ("sources", SFS) => Sources
+    ]
+);
+
+impl EncryptedSource {
+    /// Describe a file encrypted with this cipher, key and initialisation
+    /// vector, then uploaded to this URL.
+    pub fn new<U: Into<String>>(cipher: Cipher, key: Vec<u8>, iv: Vec<u8>, url: U) -> Self {
+        EncryptedSource {
+            cipher,
+            key: Key { data: key },
+            iv: Iv { data: iv },
+            hashes: Vec::new(),
+            sources: Sources {
+                urls: vec![UrlData::new(url)],
+                others: Vec::new(),
+            },
+        }
+    }
+
+    /// Add a hash of the encrypted file.
+    pub fn with_hash(mut self, hash: Hash) -> Self {
+        self.hashes.push(hash);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::Algo;
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Cipher, 1);
+        assert_size!(Key, 12);
+        assert_size!(Iv, 12);
+        assert_size!(Sources, 24);
+        assert_size!(EncryptedSource, 64);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Cipher, 1);
+        assert_size!(Key, 24);
+        assert_size!(Iv, 24);
+        assert_size!(Sources, 48);
+        assert_size!(EncryptedSource, 128);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<encrypted xmlns='urn:xmpp:esfs:0'
+                  cipher='urn:xmpp:ciphers:aes-256-gcm-nopadding:0'>
+              <key>SuRJ2agVm/pQbJQlPq/B23Xt1YOOJCcEGJA5HrcYOGQ=</key>
+              <iv>T8RDMBaiqn6Ci4Nw</iv>
+              <hash xmlns='urn:xmpp:hashes:2' algo='sha-256'>sY4g5bFfYh/jP+4LhO0TzFAhZmIzRDjpnjT/CCrbl1I=</hash>
+              <sources xmlns='urn:xmpp:sfs:0'>
+                <url-data xmlns='http://jabber.org/protocol/url-data'
+                    target='https://download.montague.lit/4a771ac1-f0b2-4a4a-9700-f2a26fa2bb67/encrypted.jpg'/>
+              </sources>
+            </encrypted>"
+            .parse()
+            .unwrap();
+        let encrypted = EncryptedSource::try_from(elem).unwrap();
+        assert_eq!(encrypted.cipher, Cipher::Aes256GcmNoPadding);
+        assert_eq!(encrypted.key.data.len(), 32);
+        assert_eq!(encrypted.iv.data.len(), 12);
+        assert_eq!(encrypted.hashes[0].algo, Algo::Sha_256);
+        assert_eq!(
+            encrypted.sources.urls[0].target,
+            "https://download.montague.lit/4a771ac1-f0b2-4a4a-9700-f2a26fa2bb67/encrypted.jpg"
+        );
+    }
+
+    #[test]
+    fn test_other_sources() {
+        let elem: Element = "<sources xmlns='urn:xmpp:sfs:0'>
+              <url-data xmlns='http://jabber.org/protocol/url-data'
+                  target='https://download.montague.lit/encrypted.jpg'/>
+              <jinglepub xmlns='urn:xmpp:jinglepub:1'
+                  from='romeo@montague.lit/resource1'
+                  id='9559976B-3FBF-4E7E-B457-2DAA225972BB'/>
+            </sources>"
+            .parse()
+            .unwrap();
+        let sources = Sources::try_from(elem).unwrap();
+        assert_eq!(sources.urls.len(), 1);
+        assert_eq!(sources.others.len(), 1);
+        assert!(sources.others[0].is("jinglepub", "urn:xmpp:jinglepub:1"));
+
+        let elem = Element::from(sources.clone());
+        assert_eq!(Sources::try_from(elem).unwrap(), sources);
+    }
+
+    #[test]
+    fn test_serialise() {
+        let encrypted = EncryptedSource::new(
+            Cipher::Aes256GcmNoPadding,
+            vec![0; 32],
+            vec![1; 12],
+            "https://download.montague.lit/encrypted.jpg",
+        )
+        .with_hash(Hash::new(Algo::Sha_256, vec![2; 32]));
+        let elem = Element::from(encrypted.clone());
+        assert_eq!(
+            elem.attr("cipher"),
+            Some("urn:xmpp:ciphers:aes-256-gcm-nopadding:0")
+        );
+        assert_eq!(EncryptedSource::try_from(elem).unwrap(), encrypted);
+    }
+
+    #[test]
+    fn test_missing_key() {
+        let elem: Element = "<encrypted xmlns='urn:xmpp:esfs:0'
+                  cipher='urn:xmpp:ciphers:aes-256-gcm-nopadding:0'>
+              <iv>T8RDMBaiqn6Ci4Nw</iv>
+              <sources xmlns='urn:xmpp:sfs:0'/>
+            </encrypted>"
+            .parse()
+            .unwrap();
+        let error = EncryptedSource::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Missing child key in encrypted element.");
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::data_forms::{DataForm, DataFormType, Field, FieldType, Option_};
+
+generate_element!(
+    /// Negotiates the options of a feature with a data form: the offer is a
+    /// form listing the options, the answer a submitted form choosing them.
+    FeatureNeg, "feature", FEATURE_NEG,
+    children: [
+        /// The form offering or choosing the options.
+        form: Required<DataForm> = ("x", DATA_FORMS) => DataForm
+    ]
+);
+
+impl FeatureNeg {
+    /// Offer to choose one of these values for this field.
+    pub fn offer<S: AsRef<str>>(var: &str, values: &[S]) -> FeatureNeg {
+        let mut field = Field::new(var, FieldType::ListSingle);
+        field.options = values
+            .iter()
+            .map(|value| Option_ {
+                label: None,
+                value: String::from(value.as_ref()),
+            })
+            .collect();
+        FeatureNeg {
+            form: DataForm {
+                type_: DataFormType::Form,
+                form_type: None,
+                title: None,
+                instructions: None,
+                fields: vec![field],
+            },
+        }
+    }
+
+    /// Choose this value for this field.
+    pub fn choose(var: &str, value: &str) -> FeatureNeg {
+        FeatureNeg {
+            form: DataForm {
+                type_: DataFormType::Submit,
+                form_type: None,
+                title: None,
+                instructions: None,
+                fields: vec![Field::new(var, FieldType::ListSingle).with_value(value)],
+            },
+        }
+    }
+
+    fn field(&self, var: &str) -> Option<&Field> {
+        self.form.fields.iter().find(|field| field.var == var)
+    }
+
+    /// The values offered for this field.
+    pub fn options(&self, var: &str) -> Vec<&str> {
+        self.field(var)
+            .map(|field| {
+                field
+                    .options
+                    .iter()
+                    .map(|option| option.value.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The value chosen for this field.
+    pub fn chosen(&self, var: &str) -> Option<&str> {
+        self.field(var)?.values.first().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(FeatureNeg, 52);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(FeatureNeg, 104);
+    }
+
+    #[test]
+    fn test_offer() {
+        let elem: Element = "<feature xmlns='http://jabber.org/protocol/feature-neg'>
+              <x xmlns='jabber:x:data' type='form'>
+                <field var='stream-method' type='list-single'>
+                  <option><value>http://jabber.org/protocol/bytestreams</value></option>
+                  <option><value>http://jabber.org/protocol/ibb</value></option>
+                </field>
+              </x>
+            </feature>"
+            .parse()
+            .unwrap();
+        let feature = FeatureNeg::try_from(elem).unwrap();
+        assert_eq!(
+            feature.options("stream-method"),
+            [
+                "http://jabber.org/protocol/bytestreams",
+                "http://jabber.org/protocol/ibb"
+            ]
+        );
+        assert_eq!(feature.chosen("stream-method"), None);
+        assert_eq!(feature.options("other"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_choose() {
+        let feature = FeatureNeg::choose("stream-method", "http://jabber.org/protocol/ibb");
+        let elem = Element::from(feature);
+        let feature = FeatureNeg::try_from(elem).unwrap();
+        assert_eq!(feature.form.type_, DataFormType::Submit);
+        assert_eq!(
+            feature.chosen("stream-method"),
+            Some("http://jabber.org/protocol/ibb")
+        );
+    }
+}
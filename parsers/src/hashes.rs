@@ -98,6 +98,7 @@ impl IntoAttributeValue for Algo {
 generate_element!(
     /// This element represents a hash of some data, defined by the hash
     /// algorithm used and the computed value.
+    #[derive(Eq, Hash)]
     Hash, "hash", HASHES,
     attributes: [
         /// The algorithm used to create this hash.
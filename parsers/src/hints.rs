@@ -0,0 +1,108 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::message::MessagePayload;
+
+generate_empty_element!(
+    /// Asks entities not to store this message permanently, e.g. in an archive, while it may
+    /// still be kept for offline delivery.
+    NoPermanentStore,
+    "no-permanent-store",
+    HINTS
+);
+
+impl MessagePayload for NoPermanentStore {}
+
+generate_empty_element!(
+    /// Asks entities not to store this message at all, neither permanently nor for offline
+    /// delivery.
+    NoStore,
+    "no-store",
+    HINTS
+);
+
+impl MessagePayload for NoStore {}
+
+generate_empty_element!(
+    /// Asks entities not to copy this message to other resources, e.g. through carbons.
+    NoCopy,
+    "no-copy",
+    HINTS
+);
+
+impl MessagePayload for NoCopy {}
+
+generate_empty_element!(
+    /// Asks entities to store this message, even if it would otherwise not be.
+    Store,
+    "store",
+    HINTS
+);
+
+impl MessagePayload for Store {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "disable-validation"))]
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[test]
+    fn test_size() {
+        assert_size!(NoPermanentStore, 0);
+        assert_size!(NoStore, 0);
+        assert_size!(NoCopy, 0);
+        assert_size!(Store, 0);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<no-permanent-store xmlns='urn:xmpp:hints'/>"
+            .parse()
+            .unwrap();
+        NoPermanentStore::try_from(elem).unwrap();
+        let elem: Element = "<no-store xmlns='urn:xmpp:hints'/>".parse().unwrap();
+        NoStore::try_from(elem).unwrap();
+        let elem: Element = "<no-copy xmlns='urn:xmpp:hints'/>".parse().unwrap();
+        NoCopy::try_from(elem).unwrap();
+        let elem: Element = "<store xmlns='urn:xmpp:hints'/>".parse().unwrap();
+        Store::try_from(elem).unwrap();
+    }
+
+    #[test]
+    fn test_serialise() {
+        let elem: Element = NoStore.into();
+        assert!(elem.is("no-store", crate::ns::HINTS));
+        assert_eq!(elem.children().count(), 0);
+    }
+
+    #[cfg(not(feature = "disable-validation"))]
+    #[test]
+    fn test_invalid_child() {
+        let elem: Element = "<no-copy xmlns='urn:xmpp:hints'><coucou/></no-copy>"
+            .parse()
+            .unwrap();
+        let error = NoCopy::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Unknown child in no-copy element.");
+    }
+
+    #[cfg(not(feature = "disable-validation"))]
+    #[test]
+    fn test_invalid_attribute() {
+        let elem: Element = "<store xmlns='urn:xmpp:hints' coucou=''/>".parse().unwrap();
+        let error = Store::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Unknown attribute in store element.");
+    }
+}
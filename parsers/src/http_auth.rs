@@ -0,0 +1,86 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::iq::{IqResultPayload, IqSetPayload};
+use crate::message::MessagePayload;
+
+generate_element!(
+    /// Asks the user to confirm that they made this HTTP request, e.g. to log
+    /// into a website with their JID, and answers with the same element.
+    Confirm, "confirm", HTTP_AUTH,
+    attributes: [
+        /// The transaction identifier given to the HTTP server, which the user
+        /// should check.
+        id: Required<String> = "id",
+
+        /// The HTTP method of the request, such as GET or POST.
+        method: Required<String> = "method",
+
+        /// The URL which got requested.
+        url: Required<String> = "url",
+    ]
+);
+
+impl IqSetPayload for Confirm {}
+impl IqResultPayload for Confirm {}
+impl MessagePayload for Confirm {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Confirm, 36);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Confirm, 72);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<confirm xmlns='http://jabber.org/protocol/http-auth'
+                  id='a7374jnjlalasdf'
+                  method='GET'
+                  url='https://files.shakespeare.lit:9345/missive.html'/>"
+            .parse()
+            .unwrap();
+        let confirm = Confirm::try_from(elem.clone()).unwrap();
+        assert_eq!(confirm.id, "a7374jnjlalasdf");
+        assert_eq!(confirm.method, "GET");
+        assert_eq!(
+            confirm.url,
+            "https://files.shakespeare.lit:9345/missive.html"
+        );
+        assert_eq!(Element::from(confirm), elem);
+    }
+
+    #[test]
+    fn test_message() {
+        let elem: Element = "<message xmlns='jabber:client'
+                  type='normal'
+                  from='files.shakespeare.lit'
+                  to='juliet@capulet.com'>
+              <thread>e0ffe42b28561960c6b12b944a092794b9683a38</thread>
+              <body>Someone (maybe you) has requested the following file.</body>
+              <confirm xmlns='http://jabber.org/protocol/http-auth'
+                  id='a7374jnjlalasdf'
+                  method='GET'
+                  url='https://files.shakespeare.lit:9345/missive.html'/>
+            </message>"
+            .parse()
+            .unwrap();
+        let message = Message::try_from(elem).unwrap();
+        let confirm = message.get_payload::<Confirm>().unwrap().unwrap();
+        assert_eq!(confirm.id, "a7374jnjlalasdf");
+    }
+}
@@ -4,6 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::date::DateTime;
 use crate::iq::{IqGetPayload, IqResultPayload};
 use crate::ns;
 use crate::util::error::Error;
@@ -111,6 +112,60 @@ generate_element!(
 
 impl IqResultPayload for SlotResult {}
 
+/// Error condition sent along a not-acceptable error when the file is too
+/// large for this service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTooLarge {
+    /// The maximum file size allowed by this service, in bytes.
+    pub max_file_size: Option<u64>,
+}
+
+impl TryFrom<Element> for FileTooLarge {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<FileTooLarge, Error> {
+        check_self!(elem, "file-too-large", HTTP_UPLOAD);
+        check_no_attributes!(elem, "file-too-large");
+        let mut max_file_size = None;
+        for child in elem.children() {
+            if child.is("max-file-size", ns::HTTP_UPLOAD) {
+                if max_file_size.is_some() {
+                    return Err(Error::ParseError(
+                        "File-too-large must not have more than one max-file-size.",
+                    ));
+                }
+                check_no_attributes!(child, "max-file-size");
+                max_file_size = Some(child.text().parse()?);
+            } else {
+                return Err(Error::ParseError(
+                    "Unknown child in file-too-large element.",
+                ));
+            }
+        }
+        Ok(FileTooLarge { max_file_size })
+    }
+}
+
+impl From<FileTooLarge> for Element {
+    fn from(file_too_large: FileTooLarge) -> Element {
+        Element::builder("file-too-large", ns::HTTP_UPLOAD)
+            .append_all(file_too_large.max_file_size.map(|size| {
+                Element::builder("max-file-size", ns::HTTP_UPLOAD).append(format!("{}", size))
+            }))
+            .build()
+    }
+}
+
+generate_element!(
+    /// Error condition sent along a resource-constraint error, e.g. when a
+    /// quota is reached, telling when to retry.
+    Retry, "retry", HTTP_UPLOAD,
+    attributes: [
+        /// The time after which a new request may succeed.
+        stamp: Required<DateTime> = "stamp",
+    ]
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,4 +234,35 @@ mod tests {
             .unwrap();
         SlotResult::try_from(elem).unwrap_err();
     }
+
+    #[test]
+    fn test_file_too_large() {
+        let elem: Element = "<file-too-large xmlns='urn:xmpp:http:upload:0'>
+            <max-file-size>20000</max-file-size>
+          </file-too-large>"
+            .parse()
+            .unwrap();
+        let file_too_large = FileTooLarge::try_from(elem).unwrap();
+        assert_eq!(file_too_large.max_file_size, Some(20000));
+
+        let elem: Element = Element::from(file_too_large);
+        assert_eq!(
+            elem.get_child("max-file-size", ns::HTTP_UPLOAD)
+                .unwrap()
+                .text(),
+            "20000"
+        );
+    }
+
+    #[test]
+    fn test_retry() {
+        let elem: Element = "<retry xmlns='urn:xmpp:http:upload:0' stamp='2017-12-03T23:42:01Z'/>"
+            .parse()
+            .unwrap();
+        let retry = Retry::try_from(elem).unwrap();
+        assert_eq!(
+            retry.stamp,
+            "2017-12-03T23:42:01Z".parse::<DateTime>().unwrap()
+        );
+    }
 }
@@ -4,6 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::bob::Data;
 use crate::data_forms::DataForm;
 use crate::iq::{IqGetPayload, IqResultPayload, IqSetPayload};
 use crate::ns;
@@ -26,6 +27,10 @@ pub struct Query {
 
     /// A data form the user must fill before being allowed to register.
     pub form: Option<DataForm>,
+
+    /// Bits of Binary referenced by the media of this form, e.g. the image of
+    /// a captcha.
+    pub data: Vec<Data>,
     // Not yet implemented.
     //pub oob: Option<Oob>,
 }
@@ -44,6 +49,7 @@ impl TryFrom<Element> for Query {
             fields: HashMap::new(),
             remove: false,
             form: None,
+            data: Vec::new(),
         };
         for child in elem.children() {
             let namespace = child.ns();
@@ -80,6 +86,8 @@ impl TryFrom<Element> for Query {
                 }
             } else if child.is("x", ns::DATA_FORMS) {
                 query.form = Some(DataForm::try_from(child.clone())?);
+            } else if child.is("data", ns::BOB) {
+                query.data.push(Data::try_from(child.clone())?);
             } else {
                 return Err(Error::ParseError("Unknown child in ibr element."));
             }
@@ -108,6 +116,7 @@ impl From<Query> for Element {
                 None
             })
             .append_all(query.form.map(Element::from))
+            .append_all(query.data)
             .build()
     }
 }
@@ -119,13 +128,13 @@ mod tests {
     #[cfg(target_pointer_width = "32")]
     #[test]
     fn test_size() {
-        assert_size!(Query, 88);
+        assert_size!(Query, 100);
     }
 
     #[cfg(target_pointer_width = "64")]
     #[test]
     fn test_size() {
-        assert_size!(Query, 160);
+        assert_size!(Query, 184);
     }
 
     #[test]
@@ -190,4 +199,29 @@ mod tests {
         let elem2 = query.into();
         assert_eq!(elem1, elem2);
     }
+
+    #[test]
+    fn test_captcha() {
+        let elem: Element = "<query xmlns='jabber:iq:register'>
+              <x xmlns='jabber:x:data' type='form'>
+                <field type='hidden' var='FORM_TYPE'><value>urn:xmpp:captcha</value></field>
+                <field label='Enter the text you see' var='ocr'>
+                  <media xmlns='urn:xmpp:media-element' height='80' width='290'>
+                    <uri type='image/png'>cid:sha1+f24030b8d91d233bac14777be5ab531ca3b9f102@bob.xmpp.org</uri>
+                  </media>
+                  <required/>
+                </field>
+              </x>
+              <data xmlns='urn:xmpp:bob'
+                    cid='sha1+f24030b8d91d233bac14777be5ab531ca3b9f102@bob.xmpp.org'
+                    type='image/png'>iVBORw0KGgo=</data>
+            </query>"
+            .parse()
+            .unwrap();
+        let query = Query::try_from(elem).unwrap();
+        let form = query.form.unwrap();
+        let cid = form.fields[0].media[0].uris[0].cid().unwrap();
+        assert_eq!(query.data.len(), 1);
+        assert_eq!(query.data[0].cid, cid);
+    }
 }
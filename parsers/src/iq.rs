@@ -8,6 +8,7 @@
 use crate::ns;
 use crate::stanza_error::StanzaError;
 use crate::util::error::Error;
+use crate::util::{get_payload, make_id};
 use crate::Element;
 use jid::Jid;
 use minidom::IntoAttributeValue;
@@ -68,7 +69,77 @@ pub struct Iq {
     pub payload: IqType,
 }
 
+/// Builds an `<iq/>` stanza, finished by the method setting its type and
+/// payload.
+///
+/// A random id is used unless one is set.
+#[derive(Debug, Clone, Default)]
+pub struct IqBuilder {
+    from: Option<Jid>,
+    to: Option<Jid>,
+    id: Option<String>,
+}
+
+impl IqBuilder {
+    /// Sets the emitter of the iq.
+    pub fn from(mut self, from: Jid) -> IqBuilder {
+        self.from = Some(from);
+        self
+    }
+
+    /// Sets the recipient of the iq.
+    pub fn to(mut self, to: Jid) -> IqBuilder {
+        self.to = Some(to);
+        self
+    }
+
+    /// Sets the id of the iq, in order to later match its response.
+    pub fn id<S: Into<String>>(mut self, id: S) -> IqBuilder {
+        self.id = Some(id.into());
+        self
+    }
+
+    fn build(self, payload: IqType) -> Iq {
+        Iq {
+            from: self.from,
+            to: self.to,
+            id: self.id.unwrap_or_else(make_id),
+            payload,
+        }
+    }
+
+    /// Creates an `<iq/>` stanza containing a get request.
+    pub fn get(self, payload: impl IqGetPayload) -> Iq {
+        self.build(IqType::Get(payload.into()))
+    }
+
+    /// Creates an `<iq/>` stanza containing a set request.
+    pub fn set(self, payload: impl IqSetPayload) -> Iq {
+        self.build(IqType::Set(payload.into()))
+    }
+
+    /// Creates an `<iq/>` stanza containing a result, if any.
+    pub fn result(self, payload: Option<impl IqResultPayload>) -> Iq {
+        self.build(IqType::Result(payload.map(Into::into)))
+    }
+
+    /// Creates an empty `<iq type="result"/>` stanza.
+    pub fn empty_result(self) -> Iq {
+        self.build(IqType::Result(None))
+    }
+
+    /// Creates an `<iq/>` stanza containing an error.
+    pub fn error(self, error: StanzaError) -> Iq {
+        self.build(IqType::Error(error))
+    }
+}
+
 impl Iq {
+    /// Starts building an `<iq/>` stanza.
+    pub fn builder() -> IqBuilder {
+        IqBuilder::default()
+    }
+
     /// Creates an `<iq/>` stanza containing a get request.
     pub fn from_get<S: Into<String>>(id: S, payload: impl IqGetPayload) -> Iq {
         Iq {
@@ -136,6 +207,52 @@ impl Iq {
         self.id = id;
         self
     }
+
+    /// Creates the error answering this iq, from its recipient to its
+    /// sender and with the same id.
+    ///
+    /// Only get and set iqs may be answered, never results or errors.
+    pub fn error_reply(iq: &Iq, error: StanzaError) -> Iq {
+        Iq {
+            from: iq.to.clone(),
+            to: iq.from.clone(),
+            id: iq.id.clone(),
+            payload: IqType::Error(error),
+        }
+    }
+
+    /// Parse the payload of this iq as the given type.
+    ///
+    /// Returns `None` if there is no payload or it is of another type, or
+    /// the result of parsing it.
+    pub fn get_payload<T: TryFrom<Element, Error = Error>>(&self) -> Option<Result<T, Error>> {
+        match self.payload {
+            IqType::Get(ref payload) | IqType::Set(ref payload) => {
+                get_payload(std::slice::from_ref(payload))
+            }
+            IqType::Result(Some(ref payload)) => get_payload(std::slice::from_ref(payload)),
+            IqType::Result(None) | IqType::Error(_) => None,
+        }
+    }
+
+    /// Consume this iq and parse its payload as the given type, without
+    /// cloning it.
+    ///
+    /// Returns `Ok(None)` if there is no payload or it is of another type,
+    /// like [`Message::extract_payload`](crate::message::Message::extract_payload).
+    #[allow(clippy::result_large_err)]
+    pub fn extract_payload<T: TryFrom<Element, Error = Error>>(self) -> Result<Option<T>, Error> {
+        match self.payload {
+            IqType::Get(payload) | IqType::Set(payload) | IqType::Result(Some(payload)) => {
+                match T::try_from(payload) {
+                    Ok(payload) => Ok(Some(payload)),
+                    Err(Error::TypeMismatch(..)) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            }
+            IqType::Result(None) | IqType::Error(_) => Ok(None),
+        }
+    }
 }
 
 impl TryFrom<Element> for Iq {
@@ -143,6 +260,19 @@ impl TryFrom<Element> for Iq {
 
     fn try_from(root: Element) -> Result<Iq, Error> {
         check_self!(root, "iq", DEFAULT_NS);
+        Iq::try_from(&root)
+    }
+}
+
+/// Parses an iq without taking the element, e.g. to keep it around when
+/// it is invalid.
+impl TryFrom<&Element> for Iq {
+    type Error = Error;
+
+    fn try_from(root: &Element) -> Result<Iq, Error> {
+        if !root.is("iq", ns::DEFAULT_NS) {
+            return Err(Error::TypeMismatch("iq", ns::DEFAULT_NS, root.clone()));
+        }
         let from = get_attr!(root, "from", Option);
         let to = get_attr!(root, "to", Option);
         let id = get_attr!(root, "id", Required);
@@ -232,15 +362,15 @@ mod tests {
     #[cfg(target_pointer_width = "32")]
     #[test]
     fn test_size() {
-        assert_size!(IqType, 92);
-        assert_size!(Iq, 156);
+        assert_size!(IqType, 88);
+        assert_size!(Iq, 152);
     }
 
     #[cfg(target_pointer_width = "64")]
     #[test]
     fn test_size() {
-        assert_size!(IqType, 184);
-        assert_size!(Iq, 272);
+        assert_size!(IqType, 176);
+        assert_size!(Iq, 264);
     }
 
     #[test]
@@ -457,4 +587,70 @@ mod tests {
         };
         assert!(disco_info.node.is_none());
     }
+
+    #[test]
+    fn test_get_payload() {
+        #[cfg(not(feature = "component"))]
+        let elem: Element = "<iq xmlns='jabber:client' type='get' id='disco'><query xmlns='http://jabber.org/protocol/disco#info'/></iq>".parse().unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<iq xmlns='jabber:component:accept' type='get' id='disco'><query xmlns='http://jabber.org/protocol/disco#info'/></iq>".parse().unwrap();
+        let iq = Iq::try_from(elem).unwrap();
+        let disco_info = iq.get_payload::<DiscoInfoQuery>().unwrap().unwrap();
+        assert!(disco_info.node.is_none());
+        assert!(iq.get_payload::<crate::ping::Ping>().is_none());
+    }
+
+    #[test]
+    fn test_builder() {
+        let to = Jid::new("plays.shakespeare.lit").unwrap();
+        let iq = Iq::builder()
+            .to(to.clone())
+            .id("info1")
+            .get(DiscoInfoQuery { node: None });
+        assert_eq!(
+            iq,
+            Iq::from_get("info1", DiscoInfoQuery { node: None }).with_to(to)
+        );
+
+        let iq = Iq::builder().empty_result();
+        assert_eq!(iq.from, None);
+        assert_eq!(iq.payload, IqType::Result(None));
+        assert!(!iq.id.is_empty());
+        assert_ne!(iq.id, Iq::builder().empty_result().id);
+    }
+
+    #[test]
+    fn test_extract_payload() {
+        let iq = Iq::from_get("disco", DiscoInfoQuery { node: None });
+        assert!(iq
+            .clone()
+            .extract_payload::<crate::ping::Ping>()
+            .unwrap()
+            .is_none());
+        let disco_info = iq.extract_payload::<DiscoInfoQuery>().unwrap().unwrap();
+        assert!(disco_info.node.is_none());
+
+        let iq = Iq::empty_result(Jid::new("romeo@montague.net").unwrap(), "disco");
+        assert!(iq.extract_payload::<DiscoInfoQuery>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_error_reply() {
+        let elem: Element = "<iq xmlns='jabber:client' type='get' id='info1' from='romeo@montague.net/orchard' to='plays.shakespeare.lit'><query xmlns='http://jabber.org/protocol/disco#info'/></iq>"
+            .parse()
+            .unwrap();
+        let iq = Iq::try_from(elem).unwrap();
+        let reply = Iq::error_reply(&iq, StanzaError::service_unavailable());
+        assert_eq!(reply.id, "info1");
+        assert_eq!(reply.from, iq.to);
+        assert_eq!(reply.to, iq.from);
+        let error = match reply.payload {
+            IqType::Error(error) => error,
+            _ => panic!(),
+        };
+        assert_eq!(
+            error.defined_condition,
+            DefinedCondition::ServiceUnavailable
+        );
+    }
 }
@@ -534,6 +534,25 @@ impl From<ReasonElement> for Element {
     }
 }
 
+generate_element_enum!(
+    /// Jingle-specific error conditions, included in stanza errors.
+    ErrorCondition, "condition", JINGLE_ERRORS, {
+        /// The request cannot occur at this point in the state machine.
+        OutOfOrder => "out-of-order",
+
+        /// The request is rejected because it was sent while the initiator
+        /// was awaiting a reply on a similar request.
+        TieBreak => "tie-break",
+
+        /// The session ID is unknown to the recipient.
+        UnknownSession => "unknown-session",
+
+        /// The recipient does not support the informational payload of a
+        /// session-info action.
+        UnsupportedInfo => "unsupported-info",
+    }
+);
+
 generate_id!(
     /// Unique identifier for a session between two JIDs.
     SessionId
@@ -672,6 +691,7 @@ impl From<Jingle> for Element {
             .append_all(jingle.contents)
             .append_all(jingle.reason.map(Element::from))
             .append_all(jingle.group.map(Element::from))
+            .append_all(jingle.other)
             .build()
     }
 }
@@ -691,6 +711,7 @@ mod tests {
         assert_size!(Content, 216);
         assert_size!(Reason, 1);
         assert_size!(ReasonElement, 16);
+        assert_size!(ErrorCondition, 1);
         assert_size!(SessionId, 12);
         assert_size!(Jingle, 104);
     }
@@ -710,6 +731,7 @@ mod tests {
         assert_size!(Content, 440);
         assert_size!(Reason, 1);
         assert_size!(ReasonElement, 32);
+        assert_size!(ErrorCondition, 1);
         assert_size!(SessionId, 24);
         assert_size!(Jingle, 208);
     }
@@ -906,4 +928,14 @@ mod tests {
         let serialized: Element = jingle.into();
         assert_eq!(serialized, reference);
     }
+
+    #[test]
+    fn test_error_condition() {
+        let elem: Element = "<unknown-session xmlns='urn:xmpp:jingle:errors:1'/>"
+            .parse()
+            .unwrap();
+        let condition = ErrorCondition::try_from(elem.clone()).unwrap();
+        assert_eq!(condition, ErrorCondition::UnknownSession);
+        assert_eq!(Element::from(condition), elem);
+    }
 }
@@ -7,6 +7,7 @@
 use crate::date::DateTime;
 use crate::hashes::Hash;
 use crate::jingle::{ContentId, Creator};
+use crate::jingle_thumnails::Thumbnail;
 use crate::ns;
 use crate::util::error::Error;
 use minidom::{Element, Node};
@@ -68,6 +69,9 @@ pub struct File {
 
     /// A list of hashes matching this entire file.
     pub hashes: Vec<Hash>,
+
+    /// Thumbnails of this file, e.g. a scaled down version of an image.
+    pub thumbnails: Vec<Thumbnail>,
 }
 
 impl File {
@@ -124,6 +128,12 @@ impl File {
         self.hashes.push(hash);
         self
     }
+
+    /// Add a thumbnail of this file.
+    pub fn add_thumbnail(mut self, thumbnail: Thumbnail) -> File {
+        self.thumbnails.push(thumbnail);
+        self
+    }
 }
 
 impl TryFrom<Element> for File {
@@ -141,6 +151,7 @@ impl TryFrom<Element> for File {
             size: None,
             range: None,
             hashes: vec![],
+            thumbnails: vec![],
         };
 
         for child in elem.children() {
@@ -181,6 +192,8 @@ impl TryFrom<Element> for File {
                 file.range = Some(Range::try_from(child.clone())?);
             } else if child.is("hash", ns::HASHES) {
                 file.hashes.push(Hash::try_from(child.clone())?);
+            } else if child.is("thumbnail", ns::JINGLE_THUMBNAILS) {
+                file.thumbnails.push(Thumbnail::try_from(child.clone())?);
             } else {
                 return Err(Error::ParseError("Unknown element in JingleFT file."));
             }
@@ -218,6 +231,7 @@ impl From<File> for Element {
             )
             .append_all(file.range)
             .append_all(file.hashes)
+            .append_all(file.thumbnails)
             .build()
     }
 }
@@ -339,9 +353,9 @@ mod tests {
     #[ignore]
     fn test_size() {
         assert_size!(Range, 32);
-        assert_size!(File, 104);
-        assert_size!(Description, 104);
-        assert_size!(Checksum, 128);
+        assert_size!(File, 116);
+        assert_size!(Description, 116);
+        assert_size!(Checksum, 140);
         assert_size!(Received, 16);
     }
 
@@ -349,9 +363,9 @@ mod tests {
     #[test]
     fn test_size() {
         assert_size!(Range, 48);
-        assert_size!(File, 176);
-        assert_size!(Description, 176);
-        assert_size!(Checksum, 208);
+        assert_size!(File, 200);
+        assert_size!(Description, 200);
+        assert_size!(Checksum, 232);
         assert_size!(Received, 32);
     }
 
@@ -387,6 +401,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_thumbnail() {
+        let elem: Element = r#"<description xmlns='urn:xmpp:jingle:apps:file-transfer:5'>
+  <file>
+    <media-type>image/jpeg</media-type>
+    <name>image.jpg</name>
+    <size>3032449</size>
+    <thumbnail xmlns='urn:xmpp:thumbs:1'
+               uri='cid:sha1+ffd7c8d28e9c5e82afea41f97108c6b4@bob.xmpp.org'
+               media-type='image/png'
+               width='128'
+               height='96'/>
+  </file>
+</description>
+"#
+        .parse()
+        .unwrap();
+        let desc = Description::try_from(elem).unwrap();
+        assert_eq!(desc.file.thumbnails.len(), 1);
+        assert_eq!(desc.file.thumbnails[0].width, 128);
+        assert_eq!(desc.file.thumbnails[0].media_type, "image/png");
+        let elem = Element::from(desc.file.clone());
+        let file = File::try_from(elem).unwrap();
+        assert_eq!(file.thumbnails, desc.file.thumbnails);
+    }
+
     #[test]
     fn test_request() {
         let elem: Element = r#"<description xmlns='urn:xmpp:jingle:apps:file-transfer:5'>
@@ -5,6 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::jingle_dtls_srtp::Fingerprint;
+use crate::jingle_webrtc_datachannel::SctpMap;
 use std::net::IpAddr;
 
 generate_element!(
@@ -23,7 +24,14 @@ generate_element!(
         candidates: Vec<Candidate> = ("candidate", JINGLE_ICE_UDP) => Candidate,
 
         /// Fingerprint of the key used for the DTLS handshake.
-        fingerprint: Option<Fingerprint> = ("fingerprint", JINGLE_DTLS) => Fingerprint
+        fingerprint: Option<Fingerprint> = ("fingerprint", JINGLE_DTLS) => Fingerprint,
+
+        /// The SCTP association of a WebRTC data channel session.
+        sctpmap: Option<SctpMap> = ("sctpmap", JINGLE_DTLS_SCTP) => SctpMap,
+
+        /// Whether the sender is done gathering candidates, once they were
+        /// all trickled.
+        end_of_candidates: Present<_> = ("end-of-candidates", JINGLE_ICE_UDP) => bool
     ]
 );
 
@@ -44,6 +52,40 @@ impl Transport {
         self.fingerprint = Some(fingerprint);
         self
     }
+
+    /// Set the User Fragment and Password of this transport, new ones in a
+    /// transport-info restarting ICE.
+    pub fn with_credentials(mut self, ufrag: String, pwd: String) -> Self {
+        self.ufrag = Some(ufrag);
+        self.pwd = Some(pwd);
+        self
+    }
+
+    /// Mark that no more candidates will be trickled.
+    pub fn with_end_of_candidates(mut self) -> Self {
+        self.end_of_candidates = true;
+        self
+    }
+
+    /// Whether this transport, received in a transport-info, restarts ICE
+    /// from the `current` one: its User Fragment or Password changed, and
+    /// all candidates gathered so far have to be discarded.
+    ///
+    /// Transports without credentials only trickle candidates.
+    pub fn is_restart_of(&self, current: &Transport) -> bool {
+        match (&self.ufrag, &self.pwd) {
+            (Some(ufrag), Some(pwd)) => {
+                current.ufrag.as_ref() != Some(ufrag) || current.pwd.as_ref() != Some(pwd)
+            }
+            _ => false,
+        }
+    }
+
+    /// Set the SCTP association of a WebRTC data channel session.
+    pub fn with_sctpmap(mut self, sctpmap: SctpMap) -> Self {
+        self.sctpmap = Some(sctpmap);
+        self
+    }
 }
 
 generate_attribute!(
@@ -118,7 +160,7 @@ mod tests {
     #[cfg(target_pointer_width = "32")]
     #[test]
     fn test_size() {
-        assert_size!(Transport, 64);
+        assert_size!(Transport, 72);
         assert_size!(Type, 1);
         assert_size!(Candidate, 88);
     }
@@ -126,7 +168,7 @@ mod tests {
     #[cfg(target_pointer_width = "64")]
     #[test]
     fn test_size() {
-        assert_size!(Transport, 128);
+        assert_size!(Transport, 136);
         assert_size!(Type, 1);
         assert_size!(Candidate, 128);
     }
@@ -203,9 +245,48 @@ mod tests {
             ufrag: None,
             candidates: vec![],
             fingerprint: Some(fingerprint),
+            sctpmap: None,
+            end_of_candidates: false,
         };
 
         let serialized: Element = transport.into();
         assert_eq!(serialized, reference);
     }
+
+    #[test]
+    fn test_trickle() {
+        let current: Element = "<transport xmlns='urn:xmpp:jingle:transports:ice-udp:1' pwd='asd88fgpdd777uzjYhagZg' ufrag='8hhy'/>"
+            .parse()
+            .unwrap();
+        let current = Transport::try_from(current).unwrap();
+        assert!(!current.end_of_candidates);
+
+        let elem: Element = "<transport xmlns='urn:xmpp:jingle:transports:ice-udp:1' pwd='asd88fgpdd777uzjYhagZg' ufrag='8hhy'>
+              <candidate component='1' foundation='1' generation='0' id='el0747fg11' ip='10.0.1.1' network='1' port='8998' priority='2130706431' protocol='udp' type='host'/>
+            </transport>"
+            .parse()
+            .unwrap();
+        let trickled = Transport::try_from(elem).unwrap();
+        assert_eq!(trickled.candidates.len(), 1);
+        assert!(!trickled.is_restart_of(&current));
+        assert!(!Transport::new().is_restart_of(&current));
+
+        let elem: Element = "<transport xmlns='urn:xmpp:jingle:transports:ice-udp:1'><end-of-candidates/></transport>"
+            .parse()
+            .unwrap();
+        let done = Transport::try_from(elem.clone()).unwrap();
+        assert!(done.end_of_candidates);
+        assert!(done.candidates.is_empty());
+        assert_eq!(
+            Element::from(Transport::new().with_end_of_candidates()),
+            elem
+        );
+
+        let restart = Transport::new()
+            .with_credentials(String::from("9uB6"), String::from("YH75Fviy6338Vbrhrlp8Yh"));
+        assert!(restart.is_restart_of(&current));
+        let restart = Transport::new()
+            .with_credentials(String::from("8hhy"), String::from("YH75Fviy6338Vbrhrlp8Yh"));
+        assert!(restart.is_restart_of(&current));
+    }
 }
@@ -109,13 +109,13 @@ mod tests {
     #[cfg(target_pointer_width = "32")]
     #[test]
     fn test_size() {
-        assert_size!(JingleMI, 72);
+        assert_size!(JingleMI, 68);
     }
 
     #[cfg(target_pointer_width = "64")]
     #[test]
     fn test_size() {
-        assert_size!(JingleMI, 144);
+        assert_size!(JingleMI, 136);
     }
 
     #[test]
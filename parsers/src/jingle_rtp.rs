@@ -149,6 +149,25 @@ generate_element!(
     ]
 );
 
+generate_element_enum!(
+    /// Informational message about an RTP session, sent in a session-info
+    /// action.  The mute and unmute messages aren’t supported yet.
+    SessionInfo, "session-info", JINGLE_RTP_INFO, {
+        /// The sender is active again, after having been on hold or muted.
+        Active => "active",
+
+        /// The sender put the call on hold.
+        Hold => "hold",
+
+        /// The sender resumed the call after having put it on hold.
+        Unhold => "unhold",
+
+        /// The device of the responder is ringing, but the user didn’t
+        /// accept the call yet.
+        Ringing => "ringing",
+    }
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,6 +180,7 @@ mod tests {
         assert_size!(Channels, 1);
         assert_size!(PayloadType, 64);
         assert_size!(Parameter, 24);
+        assert_size!(SessionInfo, 1);
     }
 
     #[cfg(target_pointer_width = "64")]
@@ -170,6 +190,7 @@ mod tests {
         assert_size!(Channels, 1);
         assert_size!(PayloadType, 104);
         assert_size!(Parameter, 48);
+        assert_size!(SessionInfo, 1);
     }
 
     #[test]
@@ -204,4 +225,20 @@ mod tests {
         assert_eq!(desc.media, "audio");
         assert_eq!(desc.ssrc, None);
     }
+
+    #[test]
+    fn test_session_info() {
+        let elem: Element = "<ringing xmlns='urn:xmpp:jingle:apps:rtp:info:1'/>"
+            .parse()
+            .unwrap();
+        let info = SessionInfo::try_from(elem.clone()).unwrap();
+        assert_eq!(info, SessionInfo::Ringing);
+        assert_eq!(Element::from(info), elem);
+
+        let elem: Element =
+            "<mute xmlns='urn:xmpp:jingle:apps:rtp:info:1' creator='responder' name='voice'/>"
+                .parse()
+                .unwrap();
+        assert!(SessionInfo::try_from(elem).is_err());
+    }
 }
@@ -21,11 +21,41 @@ generate_element!(
     ]
 );
 
+impl Thumbnail {
+    /// Create a thumbnail of these dimensions, available at this URI, e.g.
+    /// the cid of some Bits of Binary.
+    pub fn new<U: Into<String>, M: Into<String>>(
+        uri: U,
+        media_type: M,
+        width: u32,
+        height: u32,
+    ) -> Thumbnail {
+        Thumbnail {
+            uri: uri.into(),
+            media_type: media_type.into(),
+            width,
+            height,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::jingle_thumnails::Thumbnail;
     use minidom::Element;
 
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Thumbnail, 32);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Thumbnail, 56);
+    }
+
     #[test]
     fn test_simple_parse() {
         // Extracted from https://xmpp.org/extensions/xep-0264.html#example-1
@@ -0,0 +1,110 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+generate_attribute!(
+    /// The protocol carried over SCTP.
+    Protocol, "protocol", {
+        /// WebRTC data channels.
+        WebrtcDatachannel => "webrtc-datachannel",
+    }
+);
+
+generate_element!(
+    /// Describes the SCTP association of a data channel session, carried in
+    /// an ICE-UDP transport along with the DTLS fingerprint.
+    SctpMap, "sctpmap", JINGLE_DTLS_SCTP,
+    attributes: [
+        /// The SCTP port.
+        number: Required<u16> = "number",
+
+        /// The protocol carried over this association.
+        protocol: Required<Protocol> = "protocol",
+
+        /// The maximum number of streams.
+        streams: Option<u16> = "streams",
+    ]
+);
+
+impl SctpMap {
+    /// Create a new WebRTC data channel association on this SCTP port.
+    pub fn new(number: u16) -> SctpMap {
+        SctpMap {
+            number,
+            protocol: Protocol::WebrtcDatachannel,
+            streams: None,
+        }
+    }
+
+    /// Set the maximum number of streams.
+    pub fn with_streams(mut self, streams: u16) -> SctpMap {
+        self.streams = Some(streams);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jingle_ice_udp::Transport;
+    #[cfg(not(feature = "disable-validation"))]
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Protocol, 0);
+        assert_size!(SctpMap, 6);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Protocol, 0);
+        assert_size!(SctpMap, 6);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<sctpmap xmlns='urn:xmpp:jingle:transports:dtls-sctp:1' number='5000' protocol='webrtc-datachannel' streams='1024'/>"
+            .parse()
+            .unwrap();
+        let sctpmap = SctpMap::try_from(elem.clone()).unwrap();
+        assert_eq!(sctpmap, SctpMap::new(5000).with_streams(1024));
+        assert_eq!(Element::from(sctpmap), elem);
+    }
+
+    #[test]
+    fn test_transport() {
+        let elem: Element = "<transport xmlns='urn:xmpp:jingle:transports:ice-udp:1' pwd='asd88fgpdd777uzjYhagZg' ufrag='8hhy'>
+              <fingerprint xmlns='urn:xmpp:jingle:apps:dtls:0' hash='sha-256' setup='actpass'>02:1A:CC:54:27:AB:EB:9C:53:3F:3E:4B:65:2E:7D:46:3F:54:42:CD:54:F1:7A:03:A2:7D:F9:B0:7F:46:19:B2</fingerprint>
+              <sctpmap xmlns='urn:xmpp:jingle:transports:dtls-sctp:1' number='5000' protocol='webrtc-datachannel'/>
+            </transport>"
+            .parse()
+            .unwrap();
+        let transport = Transport::try_from(elem).unwrap();
+        assert!(transport.fingerprint.is_some());
+        assert_eq!(transport.sctpmap, Some(SctpMap::new(5000)));
+
+        let transport = Transport::new().with_sctpmap(SctpMap::new(5000));
+        let elem = Element::from(transport);
+        assert!(elem.has_child("sctpmap", crate::ns::JINGLE_DTLS_SCTP));
+    }
+
+    #[cfg(not(feature = "disable-validation"))]
+    #[test]
+    fn test_invalid_protocol() {
+        let elem: Element = "<sctpmap xmlns='urn:xmpp:jingle:transports:dtls-sctp:1' number='5000' protocol='sip'/>"
+            .parse()
+            .unwrap();
+        let error = SctpMap::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Unknown value for 'protocol' attribute.");
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::iq::{IqGetPayload, IqResultPayload};
+use crate::presence::PresencePayload;
+use crate::util::text_node_codecs::{Codec, OptionalCodec, Text};
+
+generate_element!(
+    /// The time elapsed since the last activity of an entity, either requested
+    /// with an iq, or included in a presence as per
+    /// [XEP-0256](https://xmpp.org/extensions/xep-0256.html), in which case it
+    /// is relative to the time the presence was sent.
+    #[derive(Default)]
+    LastActivity, "query", LAST_ACTIVITY,
+    attributes: [
+        /// The number of seconds since the last activity, absent in a request.
+        seconds: Option<u64> = "seconds",
+    ],
+    text: (
+        /// The status of the user when they went offline, if any.
+        status: OptionalCodec<Text>
+    )
+);
+
+impl LastActivity {
+    /// Create the answer to a request, or a presence payload, for this many
+    /// seconds of inactivity.
+    pub fn new(seconds: u64) -> LastActivity {
+        LastActivity {
+            seconds: Some(seconds),
+            status: None,
+        }
+    }
+}
+
+impl IqGetPayload for LastActivity {}
+impl IqResultPayload for LastActivity {}
+impl PresencePayload for LastActivity {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presence::Presence;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(LastActivity, 24);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(LastActivity, 40);
+    }
+
+    #[test]
+    fn test_request() {
+        let elem: Element = "<query xmlns='jabber:iq:last'/>".parse().unwrap();
+        let query = LastActivity::try_from(elem.clone()).unwrap();
+        assert_eq!(query, LastActivity::default());
+        assert_eq!(Element::from(query), elem);
+    }
+
+    #[test]
+    fn test_offline() {
+        let elem: Element = "<query xmlns='jabber:iq:last' seconds='903'>Heading Home</query>"
+            .parse()
+            .unwrap();
+        let query = LastActivity::try_from(elem).unwrap();
+        assert_eq!(query.seconds, Some(903));
+        assert_eq!(query.status.as_deref(), Some("Heading Home"));
+    }
+
+    #[test]
+    fn test_presence() {
+        let elem: Element = "<presence xmlns='jabber:client' from='juliet@capulet.com/balcony'>
+              <show>away</show>
+              <query xmlns='jabber:iq:last' seconds='600'/>
+            </presence>"
+            .parse()
+            .unwrap();
+        let presence = Presence::try_from(elem).unwrap();
+        let last = presence.get_payload::<LastActivity>().unwrap().unwrap();
+        assert_eq!(last, LastActivity::new(600));
+    }
+}
@@ -50,9 +50,15 @@ pub mod presence;
 /// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
 pub mod sasl;
 /// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
+pub mod stanza;
+/// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
 pub mod stanza_error;
 /// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
 pub mod stream;
+/// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
+pub mod stream_error;
+/// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
+pub mod stream_features;
 
 /// RFC 6121: Extensible Messaging and Presence Protocol (XMPP): Instant Messaging and Presence
 pub mod roster;
@@ -63,6 +69,12 @@ pub mod websocket;
 /// XEP-0004: Data Forms
 pub mod data_forms;
 
+/// XEP-0012: Last Activity
+pub mod last_activity;
+
+/// XEP-0020: Feature Negotiation
+pub mod feature_neg;
+
 /// XEP-0030: Service Discovery
 pub mod disco;
 
@@ -87,6 +99,9 @@ pub mod rsm;
 /// XEP-0060: Publish-Subscribe
 pub mod pubsub;
 
+/// XEP-0070: Verifying HTTP Requests via XMPP
+pub mod http_auth;
+
 /// XEP-0071: XHTML-IM
 pub mod xhtml;
 
@@ -105,6 +120,15 @@ pub mod chatstates;
 /// XEP-0092: Software Version
 pub mod version;
 
+/// XEP-0095: Stream Initiation
+pub mod si;
+
+/// XEP-0096: SI File Transfer
+pub mod si_ft;
+
+/// XEP-0103: URL Address Information
+pub mod url_data;
+
 /// XEP-0107: User Mood
 pub mod mood;
 
@@ -117,6 +141,9 @@ pub mod caps;
 /// XEP-0118: User Tune
 pub mod tune;
 
+/// XEP-0144: Roster Item Exchange
+pub mod rosterx;
+
 ///XEP-0153: vCard-Based Avatars
 pub mod vcard_update;
 
@@ -168,6 +195,9 @@ pub mod attention;
 /// XEP-0231: Bits of Binary
 pub mod bob;
 
+/// XEP-0232: Software Information
+pub mod software_info;
+
 /// XEP-0234: Jingle File Transfer
 pub mod jingle_ft;
 
@@ -216,30 +246,45 @@ pub mod jingle_dtls_srtp;
 /// XEP-0328: JID Prep
 pub mod jid_prep;
 
+/// XEP-0334: Message Processing Hints
+pub mod hints;
+
 /// XEP-0338: Jingle Grouping Framework
 pub mod jingle_grouping;
 
 /// XEP-0339: Source-Specific Media Attributes in Jingle
 pub mod jingle_ssma;
 
+/// XEP-0343: Signaling WebRTC datachannels in Jingle
+pub mod jingle_webrtc_datachannel;
+
 /// XEP-0352: Client State Indication
 pub mod csi;
 
 /// XEP-0353: Jingle Message Initiation
 pub mod jingle_message;
 
+/// XEP-0355: Namespace Delegation
+pub mod delegation;
+
 /// XEP-0359: Unique and Stable Stanza IDs
 pub mod stanza_id;
 
 /// XEP-0363: HTTP File Upload
 pub mod http_upload;
 
+/// XEP-0367: Message Attaching
+pub mod attaching;
+
 /// XEP-0369: Mediated Information eXchange (MIX)
 pub mod mix;
 
 /// XEP-0373: OpenPGP for XMPP
 pub mod openpgp;
 
+/// XEP-0377: Spam Reporting
+pub mod reporting;
+
 /// XEP-0380: Explicit Message Encryption
 pub mod eme;
 
@@ -249,14 +294,29 @@ pub mod legacy_omemo;
 /// XEP-0390: Entity Capabilities 2.0
 pub mod ecaps2;
 
+/// XEP-0392: Consistent Color Generation
+pub mod color;
+
 /// XEP-0402: PEP Native Bookmarks
 pub mod bookmarks2;
 
+/// XEP-0420: Stanza Content Encryption
+pub mod sce;
+
 /// XEP-0421: Anonymous unique occupant identifiers for MUCs
 pub mod occupant_id;
 
+/// XEP-0437: Room Activity Indicators
+pub mod rai;
+
 /// XEP-0441: Message Archive Management Preferences
 pub mod mam_prefs;
 
 /// XEP-0444: Message Reactions
 pub mod reactions;
+
+/// XEP-0448: Encryption for stateless file sharing
+pub mod esfs;
+
+/// XEP-0482: Call Invites
+pub mod call_invites;
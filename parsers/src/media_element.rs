@@ -4,6 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::bob::ContentId;
 use crate::util::text_node_codecs::{Codec, Text, Trimmed};
 
 generate_element!(
@@ -25,6 +26,14 @@ generate_element!(
     )
 );
 
+impl URI {
+    /// The cid of the Bits of Binary this URI references, if it is a valid
+    /// cid: URI, e.g. the image of a captcha.
+    pub fn cid(&self) -> Option<ContentId> {
+        self.uri.strip_prefix("cid:")?.parse().ok()
+    }
+}
+
 generate_element!(
     /// References a media element, to be used in [data
     /// forms](../data_forms/index.html).
@@ -89,6 +98,17 @@ mod tests {
         assert_eq!(media.uris.len(), 1);
         assert_eq!(media.uris[0].type_, "text/html");
         assert_eq!(media.uris[0].uri, "https://example.org/");
+        assert!(media.uris[0].cid().is_none());
+    }
+
+    #[test]
+    fn test_cid() {
+        let elem: Element = "<media xmlns='urn:xmpp:media-element'><uri type='image/png'>cid:sha1+f24030b8d91d233bac14777be5ab531ca3b9f102@bob.xmpp.org</uri></media>".parse().unwrap();
+        let media = MediaElement::try_from(elem).unwrap();
+        assert_eq!(
+            media.uris[0].cid().unwrap().to_string(),
+            "sha1+f24030b8d91d233bac14777be5ab531ca3b9f102@bob.xmpp.org"
+        );
     }
 
     #[test]
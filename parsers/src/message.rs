@@ -5,7 +5,9 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::ns;
+use crate::stanza_error::StanzaError;
 use crate::util::error::Error;
+use crate::util::{extract_payload, get_payload, make_id};
 use crate::Element;
 use jid::Jid;
 use std::collections::BTreeMap;
@@ -147,12 +149,44 @@ impl Message {
         Self::new_with_type(MessageType::Normal, to)
     }
 
+    /// Set the emitter of this message, this should only be useful for
+    /// servers and components, as clients can only send messages from their
+    /// own resource (which is implicit).
+    pub fn with_from<J: Into<Jid>>(mut self, from: J) -> Message {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Set the identifier for this message.
+    pub fn with_id(mut self, id: String) -> Message {
+        self.id = Some(id);
+        self
+    }
+
+    /// Set a random identifier for this message.
+    pub fn with_auto_id(mut self) -> Message {
+        self.id = Some(make_id());
+        self
+    }
+
     /// Appends a body in given lang to the Message
     pub fn with_body(mut self, lang: Lang, body: String) -> Message {
         self.bodies.insert(lang, Body(body));
         self
     }
 
+    /// Appends a subject in given lang to the Message
+    pub fn with_subject(mut self, lang: Lang, subject: String) -> Message {
+        self.subjects.insert(lang, Subject(subject));
+        self
+    }
+
+    /// Set the thread this message belongs to.
+    pub fn with_thread(mut self, thread: Thread) -> Message {
+        self.thread = Some(thread);
+        self
+    }
+
     /// Set a payload inside this message.
     pub fn with_payload<P: MessagePayload>(mut self, payload: P) -> Message {
         self.payloads.push(payload.into());
@@ -165,6 +199,23 @@ impl Message {
         self
     }
 
+    /// Creates the error bouncing this message back to its sender, with the
+    /// same id and thread.
+    ///
+    /// Messages of type error must never be answered with another error.
+    pub fn error_reply(message: &Message, error: StanzaError) -> Message {
+        Message {
+            from: message.to.clone(),
+            to: message.from.clone(),
+            id: message.id.clone(),
+            type_: MessageType::Error,
+            bodies: BTreeMap::new(),
+            subjects: BTreeMap::new(),
+            thread: message.thread.clone(),
+            payloads: vec![error.into()],
+        }
+    }
+
     fn get_best<'a, T>(
         map: &'a BTreeMap<Lang, T>,
         preferred_langs: Vec<&str>,
@@ -216,27 +267,16 @@ impl Message {
     pub fn extract_payload<T: TryFrom<Element, Error = Error>>(
         &mut self,
     ) -> Result<Option<T>, Error> {
-        let mut buf = Vec::with_capacity(self.payloads.len());
-        let mut iter = self.payloads.drain(..);
-        let mut result = Ok(None);
-        for item in &mut iter {
-            match T::try_from(item) {
-                Ok(v) => {
-                    result = Ok(Some(v));
-                    break;
-                }
-                Err(Error::TypeMismatch(_, _, residual)) => {
-                    buf.push(residual);
-                }
-                Err(other) => {
-                    result = Err(other);
-                    break;
-                }
-            }
-        }
-        buf.extend(iter);
-        std::mem::swap(&mut buf, &mut self.payloads);
-        result
+        extract_payload(&mut self.payloads)
+    }
+
+    /// Parse the first payload of the given type, leaving the message
+    /// untouched.
+    ///
+    /// Returns `None` if no payload matches, or the result of parsing the
+    /// first matching one.
+    pub fn get_payload<T: TryFrom<Element, Error = Error>>(&self) -> Option<Result<T, Error>> {
+        get_payload(&self.payloads)
     }
 }
 
@@ -245,6 +285,19 @@ impl TryFrom<Element> for Message {
 
     fn try_from(root: Element) -> Result<Message, Error> {
         check_self!(root, "message", DEFAULT_NS);
+        Message::try_from(&root)
+    }
+}
+
+/// Parses a message without taking the element, e.g. to keep it around when
+/// it is invalid.
+impl TryFrom<&Element> for Message {
+    type Error = Error;
+
+    fn try_from(root: &Element) -> Result<Message, Error> {
+        if !root.is("message", ns::DEFAULT_NS) {
+            return Err(Error::TypeMismatch("message", ns::DEFAULT_NS, root.clone()));
+        }
         let from = get_attr!(root, "from", Option);
         let to = get_attr!(root, "to", Option);
         let id = get_attr!(root, "id", Option);
@@ -324,6 +377,7 @@ impl From<Message> for Element {
                 );
                 body
             }))
+            .append_all(message.thread.map(Element::from))
             .append_all(message.payloads)
             .build()
     }
@@ -332,6 +386,7 @@ impl From<Message> for Element {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::stanza_error::ErrorType;
     use std::str::FromStr;
 
     #[cfg(target_pointer_width = "32")]
@@ -370,6 +425,30 @@ mod tests {
         assert!(message.payloads.is_empty());
     }
 
+    #[test]
+    fn test_by_reference() {
+        #[cfg(not(feature = "component"))]
+        let elem: Element = "<message xmlns='jabber:client' type='coucou'/>"
+            .parse()
+            .unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<message xmlns='jabber:component:accept' type='coucou'/>"
+            .parse()
+            .unwrap();
+        let error = Message::try_from(&elem).unwrap_err();
+        assert_eq!(
+            format!("{}", error),
+            "parse error: Unknown value for 'type' attribute."
+        );
+        assert_eq!(elem.attr("type"), Some("coucou"));
+
+        let elem: Element = "<presence xmlns='jabber:client'/>".parse().unwrap();
+        match Message::try_from(&elem) {
+            Err(Error::TypeMismatch("message", _, other)) => assert_eq!(other, elem),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn test_serialise() {
         #[cfg(not(feature = "component"))]
@@ -517,4 +596,54 @@ mod tests {
         };
         assert_eq!(message.payloads.len(), 0);
     }
+
+    #[test]
+    fn test_builders() {
+        let message = Message::chat(Jid::new("coucou@example.org").unwrap())
+            .with_from(Jid::new("test@example.org/a").unwrap())
+            .with_auto_id()
+            .with_body(Lang::new(), String::from("Hello"))
+            .with_subject(String::from("en"), String::from("Greetings"))
+            .with_thread(Thread(String::from("thread")));
+        let other = Message::chat(None).with_auto_id();
+        assert_ne!(message.id, other.id);
+
+        let elem: Element = message.into();
+        let message = Message::try_from(elem).unwrap();
+        assert_eq!(message.from.unwrap().as_str(), "test@example.org/a");
+        assert!(message.id.is_some());
+        assert_eq!(message.bodies[""], Body(String::from("Hello")));
+        assert_eq!(message.subjects["en"], Subject(String::from("Greetings")));
+        assert_eq!(message.thread, Some(Thread(String::from("thread"))));
+    }
+
+    #[test]
+    fn test_get_payload() {
+        use super::super::attention::Attention;
+        use super::super::pubsub::event::PubSubEvent;
+
+        #[cfg(not(feature = "component"))]
+        let elem: Element = "<message xmlns='jabber:client' to='coucou@example.org' type='chat'><attention xmlns='urn:xmpp:attention:0'/></message>".parse().unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<message xmlns='jabber:component:accept' to='coucou@example.org' type='chat'><attention xmlns='urn:xmpp:attention:0'/></message>".parse().unwrap();
+        let message = Message::try_from(elem).unwrap();
+        assert!(message.get_payload::<PubSubEvent>().is_none());
+        assert!(matches!(message.get_payload::<Attention>(), Some(Ok(_))));
+        assert_eq!(message.payloads.len(), 1);
+    }
+
+    #[test]
+    fn test_error_reply() {
+        let elem: Element = "<message xmlns='jabber:client' id='msg1' from='romeo@montague.net/orchard' to='juliet@capulet.lit' type='chat'><body>Art thou not Romeo, and a Montague?</body></message>"
+            .parse()
+            .unwrap();
+        let message = Message::try_from(elem).unwrap();
+        let reply = Message::error_reply(&message, StanzaError::recipient_unavailable());
+        assert_eq!(reply.type_, MessageType::Error);
+        assert_eq!(reply.id.as_deref(), Some("msg1"));
+        assert_eq!(reply.to, message.from);
+        assert!(reply.bodies.is_empty());
+        let error = reply.get_payload::<StanzaError>().unwrap().unwrap();
+        assert_eq!(error.type_, ErrorType::Wait);
+    }
 }
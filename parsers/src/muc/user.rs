@@ -11,7 +11,7 @@ use crate::presence::PresencePayload;
 use crate::util::error::Error;
 use crate::Element;
 
-use jid::FullJid;
+use jid::{FullJid, Jid};
 
 generate_attribute_enum!(
 /// Lists all of the possible status codes used in MUC presences.
@@ -259,6 +259,42 @@ impl Item {
     }
 }
 
+generate_element!(
+    /// A mediated invitation, sent to the room which forwards it to the
+    /// invitee.
+    Invite, "invite", MUC_USER, attributes: [
+        /// The invitee, when sent to the room.
+        to: Option<Jid> = "to",
+
+        /// The inviter, when forwarded by the room.
+        from: Option<Jid> = "from",
+    ], children: [
+        /// A reason for this invitation.
+        reason: Option<Reason> = ("reason", MUC_USER) => Reason,
+
+        /// Whether this continues a one-to-one discussion.
+        continue_: Option<Continue> = ("continue", MUC_USER) => Continue
+    ]
+);
+
+impl Invite {
+    /// Creates an invitation of this invitee, to send to the room.
+    pub fn new(to: Jid) -> Invite {
+        Invite {
+            to: Some(to),
+            from: None,
+            reason: None,
+            continue_: None,
+        }
+    }
+
+    /// Set a reason for this Invite
+    pub fn with_reason<S: Into<String>>(mut self, reason: S) -> Invite {
+        self.reason = Some(Reason(reason.into()));
+        self
+    }
+}
+
 generate_element!(
     /// The main muc#user element.
     MucUser, "x", MUC_USER, children: [
@@ -266,7 +302,13 @@ generate_element!(
         status: Vec<Status> = ("status", MUC_USER) => Status,
 
         /// List of items.
-        items: Vec<Item> = ("item", MUC_USER) => Item
+        items: Vec<Item> = ("item", MUC_USER) => Item,
+
+        /// List of invitations.
+        invites: Vec<Invite> = ("invite", MUC_USER) => Invite,
+
+        /// The password of the room, along with an invitation.
+        password: Option<String> = ("password", MUC_USER) => String
     ]
 );
 
@@ -282,6 +324,8 @@ impl MucUser {
         MucUser {
             status: vec![],
             items: vec![],
+            invites: vec![],
+            password: None,
         }
     }
 
@@ -296,6 +340,18 @@ impl MucUser {
         self.items = items;
         self
     }
+
+    /// Add an invitation to this MucUser
+    pub fn with_invite(mut self, invite: Invite) -> MucUser {
+        self.invites.push(invite);
+        self
+    }
+
+    /// Set the password of the room for this MucUser
+    pub fn with_password<S: Into<String>>(mut self, password: S) -> MucUser {
+        self.password = Some(password.into());
+        self
+    }
 }
 
 impl MessagePayload for MucUser {}
@@ -334,6 +390,41 @@ mod tests {
         assert_eq!(muc_user.items[0].role, Role::Moderator);
     }
 
+    #[test]
+    fn invite() {
+        let elem: Element = "<x xmlns='http://jabber.org/protocol/muc#user'>
+                <invite from='crone1@shakespeare.lit/desktop'>
+                    <reason>Hey Hecate, this is the place for all good witches!</reason>
+                </invite>
+                <password>cauldronburn</password>
+            </x>"
+            .parse()
+            .unwrap();
+        let muc_user = MucUser::try_from(elem).unwrap();
+        assert_eq!(muc_user.invites.len(), 1);
+        let invite = &muc_user.invites[0];
+        assert_eq!(invite.to, None);
+        assert_eq!(
+            invite.from,
+            Some(Jid::new("crone1@shakespeare.lit/desktop").unwrap())
+        );
+        assert_eq!(
+            invite.reason,
+            Some(Reason(String::from(
+                "Hey Hecate, this is the place for all good witches!"
+            )))
+        );
+        assert_eq!(muc_user.password.as_deref(), Some("cauldronburn"));
+
+        let elem: Element = "<x xmlns='http://jabber.org/protocol/muc#user'><invite to='hecate@shakespeare.lit'><reason>Hi</reason></invite></x>"
+            .parse()
+            .unwrap();
+        let muc_user = MucUser::new().with_invite(
+            Invite::new(Jid::new("hecate@shakespeare.lit").unwrap()).with_reason("Hi"),
+        );
+        assert_eq!(Element::from(muc_user), elem);
+    }
+
     #[test]
     fn test_invalid_child() {
         let elem: Element = "<x xmlns='http://jabber.org/protocol/muc#user'>
@@ -357,6 +448,8 @@ mod tests {
         let muc = MucUser {
             status: vec![],
             items: vec![],
+            invites: vec![],
+            password: None,
         };
         let elem2 = muc.into();
         assert_eq!(elem, elem2);
@@ -12,6 +12,8 @@ pub const XMPP_STANZAS: &str = "urn:ietf:params:xml:ns:xmpp-stanzas";
 /// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
 pub const STREAM: &str = "http://etherx.jabber.org/streams";
 /// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
+pub const XMPP_STREAMS: &str = "urn:ietf:params:xml:ns:xmpp-streams";
+/// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
 pub const TLS: &str = "urn:ietf:params:xml:ns:xmpp-tls";
 /// RFC 6120: Extensible Messaging and Presence Protocol (XMPP): Core
 pub const SASL: &str = "urn:ietf:params:xml:ns:xmpp-sasl";
@@ -27,6 +29,12 @@ pub const WEBSOCKET: &str = "urn:ietf:params:xml:ns:xmpp-framing";
 /// XEP-0004: Data Forms
 pub const DATA_FORMS: &str = "jabber:x:data";
 
+/// XEP-0012: Last Activity
+pub const LAST_ACTIVITY: &str = "jabber:iq:last";
+
+/// XEP-0020: Feature Negotiation
+pub const FEATURE_NEG: &str = "http://jabber.org/protocol/feature-neg";
+
 /// XEP-0030: Service Discovery
 pub const DISCO_INFO: &str = "http://jabber.org/protocol/disco#info";
 /// XEP-0030: Service Discovery
@@ -63,6 +71,9 @@ pub const PUBSUB_OWNER: &str = "http://jabber.org/protocol/pubsub#owner";
 /// XEP-0060: Publish-Subscribe node configuration
 pub const PUBSUB_CONFIGURE: &str = "http://jabber.org/protocol/pubsub#node_config";
 
+/// XEP-0070: Verifying HTTP Requests via XMPP
+pub const HTTP_AUTH: &str = "http://jabber.org/protocol/http-auth";
+
 /// XEP-0071: XHTML-IM
 pub const XHTML_IM: &str = "http://jabber.org/protocol/xhtml-im";
 /// XEP-0071: XHTML-IM
@@ -82,6 +93,15 @@ pub const CHATSTATES: &str = "http://jabber.org/protocol/chatstates";
 /// XEP-0092: Software Version
 pub const VERSION: &str = "jabber:iq:version";
 
+/// XEP-0095: Stream Initiation
+pub const SI: &str = "http://jabber.org/protocol/si";
+
+/// XEP-0096: SI File Transfer
+pub const SI_FILE_TRANSFER: &str = "http://jabber.org/protocol/si/profile/file-transfer";
+
+/// XEP-0103: URL Address Information
+pub const URL_DATA: &str = "http://jabber.org/protocol/url-data";
+
 /// XEP-0107: User Mood
 pub const MOOD: &str = "http://jabber.org/protocol/mood";
 
@@ -97,6 +117,12 @@ pub const CAPS: &str = "http://jabber.org/protocol/caps";
 /// XEP-0118: User Tune
 pub const TUNE: &str = "http://jabber.org/protocol/tune";
 
+/// XEP-0138: Stream Compression
+pub const COMPRESS_FEATURE: &str = "http://jabber.org/features/compress";
+
+/// XEP-0144: Roster Item Exchange
+pub const ROSTERX: &str = "http://jabber.org/protocol/rosterx";
+
 /// XEP-0153: vCard-Based Avatars
 pub const VCARD_UPDATE: &str = "vcard-temp:x:update";
 
@@ -105,6 +131,8 @@ pub const SERVER_INFO: &str = "http://jabber.org/network/serverinfo";
 
 /// XEP-0166: Jingle
 pub const JINGLE: &str = "urn:xmpp:jingle:1";
+/// XEP-0166: Jingle
+pub const JINGLE_ERRORS: &str = "urn:xmpp:jingle:errors:1";
 
 /// XEP-0167: Jingle RTP Sessions
 pub const JINGLE_RTP: &str = "urn:xmpp:jingle:apps:rtp:1";
@@ -112,6 +140,8 @@ pub const JINGLE_RTP: &str = "urn:xmpp:jingle:apps:rtp:1";
 pub const JINGLE_RTP_AUDIO: &str = "urn:xmpp:jingle:apps:rtp:audio";
 /// XEP-0167: Jingle RTP Sessions
 pub const JINGLE_RTP_VIDEO: &str = "urn:xmpp:jingle:apps:rtp:video";
+/// XEP-0167: Jingle RTP Sessions
+pub const JINGLE_RTP_INFO: &str = "urn:xmpp:jingle:apps:rtp:info:1";
 
 /// XEP-0172: User Nickname
 pub const NICK: &str = "http://jabber.org/protocol/nick";
@@ -154,11 +184,17 @@ pub const ATTENTION: &str = "urn:xmpp:attention:0";
 /// XEP-0231: Bits of Binary
 pub const BOB: &str = "urn:xmpp:bob";
 
+/// XEP-0232: Software Information
+pub const SOFTWARE_INFO: &str = "urn:xmpp:dataforms:softwareinfo";
+
 /// XEP-0234: Jingle File Transfer
 pub const JINGLE_FT: &str = "urn:xmpp:jingle:apps:file-transfer:5";
 /// XEP-0234: Jingle File Transfer
 pub const JINGLE_FT_ERROR: &str = "urn:xmpp:jingle:apps:file-transfer:errors:0";
 
+/// XEP-0237: Roster Versioning
+pub const ROSTERVER: &str = "urn:xmpp:features:rosterver";
+
 /// XEP-0257: Client Certificate Management for SASL EXTERNAL
 pub const SASL_CERT: &str = "urn:xmpp:saslcert:1";
 
@@ -219,24 +255,36 @@ pub const JINGLE_DTLS: &str = "urn:xmpp:jingle:apps:dtls:0";
 /// XEP-0328: JID Prep
 pub const JID_PREP: &str = "urn:xmpp:jidprep:0";
 
+/// XEP-0334: Message Processing Hints
+pub const HINTS: &str = "urn:xmpp:hints";
+
 /// XEP-0338: Jingle Grouping Framework
 pub const JINGLE_GROUPING: &str = "urn:xmpp:jingle:apps:grouping:0";
 
 /// XEP-0339: Source-Specific Media Attributes in Jingle
 pub const JINGLE_SSMA: &str = "urn:xmpp:jingle:apps:rtp:ssma:0";
 
+/// XEP-0343: Signaling WebRTC datachannels in Jingle
+pub const JINGLE_DTLS_SCTP: &str = "urn:xmpp:jingle:transports:dtls-sctp:1";
+
 /// XEP-0352: Client State Indication
 pub const CSI: &str = "urn:xmpp:csi:0";
 
 /// XEP-0353: Jingle Message Initiation
 pub const JINGLE_MESSAGE: &str = "urn:xmpp:jingle-message:0";
 
+/// XEP-0355: Namespace Delegation
+pub const DELEGATION: &str = "urn:xmpp:delegation:2";
+
 /// XEP-0359: Unique and Stable Stanza IDs
 pub const SID: &str = "urn:xmpp:sid:0";
 
 /// XEP-0363: HTTP File Upload
 pub const HTTP_UPLOAD: &str = "urn:xmpp:http:upload:0";
 
+/// XEP-0367: Message Attaching
+pub const MESSAGE_ATTACHING: &str = "urn:xmpp:message-attaching:1";
+
 /// XEP-0369: Mediated Information eXchange (MIX)
 pub const MIX_CORE: &str = "urn:xmpp:mix:core:1";
 /// XEP-0369: Mediated Information eXchange (MIX)
@@ -259,6 +307,9 @@ pub const OX: &str = "urn:xmpp:openpgp:0";
 /// XEP-0373: OpenPGP for XMPP
 pub const OX_PUBKEYS: &str = "urn:xmpp:openpgp:0:public-keys";
 
+/// XEP-0377: Spam Reporting
+pub const REPORTING: &str = "urn:xmpp:reporting:1";
+
 /// XEP-0380: Explicit Message Encryption
 pub const EME: &str = "urn:xmpp:eme:0";
 
@@ -281,12 +332,27 @@ pub const BOOKMARKS2_COMPAT: &str = "urn:xmpp:bookmarks:1#compat";
 /// XEP-0402: PEP Native Bookmarks
 pub const BOOKMARKS2_COMPAT_PEP: &str = "urn:xmpp:bookmarks:1#compat-pep";
 
+/// XEP-0420: Stanza Content Encryption
+pub const SCE: &str = "urn:xmpp:sce:1";
+
 /// XEP-0421: Anonymous unique occupant identifiers for MUCs
 pub const OID: &str = "urn:xmpp:occupant-id:0";
 
+/// XEP-0437: Room Activity Indicators
+pub const RAI: &str = "urn:xmpp:rai:0";
+
 /// XEP-0444: Message Reactions
 pub const REACTIONS: &str = "urn:xmpp:reactions:0";
 
+/// XEP-0447: Stateless file sharing
+pub const SFS: &str = "urn:xmpp:sfs:0";
+
+/// XEP-0448: Encryption for stateless file sharing
+pub const ESFS: &str = "urn:xmpp:esfs:0";
+
+/// XEP-0482: Call Invites
+pub const CALL_INVITES: &str = "urn:xmpp:call-invites:0";
+
 /// Alias for the main namespace of the stream, that is "jabber:client" when
 /// the component feature isn’t enabled.
 #[cfg(not(feature = "component"))]
@@ -296,3 +362,38 @@ pub const DEFAULT_NS: &str = JABBER_CLIENT;
 /// "jabber:component:accept" when the component feature is enabled.
 #[cfg(feature = "component")]
 pub const DEFAULT_NS: &str = COMPONENT_ACCEPT;
+
+/// A namespace defined outside of this crate, for instance by a crate
+/// implementing another XEP on top of this one.
+///
+/// ```
+/// use xmpp_parsers::{ns::Namespace, Element};
+///
+/// struct Example;
+///
+/// impl Namespace for Example {
+///     const NS: &'static str = "urn:example:0";
+/// }
+///
+/// let elem: Element = "<foo xmlns='urn:example:0'/>".parse().unwrap();
+/// let elem = Example::check_element(elem, "foo").unwrap();
+/// assert!(Example::check_element(elem, "bar").is_err());
+/// ```
+pub trait Namespace {
+    /// The namespace URI.
+    const NS: &'static str;
+
+    /// Checks that `elem` is a `name` element in this namespace, the same
+    /// way this crate’s own parsers do, returning it unaltered on success.
+    #[allow(clippy::result_large_err)]
+    fn check_element(
+        elem: crate::Element,
+        name: &'static str,
+    ) -> Result<crate::Element, crate::Error> {
+        if elem.is(name, Self::NS) {
+            Ok(elem)
+        } else {
+            Err(crate::Error::TypeMismatch(name, Self::NS, elem))
+        }
+    }
+}
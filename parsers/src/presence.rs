@@ -7,6 +7,7 @@
 
 use crate::ns;
 use crate::util::error::Error;
+use crate::util::{extract_payload, get_payload, make_id};
 use jid::Jid;
 use minidom::{Element, IntoAttributeValue};
 use std::collections::BTreeMap;
@@ -241,6 +242,12 @@ impl Presence {
         self
     }
 
+    /// Set a random identifier for this presence.
+    pub fn with_auto_id(mut self) -> Presence {
+        self.id = Some(make_id());
+        self
+    }
+
     /// Set the availability information of this presence.
     pub fn with_show(mut self, show: Show) -> Presence {
         self.show = Some(show);
@@ -253,6 +260,16 @@ impl Presence {
         self
     }
 
+    /// Set the status message of this presence, in given lang.
+    pub fn with_status<L, S>(mut self, lang: L, status: S) -> Presence
+    where
+        L: Into<Lang>,
+        S: Into<Status>,
+    {
+        self.set_status(lang, status);
+        self
+    }
+
     /// Set a payload inside this presence.
     pub fn with_payload<P: PresencePayload>(mut self, payload: P) -> Presence {
         self.payloads.push(payload.into());
@@ -278,6 +295,25 @@ impl Presence {
     pub fn add_payload<P: PresencePayload>(&mut self, payload: P) {
         self.payloads.push(payload.into());
     }
+
+    /// Try to extract the given payload type from the presence's payloads.
+    ///
+    /// Behaves like [`Message::extract_payload`](crate::message::Message::extract_payload).
+    #[allow(clippy::result_large_err)]
+    pub fn extract_payload<T: TryFrom<Element, Error = Error>>(
+        &mut self,
+    ) -> Result<Option<T>, Error> {
+        extract_payload(&mut self.payloads)
+    }
+
+    /// Parse the first payload of the given type, leaving the presence
+    /// untouched.
+    ///
+    /// Returns `None` if no payload matches, or the result of parsing the
+    /// first matching one.
+    pub fn get_payload<T: TryFrom<Element, Error = Error>>(&self) -> Option<Result<T, Error>> {
+        get_payload(&self.payloads)
+    }
 }
 
 impl TryFrom<Element> for Presence {
@@ -285,6 +321,23 @@ impl TryFrom<Element> for Presence {
 
     fn try_from(root: Element) -> Result<Presence, Error> {
         check_self!(root, "presence", DEFAULT_NS);
+        Presence::try_from(&root)
+    }
+}
+
+/// Parses a presence without taking the element, e.g. to keep it around when
+/// it is invalid.
+impl TryFrom<&Element> for Presence {
+    type Error = Error;
+
+    fn try_from(root: &Element) -> Result<Presence, Error> {
+        if !root.is("presence", ns::DEFAULT_NS) {
+            return Err(Error::TypeMismatch(
+                "presence",
+                ns::DEFAULT_NS,
+                root.clone(),
+            ));
+        }
         let mut show = None;
         let mut priority = None;
         let mut presence = Presence {
@@ -684,4 +737,13 @@ mod tests {
         let elem: Element = presence.into();
         assert_eq!(elem.attr("to"), Some("test@localhost/coucou"));
     }
+
+    #[test]
+    fn test_builders() {
+        let presence = Presence::available()
+            .with_auto_id()
+            .with_status("en", "Here");
+        assert!(presence.id.is_some());
+        assert_eq!(presence.statuses["en"], "Here");
+    }
 }
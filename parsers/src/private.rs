@@ -8,9 +8,10 @@
 //! This module implements [Private XML Storage](https://xmpp.org/extensions/xep-0049.html) from
 //! XEP-0049.
 //!
-//! However, only legacy bookmarks storage from [XEP-0048
-//! v1.0](https://xmpp.org/extensions/attic/xep-0048-1.0.html) is supported at the moment.
-//! This should only be used when `urn:xmpp:bookmarks:1#compat` is not advertised on the user's
+//! Legacy bookmarks storage from [XEP-0048
+//! v1.0](https://xmpp.org/extensions/attic/xep-0048-1.0.html) has its own [`Query`], any other
+//! namespaced element can be stored and retrieved with [`Data`].
+//! Bookmarks should only be stored this way when `urn:xmpp:bookmarks:1#compat` is not advertised on the user's
 //! BareJID in a disco info request.
 //!
 //! See [ModernXMPP docs](https://docs.modernxmpp.org/client/groupchat/#bookmarks) on how to handle
@@ -22,6 +23,9 @@
 use crate::{
     bookmarks::Storage,
     iq::{IqGetPayload, IqResultPayload, IqSetPayload},
+    ns,
+    util::error::Error,
+    Element,
 };
 
 generate_element!(
@@ -37,3 +41,109 @@ generate_element!(
 impl IqSetPayload for Query {}
 impl IqGetPayload for Query {}
 impl IqResultPayload for Query {}
+
+/// A Private XML Storage query for any namespaced element, e.g. small
+/// client settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Data {
+    /// The stored element, which is empty in a get query, only telling
+    /// which name and namespace to retrieve.
+    pub payload: Element,
+}
+
+impl Data {
+    /// Store this element.
+    pub fn new<P: Into<Element>>(payload: P) -> Data {
+        Data {
+            payload: payload.into(),
+        }
+    }
+
+    /// Retrieve the element of this name and namespace.
+    pub fn request(name: &str, namespace: &str) -> Data {
+        Data {
+            payload: Element::builder(name, namespace).build(),
+        }
+    }
+}
+
+impl TryFrom<Element> for Data {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<Data, Error> {
+        check_self!(elem, "query", PRIVATE);
+        check_no_attributes!(elem, "query");
+        let mut children = elem.children();
+        let (Some(payload), None) = (children.next(), children.next()) else {
+            return Err(Error::ParseError(
+                "Private XML storage query must have exactly one child.",
+            ));
+        };
+        // The reserved namespaces of XEP-0049 §3.
+        if payload.ns() == ns::DEFAULT_NS || payload.ns() == ns::PRIVATE {
+            return Err(Error::ParseError(
+                "Invalid namespace for private XML storage.",
+            ));
+        }
+        Ok(Data {
+            payload: payload.clone(),
+        })
+    }
+}
+
+impl From<Data> for Element {
+    fn from(data: Data) -> Element {
+        Element::builder("query", ns::PRIVATE)
+            .append(data.payload)
+            .build()
+    }
+}
+
+impl IqSetPayload for Data {}
+impl IqGetPayload for Data {}
+impl IqResultPayload for Data {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data() {
+        let elem: Element = "<query xmlns='jabber:iq:private'><exodus xmlns='exodus:prefs'><defaultnick>Hamlet</defaultnick></exodus></query>"
+            .parse()
+            .unwrap();
+        let data = Data::try_from(elem.clone()).unwrap();
+        assert!(data.payload.is("exodus", "exodus:prefs"));
+        assert_eq!(Element::from(data), elem);
+
+        let request = Element::from(Data::request("exodus", "exodus:prefs"));
+        let data = Data::try_from(request).unwrap();
+        assert_eq!(data.payload.children().count(), 0);
+    }
+
+    #[cfg(not(feature = "disable-validation"))]
+    #[test]
+    fn test_invalid() {
+        let elem: Element = "<query xmlns='jabber:iq:private'/>".parse().unwrap();
+        let error = Data::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(
+            message,
+            "Private XML storage query must have exactly one child."
+        );
+
+        let elem: Element =
+            "<query xmlns='jabber:iq:private'><query xmlns='jabber:iq:private'/></query>"
+                .parse()
+                .unwrap();
+        let error = Data::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Invalid namespace for private XML storage.");
+    }
+}
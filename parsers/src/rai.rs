@@ -0,0 +1,89 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::message::MessagePayload;
+use crate::presence::PresencePayload;
+use jid::BareJid;
+
+generate_elem_id!(
+    /// A room in which there has been activity since the last notification.
+    Activity,
+    "activity",
+    RAI,
+    BareJid
+);
+
+generate_element!(
+    /// Sent empty in a presence to a MUC service to subscribe to the
+    /// activity of the rooms of the user, and by the service in a message
+    /// listing the rooms with new activity.
+    #[derive(Default)]
+    Rai, "rai", RAI,
+    children: [
+        /// The rooms with new activity.
+        activities: Vec<Activity> = ("activity", RAI) => Activity
+    ]
+);
+
+impl Rai {
+    /// Create the subscription payload, without any activity.
+    pub fn new() -> Rai {
+        Rai::default()
+    }
+}
+
+impl PresencePayload for Rai {}
+impl MessagePayload for Rai {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Activity, 16);
+        assert_size!(Rai, 12);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Activity, 32);
+        assert_size!(Rai, 24);
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let elem: Element = "<rai xmlns='urn:xmpp:rai:0'/>".parse().unwrap();
+        let rai = Rai::try_from(elem.clone()).unwrap();
+        assert!(rai.activities.is_empty());
+        assert_eq!(Element::from(Rai::new()), elem);
+    }
+
+    #[test]
+    fn test_activity() {
+        let elem: Element = "<message xmlns='jabber:client' from='muc.example.org'>
+              <rai xmlns='urn:xmpp:rai:0'>
+                <activity>room1@muc.example.org</activity>
+                <activity>room2@muc.example.org</activity>
+              </rai>
+            </message>"
+            .parse()
+            .unwrap();
+        let message = Message::try_from(elem).unwrap();
+        let rai = message.get_payload::<Rai>().unwrap().unwrap();
+        assert_eq!(
+            rai.activities,
+            [
+                Activity(BareJid::new("room1@muc.example.org").unwrap()),
+                Activity(BareJid::new("room2@muc.example.org").unwrap()),
+            ]
+        );
+    }
+}
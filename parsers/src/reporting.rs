@@ -0,0 +1,165 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::ns;
+use crate::stanza_id::StanzaId;
+use crate::util::error::Error;
+use crate::Element;
+use std::collections::BTreeMap;
+
+generate_attribute!(
+    /// Why an entity gets reported.
+    Reason, "reason", {
+        /// Unsolicited messages, e.g. advertisement.
+        Spam => "urn:xmpp:reporting:spam",
+
+        /// Harassment or otherwise abusive messages.
+        Abuse => "urn:xmpp:reporting:abuse",
+    }
+);
+
+type Lang = String;
+
+/// A report about an entity, attached to an item of a
+/// [Block](crate::blocking::Block) command so that the server can act on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Why this entity gets reported.
+    pub reason: Reason,
+
+    /// The ids of the offending messages, as stamped by the server.
+    pub stanza_ids: Vec<StanzaId>,
+
+    /// Explanations for the server operator, per language.
+    pub texts: BTreeMap<Lang, String>,
+}
+
+impl Report {
+    /// Create a new report for this reason.
+    pub fn new(reason: Reason) -> Report {
+        Report {
+            reason,
+            stanza_ids: Vec::new(),
+            texts: BTreeMap::new(),
+        }
+    }
+
+    /// Point at one of the offending messages.
+    pub fn with_stanza_id(mut self, stanza_id: StanzaId) -> Report {
+        self.stanza_ids.push(stanza_id);
+        self
+    }
+
+    /// Explain this report, in this language.
+    pub fn with_text<L: Into<Lang>, T: Into<String>>(mut self, lang: L, text: T) -> Report {
+        self.texts.insert(lang.into(), text.into());
+        self
+    }
+}
+
+impl TryFrom<Element> for Report {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<Report, Error> {
+        check_self!(elem, "report", REPORTING);
+        check_no_unknown_attributes!(elem, "report", ["reason"]);
+        let mut report = Report::new(get_attr!(elem, "reason", Required));
+        for child in elem.children() {
+            if child.is("text", ns::REPORTING) {
+                check_no_children!(child, "text");
+                check_no_unknown_attributes!(child, "text", ["xml:lang"]);
+                let lang = get_attr!(child, "xml:lang", Default);
+                if report.texts.insert(lang, child.text()).is_some() {
+                    return Err(Error::ParseError(
+                        "Text element present twice for the same xml:lang.",
+                    ));
+                }
+            } else if child.is("stanza-id", ns::SID) {
+                report.stanza_ids.push(StanzaId::try_from(child.clone())?);
+            } else {
+                return Err(Error::ParseError("Unknown child in report element."));
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl From<Report> for Element {
+    fn from(report: Report) -> Element {
+        Element::builder("report", ns::REPORTING)
+            .attr("reason", report.reason)
+            .append_all(report.stanza_ids)
+            .append_all(report.texts.into_iter().map(|(lang, text)| {
+                Element::builder("text", ns::REPORTING)
+                    .attr("xml:lang", lang)
+                    .append(text)
+            }))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jid::Jid;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Reason, 1);
+        assert_size!(Report, 28);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Reason, 1);
+        assert_size!(Report, 56);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element =
+            "<report xmlns='urn:xmpp:reporting:1' reason='urn:xmpp:reporting:abuse'>
+              <stanza-id xmlns='urn:xmpp:sid:0' by='romeo@example.net' id='28482-98726-73623'/>
+              <text xml:lang='en'>Never again</text>
+            </report>"
+                .parse()
+                .unwrap();
+        let report = Report::try_from(elem).unwrap();
+        assert_eq!(report.reason, Reason::Abuse);
+        assert_eq!(report.stanza_ids[0].id, "28482-98726-73623");
+        assert_eq!(report.texts["en"], "Never again");
+    }
+
+    #[test]
+    fn test_serialise() {
+        let report = Report::new(Reason::Spam)
+            .with_stanza_id(StanzaId {
+                id: String::from("28482-98726-73623"),
+                by: Jid::new("romeo@example.net").unwrap(),
+            })
+            .with_text("en", "Buy cheap potions");
+        let elem = Element::from(report.clone());
+        assert_eq!(elem.attr("reason"), Some("urn:xmpp:reporting:spam"));
+        assert_eq!(Report::try_from(elem).unwrap(), report);
+    }
+
+    #[cfg(not(feature = "disable-validation"))]
+    #[test]
+    fn test_invalid_reason() {
+        let elem: Element =
+            "<report xmlns='urn:xmpp:reporting:1' reason='urn:xmpp:reporting:boring'/>"
+                .parse()
+                .unwrap();
+        let error = Report::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Unknown value for 'reason' attribute.");
+    }
+}
@@ -36,6 +36,51 @@ generate_attribute!(
     }, Default = None
 );
 
+impl Subscription {
+    /// Whether you receive the presence of this contact.
+    pub fn has_to(&self) -> bool {
+        matches!(self, Subscription::To | Subscription::Both)
+    }
+
+    /// Whether this contact receives your presence.
+    pub fn has_from(&self) -> bool {
+        matches!(self, Subscription::From | Subscription::Both)
+    }
+
+    /// The state after this contact approved (`true`) or cancelled (`false`)
+    /// your subscription to their presence, as per
+    /// [RFC 6121 §3](https://xmpp.org/rfcs/rfc6121.html#sub).
+    ///
+    /// [Subscription::Remove] isn’t a state, it is returned unchanged.
+    pub fn with_to(self, subscribed: bool) -> Subscription {
+        match self {
+            Subscription::Remove => self,
+            _ => Subscription::from_flags(subscribed, self.has_from()),
+        }
+    }
+
+    /// The state after you approved (`true`) or cancelled (`false`) the
+    /// subscription of this contact to your presence, as per
+    /// [RFC 6121 §3](https://xmpp.org/rfcs/rfc6121.html#sub).
+    ///
+    /// [Subscription::Remove] isn’t a state, it is returned unchanged.
+    pub fn with_from(self, subscribed: bool) -> Subscription {
+        match self {
+            Subscription::Remove => self,
+            _ => Subscription::from_flags(self.has_to(), subscribed),
+        }
+    }
+
+    fn from_flags(to: bool, from: bool) -> Subscription {
+        match (to, from) {
+            (false, false) => Subscription::None,
+            (true, false) => Subscription::To,
+            (false, true) => Subscription::From,
+            (true, true) => Subscription::Both,
+        }
+    }
+}
+
 generate_attribute!(
     /// The sub-state of subscription with a contact.
     Ask, "ask", (
@@ -84,6 +129,28 @@ generate_element!(
     ]
 );
 
+impl Roster {
+    /// Applies a roster push to this cached roster, as per
+    /// [RFC 6121 §2.1.6](https://xmpp.org/rfcs/rfc6121.html#roster-syntax-actions-push):
+    /// an item with the [Subscription::Remove] subscription removes the
+    /// contact, any other one adds or replaces it.
+    ///
+    /// Returns the previous item of this contact, if any.  The version of the
+    /// push, if any, has to be copied to `ver` separately.
+    pub fn apply_push(&mut self, item: Item) -> Option<Item> {
+        let index = self.items.iter().position(|known| known.jid == item.jid);
+        match (index, &item.subscription) {
+            (Some(index), Subscription::Remove) => Some(self.items.remove(index)),
+            (None, Subscription::Remove) => None,
+            (Some(index), _) => Some(std::mem::replace(&mut self.items[index], item)),
+            (None, _) => {
+                self.items.push(item);
+                None
+            }
+        }
+    }
+}
+
 impl IqGetPayload for Roster {}
 impl IqSetPayload for Roster {}
 impl IqResultPayload for Roster {}
@@ -262,6 +329,59 @@ mod tests {
         assert_eq!(roster.items[0].subscription, Subscription::Remove);
     }
 
+    #[test]
+    fn test_apply_push() {
+        let elem: Element = "<query xmlns='jabber:iq:roster' ver='ver7'><item jid='nurse@example.com'/><item jid='romeo@example.net' subscription='to'/></query>".parse().unwrap();
+        let mut roster = Roster::try_from(elem).unwrap();
+
+        let push = |xml: &str| {
+            let elem: Element = xml.parse().unwrap();
+            Roster::try_from(elem).unwrap().items.remove(0)
+        };
+        let previous = roster.apply_push(push(
+            "<query xmlns='jabber:iq:roster'><item jid='romeo@example.net' subscription='both' name='Romeo'/></query>",
+        ));
+        assert_eq!(previous.unwrap().subscription, Subscription::To);
+        assert_eq!(roster.items.len(), 2);
+        assert_eq!(roster.items[1].subscription, Subscription::Both);
+        assert_eq!(roster.items[1].name, Some(String::from("Romeo")));
+
+        let previous = roster.apply_push(push(
+            "<query xmlns='jabber:iq:roster'><item jid='benvolio@example.net' ask='subscribe'/></query>",
+        ));
+        assert!(previous.is_none());
+        assert_eq!(roster.items.len(), 3);
+        assert_eq!(roster.items[2].ask, Ask::Subscribe);
+
+        let previous = roster.apply_push(push(
+            "<query xmlns='jabber:iq:roster'><item jid='nurse@example.com' subscription='remove'/></query>",
+        ));
+        assert_eq!(
+            previous.unwrap().jid,
+            BareJid::new("nurse@example.com").unwrap()
+        );
+        assert_eq!(roster.items.len(), 2);
+        let previous = roster.apply_push(push(
+            "<query xmlns='jabber:iq:roster'><item jid='nurse@example.com' subscription='remove'/></query>",
+        ));
+        assert!(previous.is_none());
+        assert_eq!(roster.items.len(), 2);
+    }
+
+    #[test]
+    fn test_subscription_transitions() {
+        let subscription = Subscription::None.with_to(true);
+        assert_eq!(subscription, Subscription::To);
+        assert!(subscription.has_to());
+        assert!(!subscription.has_from());
+        assert_eq!(subscription.with_from(true), Subscription::Both);
+        assert_eq!(Subscription::Both.with_to(false), Subscription::From);
+        assert_eq!(Subscription::From.with_from(false), Subscription::None);
+        assert_eq!(Subscription::To.with_to(true), Subscription::To);
+        assert_eq!(Subscription::Remove.with_to(true), Subscription::Remove);
+        assert!(!Subscription::Remove.has_from());
+    }
+
     #[cfg(not(feature = "disable-validation"))]
     #[test]
     fn test_invalid() {
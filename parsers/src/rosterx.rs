@@ -0,0 +1,152 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::iq::IqSetPayload;
+use crate::message::MessagePayload;
+use jid::BareJid;
+
+generate_attribute!(
+    /// What the sender suggests doing with this contact.
+    Action, "action", {
+        /// Add this contact to the roster.
+        Add => "add",
+
+        /// Remove this contact from the roster.
+        Delete => "delete",
+
+        /// Change the name or groups of this contact.
+        Modify => "modify",
+    }, Default = Add
+);
+
+generate_elem_id!(
+    /// A group the suggested contact should be part of.
+    Group,
+    "group",
+    ROSTERX
+);
+
+generate_element!(
+    /// A contact suggested to be added, modified or deleted.
+    Item, "item", ROSTERX,
+    attributes: [
+        /// What to do with this contact.
+        action: Default<Action> = "action",
+
+        /// JID of this contact.
+        jid: Required<BareJid> = "jid",
+
+        /// Name suggested for this contact.
+        name: Option<String> = "name",
+    ],
+    children: [
+        /// Groups suggested for this contact.
+        groups: Vec<Group> = ("group", ROSTERX) => Group
+    ]
+);
+
+impl Item {
+    /// Suggest an action on this contact, without any name or group.
+    pub fn new(action: Action, jid: BareJid) -> Item {
+        Item {
+            action,
+            jid,
+            name: None,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Suggest a name for this contact.
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Item {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Suggest a group for this contact.
+    pub fn with_group<S: Into<String>>(mut self, group: S) -> Item {
+        self.groups.push(Group(group.into()));
+        self
+    }
+}
+
+generate_element!(
+    /// Contacts suggested by another entity, e.g. a gateway suggesting the
+    /// contacts of the user on a legacy network.
+    Exchange, "x", ROSTERX,
+    children: [
+        /// The suggested contacts.
+        items: Vec<Item> = ("item", ROSTERX) => Item
+    ]
+);
+
+impl MessagePayload for Exchange {}
+impl IqSetPayload for Exchange {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Action, 1);
+        assert_size!(Group, 12);
+        assert_size!(Item, 44);
+        assert_size!(Exchange, 12);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Action, 1);
+        assert_size!(Group, 24);
+        assert_size!(Item, 88);
+        assert_size!(Exchange, 24);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<x xmlns='http://jabber.org/protocol/rosterx'>
+              <item action='add' jid='rosencrantz@denmark.lit' name='Rosencrantz'><group>Visitors</group></item>
+              <item action='delete' jid='guildenstern@denmark.lit'/>
+              <item jid='polonius@denmark.lit'/>
+            </x>"
+            .parse()
+            .unwrap();
+        let exchange = Exchange::try_from(elem).unwrap();
+        assert_eq!(exchange.items.len(), 3);
+        assert_eq!(
+            exchange.items[0],
+            Item::new(
+                Action::Add,
+                BareJid::new("rosencrantz@denmark.lit").unwrap()
+            )
+            .with_name("Rosencrantz")
+            .with_group("Visitors")
+        );
+        assert_eq!(exchange.items[1].action, Action::Delete);
+        assert_eq!(exchange.items[2].action, Action::Add);
+
+        let elem = Element::from(exchange.clone());
+        assert_eq!(Exchange::try_from(elem).unwrap(), exchange);
+    }
+
+    #[cfg(not(feature = "disable-validation"))]
+    #[test]
+    fn test_invalid_action() {
+        let elem: Element = "<x xmlns='http://jabber.org/protocol/rosterx'><item action='rename' jid='polonius@denmark.lit'/></x>"
+            .parse()
+            .unwrap();
+        let error = Exchange::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Unknown value for 'action' attribute.");
+    }
+}
@@ -8,39 +8,81 @@ use crate::ns;
 use crate::util::error::Error;
 use crate::util::text_node_codecs::{Base64, Codec};
 use crate::Element;
+use minidom::IntoAttributeValue;
 use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// The list of available SASL mechanisms.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mechanism {
+    /// Uses no hashing mechanism and transmit the password in clear to the
+    /// server, using a single step.
+    Plain,
+
+    /// Challenge-based mechanism using HMAC and SHA-1, allows both the
+    /// client and the server to avoid having to store the password in
+    /// clear.
+    ///
+    /// See <https://www.rfc-editor.org/rfc/rfc5802>
+    ScramSha1,
+
+    /// Same as [ScramSha1](#structfield.ScramSha1), with the addition of
+    /// channel binding.
+    ScramSha1Plus,
+
+    /// Same as [ScramSha1](#structfield.ScramSha1), but using SHA-256
+    /// instead of SHA-1 as the hash function.
+    ScramSha256,
+
+    /// Same as [ScramSha256](#structfield.ScramSha256), with the addition
+    /// of channel binding.
+    ScramSha256Plus,
+
+    /// Creates a temporary JID on login, which will be destroyed on
+    /// disconnect.
+    Anonymous,
+
+    /// A mechanism not in this list, e.g. one provided by the application.
+    Unknown(String),
+}
+
+impl FromStr for Mechanism {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Mechanism, Error> {
+        Ok(match s {
+            "" => return Err(Error::ParseError("'mechanism' attribute can’t be empty.")),
 
-generate_attribute!(
-    /// The list of available SASL mechanisms.
-    Mechanism, "mechanism", {
-        /// Uses no hashing mechanism and transmit the password in clear to the
-        /// server, using a single step.
-        Plain => "PLAIN",
-
-        /// Challenge-based mechanism using HMAC and SHA-1, allows both the
-        /// client and the server to avoid having to store the password in
-        /// clear.
-        ///
-        /// See <https://www.rfc-editor.org/rfc/rfc5802>
-        ScramSha1 => "SCRAM-SHA-1",
-
-        /// Same as [ScramSha1](#structfield.ScramSha1), with the addition of
-        /// channel binding.
-        ScramSha1Plus => "SCRAM-SHA-1-PLUS",
-
-        /// Same as [ScramSha1](#structfield.ScramSha1), but using SHA-256
-        /// instead of SHA-1 as the hash function.
-        ScramSha256 => "SCRAM-SHA-256",
-
-        /// Same as [ScramSha256](#structfield.ScramSha256), with the addition
-        /// of channel binding.
-        ScramSha256Plus => "SCRAM-SHA-256-PLUS",
-
-        /// Creates a temporary JID on login, which will be destroyed on
-        /// disconnect.
-        Anonymous => "ANONYMOUS",
+            "PLAIN" => Mechanism::Plain,
+            "SCRAM-SHA-1" => Mechanism::ScramSha1,
+            "SCRAM-SHA-1-PLUS" => Mechanism::ScramSha1Plus,
+            "SCRAM-SHA-256" => Mechanism::ScramSha256,
+            "SCRAM-SHA-256-PLUS" => Mechanism::ScramSha256Plus,
+            "ANONYMOUS" => Mechanism::Anonymous,
+            value => Mechanism::Unknown(value.to_owned()),
+        })
     }
-);
+}
+
+impl From<Mechanism> for String {
+    fn from(mechanism: Mechanism) -> String {
+        String::from(match mechanism {
+            Mechanism::Plain => "PLAIN",
+            Mechanism::ScramSha1 => "SCRAM-SHA-1",
+            Mechanism::ScramSha1Plus => "SCRAM-SHA-1-PLUS",
+            Mechanism::ScramSha256 => "SCRAM-SHA-256",
+            Mechanism::ScramSha256Plus => "SCRAM-SHA-256-PLUS",
+            Mechanism::Anonymous => "ANONYMOUS",
+            Mechanism::Unknown(name) => return name,
+        })
+    }
+}
+
+impl IntoAttributeValue for Mechanism {
+    fn into_attribute_value(self) -> Option<String> {
+        Some(String::from(self))
+    }
+}
 
 generate_element!(
     /// The first step of the SASL process, selecting the mechanism and sending
@@ -217,8 +259,8 @@ mod tests {
     #[cfg(target_pointer_width = "32")]
     #[test]
     fn test_size() {
-        assert_size!(Mechanism, 1);
-        assert_size!(Auth, 16);
+        assert_size!(Mechanism, 12);
+        assert_size!(Auth, 24);
         assert_size!(Challenge, 12);
         assert_size!(Response, 12);
         assert_size!(Abort, 0);
@@ -230,8 +272,8 @@ mod tests {
     #[cfg(target_pointer_width = "64")]
     #[test]
     fn test_size() {
-        assert_size!(Mechanism, 1);
-        assert_size!(Auth, 32);
+        assert_size!(Mechanism, 24);
+        assert_size!(Auth, 48);
         assert_size!(Challenge, 24);
         assert_size!(Response, 24);
         assert_size!(Abort, 0);
@@ -248,6 +290,14 @@ mod tests {
         let auth = Auth::try_from(elem).unwrap();
         assert_eq!(auth.mechanism, Mechanism::Plain);
         assert!(auth.data.is_empty());
+
+        let elem: Element = "<auth xmlns='urn:ietf:params:xml:ns:xmpp-sasl' mechanism='X-OAUTH2'/>"
+            .parse()
+            .unwrap();
+        let auth = Auth::try_from(elem).unwrap();
+        assert_eq!(auth.mechanism, Mechanism::Unknown(String::from("X-OAUTH2")));
+        let elem = Element::from(auth);
+        assert_eq!(elem.attr("mechanism"), Some("X-OAUTH2"));
     }
 
     #[test]
@@ -0,0 +1,242 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::date::DateTime;
+use crate::ns;
+use crate::util::error::Error;
+use crate::Element;
+use jid::{BareJid, Jid};
+
+/// The payloads protected by an envelope, e.g. a `<body/>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Content {
+    /// The elements to put back into the stanza once decrypted.
+    pub payloads: Vec<Element>,
+}
+
+impl TryFrom<Element> for Content {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<Content, Error> {
+        check_self!(elem, "content", SCE);
+        check_no_attributes!(elem, "content");
+        Ok(Content {
+            payloads: elem.children().cloned().collect(),
+        })
+    }
+}
+
+impl From<Content> for Element {
+    fn from(content: Content) -> Element {
+        Element::builder("content", ns::SCE)
+            .append_all(content.payloads)
+            .build()
+    }
+}
+
+generate_elem_id!(
+    /// Random padding, hiding the length of the content.
+    Rpad,
+    "rpad",
+    SCE
+);
+
+generate_element!(
+    /// Affix noting when the envelope was created, to detect replays.
+    Time, "time", SCE,
+    attributes: [
+        /// The time at which the envelope was created.
+        stamp: Required<DateTime> = "stamp",
+    ]
+);
+
+generate_element!(
+    /// Affix noting the intended recipient, to prevent the envelope from being
+    /// sent again to someone else.
+    To, "to", SCE,
+    attributes: [
+        /// The bare JID of the recipient, or of the room.
+        jid: Required<Jid> = "jid",
+    ]
+);
+
+generate_element!(
+    /// Affix noting the sender, to prevent the envelope from being sent again
+    /// by someone else.
+    From_, "from", SCE,
+    attributes: [
+        /// The JID of the sender.
+        jid: Required<Jid> = "jid",
+    ]
+);
+
+generate_element!(
+    /// The plaintext an encryption scheme encrypts, containing the payloads of
+    /// the stanza and the affixes its profile requires.
+    Envelope, "envelope", SCE,
+    children: [
+        /// The protected payloads.
+        content: Required<Content> = ("content", SCE) => Content,
+
+        /// Random padding.
+        rpad: Option<Rpad> = ("rpad", SCE) => Rpad,
+
+        /// When the envelope was created.
+        time: Option<Time> = ("time", SCE) => Time,
+
+        /// The intended recipient.
+        to: Option<To> = ("to", SCE) => To,
+
+        /// The sender.
+        from: Option<From_> = ("from", SCE) => From_
+    ]
+);
+
+impl Envelope {
+    /// Wraps these payloads, without any affix.
+    pub fn new(payloads: Vec<Element>) -> Envelope {
+        Envelope {
+            content: Content { payloads },
+            rpad: None,
+            time: None,
+            to: None,
+            from: None,
+        }
+    }
+
+    /// Adds random padding, generated by the caller.
+    pub fn with_rpad(mut self, rpad: String) -> Envelope {
+        self.rpad = Some(Rpad(rpad));
+        self
+    }
+
+    /// Sets the creation time of the envelope.
+    pub fn with_time(mut self, stamp: DateTime) -> Envelope {
+        self.time = Some(Time { stamp });
+        self
+    }
+
+    /// Sets the intended recipient, the bare JID of a contact or a room.
+    pub fn with_to(mut self, jid: BareJid) -> Envelope {
+        self.to = Some(To { jid: jid.into() });
+        self
+    }
+
+    /// Sets the sender.
+    pub fn with_from(mut self, jid: Jid) -> Envelope {
+        self.from = Some(From_ { jid });
+        self
+    }
+
+    /// Checks the `to` and `from` affixes against the stanza this envelope
+    /// was decrypted from: `recipient` is our own bare JID, or the room's for
+    /// groupchat messages, and `sender` is the bare JID of whoever sent it.
+    ///
+    /// Absent affixes aren't checked, the encryption profile decides which
+    /// ones are required.
+    pub fn affixes_match(&self, sender: &BareJid, recipient: &BareJid) -> bool {
+        let to_matches = self
+            .to
+            .as_ref()
+            .is_none_or(|to| &to.jid.to_bare() == recipient);
+        let from_matches = self
+            .from
+            .as_ref()
+            .is_none_or(|from| &from.jid.to_bare() == sender);
+        to_matches && from_matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Content, 12);
+        assert_size!(Rpad, 12);
+        assert_size!(Time, 16);
+        assert_size!(To, 12);
+        assert_size!(From_, 12);
+        assert_size!(Envelope, 64);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Content, 24);
+        assert_size!(Rpad, 24);
+        assert_size!(Time, 16);
+        assert_size!(To, 32);
+        assert_size!(From_, 32);
+        assert_size!(Envelope, 128);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<envelope xmlns='urn:xmpp:sce:1'>
+            <content>
+              <body xmlns='jabber:client'>Have you seen that new movie?</body>
+              <x xmlns='jabber:x:oob'>
+                <url>https://en.wikipedia.org/wiki/Fight_Club#Plot</url>
+              </x>
+            </content>
+            <rpad>随机填充</rpad>
+            <time stamp='2004-01-25T05:05:00.000+00:00'/>
+            <to jid='missioncontrol@houston.gov'/>
+            <from jid='opportunity@mars.planet'/>
+          </envelope>"
+            .parse()
+            .unwrap();
+        let envelope = Envelope::try_from(elem.clone()).unwrap();
+        assert_eq!(envelope.content.payloads.len(), 2);
+        assert!(envelope.content.payloads[0].is("body", ns::DEFAULT_NS));
+        assert_eq!(envelope.rpad, Some(Rpad(String::from("随机填充"))));
+        assert_eq!(
+            envelope.time.as_ref().unwrap().stamp,
+            "2004-01-25T05:05:00Z".parse().unwrap()
+        );
+        assert_eq!(
+            envelope.to.as_ref().unwrap().jid,
+            Jid::new("missioncontrol@houston.gov").unwrap()
+        );
+
+        let elem2 = Element::from(envelope.clone());
+        assert_eq!(Envelope::try_from(elem2).unwrap(), envelope);
+    }
+
+    #[test]
+    fn test_missing_content() {
+        let elem: Element = "<envelope xmlns='urn:xmpp:sce:1'><rpad>foo</rpad></envelope>"
+            .parse()
+            .unwrap();
+        let error = Envelope::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Missing child content in envelope element.");
+    }
+
+    #[test]
+    fn test_affixes_match() {
+        let juliet = BareJid::new("juliet@capulet.lit").unwrap();
+        let romeo = BareJid::new("romeo@montague.lit").unwrap();
+        let body: Element = "<body xmlns='jabber:client'>Hi</body>".parse().unwrap();
+
+        let envelope = Envelope::new(vec![body.clone()]);
+        assert!(envelope.affixes_match(&juliet, &romeo));
+
+        let envelope = envelope
+            .with_to(romeo.clone())
+            .with_from(Jid::new("juliet@capulet.lit/balcony").unwrap());
+        assert!(envelope.affixes_match(&juliet, &romeo));
+        assert!(!envelope.affixes_match(&romeo, &romeo));
+        assert!(!envelope.affixes_match(&juliet, &juliet));
+        assert_eq!(envelope.content.payloads, [body]);
+    }
+}
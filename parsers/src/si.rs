@@ -0,0 +1,260 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::feature_neg::FeatureNeg;
+use crate::iq::{IqResultPayload, IqSetPayload};
+use crate::si_ft::File;
+use crate::util::error::Error;
+use minidom::IntoAttributeValue;
+use std::str::FromStr;
+
+/// The field of the negotiation form choosing the stream method.
+const STREAM_METHOD: &str = "stream-method";
+
+/// The profile of a stream initiation, telling what the stream is for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Profile {
+    /// Sending a file, described by a [File].
+    FileTransfer,
+
+    /// A profile not in this list, which can be refused with a
+    /// bad-profile error.
+    Other(String),
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Profile, Error> {
+        Ok(match s {
+            "" => return Err(Error::ParseError("'profile' argument can’t be empty.")),
+            "http://jabber.org/protocol/si/profile/file-transfer" => Profile::FileTransfer,
+            value => Profile::Other(value.to_owned()),
+        })
+    }
+}
+
+impl From<Profile> for String {
+    fn from(profile: Profile) -> String {
+        String::from(match profile {
+            Profile::FileTransfer => "http://jabber.org/protocol/si/profile/file-transfer",
+            Profile::Other(text) => return text,
+        })
+    }
+}
+
+impl IntoAttributeValue for Profile {
+    fn into_attribute_value(self) -> Option<String> {
+        Some(String::from(self))
+    }
+}
+
+generate_attribute!(
+    /// A bytestream method which can carry the stream.
+    StreamMethod, "stream-method", {
+        /// SOCKS5 Bytestreams (XEP-0065).
+        Bytestreams => "http://jabber.org/protocol/bytestreams",
+
+        /// In-Band Bytestreams (XEP-0047).
+        Ibb => "http://jabber.org/protocol/ibb",
+    }
+);
+
+generate_element!(
+    /// Offers a stream to another entity, in an iq set, or accepts it, in the
+    /// result, along with the stream method chosen.
+    StreamInitiation, "si", SI,
+    attributes: [
+        /// The session id, which the bytestream will reuse.
+        id: Option<String> = "id",
+
+        /// The MIME type of the data, if known.
+        mime_type: Option<String> = "mime-type",
+
+        /// The profile of this stream.
+        profile: Option<Profile> = "profile",
+    ],
+    children: [
+        /// The file to transfer, with the file transfer profile.
+        file: Option<File> = ("file", SI_FILE_TRANSFER) => File,
+
+        /// The stream methods offered, or the one chosen.
+        feature: Option<FeatureNeg> = ("feature", FEATURE_NEG) => FeatureNeg
+    ]
+);
+
+impl IqSetPayload for StreamInitiation {}
+impl IqResultPayload for StreamInitiation {}
+
+impl StreamInitiation {
+    /// Offer to send this file, over any of these stream methods in order of
+    /// preference.
+    pub fn offer_file(id: String, file: File, methods: &[StreamMethod]) -> StreamInitiation {
+        let methods: Vec<String> = methods.iter().map(|method| method.to_string()).collect();
+        StreamInitiation {
+            id: Some(id),
+            mime_type: None,
+            profile: Some(Profile::FileTransfer),
+            file: Some(file),
+            feature: Some(FeatureNeg::offer(STREAM_METHOD, &methods)),
+        }
+    }
+
+    /// Accept an offer, with this stream method.
+    pub fn accept(method: StreamMethod) -> StreamInitiation {
+        StreamInitiation {
+            id: None,
+            mime_type: None,
+            profile: None,
+            file: None,
+            feature: Some(FeatureNeg::choose(STREAM_METHOD, &method.to_string())),
+        }
+    }
+
+    /// The stream methods offered and known to us, in order of preference.
+    pub fn offered_methods(&self) -> Vec<StreamMethod> {
+        let Some(ref feature) = self.feature else {
+            return vec![];
+        };
+        feature
+            .options(STREAM_METHOD)
+            .into_iter()
+            .filter_map(|method| method.parse().ok())
+            .collect()
+    }
+
+    /// The first offered stream method among those we support, to accept it with.
+    pub fn choose_method(&self, supported: &[StreamMethod]) -> Option<StreamMethod> {
+        self.offered_methods()
+            .into_iter()
+            .find(|method| supported.contains(method))
+    }
+
+    /// The stream method chosen by the receiver.
+    pub fn chosen_method(&self) -> Option<StreamMethod> {
+        self.feature.as_ref()?.chosen(STREAM_METHOD)?.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iq::Iq;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Profile, 12);
+        assert_size!(StreamMethod, 1);
+        assert_size!(StreamInitiation, 172);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Profile, 24);
+        assert_size!(StreamMethod, 1);
+        assert_size!(StreamInitiation, 304);
+    }
+
+    #[test]
+    fn test_offer() {
+        let elem: Element =
+            "<iq xmlns='jabber:client' type='set' id='offer1' to='receiver@jabber.org/resource'>
+              <si xmlns='http://jabber.org/protocol/si'
+                  id='a0'
+                  mime-type='text/plain'
+                  profile='http://jabber.org/protocol/si/profile/file-transfer'>
+                <file xmlns='http://jabber.org/protocol/si/profile/file-transfer'
+                      name='test.txt'
+                      size='1022'/>
+                <feature xmlns='http://jabber.org/protocol/feature-neg'>
+                  <x xmlns='jabber:x:data' type='form'>
+                    <field var='stream-method' type='list-single'>
+                      <option><value>http://jabber.org/protocol/bytestreams</value></option>
+                      <option><value>jabber:iq:oob</value></option>
+                      <option><value>http://jabber.org/protocol/ibb</value></option>
+                    </field>
+                  </x>
+                </feature>
+              </si>
+            </iq>"
+                .parse()
+                .unwrap();
+        let iq = Iq::try_from(elem).unwrap();
+        let payload = match iq.payload {
+            crate::iq::IqType::Set(payload) => payload,
+            _ => panic!(),
+        };
+        let si = StreamInitiation::try_from(payload).unwrap();
+        assert_eq!(si.id.as_deref(), Some("a0"));
+        assert_eq!(si.profile, Some(Profile::FileTransfer));
+        assert_eq!(si.file.as_ref().unwrap().size, 1022);
+        assert_eq!(
+            si.offered_methods(),
+            [StreamMethod::Bytestreams, StreamMethod::Ibb]
+        );
+        assert_eq!(
+            si.choose_method(&[StreamMethod::Ibb]),
+            Some(StreamMethod::Ibb)
+        );
+    }
+
+    #[test]
+    fn test_other_profile() {
+        let elem: Element = "<si xmlns='http://jabber.org/protocol/si'
+                id='a1'
+                profile='http://jabber.org/protocol/si/profile/tunnel'/>"
+            .parse()
+            .unwrap();
+        let si = StreamInitiation::try_from(elem).unwrap();
+        let profile = Profile::Other(String::from("http://jabber.org/protocol/si/profile/tunnel"));
+        assert_eq!(si.profile, Some(profile.clone()));
+
+        let elem = Element::from(si);
+        assert_eq!(
+            elem.attr("profile"),
+            Some("http://jabber.org/protocol/si/profile/tunnel")
+        );
+    }
+
+    #[test]
+    fn test_accept() {
+        let elem: Element = Element::from(StreamInitiation::accept(StreamMethod::Bytestreams));
+        let expected: Element = "<si xmlns='http://jabber.org/protocol/si'>
+              <feature xmlns='http://jabber.org/protocol/feature-neg'>
+                <x xmlns='jabber:x:data' type='submit'>
+                  <field var='stream-method' type='list-single'>
+                    <value>http://jabber.org/protocol/bytestreams</value>
+                  </field>
+                </x>
+              </feature>
+            </si>"
+            .parse()
+            .unwrap();
+        let si = StreamInitiation::try_from(expected).unwrap();
+        assert_eq!(si.chosen_method(), Some(StreamMethod::Bytestreams));
+        let si = StreamInitiation::try_from(elem).unwrap();
+        assert_eq!(si.chosen_method(), Some(StreamMethod::Bytestreams));
+    }
+
+    #[test]
+    fn test_offer_file() {
+        let file = File::new("test.txt", 1022);
+        let si = StreamInitiation::offer_file(
+            String::from("a0"),
+            file,
+            &[StreamMethod::Bytestreams, StreamMethod::Ibb],
+        );
+        let si = StreamInitiation::try_from(Element::from(si)).unwrap();
+        assert_eq!(
+            si.offered_methods(),
+            [StreamMethod::Bytestreams, StreamMethod::Ibb]
+        );
+        assert_eq!(si.file.unwrap().name, "test.txt");
+    }
+}
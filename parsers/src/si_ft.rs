@@ -0,0 +1,134 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::date::DateTime;
+
+generate_element!(
+    /// The range of a file to transfer, to resume an interrupted transfer.
+    #[derive(Default)]
+    Range, "range", SI_FILE_TRANSFER,
+    attributes: [
+        /// The position in bytes where to start the transfer, 0 if absent.
+        offset: Option<u64> = "offset",
+
+        /// The number of bytes to transfer, up to the end of the file if absent.
+        length: Option<u64> = "length",
+    ]
+);
+
+generate_element!(
+    /// Describes a file offered in a stream initiation.
+    File, "file", SI_FILE_TRANSFER,
+    attributes: [
+        /// The name of this file.
+        name: Required<String> = "name",
+
+        /// The size of this file, in bytes.
+        size: Required<u64> = "size",
+
+        /// The MD5 hash of this file, as a hexadecimal string.
+        hash: Option<String> = "hash",
+
+        /// The date of last modification of this file.
+        date: Option<DateTime> = "date",
+    ],
+    children: [
+        /// A human-readable description of this file.
+        desc: Option<String> = ("desc", SI_FILE_TRANSFER) => String,
+
+        /// Whether the sender supports ranged transfers, or the range the
+        /// receiver requests.
+        range: Option<Range> = ("range", SI_FILE_TRANSFER) => Range
+    ]
+);
+
+impl File {
+    /// Describe a file with this name and size.
+    pub fn new<N: Into<String>>(name: N, size: u64) -> File {
+        File {
+            name: name.into(),
+            size,
+            hash: None,
+            date: None,
+            desc: None,
+            range: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Element;
+    use std::str::FromStr;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(Range, 24);
+        assert_size!(File, 84);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(Range, 32);
+        assert_size!(File, 128);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<file xmlns='http://jabber.org/protocol/si/profile/file-transfer'
+                  name='test.txt'
+                  size='1022'
+                  hash='552da749930852c69ae5d2141d3766b1'
+                  date='1969-07-21T02:56:15Z'>
+              <desc>This is a test. If this were a real file...</desc>
+              <range/>
+            </file>"
+            .parse()
+            .unwrap();
+        let file = File::try_from(elem).unwrap();
+        assert_eq!(file.name, "test.txt");
+        assert_eq!(file.size, 1022);
+        assert_eq!(
+            file.hash.as_deref(),
+            Some("552da749930852c69ae5d2141d3766b1")
+        );
+        assert_eq!(
+            file.date,
+            Some(DateTime::from_str("1969-07-21T02:56:15Z").unwrap())
+        );
+        assert_eq!(
+            file.desc.as_deref(),
+            Some("This is a test. If this were a real file...")
+        );
+        assert_eq!(file.range, Some(Range::default()));
+    }
+
+    #[test]
+    fn test_range() {
+        let elem: Element = "<file xmlns='http://jabber.org/protocol/si/profile/file-transfer'
+                  name='test.txt'
+                  size='1022'>
+              <range offset='252' length='179'/>
+            </file>"
+            .parse()
+            .unwrap();
+        let file = File::try_from(elem).unwrap();
+        let range = file.range.unwrap();
+        assert_eq!(range.offset, Some(252));
+        assert_eq!(range.length, Some(179));
+    }
+
+    #[test]
+    fn test_serialise() {
+        let file = File::new("test.txt", 1022);
+        let elem = Element::from(file.clone());
+        assert_eq!(elem.attr("size"), Some("1022"));
+        assert_eq!(File::try_from(elem).unwrap(), file);
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::data_forms::{DataForm, DataFormType, Field};
+use crate::ns;
+use crate::util::error::Error;
+
+/// Structure representing a `urn:xmpp:dataforms:softwareinfo` form type,
+/// attached to the disco#info of an entity to advertise the software it runs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SoftwareInfo {
+    /// The operating system.
+    pub os: Option<String>,
+
+    /// The version of the operating system.
+    pub os_version: Option<String>,
+
+    /// The name of the software.
+    pub software: Option<String>,
+
+    /// The version of the software.
+    pub software_version: Option<String>,
+}
+
+impl SoftwareInfo {
+    /// Create a new software information form, for this software and version.
+    pub fn new<S: Into<String>, V: Into<String>>(software: S, version: V) -> SoftwareInfo {
+        SoftwareInfo {
+            os: None,
+            os_version: None,
+            software: Some(software.into()),
+            software_version: Some(version.into()),
+        }
+    }
+
+    /// Set the operating system and its version.
+    pub fn with_os<S: Into<String>, V: Into<String>>(mut self, os: S, version: V) -> SoftwareInfo {
+        self.os = Some(os.into());
+        self.os_version = Some(version.into());
+        self
+    }
+}
+
+impl TryFrom<DataForm> for SoftwareInfo {
+    type Error = Error;
+
+    fn try_from(form: DataForm) -> Result<SoftwareInfo, Error> {
+        if form.type_ != DataFormType::Result_ {
+            return Err(Error::ParseError("Wrong type of form."));
+        }
+        if form.form_type != Some(String::from(ns::SOFTWARE_INFO)) {
+            return Err(Error::ParseError("Wrong FORM_TYPE for form."));
+        }
+        let mut software_info = SoftwareInfo::default();
+        for mut field in form.fields {
+            let value = match field.var.as_str() {
+                "os" => &mut software_info.os,
+                "os_version" => &mut software_info.os_version,
+                "software" => &mut software_info.software,
+                "software_version" => &mut software_info.software_version,
+                // The icon and any field registered later are ignored.
+                _ => continue,
+            };
+            if field.values.len() > 1 {
+                return Err(Error::ParseError("Too many values in a field."));
+            }
+            *value = field.values.pop();
+        }
+
+        Ok(software_info)
+    }
+}
+
+impl From<SoftwareInfo> for DataForm {
+    fn from(software_info: SoftwareInfo) -> DataForm {
+        let fields = [
+            ("os", software_info.os),
+            ("os_version", software_info.os_version),
+            ("software", software_info.software),
+            ("software_version", software_info.software_version),
+        ]
+        .into_iter()
+        .filter_map(|(var, value)| value.map(|value| Field::text_single(var, &value)))
+        .collect();
+        DataForm::new(DataFormType::Result_, ns::SOFTWARE_INFO, fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(SoftwareInfo, 48);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(SoftwareInfo, 96);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<x xmlns='jabber:x:data' type='result'>
+              <field var='FORM_TYPE' type='hidden'><value>urn:xmpp:dataforms:softwareinfo</value></field>
+              <field var='ip_version' type='text-multi'><value>ipv4</value><value>ipv6</value></field>
+              <field var='os'><value>Mac</value></field>
+              <field var='os_version'><value>10.5.1</value></field>
+              <field var='software'><value>Psi</value></field>
+              <field var='software_version'><value>0.11</value></field>
+            </x>"
+            .parse()
+            .unwrap();
+        let form = DataForm::try_from(elem).unwrap();
+        let software_info = SoftwareInfo::try_from(form).unwrap();
+        assert_eq!(
+            software_info,
+            SoftwareInfo::new("Psi", "0.11").with_os("Mac", "10.5.1")
+        );
+    }
+
+    #[test]
+    fn test_serialise() {
+        let form = DataForm::from(SoftwareInfo::new("xmpp-rs", "0.5.0"));
+        assert_eq!(form.form_type.as_deref(), Some(ns::SOFTWARE_INFO));
+        let vars: Vec<_> = form.fields.iter().map(|field| field.var.as_str()).collect();
+        assert_eq!(vars, ["software", "software_version"]);
+
+        let software_info = SoftwareInfo::try_from(form).unwrap();
+        assert_eq!(software_info.software.as_deref(), Some("xmpp-rs"));
+        assert_eq!(software_info.os, None);
+    }
+
+    #[test]
+    fn test_wrong_form_type() {
+        let form = DataForm::new(DataFormType::Result_, ns::SERVER_INFO, vec![]);
+        let error = SoftwareInfo::try_from(form).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Wrong FORM_TYPE for form.");
+    }
+}
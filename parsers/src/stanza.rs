@@ -0,0 +1,174 @@
+// Copyright (c) 2024 xmpp-rs contributors
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::iq::Iq;
+use crate::message::Message;
+use crate::ns;
+use crate::presence::Presence;
+use crate::util::error::Error;
+use crate::Element;
+use minidom::Node;
+
+/// Any top-level element of a stream, with the three kinds of stanzas
+/// parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stanza {
+    /// A `<message/>` stanza.
+    Message(Message),
+
+    /// A `<presence/>` stanza.
+    Presence(Presence),
+
+    /// An `<iq/>` stanza.
+    Iq(Iq),
+
+    /// Any other element, such as stream management nonzas.
+    Nonza(Element),
+}
+
+impl TryFrom<Element> for Stanza {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<Stanza, Error> {
+        Ok(if elem.is("message", ns::DEFAULT_NS) {
+            Stanza::Message(Message::try_from(elem)?)
+        } else if elem.is("presence", ns::DEFAULT_NS) {
+            Stanza::Presence(Presence::try_from(elem)?)
+        } else if elem.is("iq", ns::DEFAULT_NS) {
+            Stanza::Iq(Iq::try_from(elem)?)
+        } else {
+            Stanza::Nonza(elem)
+        })
+    }
+}
+
+impl From<Stanza> for Element {
+    fn from(stanza: Stanza) -> Element {
+        match stanza {
+            Stanza::Message(message) => message.into(),
+            Stanza::Presence(presence) => presence.into(),
+            Stanza::Iq(iq) => iq.into(),
+            Stanza::Nonza(elem) => elem,
+        }
+    }
+}
+
+impl From<Message> for Stanza {
+    fn from(message: Message) -> Stanza {
+        Stanza::Message(message)
+    }
+}
+
+impl From<Presence> for Stanza {
+    fn from(presence: Presence) -> Stanza {
+        Stanza::Presence(presence)
+    }
+}
+
+impl From<Iq> for Stanza {
+    fn from(iq: Iq) -> Stanza {
+        Stanza::Iq(iq)
+    }
+}
+
+/// Moves an element to another namespace, along with its descendants in the
+/// same namespace such as a `<body/>`, e.g. a stanza of a component stream to
+/// the `jabber:client` namespace these parsers expect. Payloads keep their
+/// own namespaces.
+pub fn with_namespace(elem: Element, namespace: &str) -> Element {
+    let old = elem.ns();
+    if old == namespace {
+        return elem;
+    }
+    move_namespace(&elem, &old, namespace)
+}
+
+fn move_namespace(elem: &Element, old: &str, namespace: &str) -> Element {
+    let mut builder = Element::builder(elem.name(), namespace);
+    for (name, value) in elem.attrs() {
+        builder = builder.attr(name, value);
+    }
+    let nodes = elem.nodes().map(|node| match node {
+        Node::Element(child) if child.has_ns(old) => {
+            Node::Element(move_namespace(child, old, namespace))
+        }
+        node => node.clone(),
+    });
+    builder.append_all(nodes).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stanzas() {
+        #[cfg(not(feature = "component"))]
+        let elem: Element = "<message xmlns='jabber:client'/>".parse().unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<message xmlns='jabber:component:accept'/>"
+            .parse()
+            .unwrap();
+        let stanza = Stanza::try_from(elem).unwrap();
+        assert!(matches!(stanza, Stanza::Message(_)));
+
+        #[cfg(not(feature = "component"))]
+        let elem: Element = "<presence xmlns='jabber:client'/>".parse().unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<presence xmlns='jabber:component:accept'/>"
+            .parse()
+            .unwrap();
+        let stanza = Stanza::try_from(elem).unwrap();
+        assert!(matches!(stanza, Stanza::Presence(_)));
+
+        #[cfg(not(feature = "component"))]
+        let elem: Element =
+            "<iq xmlns='jabber:client' type='get' id='a'><ping xmlns='urn:xmpp:ping'/></iq>"
+                .parse()
+                .unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<iq xmlns='jabber:component:accept' type='get' id='a'><ping xmlns='urn:xmpp:ping'/></iq>"
+            .parse()
+            .unwrap();
+        let stanza = Stanza::try_from(elem.clone()).unwrap();
+        assert!(matches!(stanza, Stanza::Iq(_)));
+        assert_eq!(Element::from(stanza), elem);
+    }
+
+    #[test]
+    fn test_with_namespace() {
+        let elem: Element = "<message xmlns='jabber:component:accept' to='juliet@capulet.lit'><body>Hi</body><active xmlns='http://jabber.org/protocol/chatstates'/></message>"
+            .parse()
+            .unwrap();
+        let elem = with_namespace(elem, ns::JABBER_CLIENT);
+        assert!(elem.is("message", ns::JABBER_CLIENT));
+        assert_eq!(elem.attr("to"), Some("juliet@capulet.lit"));
+        assert!(elem.get_child("body", ns::JABBER_CLIENT).is_some());
+        assert!(elem.has_child("active", ns::CHATSTATES));
+
+        let back = with_namespace(elem, ns::COMPONENT_ACCEPT);
+        assert!(back.get_child("body", ns::COMPONENT_ACCEPT).is_some());
+        assert!(back.has_child("active", ns::CHATSTATES));
+    }
+
+    #[test]
+    fn test_nonza() {
+        let elem: Element = "<r xmlns='urn:xmpp:sm:3'/>".parse().unwrap();
+        let stanza = Stanza::try_from(elem.clone()).unwrap();
+        assert_eq!(stanza, Stanza::Nonza(elem));
+    }
+
+    #[test]
+    fn test_invalid_stanza() {
+        #[cfg(not(feature = "component"))]
+        let elem: Element = "<iq xmlns='jabber:client' type='get'/>".parse().unwrap();
+        #[cfg(feature = "component")]
+        let elem: Element = "<iq xmlns='jabber:component:accept' type='get'/>"
+            .parse()
+            .unwrap();
+        Stanza::try_from(elem).unwrap_err();
+    }
+}
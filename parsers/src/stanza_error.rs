@@ -190,6 +190,36 @@ generate_element_enum!(
     }
 );
 
+impl DefinedCondition {
+    /// The error type RFC 6120 §8.3.3 recommends for this condition.
+    pub fn default_type(&self) -> ErrorType {
+        match self {
+            DefinedCondition::Forbidden
+            | DefinedCondition::NotAuthorized
+            | DefinedCondition::RegistrationRequired
+            | DefinedCondition::SubscriptionRequired => ErrorType::Auth,
+            DefinedCondition::BadRequest
+            | DefinedCondition::JidMalformed
+            | DefinedCondition::NotAcceptable
+            | DefinedCondition::PolicyViolation
+            | DefinedCondition::Redirect => ErrorType::Modify,
+            DefinedCondition::RecipientUnavailable
+            | DefinedCondition::RemoteServerTimeout
+            | DefinedCondition::ResourceConstraint
+            | DefinedCondition::UnexpectedRequest => ErrorType::Wait,
+            DefinedCondition::Conflict
+            | DefinedCondition::FeatureNotImplemented
+            | DefinedCondition::Gone
+            | DefinedCondition::InternalServerError
+            | DefinedCondition::ItemNotFound
+            | DefinedCondition::NotAllowed
+            | DefinedCondition::RemoteServerNotFound
+            | DefinedCondition::ServiceUnavailable
+            | DefinedCondition::UndefinedCondition => ErrorType::Cancel,
+        }
+    }
+}
+
 type Lang = String;
 
 /// The representation of a stanza error.
@@ -238,8 +268,99 @@ impl StanzaError {
             other: None,
         }
     }
+
+    /// Create a new `<error/>` with this condition and the type recommended
+    /// for it, without any text.
+    pub fn from_condition(defined_condition: DefinedCondition) -> StanzaError {
+        StanzaError {
+            type_: defined_condition.default_type(),
+            by: None,
+            defined_condition,
+            texts: BTreeMap::new(),
+            other: None,
+        }
+    }
+
+    /// Add a human-readable description of this error, in the given
+    /// language.
+    pub fn with_text<L: Into<Lang>, T: Into<String>>(mut self, lang: L, text: T) -> StanzaError {
+        self.texts.insert(lang.into(), text.into());
+        self
+    }
+
+    /// Set the JID of the entity who set this error.
+    pub fn with_by(mut self, by: Jid) -> StanzaError {
+        self.by = Some(by);
+        self
+    }
+
+    /// Set the protocol-specific condition of this error.
+    pub fn with_other<E: Into<Element>>(mut self, other: E) -> StanzaError {
+        self.other = Some(other.into());
+        self
+    }
 }
 
+macro_rules! condition_constructors {
+    ($($(#[$meta:meta])* $name:ident => $condition:ident,)+) => (
+        impl StanzaError {
+            $(
+                $(#[$meta])*
+                pub fn $name() -> StanzaError {
+                    StanzaError::from_condition(DefinedCondition::$condition)
+                }
+            )+
+        }
+    );
+}
+
+condition_constructors!(
+    /// Create a bad-request error, of type modify.
+    bad_request => BadRequest,
+    /// Create a conflict error, of type cancel.
+    conflict => Conflict,
+    /// Create a feature-not-implemented error, of type cancel.
+    feature_not_implemented => FeatureNotImplemented,
+    /// Create a forbidden error, of type auth.
+    forbidden => Forbidden,
+    /// Create a gone error, of type cancel.
+    gone => Gone,
+    /// Create an internal-server-error error, of type cancel.
+    internal_server_error => InternalServerError,
+    /// Create an item-not-found error, of type cancel.
+    item_not_found => ItemNotFound,
+    /// Create a jid-malformed error, of type modify.
+    jid_malformed => JidMalformed,
+    /// Create a not-acceptable error, of type modify.
+    not_acceptable => NotAcceptable,
+    /// Create a not-allowed error, of type cancel.
+    not_allowed => NotAllowed,
+    /// Create a not-authorized error, of type auth.
+    not_authorized => NotAuthorized,
+    /// Create a policy-violation error, of type modify.
+    policy_violation => PolicyViolation,
+    /// Create a recipient-unavailable error, of type wait.
+    recipient_unavailable => RecipientUnavailable,
+    /// Create a redirect error, of type modify.
+    redirect => Redirect,
+    /// Create a registration-required error, of type auth.
+    registration_required => RegistrationRequired,
+    /// Create a remote-server-not-found error, of type cancel.
+    remote_server_not_found => RemoteServerNotFound,
+    /// Create a remote-server-timeout error, of type wait.
+    remote_server_timeout => RemoteServerTimeout,
+    /// Create a resource-constraint error, of type wait.
+    resource_constraint => ResourceConstraint,
+    /// Create a service-unavailable error, of type cancel.
+    service_unavailable => ServiceUnavailable,
+    /// Create a subscription-required error, of type auth.
+    subscription_required => SubscriptionRequired,
+    /// Create an undefined-condition error, of type cancel.
+    undefined_condition => UndefinedCondition,
+    /// Create an unexpected-request error, of type wait.
+    unexpected_request => UnexpectedRequest,
+);
+
 impl TryFrom<Element> for StanzaError {
     type Error = Error;
 
@@ -319,7 +440,7 @@ mod tests {
     fn test_size() {
         assert_size!(ErrorType, 1);
         assert_size!(DefinedCondition, 1);
-        assert_size!(StanzaError, 92);
+        assert_size!(StanzaError, 88);
     }
 
     #[cfg(target_pointer_width = "64")]
@@ -327,7 +448,7 @@ mod tests {
     fn test_size() {
         assert_size!(ErrorType, 1);
         assert_size!(DefinedCondition, 1);
-        assert_size!(StanzaError, 184);
+        assert_size!(StanzaError, 176);
     }
 
     #[test]
@@ -415,4 +536,24 @@ mod tests {
         let stanza_error = StanzaError::try_from(elem).unwrap();
         assert_eq!(stanza_error.type_, ErrorType::Cancel);
     }
+
+    #[test]
+    fn test_constructors() {
+        let error = StanzaError::feature_not_implemented();
+        assert_eq!(error.type_, ErrorType::Cancel);
+        assert_eq!(
+            error.defined_condition,
+            DefinedCondition::FeatureNotImplemented
+        );
+        assert!(error.texts.is_empty());
+
+        let error = StanzaError::bad_request().with_text("en", "Missing node.");
+        let elem: Element = "<error xmlns='jabber:client' type='modify'><bad-request xmlns='urn:ietf:params:xml:ns:xmpp-stanzas'/><text xmlns='urn:ietf:params:xml:ns:xmpp-stanzas' xml:lang='en'>Missing node.</text></error>"
+            .parse()
+            .unwrap();
+        assert_eq!(Element::from(error), elem);
+
+        assert_eq!(StanzaError::forbidden().type_, ErrorType::Auth);
+        assert_eq!(StanzaError::unexpected_request().type_, ErrorType::Wait);
+    }
 }
@@ -0,0 +1,375 @@
+// Copyright (c) 2024 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::ns;
+use crate::util::error::Error;
+use crate::Element;
+
+/// List of valid stream error conditions, as defined in RFC 6120 §4.9.3.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinedCondition {
+    /// The entity has sent XML that cannot be processed.
+    BadFormat,
+
+    /// The entity has sent a namespace prefix that is unsupported, or has
+    /// sent no namespace prefix on an element that needs such a prefix.
+    BadNamespacePrefix,
+
+    /// The server either (1) is closing the existing stream for this entity
+    /// because a new stream has been initiated that conflicts with the
+    /// existing stream, or (2) is refusing a new stream for this entity
+    /// because allowing the new stream would conflict with an existing stream.
+    Conflict,
+
+    /// One party is closing the stream because it has reason to believe that
+    /// the other party has permanently lost the ability to communicate over
+    /// the stream.
+    ConnectionTimeout,
+
+    /// The value of the 'to' attribute provided in the initial stream header
+    /// corresponds to an FQDN that is no longer serviced by the receiving
+    /// entity.
+    HostGone,
+
+    /// The value of the 'to' attribute provided in the initial stream header
+    /// does not correspond to an FQDN that is serviced by the receiving
+    /// entity.
+    HostUnknown,
+
+    /// A stanza sent between two servers lacks a 'to' or 'from' attribute,
+    /// the 'from' or 'to' attribute has no value, or the value violates the
+    /// rules for XMPP addresses.
+    ImproperAddressing,
+
+    /// The server has experienced a misconfiguration or other internal error
+    /// that prevents it from servicing the stream.
+    InternalServerError,
+
+    /// The data provided in a 'from' attribute does not match an authorized
+    /// JID or validated domain as negotiated.
+    InvalidFrom,
+
+    /// The stream namespace name is something other than
+    /// "http://etherx.jabber.org/streams" or the content namespace declared
+    /// as the default namespace is not supported.
+    InvalidNamespace,
+
+    /// The entity has sent invalid XML over the stream to a server that
+    /// performs validation.
+    InvalidXml,
+
+    /// The entity has attempted to send XML stanzas or other outbound data
+    /// before the stream has been authenticated.
+    NotAuthorized,
+
+    /// The initiating entity has sent XML that violates the well-formedness
+    /// rules of XML or XML namespaces.
+    NotWellFormed,
+
+    /// The entity has violated some local service policy (e.g., a stanza
+    /// exceeds a configured size limit).
+    PolicyViolation,
+
+    /// The server is unable to properly connect to a remote entity that is
+    /// needed for authentication or authorization.
+    RemoteConnectionFailed,
+
+    /// The server is closing the stream because it has new (typically
+    /// security-critical) features to offer.
+    Reset,
+
+    /// The server lacks the system resources necessary to service the stream.
+    ResourceConstraint,
+
+    /// The entity has attempted to send restricted XML features such as a
+    /// comment, processing instruction, DTD subset, or XML entity reference.
+    RestrictedXml,
+
+    /// The server will not provide service to the initiating entity but is
+    /// redirecting traffic to another host, given as an IP address or
+    /// hostname optionally followed by a port.
+    SeeOtherHost(String),
+
+    /// The server is being shut down and all active streams are being closed.
+    SystemShutdown,
+
+    /// The error condition is not one of those defined by the other
+    /// conditions in this list.
+    UndefinedCondition,
+
+    /// The initiating entity has encoded the stream in an encoding that is
+    /// not supported by the server.
+    UnsupportedEncoding,
+
+    /// The receiving entity has advertised a mandatory-to-negotiate stream
+    /// feature that the initiating entity does not support.
+    UnsupportedFeature,
+
+    /// The initiating entity has sent a first-level child of the stream that
+    /// is not supported by the server.
+    UnsupportedStanzaType,
+
+    /// The 'version' attribute provided by the initiating entity in the
+    /// stream header specifies a version of XMPP that is not supported by
+    /// the server.
+    UnsupportedVersion,
+}
+
+impl DefinedCondition {
+    fn name(&self) -> &'static str {
+        match self {
+            DefinedCondition::BadFormat => "bad-format",
+            DefinedCondition::BadNamespacePrefix => "bad-namespace-prefix",
+            DefinedCondition::Conflict => "conflict",
+            DefinedCondition::ConnectionTimeout => "connection-timeout",
+            DefinedCondition::HostGone => "host-gone",
+            DefinedCondition::HostUnknown => "host-unknown",
+            DefinedCondition::ImproperAddressing => "improper-addressing",
+            DefinedCondition::InternalServerError => "internal-server-error",
+            DefinedCondition::InvalidFrom => "invalid-from",
+            DefinedCondition::InvalidNamespace => "invalid-namespace",
+            DefinedCondition::InvalidXml => "invalid-xml",
+            DefinedCondition::NotAuthorized => "not-authorized",
+            DefinedCondition::NotWellFormed => "not-well-formed",
+            DefinedCondition::PolicyViolation => "policy-violation",
+            DefinedCondition::RemoteConnectionFailed => "remote-connection-failed",
+            DefinedCondition::Reset => "reset",
+            DefinedCondition::ResourceConstraint => "resource-constraint",
+            DefinedCondition::RestrictedXml => "restricted-xml",
+            DefinedCondition::SeeOtherHost(_) => "see-other-host",
+            DefinedCondition::SystemShutdown => "system-shutdown",
+            DefinedCondition::UndefinedCondition => "undefined-condition",
+            DefinedCondition::UnsupportedEncoding => "unsupported-encoding",
+            DefinedCondition::UnsupportedFeature => "unsupported-feature",
+            DefinedCondition::UnsupportedStanzaType => "unsupported-stanza-type",
+            DefinedCondition::UnsupportedVersion => "unsupported-version",
+        }
+    }
+}
+
+impl TryFrom<Element> for DefinedCondition {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<DefinedCondition, Error> {
+        check_ns_only!(elem, "condition", XMPP_STREAMS);
+        check_no_children!(elem, "condition");
+        check_no_attributes!(elem, "condition");
+        Ok(match elem.name() {
+            "bad-format" => DefinedCondition::BadFormat,
+            "bad-namespace-prefix" => DefinedCondition::BadNamespacePrefix,
+            "conflict" => DefinedCondition::Conflict,
+            "connection-timeout" => DefinedCondition::ConnectionTimeout,
+            "host-gone" => DefinedCondition::HostGone,
+            "host-unknown" => DefinedCondition::HostUnknown,
+            "improper-addressing" => DefinedCondition::ImproperAddressing,
+            "internal-server-error" => DefinedCondition::InternalServerError,
+            "invalid-from" => DefinedCondition::InvalidFrom,
+            "invalid-namespace" => DefinedCondition::InvalidNamespace,
+            "invalid-xml" => DefinedCondition::InvalidXml,
+            "not-authorized" => DefinedCondition::NotAuthorized,
+            "not-well-formed" => DefinedCondition::NotWellFormed,
+            "policy-violation" => DefinedCondition::PolicyViolation,
+            "remote-connection-failed" => DefinedCondition::RemoteConnectionFailed,
+            "reset" => DefinedCondition::Reset,
+            "resource-constraint" => DefinedCondition::ResourceConstraint,
+            "restricted-xml" => DefinedCondition::RestrictedXml,
+            "see-other-host" => {
+                let host = elem.text();
+                if host.is_empty() {
+                    return Err(Error::ParseError(
+                        "see-other-host condition must contain a host.",
+                    ));
+                }
+                DefinedCondition::SeeOtherHost(host)
+            }
+            "system-shutdown" => DefinedCondition::SystemShutdown,
+            "undefined-condition" => DefinedCondition::UndefinedCondition,
+            "unsupported-encoding" => DefinedCondition::UnsupportedEncoding,
+            "unsupported-feature" => DefinedCondition::UnsupportedFeature,
+            "unsupported-stanza-type" => DefinedCondition::UnsupportedStanzaType,
+            "unsupported-version" => DefinedCondition::UnsupportedVersion,
+            _ => return Err(Error::ParseError("This is not a condition element.")),
+        })
+    }
+}
+
+impl From<DefinedCondition> for Element {
+    fn from(condition: DefinedCondition) -> Element {
+        let builder = Element::builder(condition.name(), ns::XMPP_STREAMS);
+        match condition {
+            DefinedCondition::SeeOtherHost(host) => builder.append(host),
+            _ => builder,
+        }
+        .build()
+    }
+}
+
+/// The representation of a `<stream:error/>`, after which the stream gets
+/// closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamError {
+    /// One of the defined conditions for this error to happen.
+    pub defined_condition: DefinedCondition,
+
+    /// Human-readable description of this error, along with its language.
+    pub text: Option<(Option<String>, String)>,
+
+    /// An application-specific extension for this error.
+    pub other: Option<Element>,
+}
+
+impl StreamError {
+    /// Create a new `<stream:error/>` with this condition.
+    pub fn new(defined_condition: DefinedCondition) -> StreamError {
+        StreamError {
+            defined_condition,
+            text: None,
+            other: None,
+        }
+    }
+
+    /// Set a human-readable description on this error.
+    pub fn with_text<T: Into<String>>(mut self, lang: Option<String>, text: T) -> StreamError {
+        self.text = Some((lang, text.into()));
+        self
+    }
+}
+
+impl TryFrom<Element> for StreamError {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<StreamError, Error> {
+        check_self!(elem, "error", STREAM);
+        check_no_attributes!(elem, "error");
+
+        let mut defined_condition = None;
+        let mut text = None;
+        let mut other = None;
+
+        for child in elem.children() {
+            if child.is("text", ns::XMPP_STREAMS) {
+                check_no_children!(child, "text");
+                check_no_unknown_attributes!(child, "text", ["xml:lang"]);
+                if text.is_some() {
+                    return Err(Error::ParseError(
+                        "Stream error must not have more than one text.",
+                    ));
+                }
+                text = Some((get_attr!(child, "xml:lang", Option), child.text()));
+            } else if child.has_ns(ns::XMPP_STREAMS) {
+                if defined_condition.is_some() {
+                    return Err(Error::ParseError(
+                        "Stream error must not have more than one defined-condition.",
+                    ));
+                }
+                defined_condition = Some(DefinedCondition::try_from(child.clone())?);
+            } else {
+                if other.is_some() {
+                    return Err(Error::ParseError(
+                        "Stream error must not have more than one other element.",
+                    ));
+                }
+                other = Some(child.clone());
+            }
+        }
+
+        Ok(StreamError {
+            defined_condition: defined_condition.ok_or(Error::ParseError(
+                "Stream error must have a defined-condition.",
+            ))?,
+            text,
+            other,
+        })
+    }
+}
+
+impl From<StreamError> for Element {
+    fn from(err: StreamError) -> Element {
+        Element::builder("error", ns::STREAM)
+            .append(err.defined_condition)
+            .append_all(err.text.map(|(lang, text)| {
+                Element::builder("text", ns::XMPP_STREAMS)
+                    .attr("xml:lang", lang)
+                    .append(text)
+            }))
+            .append_all(err.other)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<stream:error xmlns:stream='http://etherx.jabber.org/streams'><system-shutdown xmlns='urn:ietf:params:xml:ns:xmpp-streams'/></stream:error>".parse().unwrap();
+        let error = StreamError::try_from(elem).unwrap();
+        assert_eq!(error.defined_condition, DefinedCondition::SystemShutdown);
+        assert_eq!(error.text, None);
+        assert_eq!(error.other, None);
+    }
+
+    #[test]
+    fn test_see_other_host() {
+        let elem: Element = "<stream:error xmlns:stream='http://etherx.jabber.org/streams'><see-other-host xmlns='urn:ietf:params:xml:ns:xmpp-streams'>[2001:db8::a11:b]:5222</see-other-host></stream:error>".parse().unwrap();
+        let error = StreamError::try_from(elem).unwrap();
+        assert_eq!(
+            error.defined_condition,
+            DefinedCondition::SeeOtherHost(String::from("[2001:db8::a11:b]:5222"))
+        );
+
+        let elem: Element = "<stream:error xmlns:stream='http://etherx.jabber.org/streams'><see-other-host xmlns='urn:ietf:params:xml:ns:xmpp-streams'/></stream:error>".parse().unwrap();
+        let error = StreamError::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "see-other-host condition must contain a host.");
+    }
+
+    #[test]
+    fn test_text_and_other() {
+        let elem: Element = "<stream:error xmlns:stream='http://etherx.jabber.org/streams'><conflict xmlns='urn:ietf:params:xml:ns:xmpp-streams'/><text xmlns='urn:ietf:params:xml:ns:xmpp-streams' xml:lang='en'>Replaced by new connection</text><escape-your-data xmlns='http://example.org/ns'/></stream:error>".parse().unwrap();
+        let error = StreamError::try_from(elem).unwrap();
+        assert_eq!(error.defined_condition, DefinedCondition::Conflict);
+        assert_eq!(
+            error.text,
+            Some((
+                Some(String::from("en")),
+                String::from("Replaced by new connection")
+            ))
+        );
+        assert!(error
+            .other
+            .unwrap()
+            .is("escape-your-data", "http://example.org/ns"));
+    }
+
+    #[test]
+    fn test_missing_condition() {
+        let elem: Element = "<stream:error xmlns:stream='http://etherx.jabber.org/streams'/>"
+            .parse()
+            .unwrap();
+        let error = StreamError::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Stream error must have a defined-condition.");
+    }
+
+    #[test]
+    fn test_serialise() {
+        let error = StreamError::new(DefinedCondition::SeeOtherHost(String::from(
+            "other.example.org",
+        )))
+        .with_text(None, "Moved");
+        let elem: Element = error.clone().into();
+        assert!(elem.is("error", ns::STREAM));
+        assert_eq!(StreamError::try_from(elem).unwrap(), error);
+    }
+}
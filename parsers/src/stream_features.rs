@@ -0,0 +1,244 @@
+// Copyright (c) 2024 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::ns;
+use crate::util::error::Error;
+use crate::Element;
+
+/// Prefix of the namespaces of the versions of Stream Management.
+const SM_PREFIX: &str = "urn:xmpp:sm:";
+
+/// The advertisement of STARTTLS, see RFC 6120 §5.4.1.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StartTls {
+    /// Whether the receiving entity refuses to go on without TLS.
+    pub required: bool,
+}
+
+/// The `<stream:features/>` advertised after the stream header, see
+/// RFC 6120 §4.3.2.
+///
+/// Unlike most parsers of this crate, unknown features are kept rather than
+/// refused, since any extension may add its own.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StreamFeatures {
+    /// STARTTLS, if offered.
+    pub starttls: Option<StartTls>,
+
+    /// The SASL mechanisms offered, in the order of preference of the
+    /// server.
+    pub sasl_mechanisms: Vec<String>,
+
+    /// Whether a resource can be bound.
+    pub can_bind: bool,
+
+    /// The versions of Stream Management (XEP-0198) offered, such as 3 for
+    /// urn:xmpp:sm:3.
+    pub sm_versions: Vec<u8>,
+
+    /// Whether the server supports Roster Versioning (XEP-0237).
+    pub roster_versioning: bool,
+
+    /// The methods of Stream Compression (XEP-0138) offered, such as zlib.
+    pub compression_methods: Vec<String>,
+
+    /// The other features.
+    pub others: Vec<Element>,
+}
+
+impl StreamFeatures {
+    /// Whether Stream Management (XEP-0198) is offered in its version 3,
+    /// the one this crate implements.
+    pub fn can_sm(&self) -> bool {
+        self.sm_versions.contains(&3)
+    }
+}
+
+impl TryFrom<Element> for StreamFeatures {
+    type Error = Error;
+
+    fn try_from(elem: Element) -> Result<StreamFeatures, Error> {
+        check_self!(elem, "features", STREAM, "stream features");
+        check_no_attributes!(elem, "stream features");
+
+        let mut features = StreamFeatures::default();
+        for child in elem.children() {
+            if child.is("starttls", ns::TLS) {
+                features.starttls = Some(StartTls {
+                    required: child.has_child("required", ns::TLS),
+                });
+            } else if child.is("mechanisms", ns::SASL) {
+                features.sasl_mechanisms = child
+                    .children()
+                    .filter(|mechanism| mechanism.is("mechanism", ns::SASL))
+                    .map(|mechanism| mechanism.text())
+                    .collect();
+            } else if child.is("bind", ns::BIND) {
+                features.can_bind = true;
+            } else if child.is("ver", ns::ROSTERVER) {
+                features.roster_versioning = true;
+            } else if child.is("compression", ns::COMPRESS_FEATURE) {
+                features.compression_methods = child
+                    .children()
+                    .filter(|method| method.is("method", ns::COMPRESS_FEATURE))
+                    .map(|method| method.text())
+                    .collect();
+            } else if let Some(version) = sm_version(child) {
+                features.sm_versions.push(version);
+            } else {
+                features.others.push(child.clone());
+            }
+        }
+        Ok(features)
+    }
+}
+
+/// Get the version of Stream Management this element advertises, if any.
+fn sm_version(elem: &Element) -> Option<u8> {
+    if elem.name() != "sm" {
+        return None;
+    }
+    elem.ns().strip_prefix(SM_PREFIX)?.parse().ok()
+}
+
+impl From<StreamFeatures> for Element {
+    fn from(features: StreamFeatures) -> Element {
+        Element::builder("features", ns::STREAM)
+            .append_all(features.starttls.map(|starttls| {
+                Element::builder("starttls", ns::TLS).append_all(
+                    starttls
+                        .required
+                        .then(|| Element::builder("required", ns::TLS)),
+                )
+            }))
+            .append_all((!features.sasl_mechanisms.is_empty()).then(|| {
+                Element::builder("mechanisms", ns::SASL).append_all(
+                    features
+                        .sasl_mechanisms
+                        .into_iter()
+                        .map(|mechanism| Element::builder("mechanism", ns::SASL).append(mechanism)),
+                )
+            }))
+            .append_all(
+                features
+                    .can_bind
+                    .then(|| Element::builder("bind", ns::BIND)),
+            )
+            .append_all(
+                features
+                    .sm_versions
+                    .into_iter()
+                    .map(|version| Element::builder("sm", format!("{}{}", SM_PREFIX, version))),
+            )
+            .append_all(
+                features
+                    .roster_versioning
+                    .then(|| Element::builder("ver", ns::ROSTERVER)),
+            )
+            .append_all((!features.compression_methods.is_empty()).then(|| {
+                Element::builder("compression", ns::COMPRESS_FEATURE).append_all(
+                    features.compression_methods.into_iter().map(|method| {
+                        Element::builder("method", ns::COMPRESS_FEATURE).append(method)
+                    }),
+                )
+            }))
+            .append_all(features.others)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(StartTls, 1);
+        assert_size!(StreamFeatures, 52);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(StartTls, 1);
+        assert_size!(StreamFeatures, 104);
+    }
+
+    #[test]
+    fn test_before_auth() {
+        let elem: Element = "<stream:features xmlns:stream='http://etherx.jabber.org/streams'>
+            <starttls xmlns='urn:ietf:params:xml:ns:xmpp-tls'><required/></starttls>
+            <mechanisms xmlns='urn:ietf:params:xml:ns:xmpp-sasl'>
+                <mechanism>SCRAM-SHA-1</mechanism>
+                <mechanism>PLAIN</mechanism>
+            </mechanisms>
+            <compression xmlns='http://jabber.org/features/compress'><method>zlib</method></compression>
+            <register xmlns='http://jabber.org/features/iq-register'/>
+        </stream:features>"
+            .parse()
+            .unwrap();
+        let features = StreamFeatures::try_from(elem).unwrap();
+        assert_eq!(features.starttls, Some(StartTls { required: true }));
+        assert_eq!(features.sasl_mechanisms, ["SCRAM-SHA-1", "PLAIN"]);
+        assert_eq!(features.compression_methods, ["zlib"]);
+        assert!(!features.can_bind);
+        assert_eq!(features.others.len(), 1);
+        assert!(features.others[0].is("register", "http://jabber.org/features/iq-register"));
+    }
+
+    #[test]
+    fn test_after_auth() {
+        let elem: Element = "<stream:features xmlns:stream='http://etherx.jabber.org/streams'>
+            <bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'/>
+            <sm xmlns='urn:xmpp:sm:2'/>
+            <sm xmlns='urn:xmpp:sm:3'/>
+            <ver xmlns='urn:xmpp:features:rosterver'/>
+        </stream:features>"
+            .parse()
+            .unwrap();
+        let features = StreamFeatures::try_from(elem).unwrap();
+        assert!(features.starttls.is_none());
+        assert!(features.sasl_mechanisms.is_empty());
+        assert!(features.can_bind);
+        assert_eq!(features.sm_versions, [2, 3]);
+        assert!(features.can_sm());
+        assert!(features.roster_versioning);
+        assert!(features.others.is_empty());
+    }
+
+    #[test]
+    fn test_serialise() {
+        let features = StreamFeatures {
+            starttls: Some(StartTls { required: false }),
+            sasl_mechanisms: vec![String::from("PLAIN")],
+            can_bind: true,
+            sm_versions: vec![3],
+            ..Default::default()
+        };
+        let elem: Element = features.clone().into();
+        assert!(elem.is("features", ns::STREAM));
+        assert!(elem.has_child("sm", ns::SM));
+        assert!(!elem
+            .get_child("starttls", ns::TLS)
+            .unwrap()
+            .has_child("required", ns::TLS));
+        assert_eq!(StreamFeatures::try_from(elem).unwrap(), features);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let elem: Element = "<features xmlns='urn:ietf:params:xml:ns:xmpp-sasl'/>"
+            .parse()
+            .unwrap();
+        let error = StreamFeatures::try_from(elem.clone()).unwrap_err();
+        let returned_elem = match error {
+            Error::TypeMismatch(_, _, elem) => elem,
+            _ => panic!(),
+        };
+        assert_eq!(elem, returned_elem);
+    }
+}
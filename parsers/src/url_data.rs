@@ -0,0 +1,72 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+generate_element!(
+    /// Points at some data to download, e.g. one of the sources of a shared
+    /// file.
+    UrlData, "url-data", URL_DATA,
+    attributes: [
+        /// The URL of the data.
+        target: Required<String> = "target",
+    ]
+);
+
+impl UrlData {
+    /// Point at the data of this URL.
+    pub fn new<T: Into<String>>(target: T) -> UrlData {
+        UrlData {
+            target: target.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::error::Error;
+    use crate::Element;
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_size() {
+        assert_size!(UrlData, 12);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_size!(UrlData, 24);
+    }
+
+    #[test]
+    fn test_simple() {
+        let elem: Element = "<url-data xmlns='http://jabber.org/protocol/url-data'
+                  target='https://download.montague.lit/4a771ac1-f0b2-4a4a-9700-f2a26fa2bb67/file.jpg'/>"
+            .parse()
+            .unwrap();
+        let url_data = UrlData::try_from(elem.clone()).unwrap();
+        assert_eq!(
+            url_data,
+            UrlData::new(
+                "https://download.montague.lit/4a771ac1-f0b2-4a4a-9700-f2a26fa2bb67/file.jpg"
+            )
+        );
+        assert_eq!(Element::from(url_data), elem);
+    }
+
+    #[test]
+    fn test_missing_target() {
+        let elem: Element = "<url-data xmlns='http://jabber.org/protocol/url-data'/>"
+            .parse()
+            .unwrap();
+        let error = UrlData::try_from(elem).unwrap_err();
+        let message = match error {
+            Error::ParseError(string) => string,
+            _ => panic!(),
+        };
+        assert_eq!(message, "Required attribute 'target' missing.");
+    }
+}
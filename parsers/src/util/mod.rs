@@ -13,3 +13,58 @@ pub(crate) mod text_node_codecs;
 /// Helper macros to parse and serialise more easily.
 #[macro_use]
 mod macros;
+
+use crate::util::error::Error;
+use crate::Element;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Generates a stanza identifier, unpredictable and unique in this process.
+pub(crate) fn make_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}", hasher.finish())
+}
+
+/// Parses the first element of type `T` among `payloads`, if any.
+pub(crate) fn get_payload<T: TryFrom<Element, Error = Error>>(
+    payloads: &[Element],
+) -> Option<Result<T, Error>> {
+    for payload in payloads {
+        match T::try_from(payload.clone()) {
+            Err(Error::TypeMismatch(..)) => (),
+            result => return Some(result),
+        }
+    }
+    None
+}
+
+/// Removes the first element of type `T` from `payloads` and parses it.
+#[allow(clippy::result_large_err)]
+pub(crate) fn extract_payload<T: TryFrom<Element, Error = Error>>(
+    payloads: &mut Vec<Element>,
+) -> Result<Option<T>, Error> {
+    let mut buf = Vec::with_capacity(payloads.len());
+    let mut iter = payloads.drain(..);
+    let mut result = Ok(None);
+    for item in &mut iter {
+        match T::try_from(item) {
+            Ok(v) => {
+                result = Ok(Some(v));
+                break;
+            }
+            Err(Error::TypeMismatch(_, _, residual)) => {
+                buf.push(residual);
+            }
+            Err(other) => {
+                result = Err(other);
+                break;
+            }
+        }
+    }
+    buf.extend(iter);
+    std::mem::swap(&mut buf, payloads);
+    result
+}
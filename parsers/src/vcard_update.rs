@@ -12,6 +12,7 @@
 //! For [XEP-0054](https://xmpp.org/extensions/xep-0054.html) vCard support,
 //! see [`vcard`][crate::vcard] module.
 
+use crate::presence::PresencePayload;
 use crate::util::text_node_codecs::{Codec, FixedHex, OptionalCodec};
 
 generate_element!(
@@ -32,6 +33,8 @@ generate_element!(
     )
 );
 
+impl PresencePayload for VCardUpdate {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
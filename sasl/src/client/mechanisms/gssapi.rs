@@ -0,0 +1,206 @@
+//! Provides the SASL "GSSAPI" mechanism (RFC 4752), used for Kerberos.
+
+use crate::client::{Mechanism, MechanismError};
+use crate::common::Credentials;
+
+/// The result of one call to `GSS_Init_sec_context`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GssapiStep {
+    /// The context needs another token from the server, after this one is sent.
+    Continue(Vec<u8>),
+    /// The context is established, this last token (possibly empty) still has to be sent.
+    Complete(Vec<u8>),
+}
+
+/// A GSS-API security context on the client side.
+///
+/// This crate doesn’t link to any GSS-API implementation, implement this trait on top of the
+/// one you use (e.g. the `libgssapi` crate with MIT Kerberos or Heimdal) and pass it to
+/// `Gssapi::new`.
+pub trait GssapiContext {
+    /// Calls `GSS_Init_sec_context` with the token received from the server, which is `None`
+    /// for the first call.
+    fn step(&mut self, token: Option<&[u8]>) -> Result<GssapiStep, String>;
+
+    /// Calls `GSS_Wrap` on `message`, without requesting confidentiality.
+    fn wrap(&mut self, message: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Calls `GSS_Unwrap` on `message`.
+    fn unwrap(&mut self, message: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+// Security layers, from RFC 4752 section 3.3.
+const NO_SECURITY_LAYER: u8 = 1;
+
+enum GssapiState {
+    Initial { token: Vec<u8>, established: bool },
+    Negotiating,
+    Established,
+    Done,
+}
+
+/// A struct for the SASL GSSAPI mechanism.
+///
+/// Only the “no security layer” option is supported, which is what XMPP uses, since the stream
+/// is already protected by TLS.
+pub struct Gssapi<C: GssapiContext> {
+    context: C,
+    authzid: String,
+    state: GssapiState,
+}
+
+impl<C: GssapiContext> Gssapi<C> {
+    /// Constructs a new struct for authenticating using the SASL GSSAPI mechanism, with a
+    /// security context for the `xmpp@domain` service.
+    pub fn new(mut context: C) -> Result<Gssapi<C>, MechanismError> {
+        let state = match context
+            .step(None)
+            .map_err(MechanismError::GssapiContextError)?
+        {
+            GssapiStep::Continue(token) => GssapiState::Initial {
+                token,
+                established: false,
+            },
+            GssapiStep::Complete(token) => GssapiState::Initial {
+                token,
+                established: true,
+            },
+        };
+        Ok(Gssapi {
+            context,
+            authzid: String::new(),
+            state,
+        })
+    }
+
+    /// Requests to be authorized as `authzid` rather than the identity derived from the
+    /// Kerberos principal.
+    pub fn with_authzid<A: Into<String>>(mut self, authzid: A) -> Gssapi<C> {
+        self.authzid = authzid.into();
+        self
+    }
+}
+
+impl<C: GssapiContext> Mechanism for Gssapi<C> {
+    fn name(&self) -> &str {
+        "GSSAPI"
+    }
+
+    fn from_credentials(_credentials: Credentials) -> Result<Gssapi<C>, MechanismError> {
+        Err(MechanismError::GssapiRequiresContext)
+    }
+
+    fn initial(&mut self) -> Vec<u8> {
+        match self.state {
+            GssapiState::Initial {
+                ref mut token,
+                established,
+            } => {
+                let token = std::mem::take(token);
+                self.state = if established {
+                    GssapiState::Established
+                } else {
+                    GssapiState::Negotiating
+                };
+                token
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn response(&mut self, challenge: &[u8]) -> Result<Vec<u8>, MechanismError> {
+        match self.state {
+            GssapiState::Negotiating => {
+                match self
+                    .context
+                    .step(Some(challenge))
+                    .map_err(MechanismError::GssapiContextError)?
+                {
+                    GssapiStep::Continue(token) => Ok(token),
+                    GssapiStep::Complete(token) => {
+                        self.state = GssapiState::Established;
+                        Ok(token)
+                    }
+                }
+            }
+            GssapiState::Established => {
+                let layers = self
+                    .context
+                    .unwrap(challenge)
+                    .map_err(MechanismError::GssapiContextError)?;
+                if layers.len() != 4 {
+                    return Err(MechanismError::CannotDecodeChallenge);
+                }
+                if layers[0] & NO_SECURITY_LAYER == 0 {
+                    return Err(MechanismError::GssapiNoSecurityLayerOffered);
+                }
+                let mut message = vec![NO_SECURITY_LAYER, 0, 0, 0];
+                message.extend(self.authzid.bytes());
+                let response = self
+                    .context
+                    .wrap(&message)
+                    .map_err(MechanismError::GssapiContextError)?;
+                self.state = GssapiState::Done;
+                Ok(response)
+            }
+            GssapiState::Initial { .. } | GssapiState::Done => Err(MechanismError::InvalidState),
+        }
+    }
+
+    fn success(&mut self, _data: &[u8]) -> Result<(), MechanismError> {
+        match self.state {
+            GssapiState::Done => Ok(()),
+            _ => Err(MechanismError::InvalidState),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pretends to be a context which needs two round-trips, and “wraps” by reversing.
+    struct FakeContext {
+        steps: usize,
+    }
+
+    impl GssapiContext for FakeContext {
+        fn step(&mut self, token: Option<&[u8]>) -> Result<GssapiStep, String> {
+            self.steps += 1;
+            match (self.steps, token) {
+                (1, None) => Ok(GssapiStep::Continue(b"ap-req".to_vec())),
+                (2, Some(b"ap-rep")) => Ok(GssapiStep::Complete(Vec::new())),
+                _ => Err(String::from("unexpected token")),
+            }
+        }
+
+        fn wrap(&mut self, message: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(message.iter().rev().cloned().collect())
+        }
+
+        fn unwrap(&mut self, message: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(message.iter().rev().cloned().collect())
+        }
+    }
+
+    #[test]
+    fn gssapi_works() {
+        let mut mechanism = Gssapi::new(FakeContext { steps: 0 })
+            .unwrap()
+            .with_authzid("user@example.org");
+        assert_eq!(mechanism.initial(), b"ap-req");
+        assert_eq!(mechanism.response(b"ap-rep").unwrap(), b"");
+        let response = mechanism.response(&[0, 0x10, 0, 7]).unwrap();
+        assert_eq!(response, b"gro.elpmaxe@resu\0\0\0\x01");
+        mechanism.success(b"").unwrap();
+    }
+
+    #[test]
+    fn gssapi_no_security_layer() {
+        let mut mechanism = Gssapi::new(FakeContext { steps: 0 }).unwrap();
+        mechanism.initial();
+        mechanism.response(b"ap-rep").unwrap();
+        let err = mechanism.response(&[0, 0, 0, 4]).unwrap_err();
+        assert_eq!(err, MechanismError::GssapiNoSecurityLayerOffered);
+    }
+}
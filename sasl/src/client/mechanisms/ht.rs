@@ -0,0 +1,81 @@
+//! Provides the SASL "HT-*" hashed token mechanisms.
+
+use std::marker::PhantomData;
+
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use crate::client::{Mechanism, MechanismError};
+use crate::common::scram::ScramProvider;
+use crate::common::{ht_name, ChannelBinding, Credentials, Identity, Password, Secret};
+
+/// A struct for the SASL HT-* mechanisms, from draft-schmaus-kitten-sasl-ht.
+///
+/// The token is passed as a plaintext password, the channel binding (if any) selects between
+/// the -NONE, -UNIQ and -EXPR variants.
+pub struct Ht<S: ScramProvider> {
+    name: String,
+    username: String,
+    initiator_hashed_token: Vec<u8>,
+    responder_hashed_token: Vec<u8>,
+    _marker: PhantomData<S>,
+}
+
+impl<S: ScramProvider> Ht<S> {
+    /// Constructs a new struct for authenticating using the SASL HT-* mechanisms.
+    ///
+    /// It is recommended that instead you use a `Credentials` struct and turn it into the
+    /// requested mechanism using `from_credentials`.
+    pub fn new<N: Into<String>, T: Into<String>>(
+        username: N,
+        token: T,
+        channel_binding: ChannelBinding,
+    ) -> Result<Ht<S>, MechanismError> {
+        let token = Zeroizing::new(token.into());
+        let mut initiator = b"Initiator".to_vec();
+        initiator.extend(channel_binding.data());
+        let mut responder = b"Responder".to_vec();
+        responder.extend(channel_binding.data());
+        Ok(Ht {
+            name: ht_name(S::name(), &channel_binding),
+            username: username.into(),
+            initiator_hashed_token: S::hmac(&initiator, token.as_bytes())?,
+            responder_hashed_token: S::hmac(&responder, token.as_bytes())?,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<S: ScramProvider> Mechanism for Ht<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn from_credentials(credentials: Credentials) -> Result<Ht<S>, MechanismError> {
+        if let Secret::Password(Password::Plain(ref token)) = credentials.secret {
+            if let Identity::Username(username) = credentials.identity {
+                Ht::new(username, token.as_str(), credentials.channel_binding)
+            } else {
+                Err(MechanismError::HtRequiresUsername)
+            }
+        } else {
+            Err(MechanismError::HtRequiresToken)
+        }
+    }
+
+    fn initial(&mut self) -> Vec<u8> {
+        let mut auth = Vec::new();
+        auth.extend(self.username.bytes());
+        auth.push(0);
+        auth.extend(&self.initiator_hashed_token);
+        auth
+    }
+
+    fn success(&mut self, data: &[u8]) -> Result<(), MechanismError> {
+        if bool::from(data.ct_eq(&self.responder_hashed_token)) {
+            Ok(())
+        } else {
+            Err(MechanismError::InvalidSignatureInSuccessResponse)
+        }
+    }
+}
@@ -1,12 +1,22 @@
 //! Provides a few SASL mechanisms.
 
 mod anonymous;
+#[cfg(feature = "gssapi")]
+mod gssapi;
+#[cfg(feature = "scram")]
+mod ht;
 mod plain;
 
 #[cfg(feature = "scram")]
 mod scram;
 
 pub use self::anonymous::Anonymous;
+#[cfg(feature = "gssapi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gssapi")))]
+pub use self::gssapi::{Gssapi, GssapiContext, GssapiStep};
+#[cfg(feature = "scram")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+pub use self::ht::Ht;
 pub use self::plain::Plain;
 
 #[cfg(feature = "scram")]
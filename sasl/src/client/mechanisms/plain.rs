@@ -3,10 +3,12 @@
 use crate::client::{Mechanism, MechanismError};
 use crate::common::{Credentials, Identity, Password, Secret};
 
+use zeroize::Zeroizing;
+
 /// A struct for the SASL PLAIN mechanism.
 pub struct Plain {
     username: String,
-    password: String,
+    password: Zeroizing<String>,
 }
 
 impl Plain {
@@ -17,7 +19,7 @@ impl Plain {
     pub fn new<N: Into<String>, P: Into<String>>(username: N, password: P) -> Plain {
         Plain {
             username: username.into(),
-            password: password.into(),
+            password: Zeroizing::new(password.into()),
         }
     }
 }
@@ -28,9 +30,9 @@ impl Mechanism for Plain {
     }
 
     fn from_credentials(credentials: Credentials) -> Result<Plain, MechanismError> {
-        if let Secret::Password(Password::Plain(password)) = credentials.secret {
+        if let Secret::Password(Password::Plain(ref password)) = credentials.secret {
             if let Identity::Username(username) = credentials.identity {
-                Ok(Plain::new(username, password))
+                Ok(Plain::new(username, password.as_str()))
             } else {
                 Err(MechanismError::PlainRequiresUsername)
             }
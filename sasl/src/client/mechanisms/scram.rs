@@ -10,6 +10,10 @@ use crate::error::Error;
 
 use std::marker::PhantomData;
 
+use stringprep::saslprep;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
 enum ScramState {
     Init,
     SentInitialMessage {
@@ -44,10 +48,13 @@ impl<S: ScramProvider> Scram<S> {
         password: P,
         channel_binding: ChannelBinding,
     ) -> Result<Scram<S>, Error> {
+        let username = saslprep(&username.into())
+            .map_err(|_| Error::SaslError("username prohibited by SASLprep".to_owned()))?
+            .into_owned();
         Ok(Scram {
             name: format!("SCRAM-{}", S::name()),
             name_plus: format!("SCRAM-{}-PLUS", S::name()),
-            username: username.into(),
+            username,
             password: password.into(),
             client_nonce: generate_nonce()?,
             state: ScramState::Init,
@@ -89,8 +96,11 @@ impl<S: ScramProvider> Mechanism for Scram<S> {
     fn from_credentials(credentials: Credentials) -> Result<Scram<S>, MechanismError> {
         if let Secret::Password(password) = credentials.secret {
             if let Identity::Username(username) = credentials.identity {
-                Scram::new(username, password, credentials.channel_binding)
-                    .map_err(|_| MechanismError::CannotGenerateNonce)
+                Scram::new(username, password, credentials.channel_binding).map_err(|err| match err
+                {
+                    Error::SaslError(_) => MechanismError::ScramUsernameProhibited,
+                    Error::RngError(_) => MechanismError::CannotGenerateNonce,
+                })
             } else {
                 Err(MechanismError::ScramRequiresUsername)
             }
@@ -133,7 +143,6 @@ impl<S: ScramProvider> Mechanism for Scram<S> {
                 let server_nonce = server_nonce.ok_or_else(|| MechanismError::NoServerNonce)?;
                 let salt = salt.ok_or_else(|| MechanismError::NoServerSalt)?;
                 let iterations = iterations.ok_or_else(|| MechanismError::NoServerIterations)?;
-                // TODO: SASLprep
                 let mut client_final_message_bare = Vec::new();
                 client_final_message_bare.extend(b"c=");
                 let mut cb_data: Vec<u8> = Vec::new();
@@ -142,16 +151,16 @@ impl<S: ScramProvider> Mechanism for Scram<S> {
                 client_final_message_bare.extend(Base64.encode(&cb_data).bytes());
                 client_final_message_bare.extend(b",r=");
                 client_final_message_bare.extend(server_nonce.bytes());
-                let salted_password = S::derive(&self.password, &salt, iterations)?;
-                let client_key = S::hmac(b"Client Key", &salted_password)?;
-                let server_key = S::hmac(b"Server Key", &salted_password)?;
+                let salted_password = Zeroizing::new(S::derive(&self.password, &salt, iterations)?);
+                let client_key = Zeroizing::new(S::hmac(b"Client Key", &salted_password)?);
+                let server_key = Zeroizing::new(S::hmac(b"Server Key", &salted_password)?);
                 let mut auth_message = Vec::new();
                 auth_message.extend(initial_message);
                 auth_message.push(b',');
                 auth_message.extend(challenge);
                 auth_message.push(b',');
                 auth_message.extend(&client_final_message_bare);
-                let stored_key = S::hash(&client_key);
+                let stored_key = Zeroizing::new(S::hash(&client_key));
                 let client_signature = S::hmac(&auth_message, &stored_key)?;
                 let client_proof = xor(&client_key, &client_signature);
                 let server_signature = S::hmac(&auth_message, &server_key)?;
@@ -179,7 +188,7 @@ impl<S: ScramProvider> Mechanism for Scram<S> {
                 ref server_signature,
             } => {
                 if let Some(sig) = frame.get("v").and_then(|v| Base64.decode(&v).ok()) {
-                    if sig == *server_signature {
+                    if bool::from(sig.ct_eq(server_signature)) {
                         Ok(())
                     } else {
                         Err(MechanismError::InvalidSignatureInSuccessResponse)
@@ -196,8 +205,9 @@ impl<S: ScramProvider> Mechanism for Scram<S> {
 #[cfg(test)]
 mod tests {
     use crate::client::mechanisms::Scram;
-    use crate::client::Mechanism;
-    use crate::common::scram::{Sha1, Sha256};
+    use crate::client::{Mechanism, MechanismError};
+    use crate::common::scram::{DeriveError, ScramProvider, Sha1, Sha256};
+    use crate::common::{Credentials, Password};
 
     #[test]
     fn scram_sha1_works() {
@@ -249,4 +259,30 @@ mod tests {
         ); // again, depends on ordering…
         mechanism.success(&server_final[..]).unwrap();
     }
+
+    #[test]
+    fn scram_sha1_saslprep() {
+        // U+00AD SOFT HYPHEN is mapped to nothing, so this is the same exchange as above.
+        let client_nonce = "fyko+d2lbbFgONRv9qkxdawL";
+        let server_init = b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096";
+        let client_final =
+            b"c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts=";
+        let mut mechanism =
+            Scram::<Sha1>::new_with_nonce("user", "pen\u{AD}cil", client_nonce.to_owned());
+        mechanism.initial();
+        let resp = mechanism.response(&server_init[..]).unwrap();
+        assert_eq!(resp, client_final);
+    }
+
+    #[test]
+    fn scram_prohibited_credentials() {
+        let creds = Credentials::default()
+            .with_username("user\u{7}")
+            .with_password("pencil");
+        let err = Scram::<Sha256>::from_credentials(creds).err().unwrap();
+        assert_eq!(err, MechanismError::ScramUsernameProhibited);
+
+        let err = Sha256::derive(&Password::Plain("pen\u{7}cil".to_owned()), b"salt", 4096);
+        assert_eq!(err, Err(DeriveError::PasswordProhibited));
+    }
 }
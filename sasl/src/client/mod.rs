@@ -17,6 +17,10 @@ pub enum MechanismError {
     CannotGenerateNonce,
     ScramRequiresUsername,
     ScramRequiresPassword,
+    ScramUsernameProhibited,
+
+    HtRequiresUsername,
+    HtRequiresToken,
 
     CannotDecodeChallenge,
     NoServerNonce,
@@ -31,6 +35,13 @@ pub enum MechanismError {
     CannotDecodeSuccessResponse,
     InvalidSignatureInSuccessResponse,
     NoSignatureInSuccessResponse,
+
+    #[cfg(feature = "gssapi")]
+    GssapiRequiresContext,
+    #[cfg(feature = "gssapi")]
+    GssapiContextError(String),
+    #[cfg(feature = "gssapi")]
+    GssapiNoSecurityLayerOffered,
 }
 
 #[cfg(feature = "scram")]
@@ -65,6 +76,11 @@ impl fmt::Display for MechanismError {
                 MechanismError::CannotGenerateNonce => "can't generate nonce",
                 MechanismError::ScramRequiresUsername => "SCRAM requires a username",
                 MechanismError::ScramRequiresPassword => "SCRAM requires a password",
+                MechanismError::ScramUsernameProhibited =>
+                    "SCRAM username contains characters prohibited by SASLprep",
+
+                MechanismError::HtRequiresUsername => "HT requires a username",
+                MechanismError::HtRequiresToken => "HT requires a token as plaintext password",
 
                 MechanismError::CannotDecodeChallenge => "can't decode challenge",
                 MechanismError::NoServerNonce => "no server nonce",
@@ -81,6 +97,16 @@ impl fmt::Display for MechanismError {
                 MechanismError::InvalidSignatureInSuccessResponse =>
                     "invalid signature in success response",
                 MechanismError::NoSignatureInSuccessResponse => "no signature in success response",
+
+                #[cfg(feature = "gssapi")]
+                MechanismError::GssapiRequiresContext =>
+                    "GSSAPI requires a security context, use Gssapi::new",
+                #[cfg(feature = "gssapi")]
+                MechanismError::GssapiContextError(err) =>
+                    return write!(fmt, "GSS-API error: {}", err),
+                #[cfg(feature = "gssapi")]
+                MechanismError::GssapiNoSecurityLayerOffered =>
+                    "server requires a GSSAPI security layer",
             }
         )
     }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::string::FromUtf8Error;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 #[cfg(feature = "scram")]
 #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
@@ -94,7 +95,9 @@ impl Secret {
 }
 
 /// Represents a password.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Its contents are overwritten with zeroes when it gets dropped.
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
 pub enum Password {
     /// A plaintext password.
     Plain(String),
@@ -119,6 +122,17 @@ impl<'a> From<&'a str> for Password {
     }
 }
 
+/// Returns the name of the HT-* mechanism for this hash and channel binding.
+#[doc(hidden)]
+pub fn ht_name(hash: &str, channel_binding: &ChannelBinding) -> String {
+    let cb = match channel_binding {
+        ChannelBinding::None | ChannelBinding::Unsupported => "NONE",
+        ChannelBinding::TlsUnique(_) => "UNIQ",
+        ChannelBinding::TlsExporter(_) => "EXPR",
+    };
+    format!("HT-{}-{}", hash, cb)
+}
+
 #[cfg(test)]
 #[test]
 fn xor_works() {
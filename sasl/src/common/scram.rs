@@ -3,6 +3,8 @@ use hmac::{digest::InvalidLength, Hmac, Mac};
 use pbkdf2::pbkdf2;
 use sha1::{Digest, Sha1 as Sha1_hash};
 use sha2::Sha256 as Sha256_hash;
+use stringprep::saslprep;
+use zeroize::Zeroizing;
 
 use crate::common::Password;
 
@@ -23,6 +25,7 @@ pub enum DeriveError {
     IncorrectSalt,
     InvalidLength,
     IncompatibleIterationCount(u32, u32),
+    PasswordProhibited,
 }
 
 impl std::fmt::Display for DeriveError {
@@ -36,6 +39,9 @@ impl std::fmt::Display for DeriveError {
             DeriveError::IncompatibleIterationCount(one, two) => {
                 write!(fmt, "incompatible iteration count, {} is not {}", one, two)
             }
+            DeriveError::PasswordProhibited => {
+                write!(fmt, "password contains characters prohibited by SASLprep")
+            }
         }
     }
 }
@@ -96,6 +102,11 @@ impl ScramProvider for Sha1 {
     fn derive(password: &Password, salt: &[u8], iterations: u32) -> Result<Vec<u8>, DeriveError> {
         match *password {
             Password::Plain(ref plain) => {
+                let plain = Zeroizing::new(
+                    saslprep(plain)
+                        .map_err(|_| DeriveError::PasswordProhibited)?
+                        .into_owned(),
+                );
                 let mut result = vec![0; 20];
                 pbkdf2::<Hmac<Sha1_hash>>(plain.as_bytes(), salt, iterations, &mut result)?;
                 Ok(result)
@@ -156,6 +167,11 @@ impl ScramProvider for Sha256 {
     fn derive(password: &Password, salt: &[u8], iterations: u32) -> Result<Vec<u8>, DeriveError> {
         match *password {
             Password::Plain(ref plain) => {
+                let plain = Zeroizing::new(
+                    saslprep(plain)
+                        .map_err(|_| DeriveError::PasswordProhibited)?
+                        .into_owned(),
+                );
                 let mut result = vec![0; 32];
                 pbkdf2::<Hmac<Sha256_hash>>(plain.as_bytes(), salt, iterations, &mut result)?;
                 Ok(result)
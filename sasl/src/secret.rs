@@ -1,5 +1,9 @@
 #[cfg(feature = "scram")]
-use crate::common::scram::DeriveError;
+use crate::common::scram::{DeriveError, ScramProvider};
+#[cfg(feature = "scram")]
+use hmac::digest::InvalidLength;
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 pub trait Secret {}
 
@@ -9,12 +13,44 @@ pub trait Pbkdf2Secret {
     fn digest(&self) -> &[u8];
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// A secret the server SCRAM mechanism can authenticate against.
+#[cfg(feature = "scram")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+pub trait ScramSecret: Secret {
+    fn salt(&self) -> &[u8];
+    fn iterations(&self) -> u32;
+    /// Returns the StoredKey and ServerKey, as defined in RFC 5802.
+    fn keys<S: ScramProvider>(&self) -> Result<(Vec<u8>, Vec<u8>), InvalidLength>;
+}
+
+#[cfg(feature = "scram")]
+impl<T: Secret + Pbkdf2Secret> ScramSecret for T {
+    fn salt(&self) -> &[u8] {
+        Pbkdf2Secret::salt(self)
+    }
+    fn iterations(&self) -> u32 {
+        Pbkdf2Secret::iterations(self)
+    }
+    fn keys<S: ScramProvider>(&self) -> Result<(Vec<u8>, Vec<u8>), InvalidLength> {
+        let client_key = S::hmac(b"Client Key", self.digest())?;
+        let stored_key = S::hash(&client_key);
+        let server_key = S::hmac(b"Server Key", self.digest())?;
+        Ok((stored_key, server_key))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
 pub struct Plain(pub String);
 
 impl Secret for Plain {}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// A token for the HT-* mechanisms, as stored by the server which issued it.
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct Token(pub String);
+
+impl Secret for Token {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
 pub struct Pbkdf2Sha1 {
     pub salt: Vec<u8>,
     pub iterations: u32,
@@ -50,7 +86,7 @@ impl Pbkdf2Secret for Pbkdf2Sha1 {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
 pub struct Pbkdf2Sha256 {
     pub salt: Vec<u8>,
     pub iterations: u32,
@@ -89,3 +125,52 @@ impl Pbkdf2Secret for Pbkdf2Sha256 {
         &self.digest
     }
 }
+
+/// The keys RFC 5802 recommends servers to store, instead of the salted password.
+///
+/// They can’t be used to impersonate the user to another server with the same salt and
+/// iteration count, unlike `Pbkdf2Sha1` or `Pbkdf2Sha256`.
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct ScramStoredKey {
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+}
+
+impl ScramStoredKey {
+    /// Derives the keys from a password, for the hash function `S`.
+    #[cfg(feature = "scram")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+    pub fn derive<S: ScramProvider>(
+        password: &str,
+        salt: &[u8],
+        iterations: u32,
+    ) -> Result<ScramStoredKey, DeriveError> {
+        use crate::common::Password;
+        let salted_password = S::derive(&Password::Plain(password.to_owned()), salt, iterations)?;
+        let client_key = S::hmac(b"Client Key", &salted_password)?;
+        let server_key = S::hmac(b"Server Key", &salted_password)?;
+        Ok(ScramStoredKey {
+            stored_key: S::hash(&client_key),
+            server_key,
+            salt: salt.to_vec(),
+            iterations,
+        })
+    }
+}
+
+impl Secret for ScramStoredKey {}
+
+#[cfg(feature = "scram")]
+impl ScramSecret for ScramStoredKey {
+    fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+    fn iterations(&self) -> u32 {
+        self.iterations
+    }
+    fn keys<S: ScramProvider>(&self) -> Result<(Vec<u8>, Vec<u8>), InvalidLength> {
+        Ok((self.stored_key.clone(), self.server_key.clone()))
+    }
+}
@@ -0,0 +1,173 @@
+use std::marker::PhantomData;
+
+use subtle::ConstantTimeEq;
+
+use crate::common::scram::ScramProvider;
+use crate::common::{ht_name, ChannelBinding, Identity};
+use crate::secret;
+use crate::server::{Mechanism, MechanismError, Provider, Response, Throttle, Throttling};
+
+pub struct Ht<S, P>
+where
+    S: ScramProvider,
+    P: Provider<secret::Token>,
+{
+    name: String,
+    channel_binding: ChannelBinding,
+    provider: P,
+    done: bool,
+    throttling: Throttling,
+    _marker: PhantomData<S>,
+}
+
+impl<S, P> Ht<S, P>
+where
+    S: ScramProvider,
+    P: Provider<secret::Token>,
+{
+    pub fn new(provider: P, channel_binding: ChannelBinding) -> Ht<S, P> {
+        Ht {
+            name: ht_name(S::name(), &channel_binding),
+            channel_binding,
+            provider,
+            done: false,
+            throttling: Throttling::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consults this throttle before checking any token.
+    pub fn with_throttle<T: Throttle + Send + 'static>(mut self, throttle: T) -> Ht<S, P> {
+        self.throttling = Throttling::new(throttle);
+        self
+    }
+}
+
+impl<S, P> Mechanism for Ht<S, P>
+where
+    S: ScramProvider,
+    P: Provider<secret::Token>,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn respond(&mut self, payload: &[u8]) -> Result<Response, MechanismError> {
+        if self.done {
+            return Err(MechanismError::SaslSessionAlreadyOver);
+        }
+        self.done = true;
+        let idx = payload
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(MechanismError::NoUsernameSpecified)?;
+        let username = String::from_utf8(payload[..idx].to_vec())
+            .map_err(|_| MechanismError::ErrorDecodingUsername)?;
+        let sent_hashed_token = &payload[idx + 1..];
+        let identity = Identity::Username(username);
+        self.throttling.check(&identity)?;
+        let secret::Token(ref token) = self
+            .provider
+            .provide(&identity)
+            .map_err(|err| self.throttling.failed(&identity, err.into()))?;
+        let mut initiator = b"Initiator".to_vec();
+        initiator.extend(self.channel_binding.data());
+        let expected_hashed_token = S::hmac(&initiator, token.as_bytes())?;
+        if !bool::from(expected_hashed_token.ct_eq(sent_hashed_token)) {
+            return Err(self
+                .throttling
+                .failed(&identity, MechanismError::AuthenticationFailed));
+        }
+        self.throttling.succeeded(&identity);
+        let mut responder = b"Responder".to_vec();
+        responder.extend(self.channel_binding.data());
+        let responder_hashed_token = S::hmac(&responder, token.as_bytes())?;
+        Ok(Response::Success(identity, responder_hashed_token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mechanisms::Ht as ClientHt;
+    use crate::client::{Mechanism as ClientMechanism, MechanismError as ClientMechanismError};
+    use crate::common::scram::Sha256;
+    use crate::common::Credentials;
+    use crate::server::{ProviderError, Validator, ValidatorError};
+
+    struct MyProvider;
+
+    impl Provider<secret::Token> for MyProvider {
+        fn provide(&self, identity: &Identity) -> Result<secret::Token, ProviderError> {
+            if identity != &Identity::Username("user".to_owned()) {
+                return Err(ProviderError::AuthenticationFailed);
+            }
+            Ok(secret::Token("s3cr3tt0k3n".to_owned()))
+        }
+    }
+
+    impl Validator<secret::Token> for MyProvider {
+        fn validate(
+            &self,
+            _identity: &Identity,
+            _value: &secret::Token,
+        ) -> Result<(), ValidatorError> {
+            unreachable!()
+        }
+    }
+
+    fn exchange(
+        token: &str,
+        client_binding: ChannelBinding,
+        server_binding: ChannelBinding,
+    ) -> Result<Identity, MechanismError> {
+        let creds = Credentials::default()
+            .with_username("user")
+            .with_password(token)
+            .with_channel_binding(client_binding);
+        let mut client = ClientHt::<Sha256>::from_credentials(creds).unwrap();
+        let mut server = Ht::<Sha256, _>::new(MyProvider, server_binding);
+        assert_eq!(client.name(), server.name());
+        match server.respond(&client.initial())? {
+            Response::Success(identity, data) => {
+                client.success(&data).unwrap();
+                Ok(identity)
+            }
+            Response::Proceed(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn ht_works() {
+        let identity = exchange("s3cr3tt0k3n", ChannelBinding::None, ChannelBinding::None).unwrap();
+        assert_eq!(identity, Identity::Username("user".to_owned()));
+
+        let server = Ht::<Sha256, _>::new(MyProvider, ChannelBinding::TlsExporter(vec![1, 2, 3]));
+        assert_eq!(server.name(), "HT-SHA-256-EXPR");
+        exchange(
+            "s3cr3tt0k3n",
+            ChannelBinding::TlsExporter(vec![1, 2, 3]),
+            ChannelBinding::TlsExporter(vec![1, 2, 3]),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn ht_fails() {
+        let err = exchange("wrong", ChannelBinding::None, ChannelBinding::None).unwrap_err();
+        assert_eq!(err, MechanismError::AuthenticationFailed);
+
+        let err = exchange(
+            "s3cr3tt0k3n",
+            ChannelBinding::TlsUnique(vec![1, 2, 3]),
+            ChannelBinding::TlsUnique(vec![3, 2, 1]),
+        )
+        .unwrap_err();
+        assert_eq!(err, MechanismError::AuthenticationFailed);
+
+        let mut client =
+            ClientHt::<Sha256>::new("user", "s3cr3tt0k3n", ChannelBinding::None).unwrap();
+        let err = client.success(b"forged").unwrap_err();
+        assert_eq!(err, ClientMechanismError::InvalidSignatureInSuccessResponse);
+    }
+}
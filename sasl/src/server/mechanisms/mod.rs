@@ -1,5 +1,7 @@
 #[cfg(feature = "anonymous")]
 mod anonymous;
+#[cfg(feature = "scram")]
+mod ht;
 mod plain;
 #[cfg(feature = "scram")]
 mod scram;
@@ -7,6 +9,9 @@ mod scram;
 #[cfg(feature = "anonymous")]
 #[cfg_attr(docsrs, doc(cfg(feature = "anonymous")))]
 pub use self::anonymous::Anonymous;
+#[cfg(feature = "scram")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+pub use self::ht::Ht;
 pub use self::plain::Plain;
 #[cfg(feature = "scram")]
 #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
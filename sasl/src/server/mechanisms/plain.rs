@@ -1,17 +1,25 @@
 use crate::common::Identity;
 use crate::secret;
-use crate::server::{Mechanism, MechanismError, Response, Validator};
+use crate::server::{Mechanism, MechanismError, Response, Throttle, Throttling, Validator};
 
 pub struct Plain<V: Validator<secret::Plain>> {
     validator: V,
+    throttling: Throttling,
 }
 
 impl<V: Validator<secret::Plain>> Plain<V> {
     pub fn new(validator: V) -> Plain<V> {
         Plain {
             validator: validator,
+            throttling: Throttling::default(),
         }
     }
+
+    /// Consults this throttle before validating any password.
+    pub fn with_throttle<T: Throttle + Send + 'static>(mut self, throttle: T) -> Plain<V> {
+        self.throttling = Throttling::new(throttle);
+        self
+    }
 }
 
 impl<V: Validator<secret::Plain>> Mechanism for Plain<V> {
@@ -33,7 +41,11 @@ impl<V: Validator<secret::Plain>> Mechanism for Plain<V> {
         let password = String::from_utf8(password.to_vec())
             .map_err(|_| MechanismError::ErrorDecodingPassword)?;
         let ident = Identity::Username(username);
-        self.validator.validate(&ident, &secret::Plain(password))?;
+        self.throttling.check(&ident)?;
+        if let Err(err) = self.validator.validate(&ident, &secret::Plain(password)) {
+            return Err(self.throttling.failed(&ident, err.into()));
+        }
+        self.throttling.succeeded(&ident);
         Ok(Response::Success(ident, Vec::new()))
     }
 }
@@ -1,61 +1,116 @@
 use std::marker::PhantomData;
 
 use base64::{engine::general_purpose::STANDARD as Base64, Engine};
+use stringprep::saslprep;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
 
 use crate::common::scram::{generate_nonce, ScramProvider};
 use crate::common::{parse_frame, xor, ChannelBinding, Identity};
-use crate::secret;
-use crate::secret::Pbkdf2Secret;
-use crate::server::{Mechanism, MechanismError, Provider, Response};
+use crate::secret::{ScramSecret, ScramStoredKey};
+use crate::server::{Mechanism, MechanismError, Provider, Response, Throttle, Throttling};
 
 enum ScramState {
     Init,
     SentChallenge {
         initial_client_message: Vec<u8>,
         initial_server_message: Vec<u8>,
-        gs2_header: Vec<u8>,
+        cb_data: Vec<u8>,
         server_nonce: String,
         identity: Identity,
-        salted_password: Vec<u8>,
+        stored_key: Zeroizing<Vec<u8>>,
+        server_key: Zeroizing<Vec<u8>>,
     },
     Done,
 }
 
-pub struct Scram<S, P>
+/// The server side of the SASL SCRAM-* and SCRAM-*-PLUS mechanisms.
+///
+/// By default the provider gives the secret type of the `ScramProvider`, use
+/// `with_stored_keys` to authenticate from `ScramStoredKey` instead.
+///
+/// This is the SCRAM-* mechanism, which refuses channel binding, use `plus`
+/// to get the SCRAM-*-PLUS one, which requires it. When the connection has
+/// channel binding data, the SCRAM-* mechanism takes it as the -PLUS one
+/// being offered too, and refuses clients which would have used it (RFC 5802
+/// §6).
+pub struct Scram<S, P, K = <S as ScramProvider>::Secret>
 where
     S: ScramProvider,
-    P: Provider<S::Secret>,
-    S::Secret: secret::Pbkdf2Secret,
+    P: Provider<K>,
+    K: ScramSecret,
 {
     name: String,
     state: ScramState,
     channel_binding: ChannelBinding,
+    plus: bool,
     provider: P,
-    _marker: PhantomData<S>,
+    throttling: Throttling,
+    _marker: PhantomData<(S, K)>,
 }
 
 impl<S, P> Scram<S, P>
 where
     S: ScramProvider,
     P: Provider<S::Secret>,
-    S::Secret: secret::Pbkdf2Secret,
+    S::Secret: ScramSecret,
 {
     pub fn new(provider: P, channel_binding: ChannelBinding) -> Scram<S, P> {
+        Scram::new_inner(provider, channel_binding)
+    }
+}
+
+impl<S, P> Scram<S, P, ScramStoredKey>
+where
+    S: ScramProvider,
+    P: Provider<ScramStoredKey>,
+{
+    pub fn with_stored_keys(
+        provider: P,
+        channel_binding: ChannelBinding,
+    ) -> Scram<S, P, ScramStoredKey> {
+        Scram::new_inner(provider, channel_binding)
+    }
+}
+
+impl<S, P, K> Scram<S, P, K>
+where
+    S: ScramProvider,
+    P: Provider<K>,
+    K: ScramSecret,
+{
+    fn new_inner(provider: P, channel_binding: ChannelBinding) -> Scram<S, P, K> {
         Scram {
             name: format!("SCRAM-{}", S::name()),
             state: ScramState::Init,
             channel_binding: channel_binding,
+            plus: false,
             provider: provider,
+            throttling: Throttling::default(),
             _marker: PhantomData,
         }
     }
+
+    /// Turns this into the SCRAM-*-PLUS mechanism, which only accepts clients
+    /// binding to the channel binding data given at creation.
+    pub fn plus(mut self) -> Scram<S, P, K> {
+        self.name = format!("SCRAM-{}-PLUS", S::name());
+        self.plus = true;
+        self
+    }
+
+    /// Consults this throttle before checking the proof of any identity.
+    pub fn with_throttle<T: Throttle + Send + 'static>(mut self, throttle: T) -> Scram<S, P, K> {
+        self.throttling = Throttling::new(throttle);
+        self
+    }
 }
 
-impl<S, P> Mechanism for Scram<S, P>
+impl<S, P, K> Mechanism for Scram<S, P, K>
 where
     S: ScramProvider,
-    P: Provider<S::Secret>,
-    S::Secret: secret::Pbkdf2Secret,
+    P: Provider<K>,
+    K: ScramSecret,
 {
     fn name(&self) -> &str {
         &self.name
@@ -84,92 +139,135 @@ where
                 if commas < 2 {
                     return Err(MechanismError::FailedToDecodeMessage);
                 }
-                let gs2_header = payload[..idx].to_vec();
+                let gs2_header = &payload[..idx];
                 let rest = payload[idx..].to_vec();
-                // TODO: process gs2 header properly, not this ugly stuff
-                match self.channel_binding {
-                    ChannelBinding::None | ChannelBinding::Unsupported => {
-                        // Not supported.
-                        if gs2_header[0] != 0x79 {
-                            // ord("y")
-                            return Err(MechanismError::ChannelBindingNotSupported);
-                        }
+                let cbind_flag = gs2_header
+                    .split(|&b| b == b',')
+                    .next()
+                    .ok_or(MechanismError::FailedToDecodeMessage)?;
+                let cb_data = match cbind_flag {
+                    b"n" | b"y" if self.plus => {
+                        return Err(MechanismError::ChannelBindingRequired);
                     }
-                    ref other => {
-                        // Supported.
-                        if gs2_header[0] == 0x79 {
-                            // ord("y")
+                    b"n" => gs2_header.to_vec(),
+                    b"y" => {
+                        // The client supports channel binding but believes we don’t, which
+                        // means the -PLUS mechanism was stripped from what we advertised.
+                        if let ChannelBinding::TlsUnique(_) | ChannelBinding::TlsExporter(_) =
+                            self.channel_binding
+                        {
                             return Err(MechanismError::ChannelBindingIsSupported);
-                        } else if !other.supports("tls-unique") {
-                            // TODO: grab the data
-                            return Err(MechanismError::ChannelBindingMechanismIncorrect);
                         }
+                        gs2_header.to_vec()
                     }
-                }
+                    _ if cbind_flag.starts_with(b"p=") => {
+                        let cb_name = std::str::from_utf8(&cbind_flag[2..])
+                            .map_err(|_| MechanismError::FailedToDecodeMessage)?;
+                        match self.channel_binding {
+                            _ if !self.plus => {
+                                return Err(MechanismError::ChannelBindingNotSupported);
+                            }
+                            ChannelBinding::None | ChannelBinding::Unsupported => {
+                                return Err(MechanismError::ChannelBindingNotSupported);
+                            }
+                            ref other if !other.supports(cb_name) => {
+                                return Err(MechanismError::ChannelBindingMechanismIncorrect);
+                            }
+                            ref other => {
+                                let mut cb_data = gs2_header.to_vec();
+                                cb_data.extend(other.data());
+                                cb_data
+                            }
+                        }
+                    }
+                    _ => return Err(MechanismError::FailedToDecodeMessage),
+                };
                 let frame =
                     parse_frame(&rest).map_err(|_| MechanismError::CannotDecodeInitialMessage)?;
                 let username = frame.get("n").ok_or_else(|| MechanismError::NoUsername)?;
-                let identity = Identity::Username(username.to_owned());
+                let username =
+                    saslprep(username).map_err(|_| MechanismError::ErrorDecodingUsername)?;
+                let identity = Identity::Username(username.into_owned());
                 let client_nonce = frame.get("r").ok_or_else(|| MechanismError::NoNonce)?;
                 let mut server_nonce = String::new();
                 server_nonce += client_nonce;
                 server_nonce +=
                     &generate_nonce().map_err(|_| MechanismError::FailedToGenerateNonce)?;
-                let pbkdf2 = self.provider.provide(&identity)?;
+                self.throttling.check(&identity)?;
+                let secret = self
+                    .provider
+                    .provide(&identity)
+                    .map_err(|err| self.throttling.failed(&identity, err.into()))?;
+                let (stored_key, server_key) = secret.keys::<S>()?;
                 let mut buf = Vec::new();
                 buf.extend(b"r=");
                 buf.extend(server_nonce.bytes());
                 buf.extend(b",s=");
-                buf.extend(Base64.encode(pbkdf2.salt()).bytes());
+                buf.extend(Base64.encode(secret.salt()).bytes());
                 buf.extend(b",i=");
-                buf.extend(pbkdf2.iterations().to_string().bytes());
+                buf.extend(secret.iterations().to_string().bytes());
                 ret = Response::Proceed(buf.clone());
                 next_state = ScramState::SentChallenge {
                     server_nonce: server_nonce,
                     identity: identity,
-                    salted_password: pbkdf2.digest().to_vec(),
+                    stored_key: Zeroizing::new(stored_key),
+                    server_key: Zeroizing::new(server_key),
                     initial_client_message: rest,
                     initial_server_message: buf,
-                    gs2_header: gs2_header,
+                    cb_data,
                 };
             }
             ScramState::SentChallenge {
                 ref server_nonce,
                 ref identity,
-                ref salted_password,
-                ref gs2_header,
+                ref stored_key,
+                ref server_key,
+                ref cb_data,
                 ref initial_client_message,
                 ref initial_server_message,
             } => {
                 let frame =
                     parse_frame(payload).map_err(|_| MechanismError::CannotDecodeResponse)?;
-                let mut cb_data: Vec<u8> = Vec::new();
-                cb_data.extend(gs2_header);
-                cb_data.extend(self.channel_binding.data());
+                let sent_cb_data = frame.get("c").ok_or(MechanismError::CannotDecodeResponse)?;
+                let sent_cb_data = Base64
+                    .decode(sent_cb_data)
+                    .map_err(|_| MechanismError::CannotDecodeResponse)?;
+                if !bool::from(sent_cb_data.ct_eq(cb_data)) {
+                    return Err(MechanismError::ChannelBindingMismatch);
+                }
+                if frame.get("r") != Some(server_nonce) {
+                    return Err(MechanismError::NonceMismatch);
+                }
                 let mut client_final_message_bare = Vec::new();
                 client_final_message_bare.extend(b"c=");
-                client_final_message_bare.extend(Base64.encode(&cb_data).bytes());
+                client_final_message_bare.extend(Base64.encode(cb_data).bytes());
                 client_final_message_bare.extend(b",r=");
                 client_final_message_bare.extend(server_nonce.bytes());
-                let client_key = S::hmac(b"Client Key", &salted_password)?;
-                let server_key = S::hmac(b"Server Key", &salted_password)?;
                 let mut auth_message = Vec::new();
                 auth_message.extend(initial_client_message);
                 auth_message.extend(b",");
                 auth_message.extend(initial_server_message);
                 auth_message.extend(b",");
                 auth_message.extend(client_final_message_bare.clone());
-                let stored_key = S::hash(&client_key);
-                let client_signature = S::hmac(&auth_message, &stored_key)?;
-                let client_proof = xor(&client_key, &client_signature);
+                let client_signature = S::hmac(&auth_message, stored_key)?;
                 let sent_proof = frame.get("p").ok_or_else(|| MechanismError::NoProof)?;
                 let sent_proof = Base64
                     .decode(sent_proof)
                     .map_err(|_| MechanismError::CannotDecodeProof)?;
-                if client_proof != sent_proof {
-                    return Err(MechanismError::AuthenticationFailed);
+                if sent_proof.len() != client_signature.len() {
+                    return Err(self
+                        .throttling
+                        .failed(identity, MechanismError::AuthenticationFailed));
+                }
+                // Recover the ClientKey from the proof, and check it matches the StoredKey.
+                let client_key = Zeroizing::new(xor(&sent_proof, &client_signature));
+                if !bool::from(S::hash(&client_key).ct_eq(stored_key)) {
+                    return Err(self
+                        .throttling
+                        .failed(identity, MechanismError::AuthenticationFailed));
                 }
-                let server_signature = S::hmac(&auth_message, &server_key)?;
+                self.throttling.succeeded(identity);
+                let server_signature = S::hmac(&auth_message, server_key)?;
                 let mut buf = Vec::new();
                 buf.extend(b"v=");
                 buf.extend(Base64.encode(&server_signature).bytes());
@@ -184,3 +282,280 @@ where
         Ok(ret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mechanisms::Scram as ClientScram;
+    use crate::client::Mechanism as ClientMechanism;
+    use crate::common::scram::Sha256;
+    use crate::common::{Credentials, Password};
+    use crate::secret;
+    use crate::server::{ProviderError, Validator, ValidatorError};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const SALT: [u8; 8] = [35, 71, 92, 105, 212, 219, 114, 93];
+
+    struct MyProvider;
+
+    impl Provider<secret::Pbkdf2Sha256> for MyProvider {
+        fn provide(&self, identity: &Identity) -> Result<secret::Pbkdf2Sha256, ProviderError> {
+            if identity != &Identity::Username("user".to_owned()) {
+                return Err(ProviderError::AuthenticationFailed);
+            }
+            let digest = Sha256::derive(&Password::Plain("pencil".to_owned()), &SALT, 4096)?;
+            Ok(secret::Pbkdf2Sha256 {
+                salt: SALT.to_vec(),
+                iterations: 4096,
+                digest,
+            })
+        }
+    }
+
+    impl Validator<secret::Pbkdf2Sha256> for MyProvider {
+        fn validate(
+            &self,
+            _identity: &Identity,
+            _value: &secret::Pbkdf2Sha256,
+        ) -> Result<(), ValidatorError> {
+            unreachable!()
+        }
+    }
+
+    fn exchange(
+        client_binding: ChannelBinding,
+        server_binding: ChannelBinding,
+        plus: bool,
+    ) -> Result<Identity, MechanismError> {
+        let creds = Credentials::default()
+            .with_username("user")
+            .with_password("pencil")
+            .with_channel_binding(client_binding);
+        let mut client = ClientScram::<Sha256>::from_credentials(creds).unwrap();
+        let mut server = Scram::<Sha256, _>::new(MyProvider, server_binding);
+        if plus {
+            server = server.plus();
+        }
+        let challenge = match server.respond(&client.initial())? {
+            Response::Proceed(challenge) => challenge,
+            _ => panic!(),
+        };
+        let response = client.response(&challenge).unwrap();
+        match server.respond(&response)? {
+            Response::Success(identity, data) => {
+                client.success(&data).unwrap();
+                Ok(identity)
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn tls_exporter() {
+        let server = Scram::<Sha256, _>::new(MyProvider, ChannelBinding::TlsExporter(vec![1]));
+        assert_eq!(server.name(), "SCRAM-SHA-256");
+        assert_eq!(server.plus().name(), "SCRAM-SHA-256-PLUS");
+        let identity = exchange(
+            ChannelBinding::TlsExporter(vec![1, 2, 3]),
+            ChannelBinding::TlsExporter(vec![1, 2, 3]),
+            true,
+        )
+        .unwrap();
+        assert_eq!(identity, Identity::Username("user".to_owned()));
+    }
+
+    #[test]
+    fn without_channel_binding() {
+        exchange(ChannelBinding::None, ChannelBinding::None, false).unwrap();
+        exchange(ChannelBinding::Unsupported, ChannelBinding::None, false).unwrap();
+        exchange(
+            ChannelBinding::None,
+            ChannelBinding::TlsExporter(vec![1]),
+            false,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn channel_binding_mismatch() {
+        let err = exchange(
+            ChannelBinding::TlsExporter(vec![1, 2, 3]),
+            ChannelBinding::TlsExporter(vec![3, 2, 1]),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(err, MechanismError::ChannelBindingMismatch);
+
+        let err = exchange(
+            ChannelBinding::TlsUnique(vec![1, 2, 3]),
+            ChannelBinding::TlsExporter(vec![1, 2, 3]),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(err, MechanismError::ChannelBindingMechanismIncorrect);
+
+        let err = exchange(
+            ChannelBinding::TlsExporter(vec![1]),
+            ChannelBinding::None,
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(err, MechanismError::ChannelBindingNotSupported);
+
+        // Binding to the channel is only done by the -PLUS mechanism
+        let err = exchange(
+            ChannelBinding::TlsExporter(vec![1]),
+            ChannelBinding::TlsExporter(vec![1]),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, MechanismError::ChannelBindingNotSupported);
+    }
+
+    #[test]
+    fn downgrade() {
+        let err = exchange(
+            ChannelBinding::Unsupported,
+            ChannelBinding::TlsExporter(vec![1]),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, MechanismError::ChannelBindingIsSupported);
+
+        // The -PLUS mechanism can’t be used without channel binding
+        for client_binding in [ChannelBinding::None, ChannelBinding::Unsupported] {
+            let err =
+                exchange(client_binding, ChannelBinding::TlsExporter(vec![1]), true).unwrap_err();
+            assert_eq!(err, MechanismError::ChannelBindingRequired);
+        }
+    }
+
+    #[test]
+    fn saslprep() {
+        let creds = Credentials::default()
+            .with_username("user")
+            .with_password("pen\u{AD}cil");
+        let mut client = ClientScram::<Sha256>::from_credentials(creds).unwrap();
+        let mut server = Scram::<Sha256, _>::new(MyProvider, ChannelBinding::None);
+        let challenge = match server.respond(&client.initial()).unwrap() {
+            Response::Proceed(challenge) => challenge,
+            _ => panic!(),
+        };
+        let response = client.response(&challenge).unwrap();
+        assert!(matches!(
+            server.respond(&response),
+            Ok(Response::Success(_, _))
+        ));
+
+        let mut server = Scram::<Sha256, _>::new(MyProvider, ChannelBinding::None);
+        server.respond("n,,n=u\u{AD}ser,r=abcd".as_bytes()).unwrap();
+
+        let mut server = Scram::<Sha256, _>::new(MyProvider, ChannelBinding::None);
+        let err = server.respond("n,,n=user\u{7},r=abcd".as_bytes());
+        assert_eq!(err, Err(MechanismError::ErrorDecodingUsername));
+    }
+
+    struct StoredKeyProvider;
+
+    impl Provider<ScramStoredKey> for StoredKeyProvider {
+        fn provide(&self, identity: &Identity) -> Result<ScramStoredKey, ProviderError> {
+            if identity != &Identity::Username("user".to_owned()) {
+                return Err(ProviderError::AuthenticationFailed);
+            }
+            Ok(ScramStoredKey::derive::<Sha256>("pencil", &SALT, 4096)?)
+        }
+    }
+
+    impl Validator<ScramStoredKey> for StoredKeyProvider {
+        fn validate(
+            &self,
+            _identity: &Identity,
+            _value: &ScramStoredKey,
+        ) -> Result<(), ValidatorError> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn stored_keys() {
+        for (password, success) in [("pencil", true), ("crayon", false)] {
+            let creds = Credentials::default()
+                .with_username("user")
+                .with_password(password);
+            let mut client = ClientScram::<Sha256>::from_credentials(creds).unwrap();
+            let mut server =
+                Scram::<Sha256, _, _>::with_stored_keys(StoredKeyProvider, ChannelBinding::None);
+            let challenge = match server.respond(&client.initial()).unwrap() {
+                Response::Proceed(challenge) => challenge,
+                _ => panic!(),
+            };
+            let response = client.response(&challenge).unwrap();
+            match server.respond(&response) {
+                Ok(Response::Success(_, data)) => {
+                    assert!(success);
+                    client.success(&data).unwrap();
+                }
+                Err(err) => {
+                    assert!(!success);
+                    assert_eq!(err, MechanismError::AuthenticationFailed);
+                }
+                _ => panic!(),
+            }
+        }
+    }
+
+    /// Locks every identity out after two failures.
+    struct Lockout(Arc<AtomicUsize>);
+
+    impl Throttle for Lockout {
+        fn allow(&self, _identity: &Identity) -> bool {
+            self.0.load(Ordering::SeqCst) < 2
+        }
+
+        fn record(&self, _identity: &Identity, success: bool) {
+            if !success {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[test]
+    fn throttle() {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let attempt = |username: &str, password: &str| {
+            let creds = Credentials::default()
+                .with_username(username)
+                .with_password(password);
+            let mut client = ClientScram::<Sha256>::from_credentials(creds).unwrap();
+            let mut server =
+                Scram::<Sha256, _, _>::with_stored_keys(StoredKeyProvider, ChannelBinding::None)
+                    .with_throttle(Lockout(failures.clone()));
+            let challenge = match server.respond(&client.initial())? {
+                Response::Proceed(challenge) => challenge,
+                _ => panic!(),
+            };
+            let response = client.response(&challenge).unwrap();
+            server.respond(&response)
+        };
+        assert!(matches!(
+            attempt("user", "pencil"),
+            Ok(Response::Success(_, _))
+        ));
+        assert_eq!(
+            attempt("user", "crayon"),
+            Err(MechanismError::AuthenticationFailed)
+        );
+        assert_eq!(
+            attempt("nobody", "pencil"),
+            Err(MechanismError::ProviderError(
+                ProviderError::AuthenticationFailed
+            ))
+        );
+        assert_eq!(
+            attempt("user", "pencil"),
+            Err(MechanismError::TemporaryAuthFailure)
+        );
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+    }
+}
@@ -32,6 +32,56 @@ pub trait Validator<S: Secret> {
     fn validate(&self, identity: &Identity, value: &S) -> Result<(), ValidatorError>;
 }
 
+/// A policy against brute-force attacks, such as a rate limit or a lockout
+/// after too many failures.
+///
+/// Mechanisms given one with `with_throttle` consult it before checking the
+/// credentials of an identity, and fail with
+/// [`MechanismError::TemporaryAuthFailure`] when it refuses, then report the
+/// outcome of the check.  A mechanism is created per authentication attempt,
+/// so the throttle can also know the source of the attempt, e.g. the address
+/// of the client, and apply its limits per source.
+pub trait Throttle {
+    /// Whether this identity may attempt to authenticate now.
+    fn allow(&self, identity: &Identity) -> bool;
+
+    /// Reports whether the credentials given for this identity were valid.
+    fn record(&self, identity: &Identity, success: bool);
+}
+
+/// The throttle of a mechanism, if any.
+#[derive(Default)]
+pub(crate) struct Throttling(Option<Box<dyn Throttle + Send>>);
+
+impl Throttling {
+    pub(crate) fn new<T: Throttle + Send + 'static>(throttle: T) -> Throttling {
+        Throttling(Some(Box::new(throttle)))
+    }
+
+    pub(crate) fn check(&self, identity: &Identity) -> Result<(), MechanismError> {
+        match self.0 {
+            Some(ref throttle) if !throttle.allow(identity) => {
+                Err(MechanismError::TemporaryAuthFailure)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a failed check of the credentials, and returns its error.
+    pub(crate) fn failed(&self, identity: &Identity, err: MechanismError) -> MechanismError {
+        if let Some(ref throttle) = self.0 {
+            throttle.record(identity, false);
+        }
+        err
+    }
+
+    pub(crate) fn succeeded(&self, identity: &Identity) {
+        if let Some(ref throttle) = self.0 {
+            throttle.record(identity, true);
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ProviderError {
     AuthenticationFailed,
@@ -58,6 +108,7 @@ pub enum MechanismError {
     ChannelBindingNotSupported,
     ChannelBindingIsSupported,
     ChannelBindingMechanismIncorrect,
+    ChannelBindingRequired,
     CannotDecodeInitialMessage,
     NoUsername,
     NoNonce,
@@ -65,6 +116,8 @@ pub enum MechanismError {
     ProviderError(ProviderError),
 
     CannotDecodeResponse,
+    ChannelBindingMismatch,
+    NonceMismatch,
     #[cfg(feature = "scram")]
     #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
     InvalidKeyLength(hmac::digest::InvalidLength),
@@ -75,6 +128,7 @@ pub enum MechanismError {
     CannotDecodeProof,
     AuthenticationFailed,
     SaslSessionAlreadyOver,
+    TemporaryAuthFailure,
 }
 
 #[cfg(feature = "scram")]
@@ -150,6 +204,9 @@ impl fmt::Display for MechanismError {
             MechanismError::ChannelBindingMechanismIncorrect => {
                 write!(fmt, "channel binding mechanism is incorrect")
             }
+            MechanismError::ChannelBindingRequired => {
+                write!(fmt, "channel binding is required by this mechanism")
+            }
             MechanismError::CannotDecodeInitialMessage => {
                 write!(fmt, "can’t decode initial message")
             }
@@ -159,6 +216,10 @@ impl fmt::Display for MechanismError {
             MechanismError::ProviderError(err) => write!(fmt, "provider error: {}", err),
 
             MechanismError::CannotDecodeResponse => write!(fmt, "can’t decode response"),
+            MechanismError::ChannelBindingMismatch => {
+                write!(fmt, "channel binding data doesn’t match")
+            }
+            MechanismError::NonceMismatch => write!(fmt, "nonce doesn’t match"),
             #[cfg(feature = "scram")]
             MechanismError::InvalidKeyLength(err) => write!(fmt, "invalid key length: {}", err),
             #[cfg(any(feature = "scram", feature = "anonymous"))]
@@ -169,6 +230,9 @@ impl fmt::Display for MechanismError {
             MechanismError::CannotDecodeProof => write!(fmt, "can’t decode proof"),
             MechanismError::AuthenticationFailed => write!(fmt, "authentication failed"),
             MechanismError::SaslSessionAlreadyOver => write!(fmt, "SASL session already over"),
+            MechanismError::TemporaryAuthFailure => {
+                write!(fmt, "too many attempts, try again later")
+            }
         }
     }
 }
@@ -3,7 +3,7 @@ use std::env::args;
 use std::process::exit;
 use std::str::FromStr;
 use tokio_xmpp::AsyncClient as Client;
-use xmpp_parsers::message::{Body, Message, MessageType};
+use xmpp_parsers::message::{Message, MessageType};
 use xmpp_parsers::presence::{Presence, Show as PresenceShow, Type as PresenceType};
 use xmpp_parsers::{BareJid, Element, Jid};
 
@@ -69,17 +69,15 @@ async fn main() {
 
 // Construct a <presence/>
 fn make_presence() -> Element {
-    let mut presence = Presence::new(PresenceType::None);
-    presence.show = Some(PresenceShow::Chat);
-    presence
-        .statuses
-        .insert(String::from("en"), String::from("Echoing messages."));
-    presence.into()
+    Presence::new(PresenceType::None)
+        .with_show(PresenceShow::Chat)
+        .with_status("en", "Echoing messages.")
+        .into()
 }
 
 // Construct a chat <message/>
 fn make_reply(to: Jid, body: &str) -> Element {
-    let mut message = Message::new(Some(to));
-    message.bodies.insert(String::new(), Body(body.to_owned()));
-    message.into()
+    Message::chat(to)
+        .with_body(String::new(), body.to_owned())
+        .into()
 }
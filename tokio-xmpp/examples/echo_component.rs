@@ -3,7 +3,7 @@ use std::env::args;
 use std::process::exit;
 use std::str::FromStr;
 use tokio_xmpp::tcp::TcpComponent as Component;
-use xmpp_parsers::message::{Body, Message, MessageType};
+use xmpp_parsers::message::{Message, MessageType};
 use xmpp_parsers::presence::{Presence, Show as PresenceShow, Type as PresenceType};
 use xmpp_parsers::{Element, Jid};
 
@@ -62,19 +62,17 @@ async fn main() {
 
 // Construct a <presence/>
 fn make_presence(from: Jid, to: Jid) -> Element {
-    let mut presence = Presence::new(PresenceType::None);
-    presence.from = Some(from);
-    presence.to = Some(to);
-    presence.show = Some(PresenceShow::Chat);
-    presence
-        .statuses
-        .insert(String::from("en"), String::from("Echoing messages."));
-    presence.into()
+    Presence::new(PresenceType::None)
+        .with_from(from)
+        .with_to(to)
+        .with_show(PresenceShow::Chat)
+        .with_status("en", "Echoing messages.")
+        .into()
 }
 
 // Construct a chat <message/>
 fn make_reply(to: Jid, body: &str) -> Element {
-    let mut message = Message::new(Some(to));
-    message.bodies.insert(String::new(), Body(body.to_owned()));
-    message.into()
+    Message::chat(to)
+        .with_body(String::new(), body.to_owned())
+        .into()
 }
@@ -3,7 +3,7 @@ use std::io::{stdin, Read};
 use std::process::exit;
 use std::str::FromStr;
 use tokio_xmpp::SimpleClient as Client;
-use xmpp_parsers::message::{Body, Message};
+use xmpp_parsers::message::Message;
 use xmpp_parsers::Jid;
 
 #[tokio::main]
@@ -29,8 +29,7 @@ async fn main() {
     stdin().lock().read_to_string(&mut body).unwrap();
 
     // Send message
-    let mut message = Message::new(Some(recipient));
-    message.bodies.insert(String::new(), Body(body.to_owned()));
+    let message = Message::chat(recipient).with_body(String::new(), body);
     client.send_stanza(message).await.unwrap();
 
     // Close client connection
@@ -1,16 +1,31 @@
 use futures::{sink::SinkExt, task::Poll, Future, Sink, Stream};
 use std::mem::replace;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
-use tokio::task::JoinHandle;
-use xmpp_parsers::{ns, Element, Jid};
+use std::time::Duration;
+use xmpp_parsers::{
+    iq::Iq,
+    ns,
+    sm::R,
+    stream_error::{DefinedCondition, StreamError},
+    Element, Jid,
+};
 
+use super::auth::{CredentialsProvider, SaslMechanismFactory};
+use super::bind::ResourceConflict;
 use super::connect::client_login;
-use crate::connect::{AsyncReadAndWrite, ServerConnector};
+use super::hooks::{HookAction, Hooks};
+use super::iq::{IqResponse, IqTracker};
+use super::sm::{enable_sm, is_stanza, SmState, StanzaAck};
+use super::stats::ClientStats;
+use crate::connect::{parse_see_other_host, AsyncReadAndWrite, ServerConnector, TlsInfo};
 use crate::event::Event;
-use crate::stream_features::StreamFeatures;
+use crate::id::{add_stanza_id, IdGenerator, UuidGenerator};
+use crate::rt::{self, sleep, Instant, JoinHandle, Sleep};
+use crate::stream_features::{FeatureNegotiator, StreamFeatures};
 use crate::xmpp_codec::Packet;
-use crate::xmpp_stream::{add_stanza_id, XMPPStream};
+use crate::xmpp_stream::XMPPStream;
 use crate::{Error, ProtocolError};
 
 /// XMPP client connection and state
@@ -18,11 +33,26 @@ use crate::{Error, ProtocolError};
 /// It is able to reconnect. TODO: implement session management.
 ///
 /// This implements the `futures` crate's [`Stream`](#impl-Stream) and
-/// [`Sink`](#impl-Sink<Packet>) traits.
+/// [`Sink`](#impl-Sink<Packet>) traits, and can be `split()` into halves
+/// implementing each of them to read and write from different tasks.
 pub struct Client<C: ServerConnector> {
     config: Config<C>,
     state: ClientState<C::Stream>,
     reconnect: bool,
+    /// Reconnect once, to the server we got redirected to
+    redirect: bool,
+    iq_tracker: IqTracker,
+    /// Stream Management state, when enabled on the current stream
+    sm: Option<SmState>,
+    /// Fires after `config.whitespace_keepalive` without sending anything
+    keepalive: Option<Pin<Box<Sleep>>>,
+    inbound_hooks: Hooks,
+    outbound_hooks: Hooks,
+    /// Buffer sent stanzas instead of flushing each of them
+    corked: bool,
+    /// Counters of `stats()`, the others being computed on demand
+    stats: ClientStats,
+    online_since: Option<Instant>,
     // TODO: tls_required=true
 }
 
@@ -31,31 +61,150 @@ pub struct Client<C: ServerConnector> {
 pub struct Config<C> {
     /// jid of the account
     pub jid: Jid,
-    /// password of the account
-    pub password: String,
+    /// password of the account, if not using a `credentials_provider` or
+    /// logging in anonymously
+    pub password: Option<String>,
+    /// provides the secret at each login, instead of `password`
+    pub credentials_provider: Option<Arc<dyn CredentialsProvider>>,
     /// server configuration for the account
     pub server: C,
+    /// additional stream features to negotiate before resource binding
+    pub feature_negotiators: Vec<Arc<dyn FeatureNegotiator>>,
+    /// additional SASL mechanisms, preferred over the built-in ones
+    pub sasl_mechanisms: Vec<Arc<dyn SaslMechanismFactory>>,
+    /// what to do when the resource of `jid` is already in use
+    pub on_resource_conflict: ResourceConflict,
+    /// send a whitespace keep-alive after this long without sending anything
+    pub whitespace_keepalive: Option<Duration>,
+    /// generates the ids of sent stanzas which lack one
+    pub id_generator: Arc<dyn IdGenerator>,
+    /// enable Stream Management (XEP-0198) when the server offers it, off
+    /// by default
+    pub stream_management: bool,
 }
 
+impl<C> Config<C> {
+    /// Create a configuration with default values for the optional settings
+    pub fn new<J: Into<Jid>, P: Into<String>>(jid: J, password: P, server: C) -> Self {
+        let mut config = Self::new_anonymous(jid, server);
+        config.password = Some(password.into());
+        config
+    }
+
+    /// Create a configuration which fetches the secret from `provider` at
+    /// each login, instead of storing a password
+    pub fn new_with_credentials_provider<J: Into<Jid>>(
+        jid: J,
+        provider: Arc<dyn CredentialsProvider>,
+        server: C,
+    ) -> Self {
+        Self::new_anonymous(jid, server).with_credentials_provider(provider)
+    }
+
+    /// Create a configuration without any secret, for logging in
+    /// anonymously with a JID which has no node
+    pub fn new_anonymous<J: Into<Jid>>(jid: J, server: C) -> Self {
+        Config {
+            jid: jid.into(),
+            password: None,
+            server,
+            credentials_provider: None,
+            feature_negotiators: Vec::new(),
+            sasl_mechanisms: Vec::new(),
+            on_resource_conflict: ResourceConflict::default(),
+            whitespace_keepalive: None,
+            id_generator: Arc::new(UuidGenerator),
+            stream_management: false,
+        }
+    }
+
+    /// Fetch the secret from `provider` at each login, ignoring `password`
+    pub fn with_credentials_provider(mut self, provider: Arc<dyn CredentialsProvider>) -> Self {
+        self.credentials_provider = Some(provider);
+        self
+    }
+
+    /// Register a negotiator for an additional stream feature
+    pub fn with_feature_negotiator(mut self, negotiator: Arc<dyn FeatureNegotiator>) -> Self {
+        self.feature_negotiators.push(negotiator);
+        self
+    }
+
+    /// Register an additional SASL mechanism, used when the server offers it
+    pub fn with_sasl_mechanism(mut self, mechanism: Arc<dyn SaslMechanismFactory>) -> Self {
+        self.sasl_mechanisms.push(mechanism);
+        self
+    }
+
+    /// Choose what to do when the requested resource is already in use
+    pub fn with_resource_conflict(mut self, on_conflict: ResourceConflict) -> Self {
+        self.on_resource_conflict = on_conflict;
+        self
+    }
+
+    /// Send a single space once the connection has been idle for `interval`
+    ///
+    /// This keeps NAT mappings and proxies from expiring long-lived
+    /// connections which rarely send anything. The server doesn't answer
+    /// it, so it isn't reflected in `ClientStats::last_ping_rtt`.
+    pub fn with_whitespace_keepalive(mut self, interval: Duration) -> Self {
+        self.whitespace_keepalive = Some(interval);
+        self
+    }
+
+    /// Generate the ids of sent stanzas which lack one with `generator`,
+    /// instead of random UUIDs
+    pub fn with_id_generator(mut self, generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = generator;
+        self
+    }
+
+    /// Choose whether to enable Stream Management (XEP-0198) when the
+    /// server offers it, which `send_stanza_tracked()` requires
+    pub fn with_stream_management(mut self, enabled: bool) -> Self {
+        self.stream_management = enabled;
+        self
+    }
+}
+
+/// Port used when a redirection target doesn't specify one
+const DEFAULT_CLIENT_PORT: u16 = 5222;
+
 enum ClientState<S: AsyncReadAndWrite> {
     Invalid,
     Disconnected,
-    Connecting(JoinHandle<Result<XMPPStream<S>, Error>>),
+    Connecting(JoinHandle<Result<(XMPPStream<S>, bool), Error>>),
     Connected(XMPPStream<S>),
 }
 
+/// Log in, then enable Stream Management if configured, returning whether
+/// it got enabled
+async fn login<C: ServerConnector>(
+    config: Config<C>,
+) -> Result<(XMPPStream<C::Stream>, bool), Error> {
+    let stream_management = config.stream_management;
+    let mut stream = client_login(config).await?;
+    let sm_enabled = stream_management && enable_sm(&mut stream).await?;
+    Ok((stream, sm_enabled))
+}
+
 impl<C: ServerConnector> Client<C> {
     /// Start a new client given that the JID is already parsed.
     pub fn new_with_config(config: Config<C>) -> Self {
-        let connect = tokio::spawn(client_login(
-            config.server.clone(),
-            config.jid.clone(),
-            config.password.clone(),
-        ));
+        let connect = rt::spawn(login(config.clone()));
         let client = Client {
             config,
             state: ClientState::Connecting(connect),
             reconnect: false,
+            redirect: false,
+            iq_tracker: IqTracker::new(),
+            sm: None,
+            keepalive: None,
+            inbound_hooks: Hooks::default(),
+            outbound_hooks: Hooks::default(),
+            corked: false,
+            stats: ClientStats::default(),
+            online_since: None,
         };
         client
     }
@@ -67,6 +216,32 @@ impl<C: ServerConnector> Client<C> {
         self
     }
 
+    /// Set how long `send_iq()` waits for a response before failing
+    /// with [`Error::IqTimeout`].
+    pub fn set_iq_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.iq_tracker.default_timeout = timeout;
+        self
+    }
+
+    /// Register a hook run on every received stanza, before it is
+    /// yielded as an [`Event::Stanza`] or matched to a pending IQ
+    pub fn add_inbound_hook<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(&Element) -> HookAction + Send + 'static,
+    {
+        self.inbound_hooks.add(hook);
+        self
+    }
+
+    /// Register a hook run on every stanza about to be sent
+    pub fn add_outbound_hook<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(&Element) -> HookAction + Send + 'static,
+    {
+        self.outbound_hooks.add(hook);
+        self
+    }
+
     /// Get the client's bound JID (the one reported by the XMPP
     /// server).
     pub fn bound_jid(&self) -> Option<&Jid> {
@@ -76,10 +251,156 @@ impl<C: ServerConnector> Client<C> {
         }
     }
 
+    /// Get security information about the TLS connection, such as the
+    /// certificate chain of the server, if connected and encrypted
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        match self.state {
+            ClientState::Connected(ref stream) => C::tls_info(stream.stream.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Get a snapshot of the statistics of the current connection
+    pub fn stats(&self) -> ClientStats {
+        let mut stats = self.stats.clone();
+        stats.last_ping_rtt = self.iq_tracker.last_ping_rtt;
+        if let ClientState::Connected(ref stream) = self.state {
+            stats.uptime = self.online_since.map(|since| since.elapsed());
+            stats.bytes_sent = stream.stream.codec().bytes_sent();
+            stats.bytes_received = stream.stream.codec().bytes_received();
+        }
+        stats
+    }
+
     /// Send stanza
     pub async fn send_stanza(&mut self, stanza: Element) -> Result<(), Error> {
-        self.send(Packet::Stanza(add_stanza_id(stanza, ns::JABBER_CLIENT)))
-            .await
+        let stanza = add_stanza_id(stanza, ns::JABBER_CLIENT, &*self.config.id_generator);
+        self.send_packet(Packet::Stanza(stanza)).await
+    }
+
+    /// Make a new stanza id, from the generator of the configuration
+    pub fn make_id(&self) -> String {
+        self.config.id_generator.make_id()
+    }
+
+    /// Get the generator of stanza ids of the configuration
+    pub(super) fn id_generator(&self) -> Arc<dyn IdGenerator> {
+        self.config.id_generator.clone()
+    }
+
+    async fn send_packet(&mut self, packet: Packet) -> Result<(), Error> {
+        if self.corked {
+            self.feed(packet).await
+        } else {
+            self.send(packet).await
+        }
+    }
+
+    /// Buffer the stanzas sent from now on, and write them together on
+    /// `uncork()`, e.g. for a burst of small stanzas
+    ///
+    /// The buffer still gets written whenever it grows past the write
+    /// buffer size of the stream (8 KiB).
+    pub fn cork(&mut self) {
+        self.corked = true;
+    }
+
+    /// Write the stanzas buffered since `cork()`, and go back to writing
+    /// each stanza as it is sent
+    pub async fn uncork(&mut self) -> Result<(), Error> {
+        if !std::mem::take(&mut self.corked) {
+            return Ok(());
+        }
+        self.flush().await
+    }
+
+    /// Send an IQ request, and get a future resolving to its response
+    ///
+    /// The IQ id must be unique among the requests currently in flight.
+    /// The response is not yielded as an [`Event::Stanza`]; it is only
+    /// delivered through the returned future, which requires this client
+    /// to keep being polled. It fails with [`Error::IqTimeout`] after the
+    /// timeout set with `set_iq_timeout()`.
+    pub async fn send_iq(&mut self, iq: Iq) -> Result<IqResponse, Error> {
+        let timeout = self.iq_tracker.default_timeout;
+        self.send_iq_with_timeout(iq, timeout).await
+    }
+
+    /// Same as `send_iq()`, overriding the timeout for this IQ only
+    pub async fn send_iq_with_timeout(
+        &mut self,
+        iq: Iq,
+        timeout: Duration,
+    ) -> Result<IqResponse, Error> {
+        let id = iq.id.clone();
+        let response = self.track_iq(&iq, Some(timeout));
+        if let Err(e) = self.send_packet(Packet::Stanza(iq.into())).await {
+            self.iq_tracker.forget(&id);
+            return Err(e);
+        }
+        Ok(response)
+    }
+
+    /// Send stanza, and get a future resolving once the server
+    /// acknowledged it with Stream Management (XEP-0198)
+    ///
+    /// This requires Stream Management to be enabled on the current stream,
+    /// failing with [`Error::InvalidState`] otherwise, or when an outbound
+    /// hook dropped the stanza. The acknowledgement is requested right
+    /// away, and only received while this client keeps being polled.
+    pub async fn send_stanza_tracked(&mut self, stanza: Element) -> Result<StanzaAck, Error> {
+        self.track_stanza(stanza, None).await
+    }
+
+    /// Same as `send_stanza_tracked()`, the acknowledgement failing with
+    /// [`Error::AckTimeout`] if it didn't arrive within `timeout`
+    pub async fn send_stanza_tracked_with_timeout(
+        &mut self,
+        stanza: Element,
+        timeout: Duration,
+    ) -> Result<StanzaAck, Error> {
+        self.track_stanza(stanza, Some(timeout)).await
+    }
+
+    async fn track_stanza(
+        &mut self,
+        stanza: Element,
+        timeout: Option<Duration>,
+    ) -> Result<StanzaAck, Error> {
+        let Some(sent) = self.sm.as_ref().map(SmState::sent) else {
+            return Err(Error::InvalidState);
+        };
+        self.send_stanza(stanza).await?;
+        let Some(ref mut sm) = self.sm else {
+            return Err(Error::Disconnected);
+        };
+        if sm.sent() == sent {
+            return Err(Error::InvalidState);
+        }
+        let ack = sm.track_last(timeout);
+        self.send_packet(Packet::Stanza(R.into())).await?;
+        Ok(ack)
+    }
+
+    /// Fail the pending IQs and stanza acknowledgements, once the
+    /// connection is lost
+    fn fail_pending(&mut self) {
+        self.iq_tracker.fail_all();
+        if let Some(mut sm) = self.sm.take() {
+            sm.fail_all();
+        }
+    }
+
+    /// Start waiting for the response to an IQ about to be sent, by default
+    /// for the timeout set with `set_iq_timeout()`
+    pub(super) fn track_iq(&mut self, iq: &Iq, timeout: Option<Duration>) -> IqResponse {
+        let timeout = timeout.unwrap_or(self.iq_tracker.default_timeout);
+        self.iq_tracker.register(iq, timeout)
+    }
+
+    /// Stop waiting for the response to an IQ which couldn't be sent
+    pub(super) fn untrack_iq(&mut self, id: &str) {
+        self.iq_tracker.forget(id);
     }
 
     /// Get the stream features (`<stream:features/>`) of the underlying stream
@@ -90,6 +411,32 @@ impl<C: ServerConnector> Client<C> {
         }
     }
 
+    /// Postpone the whitespace keep-alive, as something was just sent
+    fn reset_keepalive(&mut self) {
+        let Some(interval) = self.config.whitespace_keepalive else {
+            return;
+        };
+        match self.keepalive {
+            Some(ref mut timer) => timer.as_mut().reset(Instant::now() + interval),
+            None => self.keepalive = Some(Box::pin(sleep(interval))),
+        }
+    }
+
+    /// Use the server designated by a `<see-other-host/>` stream error
+    /// for the next connection, if the connector supports it
+    fn follow_redirect(&mut self, target: &str) {
+        let redirected = parse_see_other_host(target, DEFAULT_CLIENT_PORT)
+            .and_then(|(host, port)| self.config.server.redirect(&host, port));
+        match redirected {
+            Some(server) => {
+                log::info!("Redirected to {} by the server", target);
+                self.config.server = server;
+                self.redirect = true;
+            }
+            None => log::warn!("Can't follow redirection to {}", target),
+        }
+    }
+
     /// End connection by sending `</stream:stream>`
     ///
     /// You may expect the server to respond with the same. This
@@ -99,6 +446,26 @@ impl<C: ServerConnector> Client<C> {
     pub async fn send_end(&mut self) -> Result<(), Error> {
         self.send(Packet::StreamEnd).await
     }
+
+    /// Close the connection gracefully
+    ///
+    /// This disables reconnect, flushes pending stanzas, then completes the
+    /// two-way stream closure waiting at most `timeout` for the server,
+    /// before shutting down the transport. Stanzas received meanwhile are
+    /// discarded, and pending IQs fail with [`Error::Disconnected`].
+    pub async fn close(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.reconnect = false;
+        self.redirect = false;
+        self.fail_pending();
+        match replace(&mut self.state, ClientState::Disconnected) {
+            ClientState::Connected(stream) => stream.shutdown(timeout).await,
+            ClientState::Connecting(connect) => {
+                connect.abort();
+                Ok(())
+            }
+            ClientState::Disconnected | ClientState::Invalid => Ok(()),
+        }
+    }
 }
 
 /// Incoming XMPP events
@@ -123,33 +490,43 @@ impl<C: ServerConnector> Stream for Client<C> {
 
         match state {
             ClientState::Invalid => panic!("Invalid client state"),
-            ClientState::Disconnected if self.reconnect => {
+            ClientState::Disconnected if self.reconnect || self.redirect => {
                 // TODO: add timeout
-                let connect = tokio::spawn(client_login(
-                    self.config.server.clone(),
-                    self.config.jid.clone(),
-                    self.config.password.clone(),
-                ));
+                self.redirect = false;
+                let connect = rt::spawn(login(self.config.clone()));
                 self.state = ClientState::Connecting(connect);
                 self.poll_next(cx)
             }
             ClientState::Disconnected => Poll::Ready(None),
             ClientState::Connecting(mut connect) => match Pin::new(&mut connect).poll(cx) {
-                Poll::Ready(Ok(Ok(stream))) => {
+                Poll::Ready(Ok((stream, sm_enabled))) => {
                     let bound_jid = stream.jid.clone();
+                    let requested_resource = self
+                        .config
+                        .jid
+                        .resource()
+                        .map(|resource| resource.to_string());
+                    let tls_info = C::tls_info(stream.stream.get_ref());
+                    let features = stream.stream_features.clone();
                     self.state = ClientState::Connected(stream);
+                    self.sm = sm_enabled.then(SmState::default);
+                    self.reset_keepalive();
+                    self.online_since = Some(Instant::now());
+                    self.stats = ClientStats {
+                        connections: self.stats.connections + 1,
+                        ..ClientStats::default()
+                    };
                     Poll::Ready(Some(Event::Online {
                         bound_jid,
+                        requested_resource,
                         resumed: false,
+                        tls_info,
+                        features,
                     }))
                 }
-                Poll::Ready(Ok(Err(e))) => {
-                    self.state = ClientState::Disconnected;
-                    return Poll::Ready(Some(Event::Disconnected(e.into())));
-                }
                 Poll::Ready(Err(e)) => {
                     self.state = ClientState::Disconnected;
-                    panic!("connect task: {}", e);
+                    return Poll::Ready(Some(Event::Disconnected(e.into())));
                 }
                 Poll::Pending => {
                     self.state = ClientState::Connecting(connect);
@@ -163,10 +540,35 @@ impl<C: ServerConnector> Stream for Client<C> {
                     Poll::Ready(Ok(())) => (),
                     Poll::Ready(Err(e)) => {
                         self.state = ClientState::Disconnected;
+                        self.fail_pending();
                         return Poll::Ready(Some(Event::Disconnected(e.into())));
                     }
                 };
 
+                // Send a whitespace keep-alive when idle for too long, once
+                // the sink can take it; the elapsed timer fires again until
+                // then.
+                while let Some(Poll::Ready(())) =
+                    self.keepalive.as_mut().map(|timer| timer.as_mut().poll(cx))
+                {
+                    let sent = match Pin::new(&mut stream).poll_ready(cx) {
+                        Poll::Pending => break,
+                        Poll::Ready(Ok(())) => {
+                            match Pin::new(&mut stream).start_send(Packet::Text(" ".to_owned())) {
+                                Ok(()) => Pin::new(&mut stream).poll_flush(cx),
+                                Err(e) => Poll::Ready(Err(e)),
+                            }
+                        }
+                        Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                    };
+                    if let Poll::Ready(Err(e)) = sent {
+                        self.state = ClientState::Disconnected;
+                        self.fail_pending();
+                        return Poll::Ready(Some(Event::Disconnected(e.into())));
+                    }
+                    self.reset_keepalive();
+                }
+
                 // Poll stream
                 //
                 // This needs to be a loop in order to ignore packets we don’t care about, or those
@@ -181,12 +583,73 @@ impl<C: ServerConnector> Stream for Client<C> {
                         Poll::Ready(None) => {
                             // EOF
                             self.state = ClientState::Disconnected;
+                            self.fail_pending();
                             return Poll::Ready(Some(Event::Disconnected(Error::Disconnected)));
                         }
+                        Poll::Ready(Some(Ok(Packet::Stanza(stanza))))
+                            if stanza.is("error", ns::STREAM) =>
+                        {
+                            // Fatal stream error, the server will close the stream
+                            self.state = ClientState::Disconnected;
+                            self.fail_pending();
+                            let error = match StreamError::try_from(stanza) {
+                                Ok(error) => error,
+                                Err(e) => {
+                                    return Poll::Ready(Some(Event::Disconnected(
+                                        ProtocolError::Parsers(e).into(),
+                                    )))
+                                }
+                            };
+                            if let DefinedCondition::SeeOtherHost(ref target) =
+                                error.defined_condition
+                            {
+                                self.follow_redirect(target);
+                            }
+                            return Poll::Ready(Some(Event::Disconnected(Error::StreamError(
+                                error,
+                            ))));
+                        }
+                        Poll::Ready(Some(Ok(Packet::Stanza(stanza))))
+                            if self.sm.is_some() && stanza.has_ns(ns::SM) =>
+                        {
+                            // Answer an ack request, or process an ack
+                            let sm = self.sm.as_mut().unwrap();
+                            let answer = match sm.handle(stanza) {
+                                Ok(answer) => answer,
+                                Err(nonza) => {
+                                    log::warn!("Unexpected stream management nonza: {:?}", nonza);
+                                    None
+                                }
+                            };
+                            let Some(answer) = answer else {
+                                continue;
+                            };
+                            let sent =
+                                match Pin::new(&mut stream).start_send(Packet::Stanza(answer)) {
+                                    Ok(()) => Pin::new(&mut stream).poll_flush(cx),
+                                    Err(e) => Poll::Ready(Err(e)),
+                                };
+                            if let Poll::Ready(Err(e)) = sent {
+                                self.state = ClientState::Disconnected;
+                                self.fail_pending();
+                                return Poll::Ready(Some(Event::Disconnected(e)));
+                            }
+                        }
                         Poll::Ready(Some(Ok(Packet::Stanza(stanza)))) => {
-                            // Receive stanza
-                            self.state = ClientState::Connected(stream);
-                            return Poll::Ready(Some(Event::Stanza(stanza)));
+                            // Receive stanza, unless a hook drops it or it answers a tracked IQ
+                            self.stats.stanzas_received += 1;
+                            if let Some(ref mut sm) = self.sm {
+                                if is_stanza(&stanza) {
+                                    sm.on_received();
+                                }
+                            }
+                            let Some(stanza) = self.inbound_hooks.run(stanza) else {
+                                continue;
+                            };
+                            if let Some(stanza) = self.iq_tracker.handle(stanza, &stream.jid) {
+                                self.state = ClientState::Connected(stream);
+                                return Poll::Ready(Some(Event::Stanza(stanza)));
+                            }
                         }
                         Poll::Ready(Some(Ok(Packet::Text(_)))) => {
                             // Ignore text between stanzas
@@ -194,13 +657,15 @@ impl<C: ServerConnector> Stream for Client<C> {
                         Poll::Ready(Some(Ok(Packet::StreamStart(_)))) => {
                             // <stream:stream>
                             self.state = ClientState::Disconnected;
-                            return Poll::Ready(Some(Event::Disconnected(
-                                ProtocolError::InvalidStreamStart.into(),
-                            )));
+                            self.fail_pending();
+                            let error = ProtocolError::InvalidStreamStart.into();
+                            stream.poll_fail(cx, &error);
+                            return Poll::Ready(Some(Event::Disconnected(error)));
                         }
                         Poll::Ready(Some(Ok(Packet::StreamEnd))) => {
                             // End of stream: </stream:stream>
                             self.state = ClientState::Disconnected;
+                            self.fail_pending();
                             return Poll::Ready(Some(Event::Disconnected(Error::Disconnected)));
                         }
                         Poll::Pending => {
@@ -210,7 +675,9 @@ impl<C: ServerConnector> Stream for Client<C> {
                         }
                         Poll::Ready(Some(Err(e))) => {
                             self.state = ClientState::Disconnected;
-                            return Poll::Ready(Some(Event::Disconnected(e.into())));
+                            self.fail_pending();
+                            stream.poll_fail(cx, &e);
+                            return Poll::Ready(Some(Event::Disconnected(e)));
                         }
                     }
                 }
@@ -226,12 +693,29 @@ impl<C: ServerConnector> Sink<Packet> for Client<C> {
     type Error = Error;
 
     fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        let item = match item {
+            Packet::Stanza(stanza) => match self.outbound_hooks.run(stanza) {
+                Some(stanza) => Packet::Stanza(stanza),
+                None => return Ok(()),
+            },
+            item => item,
+        };
+        // Nonzas, such as Stream Management requests, aren't counted
+        let counted = matches!(item, Packet::Stanza(ref stanza) if is_stanza(stanza));
         match self.state {
             ClientState::Connected(ref mut stream) => {
-                Pin::new(stream).start_send(item).map_err(|e| e.into())
+                Pin::new(stream).start_send(item)?;
             }
-            _ => Err(Error::InvalidState),
+            _ => return Err(Error::InvalidState),
         }
+        if counted {
+            self.stats.stanzas_sent += 1;
+            if let Some(ref mut sm) = self.sm {
+                sm.on_sent();
+            }
+        }
+        self.reset_keepalive();
+        Ok(())
     }
 
     fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
@@ -239,7 +723,7 @@ impl<C: ServerConnector> Sink<Packet> for Client<C> {
             ClientState::Connected(ref mut stream) => {
                 Pin::new(stream).poll_ready(cx).map_err(|e| e.into())
             }
-            _ => Poll::Pending,
+            _ => Poll::Ready(Err(Error::InvalidState)),
         }
     }
 
@@ -248,7 +732,7 @@ impl<C: ServerConnector> Sink<Packet> for Client<C> {
             ClientState::Connected(ref mut stream) => {
                 Pin::new(stream).poll_flush(cx).map_err(|e| e.into())
             }
-            _ => Poll::Pending,
+            _ => Poll::Ready(Err(Error::InvalidState)),
         }
     }
 
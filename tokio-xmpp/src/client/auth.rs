@@ -1,79 +1,37 @@
-use futures::stream::StreamExt;
-use sasl::client::mechanisms::{Anonymous, Plain, Scram};
-use sasl::client::Mechanism;
-use sasl::common::scram::{Sha1, Sha256};
-use sasl::common::Credentials;
-use std::collections::HashSet;
-use std::str::FromStr;
-use tokio::io::{AsyncRead, AsyncWrite};
-use xmpp_parsers::sasl::{Auth, Challenge, Failure, Mechanism as XMPPMechanism, Response, Success};
-
-use crate::xmpp_codec::Packet;
-use crate::xmpp_stream::XMPPStream;
-use crate::{AuthError, Error, ProtocolError};
-
-pub async fn auth<S: AsyncRead + AsyncWrite + Unpin>(
-    mut stream: XMPPStream<S>,
-    creds: Credentials,
-) -> Result<S, Error> {
-    let local_mechs: Vec<Box<dyn Fn() -> Box<dyn Mechanism + Send + Sync> + Send>> = vec![
-        Box::new(|| Box::new(Scram::<Sha256>::from_credentials(creds.clone()).unwrap())),
-        Box::new(|| Box::new(Scram::<Sha1>::from_credentials(creds.clone()).unwrap())),
-        Box::new(|| Box::new(Plain::from_credentials(creds.clone()).unwrap())),
-        Box::new(|| Box::new(Anonymous::new())),
-    ];
-
-    let remote_mechs: HashSet<String> = stream.stream_features.sasl_mechanisms()?.collect();
-
-    for local_mech in local_mechs {
-        let mut mechanism = local_mech();
-        if remote_mechs.contains(mechanism.name()) {
-            let initial = mechanism.initial();
-            let mechanism_name =
-                XMPPMechanism::from_str(mechanism.name()).map_err(ProtocolError::Parsers)?;
-
-            stream
-                .send_stanza(Auth {
-                    mechanism: mechanism_name,
-                    data: initial,
-                })
-                .await?;
-
-            loop {
-                match stream.next().await {
-                    Some(Ok(Packet::Stanza(stanza))) => {
-                        if let Ok(challenge) = Challenge::try_from(stanza.clone()) {
-                            let response = mechanism
-                                .response(&challenge.data)
-                                .map_err(|e| AuthError::Sasl(e))?;
-
-                            // Send response and loop
-                            stream.send_stanza(Response { data: response }).await?;
-                        } else if let Ok(_) = Success::try_from(stanza.clone()) {
-                            return Ok(stream.into_inner());
-                        } else if let Ok(failure) = Failure::try_from(stanza.clone()) {
-                            return Err(Error::Auth(AuthError::Fail(failure.defined_condition)));
-                        // TODO: This code was needed for compatibility with some broken server,
-                        // but it’s been forgotten which.  It is currently commented out so that we
-                        // can find it and fix the server software instead.
-                        /*
-                        } else if stanza.name() == "failure" {
-                            // Workaround for https://gitlab.com/xmpp-rs/xmpp-parsers/merge_requests/1
-                            return Err(Error::Auth(AuthError::Sasl("failure".to_string())));
-                        */
-                        } else {
-                            // ignore and loop
-                        }
-                    }
-                    Some(Ok(_)) => {
-                        // ignore and loop
-                    }
-                    Some(Err(e)) => return Err(e),
-                    None => return Err(Error::Disconnected),
-                }
-            }
-        }
-    }
+use futures::future::BoxFuture;
+use sasl::client::{Mechanism, MechanismError};
+use sasl::common::{Credentials, Secret};
+use std::error::Error as StdError;
+use std::fmt;
+use xmpp_parsers::Jid;
+
+/// Creates an application-provided SASL mechanism
+///
+/// Registered factories are tried before the built-in mechanisms, in the
+/// order they were registered, whenever the server offers their mechanism.
+///
+/// With the `gssapi` feature, this is how Kerberos is used: the factory
+/// returns a `sasl::client::mechanisms::Gssapi` wrapping a security context
+/// for the `xmpp` service of the server.
+pub trait SaslMechanismFactory: fmt::Debug + Send + Sync {
+    /// Name of the mechanism, as advertised by the server
+    fn name(&self) -> &str;
+
+    /// Create the mechanism for a login attempt
+    fn create(
+        &self,
+        creds: &Credentials,
+    ) -> Result<Box<dyn Mechanism + Send + Sync>, MechanismError>;
+}
 
-    Err(AuthError::NoMechanism.into())
+/// Provides the secret used to log in, each time the client connects
+///
+/// This lets applications fetch passwords or tokens from a keyring, or
+/// refresh them, instead of keeping them in the client configuration.
+pub trait CredentialsProvider: fmt::Debug + Send + Sync {
+    /// Get the secret to authenticate `jid` with
+    fn secret<'a>(
+        &'a self,
+        jid: &'a Jid,
+    ) -> BoxFuture<'a, Result<Secret, Box<dyn StdError + Send + Sync>>>;
 }
@@ -1,35 +1,66 @@
-use sasl::common::Credentials;
-use xmpp_parsers::{ns, Jid};
+use sasl::common::{Credentials, Secret};
+use xmpp_parsers::ns;
 
-use crate::client::auth::auth;
-use crate::client::bind::bind;
+use crate::client::async_client::Config;
 use crate::connect::ServerConnector;
-use crate::{xmpp_stream::XMPPStream, Error};
+use crate::sansio::{Login, LoginStep};
+use crate::xmpp_codec::XMPPCodec;
+use crate::xmpp_stream::{drive, XMPPStream};
+use crate::{AuthError, Error};
 
 /// Log into an XMPP server as a client with a jid+pass
 /// does channel binding if supported
+///
+/// A JID without a node logs in anonymously, with SASL ANONYMOUS.
 pub async fn client_login<C: ServerConnector>(
-    server: C,
-    jid: Jid,
-    password: String,
+    config: Config<C>,
 ) -> Result<XMPPStream<C::Stream>, Error> {
-    let username = jid.node().unwrap().as_str();
-    let password = password;
+    let jid = config.jid;
 
-    let xmpp_stream = server.connect(&jid, ns::JABBER_CLIENT).await?;
+    let mut xmpp_stream = config.server.connect(&jid, ns::JABBER_CLIENT).await?;
 
     let channel_binding = C::channel_binding(xmpp_stream.stream.get_ref())?;
 
-    let creds = Credentials::default()
-        .with_username(username)
-        .with_password(password)
-        .with_channel_binding(channel_binding);
-    // Authenticated (unspecified) stream
-    let stream = auth(xmpp_stream, creds).await?;
-    // Authenticated XMPPStream
-    let xmpp_stream = XMPPStream::start(stream, jid, ns::JABBER_CLIENT.to_owned()).await?;
+    let mut creds = Credentials::default().with_channel_binding(channel_binding);
+    if let Some(username) = jid.node() {
+        creds = creds.with_username(username.as_str());
+        creds.secret = match (&config.credentials_provider, config.password) {
+            (Some(provider), _) => provider
+                .secret(&jid)
+                .await
+                .map_err(AuthError::Credentials)?,
+            (None, Some(password)) => Secret::password_plain(password),
+            (None, None) => Secret::None,
+        };
+    }
 
-    // XMPPStream bound to user session
-    let xmpp_stream = bind(xmpp_stream).await?;
-    Ok(xmpp_stream)
+    // The connector already started the stream, to negotiate STARTTLS
+    let mut login = config
+        .sasl_mechanisms
+        .into_iter()
+        .fold(Login::new(jid, creds), Login::with_sasl_mechanism)
+        .with_resource_conflict(config.on_resource_conflict)
+        .started(&xmpp_stream.stream_features)?;
+    loop {
+        match drive(&mut xmpp_stream, &mut login).await? {
+            LoginStep::Restart => *xmpp_stream.stream.codec_mut() = XMPPCodec::new(),
+            LoginStep::Authenticated(header) => {
+                xmpp_stream.id = header.id;
+                xmpp_stream.stream_features = header.features;
+                // Additional features registered by the application
+                xmpp_stream = xmpp_stream
+                    .negotiate_features(&config.feature_negotiators)
+                    .await?;
+                if !login.bind(&xmpp_stream.stream_features) {
+                    // Without resource binding, the stream is probably usable as is
+                    return Ok(xmpp_stream);
+                }
+            }
+            // XMPPStream bound to user session
+            LoginStep::Bound(bound_jid) => {
+                xmpp_stream.jid = bound_jid;
+                return Ok(xmpp_stream);
+            }
+        }
+    }
 }
@@ -0,0 +1,279 @@
+//! Cloneable handles to a client running in its own task
+
+use futures::channel::{mpsc, oneshot};
+use futures::future::{select, Either};
+use futures::stream::StreamExt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use xmpp_parsers::{iq::Iq, Element};
+
+use super::async_client::Client;
+use super::iq::IqResponse;
+use crate::connect::ServerConnector;
+use crate::event::Event;
+use crate::rt;
+use crate::Error;
+
+/// How long to wait for the server to close the stream, once every handle
+/// got dropped
+const DROP_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many events a subscriber may lag behind, by default
+pub const DEFAULT_EVENT_CAPACITY: usize = 256;
+
+/// Events of a client, as received by one subscriber
+///
+/// A subscriber lagging more than the capacity given to
+/// `AsyncClient::spawn_with_capacity()` behind misses the oldest events,
+/// and gets a `tokio_stream::wrappers::errors::BroadcastStreamRecvError`
+/// telling how many instead.
+/// Ends once the client task is done.
+pub type EventReceiver = BroadcastStream<Arc<Event>>;
+
+/// Sender of the events, dropped once the client task is done
+type Subscribers = Arc<Mutex<Option<broadcast::Sender<Arc<Event>>>>>;
+
+fn lock(subscribers: &Subscribers) -> MutexGuard<'_, Option<broadcast::Sender<Arc<Event>>>> {
+    subscribers
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+enum Command {
+    SendStanza(Element, oneshot::Sender<Result<(), Error>>),
+    SendIq(
+        Iq,
+        Option<Duration>,
+        oneshot::Sender<Result<IqResponse, Error>>,
+    ),
+    Close(Duration, oneshot::Sender<Result<(), Error>>),
+}
+
+impl<C: ServerConnector> Client<C> {
+    /// Run the client in its own task, returning a handle to it and a
+    /// first subscription to its events
+    ///
+    /// The connection is closed once every handle got dropped.
+    pub fn spawn(self) -> (ClientHandle, EventReceiver) {
+        self.spawn_with_capacity(DEFAULT_EVENT_CAPACITY)
+    }
+
+    /// Same as `spawn()`, letting subscribers lag up to `capacity` events
+    /// behind instead of [`DEFAULT_EVENT_CAPACITY`]
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn spawn_with_capacity(self, capacity: usize) -> (ClientHandle, EventReceiver) {
+        let (commands, receiver) = mpsc::unbounded();
+        let (events, _) = broadcast::channel(capacity);
+        let handle = ClientHandle {
+            commands,
+            subscribers: Arc::new(Mutex::new(Some(events))),
+        };
+        let events = handle.subscribe_events();
+        let subscribers = handle.subscribers.clone();
+        rt::spawn(async move {
+            run(self, receiver, &subscribers).await;
+            // End the subscriptions
+            lock(&subscribers).take();
+        });
+        (handle, events)
+    }
+}
+
+async fn run<C: ServerConnector>(
+    mut client: Client<C>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    subscribers: &Subscribers,
+) {
+    loop {
+        // Commands come first, so that they apply in order with the events
+        // they may cause.
+        match select(commands.next(), client.next()).await {
+            Either::Left((Some(command), _)) => match command {
+                Command::SendStanza(stanza, reply) => {
+                    let _ = reply.send(client.send_stanza(stanza).await);
+                }
+                Command::SendIq(iq, None, reply) => {
+                    let _ = reply.send(client.send_iq(iq).await);
+                }
+                Command::SendIq(iq, Some(timeout), reply) => {
+                    let _ = reply.send(client.send_iq_with_timeout(iq, timeout).await);
+                }
+                Command::Close(timeout, reply) => {
+                    let _ = reply.send(client.close(timeout).await);
+                    return;
+                }
+            },
+            Either::Left((None, _)) => {
+                if let Err(e) = client.close(DROP_CLOSE_TIMEOUT).await {
+                    log::warn!("Error while closing the connection: {}", e);
+                }
+                return;
+            }
+            Either::Right((Some(event), _)) => {
+                if let Some(ref sender) = *lock(subscribers) {
+                    // Fails only without any subscriber
+                    let _ = sender.send(Arc::new(event));
+                }
+            }
+            Either::Right((None, _)) => return,
+        }
+    }
+}
+
+/// Cheap cloneable handle to a client running in its own task, see
+/// `AsyncClient::spawn()`
+///
+/// Unlike the client itself, it can send from many tasks at once.
+#[derive(Clone)]
+pub struct ClientHandle {
+    commands: mpsc::UnboundedSender<Command>,
+    subscribers: Subscribers,
+}
+
+impl ClientHandle {
+    /// Get the events of the client from now on
+    ///
+    /// The receiver ends right away if the client task is done.
+    pub fn subscribe_events(&self) -> EventReceiver {
+        let receiver = match *lock(&self.subscribers) {
+            Some(ref sender) => sender.subscribe(),
+            None => {
+                let (sender, receiver) = broadcast::channel(1);
+                drop(sender);
+                receiver
+            }
+        };
+        BroadcastStream::new(receiver)
+    }
+
+    /// Send stanza
+    pub async fn send_stanza(&self, stanza: Element) -> Result<(), Error> {
+        self.request(|reply| Command::SendStanza(stanza, reply))
+            .await?
+    }
+
+    /// Send an IQ request, and get a future resolving to its response
+    ///
+    /// See `AsyncClient::send_iq()`.
+    pub async fn send_iq(&self, iq: Iq) -> Result<IqResponse, Error> {
+        self.request(|reply| Command::SendIq(iq, None, reply))
+            .await?
+    }
+
+    /// Same as `send_iq()`, overriding the timeout for this IQ only
+    pub async fn send_iq_with_timeout(
+        &self,
+        iq: Iq,
+        timeout: Duration,
+    ) -> Result<IqResponse, Error> {
+        self.request(|reply| Command::SendIq(iq, Some(timeout), reply))
+            .await?
+    }
+
+    /// Close the connection gracefully and end the client task, see
+    /// `AsyncClient::close()`
+    pub async fn close(&self, timeout: Duration) -> Result<(), Error> {
+        self.request(|reply| Command::Close(timeout, reply)).await?
+    }
+
+    /// Whether the client task is done
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+
+    /// Hand a command to the client task, and wait for its reply
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> Command,
+    ) -> Result<T, Error> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .unbounded_send(command(reply))
+            .map_err(|_| Error::Disconnected)?;
+        response.await.map_err(|_| Error::Disconnected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::mock::{MockAsyncClient, MockServer};
+    use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+    use xmpp_parsers::{ns, Jid};
+
+    #[tokio::test]
+    async fn test_handle() {
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let (handle, mut events) =
+            MockAsyncClient::with_mock_server(MockServer::new(), jid, "romeo").spawn();
+
+        assert!(events.next().await.unwrap().unwrap().is_online());
+        let mut late_events = handle.subscribe_events();
+
+        let senders: Vec<_> = (0..3)
+            .map(|i| {
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    let message = Element::builder("message", ns::JABBER_CLIENT)
+                        .attr("id", format!("echo-{}", i))
+                        .build();
+                    handle.send_stanza(message).await.unwrap();
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.await.unwrap();
+        }
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let event = events.next().await.unwrap().unwrap();
+            let late_event = late_events.next().await.unwrap().unwrap();
+            assert!(Arc::ptr_eq(&event, &late_event));
+            ids.push(event.as_stanza().unwrap().attr("id").unwrap().to_owned());
+        }
+        ids.sort();
+        assert_eq!(ids, ["echo-0", "echo-1", "echo-2"]);
+
+        handle.close(Duration::from_millis(10)).await.unwrap();
+        assert!(events.next().await.is_none());
+        assert!(handle.is_closed());
+        assert!(matches!(
+            handle
+                .send_stanza(Element::builder("presence", ns::JABBER_CLIENT).build())
+                .await,
+            Err(Error::Disconnected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber() {
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let (handle, mut events) =
+            MockAsyncClient::with_mock_server(MockServer::new(), jid, "romeo")
+                .spawn_with_capacity(2);
+        assert!(events.next().await.unwrap().unwrap().is_online());
+        let mut lagging = handle.subscribe_events();
+
+        for i in 0..3 {
+            let message = Element::builder("message", ns::JABBER_CLIENT)
+                .attr("id", format!("echo-{}", i))
+                .build();
+            handle.send_stanza(message).await.unwrap();
+            assert!(events.next().await.unwrap().unwrap().as_stanza().is_some());
+        }
+
+        assert!(matches!(
+            lagging.next().await,
+            Some(Err(BroadcastStreamRecvError::Lagged(1)))
+        ));
+        let event = lagging.next().await.unwrap().unwrap();
+        assert_eq!(event.as_stanza().unwrap().attr("id"), Some("echo-1"));
+
+        handle.close(Duration::from_millis(10)).await.unwrap();
+        assert!(handle.subscribe_events().next().await.is_none());
+    }
+}
@@ -0,0 +1,71 @@
+//! Interception of raw stanzas by the application
+
+use xmpp_parsers::Element;
+
+/// Outcome of a stanza hook
+#[derive(Debug)]
+pub enum HookAction {
+    /// Let the stanza through unchanged
+    Pass,
+    /// Discard the stanza
+    Drop,
+    /// Let this stanza through instead
+    Replace(Element),
+}
+
+type Hook = Box<dyn Fn(&Element) -> HookAction + Send>;
+
+/// Hooks run on every stanza in one direction, in registration order
+#[derive(Default)]
+pub(crate) struct Hooks(Vec<Hook>);
+
+impl Hooks {
+    pub(crate) fn add<F: Fn(&Element) -> HookAction + Send + 'static>(&mut self, hook: F) {
+        self.0.push(Box::new(hook));
+    }
+
+    /// Returns `None` when a hook dropped the stanza
+    pub(crate) fn run(&self, mut stanza: Element) -> Option<Element> {
+        for hook in &self.0 {
+            match hook(&stanza) {
+                HookAction::Pass => (),
+                HookAction::Drop => return None,
+                HookAction::Replace(replacement) => stanza = replacement,
+            }
+        }
+        Some(stanza)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks() {
+        let mut hooks = Hooks::default();
+        hooks.add(|stanza| {
+            if stanza.is("presence", "jabber:client") {
+                HookAction::Replace(Element::builder("message", "jabber:client").build())
+            } else {
+                HookAction::Pass
+            }
+        });
+        hooks.add(|stanza| {
+            if stanza.attr("type") == Some("error") {
+                HookAction::Drop
+            } else {
+                HookAction::Pass
+            }
+        });
+
+        let presence = Element::builder("presence", "jabber:client").build();
+        let stanza = hooks.run(presence).unwrap();
+        assert!(stanza.is("message", "jabber:client"));
+
+        let error = Element::builder("iq", "jabber:client")
+            .attr("type", "error")
+            .build();
+        assert!(hooks.run(error).is_none());
+    }
+}
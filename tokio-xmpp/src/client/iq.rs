@@ -0,0 +1,237 @@
+//! Correlation of outgoing IQs with their responses
+
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use xmpp_parsers::iq::{Iq, IqType};
+use xmpp_parsers::stanza::with_namespace;
+use xmpp_parsers::{ns, Element, Jid};
+
+use crate::rt::{sleep, Instant, Sleep};
+use crate::Error;
+
+/// Default time to wait for the response to an IQ
+pub const DEFAULT_IQ_TIMEOUT: Duration = Duration::from_secs(60);
+
+struct PendingIq {
+    to: Option<Jid>,
+    sender: oneshot::Sender<Result<Iq, Error>>,
+    /// When this IQ got sent, if it is a ping whose round-trip time to measure
+    ping_sent: Option<Instant>,
+}
+
+/// Keeps track of IQs awaiting a response
+pub(crate) struct IqTracker {
+    pending: HashMap<String, PendingIq>,
+    pub(crate) default_timeout: Duration,
+    /// Round-trip time of the last answered ping
+    pub(crate) last_ping_rtt: Option<Duration>,
+}
+
+impl IqTracker {
+    pub(crate) fn new() -> Self {
+        IqTracker {
+            pending: HashMap::new(),
+            default_timeout: DEFAULT_IQ_TIMEOUT,
+            last_ping_rtt: None,
+        }
+    }
+
+    /// Register an outgoing IQ, returning the future of its response
+    pub(crate) fn register(&mut self, iq: &Iq, timeout: Duration) -> IqResponse {
+        // Forget about responses nobody is waiting for anymore.
+        self.pending
+            .retain(|_, pending| !pending.sender.is_canceled());
+
+        let (sender, receiver) = oneshot::channel();
+        let ping_sent = match iq.payload {
+            IqType::Get(ref payload) if payload.is("ping", ns::PING) => Some(Instant::now()),
+            _ => None,
+        };
+        self.pending.insert(
+            iq.id.clone(),
+            PendingIq {
+                to: iq.to.clone(),
+                sender,
+                ping_sent,
+            },
+        );
+        IqResponse {
+            receiver,
+            timeout: Box::pin(sleep(timeout)),
+        }
+    }
+
+    /// Stop waiting for the response to an IQ which couldn't be sent
+    pub(crate) fn forget(&mut self, id: &str) {
+        self.pending.remove(id);
+    }
+
+    /// Route an incoming stanza to its waiting IQ, if any
+    ///
+    /// Returns the stanza back when it isn't the response to a tracked IQ.
+    pub(crate) fn handle(&mut self, stanza: Element, bound_jid: &Jid) -> Option<Element> {
+        if !stanza.is("iq", ns::JABBER_CLIENT) && !stanza.is("iq", ns::COMPONENT_ACCEPT) {
+            return Some(stanza);
+        }
+        match stanza.attr("type") {
+            Some("result") | Some("error") => (),
+            _ => return Some(stanza),
+        }
+        let id = match stanza.attr("id") {
+            Some(id) => id,
+            None => return Some(stanza),
+        };
+        let from = stanza
+            .attr("from")
+            .and_then(|from| from.parse::<Jid>().ok());
+        let matches = match self.pending.get(id) {
+            Some(pending) => match (&pending.to, &from) {
+                (Some(to), Some(from)) => to == from,
+                (Some(_), None) => false,
+                // Responses from our own account may or may not carry a from.
+                (None, None) => true,
+                (None, Some(from)) => {
+                    *from == bound_jid.to_bare() || from.as_str() == bound_jid.domain().as_str()
+                }
+            },
+            None => false,
+        };
+        if !matches {
+            return Some(stanza);
+        }
+
+        let pending = self.pending.remove(id).unwrap();
+        if let Some(sent) = pending.ping_sent {
+            self.last_ping_rtt = Some(sent.elapsed());
+        }
+        let response = Iq::try_from(with_namespace(stanza, ns::DEFAULT_NS))
+            .map_err(|e| crate::ProtocolError::Parsers(e).into());
+        let _ = pending.sender.send(response);
+        None
+    }
+
+    /// Fail every pending IQ, used when the connection is lost
+    pub(crate) fn fail_all(&mut self) {
+        for (_, pending) in self.pending.drain() {
+            let _ = pending.sender.send(Err(Error::Disconnected));
+        }
+    }
+}
+
+/// Future resolving to the response of an IQ sent with
+/// `send_iq()`
+///
+/// The client must keep being polled for the response to be received.
+/// Resolves to [`Error::IqTimeout`] if no response arrived in time, and
+/// to [`Error::Disconnected`] if the connection was lost meanwhile.
+pub struct IqResponse {
+    receiver: oneshot::Receiver<Result<Iq, Error>>,
+    timeout: Pin<Box<Sleep>>,
+}
+
+impl Future for IqResponse {
+    type Output = Result<Iq, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(Ok(response)) => return Poll::Ready(response),
+            Poll::Ready(Err(oneshot::Canceled)) => return Poll::Ready(Err(Error::Disconnected)),
+            Poll::Pending => (),
+        }
+        match self.timeout.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Error::IqTimeout)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmpp_parsers::ping::Ping;
+
+    #[tokio::test]
+    async fn test_response_routed() {
+        let bound_jid: Jid = "foo@example.org/res".parse().unwrap();
+        let mut tracker = IqTracker::new();
+        let response = tracker.register(&Iq::from_get("ping1", Ping), DEFAULT_IQ_TIMEOUT);
+
+        let other: Element = "<iq xmlns='jabber:client' type='result' id='ping2'/>"
+            .parse()
+            .unwrap();
+        assert!(tracker.handle(other, &bound_jid).is_some());
+        assert_eq!(tracker.last_ping_rtt, None);
+
+        let result: Element =
+            "<iq xmlns='jabber:client' type='result' id='ping1' from='foo@example.org'/>"
+                .parse()
+                .unwrap();
+        assert!(tracker.handle(result, &bound_jid).is_none());
+        assert!(tracker.last_ping_rtt.is_some());
+
+        let iq = response.await.unwrap();
+        assert_eq!(iq.id, "ping1");
+        assert!(matches!(iq.payload, IqType::Result(None)));
+    }
+
+    #[tokio::test]
+    async fn test_response_from_wrong_entity() {
+        let bound_jid: Jid = "foo@example.org/res".parse().unwrap();
+        let mut tracker = IqTracker::new();
+        let to: Jid = "example.net".parse().unwrap();
+        let _response =
+            tracker.register(&Iq::from_get("ping1", Ping).with_to(to), DEFAULT_IQ_TIMEOUT);
+        let spoofed: Element =
+            "<iq xmlns='jabber:client' type='result' id='ping1' from='evil.example'/>"
+                .parse()
+                .unwrap();
+        assert!(tracker.handle(spoofed, &bound_jid).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_component_response() {
+        let component_jid: Jid = "upload.example.org".parse().unwrap();
+        let mut tracker = IqTracker::new();
+        let to: Jid = "example.org".parse().unwrap();
+        let response =
+            tracker.register(&Iq::from_get("ping1", Ping).with_to(to), DEFAULT_IQ_TIMEOUT);
+        let result: Element =
+            "<iq xmlns='jabber:component:accept' type='result' id='ping1' from='example.org'/>"
+                .parse()
+                .unwrap();
+        assert!(tracker.handle(result, &component_jid).is_none());
+        assert_eq!(response.await.unwrap().id, "ping1");
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let mut tracker = IqTracker::new();
+        let response = tracker.register(&Iq::from_get("ping1", Ping), Duration::from_millis(10));
+        assert!(matches!(response.await, Err(Error::IqTimeout)));
+    }
+
+    #[tokio::test]
+    async fn test_disconnected() {
+        let mut tracker = IqTracker::new();
+        let response = tracker.register(&Iq::from_get("ping1", Ping), DEFAULT_IQ_TIMEOUT);
+        tracker.fail_all();
+        assert!(matches!(response.await, Err(Error::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn test_forget() {
+        let bound_jid: Jid = "foo@example.org/res".parse().unwrap();
+        let mut tracker = IqTracker::new();
+        let response = tracker.register(&Iq::from_get("ping1", Ping), DEFAULT_IQ_TIMEOUT);
+        tracker.forget("ping1");
+        assert!(matches!(response.await, Err(Error::Disconnected)));
+        let result: Element = "<iq xmlns='jabber:client' type='result' id='ping1'/>"
+            .parse()
+            .unwrap();
+        assert!(tracker.handle(result, &bound_jid).is_some());
+    }
+}
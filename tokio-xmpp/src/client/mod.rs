@@ -1,7 +1,13 @@
-mod auth;
-mod bind;
+pub(crate) mod auth;
+pub(crate) mod bind;
+pub(crate) mod hooks;
+pub mod iq;
 
 pub(crate) mod connect;
 
 pub mod async_client;
+pub mod handle;
 pub mod simple_client;
+pub mod sm;
+pub mod split;
+pub mod stats;
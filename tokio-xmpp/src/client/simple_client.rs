@@ -1,15 +1,18 @@
 use futures::{sink::SinkExt, Sink, Stream};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio_stream::StreamExt;
-use xmpp_parsers::{ns, Element, Jid};
+use xmpp_parsers::{ns, stream_error::StreamError, Element, Jid};
 
 use crate::connect::ServerConnector;
+use crate::id::{add_stanza_id, IdGenerator};
 use crate::stream_features::StreamFeatures;
 use crate::xmpp_codec::Packet;
-use crate::xmpp_stream::{add_stanza_id, XMPPStream};
-use crate::Error;
+use crate::xmpp_stream::XMPPStream;
+use crate::{Error, ProtocolError};
 
+use super::async_client::Config;
 use super::connect::client_login;
 
 /// A simple XMPP client connection
@@ -18,6 +21,7 @@ use super::connect::client_login;
 /// [`Sink`](#impl-Sink<Packet>) traits.
 pub struct Client<C: ServerConnector> {
     stream: XMPPStream<C::Stream>,
+    id_generator: Arc<dyn IdGenerator>,
 }
 
 impl<C: ServerConnector> Client<C> {
@@ -27,8 +31,18 @@ impl<C: ServerConnector> Client<C> {
         jid: Jid,
         password: String,
     ) -> Result<Self, Error> {
-        let stream = client_login(connector, jid, password).await?;
-        Ok(Client { stream })
+        Self::new_with_config(Config::new(jid, password, connector)).await
+    }
+
+    /// Start a new client from the same configuration an `AsyncClient`
+    /// would use, and wait for a usable session.
+    pub async fn new_with_config(config: Config<C>) -> Result<Self, Error> {
+        let id_generator = config.id_generator.clone();
+        let stream = client_login(config).await?;
+        Ok(Client {
+            stream,
+            id_generator,
+        })
     }
 
     /// Get direct access to inner XMPP Stream
@@ -47,11 +61,8 @@ impl<C: ServerConnector> Client<C> {
     where
         E: Into<Element>,
     {
-        self.send(Packet::Stanza(add_stanza_id(
-            stanza.into(),
-            ns::JABBER_CLIENT,
-        )))
-        .await
+        let stanza = add_stanza_id(stanza.into(), ns::JABBER_CLIENT, &*self.id_generator);
+        self.send(Packet::Stanza(stanza)).await
     }
 
     /// Get the stream features (`<stream:features/>`) of the underlying stream
@@ -85,6 +96,12 @@ impl<C: ServerConnector> Stream for Client<C> {
         loop {
             match Pin::new(&mut self.stream).poll_next(cx) {
                 Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(Packet::Stanza(stanza)))) if stanza.is("error", ns::STREAM) => {
+                    return Poll::Ready(Some(Err(match StreamError::try_from(stanza) {
+                        Ok(error) => Error::StreamError(error),
+                        Err(e) => ProtocolError::Parsers(e).into(),
+                    })))
+                }
                 Poll::Ready(Some(Ok(Packet::Stanza(stanza)))) => {
                     return Poll::Ready(Some(Ok(stanza)))
                 }
@@ -0,0 +1,257 @@
+//! Acknowledgement of stanzas with Stream Management (XEP-0198)
+
+use futures::channel::oneshot;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use xmpp_parsers::sm::{A, R};
+use xmpp_parsers::{ns, Element};
+
+use crate::rt::{sleep, Sleep};
+use crate::sansio::EnableSm;
+use crate::xmpp_stream::{drive, XMPPStream};
+use crate::Error;
+
+/// Enable Stream Management once the resource got bound, if the server
+/// offers it, returning whether it got enabled
+pub async fn enable_sm<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut XMPPStream<S>,
+) -> Result<bool, Error> {
+    if !stream.stream_features.can_sm() {
+        return Ok(false);
+    }
+    drive(stream, &mut EnableSm::new()).await
+}
+
+/// Whether this element counts as a stanza for Stream Management
+pub(crate) fn is_stanza(element: &Element) -> bool {
+    element.is("message", ns::JABBER_CLIENT)
+        || element.is("presence", ns::JABBER_CLIENT)
+        || element.is("iq", ns::JABBER_CLIENT)
+}
+
+/// Whether the count `h` covers the stanza numbered `seq`, counts
+/// wrapping around at 2^32
+fn covers(h: u32, seq: u32) -> bool {
+    h.wrapping_sub(seq) < 1 << 31
+}
+
+/// State of Stream Management on the current stream, once enabled
+#[derive(Debug, Default)]
+pub(crate) struct SmState {
+    /// Stanzas sent
+    sent: u32,
+    /// Stanzas received, which the server may ask us to acknowledge
+    handled: u32,
+    /// Tracked stanzas by number, waiting for the server to acknowledge
+    /// them
+    pending: VecDeque<(u32, oneshot::Sender<Result<(), Error>>)>,
+}
+
+impl SmState {
+    /// Count a sent stanza
+    pub(crate) fn on_sent(&mut self) {
+        self.sent = self.sent.wrapping_add(1);
+    }
+
+    /// Count a received stanza
+    pub(crate) fn on_received(&mut self) {
+        self.handled = self.handled.wrapping_add(1);
+    }
+
+    /// Number of the last stanza sent
+    pub(crate) fn sent(&self) -> u32 {
+        self.sent
+    }
+
+    /// Wait for the server to acknowledge the last stanza sent, for up to
+    /// `timeout` if any
+    pub(crate) fn track_last(&mut self, timeout: Option<Duration>) -> StanzaAck {
+        let (sender, receiver) = oneshot::channel();
+        self.pending.push_back((self.sent, sender));
+        StanzaAck {
+            receiver,
+            timeout: timeout.map(|timeout| Box::pin(sleep(timeout))),
+        }
+    }
+
+    /// Handle an `<a/>` or `<r/>` from the server, returning what to
+    /// answer, or the nonza back if it is neither
+    pub(crate) fn handle(&mut self, nonza: Element) -> Result<Option<Element>, Element> {
+        if let Ok(a) = A::try_from(nonza.clone()) {
+            while let Some((seq, _)) = self.pending.front() {
+                if !covers(a.h, *seq) {
+                    break;
+                }
+                let (_, sender) = self.pending.pop_front().unwrap();
+                let _ = sender.send(Ok(()));
+            }
+            Ok(None)
+        } else if R::try_from(nonza.clone()).is_ok() {
+            Ok(Some(A::new(self.handled).into()))
+        } else {
+            Err(nonza)
+        }
+    }
+
+    /// Fail every tracked stanza, used when the connection is lost
+    pub(crate) fn fail_all(&mut self) {
+        for (_, sender) in self.pending.drain(..) {
+            let _ = sender.send(Err(Error::Disconnected));
+        }
+    }
+}
+
+/// Future resolving once the server acknowledged a stanza sent with
+/// `send_stanza_tracked()`
+///
+/// The client must keep being polled for the acknowledgement to be
+/// received. Resolves to [`Error::Disconnected`] if the connection was lost
+/// meanwhile, in which case the stanza may or may not have been received,
+/// and to [`Error::AckTimeout`] if it was sent with a timeout which expired.
+pub struct StanzaAck {
+    receiver: oneshot::Receiver<Result<(), Error>>,
+    timeout: Option<Pin<Box<Sleep>>>,
+}
+
+impl Future for StanzaAck {
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(Ok(result)) => return Poll::Ready(result),
+            Poll::Ready(Err(oneshot::Canceled)) => return Poll::Ready(Err(Error::Disconnected)),
+            Poll::Pending => (),
+        }
+        match self
+            .timeout
+            .as_mut()
+            .map(|timeout| timeout.as_mut().poll(cx))
+        {
+            Some(Poll::Ready(())) => Poll::Ready(Err(Error::AckTimeout)),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::mock::{MockAsyncClient, MockConnector, MockServer};
+    use crate::AsyncConfig;
+    use futures::future::{select, Either};
+    use futures::{FutureExt, StreamExt};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use xmpp_parsers::Jid;
+
+    fn a(h: u32) -> Element {
+        A::new(h).into()
+    }
+
+    #[test]
+    fn test_acks() {
+        let mut sm = SmState::default();
+        sm.on_sent();
+        let mut first = sm.track_last(None);
+        sm.on_sent();
+        sm.on_sent();
+        let mut third = sm.track_last(None);
+
+        assert_eq!(sm.handle(a(2)), Ok(None));
+        assert!(matches!(first.now_or_never(), Some(Ok(()))));
+        assert!((&mut third).now_or_never().is_none());
+        assert_eq!(sm.handle(a(3)), Ok(None));
+        assert!(matches!(third.now_or_never(), Some(Ok(()))));
+
+        sm.on_sent();
+        first = sm.track_last(None);
+        sm.fail_all();
+        assert!(matches!(
+            first.now_or_never(),
+            Some(Err(Error::Disconnected))
+        ));
+    }
+
+    #[test]
+    fn test_requests() {
+        let mut sm = SmState::default();
+        sm.on_received();
+        sm.on_received();
+        assert_eq!(sm.handle(R.into()), Ok(Some(a(2))));
+        let other = Element::builder("enabled", ns::SM).build();
+        assert_eq!(sm.handle(other.clone()), Err(other));
+    }
+
+    #[tokio::test]
+    async fn test_send_stanza_tracked() {
+        // Enable Stream Management, then acknowledge every stanza once
+        // requested
+        let received = AtomicU32::new(0);
+        let server = MockServer::new()
+            .with_feature(Element::builder("sm", ns::SM).build())
+            .with_handler(move |element| {
+                if element.is("enable", ns::SM) {
+                    vec![Element::builder("enabled", ns::SM).build()]
+                } else if element.is("r", ns::SM) {
+                    vec![a(received.load(Ordering::Relaxed))]
+                } else {
+                    received.fetch_add(1, Ordering::Relaxed);
+                    Vec::new()
+                }
+            });
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let config =
+            AsyncConfig::new(jid, "romeo", MockConnector::new(server)).with_stream_management(true);
+        let mut client = MockAsyncClient::new_with_config(config);
+        assert!(client.next().await.unwrap().is_online());
+
+        let message = Element::builder("message", ns::JABBER_CLIENT).build();
+        let ack = client.send_stanza_tracked(message).await.unwrap();
+        match select(ack, client.next()).await {
+            Either::Left((result, _)) => result.unwrap(),
+            Either::Right((event, _)) => panic!("{:?}", event),
+        }
+        // The <r/> is no stanza
+        assert_eq!(client.stats().stanzas_sent, 1);
+    }
+
+    #[tokio::test]
+    async fn test_ack_timeout() {
+        // Enable Stream Management, but never acknowledge anything
+        let server = MockServer::new()
+            .with_feature(Element::builder("sm", ns::SM).build())
+            .with_handler(|element| {
+                if element.is("enable", ns::SM) {
+                    vec![Element::builder("enabled", ns::SM).build()]
+                } else {
+                    Vec::new()
+                }
+            });
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let config =
+            AsyncConfig::new(jid, "romeo", MockConnector::new(server)).with_stream_management(true);
+        let mut client = MockAsyncClient::new_with_config(config);
+        assert!(client.next().await.unwrap().is_online());
+
+        let message = Element::builder("message", ns::JABBER_CLIENT).build();
+        let ack = client
+            .send_stanza_tracked_with_timeout(message, Duration::from_millis(10))
+            .await
+            .unwrap();
+        match select(ack, client.next()).await {
+            Either::Left((result, _)) => assert!(matches!(result, Err(Error::AckTimeout))),
+            Either::Right((event, _)) => panic!("{:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_wrapping() {
+        assert!(covers(5, 5));
+        assert!(!covers(4, 5));
+        assert!(covers(2, u32::MAX));
+        assert!(!covers(u32::MAX, 2));
+    }
+}
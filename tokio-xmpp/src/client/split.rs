@@ -0,0 +1,215 @@
+//! Independent read and write halves of a client
+
+use futures::task::{AtomicWaker, Poll};
+use futures::{sink::SinkExt, Sink, Stream};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Context;
+use std::time::Duration;
+use xmpp_parsers::{iq::Iq, ns, Element};
+
+use super::async_client::Client;
+use super::iq::IqResponse;
+use crate::connect::ServerConnector;
+use crate::event::Event;
+use crate::id::{add_stanza_id, IdGenerator};
+use crate::xmpp_codec::Packet;
+use crate::Error;
+
+struct Shared<C: ServerConnector> {
+    client: Mutex<Client<C>>,
+    /// Woken whenever the reader got polled, as the client only remembers
+    /// the last task waiting to write, which may be the reader
+    writer: AtomicWaker,
+}
+
+impl<C: ServerConnector> Shared<C> {
+    fn lock(&self) -> MutexGuard<'_, Client<C>> {
+        // A panic while polling leaves the client as consistent as any
+        // other, so don't poison both halves.
+        self.client
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<C: ServerConnector> Client<C> {
+    /// Split the client into halves that can be used from different tasks
+    ///
+    /// The reader drives the connection, reconnections included, and must
+    /// keep being polled for anything to be sent by the writer. Neither
+    /// half holds the client across an `.await`, so neither blocks the
+    /// other.
+    pub fn split(self) -> (ClientReader<C>, ClientWriter<C>) {
+        let id_generator = self.id_generator();
+        let shared = Arc::new(Shared {
+            client: Mutex::new(self),
+            writer: AtomicWaker::new(),
+        });
+        (
+            ClientReader {
+                shared: shared.clone(),
+            },
+            ClientWriter {
+                shared,
+                id_generator,
+            },
+        )
+    }
+}
+
+/// Read half of a client, see `AsyncClient::split()`
+///
+/// Yields the events of the client, as does its [`Stream`] implementation.
+pub struct ClientReader<C: ServerConnector> {
+    shared: Arc<Shared<C>>,
+}
+
+impl<C: ServerConnector> ClientReader<C> {
+    /// Put the client back together, if both halves come from the same one
+    pub fn reunite(self, writer: ClientWriter<C>) -> Result<Client<C>, (Self, ClientWriter<C>)> {
+        if !Arc::ptr_eq(&self.shared, &writer.shared) {
+            return Err((self, writer));
+        }
+        drop(writer);
+        let shared = Arc::into_inner(self.shared).expect("both halves were given");
+        Ok(shared
+            .client
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl<C: ServerConnector> Stream for ClientReader<C> {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut *self.shared.lock()).poll_next(cx);
+        self.shared.writer.wake();
+        poll
+    }
+}
+
+/// Write half of a client, see `AsyncClient::split()`
+///
+/// Sends packets, as does its [`Sink`] implementation.
+pub struct ClientWriter<C: ServerConnector> {
+    shared: Arc<Shared<C>>,
+    id_generator: Arc<dyn IdGenerator>,
+}
+
+impl<C: ServerConnector> ClientWriter<C> {
+    /// Send stanza
+    pub async fn send_stanza(&mut self, stanza: Element) -> Result<(), Error> {
+        let stanza = add_stanza_id(stanza, ns::JABBER_CLIENT, &*self.id_generator);
+        self.send(Packet::Stanza(stanza)).await
+    }
+
+    /// Send an IQ request, and get a future resolving to its response
+    ///
+    /// See `AsyncClient::send_iq()`.
+    pub async fn send_iq(&mut self, iq: Iq) -> Result<IqResponse, Error> {
+        let id = iq.id.clone();
+        let response = self.shared.lock().track_iq(&iq, None);
+        if let Err(e) = self.send(Packet::Stanza(iq.into())).await {
+            self.shared.lock().untrack_iq(&id);
+            return Err(e);
+        }
+        Ok(response)
+    }
+
+    /// Same as `send_iq()`, overriding the timeout for this IQ only
+    pub async fn send_iq_with_timeout(
+        &mut self,
+        iq: Iq,
+        timeout: Duration,
+    ) -> Result<IqResponse, Error> {
+        let id = iq.id.clone();
+        let response = self.shared.lock().track_iq(&iq, Some(timeout));
+        if let Err(e) = self.send(Packet::Stanza(iq.into())).await {
+            self.shared.lock().untrack_iq(&id);
+            return Err(e);
+        }
+        Ok(response)
+    }
+
+    /// End connection by sending `</stream:stream>`, see
+    /// `AsyncClient::send_end()`
+    pub async fn send_end(&mut self) -> Result<(), Error> {
+        self.send(Packet::StreamEnd).await
+    }
+
+    /// Forward a poll to the client, waiting for the reader too when pending
+    fn poll_client<T>(
+        &self,
+        cx: &mut Context,
+        poll: impl FnOnce(Pin<&mut Client<C>>, &mut Context) -> Poll<T>,
+    ) -> Poll<T> {
+        let result = poll(Pin::new(&mut *self.shared.lock()), cx);
+        if result.is_pending() {
+            self.shared.writer.register(cx.waker());
+        }
+        result
+    }
+}
+
+impl<C: ServerConnector> Sink<Packet> for ClientWriter<C> {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.poll_client(cx, |client, cx| client.poll_ready(cx))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
+        Pin::new(&mut *self.shared.lock()).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.poll_client(cx, |client, cx| client.poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.poll_client(cx, |client, cx| client.poll_close(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::mock::{MockAsyncClient, MockServer};
+    use futures::stream::StreamExt;
+    use xmpp_parsers::Jid;
+
+    #[tokio::test]
+    async fn test_split() {
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let server = MockServer::new().with_resource("balcony");
+        let client = MockAsyncClient::with_mock_server(server, jid, "romeo");
+        let (mut reader, mut writer) = client.split();
+
+        match reader.next().await {
+            Some(Event::Online { bound_jid, .. }) => {
+                assert_eq!(bound_jid.resource().unwrap().as_str(), "balcony")
+            }
+            event => panic!("{:?}", event),
+        }
+
+        let sending = tokio::spawn(async move {
+            let message = Element::builder("message", ns::JABBER_CLIENT)
+                .attr("id", "echo")
+                .build();
+            writer.send_stanza(message).await.unwrap();
+            writer
+        });
+        match reader.next().await {
+            Some(Event::Stanza(stanza)) => assert_eq!(stanza.attr("id"), Some("echo")),
+            event => panic!("{:?}", event),
+        }
+
+        let writer = sending.await.unwrap();
+        let Ok(client) = reader.reunite(writer) else {
+            panic!("halves of the same client");
+        };
+        assert_eq!(client.stats().stanzas_sent, 1);
+    }
+}
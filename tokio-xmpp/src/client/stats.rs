@@ -0,0 +1,33 @@
+//! Statistics about the connection of a client
+
+use std::time::Duration;
+
+/// Snapshot of the statistics of a client, see `AsyncClient::stats()`
+///
+/// Unless noted otherwise, counters cover the current connection and are
+/// reset on reconnection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// How long the client has been online, None while disconnected
+    pub uptime: Option<Duration>,
+    /// Round-trip time of the last ping (XEP-0199) sent with `send_iq()`
+    /// and answered, kept across reconnections
+    ///
+    /// Only the pings the application sends are measured: the client never
+    /// sends any itself, its whitespace keepalive getting no answer. None
+    /// until the application sends one.
+    pub last_ping_rtt: Option<Duration>,
+    /// Stanzas (messages, presences and iqs) sent since the client got
+    /// online, without nonzas such as Stream Management requests
+    pub stanzas_sent: u64,
+    /// Stanzas received since the client got online, including those
+    /// dropped by hooks or answering a tracked IQ
+    pub stanzas_received: u64,
+    /// Bytes sent since the last stream restart, that is after TLS and
+    /// authentication got negotiated
+    pub bytes_sent: u64,
+    /// Bytes received since the last stream restart
+    pub bytes_received: u64,
+    /// Times the client got online, reconnections included
+    pub connections: u32,
+}
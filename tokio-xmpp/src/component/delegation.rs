@@ -0,0 +1,245 @@
+//! Namespace Delegation (XEP-0355): receiving, and answering, the iqs the
+//! server delegates to a component, e.g. to handle HTTP File Upload on its
+//! behalf
+
+use xmpp_parsers::{
+    delegation::{Delegated, Delegation, DelegationForward},
+    iq::{Iq, IqType},
+    ns,
+    stanza_error::{DefinedCondition, ErrorType, StanzaError},
+    Element, Jid,
+};
+
+/// What `Delegations::handle` made of a received stanza
+#[derive(Debug)]
+pub enum DelegationEvent {
+    /// The server advertised the namespaces it delegates to this component
+    Advertised(Vec<Delegated>),
+    /// An iq of a delegated namespace, forwarded by the server
+    Request(Box<DelegatedRequest>),
+    /// Any other stanza, left for the application to handle
+    Stanza(Element),
+}
+
+/// An iq forwarded by the server, to be answered with `respond`
+#[derive(Debug)]
+pub struct DelegatedRequest {
+    component: Jid,
+    server: Jid,
+    id: String,
+    /// The iq as sent to the server
+    pub iq: Iq,
+}
+
+impl DelegatedRequest {
+    /// The namespace of the payload of the delegated iq
+    pub fn namespace(&self) -> Option<String> {
+        match self.iq.payload {
+            IqType::Get(ref payload) | IqType::Set(ref payload) => Some(payload.ns()),
+            IqType::Result(_) | IqType::Error(_) => None,
+        }
+    }
+
+    /// Builds the stanza answering this request with `payload`, which the
+    /// server forwards to the original sender on its own behalf
+    pub fn respond(self, payload: IqType) -> Element {
+        let response = Iq {
+            from: self.iq.to,
+            to: self.iq.from,
+            id: self.iq.id,
+            payload,
+        };
+        Element::builder("iq", ns::COMPONENT_ACCEPT)
+            .attr("from", self.component)
+            .attr("to", self.server)
+            .attr("id", self.id)
+            .attr("type", "result")
+            .append(DelegationForward { iq: response })
+            .build()
+    }
+
+    /// Builds the stanza answering this request with a
+    /// service-unavailable error, for namespaces this component doesn't
+    /// handle after all
+    pub fn refuse(self) -> Element {
+        let error = StanzaError::new(
+            ErrorType::Cancel,
+            DefinedCondition::ServiceUnavailable,
+            "en",
+            "",
+        );
+        self.respond(IqType::Error(error))
+    }
+}
+
+/// Keeps track of the namespaces the server delegates to a component, and
+/// extracts the iqs it forwards
+///
+/// Forwarded iqs are only accepted from the server which advertised the
+/// delegation, and for the namespaces it delegated.
+#[derive(Debug)]
+pub struct Delegations {
+    component: Jid,
+    server: Option<Jid>,
+    delegated: Vec<Delegated>,
+}
+
+impl Delegations {
+    /// Track delegations to the component `jid`
+    pub fn new(jid: Jid) -> Self {
+        Delegations {
+            component: jid,
+            server: None,
+            delegated: Vec::new(),
+        }
+    }
+
+    /// Whether the server delegated `namespace` to this component
+    pub fn is_delegated(&self, namespace: &str) -> bool {
+        self.delegated
+            .iter()
+            .any(|delegated| delegated.namespace == namespace)
+    }
+
+    /// Handles a stanza received by the component
+    pub fn handle(&mut self, stanza: Element) -> DelegationEvent {
+        let from = stanza
+            .attr("from")
+            .and_then(|from| from.parse::<Jid>().ok());
+        let Some(child) = stanza.get_child("delegation", ns::DELEGATION) else {
+            return DelegationEvent::Stanza(stanza);
+        };
+        match (stanza.name(), stanza.attr("type"), from) {
+            ("message", _, Some(from)) if self.is_server(&from) => {
+                let Ok(delegation) = Delegation::try_from(child.clone()) else {
+                    return DelegationEvent::Stanza(stanza);
+                };
+                self.server = Some(from);
+                self.delegated = delegation.delegated.clone();
+                DelegationEvent::Advertised(delegation.delegated)
+            }
+            ("iq", Some("set"), Some(from)) if self.server.as_ref() == Some(&from) => {
+                let (Ok(forward), Some(id)) = (
+                    DelegationForward::try_from(child.clone()),
+                    stanza.attr("id"),
+                ) else {
+                    return DelegationEvent::Stanza(stanza);
+                };
+                let request = DelegatedRequest {
+                    component: self.component.clone(),
+                    server: from,
+                    id: id.to_owned(),
+                    iq: forward.iq,
+                };
+                match request.namespace() {
+                    Some(namespace) if self.is_delegated(&namespace) => {
+                        DelegationEvent::Request(Box::new(request))
+                    }
+                    _ => DelegationEvent::Stanza(stanza),
+                }
+            }
+            _ => DelegationEvent::Stanza(stanza),
+        }
+    }
+
+    /// The server hosting the component is its parent domain
+    fn is_server(&self, jid: &Jid) -> bool {
+        if jid.node().is_some() || jid.resource().is_some() {
+            return false;
+        }
+        let component = self.component.domain();
+        let server = jid.domain();
+        component
+            .as_str()
+            .strip_suffix(server.as_str())
+            .is_some_and(|prefix| prefix.ends_with('.'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmpp_parsers::ping::Ping;
+
+    fn advertise(delegations: &mut Delegations, from: &str) -> bool {
+        let message: Element = format!(
+            "<message xmlns='jabber:component:accept' from='{}' to='upload.capulet.lit'>
+              <delegation xmlns='urn:xmpp:delegation:2'>
+                <delegated namespace='urn:xmpp:ping'/>
+              </delegation>
+            </message>",
+            from
+        )
+        .parse()
+        .unwrap();
+        matches!(delegations.handle(message), DelegationEvent::Advertised(_))
+    }
+
+    fn forwarded(from: &str, payload: &str) -> Element {
+        format!(
+            "<iq xmlns='jabber:component:accept' from='{}' to='upload.capulet.lit' type='set' id='delegate1'>
+              <delegation xmlns='urn:xmpp:delegation:2'>
+                <forwarded xmlns='urn:xmpp:forward:0'>
+                  <iq xmlns='jabber:client' from='juliet@capulet.lit/balcony' to='capulet.lit' type='get' id='ping1'>{}</iq>
+                </forwarded>
+              </delegation>
+            </iq>",
+            from, payload
+        )
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_advertisement() {
+        let mut delegations = Delegations::new(Jid::new("upload.capulet.lit").unwrap());
+        assert!(!advertise(&mut delegations, "montague.lit"));
+        assert!(!advertise(&mut delegations, "juliet@capulet.lit"));
+        assert!(!delegations.is_delegated(ns::PING));
+        assert!(advertise(&mut delegations, "capulet.lit"));
+        assert!(delegations.is_delegated(ns::PING));
+        assert!(!delegations.is_delegated(ns::MAM));
+    }
+
+    #[test]
+    fn test_request() {
+        let mut delegations = Delegations::new(Jid::new("upload.capulet.lit").unwrap());
+        let ping = "<ping xmlns='urn:xmpp:ping'/>";
+
+        // Nothing is delegated yet.
+        let event = delegations.handle(forwarded("capulet.lit", ping));
+        assert!(matches!(event, DelegationEvent::Stanza(_)));
+
+        assert!(advertise(&mut delegations, "capulet.lit"));
+        let event = delegations.handle(forwarded("montague.lit", ping));
+        assert!(matches!(event, DelegationEvent::Stanza(_)));
+        let event = delegations.handle(forwarded("capulet.lit", "<query xmlns='urn:xmpp:mam:2'/>"));
+        assert!(matches!(event, DelegationEvent::Stanza(_)));
+
+        let DelegationEvent::Request(request) = delegations.handle(forwarded("capulet.lit", ping))
+        else {
+            panic!();
+        };
+        assert_eq!(request.namespace().as_deref(), Some(ns::PING));
+        assert!(request.iq.get_payload::<Ping>().unwrap().is_ok());
+
+        let response = request.respond(IqType::Result(None));
+        assert_eq!(response.attr("to"), Some("capulet.lit"));
+        assert_eq!(response.attr("from"), Some("upload.capulet.lit"));
+        assert_eq!(response.attr("id"), Some("delegate1"));
+        assert_eq!(response.attr("type"), Some("result"));
+        let forward = DelegationForward::try_from(
+            response
+                .get_child("delegation", ns::DELEGATION)
+                .unwrap()
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(forward.iq.id, "ping1");
+        assert_eq!(
+            forward.iq.to,
+            Some(Jid::new("juliet@capulet.lit/balcony").unwrap())
+        );
+        assert_eq!(forward.iq.from, Some(Jid::new("capulet.lit").unwrap()));
+    }
+}
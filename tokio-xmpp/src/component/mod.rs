@@ -5,18 +5,24 @@ use futures::{sink::SinkExt, task::Poll, Sink, Stream};
 use std::pin::Pin;
 use std::str::FromStr;
 use std::task::Context;
-use xmpp_parsers::{ns, Element, Jid};
+use std::time::Duration;
+use xmpp_parsers::{iq::Iq, ns, stanza::with_namespace, Element, Jid};
 
 use self::connect::component_login;
 
 use super::xmpp_codec::Packet;
 use super::Error;
+use crate::client::iq::{IqResponse, IqTracker};
 use crate::connect::ServerConnector;
-use crate::xmpp_stream::add_stanza_id;
+use crate::id::{add_stanza_id, UuidGenerator};
 use crate::xmpp_stream::XMPPStream;
 
 mod auth;
 
+pub mod delegation;
+mod pool;
+pub use self::pool::ComponentPool;
+
 pub(crate) mod connect;
 
 /// Component connection to an XMPP server
@@ -27,6 +33,7 @@ pub struct Component<C: ServerConnector> {
     /// The component's Jabber-Id
     pub jid: Jid,
     stream: XMPPStream<C::Stream>,
+    iq_tracker: IqTracker,
 }
 
 impl<C: ServerConnector> Component<C> {
@@ -39,17 +46,73 @@ impl<C: ServerConnector> Component<C> {
         let jid = Jid::from_str(jid)?;
         let password = password.to_owned();
         let stream = component_login(connector, jid.clone(), password).await?;
-        Ok(Component { jid, stream })
+        Ok(Component {
+            jid,
+            stream,
+            iq_tracker: IqTracker::new(),
+        })
+    }
+
+    /// Set how long `send_iq()` waits for a response before failing
+    pub fn set_iq_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.iq_tracker.default_timeout = timeout;
+        self
+    }
+
+    /// Keep incoming text longer than `size` bytes as several text nodes,
+    /// see `XMPPCodec::set_text_chunk_size`
+    pub fn set_text_chunk_size(&mut self, size: Option<usize>) -> &mut Self {
+        self.stream.stream.codec_mut().set_text_chunk_size(size);
+        self
     }
 
     /// Send stanza
+    ///
+    /// Stanzas in the `jabber:client` namespace, as built by xmpp_parsers
+    /// without its `component` feature, are moved to the namespace of
+    /// component streams.
     pub async fn send_stanza(&mut self, stanza: Element) -> Result<(), Error> {
-        self.send(add_stanza_id(stanza, ns::COMPONENT_ACCEPT)).await
+        self.send(prepare_stanza(stanza)).await
+    }
+
+    /// Send an IQ request, and get a future resolving to its response
+    ///
+    /// The IQ must have a recipient, and its id must be unique among the
+    /// requests currently in flight. The response is not yielded by the
+    /// `Stream`; it is only delivered through the returned future, which
+    /// requires this component to keep being polled.
+    pub async fn send_iq(&mut self, iq: Iq) -> Result<IqResponse, Error> {
+        let timeout = self.iq_tracker.default_timeout;
+        self.send_iq_with_timeout(iq, timeout).await
+    }
+
+    /// Same as `send_iq()`, overriding the timeout for this IQ only
+    pub async fn send_iq_with_timeout(
+        &mut self,
+        iq: Iq,
+        timeout: Duration,
+    ) -> Result<IqResponse, Error> {
+        let id = iq.id.clone();
+        let response = self.iq_tracker.register(&iq, timeout);
+        if let Err(e) = self.send_stanza(iq.into()).await {
+            self.iq_tracker.forget(&id);
+            return Err(e);
+        }
+        Ok(response)
     }
 
     /// End connection
     pub async fn send_end(&mut self) -> Result<(), Error> {
-        self.close().await
+        SinkExt::close(self).await
+    }
+
+    /// Close the connection gracefully
+    ///
+    /// Flushes pending stanzas, then completes the two-way stream closure
+    /// waiting at most `timeout` for the server, before shutting down the
+    /// transport.
+    pub async fn shutdown(self, timeout: Duration) -> Result<(), Error> {
+        self.stream.shutdown(timeout).await
     }
 }
 
@@ -59,17 +122,25 @@ impl<C: ServerConnector> Stream for Component<C> {
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         loop {
             match Pin::new(&mut self.stream).poll_next(cx) {
-                Poll::Ready(Some(Ok(Packet::Stanza(stanza)))) => return Poll::Ready(Some(stanza)),
+                Poll::Ready(Some(Ok(Packet::Stanza(stanza)))) => {
+                    let jid = self.jid.clone();
+                    if let Some(stanza) = self.iq_tracker.handle(stanza, &jid) {
+                        return Poll::Ready(Some(stanza));
+                    }
+                }
                 Poll::Ready(Some(Ok(Packet::Text(_)))) => {
                     // retry
                 }
-                Poll::Ready(Some(Ok(_))) =>
-                // unexpected
-                {
-                    return Poll::Ready(None)
+                Poll::Ready(Some(Err(e))) => {
+                    self.stream.poll_fail(cx, &e);
+                    self.iq_tracker.fail_all();
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(Ok(_))) | Poll::Ready(None) => {
+                    // unexpected, or end of stream
+                    self.iq_tracker.fail_all();
+                    return Poll::Ready(None);
                 }
-                Poll::Ready(Some(Err(_))) => return Poll::Ready(None),
-                Poll::Ready(None) => return Poll::Ready(None),
                 Poll::Pending => return Poll::Pending,
             }
         }
@@ -103,3 +174,42 @@ impl<C: ServerConnector> Sink<Element> for Component<C> {
             .map_err(|e| e.into())
     }
 }
+
+/// Moves a stanza built for clients to the namespace of component streams,
+/// and gives it an id if it has none.
+pub(crate) fn prepare_stanza(stanza: Element) -> Element {
+    let is_client_stanza = ["iq", "message", "presence"]
+        .iter()
+        .any(|name| stanza.is(name, ns::JABBER_CLIENT));
+    let stanza = if is_client_stanza {
+        with_namespace(stanza, ns::COMPONENT_ACCEPT)
+    } else {
+        stanza
+    };
+    add_stanza_id(stanza, ns::COMPONENT_ACCEPT, &UuidGenerator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_stanza() {
+        let message: Element =
+            "<message xmlns='jabber:client' to='juliet@capulet.lit'><body>Hi</body></message>"
+                .parse()
+                .unwrap();
+        let message = prepare_stanza(message);
+        assert!(message.is("message", ns::COMPONENT_ACCEPT));
+        assert!(message.has_child("body", ns::COMPONENT_ACCEPT));
+        assert!(message.attr("id").is_some());
+
+        let presence: Element = "<presence xmlns='jabber:component:accept' id='a'/>"
+            .parse()
+            .unwrap();
+        assert_eq!(prepare_stanza(presence.clone()), presence);
+
+        let nonza: Element = "<r xmlns='urn:xmpp:sm:3'/>".parse().unwrap();
+        assert_eq!(prepare_stanza(nonza.clone()), nonza);
+    }
+}
@@ -0,0 +1,275 @@
+//! Several connections of the same component, for gateways with more
+//! traffic than a single stream can carry
+
+use futures::{future::try_join_all, task::Poll, Sink, Stream};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::task::Context;
+use xmpp_parsers::{Element, Jid};
+
+use super::{prepare_stanza, Component};
+use crate::connect::ServerConnector;
+use crate::Error;
+
+/// Parallel connections of a component to the server, presented as a
+/// single `Stream`/`Sink` of stanzas
+///
+/// Stanzas are received from all connections. Outgoing stanzas are spread
+/// across connections according to their recipient, so that the stanzas
+/// sent to a given address keep their order; those without a recipient
+/// are sent in turn on every connection.
+///
+/// A lost or failing connection is dropped from the pool, along with the
+/// stanzas it didn't send yet, and the stream only ends once every
+/// connection is lost. Reconnecting is up to the user.
+pub struct ComponentPool<C: ServerConnector> {
+    /// The component's Jabber-Id
+    pub jid: Jid,
+    connections: Vec<Component<C>>,
+    next_received: usize,
+    next_sent: usize,
+}
+
+impl<C: ServerConnector> ComponentPool<C> {
+    /// Open `connections` connections of the component, all authenticated
+    /// with the same secret
+    pub async fn new_with_connector(
+        jid: &str,
+        password: &str,
+        connector: C,
+        connections: usize,
+    ) -> Result<Self, Error> {
+        let connections = try_join_all(
+            (0..connections.max(1))
+                .map(|_| Component::new_with_connector(jid, password, connector.clone())),
+        )
+        .await?;
+        Ok(ComponentPool {
+            jid: connections[0].jid.clone(),
+            connections,
+            next_received: 0,
+            next_sent: 0,
+        })
+    }
+
+    /// Number of connections still open
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Whether every connection got lost
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Send stanza
+    pub async fn send_stanza(&mut self, stanza: Element) -> Result<(), Error> {
+        futures::SinkExt::send(self, prepare_stanza(stanza)).await
+    }
+
+    /// Close every connection
+    pub async fn send_end(&mut self) -> Result<(), Error> {
+        futures::SinkExt::close(self).await
+    }
+}
+
+/// Returns the index of the connection to send a stanza on
+fn connection_for(stanza: &Element, connections: usize, next: &mut usize) -> usize {
+    match stanza.attr("to") {
+        Some(to) => {
+            let mut hasher = DefaultHasher::new();
+            to.hash(&mut hasher);
+            (hasher.finish() % connections as u64) as usize
+        }
+        None => {
+            let index = *next % connections;
+            *next = index + 1;
+            index
+        }
+    }
+}
+
+impl<C: ServerConnector> Stream for ComponentPool<C> {
+    type Item = Element;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        // Start with a different connection every time, so that none of them
+        // starves the others.
+        let mut polled = 0;
+        while polled < self.connections.len() {
+            let index = self.next_received % self.connections.len();
+            match Pin::new(&mut self.connections[index]).poll_next(cx) {
+                Poll::Ready(Some(stanza)) => {
+                    self.next_received = index + 1;
+                    return Poll::Ready(Some(stanza));
+                }
+                Poll::Ready(None) => {
+                    // This connection is lost, keep going with the others.
+                    self.connections.remove(index);
+                }
+                Poll::Pending => {
+                    self.next_received = index + 1;
+                    polled += 1;
+                }
+            }
+        }
+        if self.connections.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+type PollFn<C> = fn(Pin<&mut Component<C>>, &mut Context) -> Poll<Result<(), Error>>;
+
+/// Polls every connection, dropping those which fail, until all the others
+/// are ready; only fails once no connection is left
+fn poll_all<C: ServerConnector>(
+    connections: &mut Vec<Component<C>>,
+    cx: &mut Context,
+    poll: PollFn<C>,
+) -> Poll<Result<(), Error>> {
+    let mut ready = true;
+    connections.retain_mut(|connection| match poll(Pin::new(connection), cx) {
+        Poll::Ready(Ok(())) => true,
+        Poll::Ready(Err(e)) => {
+            log::warn!("Dropping a failed connection from the pool: {}", e);
+            false
+        }
+        Poll::Pending => {
+            ready = false;
+            true
+        }
+    });
+    if connections.is_empty() {
+        Poll::Ready(Err(Error::Disconnected))
+    } else if ready {
+        Poll::Ready(Ok(()))
+    } else {
+        Poll::Pending
+    }
+}
+
+impl<C: ServerConnector> Sink<Element> for ComponentPool<C> {
+    type Error = Error;
+
+    /// Ready once every connection is, since the next stanza may go to any
+    /// of them
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        poll_all(&mut self.connections, cx, Sink::poll_ready)
+    }
+
+    /// Sends the stanza on the connection of its recipient, or on another
+    /// one if that connection fails
+    fn start_send(mut self: Pin<&mut Self>, item: Element) -> Result<(), Self::Error> {
+        let this = &mut *self;
+        while !this.connections.is_empty() {
+            let index = connection_for(&item, this.connections.len(), &mut this.next_sent);
+            match Pin::new(&mut this.connections[index]).start_send(item.clone()) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    log::warn!("Dropping a failed connection from the pool: {}", e);
+                    this.connections.remove(index);
+                }
+            }
+        }
+        Err(Error::Disconnected)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        poll_all(&mut self.connections, cx, Sink::poll_flush)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        // Failed connections are as good as closed.
+        match poll_all(&mut self.connections, cx, Sink::poll_close) {
+            Poll::Ready(_) => Poll::Ready(Ok(())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::iq::IqTracker;
+    use crate::connect::mock::MockConnector;
+    use crate::stream_features::StreamFeatures;
+    use crate::xmpp_codec::XMPPCodec;
+    use crate::xmpp_stream::XMPPStream;
+    use futures::SinkExt;
+    use tokio::io::{AsyncReadExt, DuplexStream};
+    use tokio_util::codec::Framed;
+    use xmpp_parsers::ns;
+
+    /// A connection whose stream got negotiated already, along with the
+    /// server's end of it
+    fn connection(jid: &Jid) -> (Component<MockConnector>, DuplexStream) {
+        let (client, server) = tokio::io::duplex(4096);
+        let stream = XMPPStream::new(
+            jid.clone(),
+            Framed::new(client, XMPPCodec::new()),
+            ns::COMPONENT_ACCEPT.to_owned(),
+            String::from("pool"),
+            StreamFeatures::default(),
+        );
+        let component = Component {
+            jid: jid.clone(),
+            stream,
+            iq_tracker: IqTracker::new(),
+        };
+        (component, server)
+    }
+
+    #[tokio::test]
+    async fn test_failed_connection() {
+        let jid = Jid::new("gateway.capulet.lit").unwrap();
+        let (first, first_server) = connection(&jid);
+        let (second, mut second_server) = connection(&jid);
+        let mut pool = ComponentPool {
+            jid,
+            connections: vec![first, second],
+            next_received: 0,
+            next_sent: 0,
+        };
+        drop(first_server);
+
+        // Stanzas without a recipient go to the failed connection first.
+        let presence = Element::builder("presence", ns::COMPONENT_ACCEPT).build();
+        pool.send(presence.clone()).await.unwrap();
+        assert_eq!(pool.len(), 1);
+        pool.send(presence).await.unwrap();
+
+        let mut buf = [0; 4096];
+        let read = second_server.read(&mut buf).await.unwrap();
+        assert!(std::str::from_utf8(&buf[..read])
+            .unwrap()
+            .contains("<presence"));
+    }
+
+    #[test]
+    fn test_connection_for() {
+        let mut next = 0;
+        let to_juliet: Element =
+            "<message xmlns='jabber:component:accept' to='juliet@capulet.lit/balcony'/>"
+                .parse()
+                .unwrap();
+        let index = connection_for(&to_juliet, 4, &mut next);
+        assert!(index < 4);
+        // The same recipient always gets the same connection.
+        for _ in 0..8 {
+            assert_eq!(connection_for(&to_juliet, 4, &mut next), index);
+        }
+        assert_eq!(next, 0);
+
+        let broadcast: Element = "<presence xmlns='jabber:component:accept'/>"
+            .parse()
+            .unwrap();
+        let indices: Vec<_> = (0..5)
+            .map(|_| connection_for(&broadcast, 4, &mut next))
+            .collect();
+        assert_eq!(indices, [0, 1, 2, 3, 0]);
+    }
+}
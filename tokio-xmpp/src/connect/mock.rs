@@ -0,0 +1,342 @@
+//! `MockConnector` connects to an in-memory fake server, for tests
+//!
+//! Each connection gets its own [`MockServer`] task over a
+//! `tokio::io::duplex` pipe, which expects a stream header, offers SASL
+//! PLAIN, binds a resource, then echoes every stanza back unless a handler
+//! answers them. This lets applications test against this crate without a
+//! real XMPP server.
+
+use core::fmt;
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+use xmpp_parsers::{
+    iq::{Iq, IqType},
+    ns,
+    sasl::Auth,
+    Element, Jid,
+};
+
+use crate::connect::{ServerConnector, ServerConnectorError};
+use crate::xmpp_codec::{Packet, XMPPCodec};
+use crate::xmpp_stream::XMPPStream;
+use crate::{AsyncClient, AsyncConfig};
+
+/// AsyncClient connected to a fake server
+pub type MockAsyncClient = AsyncClient<MockConnector>;
+
+/// Size of the in-memory pipes, in bytes
+const BUFFER_SIZE: usize = 65536;
+
+/// Resource bound when the client doesn't request one
+const DEFAULT_RESOURCE: &str = "mock";
+
+type Handler = dyn Fn(Element) -> Vec<Element> + Send + Sync;
+
+/// Script of the fake server
+///
+/// By default it accepts any password, binds the requested resource, and
+/// echoes every stanza and nonza it receives.
+#[derive(Clone, Default)]
+pub struct MockServer {
+    password: Option<String>,
+    resource: Option<String>,
+    features: Vec<Element>,
+    handler: Option<Arc<Handler>>,
+}
+
+impl MockServer {
+    /// A server accepting any login and echoing everything
+    pub fn new() -> Self {
+        MockServer::default()
+    }
+
+    /// Refuse logins without this password, with `<not-authorized/>`
+    pub fn with_password<P: Into<String>>(mut self, password: P) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Bind this resource whatever the client requests, as if it were
+    /// already in use
+    pub fn with_resource<R: Into<String>>(mut self, resource: R) -> Self {
+        self.resource = Some(resource.into());
+        self
+    }
+
+    /// Also offer this feature after authentication, e.g. `<sm/>` for
+    /// Stream Management, whose nonzas `with_handler()` then answers
+    pub fn with_feature(mut self, feature: Element) -> Self {
+        self.features.push(feature);
+        self
+    }
+
+    /// Answer what the client sends once online with the elements `handler`
+    /// returns, instead of echoing it
+    pub fn with_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Element) -> Vec<Element> + Send + Sync + 'static,
+    {
+        self.handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Serve one client connection, until it closes the stream
+    pub async fn serve(self, stream: DuplexStream) -> Result<(), Error> {
+        let mut stream = Framed::new(stream, XMPPCodec::new());
+
+        let domain = expect_stream_start(&mut stream).await?;
+        send_stream_start(&mut stream, &domain).await?;
+        let mechanisms = Element::builder("mechanisms", ns::SASL)
+            .append(Element::builder("mechanism", ns::SASL).append("PLAIN"));
+        send_features(&mut stream, vec![mechanisms.build()]).await?;
+
+        let auth = Auth::try_from(expect_stanza(&mut stream).await?)
+            .map_err(|_| Error::Unexpected("<auth/>"))?;
+        // authzid, authcid, password
+        let mut fields = auth.data.split(|&byte| byte == 0).skip(1);
+        let username = String::from_utf8_lossy(fields.next().unwrap_or_default()).into_owned();
+        let password = String::from_utf8_lossy(fields.next().unwrap_or_default());
+        if self.password.as_ref().is_some_and(|p| *p != password) {
+            let failure = Element::builder("failure", ns::SASL)
+                .append(Element::builder("not-authorized", ns::SASL))
+                .build();
+            stream.send(Packet::Stanza(failure)).await?;
+            stream.send(Packet::StreamEnd).await?;
+            return Ok(());
+        }
+        let success = Element::builder("success", ns::SASL).build();
+        stream.send(Packet::Stanza(success)).await?;
+
+        // The client restarts the stream, with a new parser
+        let mut stream = stream.map_codec(|_| XMPPCodec::new());
+        expect_stream_start(&mut stream).await?;
+        send_stream_start(&mut stream, &domain).await?;
+        let mut features = vec![Element::builder("bind", ns::BIND).build()];
+        features.extend(self.features.iter().cloned());
+        send_features(&mut stream, features).await?;
+
+        let iq = Iq::try_from(expect_stanza(&mut stream).await?)
+            .map_err(|_| Error::Unexpected("resource binding"))?;
+        let requested = match iq.payload {
+            IqType::Set(ref bind) => bind
+                .get_child("resource", ns::BIND)
+                .map(|resource| resource.text()),
+            _ => return Err(Error::Unexpected("resource binding")),
+        };
+        let resource = self
+            .resource
+            .clone()
+            .or(requested)
+            .unwrap_or_else(|| String::from(DEFAULT_RESOURCE));
+        let jid = if username.is_empty() {
+            format!("{}/{}", domain, resource)
+        } else {
+            format!("{}@{}/{}", username, domain, resource)
+        };
+        let bound = Element::builder("bind", ns::BIND)
+            .append(Element::builder("jid", ns::BIND).append(jid))
+            .build();
+        let result = Iq {
+            from: None,
+            to: None,
+            id: iq.id,
+            payload: IqType::Result(Some(bound)),
+        };
+        stream.send(Packet::Stanza(result.into())).await?;
+
+        while let Some(packet) = stream.next().await {
+            match packet? {
+                Packet::Stanza(stanza) => {
+                    let answers = match self.handler {
+                        Some(ref handler) => handler(stanza),
+                        None => vec![stanza],
+                    };
+                    for answer in answers {
+                        stream.feed(Packet::Stanza(answer)).await?;
+                    }
+                    stream.flush().await?;
+                }
+                Packet::StreamEnd => {
+                    stream.send(Packet::StreamEnd).await?;
+                    return Ok(());
+                }
+                Packet::Text(_) => (),
+                Packet::StreamStart(_) => return Err(Error::Unexpected("stream header")),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MockServer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("MockServer")
+            .field("password", &self.password.as_ref().map(|_| "<hidden>"))
+            .field("resource", &self.resource)
+            .field("features", &self.features)
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
+
+type ServerStream = Framed<DuplexStream, XMPPCodec>;
+
+/// Wait for the stream header, returning the domain it is addressed to
+async fn expect_stream_start(stream: &mut ServerStream) -> Result<String, Error> {
+    match stream.next().await {
+        Some(Ok(Packet::StreamStart(attrs))) => Ok(attrs.get("to").cloned().unwrap_or_default()),
+        Some(Ok(_)) => Err(Error::Unexpected("stream header")),
+        Some(Err(e)) => Err(e.into()),
+        None => Err(crate::Error::Disconnected.into()),
+    }
+}
+
+/// Wait for the next stanza or nonza, skipping whitespace
+async fn expect_stanza(stream: &mut ServerStream) -> Result<Element, Error> {
+    loop {
+        match stream.next().await {
+            Some(Ok(Packet::Stanza(stanza))) => return Ok(stanza),
+            Some(Ok(Packet::Text(_))) => (),
+            Some(Ok(_)) => return Err(Error::Unexpected("stanza")),
+            Some(Err(e)) => return Err(e.into()),
+            None => return Err(crate::Error::Disconnected.into()),
+        }
+    }
+}
+
+async fn send_stream_start(stream: &mut ServerStream, domain: &str) -> Result<(), Error> {
+    let attrs: HashMap<String, String> = [
+        ("xmlns", ns::JABBER_CLIENT),
+        ("xmlns:stream", ns::STREAM),
+        ("version", "1.0"),
+        ("from", domain),
+        ("id", "mock"),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_owned(), value.to_owned()))
+    .collect();
+    Ok(stream.send(Packet::StreamStart(attrs)).await?)
+}
+
+async fn send_features(stream: &mut ServerStream, features: Vec<Element>) -> Result<(), Error> {
+    let features = Element::builder("features", ns::STREAM)
+        .append_all(features)
+        .build();
+    Ok(stream.send(Packet::Stanza(features)).await?)
+}
+
+/// Connector starting a [`MockServer`] for each connection
+#[derive(Debug, Clone, Default)]
+pub struct MockConnector {
+    server: MockServer,
+}
+
+impl MockConnector {
+    /// Connect to fake servers following this script
+    pub fn new(server: MockServer) -> Self {
+        MockConnector { server }
+    }
+}
+
+impl ServerConnector for MockConnector {
+    type Stream = DuplexStream;
+    type Error = Error;
+    async fn connect(&self, jid: &Jid, ns: &str) -> Result<XMPPStream<Self::Stream>, Self::Error> {
+        let (client, server) = tokio::io::duplex(BUFFER_SIZE);
+        let script = self.server.clone();
+        crate::rt::spawn(async move {
+            if let Err(e) = script.serve(server).await {
+                log::warn!("Mock server failed: {}", e);
+            }
+        });
+        Ok(XMPPStream::start(client, jid.clone(), ns.to_owned()).await?)
+    }
+}
+
+impl AsyncClient<MockConnector> {
+    /// Start a new XMPP client connecting to fake servers following this
+    /// script
+    pub fn with_mock_server<J: Into<Jid>, P: Into<String>>(
+        server: MockServer,
+        jid: J,
+        password: P,
+    ) -> Self {
+        let config = AsyncConfig::new(jid, password, MockConnector::new(server));
+        Self::new_with_config(config)
+    }
+}
+
+/// Mock ServerConnector Error
+#[derive(Debug)]
+pub enum Error {
+    /// The client sent something other than the expected element
+    Unexpected(&'static str),
+    /// tokio-xmpp error
+    TokioXMPP(crate::error::Error),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Unexpected(expected) => write!(fmt, "expected {} from the client", expected),
+            Error::TokioXMPP(e) => write!(fmt, "TokioXMPP error: {}", e),
+        }
+    }
+}
+
+impl From<crate::error::Error> for Error {
+    fn from(e: crate::error::Error) -> Self {
+        Error::TokioXMPP(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::TokioXMPP(e.into())
+    }
+}
+
+impl ServerConnectorError for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Event;
+
+    #[tokio::test]
+    async fn test_echo() {
+        let jid: Jid = "juliet@capulet.lit/balcony".parse().unwrap();
+        let mut client = MockAsyncClient::with_mock_server(MockServer::new(), jid, "romeo");
+        match client.next().await {
+            Some(Event::Online { bound_jid, .. }) => {
+                assert_eq!(bound_jid.to_string(), "juliet@capulet.lit/balcony")
+            }
+            event => panic!("{:?}", event),
+        }
+
+        let message = Element::builder("message", ns::JABBER_CLIENT)
+            .attr("id", "echo")
+            .build();
+        client.send_stanza(message).await.unwrap();
+        match client.next().await {
+            Some(Event::Stanza(stanza)) => assert_eq!(stanza.attr("id"), Some("echo")),
+            event => panic!("{:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password() {
+        let server = MockServer::new().with_password("romeo");
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let mut client = MockAsyncClient::with_mock_server(server, jid, "tybalt");
+        match client.next().await {
+            Some(Event::Disconnected(crate::Error::Auth(_))) => (),
+            event => panic!("{:?}", event),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! `ServerConnector` provides streams for XMPP clients
+
+use sasl::common::ChannelBinding;
+use tokio::io::{AsyncRead, AsyncWrite};
+use xmpp_parsers::Jid;
+
+use crate::xmpp_stream::XMPPStream;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub mod stream;
+#[cfg(any(feature = "starttls", feature = "insecure-tcp"))]
+mod tcp_config;
+pub mod websocket;
+
+#[cfg(any(feature = "starttls", feature = "insecure-tcp"))]
+pub use self::tcp_config::TcpConfig;
+
+/// trait returned wrapped in XMPPStream by ServerConnector
+pub trait AsyncReadAndWrite: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncReadAndWrite for T {}
+
+/// Trait that must be extended by the implementation of ServerConnector
+pub trait ServerConnectorError: std::error::Error + Sync + Send {}
+
+/// Security information about an established TLS connection, e.g. to display
+/// it or to pin the certificate of a server on first use
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Certificate chain presented by the server, DER-encoded, starting with
+    /// its own certificate
+    ///
+    /// Only the certificate of the server is available with tls-native.
+    pub peer_certificates: Vec<Vec<u8>>,
+    /// Negotiated protocol version, e.g. `TLSv1_3`, if known
+    pub protocol_version: Option<String>,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`, if known
+    pub cipher_suite: Option<String>,
+}
+
+/// Trait called to connect to an XMPP server, perhaps called multiple times
+pub trait ServerConnector: Clone + core::fmt::Debug + Send + Unpin + 'static {
+    /// The type of Stream this ServerConnector produces
+    type Stream: AsyncReadAndWrite;
+    /// Error type to return
+    type Error: ServerConnectorError;
+    /// This must return the connection ready to login, ie if starttls is involved, after TLS has been started, and then after the <stream headers are exchanged
+    fn connect(
+        &self,
+        jid: &Jid,
+        ns: &str,
+    ) -> impl std::future::Future<Output = Result<XMPPStream<Self::Stream>, Self::Error>> + Send;
+
+    /// Return channel binding data if available
+    /// do not fail if channel binding is simply unavailable, just return Ok(None)
+    /// this should only be called after the TLS handshake is finished
+    fn channel_binding(_stream: &Self::Stream) -> Result<ChannelBinding, Self::Error> {
+        Ok(ChannelBinding::None)
+    }
+
+    /// Return information about the TLS connection of this stream
+    /// return None if the stream isn't encrypted, or the information is unavailable
+    fn tls_info(_stream: &Self::Stream) -> Option<TlsInfo> {
+        None
+    }
+
+    /// Return a connector reaching the given host instead, used to follow
+    /// `<see-other-host/>` stream errors
+    /// return None if this connector can't be redirected
+    fn redirect(&self, _host: &str, _port: u16) -> Option<Self> {
+        None
+    }
+}
+
+/// Split the target of a `<see-other-host/>` stream error into a host and
+/// a port, using `default_port` when none is given
+pub fn parse_see_other_host(target: &str, default_port: u16) -> Option<(String, u16)> {
+    let target = target.trim();
+    if let Some(rest) = target.strip_prefix('[') {
+        // [IPv6]:port or [IPv6]
+        let (host, rest) = rest.split_once(']')?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => port.parse().ok()?,
+            None if rest.is_empty() => default_port,
+            None => return None,
+        };
+        return Some((host.to_owned(), port));
+    }
+    match target.split_once(':') {
+        // A bare IPv6 address, without any port
+        Some((_, rest)) if rest.contains(':') => Some((target.to_owned(), default_port)),
+        Some((host, port)) => Some((host.to_owned(), port.parse().ok()?)),
+        None if target.is_empty() => None,
+        None => Some((target.to_owned(), default_port)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_see_other_host() {
+        assert_eq!(
+            parse_see_other_host("example.org", 5222),
+            Some((String::from("example.org"), 5222))
+        );
+        assert_eq!(
+            parse_see_other_host("example.org:5223", 5222),
+            Some((String::from("example.org"), 5223))
+        );
+        assert_eq!(
+            parse_see_other_host("[2001:db8::a11:b]:5223", 5222),
+            Some((String::from("2001:db8::a11:b"), 5223))
+        );
+        assert_eq!(
+            parse_see_other_host("2001:db8::a11:b", 5222),
+            Some((String::from("2001:db8::a11:b"), 5222))
+        );
+        assert_eq!(parse_see_other_host("example.org:coucou", 5222), None);
+        assert_eq!(parse_see_other_host("", 5222), None);
+    }
+}
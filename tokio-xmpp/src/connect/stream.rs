@@ -0,0 +1,151 @@
+//! `StreamConnector` hands a caller-supplied stream to the XMPP layer
+//!
+//! This is useful when the application already established the
+//! transport itself (SSH tunnels, custom transports, test harnesses)
+//! and only wants this crate to perform the XMPP negotiation.
+
+use core::fmt;
+use std::sync::{Arc, Mutex};
+
+use xmpp_parsers::Jid;
+
+use crate::connect::{AsyncReadAndWrite, ServerConnector, ServerConnectorError};
+use crate::xmpp_stream::XMPPStream;
+use crate::{AsyncClient, AsyncConfig, Component};
+
+/// AsyncClient running over a caller-supplied stream
+pub type StreamAsyncClient<S> = AsyncClient<StreamConnector<S>>;
+/// Component running over a caller-supplied stream
+pub type StreamComponent<S> = Component<StreamConnector<S>>;
+
+/// Connector wrapping an already-established stream
+///
+/// The stream is used as-is: no STARTTLS is attempted, so wrap it in TLS
+/// beforehand if needed. It can only be connected once, so reconnecting
+/// an `AsyncClient` using it will fail with [`Error::AlreadyConnected`].
+pub struct StreamConnector<S: AsyncReadAndWrite> {
+    stream: Arc<Mutex<Option<S>>>,
+}
+
+impl<S: AsyncReadAndWrite> StreamConnector<S> {
+    /// Wrap an already-established stream
+    pub fn new(stream: S) -> Self {
+        StreamConnector {
+            stream: Arc::new(Mutex::new(Some(stream))),
+        }
+    }
+}
+
+impl<S: AsyncReadAndWrite> Clone for StreamConnector<S> {
+    fn clone(&self) -> Self {
+        StreamConnector {
+            stream: self.stream.clone(),
+        }
+    }
+}
+
+impl<S: AsyncReadAndWrite> fmt::Debug for StreamConnector<S> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let available = self.stream.lock().is_ok_and(|s| s.is_some());
+        fmt.debug_struct("StreamConnector")
+            .field("available", &available)
+            .finish()
+    }
+}
+
+impl<S: AsyncReadAndWrite + 'static> ServerConnector for StreamConnector<S> {
+    type Stream = S;
+    type Error = Error;
+    async fn connect(&self, jid: &Jid, ns: &str) -> Result<XMPPStream<Self::Stream>, Self::Error> {
+        let stream = self
+            .stream
+            .lock()
+            .map_err(|_| Error::AlreadyConnected)?
+            .take()
+            .ok_or(Error::AlreadyConnected)?;
+        Ok(XMPPStream::start(stream, jid.clone(), ns.to_owned()).await?)
+    }
+}
+
+impl<S: AsyncReadAndWrite + 'static> AsyncClient<StreamConnector<S>> {
+    /// Start a new XMPP client over an already-established stream
+    ///
+    /// Start polling the returned instance so that it will negotiate
+    /// the XMPP session and yield events.
+    pub fn with_connected_stream<J: Into<Jid>, P: Into<String>>(
+        stream: S,
+        jid: J,
+        password: P,
+    ) -> Self {
+        let config = AsyncConfig::new(jid, password, StreamConnector::new(stream));
+        Self::new_with_config(config)
+    }
+}
+
+impl<S: AsyncReadAndWrite + 'static> Component<StreamConnector<S>> {
+    /// Start a new XMPP component over an already-established stream
+    pub async fn with_connected_stream(
+        stream: S,
+        jid: &str,
+        password: &str,
+    ) -> Result<Self, crate::Error> {
+        Self::new_with_connector(jid, password, StreamConnector::new(stream)).await
+    }
+}
+
+/// Caller-supplied stream ServerConnector Error
+#[derive(Debug)]
+pub enum Error {
+    /// The stream has already been handed out, it can't be reconnected
+    AlreadyConnected,
+    /// tokio-xmpp error
+    TokioXMPP(crate::error::Error),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::AlreadyConnected => write!(fmt, "stream already connected"),
+            Error::TokioXMPP(e) => write!(fmt, "TokioXMPP error: {}", e),
+        }
+    }
+}
+
+impl From<crate::error::Error> for Error {
+    fn from(e: crate::error::Error) -> Self {
+        Error::TokioXMPP(e)
+    }
+}
+
+impl ServerConnectorError for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_connect_once() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let _ = server.read(&mut buf).await;
+            server
+                .write_all(b"<stream:stream xmlns='jabber:component:accept' xmlns:stream='http://etherx.jabber.org/streams' id='coucou'>")
+                .await
+                .unwrap();
+            // Keep the stream open until the client is done.
+            let _ = server.read(&mut buf).await;
+        });
+
+        let jid: Jid = "component.example.org".parse().unwrap();
+        let connector = StreamConnector::new(client);
+        let stream = connector.connect(&jid, "jabber:component:accept").await;
+        assert_eq!(stream.unwrap().id, "coucou");
+
+        let again = connector.connect(&jid, "jabber:component:accept").await;
+        assert!(matches!(again, Err(Error::AlreadyConnected)));
+    }
+}
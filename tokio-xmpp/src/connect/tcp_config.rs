@@ -0,0 +1,91 @@
+//! TCP socket settings for the starttls and tcp `ServerConnector`s
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+
+/// Settings applied to the TCP sockets of a connector
+///
+/// The default leaves the system defaults untouched.
+#[derive(Clone, Debug, Default)]
+pub struct TcpConfig {
+    /// Disable Nagle’s algorithm (`TCP_NODELAY`), so that small stanzas get
+    /// sent immediately
+    pub nodelay: bool,
+    /// Enable TCP keepalive, probing the connection after it stayed idle for
+    /// this long
+    pub keepalive: Option<Duration>,
+    /// Local address to connect from, e.g. on a multi-homed host
+    pub local_addr: Option<IpAddr>,
+    /// Network interface to connect through, e.g. `eth1`, only supported on
+    /// Linux, Android and Fuchsia
+    pub interface: Option<String>,
+}
+
+impl TcpConfig {
+    /// Set `TCP_NODELAY` on the socket
+    pub fn with_nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Enable TCP keepalive, after this idle time
+    pub fn with_keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    /// Connect from this local address, with any port
+    pub fn with_local_addr(mut self, local_addr: IpAddr) -> Self {
+        self.local_addr = Some(local_addr);
+        self
+    }
+
+    /// Connect through this network interface
+    pub fn with_interface<S: Into<String>>(mut self, interface: S) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+
+    /// Connect to `addr` with these settings
+    pub async fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        if let Some(local_addr) = self.local_addr {
+            socket.bind(SocketAddr::new(local_addr, 0))?;
+        }
+        if let Some(ref interface) = self.interface {
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            socket.bind_device(Some(interface.as_bytes()))?;
+            #[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("can’t bind to interface {interface} on this platform"),
+            ));
+        }
+        let stream = socket.connect(addr).await?;
+        stream.set_nodelay(self.nodelay)?;
+        if let Some(idle) = self.keepalive {
+            SockRef::from(&stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+        }
+        Ok(stream)
+    }
+
+    /// Resolve `host` (as `host:port`) and connect to the first of its
+    /// addresses accepting the connection
+    pub async fn connect_to_host(&self, host: &str) -> io::Result<TcpStream> {
+        let mut error = io::Error::new(io::ErrorKind::NotFound, "no address to connect to");
+        for addr in lookup_host(host).await? {
+            match self.connect(addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+}
@@ -0,0 +1,567 @@
+//! XMPP over WebSocket (RFC 7395)
+//!
+//! [`WebSocketStream`] turns WebSocket text messages into the byte stream
+//! [`XMPPStream`](crate::xmpp_stream::XMPPStream) expects, whatever provides
+//! the WebSocket. On wasm32, [`WebSocketConnector`] opens one from the
+//! browser, the only transport available there.
+
+use futures::{ready, Sink, Stream};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use xmpp_parsers::{ns, Element};
+
+use crate::xmpp_codec::escape;
+
+#[cfg(target_arch = "wasm32")]
+pub use self::browser::{BrowserWebSocket, Error, WebSocketConnector};
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Where the scanner of outgoing bytes is
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Scan {
+    /// Between tags
+    #[default]
+    Text,
+    /// Just after a `<`
+    TagStart,
+    /// In a start tag, and in a quoted value if there is its quote
+    StartTag(Option<u8>),
+    /// In an end tag
+    EndTag,
+    /// In an XML declaration, comment or processing instruction
+    Declaration,
+}
+
+/// Translation between the stream written by `XMPPCodec` and the messages
+/// of RFC 7395, in which each message holds exactly one element and the
+/// stream headers become `<open/>` and `<close/>`
+#[derive(Debug, Default)]
+struct Framing {
+    /// Written bytes not forming a whole element yet
+    pending: Vec<u8>,
+    /// How far `pending` got scanned
+    scanned: usize,
+    /// Scanner state at `scanned`
+    state: Scan,
+    /// Depth at `scanned`, 1 being inside the stream header
+    depth: usize,
+    /// Where the element being scanned starts in `pending`
+    start: usize,
+}
+
+impl Framing {
+    /// Cut the bytes written by `XMPPCodec` into messages, keeping what
+    /// doesn't form a whole element yet
+    fn outgoing(&mut self, bytes: &[u8]) -> io::Result<Vec<String>> {
+        self.pending.extend_from_slice(bytes);
+        let mut messages = Vec::new();
+        let mut i = self.scanned;
+        while i < self.pending.len() {
+            let byte = self.pending[i];
+            self.state = match (self.state, byte) {
+                (Scan::Text, b'<') => {
+                    if self.depth <= 1 {
+                        self.start = i;
+                    }
+                    Scan::TagStart
+                }
+                // Whitespace keepalives have no place between messages
+                (Scan::Text, _) => Scan::Text,
+                (Scan::TagStart, b'/') => Scan::EndTag,
+                (Scan::TagStart, b'?' | b'!') => Scan::Declaration,
+                (Scan::TagStart, _) => Scan::StartTag(None),
+                (Scan::StartTag(None), b'"' | b'\'') => Scan::StartTag(Some(byte)),
+                (Scan::StartTag(None), b'>') => {
+                    let element = &self.pending[self.start..=i];
+                    if element.starts_with(b"<stream:stream") {
+                        // A new header, also when restarting the stream
+                        self.depth = 1;
+                        messages.push(open(element)?);
+                    } else if self.depth == 0 {
+                        return Err(invalid_data("element outside of the stream"));
+                    } else if self.pending[i - 1] != b'/' {
+                        self.depth += 1;
+                    } else if self.depth == 1 {
+                        messages.push(message(element)?);
+                    }
+                    Scan::Text
+                }
+                (Scan::StartTag(Some(quote)), _) if byte == quote => Scan::StartTag(None),
+                (Scan::EndTag, b'>') => {
+                    self.depth = self
+                        .depth
+                        .checked_sub(1)
+                        .ok_or_else(|| invalid_data("end tag outside of the stream"))?;
+                    match self.depth {
+                        0 => messages.push(format!("<close xmlns='{}'/>", ns::WEBSOCKET)),
+                        1 => messages.push(message(&self.pending[self.start..=i])?),
+                        _ => (),
+                    }
+                    Scan::Text
+                }
+                (Scan::Declaration, b'>') => Scan::Text,
+                (state, _) => state,
+            };
+            i += 1;
+        }
+
+        // Forget what got cut into messages
+        if self.state == Scan::Text && self.depth <= 1 {
+            self.pending.clear();
+            self.scanned = 0;
+        } else {
+            self.pending.drain(..self.start);
+            self.scanned = i - self.start;
+            self.start = 0;
+        }
+        Ok(messages)
+    }
+
+    /// Turn a received message into bytes `XMPPCodec` can parse
+    fn incoming(&mut self, message: &str) -> io::Result<String> {
+        let trimmed = message.trim_start();
+        if !trimmed.starts_with("<open") && !trimmed.starts_with("<close") {
+            return Ok(message.to_owned());
+        }
+        let element: Element = trimmed.parse().map_err(invalid_data)?;
+        if element.is("open", ns::WEBSOCKET) {
+            let mut header = format!(
+                "<stream:stream xmlns='{}' xmlns:stream='{}'",
+                ns::JABBER_CLIENT,
+                ns::STREAM
+            );
+            for (name, value) in element.attrs() {
+                let _ = write!(header, " {}=\"{}\"", name, escape(value));
+            }
+            header.push('>');
+            Ok(header)
+        } else if element.is("close", ns::WEBSOCKET) {
+            Ok(String::from("</stream:stream>"))
+        } else {
+            Ok(message.to_owned())
+        }
+    }
+}
+
+/// One element written by `XMPPCodec`, as a message
+fn message(element: &[u8]) -> io::Result<String> {
+    String::from_utf8(element.to_vec()).map_err(invalid_data)
+}
+
+/// The `<open/>` message replacing a `<stream:stream>` header
+fn open(header: &[u8]) -> io::Result<String> {
+    let header = std::str::from_utf8(header).map_err(invalid_data)?;
+    let header: Element = format!("{}</stream:stream>", header)
+        .parse()
+        .map_err(invalid_data)?;
+    let open = header
+        .attrs()
+        .fold(
+            Element::builder("open", ns::WEBSOCKET),
+            |open, (name, value)| open.attr(name, value),
+        )
+        .build();
+    Ok(String::from(&open))
+}
+
+/// A WebSocket connection speaking XMPP as per RFC 7395, read and written
+/// as the byte stream of a TCP connection
+///
+/// `T` sends and receives the text messages of the WebSocket, which must
+/// have been opened with the `xmpp` subprotocol, e.g. [`BrowserWebSocket`]
+/// on wasm32 or any native WebSocket client. Only `jabber:client` streams
+/// can be carried this way.
+#[derive(Debug)]
+pub struct WebSocketStream<T> {
+    transport: T,
+    framing: Framing,
+    /// Received bytes not read yet
+    received: Vec<u8>,
+    /// How much of `received` got read
+    read: usize,
+    /// Messages waiting for the transport to accept them
+    outgoing: VecDeque<String>,
+}
+
+impl<T> WebSocketStream<T>
+where
+    T: Stream<Item = io::Result<String>> + Sink<String, Error = io::Error> + Unpin,
+{
+    /// Speak XMPP over this WebSocket
+    pub fn new(transport: T) -> Self {
+        WebSocketStream {
+            transport,
+            framing: Framing::default(),
+            received: Vec::new(),
+            read: 0,
+            outgoing: VecDeque::new(),
+        }
+    }
+
+    /// Unwraps the WebSocket
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    fn poll_send_outgoing(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while !self.outgoing.is_empty() {
+            ready!(Pin::new(&mut self.transport).poll_ready(cx))?;
+            let message = self.outgoing.pop_front().unwrap();
+            Pin::new(&mut self.transport).start_send(message)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> AsyncRead for WebSocketStream<T>
+where
+    T: Stream<Item = io::Result<String>> + Sink<String, Error = io::Error> + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.read == this.received.len() {
+            match ready!(Pin::new(&mut this.transport).poll_next(cx)) {
+                Some(message) => {
+                    this.received = this.framing.incoming(&message?)?.into_bytes();
+                    this.read = 0;
+                }
+                // End of the stream
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+        let len = buf.remaining().min(this.received.len() - this.read);
+        buf.put_slice(&this.received[this.read..this.read + len]);
+        this.read += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> AsyncWrite for WebSocketStream<T>
+where
+    T: Stream<Item = io::Result<String>> + Sink<String, Error = io::Error> + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_send_outgoing(cx))?;
+        let messages = this.framing.outgoing(buf)?;
+        this.outgoing.extend(messages);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send_outgoing(cx))?;
+        Pin::new(&mut this.transport).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.transport).poll_close(cx)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use core::fmt;
+    use futures::{Sink, Stream};
+    use send_wrapper::SendWrapper;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use ws_stream_wasm::{WsMessage, WsMeta, WsStream};
+    use xmpp_parsers::Jid;
+
+    use super::{invalid_data, WebSocketStream};
+    use crate::connect::{ServerConnector, ServerConnectorError};
+    use crate::xmpp_stream::XMPPStream;
+
+    /// WebSocket of the browser, carrying text messages
+    #[derive(Debug)]
+    pub struct BrowserWebSocket {
+        stream: WsStream,
+    }
+
+    impl BrowserWebSocket {
+        /// Open a WebSocket to `url`, with the `xmpp` subprotocol
+        pub async fn connect(url: &str) -> Result<Self, Error> {
+            let (meta, stream) = WsMeta::connect(url, vec!["xmpp"])
+                .await
+                .map_err(|e| Error::WebSocket(e.to_string()))?;
+            if meta.protocol() != "xmpp" {
+                return Err(Error::WebSocket(String::from(
+                    "the server doesn't speak the xmpp subprotocol",
+                )));
+            }
+            Ok(BrowserWebSocket { stream })
+        }
+    }
+
+    fn to_io_err(e: ws_stream_wasm::WsErr) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, e.to_string())
+    }
+
+    impl Stream for BrowserWebSocket {
+        type Item = io::Result<String>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+            Pin::new(&mut self.stream).poll_next(cx).map(|message| {
+                message.map(|message| match message {
+                    WsMessage::Text(text) => Ok(text),
+                    WsMessage::Binary(bytes) => String::from_utf8(bytes).map_err(invalid_data),
+                })
+            })
+        }
+    }
+
+    impl Sink<String> for BrowserWebSocket {
+        type Error = io::Error;
+
+        fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.stream).poll_ready(cx).map_err(to_io_err)
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, message: String) -> io::Result<()> {
+            Pin::new(&mut self.stream)
+                .start_send(WsMessage::Text(message))
+                .map_err(to_io_err)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.stream).poll_flush(cx).map_err(to_io_err)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.stream).poll_close(cx).map_err(to_io_err)
+        }
+    }
+
+    /// Connect to an XMPP server over a WebSocket of the browser
+    #[derive(Debug, Clone)]
+    pub struct WebSocketConnector {
+        url: String,
+    }
+
+    impl WebSocketConnector {
+        /// Connect to this WebSocket endpoint, e.g.
+        /// `wss://example.org/xmpp-websocket`
+        pub fn new<U: Into<String>>(url: U) -> Self {
+            WebSocketConnector { url: url.into() }
+        }
+    }
+
+    impl ServerConnector for WebSocketConnector {
+        type Stream = WebSocketStream<BrowserWebSocket>;
+        type Error = Error;
+
+        fn connect(
+            &self,
+            jid: &Jid,
+            ns: &str,
+        ) -> impl std::future::Future<Output = Result<XMPPStream<Self::Stream>, Self::Error>> + Send
+        {
+            let (url, jid, ns) = (self.url.clone(), jid.clone(), ns.to_owned());
+            // Browser futures stay on the thread they got created on
+            SendWrapper::new(async move {
+                let websocket = BrowserWebSocket::connect(&url).await?;
+                let stream = WebSocketStream::new(websocket);
+                Ok(XMPPStream::start(stream, jid, ns).await?)
+            })
+        }
+    }
+
+    /// WebSocket ServerConnector Error
+    #[derive(Debug)]
+    pub enum Error {
+        /// The WebSocket couldn't be opened
+        WebSocket(String),
+        /// tokio-xmpp error
+        TokioXMPP(crate::error::Error),
+    }
+
+    impl std::error::Error for Error {}
+
+    impl fmt::Display for Error {
+        fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Error::WebSocket(e) => write!(fmt, "WebSocket error: {}", e),
+                Error::TokioXMPP(e) => write!(fmt, "TokioXMPP error: {}", e),
+            }
+        }
+    }
+
+    impl From<crate::error::Error> for Error {
+        fn from(e: crate::error::Error) -> Self {
+            Error::TokioXMPP(e)
+        }
+    }
+
+    impl ServerConnectorError for Error {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::{SinkExt, StreamExt};
+    use xmpp_parsers::Jid;
+
+    use crate::xmpp_stream::XMPPStream;
+
+    fn outgoing(framing: &mut Framing, bytes: &str) -> Vec<String> {
+        framing.outgoing(bytes.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_outgoing() {
+        let mut framing = Framing::default();
+        assert_eq!(
+            outgoing(
+                &mut framing,
+                "<stream:stream to=\"capulet.lit\" version=\"1.0\" xmlns=\"jabber:client\" xmlns:stream=\"http://etherx.jabber.org/streams\">"
+            ),
+            vec!["<open xmlns='urn:ietf:params:xml:ns:xmpp-framing' to=\"capulet.lit\" version=\"1.0\"/>"]
+        );
+        // Elements written in pieces, with > in values and whitespace
+        assert!(outgoing(&mut framing, " <message xmlns='jabber:client' to='a>b'><bo").is_empty());
+        assert_eq!(
+            outgoing(
+                &mut framing,
+                "dy>x</body></message>\n<r xmlns='urn:xmpp:sm:3'/>"
+            ),
+            vec![
+                "<message xmlns='jabber:client' to='a>b'><body>x</body></message>",
+                "<r xmlns='urn:xmpp:sm:3'/>",
+            ]
+        );
+        assert!(framing.pending.is_empty());
+        // Restarting the stream after authentication
+        assert_eq!(
+            outgoing(&mut framing, "<stream:stream xmlns=\"jabber:client\" xmlns:stream=\"http://etherx.jabber.org/streams\" to=\"capulet.lit\">"),
+            vec!["<open xmlns='urn:ietf:params:xml:ns:xmpp-framing' to=\"capulet.lit\"/>"]
+        );
+        assert_eq!(
+            outgoing(&mut framing, "</stream:stream>\n"),
+            vec!["<close xmlns='urn:ietf:params:xml:ns:xmpp-framing'/>"]
+        );
+        assert!(framing.outgoing(b"<message/>").is_err());
+    }
+
+    #[test]
+    fn test_incoming() {
+        let mut framing = Framing::default();
+        let header = framing
+            .incoming("<open xmlns='urn:ietf:params:xml:ns:xmpp-framing' from='capulet.lit' id='++TR84Sm6A3hnt3Q065SnAbbk3Y='/>")
+            .unwrap();
+        assert!(header.starts_with(
+            "<stream:stream xmlns='jabber:client' xmlns:stream='http://etherx.jabber.org/streams' "
+        ));
+        assert!(header.contains(" from=\"capulet.lit\""));
+        assert!(header.contains(" id=\"++TR84Sm6A3hnt3Q065SnAbbk3Y=\""));
+        let message = "<message xmlns='jabber:client'/>";
+        assert_eq!(framing.incoming(message).unwrap(), message);
+        assert_eq!(
+            framing
+                .incoming("<close xmlns='urn:ietf:params:xml:ns:xmpp-framing'/>")
+                .unwrap(),
+            "</stream:stream>"
+        );
+    }
+
+    /// Both ends of an in-memory WebSocket
+    struct Channel {
+        sender: mpsc::UnboundedSender<String>,
+        receiver: mpsc::UnboundedReceiver<String>,
+    }
+
+    fn channel() -> (Channel, Channel) {
+        let (client_sender, server_receiver) = mpsc::unbounded();
+        let (server_sender, client_receiver) = mpsc::unbounded();
+        (
+            Channel {
+                sender: client_sender,
+                receiver: client_receiver,
+            },
+            Channel {
+                sender: server_sender,
+                receiver: server_receiver,
+            },
+        )
+    }
+
+    impl Stream for Channel {
+        type Item = io::Result<String>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+            self.receiver
+                .poll_next_unpin(cx)
+                .map(|message| message.map(Ok))
+        }
+    }
+
+    impl Sink<String> for Channel {
+        type Error = io::Error;
+
+        fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+            self.sender
+                .poll_ready_unpin(cx)
+                .map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, message: String) -> io::Result<()> {
+            self.sender
+                .start_send_unpin(message)
+                .map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+            self.sender
+                .poll_flush_unpin(cx)
+                .map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+            self.sender
+                .poll_close_unpin(cx)
+                .map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_start() {
+        let (client, mut server) = channel();
+        let serve = async move {
+            let open = server.next().await.unwrap().unwrap();
+            assert!(open.starts_with("<open xmlns='urn:ietf:params:xml:ns:xmpp-framing'"));
+            server
+                .send(String::from("<open xmlns='urn:ietf:params:xml:ns:xmpp-framing' from='capulet.lit' id='ws' version='1.0'/>"))
+                .await
+                .unwrap();
+            server
+                .send(String::from("<stream:features xmlns:stream='http://etherx.jabber.org/streams'><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'/></stream:features>"))
+                .await
+                .unwrap();
+            server
+        };
+        let jid: Jid = "juliet@capulet.lit".parse().unwrap();
+        let start = XMPPStream::start(
+            WebSocketStream::new(client),
+            jid,
+            ns::JABBER_CLIENT.to_owned(),
+        );
+        let (stream, _server) = futures::join!(start, serve);
+        let stream = stream.unwrap();
+        assert_eq!(stream.id, "ws");
+        assert!(stream.stream_features.can_bind);
+    }
+}
@@ -6,6 +6,7 @@ use std::io::Error as IoError;
 use std::str::Utf8Error;
 
 use xmpp_parsers::sasl::DefinedCondition as SaslDefinedCondition;
+use xmpp_parsers::stream_error::{DefinedCondition as StreamDefinedCondition, StreamError};
 use xmpp_parsers::{Error as ParsersError, JidParseError};
 
 use crate::connect::ServerConnectorError;
@@ -31,6 +32,14 @@ pub enum Error {
     Utf8(Utf8Error),
     /// Error resolving DNS and/or establishing a connection, returned by a ServerConnector impl
     Connection(Box<dyn ServerConnectorError>),
+    /// No response was received to an IQ in time
+    IqTimeout,
+    /// The server didn't acknowledge a tracked stanza in time
+    AckTimeout,
+    /// The server closed the stream with a `<stream:error/>`
+    StreamError(StreamError),
+    /// The requested resource is already bound by another session
+    ResourceConflict,
 }
 
 impl fmt::Display for Error {
@@ -45,12 +54,27 @@ impl fmt::Display for Error {
             Error::InvalidState => write!(fmt, "invalid state"),
             Error::Fmt(e) => write!(fmt, "Fmt error: {}", e),
             Error::Utf8(e) => write!(fmt, "Utf8 error: {}", e),
+            Error::IqTimeout => write!(fmt, "IQ response timed out"),
+            Error::AckTimeout => write!(fmt, "stanza acknowledgement timed out"),
+            Error::StreamError(e) => write!(fmt, "stream error: {:?}", e.defined_condition),
+            Error::ResourceConflict => write!(fmt, "resource conflict"),
         }
     }
 }
 
 impl StdError for Error {}
 
+impl Error {
+    /// The `<stream:error/>` condition telling the other side that what it
+    /// sent caused this error, if it did
+    pub fn stream_error_condition(&self) -> Option<StreamDefinedCondition> {
+        match self {
+            Error::Protocol(e) => e.stream_error_condition(),
+            _ => None,
+        }
+    }
+}
+
 impl From<IoError> for Error {
     fn from(e: IoError) -> Self {
         Error::Io(e)
@@ -131,6 +155,10 @@ pub enum ProtocolError {
     InvalidToken,
     /// Unexpected <stream:stream> (shouldn't occur)
     InvalidStreamStart,
+    /// Incoming XML nested deeper than allowed
+    TooDeep,
+    /// Incoming stanza larger than allowed
+    TooLarge,
 }
 
 impl fmt::Display for ProtocolError {
@@ -148,12 +176,34 @@ impl fmt::Display for ProtocolError {
             ProtocolError::NoStreamId => write!(fmt, "no id attribute in <stream:stream>"),
             ProtocolError::InvalidToken => write!(fmt, "encountered an unexpected XML token"),
             ProtocolError::InvalidStreamStart => write!(fmt, "unexpected <stream:stream>"),
+            ProtocolError::TooDeep => write!(fmt, "XML nested too deeply"),
+            ProtocolError::TooLarge => write!(fmt, "stanza too large"),
         }
     }
 }
 
 impl StdError for ProtocolError {}
 
+impl ProtocolError {
+    /// The `<stream:error/>` condition telling the other side that what it
+    /// sent caused this error, if it did
+    pub fn stream_error_condition(&self) -> Option<StreamDefinedCondition> {
+        Some(match self {
+            ProtocolError::Parser(_) => StreamDefinedCondition::NotWellFormed,
+            ProtocolError::NoStreamNamespace => StreamDefinedCondition::InvalidNamespace,
+            ProtocolError::NoStreamId
+            | ProtocolError::InvalidToken
+            | ProtocolError::InvalidStreamStart => StreamDefinedCondition::BadFormat,
+            ProtocolError::TooDeep | ProtocolError::TooLarge => {
+                StreamDefinedCondition::PolicyViolation
+            }
+            ProtocolError::Parsers(_)
+            | ProtocolError::NoTls
+            | ProtocolError::InvalidBindResponse => return None,
+        })
+    }
+}
+
 impl From<minidom::Error> for ProtocolError {
     fn from(e: minidom::Error) -> Self {
         ProtocolError::Parser(e)
@@ -183,6 +233,8 @@ pub enum AuthError {
     Fail(SaslDefinedCondition),
     /// Component authentication failure
     ComponentFail,
+    /// The credentials provider failed to provide a secret
+    Credentials(Box<dyn StdError + Send + Sync>),
 }
 
 impl StdError for AuthError {}
@@ -194,6 +246,7 @@ impl fmt::Display for AuthError {
             AuthError::Sasl(s) => write!(fmt, "local SASL implementation error: {}", s),
             AuthError::Fail(c) => write!(fmt, "failure from the server: {:?}", c),
             AuthError::ComponentFail => write!(fmt, "component authentication failure"),
+            AuthError::Credentials(e) => write!(fmt, "can't get credentials: {}", e),
         }
     }
 }
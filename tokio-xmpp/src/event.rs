@@ -1,10 +1,15 @@
-use super::Error;
-use xmpp_parsers::{Element, Jid};
+use super::{connect::TlsInfo, Error};
+use xmpp_parsers::{
+    iq::Iq, message::Message, ns, presence::Presence, stream_features::StreamFeatures, Element, Jid,
+};
 
 /// High-level event on the Stream implemented by Client and Component
 #[derive(Debug)]
 pub enum Event {
     /// Stream is connected and initialized
+    ///
+    /// More fields may be added, so match it with `..`.
+    #[non_exhaustive]
     Online {
         /// Server-set Jabber-Id for your session
         ///
@@ -12,10 +17,23 @@ pub enum Event {
         /// expected, so use this one instead of the JID with which
         /// the connection was setup.
         bound_jid: Jid,
+        /// Resource requested in the JID the connection was setup with
+        ///
+        /// The server may have bound another one, see `bound_jid`.
+        requested_resource: Option<String>,
         /// Was this session resumed?
         ///
         /// Not yet implemented for the Client
         resumed: bool,
+        /// Security information about the connection, if it is encrypted
+        ///
+        /// Also available later with `AsyncClient::tls_info()`.
+        tls_info: Option<TlsInfo>,
+        /// Features of the stream, as advertised by the server
+        ///
+        /// Also available later with `AsyncClient::get_stream_features()`,
+        /// unparsed.
+        features: StreamFeatures,
     },
     /// Stream end
     Disconnected(Error),
@@ -63,4 +81,108 @@ impl Event {
             _ => None,
         }
     }
+
+    /// Parse the stanza of a `Stanza` event, see [`TypedEvent`]
+    pub fn into_typed(self) -> TypedEvent {
+        match self {
+            Event::Online {
+                bound_jid,
+                requested_resource,
+                resumed,
+                tls_info,
+                features,
+            } => TypedEvent::Online {
+                bound_jid,
+                requested_resource,
+                resumed,
+                tls_info,
+                features,
+            },
+            Event::Disconnected(e) => TypedEvent::Disconnected(e),
+            Event::Stanza(stanza) => {
+                // Parse by reference, so that only invalid stanzas get kept
+                let typed = if stanza.is("message", ns::DEFAULT_NS) {
+                    Message::try_from(&stanza).map(TypedEvent::Message)
+                } else if stanza.is("presence", ns::DEFAULT_NS) {
+                    Presence::try_from(&stanza).map(TypedEvent::Presence)
+                } else if stanza.is("iq", ns::DEFAULT_NS) {
+                    Iq::try_from(&stanza).map(TypedEvent::Iq)
+                } else {
+                    return TypedEvent::Stanza(stanza);
+                };
+                typed.unwrap_or_else(|e| {
+                    log::debug!("Failed to parse received {}: {}", stanza.name(), e);
+                    TypedEvent::Stanza(stanza)
+                })
+            }
+        }
+    }
+}
+
+/// [`Event`] with its stanzas parsed, as returned by `Event::into_typed()`
+///
+/// Use it with e.g. `client.map(Event::into_typed)`. Stanzas get parsed in
+/// the default namespace of xmpp-parsers, that of components only with its
+/// `component` feature.
+#[derive(Debug)]
+pub enum TypedEvent {
+    /// Stream is connected and initialized, see `Event::Online`
+    #[non_exhaustive]
+    Online {
+        /// Server-set Jabber-Id for your session
+        bound_jid: Jid,
+        /// Resource requested in the JID the connection was setup with
+        requested_resource: Option<String>,
+        /// Was this session resumed?
+        resumed: bool,
+        /// Security information about the connection, if it is encrypted
+        tls_info: Option<TlsInfo>,
+        /// Features of the stream, as advertised by the server
+        features: StreamFeatures,
+    },
+    /// Stream end
+    Disconnected(Error),
+    /// Received message
+    Message(Message),
+    /// Received presence
+    Presence(Presence),
+    /// Received iq
+    Iq(Iq),
+    /// Received nonza, or stanza which failed to parse
+    Stanza(Element),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_typed() {
+        let stanza = |xml: &str| Event::Stanza(xml.parse().unwrap()).into_typed();
+        assert!(matches!(
+            stanza("<message xmlns='jabber:client' type='chat'/>"),
+            TypedEvent::Message(_)
+        ));
+        assert!(matches!(
+            stanza("<presence xmlns='jabber:client'/>"),
+            TypedEvent::Presence(_)
+        ));
+        assert!(matches!(
+            stanza("<iq xmlns='jabber:client' type='result' id='a'/>"),
+            TypedEvent::Iq(_)
+        ));
+        // An iq without an id is invalid.
+        assert!(matches!(
+            stanza("<iq xmlns='jabber:client' type='result'/>"),
+            TypedEvent::Stanza(_)
+        ));
+        assert!(matches!(
+            stanza("<r xmlns='urn:xmpp:sm:3'/>"),
+            TypedEvent::Stanza(_)
+        ));
+        assert!(matches!(
+            Event::Disconnected(Error::Disconnected).into_typed(),
+            TypedEvent::Disconnected(Error::Disconnected)
+        ));
+    }
 }
@@ -0,0 +1,115 @@
+//! Generation of the ids of outgoing stanzas
+
+use rand::{thread_rng, Rng};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use xmpp_parsers::Element;
+
+/// Generates the ids given to outgoing stanzas which lack one
+///
+/// Ids must be unique, at least among the stanzas still awaiting a response.
+pub trait IdGenerator: fmt::Debug + Send + Sync {
+    /// Make a new id
+    fn make_id(&self) -> String;
+}
+
+/// Generates random UUIDs (version 4), the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidGenerator;
+
+impl IdGenerator for UuidGenerator {
+    fn make_id(&self) -> String {
+        let mut bytes: [u8; 16] = thread_rng().gen();
+        // Version 4, variant 1
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let mut id = String::with_capacity(36);
+        for (i, byte) in bytes.iter().enumerate() {
+            if let 4 | 6 | 8 | 10 = i {
+                id.push('-');
+            }
+            id.push_str(&format!("{:02x}", byte));
+        }
+        id
+    }
+}
+
+/// Generates a prefix followed by a counter, e.g. to tell the stanzas of
+/// an application apart in logs
+///
+/// The prefix should differ between runs, lest ids get reused.
+#[derive(Debug)]
+pub struct CounterGenerator {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl CounterGenerator {
+    /// Count from 0, after `prefix`
+    pub fn new<P: Into<String>>(prefix: P) -> Self {
+        CounterGenerator {
+            prefix: prefix.into(),
+            next: AtomicU64::new(0),
+        }
+    }
+}
+
+impl IdGenerator for CounterGenerator {
+    fn make_id(&self) -> String {
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}{}", self.prefix, next)
+    }
+}
+
+/// Give an id to a message, presence or iq which lacks one
+pub(crate) fn add_stanza_id(
+    mut stanza: Element,
+    default_ns: &str,
+    generator: &dyn IdGenerator,
+) -> Element {
+    let is_stanza = stanza.is("iq", default_ns)
+        || stanza.is("message", default_ns)
+        || stanza.is("presence", default_ns);
+    if is_stanza && stanza.attr("id").is_none() {
+        stanza.set_attr("id", generator.make_id());
+    }
+
+    stanza
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmpp_parsers::ns;
+
+    #[test]
+    fn test_uuid() {
+        let id = UuidGenerator.make_id();
+        assert_eq!(id.len(), 36);
+        let groups: Vec<_> = id.split('-').map(str::len).collect();
+        assert_eq!(groups, [8, 4, 4, 4, 12]);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(id, UuidGenerator.make_id());
+    }
+
+    #[test]
+    fn test_add_stanza_id() {
+        let generator = CounterGenerator::new("bot-");
+        let message = Element::builder("message", ns::JABBER_CLIENT).build();
+        let message = add_stanza_id(message, ns::JABBER_CLIENT, &generator);
+        assert_eq!(message.attr("id"), Some("bot-0"));
+
+        let iq = Element::builder("iq", ns::JABBER_CLIENT)
+            .attr("id", "mine")
+            .build();
+        let iq = add_stanza_id(iq, ns::JABBER_CLIENT, &generator);
+        assert_eq!(iq.attr("id"), Some("mine"));
+
+        let nonza = Element::builder("r", ns::SM).build();
+        let nonza = add_stanza_id(nonza, ns::JABBER_CLIENT, &generator);
+        assert_eq!(nonza.attr("id"), None);
+
+        assert_eq!(generator.make_id(), "bot-1");
+    }
+}
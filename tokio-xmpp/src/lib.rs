@@ -1,4 +1,13 @@
 //! XMPP implementation with asynchronous I/O using Tokio.
+//!
+//! [`AsyncClient`], [`SimpleClient`] and [`Component`] need a Tokio runtime,
+//! as they spawn tasks, set timers and open sockets through it. To run a
+//! client on another runtime such as async-std or smol, use
+//! [`sansio::AsyncConnection`] over a transport of that runtime.
+//!
+//! On wasm32-unknown-unknown, built without default features, tasks and
+//! timers come from the browser instead, and [`AsyncClient`] connects over a
+//! browser WebSocket with `connect::websocket::WebSocketConnector`.
 
 #![deny(unsafe_code, missing_docs, bare_trait_objects)]
 
@@ -27,18 +36,30 @@ pub mod tcp;
 mod xmpp_codec;
 pub use crate::xmpp_codec::Packet;
 mod event;
-pub use event::Event;
+pub use event::{Event, TypedEvent};
+pub use id::IdGenerator;
 mod client;
 pub mod connect;
+pub mod id;
+pub mod rt;
+pub mod sansio;
 pub mod stream_features;
 pub mod xmpp_stream;
 
 pub use client::{
     async_client::{Client as AsyncClient, Config as AsyncConfig},
+    auth::{CredentialsProvider, SaslMechanismFactory},
+    bind::ResourceConflict,
+    handle::{ClientHandle, EventReceiver, DEFAULT_EVENT_CAPACITY},
+    hooks::HookAction,
+    iq::{IqResponse, DEFAULT_IQ_TIMEOUT},
     simple_client::Client as SimpleClient,
+    sm::StanzaAck,
+    split::{ClientReader, ClientWriter},
+    stats::ClientStats,
 };
 mod component;
-pub use crate::component::Component;
+pub use crate::component::{delegation, Component, ComponentPool};
 mod error;
 pub use crate::error::{AuthError, Error, ParseError, ProtocolError};
 
@@ -0,0 +1,137 @@
+//! Timers and tasks, from Tokio natively, and from the browser on wasm32
+//! where Tokio can't provide them
+//!
+//! On wasm32 everything runs on the single thread of the browser, so tasks
+//! don't need to be `Send` there.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(target_arch = "wasm32")]
+use futures::{
+    channel::oneshot,
+    future::{abortable, AbortHandle},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use tokio::time::{sleep, timeout, Instant, Sleep};
+#[cfg(target_arch = "wasm32")]
+pub use wasmtimer::{
+    std::Instant,
+    tokio::{sleep, timeout, Sleep},
+};
+
+/// Handle to a task started with [`spawn()`], resolving to its output
+///
+/// Dropping it detaches the task.
+#[derive(Debug)]
+pub struct JoinHandle<T> {
+    #[cfg(not(target_arch = "wasm32"))]
+    task: tokio::task::JoinHandle<T>,
+    #[cfg(target_arch = "wasm32")]
+    task: AbortHandle,
+    #[cfg(target_arch = "wasm32")]
+    output: oneshot::Receiver<T>,
+}
+
+/// Run a task in the background, on the Tokio runtime, or on the event loop
+/// of the browser on wasm32
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    JoinHandle {
+        task: tokio::spawn(future),
+    }
+}
+
+/// Run a task in the background, on the Tokio runtime, or on the event loop
+/// of the browser on wasm32
+#[cfg(target_arch = "wasm32")]
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let (future, task) = abortable(future);
+    let (sender, output) = oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Ok(output) = future.await {
+            let _ = sender.send(output);
+        }
+    });
+    JoinHandle { task, output }
+}
+
+/// Run blocking code on a thread where it can block, or in a task on wasm32
+/// where there are no other threads
+pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        JoinHandle {
+            task: tokio::task::spawn_blocking(f),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        spawn(async move { f() })
+    }
+}
+
+impl<T> JoinHandle<T> {
+    /// Cancel the task, which must not be polled anymore
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    /// Resolves to the output of the task, or resumes its panic
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = match Pin::new(&mut self.task).poll(cx) {
+            Poll::Ready(Err(e)) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Poll::Ready(result) => result.map_err(|e| e.to_string()),
+            Poll::Pending => return Poll::Pending,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let result = match Pin::new(&mut self.output).poll(cx) {
+            Poll::Ready(result) => result.map_err(|e| e.to_string()),
+            Poll::Pending => return Poll::Pending,
+        };
+        match result {
+            Ok(output) => Poll::Ready(output),
+            Err(e) => panic!("task polled after being cancelled: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn() {
+        assert_eq!(spawn(async { 42 }).await, 42);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "boom")]
+    async fn test_spawn_panic() {
+        spawn(async { panic!("boom") }).await
+    }
+
+    #[tokio::test]
+    async fn test_spawn_blocking() {
+        assert_eq!(spawn_blocking(|| 42).await, 42);
+    }
+}
@@ -0,0 +1,73 @@
+//! Resource binding
+
+use std::collections::VecDeque;
+use xmpp_parsers::bind::{BindQuery, BindResponse};
+use xmpp_parsers::iq::{Iq, IqType};
+use xmpp_parsers::stanza_error::DefinedCondition;
+use xmpp_parsers::Jid;
+
+use super::Negotiation;
+use crate::client::bind::ResourceConflict;
+use crate::xmpp_codec::Packet;
+use crate::{Error, ProtocolError};
+
+const BIND_REQ_ID: &str = "resource-bind";
+
+/// Binds the resource of the JID, or one generated by the server
+///
+/// Outputs the JID the server bound, if it told.
+#[derive(Debug)]
+pub struct Bind {
+    on_conflict: ResourceConflict,
+    retried: bool,
+    outgoing: VecDeque<Packet>,
+}
+
+impl Bind {
+    /// Request the resource of `jid`, if any
+    pub fn new(jid: &Jid, on_conflict: ResourceConflict) -> Self {
+        let resource = jid.resource().map(|resource| resource.to_string());
+        let iq = Iq::from_set(BIND_REQ_ID, BindQuery::new(resource));
+        Bind {
+            on_conflict,
+            retried: false,
+            outgoing: VecDeque::from([Packet::Stanza(iq.into())]),
+        }
+    }
+}
+
+impl Negotiation for Bind {
+    type Output = Option<Jid>;
+
+    fn poll_transmit(&mut self) -> Option<Packet> {
+        self.outgoing.pop_front()
+    }
+
+    fn handle(&mut self, packet: Packet) -> Result<Option<Option<Jid>>, Error> {
+        let Packet::Stanza(stanza) = packet else {
+            return Ok(None);
+        };
+        let iq = match Iq::try_from(stanza) {
+            Ok(iq) if iq.id == BIND_REQ_ID => iq,
+            _ => return Ok(None),
+        };
+        match iq.payload {
+            IqType::Result(payload) => Ok(Some(
+                payload
+                    .and_then(|payload| BindResponse::try_from(payload).ok())
+                    .map(Jid::from),
+            )),
+            IqType::Error(error) if error.defined_condition == DefinedCondition::Conflict => {
+                if self.on_conflict == ResourceConflict::Fail || self.retried {
+                    return Err(Error::ResourceConflict);
+                }
+                // Let the server pick a resource instead
+                self.retried = true;
+                let iq = Iq::from_set(BIND_REQ_ID, BindQuery::new(None));
+                self.outgoing.push_back(Packet::Stanza(iq.into()));
+                Ok(None)
+            }
+            _ => Err(ProtocolError::InvalidBindResponse.into()),
+        }
+    }
+}
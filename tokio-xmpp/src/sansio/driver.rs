@@ -0,0 +1,180 @@
+//! Runtime-agnostic driver
+//!
+//! [`AsyncConnection`] drives a [`ClientConnection`] over any transport
+//! implementing the `AsyncRead` and `AsyncWrite` traits of the futures
+//! crate, such as the `TcpStream` of async-std or smol's `Async<TcpStream>`.
+//! It never spawns tasks nor sets timers, so it runs on any executor.
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use xmpp_parsers::Jid;
+
+use super::{ClientConnection, ClientEvent};
+use crate::stream_features::StreamFeatures;
+use crate::xmpp_codec::Packet;
+use crate::Error;
+
+/// Size of the buffer bytes get read into
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// A [`ClientConnection`] over an async transport
+///
+/// The transport must already be secure, see [`ClientConnection`].
+#[derive(Debug)]
+pub struct AsyncConnection<T> {
+    transport: T,
+    connection: ClientConnection,
+    buffer: Box<[u8]>,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> AsyncConnection<T> {
+    /// Drive `connection` over `transport`
+    pub fn new(transport: T, connection: ClientConnection) -> Self {
+        AsyncConnection {
+            transport,
+            connection,
+            buffer: vec![0; READ_BUFFER_SIZE].into_boxed_slice(),
+        }
+    }
+
+    /// Log in, returning the bound JID and the features of the stream
+    pub async fn login(&mut self) -> Result<(Jid, StreamFeatures), Error> {
+        loop {
+            match self.next_event().await? {
+                ClientEvent::Online {
+                    bound_jid,
+                    features,
+                } => return Ok((bound_jid, features)),
+                ClientEvent::Packet(_) => (),
+            }
+        }
+    }
+
+    /// Send a packet, once logged in
+    pub async fn send(&mut self, packet: Packet) -> Result<(), Error> {
+        self.connection.send(packet)?;
+        self.flush().await
+    }
+
+    /// Wait for the next packet, once logged in
+    pub async fn recv(&mut self) -> Result<Packet, Error> {
+        loop {
+            if let ClientEvent::Packet(packet) = self.next_event().await? {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Unwraps the transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    async fn next_event(&mut self) -> Result<ClientEvent, Error> {
+        loop {
+            let event = self.connection.poll_event()?;
+            self.flush().await?;
+            if let Some(event) = event {
+                return Ok(event);
+            }
+            let read = self.transport.read(&mut self.buffer).await?;
+            if read == 0 {
+                return Err(Error::Disconnected);
+            }
+            self.connection.handle_input(&self.buffer[..read]);
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        while let Some(data) = self.connection.poll_transmit()? {
+            self.transport.write_all(&data).await?;
+        }
+        self.transport.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::sasl::common::Credentials;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use xmpp_parsers::{ns, Element};
+
+    /// Replays what a server would send, and records what got written
+    struct Replay {
+        server: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl AsyncRead for Replay {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.server).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for Replay {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.written).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.written).poll_flush(cx)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.written).poll_close(cx)
+        }
+    }
+
+    #[test]
+    fn test_without_tokio() {
+        let header = "<?xml version='1.0'?><stream:stream xmlns='jabber:client' xmlns:stream='http://etherx.jabber.org/streams' id='abc' from='capulet.lit' version='1.0'>";
+        let server = [
+            header,
+            "<stream:features><mechanisms xmlns='urn:ietf:params:xml:ns:xmpp-sasl'><mechanism>PLAIN</mechanism></mechanisms></stream:features>",
+            "<success xmlns='urn:ietf:params:xml:ns:xmpp-sasl'/>",
+            header,
+            "<stream:features><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'/></stream:features>",
+            "<iq xmlns='jabber:client' type='result' id='resource-bind'><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'><jid>juliet@capulet.lit/balcony</jid></bind></iq>",
+            "<message xmlns='jabber:client' from='romeo@montague.lit/orchard'/>",
+        ]
+        .concat();
+        let transport = Replay {
+            server: Cursor::new(server.into_bytes()),
+            written: Vec::new(),
+        };
+        let creds = Credentials::default()
+            .with_username("juliet")
+            .with_password("romeo");
+        let jid = Jid::new("juliet@capulet.lit/balcony").unwrap();
+        let mut connection = AsyncConnection::new(transport, ClientConnection::new(jid, creds));
+
+        block_on(async {
+            let (bound_jid, features) = connection.login().await.unwrap();
+            assert_eq!(bound_jid.resource().unwrap().as_str(), "balcony");
+            assert!(features.can_bind);
+
+            let presence = Element::builder("presence", ns::JABBER_CLIENT).build();
+            connection.send(Packet::Stanza(presence)).await.unwrap();
+            match connection.recv().await.unwrap() {
+                Packet::Stanza(stanza) => assert_eq!(stanza.name(), "message"),
+                _ => panic!(),
+            }
+            assert!(matches!(connection.recv().await, Err(Error::Disconnected)));
+        });
+
+        let written = String::from_utf8(connection.into_inner().written).unwrap();
+        assert!(written.ends_with("<presence xmlns='jabber:client'/>"));
+    }
+}
@@ -0,0 +1,177 @@
+//! Client login: SASL, then resource binding
+
+use ::sasl::common::Credentials;
+use std::fmt;
+use std::sync::Arc;
+use xmpp_parsers::{ns, Jid};
+
+use super::{Bind, Negotiation, Sasl, StreamHeader, StreamStart};
+use crate::client::auth::SaslMechanismFactory;
+use crate::client::bind::ResourceConflict;
+use crate::stream_features::StreamFeatures;
+use crate::xmpp_codec::Packet;
+use crate::Error;
+
+/// Step reached by a [`Login`]
+#[derive(Debug)]
+pub enum LoginStep {
+    /// Authentication succeeded, the stream restarts: the parser and
+    /// serializer of the stream must be reset before sending anything else
+    Restart,
+    /// The authenticated stream started
+    ///
+    /// Negotiate any other stream feature, then call `Login::bind()`.
+    Authenticated(StreamHeader),
+    /// The server bound the resource, to this JID
+    Bound(Jid),
+}
+
+#[derive(Debug)]
+enum Phase {
+    StreamStart {
+        machine: StreamStart,
+        authenticated: bool,
+    },
+    Sasl(Sasl),
+    Authenticated,
+    Bind(Bind),
+    Done,
+}
+
+/// Chains the stream start, SASL and resource binding negotiations
+///
+/// `handle()` returns each [`LoginStep`] reached, so the negotiation is done
+/// once it returned [`LoginStep::Bound`], or once `bind()` returned false.
+pub struct Login {
+    jid: Jid,
+    creds: Option<Credentials>,
+    sasl_mechanisms: Vec<Arc<dyn SaslMechanismFactory>>,
+    on_resource_conflict: ResourceConflict,
+    phase: Phase,
+}
+
+impl fmt::Debug for Login {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Login")
+            .field("jid", &self.jid)
+            .field("phase", &self.phase)
+            .finish()
+    }
+}
+
+impl Login {
+    /// Start logging in as `jid`, which logs in anonymously if it has no
+    /// node
+    pub fn new<J: Into<Jid>>(jid: J, creds: Credentials) -> Self {
+        let jid = jid.into();
+        let machine = StreamStart::new(&jid, ns::JABBER_CLIENT);
+        Login {
+            jid,
+            creds: Some(creds),
+            sasl_mechanisms: Vec::new(),
+            on_resource_conflict: ResourceConflict::default(),
+            phase: Phase::StreamStart {
+                machine,
+                authenticated: false,
+            },
+        }
+    }
+
+    /// Register an additional SASL mechanism, preferred over the built-in
+    /// ones
+    pub fn with_sasl_mechanism(mut self, mechanism: Arc<dyn SaslMechanismFactory>) -> Self {
+        self.sasl_mechanisms.push(mechanism);
+        self
+    }
+
+    /// Set what to do when the requested resource is already in use
+    pub fn with_resource_conflict(mut self, on_conflict: ResourceConflict) -> Self {
+        self.on_resource_conflict = on_conflict;
+        self
+    }
+
+    /// Authenticate right away, over a stream the caller already started,
+    /// e.g. to negotiate STARTTLS first
+    pub fn started(mut self, features: &StreamFeatures) -> Result<Self, Error> {
+        let creds = self.creds.take().ok_or(Error::InvalidState)?;
+        self.phase = Phase::Sasl(Sasl::new(features, creds, &self.sasl_mechanisms)?);
+        Ok(self)
+    }
+
+    /// JID being logged in, which is the bound one once done
+    pub fn jid(&self) -> &Jid {
+        &self.jid
+    }
+
+    /// Bind a resource, after [`LoginStep::Authenticated`], returning
+    /// false when the server doesn't offer it
+    ///
+    /// Without resource binding, the stream is probably usable as is, and
+    /// the login is done.
+    pub fn bind(&mut self, features: &StreamFeatures) -> bool {
+        if features.can_bind {
+            self.phase = Phase::Bind(Bind::new(&self.jid, self.on_resource_conflict));
+            true
+        } else {
+            self.phase = Phase::Done;
+            false
+        }
+    }
+}
+
+impl Negotiation for Login {
+    type Output = LoginStep;
+
+    fn poll_transmit(&mut self) -> Option<Packet> {
+        match self.phase {
+            Phase::StreamStart {
+                ref mut machine, ..
+            } => machine.poll_transmit(),
+            Phase::Sasl(ref mut machine) => machine.poll_transmit(),
+            Phase::Bind(ref mut machine) => machine.poll_transmit(),
+            Phase::Authenticated | Phase::Done => None,
+        }
+    }
+
+    fn handle(&mut self, packet: Packet) -> Result<Option<LoginStep>, Error> {
+        match self.phase {
+            Phase::StreamStart {
+                ref mut machine,
+                authenticated,
+            } => {
+                let Some(header) = machine.handle(packet)? else {
+                    return Ok(None);
+                };
+                if authenticated {
+                    self.phase = Phase::Authenticated;
+                    return Ok(Some(LoginStep::Authenticated(header)));
+                }
+                let creds = self.creds.take().ok_or(Error::InvalidState)?;
+                let machine = Sasl::new(&header.features, creds, &self.sasl_mechanisms)?;
+                self.phase = Phase::Sasl(machine);
+                Ok(None)
+            }
+            Phase::Sasl(ref mut machine) => {
+                if machine.handle(packet)?.is_none() {
+                    return Ok(None);
+                }
+                self.phase = Phase::StreamStart {
+                    machine: StreamStart::new(&self.jid, ns::JABBER_CLIENT),
+                    authenticated: true,
+                };
+                Ok(Some(LoginStep::Restart))
+            }
+            Phase::Bind(ref mut machine) => {
+                let Some(bound_jid) = machine.handle(packet)? else {
+                    return Ok(None);
+                };
+                if let Some(bound_jid) = bound_jid {
+                    self.jid = bound_jid;
+                }
+                self.phase = Phase::Done;
+                Ok(Some(LoginStep::Bound(self.jid.clone())))
+            }
+            Phase::Authenticated | Phase::Done => Err(Error::InvalidState),
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Sans-io protocol core
+//!
+//! The state machines of this module take what got received from the
+//! server and tell what to send back, without performing any I/O
+//! themselves. The async clients of this crate drive them over tokio, but
+//! they can as well be driven by another runtime, a blocking socket on an
+//! embedded device, or a test feeding them canned server responses.
+//!
+//! Each step of the login is a [`Negotiation`] working on packets:
+//! [`StreamStart`], [`Sasl`] and [`Bind`], which [`Login`] chains. The
+//! async clients of this crate drive it over their packet stream, while
+//! [`ClientConnection`] runs it and then frames stanzas, working on bytes,
+//! and [`AsyncConnection`] runs that on any async runtime.
+
+// Error is large, but these functions are far from any hot path.
+#![allow(clippy::result_large_err)]
+
+use ::sasl::common::Credentials;
+use bytes::BytesMut;
+use std::fmt;
+use std::mem::take;
+use std::sync::Arc;
+use tokio_util::codec::{Decoder, Encoder};
+use xmpp_parsers::{ns, stream_error::StreamError, Jid};
+
+use crate::client::auth::SaslMechanismFactory;
+use crate::client::bind::ResourceConflict;
+use crate::stream_features::StreamFeatures;
+use crate::xmpp_codec::{Packet, XMPPCodec};
+use crate::{Error, ProtocolError};
+
+pub mod bind;
+pub mod driver;
+pub mod login;
+pub mod sasl;
+pub mod sm;
+pub mod stream_start;
+
+pub use self::bind::Bind;
+pub use self::driver::AsyncConnection;
+pub use self::login::{Login, LoginStep};
+pub use self::sasl::Sasl;
+pub use self::sm::EnableSm;
+pub use self::stream_start::{StreamHeader, StreamStart};
+
+/// A step of the negotiation of a stream
+pub trait Negotiation {
+    /// Outcome of the negotiation
+    type Output;
+
+    /// Take the next packet to send, if any
+    ///
+    /// Call this until it returns None after creating the negotiation and
+    /// after each call to `handle()`.
+    fn poll_transmit(&mut self) -> Option<Packet>;
+
+    /// Handle a packet received from the other side, returning the outcome
+    /// once the negotiation is done
+    fn handle(&mut self, packet: Packet) -> Result<Option<Self::Output>, Error>;
+}
+
+/// Event yielded by a [`ClientConnection`]
+#[derive(Debug)]
+pub enum ClientEvent {
+    /// The client logged in, stanzas can now be exchanged
+    Online {
+        /// JID bound by the server
+        bound_jid: Jid,
+        /// Features of the authenticated stream
+        features: StreamFeatures,
+    },
+    /// Packet received once online
+    Packet(Packet),
+}
+
+/// Client login and stanza framing, over bytes
+///
+/// Feed it the bytes read from the transport with `handle_input()`, handle
+/// the events returned by `poll_event()`, and write whatever
+/// `poll_transmit()` returns, until it returns None.
+///
+/// The transport must already be secure, e.g. with direct TLS, as TLS is
+/// left to the caller. The feature negotiators of `AsyncConfig` don't run
+/// either, being async.
+pub struct ClientConnection {
+    login: Login,
+    /// Features of the authenticated stream, until online
+    features: StreamFeatures,
+    online: bool,
+    codec: XMPPCodec,
+    input: BytesMut,
+    output: BytesMut,
+}
+
+impl fmt::Debug for ClientConnection {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ClientConnection")
+            .field("login", &self.login)
+            .field("online", &self.online)
+            .finish()
+    }
+}
+
+impl ClientConnection {
+    /// Start logging in as `jid`, which logs in anonymously if it has no
+    /// node
+    pub fn new<J: Into<Jid>>(jid: J, creds: Credentials) -> Self {
+        ClientConnection {
+            login: Login::new(jid, creds),
+            features: StreamFeatures::default(),
+            online: false,
+            codec: XMPPCodec::new(),
+            input: BytesMut::new(),
+            output: BytesMut::new(),
+        }
+    }
+
+    /// Register an additional SASL mechanism, preferred over the built-in
+    /// ones
+    pub fn with_sasl_mechanism(mut self, mechanism: Arc<dyn SaslMechanismFactory>) -> Self {
+        self.login = self.login.with_sasl_mechanism(mechanism);
+        self
+    }
+
+    /// Set what to do when the requested resource is already in use
+    pub fn with_resource_conflict(mut self, on_conflict: ResourceConflict) -> Self {
+        self.login = self.login.with_resource_conflict(on_conflict);
+        self
+    }
+
+    /// Whether the client logged in
+    pub fn is_online(&self) -> bool {
+        self.online
+    }
+
+    /// Buffer bytes read from the transport
+    pub fn handle_input(&mut self, data: &[u8]) {
+        self.input.extend_from_slice(data);
+    }
+
+    /// Send a packet, once online
+    pub fn send(&mut self, packet: Packet) -> Result<(), Error> {
+        if !self.is_online() {
+            return Err(Error::InvalidState);
+        }
+        self.codec.encode(packet, &mut self.output)
+    }
+
+    /// Take the bytes to write to the transport, if any
+    pub fn poll_transmit(&mut self) -> Result<Option<BytesMut>, Error> {
+        self.encode_negotiation()?;
+        if self.output.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.output.split()))
+    }
+
+    /// Process the buffered input, returning the next event if any
+    pub fn poll_event(&mut self) -> Result<Option<ClientEvent>, Error> {
+        loop {
+            self.encode_negotiation()?;
+            let Some(packet) = self.codec.decode(&mut self.input)? else {
+                return Ok(None);
+            };
+            if self.is_online() {
+                return Ok(Some(ClientEvent::Packet(packet)));
+            }
+            match packet {
+                Packet::Stanza(ref stanza) if stanza.is("error", ns::STREAM) => {
+                    let error =
+                        StreamError::try_from(stanza.clone()).map_err(ProtocolError::Parsers)?;
+                    return Err(Error::StreamError(error));
+                }
+                Packet::StreamEnd => return Err(Error::Disconnected),
+                _ => (),
+            }
+            if let Some(event) = self.negotiate(packet)? {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// Advance the login with this packet
+    fn negotiate(&mut self, packet: Packet) -> Result<Option<ClientEvent>, Error> {
+        let Some(step) = self.login.handle(packet)? else {
+            return Ok(None);
+        };
+        let bound_jid = match step {
+            LoginStep::Restart => {
+                self.codec = XMPPCodec::new();
+                return Ok(None);
+            }
+            LoginStep::Authenticated(header) => {
+                self.features = header.features;
+                if self.login.bind(&self.features) {
+                    return Ok(None);
+                }
+                self.login.jid().clone()
+            }
+            LoginStep::Bound(bound_jid) => bound_jid,
+        };
+        self.online = true;
+        Ok(Some(ClientEvent::Online {
+            bound_jid,
+            features: take(&mut self.features),
+        }))
+    }
+
+    /// Encode the packets the login wants to send
+    fn encode_negotiation(&mut self) -> Result<(), Error> {
+        if self.online {
+            return Ok(());
+        }
+        while let Some(packet) = self.login.poll_transmit() {
+            self.codec.encode(packet, &mut self.output)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmpp_parsers::Element;
+
+    const STREAM_HEADER: &str = "<?xml version='1.0'?><stream:stream xmlns='jabber:client' xmlns:stream='http://etherx.jabber.org/streams' id='abc' from='capulet.lit' version='1.0'>";
+
+    /// Take what the client wrote, as a string
+    fn written(connection: &mut ClientConnection) -> String {
+        let output = connection.poll_transmit().unwrap().unwrap();
+        String::from_utf8(output.to_vec()).unwrap()
+    }
+
+    fn login(on_conflict: ResourceConflict) -> (ClientConnection, String) {
+        let jid = Jid::new("juliet@capulet.lit/balcony").unwrap();
+        let creds = Credentials::default()
+            .with_username("juliet")
+            .with_password("romeo");
+        let mut connection = ClientConnection::new(jid, creds).with_resource_conflict(on_conflict);
+        assert!(written(&mut connection).starts_with("<stream:stream"));
+
+        connection.handle_input(STREAM_HEADER.as_bytes());
+        connection.handle_input(b"<stream:features><mechanisms xmlns='urn:ietf:params:xml:ns:xmpp-sasl'><mechanism>PLAIN</mechanism></mechanisms></stream:features>");
+        assert!(connection.poll_event().unwrap().is_none());
+        // "\0juliet\0romeo"
+        assert!(written(&mut connection).contains(">AGp1bGlldAByb21lbw==</auth>"));
+
+        connection.handle_input(b"<success xmlns='urn:ietf:params:xml:ns:xmpp-sasl'/>");
+        assert!(connection.poll_event().unwrap().is_none());
+        assert!(written(&mut connection).starts_with("<stream:stream"));
+
+        connection.handle_input(STREAM_HEADER.as_bytes());
+        connection.handle_input(
+            b"<stream:features><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'/></stream:features>",
+        );
+        assert!(connection.poll_event().unwrap().is_none());
+        let bind = written(&mut connection);
+        assert!(bind.contains("<resource>balcony</resource>"));
+        (connection, bind)
+    }
+
+    #[test]
+    fn test_login() {
+        let (mut connection, _) = login(ResourceConflict::default());
+        connection.handle_input(b"<iq xmlns='jabber:client' type='result' id='resource-bind'><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'><jid>juliet@capulet.lit/balcony-42</jid></bind></iq>");
+        match connection.poll_event().unwrap() {
+            Some(ClientEvent::Online { bound_jid, .. }) => {
+                assert_eq!(bound_jid.resource().unwrap().as_str(), "balcony-42")
+            }
+            _ => panic!(),
+        }
+        assert!(connection.is_online());
+
+        let presence = Element::builder("presence", ns::JABBER_CLIENT).build();
+        connection.send(Packet::Stanza(presence)).unwrap();
+        assert_eq!(
+            written(&mut connection),
+            "<presence xmlns='jabber:client'/>"
+        );
+
+        connection
+            .handle_input(b"<message xmlns='jabber:client' from='romeo@montague.lit/orchard'/>");
+        match connection.poll_event().unwrap() {
+            Some(ClientEvent::Packet(Packet::Stanza(stanza))) => {
+                assert!(stanza.name() == "message")
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_resource_conflict() {
+        let (mut connection, _) = login(ResourceConflict::ServerGenerated);
+        let conflict = b"<iq xmlns='jabber:client' type='error' id='resource-bind'><error type='cancel'><conflict xmlns='urn:ietf:params:xml:ns:xmpp-stanzas'/></error></iq>";
+        connection.handle_input(conflict);
+        assert!(connection.poll_event().unwrap().is_none());
+        assert!(!written(&mut connection).contains("<resource>"));
+        connection.handle_input(conflict);
+        assert!(matches!(
+            connection.poll_event(),
+            Err(Error::ResourceConflict)
+        ));
+
+        let (mut connection, _) = login(ResourceConflict::Fail);
+        connection.handle_input(conflict);
+        assert!(matches!(
+            connection.poll_event(),
+            Err(Error::ResourceConflict)
+        ));
+    }
+
+    #[test]
+    fn test_auth_failure() {
+        let jid = Jid::new("juliet@capulet.lit").unwrap();
+        let creds = Credentials::default()
+            .with_username("juliet")
+            .with_password("tybalt");
+        let mut connection = ClientConnection::new(jid, creds);
+        assert!(connection.send(Packet::Text(String::from(" "))).is_err());
+        connection.handle_input(STREAM_HEADER.as_bytes());
+        connection.handle_input(b"<stream:features><mechanisms xmlns='urn:ietf:params:xml:ns:xmpp-sasl'><mechanism>PLAIN</mechanism></mechanisms></stream:features>");
+        connection.handle_input(
+            b"<failure xmlns='urn:ietf:params:xml:ns:xmpp-sasl'><not-authorized/></failure>",
+        );
+        assert!(matches!(connection.poll_event(), Err(Error::Auth(_))));
+    }
+}
@@ -0,0 +1,125 @@
+//! SASL authentication
+
+use sasl::client::mechanisms::{Anonymous, Plain, Scram};
+use sasl::client::{Mechanism, MechanismError};
+use sasl::common::scram::{Sha1, Sha256};
+use sasl::common::{Credentials, Identity};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use xmpp_parsers::sasl::{Auth, Challenge, Failure, Mechanism as XMPPMechanism, Response, Success};
+use xmpp_parsers::Element;
+
+use super::Negotiation;
+use crate::client::auth::SaslMechanismFactory;
+use crate::stream_features::StreamFeatures;
+use crate::xmpp_codec::Packet;
+use crate::{AuthError, Error, ProtocolError};
+
+type MechanismCreator<'a> =
+    Box<dyn Fn() -> Result<Box<dyn Mechanism + Send + Sync>, MechanismError> + Send + 'a>;
+
+fn with_credentials<M: Mechanism + Send + Sync + 'static>(
+    creds: &Credentials,
+) -> MechanismCreator<'_> {
+    Box::new(move || Ok(Box::new(M::from_credentials(creds.clone())?)))
+}
+
+/// Authenticates with the preferred mechanism offered by the server
+///
+/// Mechanisms registered by the application come first, then SCRAM-SHA-256,
+/// SCRAM-SHA-1 and PLAIN when the credentials have an identity, and finally
+/// ANONYMOUS. Once negotiated, the stream must be restarted.
+pub struct Sasl {
+    mechanism: Box<dyn Mechanism + Send + Sync>,
+    outgoing: VecDeque<Packet>,
+}
+
+impl fmt::Debug for Sasl {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sasl")
+            .field("mechanism", &self.mechanism.name())
+            .finish()
+    }
+}
+
+impl Sasl {
+    /// Pick a mechanism among those advertised in `features`, and start
+    /// authenticating with it
+    pub fn new(
+        features: &StreamFeatures,
+        creds: Credentials,
+        extra_mechs: &[Arc<dyn SaslMechanismFactory>],
+    ) -> Result<Self, Error> {
+        let mut local_mechs: Vec<(&str, MechanismCreator)> = extra_mechs
+            .iter()
+            .map(|factory| {
+                let creator: MechanismCreator = Box::new(|| factory.create(&creds));
+                (factory.name(), creator)
+            })
+            .collect();
+        if creds.identity != Identity::None {
+            local_mechs.extend([
+                ("SCRAM-SHA-256", with_credentials::<Scram<Sha256>>(&creds)),
+                ("SCRAM-SHA-1", with_credentials::<Scram<Sha1>>(&creds)),
+                ("PLAIN", with_credentials::<Plain>(&creds)),
+            ]);
+        }
+        let anonymous: MechanismCreator = Box::new(|| Ok(Box::new(Anonymous::new())));
+        local_mechs.push(("ANONYMOUS", anonymous));
+
+        let remote_mechs: HashSet<&str> = features
+            .sasl_mechanisms
+            .iter()
+            .map(String::as_str)
+            .collect();
+
+        let (_, local_mech) = local_mechs
+            .into_iter()
+            .find(|(name, _)| remote_mechs.contains(*name))
+            .ok_or(AuthError::NoMechanism)?;
+        let mut mechanism = local_mech().map_err(AuthError::Sasl)?;
+        let initial = mechanism.initial();
+
+        // Mechanisms provided by the application are unknown to xmpp-parsers.
+        let auth: Element = Auth {
+            mechanism: XMPPMechanism::from_str(mechanism.name()).map_err(ProtocolError::Parsers)?,
+            data: initial,
+        }
+        .into();
+        Ok(Sasl {
+            mechanism,
+            outgoing: VecDeque::from([Packet::Stanza(auth)]),
+        })
+    }
+}
+
+impl Negotiation for Sasl {
+    type Output = ();
+
+    fn poll_transmit(&mut self) -> Option<Packet> {
+        self.outgoing.pop_front()
+    }
+
+    fn handle(&mut self, packet: Packet) -> Result<Option<()>, Error> {
+        let Packet::Stanza(stanza) = packet else {
+            return Ok(None);
+        };
+        if let Ok(challenge) = Challenge::try_from(stanza.clone()) {
+            let response = self
+                .mechanism
+                .response(&challenge.data)
+                .map_err(AuthError::Sasl)?;
+            self.outgoing
+                .push_back(Packet::Stanza(Response { data: response }.into()));
+            Ok(None)
+        } else if Success::try_from(stanza.clone()).is_ok() {
+            Ok(Some(()))
+        } else if let Ok(failure) = Failure::try_from(stanza) {
+            Err(Error::Auth(AuthError::Fail(failure.defined_condition)))
+        } else {
+            Ok(None)
+        }
+    }
+}
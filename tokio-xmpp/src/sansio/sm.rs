@@ -0,0 +1,57 @@
+//! Enabling Stream Management (XEP-0198)
+
+use std::collections::VecDeque;
+use xmpp_parsers::sm::{Enable, Enabled, Failed};
+
+use super::Negotiation;
+use crate::xmpp_codec::Packet;
+use crate::Error;
+
+/// Enables Stream Management, without resumption, once the resource got
+/// bound
+///
+/// Outputs whether the server accepted.
+#[derive(Debug)]
+pub struct EnableSm {
+    outgoing: VecDeque<Packet>,
+}
+
+impl EnableSm {
+    /// Ask the server to enable Stream Management
+    pub fn new() -> Self {
+        EnableSm {
+            outgoing: VecDeque::from([Packet::Stanza(Enable::new().into())]),
+        }
+    }
+}
+
+impl Default for EnableSm {
+    fn default() -> Self {
+        EnableSm::new()
+    }
+}
+
+impl Negotiation for EnableSm {
+    type Output = bool;
+
+    fn poll_transmit(&mut self) -> Option<Packet> {
+        self.outgoing.pop_front()
+    }
+
+    fn handle(&mut self, packet: Packet) -> Result<Option<bool>, Error> {
+        let Packet::Stanza(stanza) = packet else {
+            return Ok(None);
+        };
+        if Enabled::try_from(stanza.clone()).is_ok() {
+            Ok(Some(true))
+        } else if let Ok(failed) = Failed::try_from(stanza) {
+            log::warn!(
+                "Server failed to enable stream management: {:?}",
+                failed.error
+            );
+            Ok(Some(false))
+        } else {
+            Ok(None)
+        }
+    }
+}
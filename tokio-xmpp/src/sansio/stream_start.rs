@@ -0,0 +1,81 @@
+//! Exchange of the `<stream:stream>` headers
+
+use std::collections::VecDeque;
+use xmpp_parsers::{ns, Jid};
+
+use super::Negotiation;
+use crate::stream_features::StreamFeatures;
+use crate::xmpp_codec::Packet;
+use crate::{Error, ProtocolError};
+
+/// What the other side announced when starting its stream
+#[derive(Debug)]
+pub struct StreamHeader {
+    /// Stream `id` attribute
+    pub id: String,
+    /// `<stream:features/>`, as received after the header
+    pub features: StreamFeatures,
+}
+
+/// Sends a `<stream:stream>` header, then waits for the one of the server
+/// and, for client streams, its `<stream:features/>`
+#[derive(Debug)]
+pub struct StreamStart {
+    outgoing: VecDeque<Packet>,
+    /// Stream id, once the header of the other side got received
+    id: Option<String>,
+}
+
+impl StreamStart {
+    /// Start a stream of namespace `ns` to the domain of `jid`
+    pub fn new(jid: &Jid, ns: &str) -> Self {
+        let attrs = [
+            ("to".to_owned(), jid.domain().to_string()),
+            ("version".to_owned(), "1.0".to_owned()),
+            ("xmlns".to_owned(), ns.to_owned()),
+            ("xmlns:stream".to_owned(), ns::STREAM.to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        StreamStart {
+            outgoing: VecDeque::from([Packet::StreamStart(attrs)]),
+            id: None,
+        }
+    }
+}
+
+impl Negotiation for StreamStart {
+    type Output = StreamHeader;
+
+    fn poll_transmit(&mut self) -> Option<Packet> {
+        self.outgoing.pop_front()
+    }
+
+    fn handle(&mut self, packet: Packet) -> Result<Option<StreamHeader>, Error> {
+        match (packet, self.id.take()) {
+            (Packet::StreamStart(attrs), None) => {
+                let stream_ns = attrs.get("xmlns").ok_or(ProtocolError::NoStreamNamespace)?;
+                let id = attrs.get("id").ok_or(ProtocolError::NoStreamId)?.clone();
+                if stream_ns == "jabber:client" && attrs.contains_key("version") {
+                    // Wait for the features
+                    self.id = Some(id);
+                    Ok(None)
+                } else {
+                    // Streams without a version have no features
+                    Ok(Some(StreamHeader {
+                        id,
+                        features: StreamFeatures::default(),
+                    }))
+                }
+            }
+            (Packet::Stanza(stanza), Some(id)) if stanza.is("features", ns::STREAM) => {
+                let features = StreamFeatures::try_from(stanza).map_err(ProtocolError::Parsers)?;
+                Ok(Some(StreamHeader { id, features }))
+            }
+            (_, id) => {
+                self.id = id;
+                Ok(None)
+            }
+        }
+    }
+}
@@ -12,11 +12,14 @@ impl AsyncClient<ServerConfig> {
     /// Start polling the returned instance so that it will connect
     /// and yield events.
     pub fn new<J: Into<Jid>, P: Into<String>>(jid: J, password: P) -> Self {
-        let config = AsyncConfig {
-            jid: jid.into(),
-            password: password.into(),
-            server: ServerConfig::UseSrv,
-        };
+        let config = AsyncConfig::new(jid, password, ServerConfig::UseSrv);
+        Self::new_with_config(config)
+    }
+
+    /// Start a new XMPP client logging in anonymously to the server of
+    /// `domain`, which must not have a node
+    pub fn new_anonymous<J: Into<Jid>>(domain: J) -> Self {
+        let config = AsyncConfig::new_anonymous(domain, ServerConfig::UseSrv);
         Self::new_with_config(config)
     }
 }
@@ -32,4 +35,16 @@ impl SimpleClient<ServerConfig> {
     pub async fn new_with_jid(jid: Jid, password: String) -> Result<Self, Error> {
         Self::new_with_jid_connector(ServerConfig::UseSrv, jid, password).await
     }
+
+    /// Start a new client connecting to a manually specified server
+    /// host and port, bypassing the SRV lookup.
+    pub async fn new_with_host<P: Into<String>>(
+        jid: Jid,
+        password: P,
+        host: String,
+        port: u16,
+    ) -> Result<Self, Error> {
+        Self::new_with_jid_connector(ServerConfig::Manual { host, port }, jid, password.into())
+            .await
+    }
 }
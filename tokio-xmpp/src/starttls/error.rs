@@ -4,6 +4,8 @@ use hickory_resolver::{error::ResolveError, proto::error::ProtoError};
 #[cfg(feature = "tls-native")]
 use native_tls::Error as TlsError;
 use std::borrow::Cow;
+
+use super::socks5::Socks5Error;
 use std::error::Error as StdError;
 use std::fmt;
 #[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
@@ -96,6 +98,8 @@ pub enum ConnectorError {
     Dns(ProtoError),
     /// DNS resolution error
     Resolve(ResolveError),
+    /// SOCKS5 proxy error
+    Socks5(Socks5Error),
 }
 
 impl StdError for ConnectorError {}
@@ -7,11 +7,14 @@ use log::debug;
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
 
-pub async fn connect_to_host(domain: &str, port: u16) -> Result<TcpStream, Error> {
+use crate::connect::TcpConfig;
+
+pub async fn connect_to_host(domain: &str, port: u16, tcp: &TcpConfig) -> Result<TcpStream, Error> {
     let ascii_domain = idna::domain_to_ascii(&domain).map_err(|_| Error::Idna)?;
 
     if let Ok(ip) = ascii_domain.parse() {
-        return Ok(TcpStream::connect(&SocketAddr::new(ip, port))
+        return Ok(tcp
+            .connect(SocketAddr::new(ip, port))
             .await
             .map_err(|e| Error::from(crate::Error::Io(e)))?);
     }
@@ -29,7 +32,7 @@ pub async fn connect_to_host(domain: &str, port: u16) -> Result<TcpStream, Error
     // first to succeed
     select_ok(
         ips.into_iter()
-            .map(|ip| TcpStream::connect(SocketAddr::new(ip, port)).boxed()),
+            .map(|ip| tcp.connect(SocketAddr::new(ip, port)).boxed()),
     )
     .await
     .map(|(result, _)| result)
@@ -40,39 +43,49 @@ pub async fn connect_with_srv(
     domain: &str,
     srv: &str,
     fallback_port: u16,
+    tcp: &TcpConfig,
 ) -> Result<TcpStream, Error> {
     let ascii_domain = idna::domain_to_ascii(&domain).map_err(|_| Error::Idna)?;
 
     if let Ok(ip) = ascii_domain.parse() {
         debug!("Attempting connection to {ip}:{fallback_port}");
-        return Ok(TcpStream::connect(&SocketAddr::new(ip, fallback_port))
+        return Ok(tcp
+            .connect(SocketAddr::new(ip, fallback_port))
             .await
             .map_err(|e| Error::from(crate::Error::Io(e)))?);
     }
 
+    let targets = lookup_srv(&ascii_domain, srv).await?;
+    if targets.is_empty() {
+        // SRV lookup error, retry with hostname
+        debug!("Attempting connection to {domain}:{fallback_port}");
+        return connect_to_host(domain, fallback_port, tcp).await;
+    }
+
+    // TODO: sort lookup records by priority/weight
+    for (host, port) in targets {
+        debug!("Attempting connection to {srv}.{ascii_domain} {host}:{port}");
+        if let Ok(stream) = connect_to_host(&host, port, tcp).await {
+            return Ok(stream);
+        }
+    }
+    Err(crate::Error::Disconnected.into())
+}
+
+/// Returns the targets of the `srv` records of `domain`, or none if the
+/// lookup failed
+pub async fn lookup_srv(domain: &str, srv: &str) -> Result<Vec<(String, u16)>, Error> {
+    let ascii_domain = idna::domain_to_ascii(domain).map_err(|_| Error::Idna)?;
     let resolver = TokioAsyncResolver::tokio_from_system_conf().map_err(ConnectorError::Resolve)?;
 
     let srv_domain = format!("{}.{}.", srv, ascii_domain)
         .into_name()
         .map_err(ConnectorError::Dns)?;
-    let srv_records = resolver.srv_lookup(srv_domain.clone()).await.ok();
-
-    match srv_records {
-        Some(lookup) => {
-            // TODO: sort lookup records by priority/weight
-            for srv in lookup.iter() {
-                debug!("Attempting connection to {srv_domain} {srv}");
-                match connect_to_host(&srv.target().to_ascii(), srv.port()).await {
-                    Ok(stream) => return Ok(stream),
-                    Err(_) => {}
-                }
-            }
-            Err(crate::Error::Disconnected.into())
-        }
-        None => {
-            // SRV lookup error, retry with hostname
-            debug!("Attempting connection to {domain}:{fallback_port}");
-            connect_to_host(domain, fallback_port).await
-        }
-    }
+    Ok(match resolver.srv_lookup(srv_domain).await {
+        Ok(lookup) => lookup
+            .iter()
+            .map(|srv| (srv.target().to_ascii(), srv.port()))
+            .collect(),
+        Err(_) => Vec::new(),
+    })
 }
@@ -25,15 +25,22 @@ use tokio::{
 };
 use xmpp_parsers::{ns, Element, Jid};
 
-use crate::{connect::ServerConnector, xmpp_codec::Packet, AsyncClient, SimpleClient};
+use crate::connect::{ServerConnector, TcpConfig, TlsInfo};
 use crate::{connect::ServerConnectorError, xmpp_stream::XMPPStream};
+use crate::{xmpp_codec::Packet, AsyncClient, SimpleClient};
 
 use self::error::Error;
-use self::happy_eyeballs::{connect_to_host, connect_with_srv};
+use self::happy_eyeballs::{connect_to_host, connect_with_srv, lookup_srv};
+use self::socks5::connect_via_proxy;
 
 mod client;
 pub mod error;
 mod happy_eyeballs;
+mod socks5;
+mod tls_config;
+
+pub use self::socks5::{Socks5Config, Socks5Error};
+pub use self::tls_config::TlsConfig;
 
 /// AsyncClient that connects over StartTls
 pub type StartTlsAsyncClient = AsyncClient<ServerConfig>;
@@ -55,36 +62,134 @@ pub enum ServerConfig {
     },
 }
 
+impl ServerConfig {
+    /// Use custom TLS settings when connecting to this server
+    pub fn with_tls(self, tls: TlsConfig) -> TlsServerConfig {
+        TlsServerConfig {
+            server: self,
+            tls,
+            proxy: None,
+            tcp: TcpConfig::default(),
+        }
+    }
+
+    /// Connect to this server through a SOCKS5 proxy, e.g. Tor
+    pub fn with_proxy(self, proxy: Socks5Config) -> TlsServerConfig {
+        self.with_tls(TlsConfig::default()).with_proxy(proxy)
+    }
+
+    /// Use custom TCP socket settings when connecting to this server
+    pub fn with_tcp(self, tcp: TcpConfig) -> TlsServerConfig {
+        self.with_tls(TlsConfig::default()).with_tcp(tcp)
+    }
+}
+
+/// StartTLS XMPP server connection configuration, with custom TLS settings
+#[derive(Clone, Debug)]
+pub struct TlsServerConfig {
+    /// Server to connect to
+    pub server: ServerConfig,
+    /// TLS settings
+    pub tls: TlsConfig,
+    /// SOCKS5 proxy to connect through
+    pub proxy: Option<Socks5Config>,
+    /// TCP socket settings, applied to the connection to the proxy if any
+    pub tcp: TcpConfig,
+}
+
+impl TlsServerConfig {
+    /// Connect to this server through a SOCKS5 proxy, e.g. Tor
+    pub fn with_proxy(mut self, proxy: Socks5Config) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Use custom TCP socket settings
+    pub fn with_tcp(mut self, tcp: TcpConfig) -> Self {
+        self.tcp = tcp;
+        self
+    }
+}
+
 impl ServerConnectorError for Error {}
 
+async fn connect(
+    server: &ServerConfig,
+    tls: &TlsConfig,
+    proxy: Option<&Socks5Config>,
+    tcp: &TcpConfig,
+    jid: &Jid,
+    ns: &str,
+) -> Result<XMPPStream<TlsStream<TcpStream>>, Error> {
+    // TCP connection
+    let tcp_stream = match (server, proxy) {
+        (ServerConfig::UseSrv, None) => {
+            connect_with_srv(jid.domain().as_str(), "_xmpp-client._tcp", 5222, tcp).await?
+        }
+        (ServerConfig::Manual { host, port }, None) => {
+            connect_to_host(host.as_str(), *port, tcp).await?
+        }
+        (ServerConfig::UseSrv, Some(proxy)) => {
+            let domain = jid.domain().as_str();
+            let mut targets = Vec::new();
+            if proxy.srv_lookup {
+                targets = lookup_srv(domain, "_xmpp-client._tcp").await?;
+            }
+            if targets.is_empty() {
+                targets.push((domain.to_owned(), 5222));
+            }
+            let mut result = Err(crate::Error::Disconnected.into());
+            for (host, port) in targets {
+                result = connect_via_proxy(proxy, &host, port, tcp).await;
+                if result.is_ok() {
+                    break;
+                }
+            }
+            result?
+        }
+        (ServerConfig::Manual { host, port }, Some(proxy)) => {
+            connect_via_proxy(proxy, host, *port, tcp).await?
+        }
+    };
+
+    // Unencryped XMPPStream
+    let xmpp_stream = XMPPStream::start(tcp_stream, jid.clone(), ns.to_owned()).await?;
+
+    if xmpp_stream.stream_features.starttls.is_some() {
+        // TlsStream
+        let tls_stream = starttls_with_config(xmpp_stream, tls).await?;
+        // Encrypted XMPPStream
+        Ok(XMPPStream::start(tls_stream, jid.clone(), ns.to_owned()).await?)
+    } else {
+        return Err(crate::Error::Protocol(crate::ProtocolError::NoTls).into());
+    }
+}
+
 impl ServerConnector for ServerConfig {
     type Stream = TlsStream<TcpStream>;
     type Error = Error;
     async fn connect(&self, jid: &Jid, ns: &str) -> Result<XMPPStream<Self::Stream>, Error> {
-        // TCP connection
-        let tcp_stream = match self {
-            ServerConfig::UseSrv => {
-                connect_with_srv(jid.domain().as_str(), "_xmpp-client._tcp", 5222).await?
-            }
-            ServerConfig::Manual { host, port } => connect_to_host(host.as_str(), *port).await?,
-        };
-
-        // Unencryped XMPPStream
-        let xmpp_stream = XMPPStream::start(tcp_stream, jid.clone(), ns.to_owned()).await?;
-
-        if xmpp_stream.stream_features.can_starttls() {
-            // TlsStream
-            let tls_stream = starttls(xmpp_stream).await?;
-            // Encrypted XMPPStream
-            Ok(XMPPStream::start(tls_stream, jid.clone(), ns.to_owned()).await?)
-        } else {
-            return Err(crate::Error::Protocol(crate::ProtocolError::NoTls).into());
-        }
+        connect(
+            self,
+            &TlsConfig::default(),
+            None,
+            &TcpConfig::default(),
+            jid,
+            ns,
+        )
+        .await
+    }
+
+    fn redirect(&self, host: &str, port: u16) -> Option<Self> {
+        Some(ServerConfig::Manual {
+            host: host.to_owned(),
+            port,
+        })
     }
 
     fn channel_binding(
         #[allow(unused_variables)] stream: &Self::Stream,
-    ) -> Result<sasl::common::ChannelBinding, Error> {
+    ) -> Result<ChannelBinding, Error> {
         #[cfg(feature = "tls-native")]
         {
             log::warn!("tls-native doesn’t support channel binding, please use tls-rust if you want this feature!");
@@ -108,15 +213,88 @@ impl ServerConnector for ServerConfig {
             })
         }
     }
+
+    fn tls_info(stream: &Self::Stream) -> Option<TlsInfo> {
+        #[cfg(feature = "tls-native")]
+        {
+            let certificate = stream.get_ref().peer_certificate().ok()??;
+            Some(TlsInfo {
+                peer_certificates: vec![certificate.to_der().ok()?],
+                protocol_version: None,
+                cipher_suite: None,
+            })
+        }
+        #[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
+        {
+            let (_, connection) = stream.get_ref();
+            Some(TlsInfo {
+                peer_certificates: connection
+                    .peer_certificates()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|certificate| certificate.0.clone())
+                    .collect(),
+                protocol_version: connection
+                    .protocol_version()
+                    .and_then(|version| version.as_str())
+                    .map(String::from),
+                cipher_suite: connection
+                    .negotiated_cipher_suite()
+                    .and_then(|suite| suite.suite().as_str())
+                    .map(String::from),
+            })
+        }
+    }
+}
+
+impl ServerConnector for TlsServerConfig {
+    type Stream = TlsStream<TcpStream>;
+    type Error = Error;
+    async fn connect(&self, jid: &Jid, ns: &str) -> Result<XMPPStream<Self::Stream>, Error> {
+        connect(
+            &self.server,
+            &self.tls,
+            self.proxy.as_ref(),
+            &self.tcp,
+            jid,
+            ns,
+        )
+        .await
+    }
+
+    fn redirect(&self, host: &str, port: u16) -> Option<Self> {
+        Some(TlsServerConfig {
+            server: self.server.redirect(host, port)?,
+            tls: self.tls.clone(),
+            proxy: self.proxy.clone(),
+            tcp: self.tcp.clone(),
+        })
+    }
+
+    fn channel_binding(stream: &Self::Stream) -> Result<ChannelBinding, Error> {
+        ServerConfig::channel_binding(stream)
+    }
+
+    fn tls_info(stream: &Self::Stream) -> Option<TlsInfo> {
+        ServerConfig::tls_info(stream)
+    }
 }
 
 #[cfg(feature = "tls-native")]
 async fn get_tls_stream<S: AsyncRead + AsyncWrite + Unpin>(
     xmpp_stream: XMPPStream<S>,
+    tls: &TlsConfig,
 ) -> Result<TlsStream<S>, Error> {
-    let domain = xmpp_stream.jid.domain().to_owned();
+    let domain = match tls.server_name {
+        Some(ref server_name) => server_name.clone(),
+        None => xmpp_stream.jid.domain().to_string(),
+    };
     let stream = xmpp_stream.into_inner();
-    let tls_stream = TlsConnector::from(NativeTlsConnector::builder().build().unwrap())
+    let mut builder = NativeTlsConnector::builder();
+    for root in &tls.extra_roots {
+        builder.add_root_certificate(root.clone());
+    }
+    let tls_stream = TlsConnector::from(builder.build()?)
         .connect(&domain, stream)
         .await?;
     Ok(tls_stream)
@@ -125,23 +303,36 @@ async fn get_tls_stream<S: AsyncRead + AsyncWrite + Unpin>(
 #[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
 async fn get_tls_stream<S: AsyncRead + AsyncWrite + Unpin>(
     xmpp_stream: XMPPStream<S>,
+    tls: &TlsConfig,
 ) -> Result<TlsStream<S>, Error> {
-    let domain = xmpp_stream.jid.domain().to_string();
-    let domain = ServerName::try_from(domain.as_str())?;
+    let domain = match tls.server_name {
+        Some(ref server_name) => ServerName::try_from(server_name.as_str())?,
+        None => ServerName::try_from(xmpp_stream.jid.domain().as_str())?,
+    };
     let stream = xmpp_stream.into_inner();
-    let mut root_store = RootCertStore::empty();
-    root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
-        OwnedTrustAnchor::from_subject_spki_name_constraints(
-            ta.subject,
-            ta.spki,
-            ta.name_constraints,
-        )
-    }));
-    let config = ClientConfig::builder()
-        .with_safe_defaults()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
-    let tls_stream = TlsConnector::from(Arc::new(config))
+    let config = match tls.client_config {
+        Some(ref config) => config.clone(),
+        None => {
+            let mut root_store = RootCertStore::empty();
+            root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            }));
+            for root in &tls.extra_roots {
+                root_store.add(root)?;
+            }
+            Arc::new(
+                ClientConfig::builder()
+                    .with_safe_defaults()
+                    .with_root_certificates(root_store)
+                    .with_no_client_auth(),
+            )
+        }
+    };
+    let tls_stream = TlsConnector::from(config)
         .connect(domain, stream)
         .await
         .map_err(|e| Error::from(crate::Error::Io(e)))?;
@@ -151,7 +342,15 @@ async fn get_tls_stream<S: AsyncRead + AsyncWrite + Unpin>(
 /// Performs `<starttls/>` on an XMPPStream and returns a binary
 /// TlsStream.
 pub async fn starttls<S: AsyncRead + AsyncWrite + Unpin>(
+    xmpp_stream: XMPPStream<S>,
+) -> Result<TlsStream<S>, Error> {
+    starttls_with_config(xmpp_stream, &TlsConfig::default()).await
+}
+
+/// Same as `starttls()`, with custom TLS settings
+pub async fn starttls_with_config<S: AsyncRead + AsyncWrite + Unpin>(
     mut xmpp_stream: XMPPStream<S>,
+    tls: &TlsConfig,
 ) -> Result<TlsStream<S>, Error> {
     let nonza = Element::builder("starttls", ns::TLS).build();
     let packet = Packet::Stanza(nonza);
@@ -168,5 +367,5 @@ pub async fn starttls<S: AsyncRead + AsyncWrite + Unpin>(
         }
     }
 
-    get_tls_stream(xmpp_stream).await
+    get_tls_stream(xmpp_stream, tls).await
 }
@@ -0,0 +1,292 @@
+//! SOCKS5 proxy support for the starttls `ServerConnector`, e.g. to connect
+//! through Tor
+
+use log::debug;
+use rand::{distributions::Alphanumeric, Rng};
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::IpAddr;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::error::{ConnectorError, Error};
+use crate::connect::TcpConfig;
+
+const VERSION: u8 = 5;
+const NO_AUTHENTICATION: u8 = 0;
+const USERNAME_PASSWORD: u8 = 2;
+const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const CONNECT: u8 = 1;
+const IPV4: u8 = 1;
+const DOMAIN_NAME: u8 = 3;
+const IPV6: u8 = 4;
+
+/// SOCKS5 proxy settings
+///
+/// Host names are always sent to the proxy to be resolved there, so no DNS
+/// query leaves this machine, except SRV lookups if enabled with
+/// `with_srv_lookup`.
+#[derive(Clone)]
+pub struct Socks5Config {
+    /// Address of the proxy, e.g. `127.0.0.1:9050` for Tor
+    pub address: String,
+    /// Username and password to authenticate to the proxy with
+    pub credentials: Option<(String, String)>,
+    /// Whether to look up SRV records of the JID domain, which happens
+    /// outside of the proxy
+    pub srv_lookup: bool,
+}
+
+impl Socks5Config {
+    /// Connect through the SOCKS5 proxy at `address`, without
+    /// authentication
+    pub fn new<S: Into<String>>(address: S) -> Self {
+        Socks5Config {
+            address: address.into(),
+            credentials: None,
+            srv_lookup: false,
+        }
+    }
+
+    /// Authenticate to the proxy with a username and password
+    ///
+    /// Tor uses different circuits for different credentials, so giving a
+    /// distinct username to each account isolates their streams.
+    pub fn with_credentials<U: Into<String>, P: Into<String>>(
+        mut self,
+        username: U,
+        password: P,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Authenticate with random credentials, so that Tor uses circuits not
+    /// shared with any other connection
+    pub fn with_stream_isolation(self) -> Self {
+        let username: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        self.with_credentials(username, "xmpp")
+    }
+
+    /// Look up SRV records of the JID domain with the system resolver,
+    /// leaking it to the DNS server
+    pub fn with_srv_lookup(mut self) -> Self {
+        self.srv_lookup = true;
+        self
+    }
+}
+
+impl fmt::Debug for Socks5Config {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Socks5Config")
+            .field("address", &self.address)
+            .field(
+                "username",
+                &self.credentials.as_ref().map(|(username, _)| username),
+            )
+            .field("srv_lookup", &self.srv_lookup)
+            .finish()
+    }
+}
+
+/// Error reported by, or talking to, a SOCKS5 proxy
+#[derive(Debug)]
+pub enum Socks5Error {
+    /// The proxy answered something which isn't SOCKS5
+    Protocol,
+    /// The proxy doesn't accept our authentication method
+    NoAcceptableMethod,
+    /// The proxy rejected our credentials
+    AuthenticationFailed,
+    /// Username, password or host name longer than 255 bytes
+    TooLong,
+    /// The proxy couldn't connect to the server, with the reply code of
+    /// RFC 1928 section 6
+    Reply(u8),
+}
+
+impl StdError for Socks5Error {}
+
+impl fmt::Display for Socks5Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Socks5Error::Protocol => write!(fmt, "invalid SOCKS5 reply"),
+            Socks5Error::NoAcceptableMethod => {
+                write!(fmt, "no acceptable SOCKS5 authentication method")
+            }
+            Socks5Error::AuthenticationFailed => write!(fmt, "SOCKS5 authentication failed"),
+            Socks5Error::TooLong => write!(fmt, "SOCKS5 field longer than 255 bytes"),
+            Socks5Error::Reply(code) => write!(fmt, "SOCKS5 connection failed: code {}", code),
+        }
+    }
+}
+
+impl From<Socks5Error> for Error {
+    fn from(e: Socks5Error) -> Self {
+        Error::Connection(ConnectorError::Socks5(e))
+    }
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::from(crate::Error::Io(e))
+}
+
+fn push_field(buf: &mut Vec<u8>, field: &[u8]) -> Result<(), Socks5Error> {
+    let len = u8::try_from(field.len()).map_err(|_| Socks5Error::TooLong)?;
+    buf.push(len);
+    buf.extend_from_slice(field);
+    Ok(())
+}
+
+/// Connects to `host` through the proxy, which resolves it
+pub async fn connect_via_proxy(
+    proxy: &Socks5Config,
+    host: &str,
+    port: u16,
+    tcp: &TcpConfig,
+) -> Result<TcpStream, Error> {
+    debug!(
+        "Attempting connection to {host}:{port} via {}",
+        proxy.address
+    );
+    let mut stream = tcp
+        .connect_to_host(proxy.address.as_str())
+        .await
+        .map_err(io_error)?;
+    handshake(&mut stream, proxy.credentials.as_ref(), host, port).await?;
+    Ok(stream)
+}
+
+async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    credentials: Option<&(String, String)>,
+    host: &str,
+    port: u16,
+) -> Result<(), Error> {
+    let method = match credentials {
+        Some(_) => USERNAME_PASSWORD,
+        None => NO_AUTHENTICATION,
+    };
+    stream
+        .write_all(&[VERSION, 1, method])
+        .await
+        .map_err(io_error)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await.map_err(io_error)?;
+    match reply {
+        [VERSION, NO_ACCEPTABLE_METHOD] => return Err(Socks5Error::NoAcceptableMethod.into()),
+        [VERSION, chosen] if chosen == method => (),
+        _ => return Err(Socks5Error::Protocol.into()),
+    }
+
+    // Username/password authentication, RFC 1929
+    if let Some((username, password)) = credentials {
+        let mut request = vec![1];
+        push_field(&mut request, username.as_bytes())?;
+        push_field(&mut request, password.as_bytes())?;
+        stream.write_all(&request).await.map_err(io_error)?;
+        stream.read_exact(&mut reply).await.map_err(io_error)?;
+        if reply[1] != 0 {
+            return Err(Socks5Error::AuthenticationFailed.into());
+        }
+    }
+
+    let mut request = vec![VERSION, CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let ascii_host = idna::domain_to_ascii(host).map_err(|_| Error::Idna)?;
+            request.push(DOMAIN_NAME);
+            push_field(&mut request, ascii_host.as_bytes())?;
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await.map_err(io_error)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await.map_err(io_error)?;
+    if reply[0] != VERSION {
+        return Err(Socks5Error::Protocol.into());
+    }
+    if reply[1] != 0 {
+        return Err(Socks5Error::Reply(reply[1]).into());
+    }
+    // Skip the address the proxy bound to, and its port
+    let len = match reply[3] {
+        IPV4 => 4,
+        IPV6 => 16,
+        DOMAIN_NAME => stream.read_u8().await.map_err(io_error)? as usize,
+        _ => return Err(Socks5Error::Protocol.into()),
+    };
+    let mut bound = vec![0u8; len + 2];
+    stream.read_exact(&mut bound).await.map_err(io_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handshake() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            let mut buf = [0u8; 3];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [5, 1, 2]);
+            proxy.write_all(&[5, 2]).await.unwrap();
+
+            let mut buf = [0u8; 11];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"\x01\x04user\x04pass");
+            proxy.write_all(&[1, 0]).await.unwrap();
+
+            let mut buf = [0u8; 18];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"\x05\x01\x00\x03\x0bexample.org\x14\x66");
+            proxy
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0x14, 0x66])
+                .await
+                .unwrap();
+        });
+        let credentials = (String::from("user"), String::from("pass"));
+        handshake(&mut client, Some(&credentials), "example.org", 5222)
+            .await
+            .unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_refused() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 3];
+            proxy.read_exact(&mut buf).await.unwrap();
+            proxy.write_all(&[5, 0]).await.unwrap();
+            let mut buf = [0u8; 10];
+            proxy.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [5, 1, 0, 1, 192, 0, 2, 1, 0x14, 0x66]);
+            proxy.write_all(&[5, 5, 0, 1]).await.unwrap();
+        });
+        let err = handshake(&mut client, None, "192.0.2.1", 5222)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Connection(ConnectorError::Socks5(Socks5Error::Reply(5)))
+        ));
+    }
+}
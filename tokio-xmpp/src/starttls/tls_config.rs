@@ -0,0 +1,62 @@
+//! TLS settings for the starttls `ServerConnector`
+
+use std::fmt;
+
+#[cfg(feature = "tls-native")]
+use native_tls::Certificate;
+#[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
+use {
+    std::sync::Arc,
+    tokio_rustls::rustls::{Certificate, ClientConfig},
+};
+
+/// TLS settings used after `<starttls/>`
+///
+/// The default validates the server certificate against the usual public
+/// roots, for the domain of the JID.
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    /// Name to send as SNI and to validate the certificate against,
+    /// instead of the JID domain
+    pub server_name: Option<String>,
+    /// Root certificates trusted in addition to the default ones
+    pub extra_roots: Vec<Certificate>,
+    /// Pre-built rustls configuration, used as is instead of the default
+    /// one and `extra_roots`
+    #[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
+    pub client_config: Option<Arc<ClientConfig>>,
+}
+
+impl TlsConfig {
+    /// Validate the certificate against `server_name` rather than the JID
+    /// domain, e.g. when SRV records delegate to another host
+    pub fn with_server_name<S: Into<String>>(mut self, server_name: S) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    /// Trust an additional root certificate, e.g. a private CA
+    pub fn with_extra_root(mut self, root: Certificate) -> Self {
+        self.extra_roots.push(root);
+        self
+    }
+
+    /// Use a pre-built rustls `ClientConfig`
+    #[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
+    pub fn with_client_config(mut self, client_config: Arc<ClientConfig>) -> Self {
+        self.client_config = Some(client_config);
+        self
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = fmt.debug_struct("TlsConfig");
+        debug
+            .field("server_name", &self.server_name)
+            .field("extra_roots", &self.extra_roots.len());
+        #[cfg(all(feature = "tls-rust", not(feature = "tls-native")))]
+        debug.field("client_config", &self.client_config.is_some());
+        debug.finish()
+    }
+}
@@ -1,40 +1,41 @@
-//! Contains wrapper for `<stream:features/>`
+//! `<stream:features/>`, and the negotiation of additional ones
 
-use crate::error::AuthError;
-use xmpp_parsers::{ns, Element};
+use futures::{future::BoxFuture, Sink, Stream};
+use std::fmt;
 
-/// Wraps `<stream:features/>`, usually the very first nonza of an
-/// XMPPStream.
-///
-/// TODO: should this rather go into xmpp-parsers, kept in a decoded
-/// struct?
-#[derive(Debug)]
-pub struct StreamFeatures(pub Element);
+use crate::connect::AsyncReadAndWrite;
+use crate::error::Error;
+use crate::xmpp_codec::Packet;
+use crate::xmpp_stream::XMPPStream;
 
-impl StreamFeatures {
-    /// Wrap the nonza
-    pub fn new(element: Element) -> Self {
-        StreamFeatures(element)
-    }
+/// `<stream:features/>`, usually the very first nonza of an XMPPStream,
+/// parsed; the features it doesn't know are kept in `others`
+pub use xmpp_parsers::stream_features::StreamFeatures;
 
-    /// Can initiate TLS session with this server?
-    pub fn can_starttls(&self) -> bool {
-        self.0.get_child("starttls", ns::TLS).is_some()
-    }
+/// Stream over which a [`FeatureNegotiator`] exchanges its nonzas
+pub trait NegotiationStream:
+    Stream<Item = Result<Packet, Error>> + Sink<Packet, Error = Error> + Unpin + Send
+{
+}
+impl<S: AsyncReadAndWrite> NegotiationStream for XMPPStream<S> {}
 
-    /// Iterate over SASL mechanisms
-    pub fn sasl_mechanisms<'a>(&'a self) -> Result<impl Iterator<Item = String> + 'a, AuthError> {
-        Ok(self
-            .0
-            .get_child("mechanisms", ns::SASL)
-            .ok_or(AuthError::NoMechanism)?
-            .children()
-            .filter(|child| child.is("mechanism", ns::SASL))
-            .map(|mech_el| mech_el.text()))
-    }
+/// Negotiates an additional stream feature
+///
+/// Registered negotiators run once authentication is done and before
+/// resource binding, in the order they were registered, letting
+/// applications experiment with custom nonzas without forking the
+/// connection logic.
+pub trait FeatureNegotiator: fmt::Debug + Send + Sync {
+    /// Whether this negotiator should run, given the features advertised
+    /// by the server
+    fn wants(&self, features: &StreamFeatures) -> bool;
 
-    /// Does server support user resource binding?
-    pub fn can_bind(&self) -> bool {
-        self.0.get_child("bind", ns::BIND).is_some()
-    }
+    /// Negotiate the feature over the stream
+    ///
+    /// Returns whether the stream must be restarted afterwards.
+    fn negotiate<'a>(
+        &'a self,
+        features: &'a StreamFeatures,
+        stream: &'a mut dyn NegotiationStream,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
 }
@@ -1,11 +1,12 @@
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::sink::SinkExt;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
-use xmpp_parsers::{ns, Element, Jid};
+use xmpp_parsers::{stream_error::StreamError, Jid};
 
+use crate::sansio::StreamStart;
 use crate::xmpp_codec::{Packet, XMPPCodec};
-use crate::xmpp_stream::XMPPStream;
-use crate::{Error, ProtocolError};
+use crate::xmpp_stream::{drive, XMPPStream};
+use crate::Error;
 
 /// Sends a `<stream:stream>`, then wait for one from the server, and
 /// construct an XMPPStream.
@@ -14,61 +15,26 @@ pub async fn start<S: AsyncRead + AsyncWrite + Unpin>(
     jid: Jid,
     ns: String,
 ) -> Result<XMPPStream<S>, Error> {
-    let attrs = [
-        ("to".to_owned(), jid.domain().to_string()),
-        ("version".to_owned(), "1.0".to_owned()),
-        ("xmlns".to_owned(), ns.clone()),
-        ("xmlns:stream".to_owned(), ns::STREAM.to_owned()),
-    ]
-    .iter()
-    .cloned()
-    .collect();
-    stream.send(Packet::StreamStart(attrs)).await?;
+    let mut machine = StreamStart::new(&jid, &ns);
+    let header = match drive(&mut stream, &mut machine).await {
+        Ok(header) => header,
+        Err(e) => return Err(fail(stream, e).await),
+    };
+    Ok(XMPPStream::new(jid, stream, ns, header.id, header.features))
+}
 
-    let stream_attrs;
-    loop {
-        match stream.next().await {
-            Some(Ok(Packet::StreamStart(attrs))) => {
-                stream_attrs = attrs;
-                break;
-            }
-            Some(Ok(_)) => {}
-            Some(Err(e)) => return Err(e.into()),
-            None => return Err(Error::Disconnected),
-        }
+/// Tells the other side what was wrong with what it sent, if this error
+/// has a stream error condition, and closes the stream
+async fn fail<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: Framed<S, XMPPCodec>,
+    error: Error,
+) -> Error {
+    if let Some(condition) = error.stream_error_condition() {
+        let _ = stream
+            .feed(Packet::Stanza(StreamError::new(condition).into()))
+            .await;
+        let _ = stream.send(Packet::StreamEnd).await;
+        let _ = stream.close().await;
     }
-
-    let stream_ns = stream_attrs
-        .get("xmlns")
-        .ok_or(ProtocolError::NoStreamNamespace)?
-        .clone();
-    let stream_id = stream_attrs
-        .get("id")
-        .ok_or(ProtocolError::NoStreamId)?
-        .clone();
-    let stream = if stream_ns == "jabber:client" && stream_attrs.get("version").is_some() {
-        let stream_features;
-        loop {
-            match stream.next().await {
-                Some(Ok(Packet::Stanza(stanza))) if stanza.is("features", ns::STREAM) => {
-                    stream_features = stanza;
-                    break;
-                }
-                Some(Ok(_)) => {}
-                Some(Err(e)) => return Err(e.into()),
-                None => return Err(Error::Disconnected),
-            }
-        }
-        XMPPStream::new(jid, stream, ns, stream_id, stream_features)
-    } else {
-        // FIXME: huge hack, shouldn’t be an element!
-        XMPPStream::new(
-            jid,
-            stream,
-            ns,
-            stream_id.clone(),
-            Element::builder(stream_id, ns::STREAM).build(),
-        )
-    };
-    Ok(stream)
+    error
 }
@@ -5,9 +5,9 @@ use std::sync::Arc;
 use tokio::net::TcpStream;
 
 use crate::{
-    connect::{ServerConnector, ServerConnectorError},
+    connect::{ServerConnector, ServerConnectorError, TcpConfig},
     xmpp_stream::XMPPStream,
-    Component,
+    AsyncClient, Component, SimpleClient,
 };
 
 use self::error::Error;
@@ -18,16 +18,34 @@ pub mod error;
 /// Component that connects over TCP
 pub type TcpComponent = Component<TcpServerConnector>;
 
+/// AsyncClient that connects over plaintext TCP
+pub type TcpAsyncClient = AsyncClient<TcpServerConnector>;
+
+/// SimpleClient that connects over plaintext TCP
+pub type TcpSimpleClient = SimpleClient<TcpServerConnector>;
+
 /// Connect via insecure plaintext TCP to an XMPP server
 /// This should only be used over localhost or otherwise when you know what you are doing
 /// Probably mostly useful for Components
 #[derive(Debug, Clone)]
-pub struct TcpServerConnector(Arc<String>);
+pub struct TcpServerConnector {
+    addr: Arc<String>,
+    tcp: TcpConfig,
+}
 
 impl TcpServerConnector {
     /// Create a new connector with the given address
     pub fn new(addr: String) -> Self {
-        Self(addr.into())
+        Self {
+            addr: addr.into(),
+            tcp: TcpConfig::default(),
+        }
+    }
+
+    /// Use custom TCP socket settings
+    pub fn with_tcp(mut self, tcp: TcpConfig) -> Self {
+        self.tcp = tcp;
+        self
     }
 }
 
@@ -41,9 +59,20 @@ impl ServerConnector for TcpServerConnector {
         jid: &xmpp_parsers::Jid,
         ns: &str,
     ) -> Result<XMPPStream<Self::Stream>, Self::Error> {
-        let stream = TcpStream::connect(&*self.0)
+        let stream = self
+            .tcp
+            .connect_to_host(&self.addr)
             .await
             .map_err(|e| crate::Error::Io(e))?;
         Ok(XMPPStream::start(stream, jid.clone(), ns.to_owned()).await?)
     }
+
+    fn redirect(&self, host: &str, port: u16) -> Option<Self> {
+        let addr = if host.contains(':') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        };
+        Some(Self::new(addr).with_tcp(self.tcp.clone()))
+    }
 }
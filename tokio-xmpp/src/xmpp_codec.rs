@@ -1,6 +1,6 @@
 //! XML stream parser for XMPP
 
-use crate::Error;
+use crate::{Error, ProtocolError};
 use bytes::{BufMut, BytesMut};
 use log::debug;
 use minidom::tree_builder::TreeBuilder;
@@ -58,13 +58,30 @@ pub enum Packet {
     StreamEnd,
 }
 
+/// Default maximum nesting depth of incoming XML, `<stream:stream>` included
+const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Smallest space reserved in the output buffer before encoding a packet
+const MIN_RESERVE: usize = 512;
+
 /// Stateful encoder/decoder for a bytestream from/to XMPP `Packet`
 pub struct XMPPCodec {
     /// Outgoing
     ns: Option<String>,
+    /// Space to reserve before encoding, following recent packet sizes
+    reserve: usize,
+    /// Reused to format `<stream:stream>`
+    scratch: String,
     /// Incoming
     driver: RawParser,
     stanza_builder: TreeBuilder,
+    max_depth: usize,
+    max_stanza_size: Option<usize>,
+    /// Bytes of the stanza being decoded so far
+    stanza_size: usize,
+    /// Bytes decoded and encoded so far
+    bytes_received: u64,
+    bytes_sent: u64,
 }
 
 impl XMPPCodec {
@@ -78,10 +95,56 @@ impl XMPPCodec {
         }
         XMPPCodec {
             ns: None,
+            reserve: MIN_RESERVE,
+            scratch: String::new(),
             driver,
             stanza_builder,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_stanza_size: None,
+            stanza_size: 0,
+            bytes_received: 0,
+            bytes_sent: 0,
         }
     }
+
+    /// Set the maximum nesting depth of incoming XML, counting
+    /// `<stream:stream>`, past which decoding fails with
+    /// [`ProtocolError::TooDeep`]
+    ///
+    /// Defaults to 128.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Set the maximum size of incoming stanzas in bytes, past which
+    /// decoding fails with [`ProtocolError::TooLarge`], closing the stream
+    /// with a `<policy-violation/>` stream error
+    ///
+    /// Unlimited by default.
+    pub fn set_max_stanza_size(&mut self, size: Option<usize>) {
+        self.max_stanza_size = size;
+    }
+
+    /// Keep incoming text longer than `size` bytes as several consecutive
+    /// text nodes, so that relaying huge payloads (e.g. vCards with inline
+    /// photos) doesn't reallocate a multi-megabyte `String` as it grows
+    ///
+    /// The parser reads text by pieces of up to 8 KiB which are never split,
+    /// so a smaller `size` gives one node per piece. `Element::text()` still
+    /// returns the whole text. Disabled by default.
+    pub fn set_text_chunk_size(&mut self, size: Option<usize>) {
+        self.stanza_builder.set_text_chunk_size(size);
+    }
+
+    /// Number of bytes decoded by this codec so far
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Number of bytes encoded by this codec so far
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
 }
 
 impl Default for XMPPCodec {
@@ -96,7 +159,10 @@ impl Decoder for XMPPCodec {
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         loop {
-            let token = match self.driver.parse(buf, false) {
+            let len = buf.len();
+            let token = self.driver.parse(buf, false);
+            self.bytes_received += (len - buf.len()) as u64;
+            let token = match token {
                 Ok(Some(token)) => token,
                 Ok(None) => break,
                 Err(rxml::Error::IO(e)) if e.kind() == std::io::ErrorKind::WouldBlock => break,
@@ -104,7 +170,20 @@ impl Decoder for XMPPCodec {
             };
 
             let had_stream_root = self.stanza_builder.depth() > 0;
+            let in_stanza = self.stanza_builder.depth() > 1;
             self.stanza_builder.process_event(token)?;
+            if self.stanza_builder.depth() > self.max_depth {
+                return Err(ProtocolError::TooDeep.into());
+            }
+            if in_stanza || self.stanza_builder.depth() > 1 {
+                self.stanza_size += len - buf.len();
+                if self
+                    .max_stanza_size
+                    .is_some_and(|max| self.stanza_size > max)
+                {
+                    return Err(ProtocolError::TooLarge.into());
+                }
+            }
             let has_stream_root = self.stanza_builder.depth() > 0;
 
             if !had_stream_root && has_stream_root {
@@ -130,6 +209,7 @@ impl Decoder for XMPPCodec {
                 self.driver.release_temporaries();
 
                 if let Some(stanza) = self.stanza_builder.unshift_child() {
+                    self.stanza_size = 0;
                     debug!("<< {}", highlight_xml(&String::from(&stanza)));
                     return Ok(Some(Packet::Stanza(stanza)));
                 }
@@ -153,11 +233,8 @@ impl Encoder<Packet> for XMPPCodec {
     type Error = Error;
 
     fn encode(&mut self, item: Packet, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let remaining = dst.capacity() - dst.len();
-        let max_stanza_size: usize = 2usize.pow(16);
-        if remaining < max_stanza_size {
-            dst.reserve(max_stanza_size - remaining);
-        }
+        dst.reserve(self.reserve);
+        let start = dst.len();
 
         fn to_io_err<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
             io::Error::new(io::ErrorKind::InvalidInput, e)
@@ -165,7 +242,8 @@ impl Encoder<Packet> for XMPPCodec {
 
         match item {
             Packet::StreamStart(start_attrs) => {
-                let mut buf = String::new();
+                let buf = &mut self.scratch;
+                buf.clear();
                 write!(buf, "<stream:stream").map_err(to_io_err)?;
                 for (name, value) in start_attrs {
                     write!(buf, " {}=\"{}\"", escape(&name), escape(&value)).map_err(to_io_err)?;
@@ -175,20 +253,20 @@ impl Encoder<Packet> for XMPPCodec {
                 }
                 write!(buf, ">").map_err(to_io_err)?;
 
-                write!(dst, "{}", buf)?;
-                let utf8 = std::str::from_utf8(dst)?;
+                dst.put_slice(buf.as_bytes());
+                let utf8 = std::str::from_utf8(&dst[start..])?;
                 debug!(">> {}", highlight_xml(utf8))
             }
             Packet::Stanza(stanza) => {
                 let _ = stanza
                     .write_to(&mut WriteBytes::new(dst))
                     .map_err(|e| to_io_err(format!("{}", e)))?;
-                let utf8 = std::str::from_utf8(dst)?;
+                let utf8 = std::str::from_utf8(&dst[start..])?;
                 debug!(">> {}", highlight_xml(utf8));
             }
             Packet::Text(text) => {
                 let _ = write_text(&text, dst).map_err(to_io_err)?;
-                let utf8 = std::str::from_utf8(dst)?;
+                let utf8 = std::str::from_utf8(&dst[start..])?;
                 debug!(">> {}", highlight_xml(utf8));
             }
             Packet::StreamEnd => {
@@ -197,6 +275,13 @@ impl Encoder<Packet> for XMPPCodec {
             }
         }
 
+        // Grow quickly to fit large packets, shrink slowly after them
+        let written = dst.len() - start;
+        self.bytes_sent += written as u64;
+        self.reserve = written
+            .max(self.reserve - self.reserve / 4)
+            .max(MIN_RESERVE);
+
         Ok(())
     }
 }
@@ -250,6 +335,7 @@ impl<'a> std::io::Write for WriteBytes<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use xmpp_parsers::stream_error::DefinedCondition as StreamDefinedCondition;
 
     #[test]
     fn test_stream_start() {
@@ -386,6 +472,124 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_max_depth() {
+        let mut c = XMPPCodec::new();
+        c.set_max_depth(3);
+        let mut b = BytesMut::with_capacity(1024);
+        b.put_slice(b"<?xml version='1.0'?><stream:stream xmlns:stream='http://etherx.jabber.org/streams' version='1.0' xmlns='jabber:client'>");
+        let r = c.decode(&mut b);
+        assert!(matches!(r, Ok(Some(Packet::StreamStart(_)))));
+
+        b.put_slice(b"<message><body>Foo</body></message>");
+        let r = c.decode(&mut b);
+        assert!(matches!(r, Ok(Some(Packet::Stanza(_)))));
+
+        b.put_slice(b"<message><body><b>Foo</b></body></message>");
+        let r = c.decode(&mut b);
+        assert!(matches!(r, Err(Error::Protocol(ProtocolError::TooDeep))));
+    }
+
+    #[test]
+    fn test_max_stanza_size() {
+        let mut c = XMPPCodec::new();
+        c.set_max_stanza_size(Some(64));
+        let mut b = BytesMut::with_capacity(1024);
+        b.put_slice(b"<?xml version='1.0'?><stream:stream xmlns:stream='http://etherx.jabber.org/streams' version='1.0' xmlns='jabber:client'>");
+        let r = c.decode(&mut b);
+        assert!(matches!(r, Ok(Some(Packet::StreamStart(_)))));
+
+        // Each stanza gets its own budget.
+        for _ in 0..2 {
+            b.put_slice(b"<message><body>Foo</body></message>");
+            let r = c.decode(&mut b);
+            assert!(matches!(r, Ok(Some(Packet::Stanza(_)))));
+        }
+
+        b.put_slice(b"<message><body>");
+        b.put_slice(&[b'a'; 64]);
+        b.put_slice(b"</body></message>");
+        let r = c.decode(&mut b);
+        match r {
+            Err(Error::Protocol(e)) => {
+                assert!(matches!(e, ProtocolError::TooLarge));
+                assert_eq!(
+                    e.stream_error_condition(),
+                    Some(StreamDefinedCondition::PolicyViolation)
+                );
+            }
+            _ => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_text_chunk_size() {
+        let mut c = XMPPCodec::new();
+        c.set_text_chunk_size(Some(2usize.pow(14)));
+        let mut b = BytesMut::with_capacity(1024);
+        b.put_slice(b"<?xml version='1.0'?><stream:stream xmlns:stream='http://etherx.jabber.org/streams' version='1.0' xmlns='jabber:client'>");
+        let r = c.decode(&mut b);
+        assert!(matches!(r, Ok(Some(Packet::StreamStart(_)))));
+
+        let text = "A".repeat(2usize.pow(16));
+        b.put_slice(format!("<message><body>{}</body></message>", text).as_bytes());
+        let Ok(Some(Packet::Stanza(message))) = c.decode(&mut b) else {
+            panic!();
+        };
+        let body = message.get_child("body", "jabber:client").unwrap();
+        assert_eq!(body.texts().count(), 4);
+        assert!(body.texts().all(|chunk| chunk.len() == 2usize.pow(14)));
+        assert_eq!(body.text(), text);
+        assert_eq!(
+            String::from(&message),
+            format!(
+                "<message xmlns='jabber:client'><body>{}</body></message>",
+                text
+            )
+        );
+    }
+
+    #[test]
+    fn test_encode_reserve() {
+        let mut c = XMPPCodec::new();
+        let mut b = BytesMut::new();
+        let stanza = Element::builder("presence", "jabber:client").build();
+        c.encode(Packet::Stanza(stanza.clone()), &mut b).unwrap();
+        assert!(b.capacity() < 2usize.pow(12));
+
+        let text = "A".repeat(2usize.pow(15));
+        let message = Element::builder("message", "jabber:client")
+            .append(text.as_ref())
+            .build();
+        c.encode(Packet::Stanza(message), &mut b).unwrap();
+        assert!(c.reserve > 2usize.pow(15));
+
+        for _ in 0..32 {
+            b.clear();
+            c.encode(Packet::Stanza(stanza.clone()), &mut b).unwrap();
+        }
+        assert_eq!(c.reserve, MIN_RESERVE);
+    }
+
+    #[test]
+    fn test_byte_counts() {
+        let mut c = XMPPCodec::new();
+        let mut b = BytesMut::new();
+        let header = "<stream:stream xmlns:stream='http://etherx.jabber.org/streams' version='1.0' xmlns='jabber:client'>";
+        b.put_slice(header.as_bytes());
+        b.put_slice(b"<presence/><mess");
+        assert!(matches!(c.decode(&mut b), Ok(Some(Packet::StreamStart(_)))));
+        assert!(matches!(c.decode(&mut b), Ok(Some(Packet::Stanza(_)))));
+        assert!(matches!(c.decode(&mut b), Ok(None)));
+        assert_eq!(c.bytes_received(), header.len() as u64 + 16);
+
+        let mut b = BytesMut::new();
+        let stanza = Element::builder("presence", "jabber:client").build();
+        c.encode(Packet::Stanza(stanza), &mut b).unwrap();
+        c.encode(Packet::Text(String::from(" ")), &mut b).unwrap();
+        assert_eq!(c.bytes_sent(), b.len() as u64);
+    }
+
     #[test]
     fn test_cut_out_stanza() {
         let mut c = XMPPCodec::new();
@@ -1,35 +1,42 @@
 //! `XMPPStream` provides encoding/decoding for XMPP
 
 use futures::sink::Send;
-use futures::{sink::SinkExt, task::Poll, Sink, Stream};
-use rand::{thread_rng, Rng};
+use futures::{sink::SinkExt, stream::StreamExt, task::Poll, Sink, Stream};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
-use xmpp_parsers::{Element, Jid};
+use xmpp_parsers::{stream_error::StreamError, Element, Jid};
 
-use crate::stream_features::StreamFeatures;
+use crate::sansio::Negotiation;
+use crate::stream_features::{FeatureNegotiator, StreamFeatures};
 use crate::stream_start;
 use crate::xmpp_codec::{Packet, XMPPCodec};
 use crate::Error;
 
-fn make_id() -> String {
-    let id: u64 = thread_rng().gen();
-    format!("{}", id)
-}
-
-pub(crate) fn add_stanza_id(mut stanza: Element, default_ns: &str) -> Element {
-    if stanza.is("iq", default_ns)
-        || stanza.is("message", default_ns)
-        || stanza.is("presence", default_ns)
-    {
-        if stanza.attr("id").is_none() {
-            stanza.set_attr("id", make_id());
+/// Run a sans-io negotiation over a stream of packets, until it's done
+pub(crate) async fn drive<T, N>(stream: &mut T, machine: &mut N) -> Result<N::Output, Error>
+where
+    T: Stream<Item = Result<Packet, Error>> + Sink<Packet, Error = Error> + Unpin,
+    N: Negotiation,
+{
+    loop {
+        while let Some(packet) = machine.poll_transmit() {
+            stream.feed(packet).await?;
+        }
+        stream.flush().await?;
+        match stream.next().await {
+            Some(Ok(packet)) => {
+                if let Some(output) = machine.handle(packet)? {
+                    return Ok(output);
+                }
+            }
+            Some(Err(e)) => return Err(e),
+            None => return Err(Error::Disconnected),
         }
     }
-
-    stanza
 }
 
 /// Wraps a binary stream (tokio's `AsyncRead + AsyncWrite`) to decode
@@ -41,7 +48,7 @@ pub struct XMPPStream<S: AsyncRead + AsyncWrite + Unpin> {
     pub jid: Jid,
     /// Codec instance
     pub stream: Framed<S, XMPPCodec>,
-    /// `<stream:features/>` for XMPP version 1.0
+    /// `<stream:features/>` for XMPP version 1.0, none otherwise
     pub stream_features: StreamFeatures,
     /// Root namespace
     ///
@@ -59,12 +66,12 @@ impl<S: AsyncRead + AsyncWrite + Unpin> XMPPStream<S> {
         stream: Framed<S, XMPPCodec>,
         ns: String,
         id: String,
-        stream_features: Element,
+        stream_features: StreamFeatures,
     ) -> Self {
         XMPPStream {
             jid,
             stream,
-            stream_features: StreamFeatures::new(stream_features),
+            stream_features,
             ns,
             id,
         }
@@ -86,6 +93,75 @@ impl<S: AsyncRead + AsyncWrite + Unpin> XMPPStream<S> {
         let stream = self.stream.into_inner();
         Self::start(stream, self.jid, self.ns).await
     }
+
+    /// Close the stream gracefully
+    ///
+    /// Sends `</stream:stream>` once every pending packet got flushed, then
+    /// waits up to `timeout` for the server to close its side of the
+    /// stream, discarding whatever it sends meanwhile, and finally shuts
+    /// down the transport (sending a TLS close_notify if applicable).
+    pub async fn shutdown(mut self, timeout: Duration) -> Result<(), Error> {
+        self.send(Packet::StreamEnd).await?;
+
+        let closing = async {
+            loop {
+                match self.next().await {
+                    Some(Ok(Packet::StreamEnd)) | None => return Ok(()),
+                    Some(Ok(_)) => (),
+                    Some(Err(e)) => return Err(e),
+                }
+            }
+        };
+        match crate::rt::timeout(timeout, closing).await {
+            Ok(result) => result?,
+            Err(_) => log::warn!("Server didn’t close the stream in time"),
+        }
+
+        self.close().await
+    }
+
+    /// Report an error caused by what the other side sent with a
+    /// `<stream:error/>`, if it has a stream error condition, then close the
+    /// stream without waiting for the other side
+    ///
+    /// This is best effort: whatever can't be written right away is dropped
+    /// along with the connection.
+    pub(crate) fn poll_fail(&mut self, cx: &mut Context, error: &Error) {
+        let Some(condition) = error.stream_error_condition() else {
+            return;
+        };
+        let mut stream = Pin::new(&mut self.stream);
+        let error = StreamError::new(condition);
+        if stream
+            .as_mut()
+            .start_send(Packet::Stanza(error.into()))
+            .is_ok()
+            && stream.as_mut().start_send(Packet::StreamEnd).is_ok()
+        {
+            let _ = stream.poll_close(cx);
+        }
+    }
+
+    /// Run every negotiator wanting one of the advertised features,
+    /// restarting the stream whenever one of them requires it
+    pub async fn negotiate_features(
+        mut self,
+        negotiators: &[Arc<dyn FeatureNegotiator>],
+    ) -> Result<Self, Error>
+    where
+        S: std::marker::Send,
+    {
+        for negotiator in negotiators {
+            if !negotiator.wants(&self.stream_features) {
+                continue;
+            }
+            let features = self.stream_features.clone();
+            if negotiator.negotiate(&features, &mut self).await? {
+                self = self.restart().await?;
+            }
+        }
+        Ok(self)
+    }
 }
 
 impl<S: AsyncRead + AsyncWrite + Unpin> XMPPStream<S> {
@@ -99,10 +175,10 @@ impl<S: AsyncRead + AsyncWrite + Unpin> XMPPStream<S> {
 impl<S: AsyncRead + AsyncWrite + Unpin> Sink<Packet> for XMPPStream<S> {
     type Error = crate::Error;
 
-    fn poll_ready(self: Pin<&mut Self>, _ctx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        // Pin::new(&mut self.stream).poll_ready(ctx)
-        //     .map_err(|e| e.into())
-        Poll::Ready(Ok(()))
+    /// Writes the buffered packets once they exceed the write buffer size,
+    /// so that fed packets don't accumulate without bound
+    fn poll_ready(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.stream).poll_ready(ctx)
     }
 
     fn start_send(mut self: Pin<&mut Self>, item: Packet) -> Result<(), Self::Error> {
@@ -134,3 +210,121 @@ impl<S: AsyncRead + AsyncWrite + Unpin> Stream for XMPPStream<S> {
             .map(|result| result.map(|result| result.map_err(|e| e.into())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    async fn fake_server(mut server: DuplexStream, answer_close: bool) -> Vec<u8> {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 1024];
+        let n = server.read(&mut buf).await.unwrap();
+        received.extend_from_slice(&buf[..n]);
+        server
+            .write_all(b"<stream:stream xmlns='jabber:component:accept' xmlns:stream='http://etherx.jabber.org/streams' id='coucou'>")
+            .await
+            .unwrap();
+        loop {
+            let n = server.read(&mut buf).await.unwrap();
+            if n == 0 {
+                return received;
+            }
+            received.extend_from_slice(&buf[..n]);
+            if answer_close && received.ends_with(b"</stream:stream>\n") {
+                server.write_all(b"</stream:stream>").await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(fake_server(server, true));
+        let jid: Jid = "component.example.org".parse().unwrap();
+        let stream = XMPPStream::start(client, jid, "jabber:component:accept".to_owned())
+            .await
+            .unwrap();
+        stream.shutdown(Duration::from_secs(10)).await.unwrap();
+        let received = server.await.unwrap();
+        assert!(received.ends_with(b"</stream:stream>\n"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_timeout() {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(fake_server(server, false));
+        let jid: Jid = "component.example.org".parse().unwrap();
+        let stream = XMPPStream::start(client, jid, "jabber:component:accept".to_owned())
+            .await
+            .unwrap();
+        stream.shutdown(Duration::from_millis(10)).await.unwrap();
+        let received = server.await.unwrap();
+        assert!(received.ends_with(b"</stream:stream>\n"));
+    }
+
+    async fn read_written(
+        server: &mut DuplexStream,
+        buf: &mut [u8],
+    ) -> Result<std::io::Result<usize>, tokio::time::error::Elapsed> {
+        tokio::time::timeout(Duration::from_millis(10), server.read(buf)).await
+    }
+
+    #[tokio::test]
+    async fn test_write_threshold() {
+        let (client, mut server) = tokio::io::duplex(65536);
+        let jid: Jid = "component.example.org".parse().unwrap();
+        let mut buf = vec![0u8; 65536];
+        let (stream, _) = tokio::join!(
+            XMPPStream::start(client, jid, "jabber:component:accept".to_owned()),
+            async {
+                assert!(server.read(&mut buf).await.unwrap() > 0);
+                server
+                    .write_all(b"<stream:stream xmlns='jabber:component:accept' xmlns:stream='http://etherx.jabber.org/streams' id='coucou'>")
+                    .await
+                    .unwrap();
+            }
+        );
+        let mut stream = stream.unwrap();
+
+        let small = Element::builder("presence", "jabber:component:accept").build();
+        stream.feed(Packet::Stanza(small.clone())).await.unwrap();
+        assert!(read_written(&mut server, &mut buf).await.is_err());
+
+        // Feeding past the write buffer size writes what was buffered.
+        let large = Element::builder("message", "jabber:component:accept")
+            .append("x".repeat(10000))
+            .build();
+        stream.feed(Packet::Stanza(large)).await.unwrap();
+        stream.feed(Packet::Stanza(small)).await.unwrap();
+        let n = read_written(&mut server, &mut buf).await.unwrap().unwrap();
+        assert!(buf[..n].starts_with(b"<presence"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_stream_start() {
+        let (client, mut server) = tokio::io::duplex(4096);
+        let jid: Jid = "component.example.org".parse().unwrap();
+        let mut buf = vec![0u8; 4096];
+        let (stream, received) = tokio::join!(
+            XMPPStream::start(client, jid, "jabber:component:accept".to_owned()),
+            async {
+                assert!(server.read(&mut buf).await.unwrap() > 0);
+                server
+                    .write_all(b"<stream:stream xmlns='jabber:component:accept' xmlns:stream='http://etherx.jabber.org/streams'>")
+                    .await
+                    .unwrap();
+                let mut received = Vec::new();
+                server.read_to_end(&mut received).await.unwrap();
+                received
+            }
+        );
+        assert!(matches!(
+            stream,
+            Err(Error::Protocol(crate::ProtocolError::NoStreamId))
+        ));
+        let received = String::from_utf8(received).unwrap();
+        assert!(received.contains("<bad-format"));
+        assert!(received.ends_with("</stream:stream>\n"));
+    }
+}
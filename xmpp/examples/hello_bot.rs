@@ -51,7 +51,7 @@ async fn main() -> Result<(), Option<()>> {
                 Event::ContactChanged(contact) => {
                     println!("Contact {} changed.", contact.jid);
                 }
-                Event::ChatMessage(_id, jid, body, time_info) => {
+                Event::ChatMessage(_id, jid, body, time_info, _encryption) => {
                     println!("Message from {} at {}: {}", jid, time_info.received, body.0);
                 }
                 Event::JoinRoom(jid, conference) => {
@@ -76,12 +76,13 @@ async fn main() -> Result<(), Option<()>> {
                     println!("Joined room {}.", jid);
                     client
                         .send_message(Jid::from(jid), MessageType::Groupchat, "en", "Hello world!")
-                        .await;
+                        .await
+                        .unwrap();
                 }
                 Event::RoomLeft(jid) => {
                     println!("Left room {}.", jid);
                 }
-                Event::RoomMessage(_id, jid, nick, body, time_info) => {
+                Event::RoomMessage(_id, jid, nick, body, time_info, _encryption) => {
                     println!(
                         "Message in room {} from {} at {}: {}",
                         jid, nick, time_info.received, body.0
@@ -4,30 +4,107 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error as StdError;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
-use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::connect::{ServerConnector, TlsInfo};
 pub use tokio_xmpp::parsers;
-use tokio_xmpp::parsers::{disco::DiscoInfoResult, message::MessageType};
+use tokio_xmpp::parsers::{
+    bob::{ContentId, Data},
+    call_invites::{Accept, Invite},
+    chatstates::ChatState,
+    data_forms::DataForm,
+    date::DateTime,
+    disco::{DiscoInfoResult, Identity, Item as DiscoItem},
+    jingle::{Content, Reason, SessionId},
+    message::MessageType,
+    ns,
+    reporting::{Reason as ReportReason, Report},
+    roster::{Item as RosterItem, Roster},
+    rosterx::Item as SuggestedItem,
+    server_info::ServerInfo,
+};
+use tokio_xmpp::ClientStats;
 pub use tokio_xmpp::{AsyncClient as TokioXmppClient, BareJid, Element, FullJid, Jid};
 
-use crate::{event_loop, message, muc, upload, Error, Event, RoomNick};
+use crate::blocking::BlockingCommands;
+use crate::bob::BitsOfBinary;
+use crate::call::{CallState, Calls};
+use crate::disco::browse::ItemQueries;
+use crate::disco::items::DiscoItems;
+use crate::encryption::EncryptionError;
+use crate::file_transfer::{self, FileTransfers};
+use crate::http_auth::HttpRequests;
+use crate::idle::IdleState;
+use crate::mam::ArchiveCatchUp;
+use crate::message::dedup::SeenMessages;
+use crate::message::queue::OfflineQueue;
+use crate::message::send::MessageHint;
+use crate::muc::activity::ActivitySubscriptions;
+use crate::muc::history::RoomHistory;
+use crate::muc::register::Registrations;
+use crate::ping::{self, Pong};
+use crate::private::{PrivateData, PrivateStorage};
+use crate::upload::manager::{UploadId, UploadManager};
+use crate::{
+    blocking, bob, call, call_invite, disco, event_loop, http_auth, idle, message, muc, private,
+    pubsub, roster, upload, EncryptionProtocol, Error, Event, RoomNick,
+};
 
 pub struct Agent<C: ServerConnector> {
     pub(crate) client: TokioXmppClient<C>,
     pub(crate) default_nick: Arc<RwLock<String>>,
     pub(crate) lang: Arc<Vec<String>>,
     pub(crate) disco: DiscoInfoResult,
+    pub(crate) disco_items: DiscoItems,
+    pub(crate) item_queries: ItemQueries,
     pub(crate) node: String,
-    pub(crate) uploads: Vec<(String, Jid, PathBuf)>,
+    pub(crate) uploads: UploadManager,
     pub(crate) awaiting_disco_bookmarks_type: bool,
+    pub(crate) encryption: Vec<Box<dyn EncryptionProtocol>>,
+    pub(crate) archive: ArchiveCatchUp,
+    pub(crate) seen_messages: SeenMessages,
+    pub(crate) idle: IdleState,
+    pub(crate) muc_registrations: Registrations,
+    pub(crate) room_history: RoomHistory,
+    pub(crate) room_passwords: HashMap<BareJid, String>,
+    pub(crate) joined_rooms: HashMap<BareJid, RoomNick>,
+    /// Rooms joined with [`Agent::join_room_quietly`], whose occupants are ignored.
+    pub(crate) quiet_rooms: HashSet<BareJid>,
+    pub(crate) room_activity: ActivitySubscriptions,
+    pub(crate) roster: Roster,
+    /// Events of archived messages replayed while handling a stanza, with the stanza each message
+    /// came in.
+    pub(crate) replayed_events: Vec<(Event, Element)>,
+    pub(crate) calls: Calls,
+    pub(crate) file_transfers: FileTransfers,
+    pub(crate) offline_queue: OfflineQueue,
+    pub(crate) private_storage: PrivateStorage,
+    pub(crate) blocking: BlockingCommands,
+    pub(crate) bits_of_binary: BitsOfBinary,
+    pub(crate) http_requests: HttpRequests,
+    pub(crate) server_info: Option<ServerInfo>,
+    pub(crate) server_info_query: Option<String>,
+    #[cfg(feature = "avatars")]
+    pub(crate) vcard_avatars: crate::pubsub::avatar::VCardAvatars,
+    #[cfg(feature = "avatars")]
+    pub(crate) avatar_fetches: crate::pubsub::avatar::AvatarFetches,
 }
 
 impl<C: ServerConnector> Agent<C> {
+    /// Closes the stream, once the offline queue got saved, see [`Agent::flush_queue_storage`].
     pub async fn disconnect(&mut self) -> Result<(), Error> {
+        self.flush_queue_storage().await;
         self.client.send_end().await
     }
 
+    /// Waits for the offline queue to be written to its storage, if any, as it gets saved in the
+    /// background whenever it changes. Call it before exiting, to not lose the last changes.
+    pub async fn flush_queue_storage(&mut self) {
+        self.offline_queue.saved().await;
+    }
+
     pub async fn join_room(
         &mut self,
         room: BareJid,
@@ -39,6 +116,28 @@ impl<C: ServerConnector> Agent<C> {
         muc::room::join_room(self, room, nick, password, lang, status).await
     }
 
+    /// Joins a room without its history, ignoring the presences of its occupants. See
+    /// [muc::room::join_room_quietly].
+    pub async fn join_room_quietly(
+        &mut self,
+        room: BareJid,
+        nick: Option<String>,
+        password: Option<String>,
+    ) {
+        muc::room::join_room_quietly(self, room, nick, password).await
+    }
+
+    /// Invites someone to a room, with an optional reason. The room forwards the invitation,
+    /// along with the password this room was joined with, if any.
+    pub async fn invite(&mut self, room: BareJid, invitee: BareJid, reason: Option<String>) {
+        muc::room::invite(self, room, invitee, reason).await
+    }
+
+    /// Changes the subject of a room, an empty one clearing it. See [muc::room::set_subject].
+    pub async fn set_room_subject(&mut self, room: BareJid, subject: String) {
+        muc::room::set_subject(self, room, subject).await
+    }
+
     /// Request to leave a chatroom.
     ///
     /// If successful, an [Event::RoomLeft] event will be produced. This method does not remove the room
@@ -60,26 +159,261 @@ impl<C: ServerConnector> Agent<C> {
         muc::room::leave_room(self, room_jid, nickname, lang, status).await
     }
 
+    /// Sends a message, encrypted with the first registered [`EncryptionProtocol`] which
+    /// accepts it. If one fails, the message isn’t sent and its error is returned.
     pub async fn send_message(
         &mut self,
         recipient: Jid,
         type_: MessageType,
         lang: &str,
         text: &str,
-    ) {
-        message::send::send_message(self, recipient, type_, lang, text).await
+    ) -> Result<(), EncryptionError> {
+        message::send::send_message(self, recipient, type_, lang, text, &[]).await
     }
 
+    /// Sends a message with Message Processing Hints, e.g. to keep it out of the archives. See
+    /// [`Agent::send_message`] about encryption.
+    pub async fn send_message_with_hints(
+        &mut self,
+        recipient: Jid,
+        type_: MessageType,
+        lang: &str,
+        text: &str,
+        hints: &[MessageHint],
+    ) -> Result<(), EncryptionError> {
+        message::send::send_message(self, recipient, type_, lang, text, hints).await
+    }
+
+    /// Tells the recipient whether we are composing, paused, etc. These notifications are sent
+    /// with a no-store hint, so they don’t end up in archives or offline storage.
+    pub async fn send_chat_state(&mut self, recipient: Jid, type_: MessageType, state: ChatState) {
+        message::send::send_chat_state(self, recipient, type_, state).await
+    }
+
+    /// Sends a private message to an occupant of a room, failing like [`Agent::send_message`].
     pub async fn send_room_private_message(
         &mut self,
         room: BareJid,
         recipient: RoomNick,
         lang: &str,
         text: &str,
-    ) {
+    ) -> Result<(), EncryptionError> {
         muc::private_message::send_room_private_message(self, room, recipient, lang, text).await
     }
 
+    /// Fetches up to `limit` messages of a room from its archive, before the message with this
+    /// stanza-id, or its latest ones if None. They are reported oldest first as
+    /// [`Event::RoomMessage`] with [`StanzaTimeInfo::historical`](crate::delay::StanzaTimeInfo)
+    /// set, followed by [`Event::RoomHistoryFetched`] with the id to fetch the previous page.
+    pub async fn fetch_room_history(
+        &mut self,
+        room: BareJid,
+        before: Option<String>,
+        limit: usize,
+    ) {
+        muc::history::fetch_history(self, room, before, limit).await
+    }
+
+    /// Subscribes to the activity of the rooms of this MUC service, which has to support Room
+    /// Activity Indicators, instead of joining them: the rooms with new messages are reported with
+    /// [`Event::RoomActivity`]. The subscription is renewed on every new session.
+    pub async fn subscribe_room_activity(&mut self, service: BareJid) {
+        muc::activity::subscribe(self, service).await
+    }
+
+    /// Stops the activity notifications of this MUC service.
+    pub async fn unsubscribe_room_activity(&mut self, service: BareJid) {
+        muc::activity::unsubscribe(self, service).await
+    }
+
+    /// Fetches up to `limit` items of an entity under this node, e.g. the rooms of a MUC service,
+    /// after the item with this UID, or from the start if None. They are reported with
+    /// [`Event::DiscoItemsFetched`], along with the UID to fetch the next page.
+    pub async fn fetch_disco_items(
+        &mut self,
+        jid: Jid,
+        node: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) {
+        disco::browse::fetch_items(self, jid, node, after, limit).await
+    }
+
+    /// Retrieves the element of this name and namespace from the private storage of the account,
+    /// as a `T`, empty if nothing is stored yet. The returned future resolves once the server
+    /// answered, while [`Agent::wait_for_events`] keeps being called.
+    pub async fn private_get<T>(&mut self, name: &str, namespace: &str) -> PrivateData<T>
+    where
+        T: TryFrom<Element>,
+        T::Error: StdError + Send + Sync + 'static,
+    {
+        private::get(self, name, namespace).await
+    }
+
+    /// Stores this element in the private storage of the account, replacing the one of the same
+    /// name and namespace, confirmed with [`Event::PrivateDataStored`].
+    pub async fn private_set<P: Into<Element>>(&mut self, payload: P) {
+        private::set(self, payload.into()).await
+    }
+
+    /// Blocks all communications with this JID, confirmed with [`Event::Blocked`].
+    pub async fn block(&mut self, jid: Jid) {
+        blocking::block(self, jid, None).await
+    }
+
+    /// Blocks this JID like [`Agent::block`], and reports it to the server as spam or abuse, for
+    /// servers supporting [XEP-0377](https://xmpp.org/extensions/xep-0377.html).
+    pub async fn report_and_block(&mut self, jid: Jid, reason: ReportReason) {
+        blocking::block(self, jid, Some(Report::new(reason))).await
+    }
+
+    /// Unblocks this JID, confirmed with [`Event::Unblocked`].
+    pub async fn unblock(&mut self, jid: Jid) {
+        blocking::unblock(self, jid).await
+    }
+
+    /// Keeps this data, e.g. a small image created with [`Data::new`], to answer the requests for
+    /// it, returning the cid to reference it with. Small data can also be included in the message
+    /// referencing it, saving the request.
+    pub fn add_bits_of_binary(&mut self, data: Data) -> ContentId {
+        bob::add(self, data)
+    }
+
+    /// The data of this cid, if we added it, or received it and may still keep it.
+    pub fn bits_of_binary(&self, cid: &ContentId) -> Option<&Data> {
+        bob::get(self, cid)
+    }
+
+    /// Asks the entity which referenced this cid for its data, reported with
+    /// [`Event::BitsOfBinaryFetched`]. Check [`Agent::bits_of_binary`] first, as it may be cached.
+    pub async fn request_bits_of_binary(&mut self, from: Jid, cid: ContentId) {
+        bob::request(self, from, cid).await
+    }
+
+    /// Confirms that the user made the HTTP request of this transaction id, as asked by this
+    /// entity with [`Event::HttpRequestConfirmation`].
+    pub async fn accept_http_request(&mut self, from: Jid, id: &str) {
+        http_auth::answer(self, from, id, true).await
+    }
+
+    /// Denies having made the HTTP request of this transaction id, as asked by this entity with
+    /// [`Event::HttpRequestConfirmation`].
+    pub async fn deny_http_request(&mut self, from: Jid, id: &str) {
+        http_auth::answer(self, from, id, false).await
+    }
+
+    /// Asks a room for the form to register a nickname with, answered with
+    /// [`Event::RoomRegistrationForm`], or [`Event::RoomRegistered`] if already registered.
+    pub async fn request_room_registration(&mut self, room: BareJid) {
+        muc::register::request_registration_form(self, room).await
+    }
+
+    /// Registers a nickname with a room, submitting the form received with
+    /// [`Event::RoomRegistrationForm`] once filled. Answered with [`Event::RoomRegistered`], or
+    /// [`Event::RoomRegistrationFailed`], e.g. with a conflict if the nickname is taken.
+    pub async fn register_nick(&mut self, room: BareJid, form: DataForm) {
+        muc::register::register_nick(self, room, form).await
+    }
+
+    /// Tells the server the user stopped interacting at this time, so it can hold back
+    /// unimportant traffic, and sends the away presence if one is set with
+    /// [`ClientBuilder::set_auto_away`](crate::ClientBuilder::set_auto_away).
+    pub async fn report_inactivity(&mut self, since: DateTime) {
+        idle::report_inactivity(self, since).await
+    }
+
+    /// Tells the server the user is back, and restores the usual presence.
+    pub async fn report_activity(&mut self) {
+        idle::report_activity(self).await
+    }
+
+    /// Advertises support for this feature in disco#info, e.g. to receive the PEP notifications
+    /// of a node with its `+notify` variant. Our presence gets rebroadcast with the new caps hash.
+    pub async fn add_feature(&mut self, var: &str) {
+        if disco::add_feature(&mut self.disco, var) {
+            disco::advertise_changes(self).await;
+        }
+    }
+
+    /// Stops advertising this feature, rebroadcasting our presence like [`Agent::add_feature`].
+    pub async fn remove_feature(&mut self, var: &str) {
+        if disco::remove_feature(&mut self.disco, var) {
+            disco::advertise_changes(self).await;
+        }
+    }
+
+    /// Asks for the notifications of this PEP node, e.g. `urn:xmpp:mood:0`, from our contacts
+    /// and our own account, by advertising its `+notify` feature like [`Agent::add_feature`].
+    /// They are reported with [`Event::PepItemsPublished`] and the related events.
+    pub async fn subscribe_pep(&mut self, node: &str) {
+        self.add_feature(&pubsub::notify_feature(node)).await
+    }
+
+    /// Stops asking for the notifications of this PEP node.
+    pub async fn unsubscribe_pep(&mut self, node: &str) {
+        self.remove_feature(&pubsub::notify_feature(node)).await
+    }
+
+    /// Adds an identity to our disco#info, rebroadcasting our presence like
+    /// [`Agent::add_feature`].
+    pub async fn add_identity(&mut self, identity: Identity) {
+        if disco::add_identity(&mut self.disco, identity) {
+            disco::advertise_changes(self).await;
+        }
+    }
+
+    /// Lists this item in our disco#items, at the root or under `node`, and starts advertising
+    /// disco#items like [`Agent::add_feature`] if needed.
+    pub async fn add_disco_item(&mut self, node: Option<&str>, item: DiscoItem) {
+        if self.disco_items.add(node, item) && disco::add_feature(&mut self.disco, ns::DISCO_ITEMS)
+        {
+            disco::advertise_changes(self).await;
+        }
+    }
+
+    /// Stops listing the item pointing to `jid` and `item_node` under `node`, returning whether
+    /// it was listed. Once no item is left, disco#items stops being advertised like
+    /// [`Agent::remove_feature`].
+    pub async fn remove_disco_item(
+        &mut self,
+        node: Option<&str>,
+        jid: &Jid,
+        item_node: Option<&str>,
+    ) -> bool {
+        if !self.disco_items.remove(node, jid, item_node) {
+            return false;
+        }
+        if self.disco_items.is_empty() && disco::remove_feature(&mut self.disco, ns::DISCO_ITEMS) {
+            disco::advertise_changes(self).await;
+        }
+        true
+    }
+
+    /// Adds this extension form to our disco#info, e.g. the software information of XEP-0232,
+    /// replacing the one with the same FORM_TYPE, and rebroadcasts our presence like
+    /// [`Agent::add_feature`].
+    pub async fn add_disco_extension(&mut self, form: DataForm) {
+        if disco::add_extension(&mut self.disco, form) {
+            disco::advertise_changes(self).await;
+        }
+    }
+
+    /// Removes the extension form with this FORM_TYPE from our disco#info, rebroadcasting our
+    /// presence like [`Agent::add_feature`].
+    pub async fn remove_disco_extension(&mut self, form_type: &str) {
+        if disco::remove_extension(&mut self.disco, form_type) {
+            disco::advertise_changes(self).await;
+        }
+    }
+
+    /// Removes the identities of this category and type from our disco#info, rebroadcasting our
+    /// presence like [`Agent::add_feature`].
+    pub async fn remove_identity(&mut self, category: &str, type_: &str) {
+        if disco::remove_identity(&mut self.disco, category, type_) {
+            disco::advertise_changes(self).await;
+        }
+    }
+
     /// Wait for new events.
     ///
     /// # Returns
@@ -90,14 +424,199 @@ impl<C: ServerConnector> Agent<C> {
         event_loop::wait_for_events(self).await
     }
 
-    pub async fn upload_file_with(&mut self, service: &str, path: &Path) {
+    /// Wait for new events, like [Agent::wait_for_events], along with the stanza each one comes
+    /// from, as received, if any.
+    ///
+    /// Events only report what the Agent understands of a stanza, this gives access to the rest,
+    /// e.g. payloads of custom extensions on a message. Encrypted messages are returned as they
+    /// were before decryption, and messages fetched from an archive come with the stanza which
+    /// carried them.
+    pub async fn wait_for_events_with_stanzas(&mut self) -> Option<Vec<(Event, Option<Element>)>> {
+        event_loop::wait_for_events_with_stanzas(self).await
+    }
+
+    /// Uploads a file to the given HTTP File Upload service.
+    ///
+    /// The upload gets queued if too many are already in progress, progress is reported with
+    /// [Event::HttpUploadProgress] and completion with
+    /// [Event::HttpUploadedFile] or [Event::HttpUploadFailed].
+    pub async fn upload_file_with(&mut self, service: &str, path: &Path) -> UploadId {
         upload::send::upload_file_with(self, service, path).await
     }
 
+    /// Cancels an upload, which then produces an [Event::HttpUploadCancelled] event.
+    ///
+    /// Returns false if this upload is unknown or already over.
+    pub fn cancel_upload(&mut self, id: &str) -> bool {
+        self.uploads.cancel(id)
+    }
+
+    /// Publishes this image as the avatar of the account. Any format the image crate decodes is
+    /// accepted: the image is cropped to a square and scaled to the sizes recommended by
+    /// [XEP-0084](https://xmpp.org/extensions/xep-0084.html#proto-info), as PNG.
+    #[cfg(feature = "image")]
+    pub async fn publish_avatar(&mut self, image: &[u8]) -> Result<(), image::ImageError> {
+        crate::pubsub::avatar::publish_avatar(self, image).await
+    }
+
     /// Get the bound jid of the client.
     ///
     /// If the client is not connected, this will be None.
     pub fn bound_jid(&self) -> Option<&Jid> {
         self.client.bound_jid()
     }
+
+    /// Returns security information about the connection to the server, such as its certificate
+    /// chain, e.g. to display it or to pin the certificate on first use.
+    ///
+    /// If the client is not connected, or not over TLS, this will be None.
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        self.client.tls_info()
+    }
+
+    /// Returns statistics about the connection to the server, such as its uptime, the traffic
+    /// so far and the round-trip time of the last ping, see [`Agent::ping`].
+    pub fn stats(&self) -> ClientStats {
+        self.client.stats()
+    }
+
+    /// Pings our server, returning a future resolving to the round-trip time once it answered,
+    /// while [`Agent::wait_for_events`] keeps being called. The time is also reported in
+    /// [`ClientStats::last_ping_rtt`]. Fails with [Error::InvalidState] while offline.
+    pub async fn ping(&mut self) -> Pong {
+        ping::ping(self).await
+    }
+
+    /// Returns the contact addresses of our server, e.g. to offer reporting abuse or contacting
+    /// its admins, once received after connecting if it advertises any. They are forgotten on
+    /// every new session until the server sends them again.
+    pub fn server_contact_addresses(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    /// Returns every contact of the account, with their name, groups, subscription state and
+    /// pending subscription request, as last received from the server.
+    pub fn contacts(&self) -> impl Iterator<Item = &RosterItem> {
+        self.roster.items.iter()
+    }
+
+    /// Returns the contact with this JID, if it is in the contact list.
+    pub fn contact(&self, jid: &BareJid) -> Option<&RosterItem> {
+        roster::contact(&self.roster, jid)
+    }
+
+    /// Returns the contacts in this group.
+    pub fn contacts_in_group<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a RosterItem> {
+        roster::contacts_in_group(&self.roster, group)
+    }
+
+    /// Returns the names of the groups of the contact list, sorted.
+    pub fn contact_groups(&self) -> BTreeSet<&str> {
+        roster::groups(&self.roster)
+    }
+
+    /// Suggests contacts to add, modify or delete to another entity, with Roster Item Exchange.
+    pub async fn suggest_contacts(&mut self, recipient: Jid, items: Vec<SuggestedItem>) {
+        roster::suggest_contacts(self, recipient, items).await
+    }
+
+    /// Calls this resource, offering these contents, whose descriptions and transports the
+    /// application negotiates. Requires [ClientFeature::Calls](crate::ClientFeature::Calls).
+    /// Fails with [Error::InvalidState] while offline.
+    pub async fn call(
+        &mut self,
+        peer: FullJid,
+        contents: Vec<Content>,
+    ) -> Result<SessionId, Error> {
+        call::call(self, peer, contents).await
+    }
+
+    /// Accepts a call reported by [Event::IncomingCall], answering with these contents. Fails
+    /// with [Error::InvalidState] while offline, or if the call isn’t waiting for an answer.
+    pub async fn accept_call(
+        &mut self,
+        sid: &SessionId,
+        contents: Vec<Content>,
+    ) -> Result<(), Error> {
+        call::accept(self, sid, contents).await
+    }
+
+    /// Declines a call reported by [Event::IncomingCall]. Fails with [Error::InvalidState] if
+    /// the call is unknown or already ended.
+    pub async fn reject_call(&mut self, sid: &SessionId) -> Result<(), Error> {
+        call::terminate(self, sid, Some(Reason::Decline)).await
+    }
+
+    /// Hangs up, or cancels a call that wasn’t answered yet. Fails with [Error::InvalidState] if
+    /// the call is unknown or already ended.
+    pub async fn terminate_call(&mut self, sid: &SessionId) -> Result<(), Error> {
+        call::terminate(self, sid, None).await
+    }
+
+    /// Puts an active call on hold, or resumes it. Fails with [Error::InvalidState] if the call
+    /// is unknown, ended, or already in that state.
+    pub async fn hold_call(&mut self, sid: &SessionId, hold: bool) -> Result<(), Error> {
+        call::hold(self, sid, hold).await
+    }
+
+    /// Sends more transport information for a call, such as trickled ICE candidates. Fails with
+    /// [Error::InvalidState] if the call is unknown or already ended.
+    pub async fn send_call_transport_info(
+        &mut self,
+        sid: &SessionId,
+        contents: Vec<Content>,
+    ) -> Result<(), Error> {
+        call::send_transport_info(self, sid, contents).await
+    }
+
+    /// Returns the state of this call, or None once it ended.
+    pub fn call_state(&self, sid: &SessionId) -> Option<CallState> {
+        call::state(self, sid)
+    }
+
+    /// Invites a contact, or a room with a groupchat message, to a call, returning the id of the
+    /// invite which the answers refer to.
+    pub async fn invite_to_call(&mut self, to: Jid, type_: MessageType, invite: Invite) -> String {
+        call_invite::invite(self, to, type_, invite).await
+    }
+
+    /// Cancels an invite we sent, e.g. because nobody joined.
+    pub async fn retract_call_invite(&mut self, to: Jid, type_: MessageType, id: String) {
+        call_invite::retract(self, to, type_, id).await
+    }
+
+    /// Offers this file to this resource with Stream Initiation File Transfer, for clients without
+    /// Jingle File Transfer, returning the session id of the transfer. Fails if the file can’t be
+    /// read, or with [Error::InvalidState] while offline. See [file_transfer].
+    pub async fn send_file(&mut self, to: FullJid, path: &Path) -> Result<String, Error> {
+        file_transfer::send_file(self, to, path).await
+    }
+
+    /// Accepts a file offered with [Event::FileOffered], saving it at this path. Fails if the file
+    /// can’t be created, or with [Error::InvalidState] if no offer with this session id waits
+    /// for an answer.
+    pub async fn accept_file(&mut self, sid: &str, path: PathBuf) -> Result<(), Error> {
+        file_transfer::accept(self, sid, path).await
+    }
+
+    /// Declines a file offered with [Event::FileOffered]. Fails with [Error::InvalidState] if no
+    /// offer with this session id waits for an answer.
+    pub async fn reject_file(&mut self, sid: &str) -> Result<(), Error> {
+        file_transfer::reject(self, sid).await
+    }
+
+    /// Accepts an invite reported by [Event::CallInvite], with the way we joined the call.
+    pub async fn accept_call_invite(&mut self, to: Jid, type_: MessageType, accept: Accept) {
+        call_invite::accept(self, to, type_, accept).await
+    }
+
+    /// Declines an invite reported by [Event::CallInvite].
+    pub async fn reject_call_invite(&mut self, to: Jid, type_: MessageType, id: String) {
+        call_invite::reject(self, to, type_, id).await
+    }
+
+    /// Tells the others that we left the call of this invite.
+    pub async fn leave_call(&mut self, to: Jid, type_: MessageType, id: String) {
+        call_invite::leave(self, to, type_, id).await
+    }
 }
@@ -0,0 +1,120 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Blocking other entities with the Blocking Command
+//! ([XEP-0191](https://xmpp.org/extensions/xep-0191.html)), optionally reporting them to the
+//! server as spam or abuse ([XEP-0377](https://xmpp.org/extensions/xep-0377.html)).
+
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        blocking::{Block, BlockItem, Unblock},
+        iq::Iq,
+        reporting::Report,
+        stanza_error::StanzaError,
+    },
+    BareJid, Jid,
+};
+
+use crate::{iq::pending::PendingIqs, Agent, Event};
+
+enum Command {
+    Block,
+    Unblock,
+}
+
+/// Blocking commands waiting for an answer from our account.
+#[derive(Default)]
+pub(crate) struct BlockingCommands {
+    pending: PendingIqs<(Jid, Command)>,
+}
+
+impl BlockingCommands {
+    fn insert(&mut self, id: String, own_jid: BareJid, jid: Jid, command: Command) {
+        self.pending.insert(id, own_jid.into(), (jid, command));
+    }
+
+    /// Returns the JID and command of a pending request, if this answer comes from our account.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<(Jid, Command)> {
+        self.pending.take(from, id)
+    }
+}
+
+/// Sends a command about this JID to our account, waiting for its answer.
+async fn send<C: ServerConnector>(agent: &mut Agent<C>, jid: Jid, command: Command, iq: Iq) {
+    let Some(own_jid) = agent.client.bound_jid().map(Jid::to_bare) else {
+        return;
+    };
+    agent.blocking.insert(iq.id.clone(), own_jid, jid, command);
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+/// Blocks this JID, reporting it to the server if a report is given.
+pub(crate) async fn block<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    jid: Jid,
+    report: Option<Report>,
+) {
+    let item = BlockItem {
+        jid: jid.clone(),
+        report,
+    };
+    let block = Block { items: vec![item] };
+    let iq = Iq::from_set(agent.client.make_id(), block);
+    send(agent, jid, Command::Block, iq).await;
+}
+
+/// Unblocks this JID.
+pub(crate) async fn unblock<C: ServerConnector>(agent: &mut Agent<C>, jid: Jid) {
+    let unblock = Unblock {
+        items: vec![jid.clone()],
+    };
+    let iq = Iq::from_set(agent.client.make_id(), unblock);
+    send(agent, jid, Command::Unblock, iq).await;
+}
+
+pub(crate) fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+) -> Option<Event> {
+    let (jid, command) = agent.blocking.take(from, id)?;
+    Some(match command {
+        Command::Block => Event::Blocked(jid),
+        Command::Unblock => Event::Unblocked(jid),
+    })
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let (jid, _) = agent.blocking.take(from, id)?;
+    Some(Event::BlockingFailed(jid, error.take()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending() {
+        let own_jid = BareJid::new("juliet@capulet.lit").unwrap();
+        let romeo = Jid::new("romeo@montague.lit").unwrap();
+        let mut commands = BlockingCommands::default();
+        let id = String::from("block");
+        commands.insert(id.clone(), own_jid.clone(), romeo.clone(), Command::Block);
+
+        assert!(commands.take(&romeo, &id).is_none());
+        match commands.take(&own_jid.clone().into(), &id) {
+            Some((jid, Command::Block)) => assert_eq!(jid, romeo),
+            _ => panic!(),
+        }
+        assert!(commands.take(&own_jid.into(), &id).is_none());
+    }
+}
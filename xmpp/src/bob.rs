@@ -0,0 +1,237 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Small pieces of data referenced by their cid, e.g. captchas or inline images, with Bits of
+//! Binary ([XEP-0231](https://xmpp.org/extensions/xep-0231.html)).
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::rt::Instant;
+use tokio_xmpp::{
+    parsers::{
+        bob::{ContentId, Data},
+        iq::Iq,
+        stanza_error::StanzaError,
+    },
+    Element, Jid,
+};
+
+use crate::{iq::pending::PendingIqs, Agent, Event};
+
+/// How many pieces of received data to keep, the oldest ones getting dropped first.
+const MAX_RECEIVED: usize = 256;
+
+/// Data we sent, which we answer requests for, along with the data we received, kept for as
+/// long as its sender allowed.
+#[derive(Default)]
+pub(crate) struct BitsOfBinary {
+    own: HashMap<ContentId, Data>,
+    received: HashMap<ContentId, (Data, Option<Instant>)>,
+    /// The cids of the received data, oldest first.
+    order: VecDeque<ContentId>,
+    pending: PendingIqs<ContentId>,
+}
+
+impl BitsOfBinary {
+    /// Returns the cid of a pending request, if this answer comes from the entity asked.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<ContentId> {
+        self.pending.take(from, id)
+    }
+
+    fn add(&mut self, data: Data) -> ContentId {
+        let cid = data.cid.clone();
+        self.own.insert(cid.clone(), data);
+        cid
+    }
+
+    /// Keeps received data, unless its max-age forbids caching it, or its cid isn’t its hash, as
+    /// anyone could then make it replace the data of any cid.
+    fn store(&mut self, data: Data, now: Instant) {
+        if !data.matches_cid() {
+            warn!("Ignoring data not matching its cid {}", data.cid);
+            return;
+        }
+        let expiry = match data.max_age {
+            Some(0) => return,
+            Some(max_age) => Some(now + Duration::from_secs(max_age as u64)),
+            None => None,
+        };
+        self.received
+            .retain(|_, (_, expiry)| expiry.is_none_or(|expiry| expiry > now));
+        let received = &self.received;
+        self.order.retain(|cid| received.contains_key(cid));
+        let cid = data.cid.clone();
+        if self.received.insert(cid.clone(), (data, expiry)).is_none() {
+            self.order.push_back(cid);
+        }
+        while self.order.len() > MAX_RECEIVED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.received.remove(&oldest);
+            }
+        }
+    }
+
+    fn get(&self, cid: &ContentId, now: Instant) -> Option<&Data> {
+        if let Some(data) = self.own.get(cid) {
+            return Some(data);
+        }
+        match self.received.get(cid) {
+            Some((_, Some(expiry))) if *expiry <= now => None,
+            Some((data, _)) => Some(data),
+            None => None,
+        }
+    }
+}
+
+/// Keeps this data to answer requests for it, returning the cid to reference it with.
+pub(crate) fn add<C: ServerConnector>(agent: &mut Agent<C>, data: Data) -> ContentId {
+    agent.bits_of_binary.add(data)
+}
+
+/// Looks up data we sent, or received and may still keep.
+pub(crate) fn get<'a, C: ServerConnector>(
+    agent: &'a Agent<C>,
+    cid: &ContentId,
+) -> Option<&'a Data> {
+    agent.bits_of_binary.get(cid, Instant::now())
+}
+
+/// Asks this entity for the data of this cid, see [Event::BitsOfBinaryFetched].
+pub(crate) async fn request<C: ServerConnector>(agent: &mut Agent<C>, from: Jid, cid: ContentId) {
+    let id = agent.client.make_id();
+    agent
+        .bits_of_binary
+        .pending
+        .insert(id.clone(), from.clone(), cid.clone());
+    let iq = Iq::builder().to(from).id(id).get(Data::request(cid));
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+/// Answers a request for data we sent, or with item-not-found.
+pub(crate) async fn handle_get<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    request: Data,
+) {
+    match agent.bits_of_binary.own.get(&request.cid) {
+        Some(data) => {
+            let iq = Iq::from_result(id, Some(data.clone())).with_to(from);
+            let _ = agent.client.send_stanza(iq.into()).await;
+        }
+        None => {
+            let error = StanzaError::item_not_found();
+            crate::iq::send_error(agent, from, id, error).await;
+        }
+    }
+}
+
+/// Keeps the data included in a message, e.g. along with a captcha.
+pub(crate) fn handle_data<C: ServerConnector>(agent: &mut Agent<C>, data: Data) {
+    agent.bits_of_binary.store(data, Instant::now());
+}
+
+pub(crate) fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    payload: Element,
+) -> Option<Event> {
+    let cid = agent.bits_of_binary.take(from, id)?;
+    Some(match Data::try_from(payload) {
+        Ok(data) if data.cid == cid && data.matches_cid() => {
+            agent.bits_of_binary.store(data.clone(), Instant::now());
+            Event::BitsOfBinaryFetched(data)
+        }
+        _ => {
+            let error = StanzaError::undefined_condition()
+                .with_text("en", "The entity sent no data for this cid.");
+            Event::BitsOfBinaryFailed(cid, error)
+        }
+    })
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let cid = agent.bits_of_binary.take(from, id)?;
+    Some(Event::BitsOfBinaryFailed(cid, error.take()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache() {
+        let mut bob = BitsOfBinary::default();
+        let now = Instant::now();
+
+        let own = Data::new(None, b"own".to_vec());
+        let cid = bob.add(own.clone());
+        assert_eq!(bob.get(&cid, now), Some(&own));
+
+        let mut uncacheable = Data::new(None, b"uncacheable".to_vec());
+        uncacheable.max_age = Some(0);
+        bob.store(uncacheable.clone(), now);
+        assert_eq!(bob.get(&uncacheable.cid, now), None);
+
+        let mut short = Data::new(None, b"short".to_vec());
+        short.max_age = Some(60);
+        bob.store(short.clone(), now);
+        assert_eq!(bob.get(&short.cid, now), Some(&short));
+        assert_eq!(bob.get(&short.cid, now + Duration::from_secs(60)), None);
+
+        let forever = Data::new(None, b"forever".to_vec());
+        bob.store(forever.clone(), now);
+        assert_eq!(
+            bob.get(&forever.cid, now + Duration::from_secs(86400)),
+            Some(&forever)
+        );
+    }
+
+    #[test]
+    fn forged_and_too_many() {
+        let mut bob = BitsOfBinary::default();
+        let now = Instant::now();
+
+        let mut forged = Data::new(None, b"captcha".to_vec());
+        forged.data = b"something else".to_vec();
+        bob.store(forged.clone(), now);
+        assert_eq!(bob.get(&forged.cid, now), None);
+
+        let mut expired = Data::new(None, b"expired".to_vec());
+        expired.max_age = Some(1);
+        bob.store(expired, now);
+        let later = now + Duration::from_secs(1);
+        let first = Data::new(None, b"0".to_vec());
+        bob.store(first.clone(), later);
+        assert_eq!(bob.received.len(), 1);
+        for i in 1..=MAX_RECEIVED {
+            bob.store(Data::new(None, i.to_string().into_bytes()), later);
+        }
+        assert_eq!(bob.received.len(), MAX_RECEIVED);
+        assert_eq!(bob.get(&first.cid, later), None);
+    }
+
+    #[test]
+    fn pending() {
+        let romeo = Jid::new("romeo@montague.lit/orchard").unwrap();
+        let juliet = Jid::new("juliet@capulet.lit/balcony").unwrap();
+        let cid = ContentId::from_data(b"captcha");
+        let mut bob = BitsOfBinary::default();
+        let id = String::from("bob");
+        bob.pending.insert(id.clone(), romeo.clone(), cid.clone());
+
+        assert_eq!(bob.take(&juliet, &id), None);
+        assert_eq!(bob.take(&romeo, &id), Some(cid));
+        assert_eq!(bob.take(&romeo, &id), None);
+    }
+}
@@ -4,17 +4,37 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
     parsers::{
+        data_forms::DataForm,
         disco::{DiscoInfoResult, Feature, Identity},
         ns,
+        roster::Roster,
+        software_info::SoftwareInfo,
     },
-    AsyncClient as TokioXmppClient, AsyncConfig, BareJid, Jid,
+    AsyncClient as TokioXmppClient, AsyncConfig, BareJid, IdGenerator, Jid,
 };
 
-use crate::{Agent, ClientFeature};
+use crate::blocking::BlockingCommands;
+use crate::bob::BitsOfBinary;
+use crate::call::Calls;
+use crate::disco::browse::ItemQueries;
+use crate::disco::items::DiscoItems;
+use crate::file_transfer::FileTransfers;
+use crate::http_auth::HttpRequests;
+use crate::idle::IdleState;
+use crate::mam::ArchiveCatchUp;
+use crate::message::dedup::SeenMessages;
+use crate::message::queue::{OfflineQueue, QueueStorage};
+use crate::muc::activity::ActivitySubscriptions;
+use crate::muc::history::RoomHistory;
+use crate::muc::register::Registrations;
+use crate::private::PrivateStorage;
+use crate::upload::manager::{RetryPolicy, UploadManager};
+use crate::{disco, Agent, AutoAway, ClientFeature, EncryptionProtocol, OverflowPolicy};
 
 #[derive(Debug)]
 pub enum ClientType {
@@ -47,6 +67,15 @@ pub struct ClientBuilder<'a, C: ServerConnector> {
     disco: (ClientType, String),
     features: Vec<ClientFeature>,
     resource: Option<String>,
+    encryption: Vec<Box<dyn EncryptionProtocol>>,
+    max_concurrent_uploads: usize,
+    upload_retry_policy: RetryPolicy,
+    auto_away: Option<AutoAway>,
+    offline_queue: (usize, OverflowPolicy),
+    queue_storage: Option<Box<dyn QueueStorage>>,
+    disco_extensions: Vec<DataForm>,
+    software_version: Option<String>,
+    id_generator: Option<Arc<dyn IdGenerator>>,
 }
 
 #[cfg(any(feature = "starttls-rust", feature = "starttls-native"))]
@@ -75,6 +104,15 @@ impl<C: ServerConnector> ClientBuilder<'_, C> {
             disco: (ClientType::default(), String::from("tokio-xmpp")),
             features: vec![],
             resource: None,
+            encryption: Vec::new(),
+            max_concurrent_uploads: 3,
+            upload_retry_policy: RetryPolicy::default(),
+            auto_away: None,
+            offline_queue: (0, OverflowPolicy::DropOldest),
+            queue_storage: None,
+            disco_extensions: Vec::new(),
+            software_version: None,
+            id_generator: None,
         }
     }
 
@@ -109,6 +147,74 @@ impl<C: ServerConnector> ClientBuilder<'_, C> {
         self
     }
 
+    /// Sets how many uploads can run at the same time, the next ones get queued.
+    pub fn set_max_concurrent_uploads(mut self, max: usize) -> Self {
+        self.max_concurrent_uploads = max;
+        self
+    }
+
+    /// Sends this presence while the application reports the user as inactive, see
+    /// [`Agent::report_inactivity`]. Otherwise only the server is told, with Client State
+    /// Indication.
+    pub fn set_auto_away(mut self, auto_away: AutoAway) -> Self {
+        self.auto_away = Some(auto_away);
+        self
+    }
+
+    /// Keeps up to `capacity` messages sent while disconnected, e.g. with [`Agent::send_message`],
+    /// and sends them once online again. Without it, they are lost. This enables Stream
+    /// Management (XEP-0198) when the server offers it, to also keep the messages sent until the
+    /// server acknowledged them.
+    pub fn set_offline_queue(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.offline_queue = (capacity, policy);
+        self
+    }
+
+    /// Saves the offline queue to this storage whenever it changes, and starts with the messages
+    /// it kept from a previous run, e.g. [`FileQueueStorage`](crate::FileQueueStorage). This
+    /// needs [`ClientBuilder::set_offline_queue`]. Messages leave the queue once acknowledged by
+    /// the server, or once written to the connection without Stream Management.
+    pub fn set_queue_storage(mut self, storage: Box<dyn QueueStorage>) -> Self {
+        self.queue_storage = Some(storage);
+        self
+    }
+
+    /// Adds an extension form to our disco#info, e.g. the software information of XEP-0232. More
+    /// can be added once connected with [`Agent::add_disco_extension`].
+    pub fn add_disco_extension(mut self, form: DataForm) -> Self {
+        self.disco_extensions.push(form);
+        self
+    }
+
+    /// Advertises the name set with [`ClientBuilder::set_client`] and this version in our
+    /// disco#info, with the software information form of XEP-0232, so that peers get them from
+    /// our caps. The operating system is left out; a complete [`SoftwareInfo`] form can be added
+    /// with [`ClientBuilder::add_disco_extension`] instead.
+    pub fn set_software_version(mut self, version: &str) -> Self {
+        self.software_version = Some(String::from(version));
+        self
+    }
+
+    /// Sets how failed uploads get retried.
+    pub fn set_upload_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.upload_retry_policy = policy;
+        self
+    }
+
+    /// Sets how the ids of the messages, presences and iqs sent are made, by default random
+    /// UUIDs.
+    pub fn set_id_generator(mut self, generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = Some(generator);
+        self
+    }
+
+    /// Registers an end-to-end encryption scheme, tried in registration order on every message
+    /// sent, and on every message received.
+    pub fn add_encryption_protocol<P: EncryptionProtocol + 'static>(mut self, protocol: P) -> Self {
+        self.encryption.push(Box::new(protocol));
+        self
+    }
+
     fn make_disco(&self) -> DiscoInfoResult {
         let identities = vec![Identity::new(
             "client",
@@ -116,7 +222,11 @@ impl<C: ServerConnector> ClientBuilder<'_, C> {
             "en",
             self.disco.1.to_string(),
         )];
-        let mut features = vec![Feature::new(ns::DISCO_INFO)];
+        let mut features = vec![
+            Feature::new(ns::DISCO_INFO),
+            Feature::new(ns::BOB),
+            Feature::new(ns::HTTP_AUTH),
+        ];
         #[cfg(feature = "avatars")]
         {
             if self.features.contains(&ClientFeature::Avatars) {
@@ -126,12 +236,41 @@ impl<C: ServerConnector> ClientBuilder<'_, C> {
         if self.features.contains(&ClientFeature::JoinRooms) {
             features.push(Feature::new(format!("{}+notify", ns::BOOKMARKS2)));
         }
-        DiscoInfoResult {
+        if self.features.contains(&ClientFeature::Calls) {
+            for var in [
+                ns::JINGLE,
+                ns::JINGLE_RTP,
+                ns::JINGLE_RTP_AUDIO,
+                ns::JINGLE_RTP_VIDEO,
+                ns::JINGLE_ICE_UDP,
+                ns::JINGLE_DTLS,
+                ns::CALL_INVITES,
+            ] {
+                features.push(Feature::new(var));
+            }
+        }
+        if self.features.contains(&ClientFeature::FileTransfer) {
+            for var in [ns::SI, ns::SI_FILE_TRANSFER, ns::IBB] {
+                features.push(Feature::new(var));
+            }
+        }
+        if self.features.contains(&ClientFeature::RosterExchange) {
+            features.push(Feature::new(ns::ROSTERX));
+        }
+        let mut info = DiscoInfoResult {
             node: None,
             identities,
             features,
             extensions: vec![],
+        };
+        if let Some(version) = &self.software_version {
+            let software_info = SoftwareInfo::new(self.disco.1.clone(), version.clone());
+            disco::add_extension(&mut info, software_info.into());
         }
+        for form in &self.disco_extensions {
+            disco::add_extension(&mut info, form.clone());
+        }
+        info
     }
 
     pub fn build(self) -> Agent<C> {
@@ -141,11 +280,13 @@ impl<C: ServerConnector> ClientBuilder<'_, C> {
             self.jid.clone().into()
         };
 
-        let config = AsyncConfig {
-            jid,
-            password: self.password.into(),
-            server: self.server_connector.clone(),
-        };
+        let mut config = AsyncConfig::new(jid, self.password, self.server_connector.clone());
+        if let Some(generator) = &self.id_generator {
+            config = config.with_id_generator(generator.clone());
+        }
+        if self.offline_queue.0 > 0 || self.queue_storage.is_some() {
+            config = config.with_stream_management(true);
+        }
         let client = TokioXmppClient::new_with_config(config);
         self.build_impl(client)
     }
@@ -154,15 +295,57 @@ impl<C: ServerConnector> ClientBuilder<'_, C> {
     pub(crate) fn build_impl(self, client: TokioXmppClient<C>) -> Agent<C> {
         let disco = self.make_disco();
         let node = self.website;
+        #[cfg_attr(not(feature = "image"), allow(unused_mut))]
+        let mut uploads = UploadManager::new(self.max_concurrent_uploads, self.upload_retry_policy);
+        #[cfg(feature = "image")]
+        {
+            uploads.thumbnails = self.features.contains(&ClientFeature::Thumbnails);
+        }
 
         Agent {
             client,
             default_nick: Arc::new(RwLock::new(self.default_nick)),
             lang: Arc::new(self.lang),
             disco,
+            disco_items: DiscoItems::default(),
+            item_queries: ItemQueries::default(),
             node,
-            uploads: Vec::new(),
+            uploads,
             awaiting_disco_bookmarks_type: false,
+            encryption: self.encryption,
+            archive: ArchiveCatchUp::new(self.features.contains(&ClientFeature::ArchiveCatchUp)),
+            seen_messages: SeenMessages::default(),
+            idle: IdleState::new(self.auto_away),
+            muc_registrations: Registrations::default(),
+            room_history: RoomHistory::default(),
+            room_passwords: HashMap::new(),
+            joined_rooms: HashMap::new(),
+            quiet_rooms: HashSet::new(),
+            room_activity: ActivitySubscriptions::default(),
+            roster: Roster {
+                ver: None,
+                items: Vec::new(),
+            },
+            replayed_events: Vec::new(),
+            calls: Calls::new(self.features.contains(&ClientFeature::Calls)),
+            file_transfers: FileTransfers::new(
+                self.features.contains(&ClientFeature::FileTransfer),
+            ),
+            offline_queue: OfflineQueue::new(
+                self.offline_queue.0,
+                self.offline_queue.1,
+                self.queue_storage,
+            ),
+            private_storage: PrivateStorage::default(),
+            blocking: BlockingCommands::default(),
+            bits_of_binary: BitsOfBinary::default(),
+            http_requests: HttpRequests::default(),
+            server_info: None,
+            server_info_query: None,
+            #[cfg(feature = "avatars")]
+            vcard_avatars: Default::default(),
+            #[cfg(feature = "avatars")]
+            avatar_fetches: Default::default(),
         }
     }
 }
@@ -0,0 +1,583 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Audio and video calls ([XEP-0167](https://xmpp.org/extensions/xep-0167.html)), signalled with
+//! Jingle ([XEP-0166](https://xmpp.org/extensions/xep-0166.html)).
+//!
+//! The Agent negotiates the session and keeps its state, while the application handles the media:
+//! it provides the contents of its offers and answers, with their descriptions and transports,
+//! and receives those of the peer in [Event::IncomingCall], [Event::CallAccepted] and
+//! [Event::CallTransportInfo].
+
+use rand::{thread_rng, Rng};
+use std::collections::{BTreeMap, HashMap};
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        iq::Iq,
+        jingle::{
+            Action, Content, ContentId, ErrorCondition, Jingle, Reason, ReasonElement, SessionId,
+            Transport,
+        },
+        jingle_ice_udp::Transport as IceUdpTransport,
+        jingle_rtp::SessionInfo,
+        stanza_error::{DefinedCondition, ErrorType, StanzaError},
+    },
+    Element, FullJid, Jid,
+};
+
+use crate::{Agent, Error, Event};
+
+/// The state of a call, as seen by the Agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallState {
+    /// We called someone, whose device didn’t ring yet.
+    Calling,
+    /// We called someone, whose device is ringing.
+    Ringing,
+    /// Someone called us, and we didn’t answer yet.
+    Incoming,
+    /// The call got accepted.
+    Active,
+    /// Either side put the call on hold.
+    Held,
+}
+
+struct Call {
+    peer: FullJid,
+    initiator: bool,
+    state: CallState,
+    /// The id of the session-initiate we sent, whose failure ends the call.
+    initiate_id: Option<String>,
+    /// The ICE-UDP credentials of the peer for each content, which a transport-info changes to
+    /// restart ICE.
+    remote_ice: HashMap<ContentId, IceUdpTransport>,
+}
+
+impl Call {
+    /// Keeps the ICE-UDP credentials of the peer from a transport-info, or the contents of its
+    /// offer or answer, returning the contents for which they changed, restarting ICE.
+    fn update_remote_ice(&mut self, contents: &[Content]) -> Vec<ContentId> {
+        let mut restarted = Vec::new();
+        for content in contents {
+            let Some(Transport::IceUdp(ref transport)) = content.transport else {
+                continue;
+            };
+            let (Some(ufrag), Some(pwd)) = (&transport.ufrag, &transport.pwd) else {
+                continue;
+            };
+            let credentials = IceUdpTransport::new().with_credentials(ufrag.clone(), pwd.clone());
+            match self.remote_ice.get(&content.name) {
+                Some(current) if transport.is_restart_of(current) => {
+                    restarted.push(content.name.clone())
+                }
+                Some(_) => continue,
+                None => (),
+            }
+            self.remote_ice.insert(content.name.clone(), credentials);
+        }
+        restarted
+    }
+}
+
+/// The calls in progress.
+pub(crate) struct Calls {
+    pub(crate) enabled: bool,
+    calls: HashMap<SessionId, Call>,
+}
+
+/// A stanza error condition, along with the Jingle one refining it.
+type Refusal = (DefinedCondition, Option<ErrorCondition>);
+
+fn make_error((condition, jingle): Refusal) -> StanzaError {
+    let type_ = match condition {
+        DefinedCondition::UnexpectedRequest => ErrorType::Wait,
+        _ => ErrorType::Cancel,
+    };
+    StanzaError {
+        type_,
+        by: None,
+        defined_condition: condition,
+        texts: BTreeMap::new(),
+        other: jingle.map(Element::from),
+    }
+}
+
+impl Calls {
+    pub(crate) fn new(enabled: bool) -> Calls {
+        Calls {
+            enabled,
+            calls: HashMap::new(),
+        }
+    }
+
+    /// Session ids must be unique between both parties, so they aren’t just a counter, nor a
+    /// stanza id, which the client may make from one.
+    fn make_sid() -> SessionId {
+        let sid: u128 = thread_rng().gen();
+        SessionId(format!("{:032x}", sid))
+    }
+
+    /// Wraps this action in an iq to the peer of its call.
+    fn make_iq(&self, id: String, jingle: Jingle) -> Option<Iq> {
+        let peer = self.calls.get(&jingle.sid)?.peer.clone();
+        Some(Iq::builder().to(peer.into()).id(id).set(jingle))
+    }
+
+    fn start(
+        &mut self,
+        id: String,
+        own_jid: Jid,
+        peer: FullJid,
+        contents: Vec<Content>,
+    ) -> (SessionId, Iq) {
+        let sid = Calls::make_sid();
+        let mut jingle = Jingle::new(Action::SessionInitiate, sid.clone()).with_initiator(own_jid);
+        jingle.contents = contents;
+        let iq = Iq::builder()
+            .to(peer.clone().into())
+            .id(id.clone())
+            .set(jingle);
+        self.calls.insert(
+            sid.clone(),
+            Call {
+                peer,
+                initiator: true,
+                state: CallState::Calling,
+                initiate_id: Some(id),
+                remote_ice: HashMap::new(),
+            },
+        );
+        (sid, iq)
+    }
+
+    fn accept(
+        &mut self,
+        id: String,
+        own_jid: Jid,
+        sid: &SessionId,
+        contents: Vec<Content>,
+    ) -> Option<Iq> {
+        let call = self.calls.get_mut(sid)?;
+        if call.state != CallState::Incoming {
+            return None;
+        }
+        call.state = CallState::Active;
+        let mut jingle = Jingle::new(Action::SessionAccept, sid.clone()).with_responder(own_jid);
+        jingle.contents = contents;
+        self.make_iq(id, jingle)
+    }
+
+    fn terminate(&mut self, id: String, sid: &SessionId, reason: Option<Reason>) -> Option<Iq> {
+        let reason = match (reason, self.calls.get(sid)?) {
+            (Some(reason), _) => reason,
+            (None, call) if matches!(call.state, CallState::Calling | CallState::Ringing) => {
+                Reason::Cancel
+            }
+            (None, _) => Reason::Success,
+        };
+        let jingle = Jingle::new(Action::SessionTerminate, sid.clone()).set_reason(ReasonElement {
+            reason,
+            texts: BTreeMap::new(),
+        });
+        let iq = self.make_iq(id, jingle);
+        self.calls.remove(sid);
+        iq
+    }
+
+    fn info(&self, id: String, sid: &SessionId, info: SessionInfo) -> Option<Iq> {
+        let mut jingle = Jingle::new(Action::SessionInfo, sid.clone());
+        jingle.other.push(info.into());
+        self.make_iq(id, jingle)
+    }
+
+    fn hold(&mut self, id: String, sid: &SessionId, hold: bool) -> Option<Iq> {
+        let call = self.calls.get_mut(sid)?;
+        let info = match (call.state, hold) {
+            (CallState::Active, true) => {
+                call.state = CallState::Held;
+                SessionInfo::Hold
+            }
+            (CallState::Held, false) => {
+                call.state = CallState::Active;
+                SessionInfo::Unhold
+            }
+            _ => return None,
+        };
+        self.info(id, sid, info)
+    }
+
+    fn transport_info(&self, id: String, sid: &SessionId, contents: Vec<Content>) -> Option<Iq> {
+        let mut jingle = Jingle::new(Action::TransportInfo, sid.clone());
+        jingle.contents = contents;
+        self.make_iq(id, jingle)
+    }
+
+    /// Updates the calls with an action from a peer, returning the resulting events, or the error
+    /// to reply with.
+    fn receive(&mut self, from: &Jid, jingle: Jingle) -> Result<Vec<Event>, Refusal> {
+        let Ok(from) = from.try_as_full() else {
+            return Err((DefinedCondition::BadRequest, None));
+        };
+        let sid = jingle.sid;
+        if jingle.action == Action::SessionInitiate {
+            if self.calls.contains_key(&sid) {
+                return Err((
+                    DefinedCondition::UnexpectedRequest,
+                    Some(ErrorCondition::OutOfOrder),
+                ));
+            }
+            let mut call = Call {
+                peer: from.clone(),
+                initiator: false,
+                state: CallState::Incoming,
+                initiate_id: None,
+                remote_ice: HashMap::new(),
+            };
+            call.update_remote_ice(&jingle.contents);
+            self.calls.insert(sid.clone(), call);
+            return Ok(vec![Event::IncomingCall(
+                sid,
+                from.clone(),
+                jingle.contents,
+            )]);
+        }
+
+        let call = match self.calls.get_mut(&sid) {
+            Some(call) if &call.peer == from => call,
+            _ => {
+                return Err((
+                    DefinedCondition::ItemNotFound,
+                    Some(ErrorCondition::UnknownSession),
+                ))
+            }
+        };
+        let out_of_order = || {
+            (
+                DefinedCondition::UnexpectedRequest,
+                Some(ErrorCondition::OutOfOrder),
+            )
+        };
+        match jingle.action {
+            Action::SessionAccept => {
+                if !call.initiator || !matches!(call.state, CallState::Calling | CallState::Ringing)
+                {
+                    return Err(out_of_order());
+                }
+                call.state = CallState::Active;
+                call.initiate_id = None;
+                call.update_remote_ice(&jingle.contents);
+                Ok(vec![Event::CallAccepted(sid, jingle.contents)])
+            }
+            Action::SessionInfo => {
+                // An empty session-info is only a ping.
+                let Some(payload) = jingle.other.into_iter().next() else {
+                    return Ok(vec![]);
+                };
+                let Ok(info) = SessionInfo::try_from(payload) else {
+                    return Err((
+                        DefinedCondition::FeatureNotImplemented,
+                        Some(ErrorCondition::UnsupportedInfo),
+                    ));
+                };
+                Ok(match (info, call.state) {
+                    (SessionInfo::Ringing, CallState::Calling) => {
+                        call.state = CallState::Ringing;
+                        vec![Event::CallRinging(sid)]
+                    }
+                    (SessionInfo::Hold, CallState::Active) => {
+                        call.state = CallState::Held;
+                        vec![Event::CallHeld(sid, true)]
+                    }
+                    (SessionInfo::Unhold | SessionInfo::Active, CallState::Held) => {
+                        call.state = CallState::Active;
+                        vec![Event::CallHeld(sid, false)]
+                    }
+                    _ => vec![],
+                })
+            }
+            Action::TransportInfo => {
+                let restarted = call.update_remote_ice(&jingle.contents);
+                let mut events: Vec<_> = restarted
+                    .into_iter()
+                    .map(|content| Event::CallIceRestarted(sid.clone(), content))
+                    .collect();
+                events.push(Event::CallTransportInfo(sid, jingle.contents));
+                Ok(events)
+            }
+            Action::SessionTerminate => {
+                self.calls.remove(&sid);
+                Ok(vec![Event::CallEnded(sid, jingle.reason)])
+            }
+            _ => Err((DefinedCondition::FeatureNotImplemented, None)),
+        }
+    }
+
+    /// Ends the call whose session-initiate got refused, if this iq is one.
+    fn fail(&mut self, id: &str) -> Option<Event> {
+        let sid = self
+            .calls
+            .iter()
+            .find(|(_, call)| call.initiate_id.as_deref() == Some(id))
+            .map(|(sid, _)| sid.clone())?;
+        self.calls.remove(&sid);
+        Some(Event::CallEnded(sid, None))
+    }
+}
+
+/// Sends an action on a call, failing with [Error::InvalidState] if there was none, the call
+/// being unknown, ended, or in the wrong state for it.
+async fn send<C: ServerConnector>(agent: &mut Agent<C>, iq: Option<Iq>) -> Result<(), Error> {
+    let iq = iq.ok_or(Error::InvalidState)?;
+    agent.client.send_stanza(iq.into()).await
+}
+
+/// Calls this resource, offering these contents. See [Agent::call](crate::Agent::call).
+/// Fails with [Error::InvalidState] while offline.
+pub async fn call<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    peer: FullJid,
+    contents: Vec<Content>,
+) -> Result<SessionId, Error> {
+    let own_jid = agent.client.bound_jid().ok_or(Error::InvalidState)?.clone();
+    let (sid, iq) = agent
+        .calls
+        .start(agent.client.make_id(), own_jid, peer, contents);
+    send(agent, Some(iq)).await?;
+    Ok(sid)
+}
+
+/// Accepts an incoming call, answering with these contents. Fails with [Error::InvalidState]
+/// while offline, or if the call isn’t an incoming one waiting for an answer.
+pub async fn accept<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    sid: &SessionId,
+    contents: Vec<Content>,
+) -> Result<(), Error> {
+    let own_jid = agent.client.bound_jid().ok_or(Error::InvalidState)?.clone();
+    let iq = agent
+        .calls
+        .accept(agent.client.make_id(), own_jid, sid, contents);
+    send(agent, iq).await
+}
+
+/// Ends a call, with this reason or the usual one for its state. Fails with
+/// [Error::InvalidState] if the call is unknown or already ended.
+pub async fn terminate<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    sid: &SessionId,
+    reason: Option<Reason>,
+) -> Result<(), Error> {
+    let iq = agent.calls.terminate(agent.client.make_id(), sid, reason);
+    send(agent, iq).await
+}
+
+/// Puts a call on hold, or resumes it. Fails with [Error::InvalidState] if the call is unknown,
+/// ended, or already in that state.
+pub async fn hold<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    sid: &SessionId,
+    hold: bool,
+) -> Result<(), Error> {
+    let iq = agent.calls.hold(agent.client.make_id(), sid, hold);
+    send(agent, iq).await
+}
+
+/// Sends more transport information for a call, such as trickled ICE candidates. Fails with
+/// [Error::InvalidState] if the call is unknown or already ended.
+pub async fn send_transport_info<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    sid: &SessionId,
+    contents: Vec<Content>,
+) -> Result<(), Error> {
+    let iq = agent
+        .calls
+        .transport_info(agent.client.make_id(), sid, contents);
+    send(agent, iq).await
+}
+
+pub(crate) fn state<C: ServerConnector>(agent: &Agent<C>, sid: &SessionId) -> Option<CallState> {
+    agent.calls.calls.get(sid).map(|call| call.state)
+}
+
+/// Acknowledges a Jingle action, or refuses it, and tells the caller that our device is ringing
+/// for new calls.
+pub(crate) async fn handle_jingle<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    jingle: Jingle,
+) -> Vec<Event> {
+    let initiate = jingle.action == Action::SessionInitiate;
+    let sid = jingle.sid.clone();
+    match agent.calls.receive(&from, jingle) {
+        Ok(events) => {
+            let iq = Iq::empty_result(from, id);
+            let _ = agent.client.send_stanza(iq.into()).await;
+            if initiate {
+                let iq = agent
+                    .calls
+                    .info(agent.client.make_id(), &sid, SessionInfo::Ringing);
+                let _ = send(agent, iq).await;
+            }
+            events
+        }
+        Err(refusal) => {
+            let iq = Iq::from_error(id, make_error(refusal)).with_to(from);
+            let _ = agent.client.send_stanza(iq.into()).await;
+            vec![]
+        }
+    }
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(agent: &mut Agent<C>, id: &str) -> Option<Event> {
+    agent.calls.fail(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::parsers::jingle::{ContentId, Creator};
+
+    fn receive(calls: &mut Calls, from: &str, xml: &str) -> Result<Vec<Event>, Refusal> {
+        let elem: Element = xml.parse().unwrap();
+        calls.receive(&Jid::new(from).unwrap(), Jingle::try_from(elem).unwrap())
+    }
+
+    #[test]
+    fn outgoing() {
+        let romeo = Jid::new("romeo@montague.lit/orchard").unwrap();
+        let juliet = FullJid::new("juliet@capulet.lit/balcony").unwrap();
+        let mut calls = Calls::new(true);
+        let content = Content::new(Creator::Initiator, ContentId(String::from("voice")));
+        let (sid, iq) = calls.start(String::from("1"), romeo, juliet.clone(), vec![content]);
+        let jingle = iq.get_payload::<Jingle>().unwrap().unwrap();
+        assert_eq!(jingle.action, Action::SessionInitiate);
+        assert_eq!(jingle.contents.len(), 1);
+        assert_eq!(calls.calls[&sid].state, CallState::Calling);
+
+        let ringing = format!(
+            "<jingle xmlns='urn:xmpp:jingle:1' action='session-info' sid='{}'>
+              <ringing xmlns='urn:xmpp:jingle:apps:rtp:info:1'/>
+            </jingle>",
+            sid.0
+        );
+        let error = receive(&mut calls, "juliet@capulet.lit/chamber", &ringing).unwrap_err();
+        assert_eq!(error.0, DefinedCondition::ItemNotFound);
+        let events = receive(&mut calls, "juliet@capulet.lit/balcony", &ringing).unwrap();
+        assert!(matches!(events[..], [Event::CallRinging(_)]));
+        assert_eq!(calls.calls[&sid].state, CallState::Ringing);
+
+        let accept = format!(
+            "<jingle xmlns='urn:xmpp:jingle:1' action='session-accept' sid='{}'/>",
+            sid.0
+        );
+        let events = receive(&mut calls, "juliet@capulet.lit/balcony", &accept).unwrap();
+        assert!(matches!(events[..], [Event::CallAccepted(_, _)]));
+        assert!(receive(&mut calls, "juliet@capulet.lit/balcony", &accept).is_err());
+
+        assert!(calls.hold(String::from("2"), &sid, false).is_none());
+        let iq = calls.hold(String::from("3"), &sid, true).unwrap();
+        let jingle = iq.get_payload::<Jingle>().unwrap().unwrap();
+        assert_eq!(
+            SessionInfo::try_from(jingle.other[0].clone()).unwrap(),
+            SessionInfo::Hold
+        );
+        assert_eq!(calls.calls[&sid].state, CallState::Held);
+
+        let iq = calls.terminate(String::from("4"), &sid, None).unwrap();
+        let jingle = iq.get_payload::<Jingle>().unwrap().unwrap();
+        assert_eq!(jingle.reason.unwrap().reason, Reason::Success);
+        assert!(calls.calls.is_empty());
+    }
+
+    #[test]
+    fn incoming() {
+        let mut calls = Calls::new(true);
+        let events = receive(
+            &mut calls,
+            "romeo@montague.lit/orchard",
+            "<jingle xmlns='urn:xmpp:jingle:1' action='session-initiate' initiator='romeo@montague.lit/orchard' sid='a73sjjvkla37jfea'/>",
+        )
+        .unwrap();
+        let sid = match &events[..] {
+            [Event::IncomingCall(sid, peer, _)] => {
+                assert_eq!(peer.resource().as_str(), "orchard");
+                sid.clone()
+            }
+            _ => panic!(),
+        };
+        assert_eq!(calls.calls[&sid].state, CallState::Incoming);
+
+        let iq = calls
+            .terminate(String::from("1"), &sid, Some(Reason::Decline))
+            .unwrap();
+        let jingle = iq.get_payload::<Jingle>().unwrap().unwrap();
+        assert_eq!(jingle.reason.unwrap().reason, Reason::Decline);
+        assert!(calls.terminate(String::from("2"), &sid, None).is_none());
+        assert!(calls
+            .transport_info(String::from("3"), &sid, vec![])
+            .is_none());
+
+        let error = receive(
+            &mut calls,
+            "romeo@montague.lit/orchard",
+            "<jingle xmlns='urn:xmpp:jingle:1' action='session-terminate' sid='a73sjjvkla37jfea'/>",
+        )
+        .unwrap_err();
+        assert_eq!(error.1, Some(ErrorCondition::UnknownSession));
+    }
+
+    #[test]
+    fn ice_restart() {
+        let mut calls = Calls::new(true);
+        let from = "romeo@montague.lit/orchard";
+        let jingle = |action: &str, ufrag: &str, pwd: &str| {
+            format!(
+                "<jingle xmlns='urn:xmpp:jingle:1' action='{}' sid='a73sjjvkla37jfea'>
+                  <content creator='initiator' name='voice'>
+                    <transport xmlns='urn:xmpp:jingle:transports:ice-udp:1' ufrag='{}' pwd='{}'/>
+                  </content>
+                </jingle>",
+                action, ufrag, pwd
+            )
+        };
+        let events = receive(
+            &mut calls,
+            from,
+            &jingle("session-initiate", "8hhy", "asd88"),
+        )
+        .unwrap();
+        let sid = match &events[..] {
+            [Event::IncomingCall(sid, _, _)] => sid.clone(),
+            _ => panic!(),
+        };
+
+        let trickle =
+            "<jingle xmlns='urn:xmpp:jingle:1' action='transport-info' sid='a73sjjvkla37jfea'>
+              <content creator='initiator' name='voice'>
+                <transport xmlns='urn:xmpp:jingle:transports:ice-udp:1'>
+                  <end-of-candidates/>
+                </transport>
+              </content>
+            </jingle>";
+        let events = receive(&mut calls, from, trickle).unwrap();
+        assert!(matches!(events[..], [Event::CallTransportInfo(_, _)]));
+        let events = receive(&mut calls, from, &jingle("transport-info", "8hhy", "asd88")).unwrap();
+        assert!(matches!(events[..], [Event::CallTransportInfo(_, _)]));
+
+        let events = receive(&mut calls, from, &jingle("transport-info", "9uuz", "dsa99")).unwrap();
+        match &events[..] {
+            [Event::CallIceRestarted(restarted, content), Event::CallTransportInfo(_, _)] => {
+                assert_eq!(restarted, &sid);
+                assert_eq!(content.0, "voice");
+            }
+            _ => panic!(),
+        }
+        let events = receive(&mut calls, from, &jingle("transport-info", "9uuz", "dsa99")).unwrap();
+        assert!(matches!(events[..], [Event::CallTransportInfo(_, _)]));
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Call invites ([XEP-0482](https://xmpp.org/extensions/xep-0482.html)), announcing a call to a
+//! contact or a whole room in a message, with the ways to join it, be it a Jingle session
+//! ([crate::call]) or an external URI.
+//!
+//! Invites are referred to by the id of the message carrying them, which
+//! [Agent::invite_to_call](crate::Agent::invite_to_call) returns and [Event::CallInvite] reports.
+//! They are sent right away, never through the offline queue, as a call is only worth joining
+//! while it is going on.
+
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        call_invites::{Accept, Invite, Left, Reject, Retract},
+        message::{Message, MessagePayload, MessageType},
+    },
+    Element, Jid,
+};
+
+use crate::{Agent, Event, Id};
+
+/// Sends this payload in a message of this type, returning its id.
+async fn send<C: ServerConnector, P: MessagePayload>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    type_: MessageType,
+    payload: P,
+) -> String {
+    let id = agent.client.make_id();
+    let message = Message::new_with_type(type_, to)
+        .with_id(id.clone())
+        .with_payload(payload);
+    let _ = agent.client.send_stanza(message.into()).await;
+    id
+}
+
+/// Invites a contact, or a room with a groupchat message, to a call.
+pub(crate) async fn invite<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    type_: MessageType,
+    invite: Invite,
+) -> String {
+    send(agent, to, type_, invite).await
+}
+
+/// Cancels an invite we sent.
+pub(crate) async fn retract<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    type_: MessageType,
+    id: String,
+) {
+    send(agent, to, type_, Retract { id }).await;
+}
+
+/// Accepts an invite, with the way we joined the call.
+pub(crate) async fn accept<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    type_: MessageType,
+    accept: Accept,
+) {
+    send(agent, to, type_, accept).await;
+}
+
+/// Declines an invite.
+pub(crate) async fn reject<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    type_: MessageType,
+    id: String,
+) {
+    send(agent, to, type_, Reject { id }).await;
+}
+
+/// Tells the others that we left the call of an invite.
+pub(crate) async fn leave<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    type_: MessageType,
+    id: String,
+) {
+    send(agent, to, type_, Left { id }).await;
+}
+
+/// Turns a payload of this namespace into the matching event, if valid.
+pub(crate) fn handle_payload(from: &Jid, id: &Id, child: Element) -> Option<Event> {
+    let from = from.clone();
+    match child.name() {
+        "invite" => Invite::try_from(child)
+            .ok()
+            .map(|invite| Event::CallInvite(from, id.clone(), invite)),
+        "retract" => Retract::try_from(child)
+            .ok()
+            .map(|retract| Event::CallInviteRetracted(from, retract.id)),
+        "accept" => Accept::try_from(child)
+            .ok()
+            .map(|accept| Event::CallInviteAccepted(from, accept)),
+        "reject" => Reject::try_from(child)
+            .ok()
+            .map(|reject| Event::CallInviteRejected(from, reject.id)),
+        "left" => Left::try_from(child)
+            .ok()
+            .map(|left| Event::CallLeft(from, left.id)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::parsers::call_invites::Video;
+
+    fn handle(xml: &str) -> Option<Event> {
+        let from = Jid::new("romeo@montague.lit/orchard").unwrap();
+        let id = Some(String::from("invite1"));
+        handle_payload(&from, &id, xml.parse().unwrap())
+    }
+
+    #[test]
+    fn payloads() {
+        match handle(
+            "<invite xmlns='urn:xmpp:call-invites:0' video='true'>
+              <external uri='https://meet.jit.si/ProjectRomeoAndJuliet'/>
+            </invite>",
+        ) {
+            Some(Event::CallInvite(_, Some(id), invite)) => {
+                assert_eq!(id, "invite1");
+                assert_eq!(invite.video, Video::True);
+                assert_eq!(invite.external.len(), 1);
+            }
+            _ => panic!(),
+        }
+        assert!(matches!(
+            handle("<retract xmlns='urn:xmpp:call-invites:0' id='invite1'/>"),
+            Some(Event::CallInviteRetracted(_, id)) if id == "invite1"
+        ));
+        assert!(matches!(
+            handle("<accept xmlns='urn:xmpp:call-invites:0' id='invite1'/>"),
+            Some(Event::CallInviteAccepted(_, accept)) if accept.id == "invite1"
+        ));
+        assert!(matches!(
+            handle("<reject xmlns='urn:xmpp:call-invites:0' id='invite1'/>"),
+            Some(Event::CallInviteRejected(_, id)) if id == "invite1"
+        ));
+        assert!(matches!(
+            handle("<left xmlns='urn:xmpp:call-invites:0' id='invite1'/>"),
+            Some(Event::CallLeft(_, id)) if id == "invite1"
+        ));
+        assert!(handle("<reject xmlns='urn:xmpp:call-invites:0'/>").is_none());
+    }
+}
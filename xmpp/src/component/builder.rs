@@ -0,0 +1,132 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::time::Duration;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    delegation::Delegations,
+    parsers::{
+        data_forms::DataForm,
+        disco::{DiscoInfoResult, Feature, Identity, Item as DiscoItem},
+        ns,
+    },
+    Component, Jid,
+};
+
+use crate::component::{session::Sessions, ComponentAgent};
+use crate::disco::{self, items::DiscoItems};
+use crate::Error;
+
+/// Configures and connects a [`ComponentAgent`], the component counterpart of
+/// [`ClientBuilder`](crate::ClientBuilder).
+///
+/// The component authenticates with the secret shared with the server, and advertises the
+/// identity, features and items set here in its disco#info and disco#items.
+pub struct ComponentBuilder<'a, C: ServerConnector> {
+    jid: Jid,
+    password: &'a str,
+    server_connector: C,
+    identity: Identity,
+    features: Vec<String>,
+    extensions: Vec<DataForm>,
+    disco_items: DiscoItems,
+    iq_timeout: Option<Duration>,
+}
+
+impl<C: ServerConnector> ComponentBuilder<'_, C> {
+    /// Prepares a component logging in as `jid`, a domain handled by the server, with the
+    /// secret `password`, connecting with `server_connector`.
+    pub fn new_with_connector<'a>(
+        jid: Jid,
+        password: &'a str,
+        server_connector: C,
+    ) -> ComponentBuilder<'a, C> {
+        ComponentBuilder {
+            jid,
+            password,
+            server_connector,
+            identity: Identity::new("component", "generic", "en", "xmpp-rs"),
+            features: vec![],
+            extensions: vec![],
+            disco_items: DiscoItems::default(),
+            iq_timeout: None,
+        }
+    }
+
+    /// Sets the identity advertised in disco#info, e.g. `("gateway", "irc", "IRC gateway")`.
+    pub fn set_identity(mut self, category: &str, type_: &str, name: &str) -> Self {
+        self.identity = Identity::new(category, type_, "en", name);
+        self
+    }
+
+    /// Advertises a feature in disco#info, besides disco#info, disco#items and ping which the
+    /// component answers itself.
+    pub fn add_feature(mut self, namespace: &str) -> Self {
+        self.features.push(String::from(namespace));
+        self
+    }
+
+    /// Adds an extension form to disco#info, e.g. the room information of a MUC component; more
+    /// can be added later with [`ComponentAgent::add_disco_extension`].
+    pub fn add_disco_extension(mut self, form: DataForm) -> Self {
+        self.extensions.push(form);
+        self
+    }
+
+    /// Lists this item in disco#items, at the root or under `node`; more can be added later with
+    /// [`ComponentAgent::add_disco_item`].
+    pub fn add_disco_item(mut self, node: Option<&str>, item: DiscoItem) -> Self {
+        self.disco_items.add(node, item);
+        self
+    }
+
+    /// Sets how long [`ComponentAgent::send_iq`] waits for a response.
+    pub fn set_iq_timeout(mut self, timeout: Duration) -> Self {
+        self.iq_timeout = Some(timeout);
+        self
+    }
+
+    fn make_disco(&self) -> DiscoInfoResult {
+        let mut features = vec![
+            Feature::new(ns::DISCO_INFO),
+            Feature::new(ns::DISCO_ITEMS),
+            Feature::new(ns::PING),
+        ];
+        features.extend(self.features.iter().map(Feature::new));
+        let mut info = DiscoInfoResult {
+            node: None,
+            identities: vec![self.identity.clone()],
+            features,
+            extensions: vec![],
+        };
+        for form in &self.extensions {
+            disco::add_extension(&mut info, form.clone());
+        }
+        info
+    }
+
+    /// Connects and authenticates the component to the server.
+    pub async fn build(self) -> Result<ComponentAgent<C>, Error> {
+        let disco = self.make_disco();
+        let mut component = Component::new_with_connector(
+            &self.jid.to_string(),
+            self.password,
+            self.server_connector,
+        )
+        .await?;
+        if let Some(timeout) = self.iq_timeout {
+            component.set_iq_timeout(timeout);
+        }
+
+        Ok(ComponentAgent {
+            delegations: Delegations::new(self.jid),
+            component,
+            disco,
+            disco_items: self.disco_items,
+            sessions: Sessions::default(),
+        })
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use tokio_xmpp::{
+    delegation::DelegatedRequest,
+    parsers::{delegation::Delegated, iq::Iq, message::Message, presence::Presence},
+    FullJid,
+};
+
+#[derive(Debug)]
+pub enum ComponentEvent {
+    /// A message was received, for the component or any of its addresses.
+    Message(Message),
+    /// A presence was received. Sessions are tracked before it is reported.
+    Presence(Presence),
+    /// A get or set iq was received which the component doesn’t answer itself. It MUST be answered,
+    /// e.g. with [`ComponentAgent::send_iq_result`](crate::component::ComponentAgent::send_iq_result)
+    /// or [`ComponentAgent::send_iq_error`](crate::component::ComponentAgent::send_iq_error).
+    Iq(Iq),
+    /// A user sent its first available presence from this resource.
+    SessionStarted(FullJid),
+    /// A user went unavailable from this resource.
+    SessionEnded(FullJid),
+    /// The server advertised the namespaces it delegates to the component (XEP-0355).
+    DelegationAdvertised(Vec<Delegated>),
+    /// An iq of a delegated namespace, forwarded by the server. It MUST be answered with
+    /// [`ComponentAgent::send_stanza`](crate::component::ComponentAgent::send_stanza) and
+    /// [`DelegatedRequest::respond`] or [`DelegatedRequest::refuse`].
+    DelegatedIq(Box<DelegatedRequest>),
+}
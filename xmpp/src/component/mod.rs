@@ -0,0 +1,299 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The counterpart of [`Agent`](crate::Agent) for components
+//! ([XEP-0114](https://xmpp.org/extensions/xep-0114.html)), answering disco#info, disco#items and pings, and
+//! keeping track of the sessions of the users talking to the component.
+
+use futures::StreamExt;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    delegation::{DelegationEvent, Delegations},
+    parsers::{
+        data_forms::DataForm,
+        disco::{DiscoInfoQuery, DiscoInfoResult, DiscoItemsQuery, Item as DiscoItem},
+        iq::{Iq, IqType},
+        ns,
+        ping::Ping,
+        stanza::{with_namespace, Stanza},
+        stanza_error::{DefinedCondition, ErrorType, StanzaError},
+    },
+    BareJid, Component, Element, FullJid, IqResponse, Jid,
+};
+
+use crate::disco::{self, items::DiscoItems};
+use crate::Error;
+
+pub mod builder;
+pub mod event;
+pub(crate) mod session;
+
+pub use builder::ComponentBuilder;
+pub use event::ComponentEvent;
+use session::{SessionChange, Sessions};
+
+/// A connected component, built with [`ComponentBuilder`].
+///
+/// It answers disco#info, disco#items and pings itself, tracks the sessions of users from their
+/// presences, and reports everything else as [`ComponentEvent`]s from
+/// [`ComponentAgent::wait_for_events`], which has to keep being called. Stanzas are sent in the
+/// `jabber:component:accept` namespace of component streams.
+pub struct ComponentAgent<C: ServerConnector> {
+    pub(crate) component: Component<C>,
+    pub(crate) disco: DiscoInfoResult,
+    pub(crate) disco_items: DiscoItems,
+    pub(crate) sessions: Sessions,
+    pub(crate) delegations: Delegations,
+}
+
+impl<C: ServerConnector> ComponentAgent<C> {
+    /// The address of the component.
+    pub fn jid(&self) -> &Jid {
+        &self.component.jid
+    }
+
+    /// Closes the stream.
+    pub async fn disconnect(&mut self) -> Result<(), Error> {
+        self.component.send_end().await
+    }
+
+    /// Sends any stanza. Unlike clients, components have to set its from, which may be any
+    /// address of their domain.
+    pub async fn send_stanza<E: Into<Element>>(&mut self, stanza: E) -> Result<(), Error> {
+        self.component.send_stanza(stanza.into()).await
+    }
+
+    /// Sends an iq request, and returns the future of its response, which only resolves while
+    /// [`ComponentAgent::wait_for_events`] keeps being called.
+    pub async fn send_iq(&mut self, iq: Iq) -> Result<IqResponse, Error> {
+        self.component.send_iq(iq).await
+    }
+
+    /// Answers an iq reported by [`ComponentEvent::Iq`].
+    pub async fn send_iq_result(
+        &mut self,
+        request: &Iq,
+        payload: Option<Element>,
+    ) -> Result<(), Error> {
+        let response = response_to(request, IqType::Result(payload));
+        self.send_stanza(response).await
+    }
+
+    /// Answers an iq reported by [`ComponentEvent::Iq`] with an error.
+    pub async fn send_iq_error(&mut self, request: &Iq, error: StanzaError) -> Result<(), Error> {
+        let response = response_to(request, IqType::Error(error));
+        self.send_stanza(response).await
+    }
+
+    /// Adds this extension form to our disco#info, replacing the one with the same FORM_TYPE.
+    pub fn add_disco_extension(&mut self, form: DataForm) {
+        disco::add_extension(&mut self.disco, form);
+    }
+
+    /// Removes the extension form with this FORM_TYPE from our disco#info.
+    pub fn remove_disco_extension(&mut self, form_type: &str) {
+        disco::remove_extension(&mut self.disco, form_type);
+    }
+
+    /// Lists this item in our disco#items, at the root or under `node`, e.g. a room of a gateway.
+    pub fn add_disco_item(&mut self, node: Option<&str>, item: DiscoItem) {
+        self.disco_items.add(node, item);
+    }
+
+    /// Stops listing the item pointing to `jid` and `item_node` under `node`, returning whether
+    /// it was listed.
+    pub fn remove_disco_item(
+        &mut self,
+        node: Option<&str>,
+        jid: &Jid,
+        item_node: Option<&str>,
+    ) -> bool {
+        self.disco_items.remove(node, jid, item_node)
+    }
+
+    /// The resources this user sent an available presence from.
+    pub fn sessions(&self, user: &BareJid) -> impl Iterator<Item = &FullJid> {
+        self.sessions.resources(user)
+    }
+
+    /// Whether this user has at least one available resource.
+    pub fn is_online(&self, user: &BareJid) -> bool {
+        self.sessions.is_online(user)
+    }
+
+    /// Wait for new events.
+    ///
+    /// # Returns
+    ///
+    /// - `Some(events)` if there are new events; multiple may be returned at once.
+    /// - `None` if the underlying stream is closed.
+    pub async fn wait_for_events(&mut self) -> Option<Vec<ComponentEvent>> {
+        let stanza = into_default_ns(self.component.next().await?);
+        let mut events = vec![];
+
+        let stanza = match self.delegations.handle(stanza) {
+            DelegationEvent::Advertised(delegated) => {
+                events.push(ComponentEvent::DelegationAdvertised(delegated));
+                return Some(events);
+            }
+            DelegationEvent::Request(request) => {
+                events.push(ComponentEvent::DelegatedIq(request));
+                return Some(events);
+            }
+            DelegationEvent::Stanza(stanza) => stanza,
+        };
+
+        match Stanza::try_from(stanza) {
+            Ok(Stanza::Iq(iq)) => {
+                if let Some(response) = answer_iq(&self.disco, &self.disco_items, &iq) {
+                    let _ = self.send_stanza(response).await;
+                } else if let IqType::Get(_) | IqType::Set(_) = iq.payload {
+                    events.push(ComponentEvent::Iq(iq));
+                }
+            }
+            Ok(Stanza::Message(message)) => events.push(ComponentEvent::Message(message)),
+            Ok(Stanza::Presence(presence)) => {
+                match self.sessions.handle_presence(&presence) {
+                    Some(SessionChange::Started(jid)) => {
+                        events.push(ComponentEvent::SessionStarted(jid))
+                    }
+                    Some(SessionChange::Ended(jid)) => {
+                        events.push(ComponentEvent::SessionEnded(jid))
+                    }
+                    None => (),
+                }
+                events.push(ComponentEvent::Presence(presence));
+            }
+            Ok(Stanza::Nonza(elem)) => warn!("Unknown stanza: {}", String::from(&elem)),
+            Err(err) => warn!("Invalid stanza: {}", err),
+        }
+
+        Some(events)
+    }
+}
+
+/// Stanzas of a component stream are in its own namespace, which the parsers don’t know about
+/// unless built for components.
+fn into_default_ns(stanza: Element) -> Element {
+    if stanza.ns() != ns::COMPONENT_ACCEPT {
+        return stanza;
+    }
+    with_namespace(stanza, ns::DEFAULT_NS)
+}
+
+fn response_to(request: &Iq, payload: IqType) -> Iq {
+    Iq {
+        from: request.to.clone(),
+        to: request.from.clone(),
+        id: request.id.clone(),
+        payload,
+    }
+}
+
+/// Answers the iqs every component supports: disco#info, disco#items and ping.
+fn answer_iq(disco: &DiscoInfoResult, items: &DiscoItems, iq: &Iq) -> Option<Iq> {
+    let IqType::Get(ref payload) = iq.payload else {
+        return None;
+    };
+    if payload.is("query", ns::DISCO_INFO) {
+        let payload = match DiscoInfoQuery::try_from(payload.clone()) {
+            Ok(query) => {
+                let mut disco = disco.clone();
+                disco.node = query.node;
+                IqType::Result(Some(disco.into()))
+            }
+            Err(err) => IqType::Error(StanzaError::new(
+                ErrorType::Modify,
+                DefinedCondition::BadRequest,
+                "en",
+                format!("{}", err),
+            )),
+        };
+        Some(response_to(iq, payload))
+    } else if payload.is("query", ns::DISCO_ITEMS) {
+        let payload = match DiscoItemsQuery::try_from(payload.clone()) {
+            Ok(query) => match items.answer(query) {
+                Some(items) => IqType::Result(Some(items.into())),
+                None => IqType::Error(StanzaError::item_not_found()),
+            },
+            Err(err) => IqType::Error(StanzaError::bad_request().with_text("en", err.to_string())),
+        };
+        Some(response_to(iq, payload))
+    } else if payload.is("ping", ns::PING) && Ping::try_from(payload.clone()).is_ok() {
+        Some(response_to(iq, IqType::Result(None)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::parsers::disco::{DiscoItemsResult, Feature, Identity};
+
+    fn parse_iq(xml: &str) -> Iq {
+        let elem: Element = xml.parse().unwrap();
+        Iq::try_from(into_default_ns(elem)).unwrap()
+    }
+
+    #[test]
+    fn responders() {
+        let disco = DiscoInfoResult {
+            node: None,
+            identities: vec![Identity::new("gateway", "irc", "en", "IRC")],
+            features: vec![Feature::new(ns::DISCO_INFO), Feature::new(ns::PING)],
+            extensions: vec![],
+        };
+        let mut items = DiscoItems::default();
+        items.add(
+            None,
+            DiscoItem {
+                jid: Jid::new("#montague%irc.capulet.lit").unwrap(),
+                node: None,
+                name: Some(String::from("#montague")),
+            },
+        );
+
+        let ping = parse_iq(
+            "<iq xmlns='jabber:component:accept' from='juliet@capulet.lit/balcony' to='irc.capulet.lit' type='get' id='ping1'>
+              <ping xmlns='urn:xmpp:ping'/>
+            </iq>",
+        );
+        let response = answer_iq(&disco, &items, &ping).unwrap();
+        assert_eq!(response.id, "ping1");
+        assert_eq!(response.from, Some(Jid::new("irc.capulet.lit").unwrap()));
+        assert_eq!(
+            response.to,
+            Some(Jid::new("juliet@capulet.lit/balcony").unwrap())
+        );
+        assert!(matches!(response.payload, IqType::Result(None)));
+
+        let query = parse_iq(
+            "<iq xmlns='jabber:component:accept' from='juliet@capulet.lit/balcony' to='irc.capulet.lit' type='get' id='disco1'>
+              <query xmlns='http://jabber.org/protocol/disco#info'/>
+            </iq>",
+        );
+        let response = answer_iq(&disco, &items, &query).unwrap();
+        let result = response.get_payload::<DiscoInfoResult>().unwrap().unwrap();
+        assert_eq!(result.identities[0].category, "gateway");
+
+        let query = parse_iq(
+            "<iq xmlns='jabber:component:accept' from='juliet@capulet.lit/balcony' to='irc.capulet.lit' type='get' id='items1'>
+              <query xmlns='http://jabber.org/protocol/disco#items'/>
+            </iq>",
+        );
+        let response = answer_iq(&disco, &items, &query).unwrap();
+        let result = response.get_payload::<DiscoItemsResult>().unwrap().unwrap();
+        assert_eq!(result.items[0].name.as_deref(), Some("#montague"));
+
+        let version = parse_iq(
+            "<iq xmlns='jabber:component:accept' from='juliet@capulet.lit/balcony' to='irc.capulet.lit' type='get' id='version1'>
+              <query xmlns='jabber:iq:version'/>
+            </iq>",
+        );
+        assert!(answer_iq(&disco, &items, &version).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::collections::{HashMap, HashSet};
+use tokio_xmpp::{
+    parsers::presence::{Presence, Type as PresenceType},
+    BareJid, FullJid,
+};
+
+/// A change in the sessions of users, see [`ComponentEvent`](crate::component::ComponentEvent).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SessionChange {
+    Started(FullJid),
+    Ended(FullJid),
+}
+
+/// The resources of every user who sent an available presence to the component.
+#[derive(Debug, Default)]
+pub(crate) struct Sessions {
+    users: HashMap<BareJid, HashSet<FullJid>>,
+}
+
+impl Sessions {
+    /// Updates the sessions with a presence received by the component.
+    pub(crate) fn handle_presence(&mut self, presence: &Presence) -> Option<SessionChange> {
+        let from = presence.from.clone()?.try_into_full().ok()?;
+        match presence.type_ {
+            PresenceType::None => {
+                let resources = self.users.entry(from.to_bare()).or_default();
+                resources
+                    .insert(from.clone())
+                    .then_some(SessionChange::Started(from))
+            }
+            PresenceType::Unavailable | PresenceType::Error => {
+                let bare = from.to_bare();
+                let resources = self.users.get_mut(&bare)?;
+                let removed = resources.remove(&from);
+                if resources.is_empty() {
+                    self.users.remove(&bare);
+                }
+                removed.then_some(SessionChange::Ended(from))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn resources(&self, user: &BareJid) -> impl Iterator<Item = &FullJid> {
+        self.users.get(user).into_iter().flatten()
+    }
+
+    pub(crate) fn is_online(&self, user: &BareJid) -> bool {
+        self.users.contains_key(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::Jid;
+
+    fn presence(from: &str, type_: PresenceType) -> Presence {
+        let mut presence = Presence::new(type_);
+        presence.from = Some(Jid::new(from).unwrap());
+        presence
+    }
+
+    #[test]
+    fn sessions() {
+        let mut sessions = Sessions::default();
+        let juliet = BareJid::new("juliet@capulet.lit").unwrap();
+        let balcony = FullJid::new("juliet@capulet.lit/balcony").unwrap();
+
+        assert_eq!(
+            sessions.handle_presence(&presence("juliet@capulet.lit/balcony", PresenceType::None)),
+            Some(SessionChange::Started(balcony.clone()))
+        );
+        // Presence updates of a known resource.
+        assert_eq!(
+            sessions.handle_presence(&presence("juliet@capulet.lit/balcony", PresenceType::None)),
+            None
+        );
+        assert_eq!(
+            sessions.handle_presence(&presence("juliet@capulet.lit/chamber", PresenceType::None)),
+            Some(SessionChange::Started(
+                FullJid::new("juliet@capulet.lit/chamber").unwrap()
+            ))
+        );
+        assert_eq!(sessions.resources(&juliet).count(), 2);
+        // Bare JIDs can't have a session.
+        assert_eq!(
+            sessions.handle_presence(&presence("romeo@montague.lit", PresenceType::None)),
+            None
+        );
+
+        assert_eq!(
+            sessions.handle_presence(&presence(
+                "juliet@capulet.lit/balcony",
+                PresenceType::Unavailable
+            )),
+            Some(SessionChange::Ended(balcony))
+        );
+        assert!(sessions.is_online(&juliet));
+        sessions.handle_presence(&presence(
+            "juliet@capulet.lit/chamber",
+            PresenceType::Unavailable,
+        ));
+        assert!(!sessions.is_online(&juliet));
+        assert_eq!(sessions.resources(&juliet).count(), 0);
+    }
+}
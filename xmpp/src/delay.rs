@@ -22,6 +22,15 @@ pub struct StanzaTimeInfo {
     ///
     /// **Warning**: this has security implications. See [XEP-0203 security section](https://xmpp.org/extensions/xep-0203.html#security).
     pub delays: Vec<Delay>,
+
+    /// Whether this message was retrieved from an archive when catching up after a reconnection,
+    /// or when fetching the history of a room, rather than received live.
+    pub from_archive: bool,
+
+    /// Whether this message is part of the history requested with
+    /// [Agent::fetch_room_history](crate::Agent::fetch_room_history), so likely older than what
+    /// was already shown.
+    pub historical: bool,
 }
 
 impl StanzaTimeInfo {
@@ -61,5 +70,7 @@ pub fn message_time_info(message: &Message) -> StanzaTimeInfo {
     StanzaTimeInfo {
         received: Utc::now(),
         delays,
+        from_archive: false,
+        historical: false,
     }
 }
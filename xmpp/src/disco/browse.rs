@@ -0,0 +1,175 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Listing the items of other entities, e.g. the rooms of a MUC service, page by page using
+//! Result Set Management ([XEP-0059](https://xmpp.org/extensions/xep-0059.html)).
+
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        disco::{DiscoItemsQuery, DiscoItemsResult},
+        iq::Iq,
+        rsm::SetQuery,
+        stanza_error::StanzaError,
+    },
+    Element, Jid,
+};
+
+use crate::{iq::pending::PendingIqs, Agent, Event};
+
+/// disco#items queries waiting for their page.
+#[derive(Default)]
+pub(crate) struct ItemQueries {
+    pending: PendingIqs<Jid>,
+}
+
+impl ItemQueries {
+    fn make_query(
+        &mut self,
+        id: String,
+        jid: Jid,
+        node: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Iq {
+        let query = DiscoItemsQuery {
+            node,
+            rsm: Some(SetQuery {
+                max: Some(limit),
+                after,
+                before: None,
+                index: None,
+            }),
+        };
+        let iq = Iq::builder().to(jid.clone()).id(id.clone()).get(query);
+        self.pending.insert(id, jid.clone(), jid);
+        iq
+    }
+
+    /// Returns the entity queried by this iq, if it is the one answering.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<Jid> {
+        self.pending.take(from, id)
+    }
+}
+
+/// The UID of the last item of this page, to fetch the next one, or None if it is the last
+/// page. Entities not supporting RSM send all their items at once.
+fn next_page(result: &DiscoItemsResult) -> Option<String> {
+    let rsm = result.rsm.as_ref()?;
+    let last = rsm.last.clone()?;
+    match (rsm.first_index, rsm.count) {
+        (Some(first_index), Some(count)) if first_index + result.items.len() >= count => None,
+        _ => Some(last),
+    }
+}
+
+/// Asks an entity for up to `limit` of its items under this node, after the item with this UID,
+/// or from the start if None.
+pub async fn fetch_items<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    jid: Jid,
+    node: Option<String>,
+    after: Option<String>,
+    limit: usize,
+) {
+    let id = agent.client.make_id();
+    let iq = agent.item_queries.make_query(id, jid, node, after, limit);
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+/// Reports a page of items answering one of our queries.
+pub(crate) fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    payload: Element,
+) -> Option<Event> {
+    let jid = agent.item_queries.take(from, id)?;
+    match DiscoItemsResult::try_from(payload) {
+        Ok(result) => {
+            let next = next_page(&result);
+            Some(Event::DiscoItemsFetched(
+                jid,
+                result.node,
+                result.items,
+                next,
+            ))
+        }
+        Err(err) => {
+            let error = StanzaError::bad_request().with_text("en", err.to_string());
+            Some(Event::DiscoItemsFailed(jid, error))
+        }
+    }
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let jid = agent.item_queries.take(from, id)?;
+    Some(Event::DiscoItemsFailed(jid, error.take()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> DiscoItemsResult {
+        let elem: Element = xml.parse().unwrap();
+        DiscoItemsResult::try_from(elem).unwrap()
+    }
+
+    #[test]
+    fn pages() {
+        let service = Jid::new("chat.shakespeare.lit").unwrap();
+        let mut queries = ItemQueries::default();
+        let id = String::from("items");
+        let iq = queries.make_query(id, service.clone(), None, Some(String::from("12")), 2);
+        assert_eq!(iq.id, "items");
+        let query = iq.get_payload::<DiscoItemsQuery>().unwrap().unwrap();
+        let set = query.rsm.unwrap();
+        assert_eq!(set.max, Some(2));
+        assert_eq!(set.after.as_deref(), Some("12"));
+
+        let other = Jid::new("darkcave@chat.shakespeare.lit").unwrap();
+        assert!(queries.take(&other, "items").is_none());
+        assert_eq!(queries.take(&service, "items"), Some(service));
+
+        let page = parse(
+            "<query xmlns='http://jabber.org/protocol/disco#items'>
+              <item jid='coven@chat.shakespeare.lit'/>
+              <item jid='darkcave@chat.shakespeare.lit'/>
+              <set xmlns='http://jabber.org/protocol/rsm'>
+                <first index='0'>coven</first>
+                <last>darkcave</last>
+                <count>3</count>
+              </set>
+            </query>",
+        );
+        assert_eq!(next_page(&page).as_deref(), Some("darkcave"));
+
+        let last = parse(
+            "<query xmlns='http://jabber.org/protocol/disco#items'>
+              <item jid='heath@chat.shakespeare.lit'/>
+              <set xmlns='http://jabber.org/protocol/rsm'>
+                <first index='2'>heath</first>
+                <last>heath</last>
+                <count>3</count>
+              </set>
+            </query>",
+        );
+        assert_eq!(next_page(&last), None);
+
+        let unpaged = parse(
+            "<query xmlns='http://jabber.org/protocol/disco#items'>
+              <item jid='heath@chat.shakespeare.lit'/>
+            </query>",
+        );
+        assert_eq!(next_page(&unpaged), None);
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The items we list in answer to disco#items queries, paged with
+//! [XEP-0059](https://xmpp.org/extensions/xep-0059.html) when asked to.
+
+use std::collections::HashMap;
+use tokio_xmpp::{
+    parsers::{
+        disco::{DiscoItemsQuery, DiscoItemsResult, Item},
+        rsm::{SetQuery, SetResult},
+    },
+    Jid,
+};
+
+/// The items registered by the application, by node, `None` being the root.
+#[derive(Debug, Default)]
+pub(crate) struct DiscoItems {
+    nodes: HashMap<Option<String>, Vec<Item>>,
+}
+
+impl DiscoItems {
+    /// Lists this item under this node, unless it already is.
+    pub(crate) fn add(&mut self, node: Option<&str>, item: Item) -> bool {
+        let items = self.nodes.entry(node.map(String::from)).or_default();
+        if items.contains(&item) {
+            return false;
+        }
+        items.push(item);
+        true
+    }
+
+    /// Stops listing the item pointing to this JID and node under this node.
+    pub(crate) fn remove(
+        &mut self,
+        node: Option<&str>,
+        jid: &Jid,
+        item_node: Option<&str>,
+    ) -> bool {
+        let Some(items) = self.nodes.get_mut(&node.map(String::from)) else {
+            return false;
+        };
+        let len = items.len();
+        items.retain(|item| &item.jid != jid || item.node.as_deref() != item_node);
+        let removed = items.len() != len;
+        if items.is_empty() {
+            self.nodes.remove(&node.map(String::from));
+        }
+        removed
+    }
+
+    /// Whether no item is listed under any node.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Answers a disco#items query, with the requested page if it uses RSM, or `None` if that page
+    /// is relative to an unknown item. The UIDs of the items are their positions in the list.
+    pub(crate) fn answer(&self, query: DiscoItemsQuery) -> Option<DiscoItemsResult> {
+        let items = self
+            .nodes
+            .get(&query.node)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let (items, rsm) = match query.rsm {
+            Some(set) => {
+                let (page, rsm) = page(items, set)?;
+                (page.to_vec(), Some(rsm))
+            }
+            None => (items.to_vec(), None),
+        };
+        Some(DiscoItemsResult {
+            node: query.node,
+            items,
+            rsm,
+        })
+    }
+}
+
+/// The position of the item with this UID.
+fn position(uid: &str, len: usize) -> Option<usize> {
+    uid.parse().ok().filter(|&position| position < len)
+}
+
+/// Selects the requested page, see [XEP-0059 §2](https://xmpp.org/extensions/xep-0059.html#limit).
+fn page(items: &[Item], set: SetQuery) -> Option<(&[Item], SetResult)> {
+    let count = items.len();
+    let max = set.max.unwrap_or(count);
+    let (start, end) = if let Some(before) = set.before {
+        let end = if before.is_empty() {
+            count
+        } else {
+            position(&before, count)?
+        };
+        (end.saturating_sub(max), end)
+    } else {
+        let start = match set.after {
+            Some(after) => position(&after, count)? + 1,
+            None => set.index.unwrap_or(0).min(count),
+        };
+        (start, start.saturating_add(max).min(count))
+    };
+    let not_empty = start < end;
+    let rsm = SetResult {
+        first: not_empty.then(|| start.to_string()),
+        first_index: not_empty.then_some(start),
+        last: not_empty.then(|| (end - 1).to_string()),
+        count: Some(count),
+    };
+    Some((&items[start..end], rsm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(i: usize) -> Item {
+        Item {
+            jid: Jid::new(&format!("room{}@chat.shakespeare.lit", i)).unwrap(),
+            node: None,
+            name: None,
+        }
+    }
+
+    fn query(max: Option<usize>, after: Option<&str>, before: Option<&str>) -> DiscoItemsQuery {
+        DiscoItemsQuery {
+            node: None,
+            rsm: Some(SetQuery {
+                max,
+                after: after.map(String::from),
+                before: before.map(String::from),
+                index: None,
+            }),
+        }
+    }
+
+    fn uids(result: &DiscoItemsResult) -> (Option<&str>, Option<&str>, Option<usize>) {
+        let rsm = result.rsm.as_ref().unwrap();
+        (rsm.first.as_deref(), rsm.last.as_deref(), rsm.count)
+    }
+
+    #[test]
+    fn paging() {
+        let mut items = DiscoItems::default();
+        for i in 0..5 {
+            assert!(items.add(None, item(i)));
+        }
+        assert!(!items.add(None, item(0)));
+
+        let all = items
+            .answer(DiscoItemsQuery {
+                node: None,
+                rsm: None,
+            })
+            .unwrap();
+        assert_eq!(all.items.len(), 5);
+        assert!(all.rsm.is_none());
+
+        let first = items.answer(query(Some(2), None, None)).unwrap();
+        assert_eq!(first.items, [item(0), item(1)]);
+        assert_eq!(uids(&first), (Some("0"), Some("1"), Some(5)));
+
+        let next = items.answer(query(Some(2), Some("1"), None)).unwrap();
+        assert_eq!(next.items, [item(2), item(3)]);
+
+        let last = items.answer(query(Some(2), None, Some(""))).unwrap();
+        assert_eq!(last.items, [item(3), item(4)]);
+
+        let previous = items.answer(query(Some(2), None, Some("3"))).unwrap();
+        assert_eq!(previous.items, [item(1), item(2)]);
+
+        let end = items.answer(query(Some(2), Some("4"), None)).unwrap();
+        assert!(end.items.is_empty());
+        assert_eq!(uids(&end), (None, None, Some(5)));
+
+        let count = items.answer(query(Some(0), None, None)).unwrap();
+        assert!(count.items.is_empty());
+        assert_eq!(uids(&count).2, Some(5));
+
+        assert!(items.answer(query(Some(2), Some("12"), None)).is_none());
+
+        assert!(items.remove(None, &item(0).jid, None));
+        assert!(!items.remove(None, &item(0).jid, None));
+        assert!(!items.remove(Some("rooms"), &item(1).jid, None));
+        for i in 1..5 {
+            assert!(!items.is_empty());
+            assert!(items.remove(None, &item(i).jid, None));
+        }
+        assert!(items.is_empty());
+    }
+}
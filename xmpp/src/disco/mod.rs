@@ -4,21 +4,135 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod browse;
+pub(crate) mod items;
+
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
     parsers::{
         bookmarks,
-        disco::{DiscoInfoResult, Feature},
+        data_forms::{DataForm, DataFormType},
+        disco::{DiscoInfoQuery, DiscoInfoResult, Feature, Identity},
         iq::Iq,
         ns,
         private::Query as PrivateXMLQuery,
         pubsub::pubsub::{Items, PubSub},
+        server_info::ServerInfo,
         Error as ParsersError,
     },
-    Element, Jid,
+    BareJid, Element, Jid,
 };
 
-use crate::Agent;
+use crate::{idle, presence::send::broadcast_presence, Agent};
+
+pub(crate) fn add_feature(disco: &mut DiscoInfoResult, var: &str) -> bool {
+    if disco.features.iter().any(|feature| feature.var == var) {
+        return false;
+    }
+    disco.features.push(Feature::new(var));
+    true
+}
+
+pub(crate) fn remove_feature(disco: &mut DiscoInfoResult, var: &str) -> bool {
+    let len = disco.features.len();
+    disco.features.retain(|feature| feature.var != var);
+    disco.features.len() != len
+}
+
+pub(crate) fn add_identity(disco: &mut DiscoInfoResult, identity: Identity) -> bool {
+    if disco.identities.contains(&identity) {
+        return false;
+    }
+    disco.identities.push(identity);
+    true
+}
+
+pub(crate) fn remove_identity(disco: &mut DiscoInfoResult, category: &str, type_: &str) -> bool {
+    let len = disco.identities.len();
+    disco
+        .identities
+        .retain(|identity| identity.category != category || identity.type_ != type_);
+    disco.identities.len() != len
+}
+
+/// Adds this extension form ([XEP-0128](https://xmpp.org/extensions/xep-0128.html)), replacing
+/// the one with the same FORM_TYPE if any.
+pub(crate) fn add_extension(disco: &mut DiscoInfoResult, mut form: DataForm) -> bool {
+    form.type_ = DataFormType::Result_;
+    if disco.extensions.contains(&form) {
+        return false;
+    }
+    disco
+        .extensions
+        .retain(|known| known.form_type != form.form_type);
+    disco.extensions.push(form);
+    true
+}
+
+pub(crate) fn remove_extension(disco: &mut DiscoInfoResult, form_type: &str) -> bool {
+    let len = disco.extensions.len();
+    disco
+        .extensions
+        .retain(|form| form.form_type.as_deref() != Some(form_type));
+    disco.extensions.len() != len
+}
+
+/// Rebroadcasts our presence after a change to what we advertise, so that its caps hash keeps
+/// matching our disco#info responses. Before we are online, the initial presence takes care of
+/// it.
+pub(crate) async fn advertise_changes<C: ServerConnector>(agent: &mut Agent<C>) {
+    if agent.client.bound_jid().is_none() {
+        return;
+    }
+    let presence = idle::make_presence(agent);
+    broadcast_presence(agent, presence).await;
+}
+
+pub(crate) fn server<C: ServerConnector>(agent: &Agent<C>) -> Option<BareJid> {
+    let jid = agent.client.bound_jid()?;
+    Some(BareJid::from_parts(None, jid.domain()))
+}
+
+/// Asks our server for its disco#info, to learn its contact addresses.
+pub(crate) async fn query_server<C: ServerConnector>(agent: &mut Agent<C>) {
+    let Some(server) = server(agent) else {
+        return;
+    };
+    let id = agent.client.make_id();
+    let iq = Iq::builder()
+        .to(server.into())
+        .id(id.clone())
+        .get(DiscoInfoQuery { node: None });
+    agent.server_info_query = Some(id);
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+/// Keeps the contact addresses ([XEP-0157](https://xmpp.org/extensions/xep-0157.html)) from the
+/// disco#info of our server.
+pub(crate) fn handle_server_info<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    disco: DiscoInfoResult,
+) {
+    if server(agent).is_some_and(|server| from == &server) {
+        agent.server_info_query = None;
+        agent.server_info = server_info(disco);
+    }
+}
+
+fn server_info(disco: DiscoInfoResult) -> Option<ServerInfo> {
+    let form = disco
+        .extensions
+        .into_iter()
+        .find(|form| form.form_type.as_deref() == Some(ns::SERVER_INFO))?;
+    match ServerInfo::try_from(form) {
+        Ok(server_info) => Some(server_info),
+        Err(err) => {
+            warn!("Invalid contact addresses from our server: {}", err);
+            None
+        }
+    }
+}
 
 // This method is a workaround due to prosody bug https://issues.prosody.im/1664
 // FIXME: To be removed in the future
@@ -81,12 +195,16 @@ pub async fn handle_disco_info_result<C: ServerConnector>(
 
         if perform_bookmarks2 {
             // XEP-0402 bookmarks (modern)
-            let iq = Iq::from_get("bookmarks", PubSub::Items(Items::new(ns::BOOKMARKS2))).into();
+            let iq = Iq::from_get(
+                agent.client.make_id(),
+                PubSub::Items(Items::new(ns::BOOKMARKS2)),
+            )
+            .into();
             let _ = agent.client.send_stanza(iq).await;
         } else {
             // XEP-0048 v1.0 bookmarks (legacy)
             let iq = Iq::from_get(
-                "bookmarks-legacy",
+                agent.client.make_id(),
                 PrivateXMLQuery {
                     storage: bookmarks::Storage::new(),
                 },
@@ -98,3 +216,100 @@ pub async fn handle_disco_info_result<C: ServerConnector>(
         unimplemented!("Ignored disco#info response from {}", from);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::parsers::caps::{compute_disco, hash_caps};
+    use tokio_xmpp::parsers::data_forms::Field;
+    use tokio_xmpp::parsers::hashes::Algo;
+
+    fn caps_hash(disco: &DiscoInfoResult) -> Vec<u8> {
+        hash_caps(&compute_disco(disco), Algo::Sha_1).unwrap().hash
+    }
+
+    #[test]
+    fn contact_addresses() {
+        let elem: Element = "<query xmlns='http://jabber.org/protocol/disco#info'>
+              <identity category='server' type='im'/>
+              <feature var='http://jabber.org/protocol/disco#info'/>
+              <x xmlns='jabber:x:data' type='result'>
+                <field var='FORM_TYPE' type='hidden'>
+                  <value>http://jabber.org/network/serverinfo</value>
+                </field>
+                <field var='abuse-addresses' type='list-multi'>
+                  <value>mailto:abuse@shakespeare.lit</value>
+                  <value>xmpp:abuse@shakespeare.lit</value>
+                </field>
+                <field var='admin-addresses' type='list-multi'>
+                  <value>xmpp:admin@shakespeare.lit</value>
+                </field>
+              </x>
+            </query>"
+            .parse()
+            .unwrap();
+        let disco = DiscoInfoResult::try_from(elem).unwrap();
+        let info = server_info(disco.clone()).unwrap();
+        assert_eq!(
+            info.abuse,
+            ["mailto:abuse@shakespeare.lit", "xmpp:abuse@shakespeare.lit"]
+        );
+        assert_eq!(info.admin, ["xmpp:admin@shakespeare.lit"]);
+
+        let mut disco = disco;
+        disco.extensions.clear();
+        assert!(server_info(disco).is_none());
+    }
+
+    #[test]
+    fn local_changes() {
+        let mut disco = DiscoInfoResult {
+            node: None,
+            identities: vec![Identity::new("client", "pc", "en", "xmpp-rs")],
+            features: vec![Feature::new(ns::DISCO_INFO)],
+            extensions: vec![],
+        };
+        let initial = caps_hash(&disco);
+
+        assert!(add_feature(&mut disco, ns::PING));
+        assert!(!add_feature(&mut disco, ns::PING));
+        let with_ping = caps_hash(&disco);
+        assert_ne!(with_ping, initial);
+
+        let bot = Identity::new("client", "bot", "en", "xmpp-rs");
+        assert!(add_identity(&mut disco, bot.clone()));
+        assert!(!add_identity(&mut disco, bot));
+        assert_ne!(caps_hash(&disco), with_ping);
+        assert!(remove_identity(&mut disco, "client", "bot"));
+        assert!(!remove_identity(&mut disco, "client", "bot"));
+        assert_eq!(caps_hash(&disco), with_ping);
+
+        assert!(remove_feature(&mut disco, ns::PING));
+        assert!(!remove_feature(&mut disco, ns::PING));
+        assert_eq!(caps_hash(&disco), initial);
+
+        let software = |version| {
+            DataForm::new(
+                DataFormType::Result_,
+                "urn:xmpp:dataforms:softwareinfo",
+                vec![Field::text_single("software_version", version)],
+            )
+        };
+        assert!(add_extension(&mut disco, software("0.1")));
+        assert!(!add_extension(&mut disco, software("0.1")));
+        let with_form = caps_hash(&disco);
+        assert_ne!(with_form, initial);
+        assert!(add_extension(&mut disco, software("0.2")));
+        assert_eq!(disco.extensions.len(), 1);
+        assert_ne!(caps_hash(&disco), with_form);
+        assert!(remove_extension(
+            &mut disco,
+            "urn:xmpp:dataforms:softwareinfo"
+        ));
+        assert!(!remove_extension(
+            &mut disco,
+            "urn:xmpp:dataforms:softwareinfo"
+        ));
+        assert_eq!(caps_hash(&disco), initial);
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Integration point for end-to-end encryption schemes (OMEMO, OX, …), which can be implemented
+//! in separate crates and registered with
+//! [`ClientBuilder::add_encryption_protocol`](crate::ClientBuilder::add_encryption_protocol).
+
+use std::collections::BTreeMap;
+use tokio_xmpp::parsers::{
+    eme::ExplicitMessageEncryption,
+    message::{Body, Message},
+};
+use tokio_xmpp::Element;
+
+/// The error an [`EncryptionProtocol`] can return, passed on to the application when sending a
+/// message and only logged when receiving one.
+pub type EncryptionError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An end-to-end encryption scheme, which the [`Agent`](crate::Agent) calls on every message
+/// it sends and receives.
+pub trait EncryptionProtocol: Send {
+    /// The namespace identifying this scheme, used in its
+    /// [XEP-0380](https://xmpp.org/extensions/xep-0380.html) hint.
+    fn namespace(&self) -> &str;
+
+    /// A user-friendly name for this scheme, used in its XEP-0380 hint.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Encrypts the bodies of an outgoing message, returning the payloads which replace them.
+    ///
+    /// Returns `Ok(None)` if this scheme can’t be used for this message, for instance when there
+    /// is no session with its recipient, in which case the next registered scheme gets tried.
+    fn encrypt(&mut self, message: &Message) -> Result<Option<Vec<Element>>, EncryptionError>;
+
+    /// Decrypts the payloads of an incoming message, returning its plaintext body.
+    ///
+    /// Returns `Ok(None)` if this message doesn’t contain any payload for this scheme.
+    fn decrypt(&mut self, message: &Message) -> Result<Option<String>, EncryptionError>;
+}
+
+/// Returns the XEP-0380 hint of an incoming message, naming the scheme it claims to be encrypted
+/// with.
+pub(crate) fn message_encryption(message: &Message) -> Option<ExplicitMessageEncryption> {
+    message
+        .get_payload::<ExplicitMessageEncryption>()
+        .and_then(Result::ok)
+}
+
+/// Encrypts an outgoing message with the first scheme which accepts it.
+///
+/// Returns the error of the scheme which failed, in which case the message must not be sent, to
+/// avoid leaking it in plaintext.
+pub(crate) fn encrypt_message(
+    protocols: &mut [Box<dyn EncryptionProtocol>],
+    mut message: Message,
+) -> Result<Message, EncryptionError> {
+    if message.bodies.is_empty() {
+        return Ok(message);
+    }
+    for protocol in protocols.iter_mut() {
+        match protocol.encrypt(&message) {
+            Ok(None) => continue,
+            Ok(Some(payloads)) => {
+                let name = protocol.name().unwrap_or(protocol.namespace());
+                let fallback = format!(
+                    "This message is encrypted with {}, but your client doesn’t seem to support that.",
+                    name
+                );
+                message.bodies = BTreeMap::new();
+                message.bodies.insert(String::new(), Body(fallback));
+                message.payloads.extend(payloads);
+                message.payloads.push(
+                    ExplicitMessageEncryption {
+                        namespace: protocol.namespace().to_owned(),
+                        name: protocol.name().map(ToOwned::to_owned),
+                    }
+                    .into(),
+                );
+                return Ok(message);
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to encrypt message with {}, not sending it: {}",
+                    protocol.namespace(),
+                    err
+                );
+                return Err(err);
+            }
+        }
+    }
+    Ok(message)
+}
+
+/// Replaces the bodies of an incoming message with its plaintext, if a scheme could decrypt it.
+///
+/// On failure the message is kept as is, with its fallback body if any.
+pub(crate) fn decrypt_message(
+    protocols: &mut [Box<dyn EncryptionProtocol>],
+    message: &mut Message,
+) {
+    for protocol in protocols.iter_mut() {
+        match protocol.decrypt(message) {
+            Ok(None) => continue,
+            Ok(Some(body)) => {
+                message.bodies = BTreeMap::new();
+                message.bodies.insert(String::new(), Body(body));
+                return;
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to decrypt message with {}: {}",
+                    protocol.namespace(),
+                    err
+                );
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::{parsers::message::MessageType, BareJid};
+
+    // Pretends to encrypt by reversing the body.
+    struct Reverse;
+
+    impl EncryptionProtocol for Reverse {
+        fn namespace(&self) -> &str {
+            "urn:example:reverse"
+        }
+
+        fn encrypt(&mut self, message: &Message) -> Result<Option<Vec<Element>>, EncryptionError> {
+            let body = &message.bodies[""].0;
+            let payload = Element::builder("reversed", "urn:example:reverse")
+                .append(body.chars().rev().collect::<String>())
+                .build();
+            Ok(Some(vec![payload]))
+        }
+
+        fn decrypt(&mut self, message: &Message) -> Result<Option<String>, EncryptionError> {
+            Ok(message
+                .payloads
+                .iter()
+                .find(|payload| payload.is("reversed", "urn:example:reverse"))
+                .map(|payload| payload.text().chars().rev().collect()))
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut protocols: Vec<Box<dyn EncryptionProtocol>> = vec![Box::new(Reverse)];
+        let to = BareJid::new("juliet@example.org").unwrap();
+        let message = Message::new_with_type(MessageType::Chat, Some(to.into()))
+            .with_body(String::new(), String::from("Hello"));
+
+        let mut message = encrypt_message(&mut protocols, message).unwrap();
+        assert!(!message.bodies[""].0.contains("Hello"));
+        assert_eq!(message.payloads[0].text(), "olleH");
+        assert!(message.payloads[1].is("encryption", tokio_xmpp::parsers::ns::EME));
+
+        decrypt_message(&mut protocols, &mut message);
+        assert_eq!(message.bodies[""].0, "Hello");
+        let encryption = message_encryption(&message).unwrap();
+        assert_eq!(encryption.namespace, "urn:example:reverse");
+        assert_eq!(encryption.name, None);
+    }
+
+    struct Broken;
+
+    impl EncryptionProtocol for Broken {
+        fn namespace(&self) -> &str {
+            "urn:example:broken"
+        }
+
+        fn encrypt(&mut self, _: &Message) -> Result<Option<Vec<Element>>, EncryptionError> {
+            Err("no session".into())
+        }
+
+        fn decrypt(&mut self, _: &Message) -> Result<Option<String>, EncryptionError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn failure() {
+        let mut protocols: Vec<Box<dyn EncryptionProtocol>> =
+            vec![Box::new(Broken), Box::new(Reverse)];
+        let to = BareJid::new("juliet@example.org").unwrap();
+        let message = Message::new_with_type(MessageType::Chat, Some(to.into()))
+            .with_body(String::new(), String::from("Hello"));
+
+        let err = encrypt_message(&mut protocols, message).unwrap_err();
+        assert_eq!(err.to_string(), "no session");
+    }
+}
@@ -4,11 +4,32 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-#[cfg(feature = "avatars")]
-use tokio_xmpp::parsers::Jid;
-use tokio_xmpp::parsers::{bookmarks2, message::Body, roster::Item as RosterItem, BareJid};
+use std::path::PathBuf;
+use tokio_xmpp::parsers::{
+    bob::{ContentId, Data},
+    bookmarks2,
+    call_invites::{Accept, Invite},
+    data_forms::DataForm,
+    date::DateTime,
+    disco::Item as DiscoItem,
+    eme::ExplicitMessageEncryption,
+    http_auth::Confirm,
+    jingle::{self, Content, ReasonElement, SessionId},
+    message::Body,
+    pubsub::{Item as PubSubItem, ItemId, NodeName},
+    roster::Item as RosterItem,
+    rosterx::Item as SuggestedItem,
+    si_ft::File,
+    stanza_error::StanzaError,
+    BareJid, FullJid, Jid,
+};
 
-use crate::{delay::StanzaTimeInfo, Error, Id, RoomNick};
+use crate::{
+    delay::StanzaTimeInfo,
+    file_transfer::TransferError,
+    upload::manager::{UploadError, UploadId, UploadedFile},
+    Error, Id, RoomNick,
+};
 
 #[derive(Debug)]
 pub enum Event {
@@ -17,6 +38,14 @@ pub enum Event {
     ContactAdded(RosterItem),
     ContactRemoved(RosterItem),
     ContactChanged(RosterItem),
+    /// Contacts to add, modify or delete, suggested by this entity, see
+    /// [ClientFeature::RosterExchange](crate::ClientFeature::RosterExchange). It is up to the
+    /// user to apply them, and to trust the sender.
+    ContactsSuggested(Jid, Vec<SuggestedItem>),
+    /// This resource of a contact has been idle since this time, as reported in its presence.
+    ContactIdle(Jid, DateTime),
+    /// This resource of a contact, previously reported with [Event::ContactIdle], is active again.
+    ContactActive(Jid),
     #[cfg(feature = "avatars")]
     AvatarRetrieved(Jid, String),
     /// A chat message was received. It may have been delayed on the network.
@@ -24,21 +53,165 @@ pub enum Event {
     /// - The [`BareJid`] is the sender's JID.
     /// - The [`Body`] is the message body.
     /// - The [`StanzaTimeInfo`] about when message was received, and when the message was claimed sent.
-    ChatMessage(Id, BareJid, Body, StanzaTimeInfo),
+    /// - The [`ExplicitMessageEncryption`] naming the scheme the message was encrypted with, if the
+    ///   sender said so, whether or not it could be decrypted. Like the rest of the stanza, it is
+    ///   only a claim of the sender.
+    ChatMessage(
+        Id,
+        BareJid,
+        Body,
+        StanzaTimeInfo,
+        Option<ExplicitMessageEncryption>,
+    ),
+    /// This message, from this entity, is attached to the earlier message of the [`String`] id,
+    /// its stanza-id in a room. It may carry no body, e.g. a receipt or an uploaded file sent as
+    /// a follow-up, and comes along with the other events about this message, if any.
+    MessageAttached(Jid, Id, String),
     JoinRoom(BareJid, bookmarks2::Conference),
+    /// The bookmark of this room got removed or lost its autojoin flag. If we were in this room,
+    /// the agent left it already, [Event::RoomLeft] follows.
     LeaveRoom(BareJid),
+    /// All bookmarks got removed, the agent left every room we were in.
     LeaveAllRooms,
     RoomJoined(BareJid),
+    /// There are new messages in this room, on a MUC service we subscribed to with
+    /// [`Agent::subscribe_room_activity`](crate::Agent::subscribe_room_activity).
+    RoomActivity(BareJid),
     RoomLeft(BareJid),
-    RoomMessage(Id, BareJid, RoomNick, Body, StanzaTimeInfo),
+    /// A message received in a room, with the same details as [Event::ChatMessage].
+    RoomMessage(
+        Id,
+        BareJid,
+        RoomNick,
+        Body,
+        StanzaTimeInfo,
+        Option<ExplicitMessageEncryption>,
+    ),
     /// The subject of a room was received.
     /// - The BareJid is the room's address.
-    /// - The RoomNick is the nickname of the room member who set the subject.
-    /// - The String is the new subject.
-    RoomSubject(BareJid, Option<RoomNick>, String, StanzaTimeInfo),
+    /// - The RoomNick is the nickname of the room member who set the subject, if any.
+    /// - The String is the new subject, empty if it got cleared.
+    ///
+    /// This is sent when joining a room, and whenever its subject changes. Messages with both a
+    /// subject and a body aren’t subject changes.
+    RoomSubjectChanged(BareJid, Option<RoomNick>, String, StanzaTimeInfo),
     /// A private message received from a room, containing the message ID, the room's BareJid,
     /// the sender's nickname, and the message body.
-    RoomPrivateMessage(Id, BareJid, RoomNick, Body, StanzaTimeInfo),
-    ServiceMessage(Id, BareJid, Body, StanzaTimeInfo),
-    HttpUploadedFile(String),
+    RoomPrivateMessage(
+        Id,
+        BareJid,
+        RoomNick,
+        Body,
+        StanzaTimeInfo,
+        Option<ExplicitMessageEncryption>,
+    ),
+    ServiceMessage(
+        Id,
+        BareJid,
+        Body,
+        StanzaTimeInfo,
+        Option<ExplicitMessageEncryption>,
+    ),
+    /// A page of history requested with
+    /// [Agent::fetch_room_history](crate::Agent::fetch_room_history) was reported, as
+    /// [Event::RoomMessage] events flagged as historical.
+    /// - The Option<String> is the stanza-id of its oldest message, to fetch the page before.
+    /// - The bool is true once the start of the archive is reached.
+    RoomHistoryFetched(BareJid, Option<String>, bool),
+    /// The room refused to send its history, e.g. because it isn’t archived.
+    RoomHistoryFailed(BareJid, StanzaError),
+    /// The form to fill to register a nickname with this room, see
+    /// [Agent::register_nick](crate::Agent::register_nick). The media of its fields, e.g. the
+    /// image of a captcha, may reference Bits of Binary sent along, available with
+    /// [Agent::bits_of_binary](crate::Agent::bits_of_binary).
+    RoomRegistrationForm(BareJid, DataForm),
+    /// Our nickname is registered with this room.
+    RoomRegistered(BareJid),
+    /// The room refused our registration, e.g. with a conflict if the nickname is already taken.
+    RoomRegistrationFailed(BareJid, StanzaError),
+    /// A file got uploaded, see [Agent::upload_file_with](crate::Agent::upload_file_with).
+    HttpUploadedFile(UploadedFile),
+    /// An upload failed, the [`PathBuf`] is the path of the file.
+    HttpUploadFailed(UploadId, PathBuf, UploadError),
+    /// Progress of an upload, with the number of bytes sent so far and the size of the file.
+    HttpUploadProgress(UploadId, u64, u64),
+    /// An upload was cancelled with [Agent::cancel_upload](crate::Agent::cancel_upload).
+    HttpUploadCancelled(UploadId),
+    /// Someone is calling, with the contents they offer. Our device rings for them until the
+    /// call is accepted with [Agent::accept_call](crate::Agent::accept_call) or rejected.
+    IncomingCall(SessionId, FullJid, Vec<Content>),
+    /// The device of the person we called is ringing.
+    CallRinging(SessionId),
+    /// The call got accepted, with the contents answering our offer.
+    CallAccepted(SessionId, Vec<Content>),
+    /// The peer put the call on hold (true), or resumed it (false).
+    CallHeld(SessionId, bool),
+    /// More transport information from the peer, such as trickled ICE candidates.
+    CallTransportInfo(SessionId, Vec<Content>),
+    /// The peer restarted ICE for this content, with new credentials: the candidates gathered so
+    /// far have to be discarded. Followed by the [Event::CallTransportInfo] carrying the new ones.
+    CallIceRestarted(SessionId, jingle::ContentId),
+    /// The call ended, with the reason given by the peer if it hung up, or None if the call
+    /// couldn’t be made.
+    CallEnded(SessionId, Option<ReasonElement>),
+    /// This contact, or this occupant of a room, invites us to a call, in the message of this
+    /// id, which the answers refer to. See [crate::call_invite].
+    CallInvite(Jid, Id, Invite),
+    /// The invite of this message id got cancelled by its sender.
+    CallInviteRetracted(Jid, String),
+    /// This entity accepted an invite, possibly from another of our devices, which can then stop
+    /// ringing.
+    CallInviteAccepted(Jid, Accept),
+    /// This entity declined the invite of this message id.
+    CallInviteRejected(Jid, String),
+    /// This entity left the call of the invite of this message id.
+    CallLeft(Jid, String),
+    /// This resource offers to send us this file, with the session id to accept it with
+    /// [Agent::accept_file](crate::Agent::accept_file) or decline it with
+    /// [Agent::reject_file](crate::Agent::reject_file). Requires
+    /// [ClientFeature::FileTransfer](crate::ClientFeature::FileTransfer).
+    FileOffered(Jid, String, File),
+    /// The file of this session got entirely received, and saved at this path.
+    FileReceived(Jid, String, PathBuf),
+    /// The file of this session got entirely sent.
+    FileSent(Jid, String),
+    /// The transfer of this session failed, in either direction.
+    FileTransferFailed(Jid, String, TransferError),
+    /// Items got published on a PEP node whose notifications were asked for with
+    /// [Agent::subscribe_pep](crate::Agent::subscribe_pep), by the account of this JID.
+    PepItemsPublished(Jid, NodeName, Vec<PubSubItem>),
+    /// Items got retracted from such a PEP node.
+    PepItemsRetracted(Jid, NodeName, Vec<ItemId>),
+    /// Every item of such a PEP node got removed.
+    PepNodePurged(Jid, NodeName),
+    /// The element of this namespace got stored with
+    /// [Agent::private_set](crate::Agent::private_set).
+    PrivateDataStored(String),
+    /// The private storage request about this namespace failed.
+    PrivateDataFailed(String, StanzaError),
+    /// This JID got blocked, with [Agent::block](crate::Agent::block) or
+    /// [Agent::report_and_block](crate::Agent::report_and_block).
+    Blocked(Jid),
+    /// This JID got unblocked, with [Agent::unblock](crate::Agent::unblock).
+    Unblocked(Jid),
+    /// Blocking or unblocking this JID failed.
+    BlockingFailed(Jid, StanzaError),
+    /// The data requested with
+    /// [Agent::request_bits_of_binary](crate::Agent::request_bits_of_binary).
+    BitsOfBinaryFetched(Data),
+    /// The data of this cid couldn’t be fetched.
+    BitsOfBinaryFailed(ContentId, StanzaError),
+    /// This entity asks whether the user made this HTTP request, e.g. to log into a website with
+    /// their JID. Answer with [Agent::accept_http_request](crate::Agent::accept_http_request) or
+    /// [Agent::deny_http_request](crate::Agent::deny_http_request), after checking the
+    /// transaction id given by the website.
+    HttpRequestConfirmation(Jid, Confirm),
+    /// A page of items requested with
+    /// [Agent::fetch_disco_items](crate::Agent::fetch_disco_items).
+    /// - The first Option<String> is the node queried.
+    /// - The last one is the UID of the last item, to fetch the next page, or None once all the
+    ///   items got fetched.
+    DiscoItemsFetched(Jid, Option<String>, Vec<DiscoItem>, Option<String>),
+    /// The entity refused to list its items.
+    DiscoItemsFailed(Jid, StanzaError),
 }
@@ -7,13 +7,21 @@
 use futures::StreamExt;
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
-    parsers::{
-        disco::DiscoInfoQuery, iq::Iq, message::Message, presence::Presence, roster::Roster,
-    },
-    Event as TokioXmppEvent,
+    parsers::{disco::DiscoInfoQuery, iq::Iq, roster::Roster, stanza::Stanza},
+    Element, Event as TokioXmppEvent,
 };
 
-use crate::{iq, message, presence, Agent, Event};
+use crate::{disco, file_transfer, idle, iq, mam, message, muc, presence, upload, Agent, Event};
+
+/// What woke the agent up.
+enum Next<F> {
+    Client(Option<TokioXmppEvent>),
+    Upload(upload::manager::TaskEvent),
+    /// An avatar downloaded over HTTP.
+    Avatar(F),
+    /// The server acknowledged messages of the offline queue.
+    Acked,
+}
 
 /// Wait for new events.
 ///
@@ -22,18 +30,73 @@ use crate::{iq, message, presence, Agent, Event};
 /// - `Some(events)` if there are new events; multiple may be returned at once.
 /// - `None` if the underlying stream is closed.
 pub async fn wait_for_events<C: ServerConnector>(agent: &mut Agent<C>) -> Option<Vec<Event>> {
-    if let Some(event) = agent.client.next().await {
+    let events = wait_for_events_with_stanzas(agent).await?;
+    Some(events.into_iter().map(|(event, _)| event).collect())
+}
+
+/// Wait for new events, along with the stanza each one comes from, as received, if any.
+///
+/// # Returns
+///
+/// - `Some(events)` if there are new events; multiple may be returned at once.
+/// - `None` if the underlying stream is closed.
+pub async fn wait_for_events_with_stanzas<C: ServerConnector>(
+    agent: &mut Agent<C>,
+) -> Option<Vec<(Event, Option<Element>)>> {
+    #[cfg(feature = "avatars")]
+    let fetched = agent.avatar_fetches.receiver.recv();
+    #[cfg(not(feature = "avatars"))]
+    let fetched = std::future::pending::<Option<std::convert::Infallible>>();
+    let next = tokio::select! {
+        event = agent.client.next() => Next::Client(event),
+        Some(event) = agent.uploads.receiver.recv() => Next::Upload(event),
+        Some(fetched) = fetched => Next::Avatar(fetched),
+        () = agent.offline_queue.acked() => Next::Acked,
+    };
+    let event = match next {
+        Next::Client(event) => event,
+        Next::Upload(event) => {
+            let events = upload::manager::handle_task_event(agent, event).await;
+            return Some(events.into_iter().map(|event| (event, None)).collect());
+        }
+        Next::Avatar(fetched) => {
+            #[cfg(feature = "avatars")]
+            return Some(
+                crate::pubsub::avatar::handle_fetched(agent, fetched)
+                    .into_iter()
+                    .map(|event| (event, None))
+                    .collect(),
+            );
+            #[cfg(not(feature = "avatars"))]
+            match fetched {}
+        }
+        Next::Acked => return Some(Vec::new()),
+    };
+
+    if let Some(event) = event {
         let mut events = Vec::new();
 
         match event {
             TokioXmppEvent::Online { resumed: false, .. } => {
-                let presence =
-                    presence::send::make_initial_presence(&agent.disco, &agent.node).into();
+                // A new session isn’t in any room yet.
+                agent.joined_rooms.clear();
+                agent.quiet_rooms.clear();
+                events.extend(
+                    file_transfer::reset(agent)
+                        .into_iter()
+                        .map(|event| (event, None)),
+                );
+                // Nor knows anything about the server, which may have changed since.
+                agent.server_info = None;
+                agent.server_info_query = None;
+                // Write the initial burst of stanzas together.
+                agent.client.cork();
+                let presence = presence::send::make_initial_presence(agent).into();
                 let _ = agent.client.send_stanza(presence).await;
-                events.push(Event::Online);
+                events.push((Event::Online, None));
                 // TODO: only send this when the ContactList feature is enabled.
                 let iq = Iq::from_get(
-                    "roster",
+                    agent.client.make_id(),
                     Roster {
                         ver: None,
                         items: vec![],
@@ -43,32 +106,52 @@ pub async fn wait_for_events<C: ServerConnector>(agent: &mut Agent<C>) -> Option
                 let _ = agent.client.send_stanza(iq).await;
 
                 // Query account disco to know what bookmarks spec is used
-                let iq = Iq::from_get("disco-account", DiscoInfoQuery { node: None }).into();
+                let iq = Iq::from_get(agent.client.make_id(), DiscoInfoQuery { node: None }).into();
                 let _ = agent.client.send_stanza(iq).await;
                 agent.awaiting_disco_bookmarks_type = true;
+                disco::query_server(agent).await;
+
+                muc::activity::resubscribe(agent).await;
+                mam::catch_up(agent).await;
+                idle::send_state(agent).await;
+                let _ = agent.client.uncork().await;
+                // Not corked, so that a message only leaves the queue once actually written.
+                message::queue::flush(agent).await;
+            }
+            TokioXmppEvent::Online { resumed: true, .. } => {
+                message::queue::flush(agent).await;
             }
-            TokioXmppEvent::Online { resumed: true, .. } => {}
             TokioXmppEvent::Disconnected(e) => {
-                events.push(Event::Disconnected(e));
+                events.push((Event::Disconnected(e), None));
             }
             TokioXmppEvent::Stanza(elem) => {
-                if elem.is("iq", "jabber:client") {
-                    let iq = Iq::try_from(elem).unwrap();
-                    let new_events = iq::handle_iq(agent, iq).await;
-                    events.extend(new_events);
-                } else if elem.is("message", "jabber:client") {
-                    let message = Message::try_from(elem).unwrap();
-                    let new_events = message::receive::handle_message(agent, message).await;
-                    events.extend(new_events);
-                } else if elem.is("presence", "jabber:client") {
-                    let presence = Presence::try_from(elem).unwrap();
-                    let new_events = presence::receive::handle_presence(agent, presence).await;
-                    events.extend(new_events);
-                } else if elem.is("error", "http://etherx.jabber.org/streams") {
-                    println!("Received a fatal stream error: {}", String::from(&elem));
-                } else {
-                    panic!("Unknown stanza: {}", String::from(&elem));
-                }
+                let stanza = match Stanza::try_from(elem.clone()) {
+                    Ok(stanza) => stanza,
+                    Err(err) => {
+                        iq::handle_invalid(agent, &elem, err).await;
+                        return Some(events);
+                    }
+                };
+                let new_events = match stanza {
+                    Stanza::Iq(iq) => iq::handle_iq(agent, iq).await,
+                    Stanza::Message(message) => {
+                        message::receive::handle_message(agent, message, &elem).await
+                    }
+                    Stanza::Presence(presence) => {
+                        presence::receive::handle_presence(agent, presence).await
+                    }
+                    Stanza::Nonza(elem) => {
+                        panic!("Unknown stanza: {}", String::from(&elem));
+                    }
+                };
+                // Archived messages replayed on this stanza come from the stanzas they came in.
+                let replayed = agent.replayed_events.drain(..);
+                events.extend(replayed.map(|(event, source)| (event, Some(source))));
+                events.extend(
+                    new_events
+                        .into_iter()
+                        .map(|event| (event, Some(elem.clone()))),
+                );
             }
         }
 
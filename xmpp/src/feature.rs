@@ -10,4 +10,24 @@ pub enum ClientFeature {
     Avatars,
     ContactList,
     JoinRooms,
+    /// After reconnecting without resuming the stream, query the archives for the messages
+    /// missed in every conversation, replayed with
+    /// [StanzaTimeInfo::from_archive](crate::delay::StanzaTimeInfo::from_archive) set.
+    ArchiveCatchUp,
+    /// Advertise support for audio and video calls, and report incoming ones with
+    /// [Event::IncomingCall](crate::Event::IncomingCall).
+    Calls,
+    /// Advertise Stream Initiation File Transfer over In-Band Bytestreams, for older clients
+    /// without Jingle File Transfer, and report the files they offer with
+    /// [Event::FileOffered](crate::Event::FileOffered).
+    FileTransfer,
+    /// Advertise Roster Item Exchange, and report the contacts suggested by other entities, e.g.
+    /// gateways, with [Event::ContactsSuggested](crate::Event::ContactsSuggested).
+    RosterExchange,
+    /// Make a thumbnail of the images uploaded with
+    /// [Agent::upload_file_with](crate::Agent::upload_file_with), reported in
+    /// [UploadedFile::thumbnail](crate::upload::manager::UploadedFile::thumbnail) and shared with
+    /// Bits of Binary.
+    #[cfg(feature = "image")]
+    Thumbnails,
 }
@@ -0,0 +1,644 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Sending and receiving files with the legacy Stream Initiation File Transfer
+//! ([XEP-0095](https://xmpp.org/extensions/xep-0095.html),
+//! [XEP-0096](https://xmpp.org/extensions/xep-0096.html)), for older clients which don’t speak
+//! Jingle File Transfer.
+//!
+//! Files are carried over In-Band Bytestreams
+//! ([XEP-0047](https://xmpp.org/extensions/xep-0047.html)) in iqs, so each chunk gets acknowledged
+//! before the next one is sent.
+
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        ibb::{Close, Data, Open, Stanza, StreamId},
+        iq::{Iq, IqType},
+        ns,
+        si::{Profile, StreamInitiation, StreamMethod},
+        si_ft,
+        stanza_error::{DefinedCondition, StanzaError},
+    },
+    Element, FullJid, Jid,
+};
+
+use crate::{iq::pending::PendingIqs, Agent, Error, Event};
+
+/// The size of the chunks we send.
+const BLOCK_SIZE: u16 = 4096;
+
+/// Why a file transfer failed, see [Event::FileTransferFailed].
+#[derive(Debug)]
+pub enum TransferError {
+    /// The peer declined the offer, or refused a chunk.
+    Refused(StanzaError),
+    /// The peer accepted the offer with a stream method we didn’t offer.
+    InvalidAnswer,
+    /// The stream got closed before the whole file went through, with the number of bytes
+    /// received so far.
+    Incomplete(u64),
+    /// The peer sent the chunks out of order, or more data than announced.
+    InvalidChunk,
+    /// The file couldn’t be read or written.
+    Io(io::Error),
+    /// A new session got established, losing the transfers of the previous one.
+    Disconnected,
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransferError::Refused(error) => {
+                write!(fmt, "transfer refused: {:?}", error.defined_condition)
+            }
+            TransferError::InvalidAnswer => write!(fmt, "no supported stream method chosen"),
+            TransferError::Incomplete(received) => {
+                write!(fmt, "stream closed after {} bytes", received)
+            }
+            TransferError::InvalidChunk => write!(fmt, "invalid chunk"),
+            TransferError::Io(error) => write!(fmt, "I/O error: {}", error),
+            TransferError::Disconnected => write!(fmt, "disconnected"),
+        }
+    }
+}
+
+impl StdError for TransferError {}
+
+/// A file offered to us, waiting for the application to accept or decline it.
+struct Offer {
+    from: Jid,
+    /// The id of the offer, answered once the application decides.
+    id: String,
+    size: u64,
+}
+
+/// A file being received, once its offer got accepted.
+struct Incoming {
+    from: Jid,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    received: u64,
+    /// The sequence number of the next chunk.
+    seq: u16,
+}
+
+/// Where a file we send stands, waiting for the answer to the last iq sent.
+enum Step {
+    Offered,
+    Opening,
+    Sending,
+    Closing,
+}
+
+/// A file being sent.
+struct Outgoing {
+    to: FullJid,
+    file: File,
+    /// The sequence number of the next chunk.
+    seq: u16,
+    step: Step,
+}
+
+/// The file transfers in progress, by session id, which is also the id of their bytestream.
+pub(crate) struct FileTransfers {
+    pub(crate) enabled: bool,
+    offers: HashMap<String, Offer>,
+    incoming: HashMap<String, Incoming>,
+    outgoing: HashMap<String, Outgoing>,
+    /// Our iqs waiting for an answer, with the session id of their transfer.
+    pending: PendingIqs<String>,
+}
+
+/// A stanza error condition, along with the Stream Initiation one refining it.
+type Refusal = (DefinedCondition, Option<&'static str>);
+
+fn make_error((condition, si): Refusal) -> StanzaError {
+    let error = StanzaError::from_condition(condition);
+    match si {
+        Some(si) => error.with_other(Element::builder(si, ns::SI).build()),
+        None => error,
+    }
+}
+
+impl FileTransfers {
+    pub(crate) fn new(enabled: bool) -> FileTransfers {
+        FileTransfers {
+            enabled,
+            offers: HashMap::new(),
+            incoming: HashMap::new(),
+            outgoing: HashMap::new(),
+            pending: PendingIqs::default(),
+        }
+    }
+
+    /// Session ids must be unique between both parties, like those of calls.
+    fn make_sid() -> String {
+        let sid: u128 = thread_rng().gen();
+        format!("{:032x}", sid)
+    }
+
+    /// Keeps an offer for the application to answer, unless we can’t take it.
+    fn receive_offer(
+        &mut self,
+        from: Jid,
+        id: String,
+        si: StreamInitiation,
+    ) -> Result<Event, Refusal> {
+        if si.profile != Some(Profile::FileTransfer) {
+            return Err((DefinedCondition::BadRequest, Some("bad-profile")));
+        }
+        if !si.offered_methods().contains(&StreamMethod::Ibb) {
+            return Err((DefinedCondition::BadRequest, Some("no-valid-streams")));
+        }
+        let (Some(sid), Some(file)) = (si.id, si.file) else {
+            return Err((DefinedCondition::BadRequest, None));
+        };
+        if self.offers.contains_key(&sid) || self.incoming.contains_key(&sid) {
+            return Err((DefinedCondition::Conflict, None));
+        }
+        let offer = Offer {
+            from: from.clone(),
+            id,
+            size: file.size,
+        };
+        self.offers.insert(sid.clone(), offer);
+        Ok(Event::FileOffered(from, sid, file))
+    }
+
+    /// Handles an In-Band Bytestream iq of a file being received, with the event it ends the
+    /// transfer with, if any.
+    fn receive_ibb(
+        &mut self,
+        from: &Jid,
+        payload: Element,
+    ) -> (Result<(), StanzaError>, Option<Event>) {
+        let (sid, chunk) = if payload.is("open", ns::IBB) {
+            match Open::try_from(payload) {
+                Ok(open) if open.stanza != Stanza::Iq => {
+                    return (Err(StanzaError::not_acceptable()), None)
+                }
+                Ok(open) => (open.sid.0, None),
+                Err(err) => return (Err(bad_request(err)), None),
+            }
+        } else if payload.is("data", ns::IBB) {
+            match Data::try_from(payload) {
+                Ok(data) => (data.sid.0, Some((data.seq, data.data))),
+                Err(err) => return (Err(bad_request(err)), None),
+            }
+        } else {
+            match Close::try_from(payload) {
+                Ok(close) => return self.close(from, &close.sid.0),
+                Err(err) => return (Err(bad_request(err)), None),
+            }
+        };
+        let transfer = match self.incoming.get_mut(&sid) {
+            Some(transfer) if &transfer.from == from => transfer,
+            _ if chunk.is_none() => return (Err(StanzaError::not_acceptable()), None),
+            _ => return (Err(StanzaError::item_not_found()), None),
+        };
+        let Some((seq, data)) = chunk else {
+            return (Ok(()), None);
+        };
+        let received = transfer.received + data.len() as u64;
+        let result = if seq != transfer.seq || received > transfer.size {
+            Err((
+                StanzaError::unexpected_request(),
+                TransferError::InvalidChunk,
+            ))
+        } else {
+            transfer.file.write_all(&data).map_err(|err| {
+                let error = StanzaError::resource_constraint().with_text("en", err.to_string());
+                (error, TransferError::Io(err))
+            })
+        };
+        match result {
+            Ok(()) => {
+                transfer.received = received;
+                transfer.seq = seq.wrapping_add(1);
+                (Ok(()), None)
+            }
+            Err((error, reason)) => {
+                let transfer = self.incoming.remove(&sid).unwrap();
+                let event = Event::FileTransferFailed(transfer.from, sid, reason);
+                (Err(error), Some(event))
+            }
+        }
+    }
+
+    /// Ends a transfer once its peer closed the stream, complete or not.
+    fn close(&mut self, from: &Jid, sid: &str) -> (Result<(), StanzaError>, Option<Event>) {
+        match self.incoming.get(sid) {
+            Some(transfer) if &transfer.from == from => (),
+            _ => return (Err(StanzaError::item_not_found()), None),
+        }
+        let mut transfer = self.incoming.remove(sid).unwrap();
+        let sid = String::from(sid);
+        let event = if let Err(err) = transfer.file.flush() {
+            Event::FileTransferFailed(transfer.from, sid, TransferError::Io(err))
+        } else if transfer.received == transfer.size {
+            Event::FileReceived(transfer.from, sid, transfer.path)
+        } else {
+            let reason = TransferError::Incomplete(transfer.received);
+            Event::FileTransferFailed(transfer.from, sid, reason)
+        };
+        (Ok(()), Some(event))
+    }
+
+    /// Moves a file we send to its next step, once the peer answered the previous one, returning
+    /// the iq to send next, and the event ending the transfer, if any.
+    fn advance(
+        &mut self,
+        id: String,
+        sid: String,
+        answer: Option<Element>,
+    ) -> (Option<Iq>, Option<Event>) {
+        let Some(transfer) = self.outgoing.get_mut(&sid) else {
+            return (None, None);
+        };
+        let to = Jid::from(transfer.to.clone());
+        let payload: Element = match transfer.step {
+            Step::Offered => {
+                let chosen = answer
+                    .and_then(|answer| StreamInitiation::try_from(answer).ok())
+                    .and_then(|si| si.chosen_method());
+                if chosen != Some(StreamMethod::Ibb) {
+                    self.outgoing.remove(&sid);
+                    let reason = TransferError::InvalidAnswer;
+                    return (None, Some(Event::FileTransferFailed(to, sid, reason)));
+                }
+                transfer.step = Step::Opening;
+                Open {
+                    block_size: BLOCK_SIZE,
+                    sid: StreamId(sid.clone()),
+                    stanza: Stanza::Iq,
+                }
+                .into()
+            }
+            Step::Opening | Step::Sending => {
+                let mut data = vec![0; usize::from(BLOCK_SIZE)];
+                let close = Close {
+                    sid: StreamId(sid.clone()),
+                };
+                match transfer.file.read(&mut data) {
+                    Ok(0) => {
+                        transfer.step = Step::Closing;
+                        close.into()
+                    }
+                    Ok(read) => {
+                        data.truncate(read);
+                        let seq = transfer.seq;
+                        transfer.seq = seq.wrapping_add(1);
+                        transfer.step = Step::Sending;
+                        Data {
+                            seq,
+                            sid: StreamId(sid.clone()),
+                            data,
+                        }
+                        .into()
+                    }
+                    Err(err) => {
+                        let transfer = self.outgoing.remove(&sid).unwrap();
+                        let iq = Iq::builder().to(transfer.to.into()).id(id).set(close);
+                        let event = Event::FileTransferFailed(to, sid, TransferError::Io(err));
+                        // Closes the stream after a local failure.
+                        return (Some(iq), Some(event));
+                    }
+                }
+            }
+            Step::Closing => {
+                self.outgoing.remove(&sid);
+                return (None, Some(Event::FileSent(to, sid)));
+            }
+        };
+        self.pending.insert(id.clone(), to.clone(), sid);
+        let iq = Iq {
+            from: None,
+            to: Some(to),
+            id,
+            payload: IqType::Set(payload),
+        };
+        (Some(iq), None)
+    }
+
+    /// Forgets every transfer, whose peer won’t answer in the new session.
+    fn reset(&mut self) -> Vec<Event> {
+        self.pending.clear();
+        self.offers.clear();
+        let incoming = self
+            .incoming
+            .drain()
+            .map(|(sid, transfer)| (transfer.from, sid));
+        let outgoing = self
+            .outgoing
+            .drain()
+            .map(|(sid, transfer)| (Jid::from(transfer.to), sid));
+        incoming
+            .chain(outgoing)
+            .map(|(jid, sid)| Event::FileTransferFailed(jid, sid, TransferError::Disconnected))
+            .collect()
+    }
+}
+
+fn bad_request(err: impl ToString) -> StanzaError {
+    StanzaError::bad_request().with_text("en", err.to_string())
+}
+
+/// Offers this file to this resource, returning the session id its events will carry. Fails if
+/// the file can’t be read, or with [Error::InvalidState] while offline.
+pub async fn send_file<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: FullJid,
+    path: &Path,
+) -> Result<String, Error> {
+    if agent.client.bound_jid().is_none() {
+        return Err(Error::InvalidState);
+    }
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return Err(io::Error::from(io::ErrorKind::InvalidInput).into()),
+    };
+    let sid = FileTransfers::make_sid();
+    let offer = StreamInitiation::offer_file(
+        sid.clone(),
+        si_ft::File::new(name, size),
+        &[StreamMethod::Ibb],
+    );
+    let id = agent.client.make_id();
+    let iq = Iq::builder()
+        .to(to.clone().into())
+        .id(id.clone())
+        .set(offer);
+    let transfer = Outgoing {
+        to: to.clone(),
+        file,
+        seq: 0,
+        step: Step::Offered,
+    };
+    agent.file_transfers.outgoing.insert(sid.clone(), transfer);
+    agent
+        .file_transfers
+        .pending
+        .insert(id, to.into(), sid.clone());
+    agent.client.send_stanza(iq.into()).await?;
+    Ok(sid)
+}
+
+/// Accepts an offer reported by [Event::FileOffered], saving the file at this path. Fails if the
+/// file can’t be created, or with [Error::InvalidState] if nothing waits for an answer with this
+/// session id.
+pub async fn accept<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    sid: &str,
+    path: PathBuf,
+) -> Result<(), Error> {
+    if !agent.file_transfers.offers.contains_key(sid) {
+        return Err(Error::InvalidState);
+    }
+    let file = File::create(&path)?;
+    let offer = agent.file_transfers.offers.remove(sid).unwrap();
+    let transfer = Incoming {
+        from: offer.from.clone(),
+        path,
+        file,
+        size: offer.size,
+        received: 0,
+        seq: 0,
+    };
+    agent
+        .file_transfers
+        .incoming
+        .insert(String::from(sid), transfer);
+    let answer = StreamInitiation::accept(StreamMethod::Ibb);
+    let iq = Iq::from_result(offer.id, Some(answer)).with_to(offer.from);
+    agent.client.send_stanza(iq.into()).await
+}
+
+/// Declines an offer reported by [Event::FileOffered]. Fails with [Error::InvalidState] if nothing
+/// waits for an answer with this session id.
+pub async fn reject<C: ServerConnector>(agent: &mut Agent<C>, sid: &str) -> Result<(), Error> {
+    let Some(offer) = agent.file_transfers.offers.remove(sid) else {
+        return Err(Error::InvalidState);
+    };
+    let error = StanzaError::forbidden().with_text("en", "Offer Declined");
+    let iq = Iq::from_error(offer.id, error).with_to(offer.from);
+    agent.client.send_stanza(iq.into()).await
+}
+
+pub(crate) async fn handle_offer<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    si: StreamInitiation,
+) -> Vec<Event> {
+    match agent
+        .file_transfers
+        .receive_offer(from.clone(), id.clone(), si)
+    {
+        Ok(event) => vec![event],
+        Err(refusal) => {
+            crate::iq::send_error(agent, from, id, make_error(refusal)).await;
+            vec![]
+        }
+    }
+}
+
+pub(crate) async fn handle_ibb<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    payload: Element,
+) -> Vec<Event> {
+    let (answer, event) = agent.file_transfers.receive_ibb(&from, payload);
+    match answer {
+        Ok(()) => {
+            let iq = Iq::empty_result(from, id);
+            let _ = agent.client.send_stanza(iq.into()).await;
+        }
+        Err(error) => crate::iq::send_error(agent, from, id, error).await,
+    }
+    event.into_iter().collect()
+}
+
+pub(crate) async fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    answer: Option<Element>,
+) -> Vec<Event> {
+    let Some(sid) = agent.file_transfers.pending.take(from, id) else {
+        return vec![];
+    };
+    let id = agent.client.make_id();
+    let (iq, event) = agent.file_transfers.advance(id, sid, answer);
+    if let Some(iq) = iq {
+        let _ = agent.client.send_stanza(iq.into()).await;
+    }
+    event.into_iter().collect()
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let sid = agent.file_transfers.pending.take(from, id)?;
+    let transfer = agent.file_transfers.outgoing.remove(&sid)?;
+    let reason = TransferError::Refused(error.take()?);
+    Some(Event::FileTransferFailed(transfer.to.into(), sid, reason))
+}
+
+/// Fails every transfer once a new session got established.
+pub(crate) fn reset<C: ServerConnector>(agent: &mut Agent<C>) -> Vec<Event> {
+    agent.file_transfers.reset()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("xmpp-rs-{}-{}", name, std::process::id()))
+    }
+
+    fn offer(sid: &str, size: u64, methods: &[StreamMethod]) -> StreamInitiation {
+        StreamInitiation::offer_file(
+            String::from(sid),
+            si_ft::File::new("test.txt", size),
+            methods,
+        )
+    }
+
+    #[test]
+    fn refused_offers() {
+        let romeo = Jid::new("romeo@montague.lit/orchard").unwrap();
+        let mut transfers = FileTransfers::new(true);
+
+        let si = offer("a", 3, &[StreamMethod::Bytestreams]);
+        let error = transfers
+            .receive_offer(romeo.clone(), String::from("1"), si)
+            .unwrap_err();
+        assert_eq!(error.1, Some("no-valid-streams"));
+
+        let mut si = offer("a", 3, &[StreamMethod::Ibb]);
+        si.profile = Some(Profile::Other(String::from("urn:example:tunnel")));
+        let error = transfers
+            .receive_offer(romeo.clone(), String::from("2"), si)
+            .unwrap_err();
+        assert_eq!(error.1, Some("bad-profile"));
+
+        let si = offer("a", 3, &[StreamMethod::Bytestreams, StreamMethod::Ibb]);
+        let event = transfers.receive_offer(romeo.clone(), String::from("3"), si);
+        assert!(matches!(event, Ok(Event::FileOffered(_, ref sid, _)) if sid == "a"));
+        let si = offer("a", 3, &[StreamMethod::Ibb]);
+        assert!(transfers
+            .receive_offer(romeo, String::from("4"), si)
+            .is_err());
+    }
+
+    fn ibb(xml: &str) -> Element {
+        xml.parse().unwrap()
+    }
+
+    #[test]
+    fn receive() {
+        let romeo = Jid::new("romeo@montague.lit/orchard").unwrap();
+        let path = temporary_path("si-receive");
+        let mut transfers = FileTransfers::new(true);
+        let si = offer("a", 6, &[StreamMethod::Ibb]);
+        transfers
+            .receive_offer(romeo.clone(), String::from("1"), si)
+            .unwrap();
+        let offer = transfers.offers.remove("a").unwrap();
+        let transfer = Incoming {
+            from: offer.from,
+            path: path.clone(),
+            file: File::create(&path).unwrap(),
+            size: offer.size,
+            received: 0,
+            seq: 0,
+        };
+        transfers.incoming.insert(String::from("a"), transfer);
+
+        let open = ibb("<open xmlns='http://jabber.org/protocol/ibb' block-size='3' sid='a'/>");
+        let stranger = Jid::new("tybalt@capulet.lit/sword").unwrap();
+        assert!(transfers.receive_ibb(&stranger, open.clone()).0.is_err());
+        assert!(transfers.receive_ibb(&romeo, open).0.is_ok());
+
+        for (seq, data) in [(0, "Zm9v"), (1, "YmFy")] {
+            let data = ibb(&format!(
+                "<data xmlns='http://jabber.org/protocol/ibb' seq='{}' sid='a'>{}</data>",
+                seq, data
+            ));
+            assert!(matches!(
+                transfers.receive_ibb(&romeo, data),
+                (Ok(()), None)
+            ));
+        }
+
+        let close = ibb("<close xmlns='http://jabber.org/protocol/ibb' sid='a'/>");
+        match transfers.receive_ibb(&romeo, close) {
+            (Ok(()), Some(Event::FileReceived(from, sid, saved))) => {
+                assert_eq!((from, sid, saved), (romeo, String::from("a"), path.clone()));
+            }
+            _ => panic!(),
+        }
+        assert_eq!(std::fs::read(&path).unwrap(), b"foobar");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn send() {
+        let juliet = FullJid::new("juliet@capulet.lit/balcony").unwrap();
+        let path = temporary_path("si-send");
+        std::fs::write(&path, vec![1; usize::from(BLOCK_SIZE) + 1]).unwrap();
+        let mut transfers = FileTransfers::new(true);
+        let transfer = Outgoing {
+            to: juliet.clone(),
+            file: File::open(&path).unwrap(),
+            seq: 0,
+            step: Step::Offered,
+        };
+        transfers.outgoing.insert(String::from("a"), transfer);
+
+        let mut next = |answer: Option<Element>| match transfers.advance(
+            String::from("id"),
+            String::from("a"),
+            answer,
+        ) {
+            (Some(iq), None) => Some(Element::from(iq)),
+            (None, Some(Event::FileSent(to, sid))) => {
+                assert_eq!((to, sid), (Jid::from(juliet.clone()), String::from("a")));
+                None
+            }
+            _ => panic!(),
+        };
+        let accept = StreamInitiation::accept(StreamMethod::Ibb).into();
+        let open = next(Some(accept)).unwrap();
+        assert!(open.has_child("open", ns::IBB));
+        let mut sizes = Vec::new();
+        while let Some(iq) = next(None) {
+            if let Some(data) = iq.get_child("data", ns::IBB) {
+                sizes.push(Data::try_from(data.clone()).unwrap().data.len());
+            } else {
+                assert!(iq.has_child("close", ns::IBB));
+            }
+        }
+        assert_eq!(sizes, [usize::from(BLOCK_SIZE), 1]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
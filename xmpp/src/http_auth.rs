@@ -0,0 +1,190 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Confirming that the user made an HTTP request, e.g. to log into a website with their JID,
+//! with Verifying HTTP Requests via XMPP ([XEP-0070](https://xmpp.org/extensions/xep-0070.html)).
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::rt::Instant;
+use tokio_xmpp::{
+    parsers::{
+        http_auth::Confirm,
+        iq::Iq,
+        message::{Message, Thread},
+        stanza_error::StanzaError,
+    },
+    Jid,
+};
+
+use crate::{Agent, Event};
+
+/// How a confirmation got requested, and thus how to answer it.
+enum Via {
+    /// An iq, with this id.
+    Iq(String),
+    /// A message, with this thread.
+    Message(Option<Thread>),
+}
+
+/// How many confirmation requests may wait for the user, the oldest ones getting dropped first.
+const MAX_PENDING: usize = 64;
+
+/// How long a confirmation request waits for the user, after which it can’t be answered anymore.
+const EXPIRY: Duration = Duration::from_secs(600);
+
+/// Confirmation requests waiting for the user, by requester and transaction id, along with when
+/// they got received.
+#[derive(Default)]
+pub(crate) struct HttpRequests {
+    pending: HashMap<(Jid, String), (Confirm, Via, Instant)>,
+}
+
+impl HttpRequests {
+    fn insert(&mut self, from: Jid, confirm: Confirm, via: Via, now: Instant) -> Event {
+        self.pending
+            .retain(|_, (_, _, received)| now.duration_since(*received) < EXPIRY);
+        let key = (from.clone(), confirm.id.clone());
+        if !self.pending.contains_key(&key) && self.pending.len() >= MAX_PENDING {
+            let oldest = self
+                .pending
+                .iter()
+                .min_by_key(|(_, (_, _, received))| *received)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                warn!("Too many HTTP requests to confirm, dropping the oldest one.");
+                self.pending.remove(&oldest);
+            }
+        }
+        self.pending.insert(key, (confirm.clone(), via, now));
+        Event::HttpRequestConfirmation(from, confirm)
+    }
+
+    fn take(&mut self, from: Jid, id: &str, now: Instant) -> Option<(Jid, Confirm, Via)> {
+        let key = (from, String::from(id));
+        let (confirm, via, received) = self.pending.remove(&key)?;
+        if now.duration_since(received) >= EXPIRY {
+            return None;
+        }
+        Some((key.0, confirm, via))
+    }
+}
+
+pub(crate) fn handle_iq<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    confirm: Confirm,
+) -> Event {
+    agent
+        .http_requests
+        .insert(from, confirm, Via::Iq(id), Instant::now())
+}
+
+pub(crate) fn handle_message<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    thread: Option<Thread>,
+    confirm: Confirm,
+) -> Event {
+    agent
+        .http_requests
+        .insert(from, confirm, Via::Message(thread), Instant::now())
+}
+
+/// Answers the confirmation request of this transaction id, confirming the request if `accept`
+/// or denying it with not-authorized otherwise. Does nothing if no such request is pending, or
+/// if it waited for too long.
+pub(crate) async fn answer<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: &str,
+    accept: bool,
+) {
+    let Some((from, confirm, via)) = agent.http_requests.take(from, id, Instant::now()) else {
+        return;
+    };
+    let stanza = match via {
+        Via::Iq(id) if accept => Iq::from_result(id, Some(confirm)).with_to(from).into(),
+        Via::Iq(id) => Iq::from_error(id, StanzaError::not_authorized())
+            .with_to(from)
+            .into(),
+        Via::Message(thread) => {
+            let mut message = if accept {
+                Message::new(from).with_payload(confirm)
+            } else {
+                Message::error(from)
+                    .with_payload(confirm)
+                    .with_payload(StanzaError::not_authorized())
+            };
+            message.thread = thread;
+            message.into()
+        }
+    };
+    let _ = agent.client.send_stanza(stanza).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending() {
+        let server = Jid::new("files.shakespeare.lit").unwrap();
+        let other = Jid::new("romeo@montague.lit").unwrap();
+        let confirm = Confirm {
+            id: String::from("a7374jnjlalasdf"),
+            method: String::from("GET"),
+            url: String::from("https://files.shakespeare.lit:9345/missive.html"),
+        };
+        let mut requests = HttpRequests::default();
+        let now = Instant::now();
+        match requests.insert(
+            server.clone(),
+            confirm.clone(),
+            Via::Iq(String::from("ha000")),
+            now,
+        ) {
+            Event::HttpRequestConfirmation(jid, event) => {
+                assert_eq!(jid, server);
+                assert_eq!(event, confirm);
+            }
+            _ => panic!(),
+        }
+
+        assert!(requests.take(other, &confirm.id, now).is_none());
+        match requests.take(server.clone(), &confirm.id, now) {
+            Some((_, _, Via::Iq(id))) => assert_eq!(id, "ha000"),
+            _ => panic!(),
+        }
+        assert!(requests.take(server, &confirm.id, now).is_none());
+    }
+
+    #[test]
+    fn bounded() {
+        let server = Jid::new("files.shakespeare.lit").unwrap();
+        let confirm = |id: usize| Confirm {
+            id: id.to_string(),
+            method: String::from("GET"),
+            url: String::from("https://files.shakespeare.lit:9345/missive.html"),
+        };
+        let mut requests = HttpRequests::default();
+        let now = Instant::now();
+        for id in 0..=MAX_PENDING {
+            let received = now + Duration::from_secs(id as u64);
+            requests.insert(server.clone(), confirm(id), Via::Message(None), received);
+        }
+        assert_eq!(requests.pending.len(), MAX_PENDING);
+        assert!(requests.take(server.clone(), "0", now).is_none());
+        assert!(requests.take(server.clone(), "1", now).is_some());
+
+        let later = now + EXPIRY + Duration::from_secs(MAX_PENDING as u64);
+        assert!(requests.take(server.clone(), "2", later).is_none());
+        requests.insert(server, confirm(0), Via::Message(None), later);
+        assert_eq!(requests.pending.len(), 1);
+    }
+}
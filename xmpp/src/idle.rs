@@ -0,0 +1,278 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Telling the server and contacts when the user is away, from the inactivity reported by the
+//! application: Client State Indication
+//! ([XEP-0352](https://xmpp.org/extensions/xep-0352.html)), and optionally an away presence with
+//! the time of the last interaction ([XEP-0319](https://xmpp.org/extensions/xep-0319.html)).
+//! The same way, the idle time of contacts is reported from their presences.
+
+use chrono::{Duration, Utc};
+use std::collections::HashSet;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::parsers::{
+    csi::{Active, Inactive},
+    date::DateTime,
+    delay::Delay,
+    idle::Idle,
+    last_activity::LastActivity,
+    ns,
+    presence::{Presence, Show, Type as PresenceType},
+};
+use tokio_xmpp::Jid;
+
+use crate::{
+    presence::send::{broadcast_presence, make_initial_presence},
+    Agent, Event,
+};
+
+/// The presence sent while the user is inactive, see
+/// [`ClientBuilder::set_auto_away`](crate::ClientBuilder::set_auto_away).
+#[derive(Debug, Clone)]
+pub struct AutoAway {
+    /// Usually [`Show::Away`] or [`Show::Xa`].
+    pub show: Show,
+    /// A priority lower than the usual 0, so that messages go to active clients first.
+    pub priority: i8,
+    /// A status message, in English.
+    pub status: Option<String>,
+}
+
+impl Default for AutoAway {
+    fn default() -> Self {
+        AutoAway {
+            show: Show::Away,
+            priority: -1,
+            status: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct IdleState {
+    pub(crate) auto_away: Option<AutoAway>,
+    pub(crate) inactive_since: Option<DateTime>,
+    /// The resources of contacts last reported idle.
+    idle_contacts: HashSet<Jid>,
+}
+
+impl IdleState {
+    pub(crate) fn new(auto_away: Option<AutoAway>) -> IdleState {
+        IdleState {
+            auto_away,
+            inactive_since: None,
+            idle_contacts: HashSet::new(),
+        }
+    }
+
+    fn make_away_presence(&self, mut presence: Presence) -> Option<Presence> {
+        let (auto_away, since) = (self.auto_away.as_ref()?, self.inactive_since.clone()?);
+        presence.show = Some(auto_away.show.clone());
+        presence.priority = auto_away.priority;
+        if let Some(ref status) = auto_away.status {
+            presence.set_status("en", status.as_str());
+        }
+        Some(presence.with_payload(Idle { since }))
+    }
+}
+
+/// The presence to broadcast now: the away one while inactive, if any, else the usual one.
+pub(crate) fn make_presence<C: ServerConnector>(agent: &Agent<C>) -> Presence {
+    let presence = make_initial_presence(agent);
+    agent
+        .idle
+        .make_away_presence(presence.clone())
+        .unwrap_or(presence)
+}
+
+fn supports_csi<C: ServerConnector>(agent: &Agent<C>) -> bool {
+    agent.client.get_stream_features().is_some_and(|features| {
+        features
+            .others
+            .iter()
+            .any(|feature| feature.is("csi", ns::CSI))
+    })
+}
+
+/// Marks the user inactive since this time, if they weren’t already.
+pub(crate) async fn report_inactivity<C: ServerConnector>(agent: &mut Agent<C>, since: DateTime) {
+    if agent.idle.inactive_since.is_some() {
+        return;
+    }
+    agent.idle.inactive_since = Some(since);
+    send_state(agent).await;
+}
+
+/// Marks the user active again, restoring the usual presence.
+pub(crate) async fn report_activity<C: ServerConnector>(agent: &mut Agent<C>) {
+    if agent.idle.inactive_since.take().is_none() {
+        return;
+    }
+    if supports_csi(agent) {
+        let _ = agent.client.send_stanza(Active.into()).await;
+    }
+    if agent.idle.auto_away.is_some() {
+        let presence = make_initial_presence(agent);
+        broadcast_presence(agent, presence).await;
+    }
+}
+
+/// Sends the inactive state and away presence, also after a new connection where the server
+/// considers the client active.
+pub(crate) async fn send_state<C: ServerConnector>(agent: &mut Agent<C>) {
+    if agent.idle.inactive_since.is_none() {
+        return;
+    }
+    if supports_csi(agent) {
+        let _ = agent.client.send_stanza(Inactive.into()).await;
+    }
+    let presence = make_initial_presence(agent);
+    if let Some(presence) = agent.idle.make_away_presence(presence) {
+        broadcast_presence(agent, presence).await;
+    }
+}
+
+/// The time since which a presence reports its sender idle: the last interaction of XEP-0319, or
+/// else the seconds of inactivity of [XEP-0256](https://xmpp.org/extensions/xep-0256.html),
+/// counted from when the presence was sent.
+fn idle_since(presence: &Presence, received: DateTime) -> Option<DateTime> {
+    if let Some(Ok(idle)) = presence.get_payload::<Idle>() {
+        return Some(idle.since);
+    }
+    let seconds = presence.get_payload::<LastActivity>()?.ok()?.seconds?;
+    let sent = match presence.get_payload::<Delay>() {
+        Some(Ok(delay)) => delay.stamp,
+        _ => received,
+    };
+    let seconds = Duration::try_seconds(seconds.try_into().ok()?)?;
+    Some(DateTime(sent.0.checked_sub_signed(seconds)?))
+}
+
+impl IdleState {
+    /// Reports the idle time of a contact resource, or that it is back after having been idle.
+    fn handle_presence(
+        &mut self,
+        from: &Jid,
+        presence: &Presence,
+        received: DateTime,
+    ) -> Option<Event> {
+        match presence.type_ {
+            PresenceType::None => (),
+            PresenceType::Unavailable => {
+                self.idle_contacts.remove(from);
+                return None;
+            }
+            _ => return None,
+        }
+        match idle_since(presence, received) {
+            Some(since) => {
+                self.idle_contacts.insert(from.clone());
+                Some(Event::ContactIdle(from.clone(), since))
+            }
+            None if self.idle_contacts.remove(from) => Some(Event::ContactActive(from.clone())),
+            None => None,
+        }
+    }
+}
+
+/// Reports the idle time of a contact from its presence, see [`Event::ContactIdle`].
+pub(crate) fn handle_presence<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    presence: &Presence,
+) -> Option<Event> {
+    let received = DateTime(Utc::now().into());
+    agent.idle.handle_presence(from, presence, received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::Element;
+
+    #[test]
+    fn away_presence() {
+        let since: DateTime = "2023-11-05T08:15:00Z".parse().unwrap();
+        let mut state = IdleState::new(None);
+        state.inactive_since = Some(since.clone());
+        assert!(state
+            .make_away_presence(Presence::new(PresenceType::None))
+            .is_none());
+
+        let mut state = IdleState::new(Some(AutoAway {
+            show: Show::Xa,
+            priority: -5,
+            status: Some(String::from("Gone fishing")),
+        }));
+        assert!(state
+            .make_away_presence(Presence::new(PresenceType::None))
+            .is_none());
+        state.inactive_since = Some(since.clone());
+        let presence = state
+            .make_away_presence(Presence::new(PresenceType::None))
+            .unwrap();
+        assert_eq!(presence.show, Some(Show::Xa));
+        assert_eq!(presence.priority, -5);
+        assert_eq!(presence.statuses["en"], "Gone fishing");
+        let idle = presence
+            .payloads
+            .iter()
+            .find(|payload| payload.is("idle", ns::IDLE))
+            .cloned()
+            .map(Idle::try_from)
+            .unwrap()
+            .unwrap();
+        assert_eq!(idle.since, since);
+    }
+
+    #[test]
+    fn contacts() {
+        let received: DateTime = "2023-11-05T08:15:00Z".parse().unwrap();
+        let juliet = Jid::new("juliet@capulet.lit/balcony").unwrap();
+        let presence = |xml: &str| Presence::try_from(xml.parse::<Element>().unwrap()).unwrap();
+        let mut state = IdleState::new(None);
+
+        let event = state.handle_presence(
+            &juliet,
+            &presence("<presence xmlns='jabber:client'><idle xmlns='urn:xmpp:idle:1' since='2023-11-05T07:00:00Z'/></presence>"),
+            received.clone(),
+        );
+        match event {
+            Some(Event::ContactIdle(jid, since)) => {
+                assert_eq!(jid, juliet);
+                assert_eq!(since, "2023-11-05T07:00:00Z".parse().unwrap());
+            }
+            _ => panic!(),
+        }
+
+        // XEP-0256, counted from the delay if the presence got stored.
+        let event = state.handle_presence(
+            &juliet,
+            &presence(
+                "<presence xmlns='jabber:client'>
+                <query xmlns='jabber:iq:last' seconds='600'/>
+                <delay xmlns='urn:xmpp:delay' stamp='2023-11-05T08:00:00Z'/>
+              </presence>",
+            ),
+            received.clone(),
+        );
+        match event {
+            Some(Event::ContactIdle(_, since)) => {
+                assert_eq!(since, "2023-11-05T07:50:00Z".parse().unwrap())
+            }
+            _ => panic!(),
+        }
+
+        let available = presence("<presence xmlns='jabber:client'/>");
+        assert!(matches!(
+            state.handle_presence(&juliet, &available, received.clone()),
+            Some(Event::ContactActive(_))
+        ));
+        assert!(state
+            .handle_presence(&juliet, &available, received)
+            .is_none());
+    }
+}
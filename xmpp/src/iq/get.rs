@@ -7,10 +7,11 @@
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
     parsers::{
-        disco::DiscoInfoQuery,
+        bob::Data,
+        disco::{DiscoInfoQuery, DiscoItemsQuery},
         iq::Iq,
         ns,
-        stanza_error::{DefinedCondition, ErrorType, StanzaError},
+        stanza_error::StanzaError,
     },
     Element, Jid,
 };
@@ -35,25 +36,39 @@ pub async fn handle_iq_get<C: ServerConnector>(
                 let _ = agent.client.send_stanza(iq).await;
             }
             Err(err) => {
-                let error = StanzaError::new(
-                    ErrorType::Modify,
-                    DefinedCondition::BadRequest,
-                    "en",
-                    &format!("{}", err),
-                );
-                let iq = Iq::from_error(id, error).with_to(from).into();
+                let error = StanzaError::bad_request().with_text("en", err.to_string());
+                super::send_error(agent, from, id, error).await;
+            }
+        }
+    } else if payload.is("query", ns::DISCO_ITEMS) {
+        let query = match DiscoItemsQuery::try_from(payload) {
+            Ok(query) => query,
+            Err(err) => {
+                let error = StanzaError::bad_request().with_text("en", err.to_string());
+                super::send_error(agent, from, id, error).await;
+                return;
+            }
+        };
+        match agent.disco_items.answer(query) {
+            Some(items) => {
+                let iq = Iq::from_result(id, Some(items)).with_to(from).into();
                 let _ = agent.client.send_stanza(iq).await;
             }
+            None => {
+                let error = StanzaError::item_not_found();
+                super::send_error(agent, from, id, error).await;
+            }
+        }
+    } else if payload.is("data", ns::BOB) {
+        match Data::try_from(payload) {
+            Ok(request) => crate::bob::handle_get(agent, from, id, request).await,
+            Err(err) => {
+                let error = StanzaError::bad_request().with_text("en", err.to_string());
+                super::send_error(agent, from, id, error).await;
+            }
         }
     } else {
-        // We MUST answer unhandled get iqs with a service-unavailable error.
-        let error = StanzaError::new(
-            ErrorType::Cancel,
-            DefinedCondition::ServiceUnavailable,
-            "en",
-            "No handler defined for this kind of iq.",
-        );
-        let iq = Iq::from_error(id, error).with_to(from).into();
-        let _ = agent.client.send_stanza(iq).await;
+        let error = super::unhandled_error(&agent.disco, &payload);
+        super::send_error(agent, from, id, error).await;
     }
 }
@@ -5,11 +5,21 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use tokio_xmpp::connect::ServerConnector;
-use tokio_xmpp::parsers::iq::{Iq, IqType};
+use tokio_xmpp::{
+    parsers::{
+        disco::DiscoInfoResult,
+        iq::{Iq, IqType},
+        ns,
+        stanza_error::StanzaError,
+        Error as ParseError,
+    },
+    Element, Jid,
+};
 
-use crate::{Agent, Event};
+use crate::{blocking, bob, call, disco, file_transfer, mam, muc, private, upload, Agent, Event};
 
 pub mod get;
+pub(crate) mod pending;
 pub mod result;
 pub mod set;
 
@@ -23,8 +33,129 @@ pub async fn handle_iq<C: ServerConnector>(agent: &mut Agent<C>, iq: Iq) -> Vec<
         get::handle_iq_get(agent, &mut events, from, iq.to, iq.id, payload).await;
     } else if let IqType::Result(Some(payload)) = iq.payload {
         result::handle_iq_result(agent, &mut events, from, iq.to, iq.id, payload).await;
+    } else if let IqType::Result(None) = iq.payload {
+        events.extend(muc::register::handle_result(agent, &from, &iq.id, None));
+        events.extend(private::handle_result(agent, &from, &iq.id));
+        events.extend(blocking::handle_result(agent, &from, &iq.id));
+        events.extend(file_transfer::handle_result(agent, &from, &iq.id, None).await);
+        #[cfg(feature = "avatars")]
+        events.extend(crate::pubsub::avatar::handle_vcard_result(
+            agent, &from, &iq.id, None,
+        ));
     } else if let IqType::Set(payload) = iq.payload {
         set::handle_iq_set(agent, &mut events, from, iq.to, iq.id, payload).await;
+    } else if let IqType::Error(error) = iq.payload {
+        mam::handle_error(agent, &from, &iq.id);
+        events.extend(call::handle_error(agent, &iq.id));
+        #[cfg(feature = "avatars")]
+        crate::pubsub::avatar::handle_vcard_error(agent, &from, &iq.id);
+        events.extend(handle_error(agent, &from, &iq.id, error));
     }
     events
 }
+
+/// Hands an error to the module which sent the iq of this id, ids being unique: only that one
+/// takes the error, instead of each getting a copy.
+fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: StanzaError,
+) -> Option<Event> {
+    let mut error = Some(error);
+    let event = disco::browse::handle_error(agent, from, id, &mut error)
+        .or_else(|| muc::history::handle_error(agent, from, id, &mut error))
+        .or_else(|| muc::register::handle_error(agent, from, id, &mut error))
+        .or_else(|| private::handle_error(agent, from, id, &mut error))
+        .or_else(|| blocking::handle_error(agent, from, id, &mut error))
+        .or_else(|| bob::handle_error(agent, from, id, &mut error))
+        .or_else(|| file_transfer::handle_error(agent, from, id, &mut error));
+    if let Some(error) = error {
+        upload::receive::handle_upload_error(from, id, error, agent);
+    }
+    event
+}
+
+/// Answers a received stanza which failed to parse with a bad-request error if it is a get or
+/// set iq; anything else gets dropped.
+pub(crate) async fn handle_invalid<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    elem: &Element,
+    err: ParseError,
+) {
+    let answerable =
+        elem.is("iq", ns::DEFAULT_NS) && matches!(elem.attr("type"), Some("get") | Some("set"));
+    let id = match elem.attr("id") {
+        Some(id) if answerable => id.to_owned(),
+        _ => {
+            warn!("Dropping invalid stanza: {}", err);
+            return;
+        }
+    };
+    let from = match elem.attr("from").map(Jid::new) {
+        Some(Ok(from)) => from,
+        Some(Err(_)) => {
+            warn!("Dropping invalid iq with an invalid from: {}", err);
+            return;
+        }
+        None => agent.client.bound_jid().unwrap().to_bare().into(),
+    };
+    let error = StanzaError::bad_request().with_text("en", err.to_string());
+    send_error(agent, from, id, error).await;
+}
+
+/// The error answering a get or set iq no handler claimed, which we MUST answer per
+/// [RFC 6120 §8.4](https://xmpp.org/rfcs/rfc6120.html#stanzas-semantics-iq): feature-not-implemented
+/// if we advertise the namespace of its payload, service-unavailable otherwise.
+pub(crate) fn unhandled_error(disco: &DiscoInfoResult, payload: &Element) -> StanzaError {
+    let ns = payload.ns();
+    let error = if disco.features.iter().any(|feature| feature.var == ns) {
+        StanzaError::feature_not_implemented()
+    } else {
+        StanzaError::service_unavailable()
+    };
+    error.with_text("en", "No handler defined for this kind of iq.")
+}
+
+/// Answers a get or set iq with this error.
+pub(crate) async fn send_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    to: Jid,
+    id: String,
+    error: StanzaError,
+) {
+    let iq = Iq::from_error(id, error).with_to(to).into();
+    let _ = agent.client.send_stanza(iq).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::parsers::{
+        disco::{Feature, Identity},
+        ns,
+        stanza_error::DefinedCondition,
+    };
+
+    #[test]
+    fn unhandled() {
+        let disco = DiscoInfoResult {
+            node: None,
+            identities: vec![Identity::new("client", "pc", "en", "xmpp-rs")],
+            features: vec![Feature::new(ns::DISCO_INFO), Feature::new(ns::JINGLE)],
+            extensions: vec![],
+        };
+        let jingle = Element::builder("jingle", ns::JINGLE).build();
+        let error = unhandled_error(&disco, &jingle);
+        assert_eq!(
+            error.defined_condition,
+            DefinedCondition::FeatureNotImplemented
+        );
+        let version = Element::builder("query", ns::VERSION).build();
+        let error = unhandled_error(&disco, &version);
+        assert_eq!(
+            error.defined_condition,
+            DefinedCondition::ServiceUnavailable
+        );
+    }
+}
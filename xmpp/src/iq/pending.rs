@@ -0,0 +1,88 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+use tokio_xmpp::Jid;
+
+/// Iqs we sent and wait for the answer of, with the entity each one got sent to and what to do
+/// with its answer.
+///
+/// Ids come from `Client::make_id()`, and answers are only accepted from the entity asked, so
+/// nobody else can answer in its stead. Iqs to our own account get their answer from our bare
+/// JID, which `handle_iq` substitutes for a missing from.
+pub(crate) struct PendingIqs<T> {
+    pending: HashMap<String, (Jid, T)>,
+}
+
+impl<T> Default for PendingIqs<T> {
+    fn default() -> Self {
+        PendingIqs {
+            pending: HashMap::new(),
+        }
+    }
+}
+
+impl<T> PendingIqs<T> {
+    /// Waits for the answer to the iq of this id, sent to `to`.
+    pub(crate) fn insert(&mut self, id: String, to: Jid, context: T) {
+        self.pending.insert(id, (to, context));
+    }
+
+    pub(crate) fn contains(&self, id: &str) -> bool {
+        self.pending.contains_key(id)
+    }
+
+    /// Returns the context of an iq, if this answer comes from the entity asked.
+    pub(crate) fn get(&self, from: &Jid, id: &str) -> Option<&T> {
+        match self.pending.get(id) {
+            Some((to, context)) if to == from => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Like `get`, to update the context with a partial answer.
+    pub(crate) fn get_mut(&mut self, from: &Jid, id: &str) -> Option<&mut T> {
+        match self.pending.get_mut(id) {
+            Some((to, context)) if to == from => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Stops waiting for an iq, returning its context, if this answer comes from the entity
+    /// asked.
+    pub(crate) fn take(&mut self, from: &Jid, id: &str) -> Option<T> {
+        self.get(from, id)?;
+        self.pending.remove(id).map(|(_, context)| context)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_from_the_entity_asked() {
+        let room = Jid::new("coven@chat.shakespeare.lit").unwrap();
+        let mut pending = PendingIqs::default();
+        pending.insert(String::from("a"), room.clone(), 1);
+        assert!(pending.contains("a"));
+
+        let other = Jid::new("darkcave@chat.shakespeare.lit").unwrap();
+        assert_eq!(pending.take(&other, "a"), None);
+        let occupant = Jid::new("coven@chat.shakespeare.lit/thirdwitch").unwrap();
+        assert_eq!(pending.take(&occupant, "a"), None);
+        assert_eq!(pending.take(&room, "b"), None);
+
+        *pending.get_mut(&room, "a").unwrap() += 1;
+        assert_eq!(pending.take(&room, "a"), Some(2));
+        assert!(!pending.contains("a"));
+        assert_eq!(pending.take(&room, "a"), None);
+    }
+}
@@ -6,11 +6,13 @@
 
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
-    parsers::{ns, private::Query as PrivateXMLQuery, roster::Roster},
+    parsers::{
+        disco::DiscoInfoResult, mam::Fin, ns, private::Query as PrivateXMLQuery, roster::Roster,
+    },
     Element, Jid,
 };
 
-use crate::{disco, pubsub, upload, Agent, Event};
+use crate::{bob, disco, file_transfer, mam, muc, private, pubsub, roster, upload, Agent, Event};
 
 pub async fn handle_iq_result<C: ServerConnector>(
     agent: &mut Agent<C>,
@@ -24,15 +26,15 @@ pub async fn handle_iq_result<C: ServerConnector>(
     // security reasons.
     if payload.is("query", ns::ROSTER) && from == agent.client.bound_jid().unwrap().to_bare() {
         let roster = Roster::try_from(payload).unwrap();
-        for item in roster.items.into_iter() {
-            events.push(Event::ContactAdded(item));
-        }
+        events.extend(roster::handle_result(agent, roster));
     } else if payload.is("pubsub", ns::PUBSUB) {
         let new_events = pubsub::handle_iq_result(&from, payload);
         events.extend(new_events);
     } else if payload.is("slot", ns::HTTP_UPLOAD) {
         let new_events = upload::receive::handle_upload_result(&from, id, payload, agent).await;
         events.extend(new_events);
+    } else if payload.is("query", ns::PRIVATE) && agent.private_storage.is_pending(&id) {
+        events.extend(private::handle_result(agent, &from, &id));
     } else if payload.is("query", ns::PRIVATE) {
         match PrivateXMLQuery::try_from(payload) {
             Ok(query) => {
@@ -45,6 +47,38 @@ pub async fn handle_iq_result<C: ServerConnector>(
                 panic!("Wrong XEP-0048 v1.0 Bookmark format: {}", e);
             }
         }
+    } else if payload.is("fin", ns::MAM) {
+        if let Ok(fin) = Fin::try_from(payload) {
+            events.extend(muc::history::handle_fin(agent, &from, &id, fin.clone()).await);
+            mam::handle_fin(agent, &from, &id, fin).await;
+        }
+    } else if payload.is("query", ns::REGISTER) {
+        events.extend(muc::register::handle_result(
+            agent,
+            &from,
+            &id,
+            Some(payload),
+        ));
+    } else if payload.is("vCard", ns::VCARD) {
+        #[cfg(feature = "avatars")]
+        events.extend(pubsub::avatar::handle_vcard_result(
+            agent,
+            &from,
+            &id,
+            Some(payload),
+        ));
+    } else if payload.is("si", ns::SI) {
+        events.extend(file_transfer::handle_result(agent, &from, &id, Some(payload)).await);
+    } else if payload.is("data", ns::BOB) {
+        events.extend(bob::handle_result(agent, &from, &id, payload));
+    } else if payload.is("query", ns::DISCO_ITEMS) {
+        events.extend(disco::browse::handle_result(agent, &from, &id, payload));
+    } else if payload.is("query", ns::DISCO_INFO)
+        && agent.server_info_query.as_deref() == Some(id.as_str())
+    {
+        if let Ok(disco) = DiscoInfoResult::try_from(payload) {
+            disco::handle_server_info(agent, &from, disco);
+        }
     } else if payload.is("query", ns::DISCO_INFO) {
         disco::handle_disco_info_result_payload(agent, payload, from).await;
     }
@@ -7,29 +7,70 @@
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
     parsers::{
-        iq::Iq,
-        stanza_error::{DefinedCondition, ErrorType, StanzaError},
+        http_auth::Confirm, jingle::Jingle, ns, roster::Roster, rosterx::Exchange,
+        si::StreamInitiation, stanza_error::StanzaError,
     },
     Element, Jid,
 };
 
-use crate::{Agent, Event};
+use crate::{call, file_transfer, http_auth, roster, Agent, Event};
 
 pub async fn handle_iq_set<C: ServerConnector>(
     agent: &mut Agent<C>,
-    _events: &mut Vec<Event>,
+    events: &mut Vec<Event>,
     from: Jid,
     _to: Option<Jid>,
     id: String,
-    _payload: Element,
+    payload: Element,
 ) {
-    // We MUST answer unhandled set iqs with a service-unavailable error.
-    let error = StanzaError::new(
-        ErrorType::Cancel,
-        DefinedCondition::ServiceUnavailable,
-        "en",
-        "No handler defined for this kind of iq.",
-    );
-    let iq = Iq::from_error(id, error).with_to(from).into();
-    let _ = agent.client.send_stanza(iq).await;
+    // Roster pushes are only valid from our own account, see
+    // https://xmpp.org/rfcs/rfc6121.html#roster-syntax-actions-push
+    let error =
+        if payload.is("query", ns::ROSTER) && from == agent.client.bound_jid().unwrap().to_bare() {
+            match Roster::try_from(payload) {
+                Ok(push) => {
+                    events.extend(roster::handle_push(agent, from, id, push).await);
+                    return;
+                }
+                Err(err) => StanzaError::bad_request().with_text("en", err.to_string()),
+            }
+        } else if payload.is("jingle", ns::JINGLE) && agent.calls.enabled {
+            match Jingle::try_from(payload) {
+                Ok(jingle) => {
+                    events.extend(call::handle_jingle(agent, from, id, jingle).await);
+                    return;
+                }
+                Err(err) => StanzaError::bad_request().with_text("en", err.to_string()),
+            }
+        } else if payload.is("x", ns::ROSTERX) && roster::exchange_enabled(agent) {
+            match Exchange::try_from(payload) {
+                Ok(exchange) => {
+                    events.push(roster::handle_exchange_iq(agent, from, id, exchange).await);
+                    return;
+                }
+                Err(err) => StanzaError::bad_request().with_text("en", err.to_string()),
+            }
+        } else if payload.is("si", ns::SI) && agent.file_transfers.enabled {
+            match StreamInitiation::try_from(payload) {
+                Ok(si) => {
+                    events.extend(file_transfer::handle_offer(agent, from, id, si).await);
+                    return;
+                }
+                Err(err) => StanzaError::bad_request().with_text("en", err.to_string()),
+            }
+        } else if payload.ns() == ns::IBB && agent.file_transfers.enabled {
+            events.extend(file_transfer::handle_ibb(agent, from, id, payload).await);
+            return;
+        } else if payload.is("confirm", ns::HTTP_AUTH) {
+            match Confirm::try_from(payload) {
+                Ok(confirm) => {
+                    events.push(http_auth::handle_iq(agent, from, id, confirm));
+                    return;
+                }
+                Err(err) => StanzaError::bad_request().with_text("en", err.to_string()),
+            }
+        } else {
+            super::unhandled_error(&agent.disco, &payload)
+        };
+    super::send_error(agent, from, id, error).await;
 }
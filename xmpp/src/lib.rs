@@ -12,24 +12,42 @@ pub use tokio_xmpp::{BareJid, Element, FullJid, Jid};
 extern crate log;
 
 pub mod agent;
+pub mod blocking;
+pub mod bob;
 pub mod builder;
+pub mod call;
+pub mod call_invite;
+pub mod component;
 pub mod delay;
 pub mod disco;
+pub mod encryption;
 pub mod event;
 pub mod event_loop;
 pub mod feature;
+pub mod file_transfer;
+pub mod http_auth;
+pub mod idle;
 pub mod iq;
+pub mod mam;
 pub mod message;
 pub mod muc;
+pub mod ping;
 pub mod presence;
+pub mod private;
 pub mod pubsub;
+pub mod roster;
 pub mod upload;
 
 // Module re-exports
 pub use agent::Agent;
 pub use builder::{ClientBuilder, ClientType};
+pub use component::{ComponentAgent, ComponentBuilder, ComponentEvent};
+pub use encryption::EncryptionProtocol;
 pub use event::Event;
 pub use feature::ClientFeature;
+pub use idle::AutoAway;
+pub use message::queue::{FileQueueStorage, OverflowPolicy, QueueStorage};
+pub use message::send::MessageHint;
 
 pub type Error = tokio_xmpp::Error;
 pub type Id = Option<String>;
@@ -0,0 +1,279 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Catching up on the messages missed while disconnected, using Message Archive Management
+//! ([XEP-0313](https://xmpp.org/extensions/xep-0313.html)).
+
+use std::collections::HashMap;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        data_forms::{DataForm, DataFormType, Field},
+        iq::Iq,
+        mam::{Complete, Fin, Query, QueryId},
+        message::{Message, MessageType},
+        ns,
+        rsm::SetQuery,
+        stanza_id::StanzaId,
+    },
+    BareJid, Element, Jid,
+};
+
+use crate::{iq::pending::PendingIqs, Agent};
+
+/// The last archived message seen in a conversation.
+struct LastSeen {
+    /// The archive this id comes from, our own account or a room.
+    archive: BareJid,
+    id: String,
+}
+
+/// A catch-up query in progress.
+struct PendingQuery {
+    archive: BareJid,
+    conversation: BareJid,
+}
+
+/// Remembers the last stanza-id seen per conversation, to query the archives for what got missed
+/// after a reconnection.
+#[derive(Default)]
+pub(crate) struct ArchiveCatchUp {
+    pub(crate) enabled: bool,
+    last_seen: HashMap<BareJid, LastSeen>,
+    pending: PendingIqs<PendingQuery>,
+}
+
+impl ArchiveCatchUp {
+    pub(crate) fn new(enabled: bool) -> ArchiveCatchUp {
+        ArchiveCatchUp {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    /// Records the stanza-id stamped by the expected archive on a live message.
+    pub(crate) fn record(&mut self, own_jid: Option<BareJid>, message: &Message) {
+        if !self.enabled {
+            return;
+        }
+        let Some(from) = message.from.as_ref().map(Jid::to_bare) else {
+            return;
+        };
+        let Some(archive) = message_archive(own_jid, message) else {
+            return;
+        };
+        if let Some(id) = archive_stanza_id(&archive, message) {
+            self.last_seen.insert(from, LastSeen { archive, id });
+        }
+    }
+
+    /// Returns the conversation of an archived message, if it is a result of one of our queries
+    /// from the expected archive, and records it as the last seen.
+    pub(crate) fn record_result(
+        &mut self,
+        own_jid: Option<BareJid>,
+        from: Option<&Jid>,
+        queryid: &QueryId,
+        id: &str,
+    ) -> Option<BareJid> {
+        // Results from our own archive may come without a from.
+        let from = from.cloned().or(own_jid.map(Jid::from))?;
+        let query = self.pending.get(&from, &queryid.0)?;
+        self.last_seen.insert(
+            query.conversation.clone(),
+            LastSeen {
+                archive: query.archive.clone(),
+                id: id.to_owned(),
+            },
+        );
+        Some(query.conversation.clone())
+    }
+
+    fn make_query(
+        &mut self,
+        id: String,
+        own_jid: &BareJid,
+        archive: BareJid,
+        conversation: BareJid,
+        after: String,
+    ) -> Element {
+        let form = if &archive == own_jid {
+            let with = Field::text_single("with", &conversation.to_string());
+            Some(DataForm::new(DataFormType::Submit, ns::MAM, vec![with]))
+        } else {
+            None
+        };
+        let query = Query {
+            queryid: Some(QueryId(id.clone())),
+            node: None,
+            form,
+            set: Some(SetQuery {
+                max: None,
+                after: Some(after),
+                before: None,
+                index: None,
+            }),
+            flip_page: false,
+        };
+        let mut iq = Iq::from_set(id.clone(), query);
+        if &archive != own_jid {
+            iq = iq.with_to(Jid::from(archive.clone()));
+        }
+        self.pending.insert(
+            id,
+            archive.clone().into(),
+            PendingQuery {
+                archive,
+                conversation,
+            },
+        );
+        iq.into()
+    }
+}
+
+/// Returns the archive a message gets stored in: the room for groupchat messages, our own account
+/// otherwise.
+pub(crate) fn message_archive(own_jid: Option<BareJid>, message: &Message) -> Option<BareJid> {
+    match message.type_ {
+        MessageType::Groupchat => message.from.as_ref().map(Jid::to_bare),
+        _ => own_jid,
+    }
+}
+
+/// Returns the stanza-id of a message stamped by `archive`, ignoring those anyone else could have
+/// added.
+pub(crate) fn archive_stanza_id(archive: &BareJid, message: &Message) -> Option<String> {
+    message
+        .payloads
+        .iter()
+        .filter(|payload| payload.is("stanza-id", ns::SID))
+        .filter_map(|payload| StanzaId::try_from(payload.clone()).ok())
+        .find(|stanza_id| &stanza_id.by == archive)
+        .map(|stanza_id| stanza_id.id)
+}
+
+/// Queries the archives for every conversation, since the last message seen in it.
+pub(crate) async fn catch_up<C: ServerConnector>(agent: &mut Agent<C>) {
+    if !agent.archive.enabled {
+        return;
+    }
+    let Some(own_jid) = agent.client.bound_jid().map(Jid::to_bare) else {
+        return;
+    };
+    agent.archive.pending.clear();
+    let conversations: Vec<_> = agent
+        .archive
+        .last_seen
+        .iter()
+        .map(|(conversation, last)| (conversation.clone(), last.archive.clone(), last.id.clone()))
+        .collect();
+    for (conversation, archive, after) in conversations {
+        let id = agent.client.make_id();
+        let iq = agent
+            .archive
+            .make_query(id, &own_jid, archive, conversation, after);
+        let _ = agent.client.send_stanza(iq).await;
+    }
+}
+
+/// Continues with the next page of a catch-up query, until the archive is exhausted.
+pub(crate) async fn handle_fin<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    fin: Fin,
+) {
+    let Some(query) = agent.archive.pending.take(from, id) else {
+        return;
+    };
+    if fin.complete == Complete::True {
+        return;
+    }
+    let (Some(own_jid), Some(last)) = (agent.client.bound_jid().map(Jid::to_bare), fin.set.last)
+    else {
+        return;
+    };
+    let id = agent.client.make_id();
+    let iq = agent
+        .archive
+        .make_query(id, &own_jid, query.archive, query.conversation, last);
+    let _ = agent.client.send_stanza(iq).await;
+}
+
+/// Forgets about a catch-up query the archive refused.
+pub(crate) fn handle_error<C: ServerConnector>(agent: &mut Agent<C>, from: &Jid, id: &str) {
+    if agent.archive.pending.take(from, id).is_some() {
+        warn!("Archive refused catch-up query {}", id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_seen() {
+        let own_jid = BareJid::new("juliet@capulet.lit").unwrap();
+        let mut archive = ArchiveCatchUp::new(true);
+        let message: Element =
+            "<message xmlns='jabber:client' from='romeo@montague.lit/orchard' type='chat'>
+            <body>Hi</body>
+            <stanza-id xmlns='urn:xmpp:sid:0' by='romeo@montague.lit' id='forged'/>
+            <stanza-id xmlns='urn:xmpp:sid:0' by='juliet@capulet.lit' id='28482-98726-73623'/>
+          </message>"
+                .parse()
+                .unwrap();
+        archive.record(Some(own_jid.clone()), &Message::try_from(message).unwrap());
+
+        let romeo = BareJid::new("romeo@montague.lit").unwrap();
+        let last = &archive.last_seen[&romeo];
+        assert_eq!(last.archive, own_jid);
+        assert_eq!(last.id, "28482-98726-73623");
+
+        let id = String::from("catch-up");
+        archive.make_query(
+            id,
+            &own_jid,
+            own_jid.clone(),
+            romeo.clone(),
+            last.id.clone(),
+        );
+        let queryid = QueryId(String::from("catch-up"));
+        let own = Some(own_jid.clone());
+        let room = Jid::new("coven@chat.shakespeare.lit").unwrap();
+        assert_eq!(
+            archive.record_result(own.clone(), Some(&room), &queryid, "1"),
+            None
+        );
+        // Without a from, the result must come from our own archive.
+        assert_eq!(archive.record_result(None, None, &queryid, "2"), None);
+        assert_eq!(
+            archive.record_result(own.clone(), None, &queryid, "09af3-cc343-b409f"),
+            Some(romeo.clone())
+        );
+        assert_eq!(archive.last_seen[&romeo].id, "09af3-cc343-b409f");
+
+        // Occupants can send us messages from their full JID in a room.
+        let room = BareJid::new("coven@chat.shakespeare.lit").unwrap();
+        archive.make_query(
+            String::from("room"),
+            &own_jid,
+            room.clone(),
+            room.clone(),
+            String::from("0"),
+        );
+        let queryid = QueryId(String::from("room"));
+        let occupant = Jid::new("coven@chat.shakespeare.lit/thirdwitch").unwrap();
+        assert_eq!(
+            archive.record_result(own.clone(), Some(&occupant), &queryid, "3"),
+            None
+        );
+        assert_eq!(
+            archive.record_result(own, Some(&room.clone().into()), &queryid, "4"),
+            Some(room)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Suppressing messages received more than once, e.g. live and again from a catch-up query, or our
+//! own messages reflected by a room, using the ids of
+//! [XEP-0359](https://xmpp.org/extensions/xep-0359.html).
+
+use std::collections::{HashMap, VecDeque};
+use tokio_xmpp::{
+    parsers::{message::Message, stanza_id::OriginId},
+    BareJid, Jid,
+};
+
+/// How many ids are remembered per conversation.
+const WINDOW: usize = 100;
+
+/// The ids of the last messages received in every conversation.
+#[derive(Default)]
+pub(crate) struct SeenMessages {
+    seen: HashMap<BareJid, VecDeque<String>>,
+}
+
+impl SeenMessages {
+    /// Returns true if a message with one of these ids was already received in this conversation.
+    ///
+    /// The ids not seen yet get remembered either way, so that a reflection recognised by its
+    /// origin-id also makes its stanza-id known, for when it comes again from the archive.
+    pub(crate) fn is_duplicate(&mut self, conversation: BareJid, ids: Vec<String>) -> bool {
+        let seen = self.seen.entry(conversation).or_default();
+        let duplicate = ids.iter().any(|id| seen.contains(id));
+        for id in ids {
+            if seen.contains(&id) {
+                continue;
+            }
+            if seen.len() == WINDOW {
+                seen.pop_front();
+            }
+            seen.push_back(id);
+        }
+        duplicate
+    }
+
+    /// Remembers a message we sent to a room as this occupant, so that the room reflecting it
+    /// back to us doesn’t make it a new message.
+    pub(crate) fn record_sent(&mut self, room: BareJid, occupant: &Jid, origin_id: &str) {
+        self.is_duplicate(room, vec![origin_key(occupant, origin_id)]);
+    }
+}
+
+fn origin_key(from: &Jid, id: &str) -> String {
+    format!("origin {} {}", from, id)
+}
+
+/// Returns the ids identifying a message: its origin-id, scoped to its sender since anyone can
+/// pick it, and the stanza-id given by its archive, if any.
+pub(crate) fn message_ids(message: &Message, stanza_id: Option<(BareJid, String)>) -> Vec<String> {
+    let mut ids = vec![];
+    if let (Some(from), Some(Ok(origin_id))) = (&message.from, message.get_payload::<OriginId>()) {
+        ids.push(origin_key(from, &origin_id.id));
+    }
+    if let Some((archive, id)) = stanza_id {
+        ids.push(format!("stanza {} {}", archive, id));
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::Element;
+
+    fn parse(xml: &str) -> Message {
+        Message::try_from(xml.parse::<Element>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn duplicates() {
+        let room = BareJid::new("coven@chat.shakespeare.lit").unwrap();
+        let live = parse(
+            "<message xmlns='jabber:client' from='coven@chat.shakespeare.lit/thirdwitch' type='groupchat'>
+              <body>Hi</body>
+              <origin-id xmlns='urn:xmpp:sid:0' id='de305d54'/>
+            </message>",
+        );
+        let mut seen = SeenMessages::default();
+        let ids = message_ids(&live, Some((room.clone(), String::from("1"))));
+        assert_eq!(ids.len(), 2);
+        assert!(!seen.is_duplicate(room.clone(), ids.clone()));
+        assert!(seen.is_duplicate(room.clone(), ids));
+
+        // The same message replayed from the archive, without its origin-id.
+        let archived = parse(
+            "<message xmlns='jabber:client' from='coven@chat.shakespeare.lit/thirdwitch' type='groupchat'>
+              <body>Hi</body>
+            </message>",
+        );
+        let ids = message_ids(&archived, Some((room.clone(), String::from("1"))));
+        assert!(seen.is_duplicate(room.clone(), ids));
+
+        // Another occupant reusing the same origin-id.
+        let other = parse(
+            "<message xmlns='jabber:client' from='coven@chat.shakespeare.lit/firstwitch' type='groupchat'>
+              <body>Hi</body>
+              <origin-id xmlns='urn:xmpp:sid:0' id='de305d54'/>
+            </message>",
+        );
+        assert!(!seen.is_duplicate(room.clone(), message_ids(&other, None)));
+
+        // Messages without ids are never duplicates.
+        assert!(!seen.is_duplicate(room.clone(), vec![]));
+        assert!(!seen.is_duplicate(room, vec![]));
+    }
+
+    #[test]
+    fn reflections() {
+        let room = BareJid::new("coven@chat.shakespeare.lit").unwrap();
+        let occupant = Jid::new("coven@chat.shakespeare.lit/secondwitch").unwrap();
+        let mut seen = SeenMessages::default();
+        seen.record_sent(room.clone(), &occupant, "de305d54");
+
+        // The room sends our message back, with its origin-id and the stanza-id it archived it as.
+        let reflection = parse(
+            "<message xmlns='jabber:client' from='coven@chat.shakespeare.lit/secondwitch' type='groupchat'>
+              <body>Hi</body>
+              <origin-id xmlns='urn:xmpp:sid:0' id='de305d54'/>
+            </message>",
+        );
+        let ids = message_ids(&reflection, Some((room.clone(), String::from("1"))));
+        assert!(seen.is_duplicate(room.clone(), ids));
+
+        // Then again from the archive, only known by its stanza-id.
+        let archived = parse(
+            "<message xmlns='jabber:client' from='coven@chat.shakespeare.lit/secondwitch' type='groupchat'>
+              <body>Hi</body>
+            </message>",
+        );
+        let ids = message_ids(&archived, Some((room.clone(), String::from("1"))));
+        assert!(seen.is_duplicate(room.clone(), ids));
+
+        // Another occupant using the same origin-id isn’t us.
+        let other = parse(
+            "<message xmlns='jabber:client' from='coven@chat.shakespeare.lit/firstwitch' type='groupchat'>
+              <body>Hi</body>
+              <origin-id xmlns='urn:xmpp:sid:0' id='de305d54'/>
+            </message>",
+        );
+        assert!(!seen.is_duplicate(room, message_ids(&other, None)));
+    }
+
+    #[test]
+    fn window() {
+        let romeo = BareJid::new("romeo@montague.lit").unwrap();
+        let mut seen = SeenMessages::default();
+        for i in 0..=WINDOW {
+            assert!(!seen.is_duplicate(romeo.clone(), vec![i.to_string()]));
+        }
+        assert!(!seen.is_duplicate(romeo.clone(), vec![String::from("0")]));
+        assert!(seen.is_duplicate(romeo, vec![WINDOW.to_string()]));
+    }
+}
@@ -4,5 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub(crate) mod dedup;
+pub mod queue;
 pub mod receive;
 pub mod send;
@@ -0,0 +1,562 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Keeping the messages sent while disconnected, to send them once online again, see
+//! [`ClientBuilder::set_offline_queue`](crate::ClientBuilder::set_offline_queue). With a
+//! [`QueueStorage`], they even survive a restart of the process.
+//!
+//! With Stream Management, messages only leave the queue once the server acknowledged them, and
+//! the ones still unacknowledged when the connection got lost are sent again.
+
+use futures::future::{self, FutureExt};
+use std::collections::VecDeque;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::parsers::message::Message;
+use tokio_xmpp::{rt, Element, StanzaAck};
+
+use crate::{Agent, Error};
+
+/// Namespace of the root element of the files written by [`FileQueueStorage`].
+const QUEUE_FILE_NS: &str = "urn:xmpp-rs:offline-queue:0";
+
+/// Persists the offline queue, see
+/// [`ClientBuilder::set_queue_storage`](crate::ClientBuilder::set_queue_storage).
+pub trait QueueStorage: Send {
+    /// Loads the messages saved by a previous run, oldest first.
+    fn load(&mut self) -> io::Result<Vec<Message>>;
+
+    /// Replaces the saved messages with these, oldest first, whenever the queue changes. This
+    /// runs on a thread where blocking is fine.
+    fn save(&mut self, messages: &[Message]) -> io::Result<()>;
+}
+
+/// Keeps the offline queue in a file, as XML.
+#[derive(Debug, Clone)]
+pub struct FileQueueStorage {
+    path: PathBuf,
+}
+
+impl FileQueueStorage {
+    /// Uses this file, which doesn’t need to exist yet.
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileQueueStorage {
+        FileQueueStorage { path: path.into() }
+    }
+}
+
+impl QueueStorage for FileQueueStorage {
+    /// Messages which can't be parsed anymore are skipped, keeping a copy of the file with
+    /// `.invalid` appended to its name, as the next save replaces it.
+    fn load(&mut self) -> io::Result<Vec<Message>> {
+        let xml = match fs::read_to_string(&self.path) {
+            Ok(xml) => xml,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let root: Element = xml
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if !root.is("queue", QUEUE_FILE_NS) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an offline queue",
+            ));
+        }
+        let mut messages = Vec::new();
+        let mut invalid = false;
+        for child in root.children() {
+            match Message::try_from(child.clone()) {
+                Ok(message) => messages.push(message),
+                Err(e) => {
+                    warn!("Skipping an invalid message of the offline queue: {}", e);
+                    invalid = true;
+                }
+            }
+        }
+        if invalid {
+            let mut copy = self.path.clone().into_os_string();
+            copy.push(".invalid");
+            fs::copy(&self.path, &copy)?;
+        }
+        Ok(messages)
+    }
+
+    fn save(&mut self, messages: &[Message]) -> io::Result<()> {
+        let root = Element::builder("queue", QUEUE_FILE_NS)
+            .append_all(messages.iter().cloned().map(Element::from))
+            .build();
+        // Write then rename, so that a crash meanwhile keeps the previous queue.
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, String::from(&root))?;
+        fs::rename(&temporary, &self.path)
+    }
+}
+
+/// What to do with a message sent while the offline queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Drop the new message.
+    DropNewest,
+}
+
+/// The messages waiting for the connection to come back, or for the server to acknowledge them.
+pub(crate) struct OfflineQueue {
+    capacity: usize,
+    policy: OverflowPolicy,
+    messages: VecDeque<Message>,
+    /// Messages sent, oldest first, along with the acknowledgement of each.
+    unacked: VecDeque<(Message, StanzaAck)>,
+    storage: Option<Arc<Mutex<Box<dyn QueueStorage>>>>,
+    /// The last save, which the next one waits for.
+    saving: Option<rt::JoinHandle<()>>,
+}
+
+impl OfflineQueue {
+    /// A queue of this capacity, zero disabling it, starting with the messages saved in
+    /// `storage` if any, even beyond the capacity. A storage which fails to load isn't saved to
+    /// either, to keep what it has.
+    pub(crate) fn new(
+        capacity: usize,
+        policy: OverflowPolicy,
+        storage: Option<Box<dyn QueueStorage>>,
+    ) -> OfflineQueue {
+        let mut queue = OfflineQueue {
+            capacity,
+            policy,
+            messages: VecDeque::new(),
+            unacked: VecDeque::new(),
+            storage: None,
+            saving: None,
+        };
+        let Some(mut storage) = storage else {
+            return queue;
+        };
+        match storage.load() {
+            Ok(saved) => {
+                if saved.len() > capacity {
+                    warn!(
+                        "Keeping the {} saved messages of the offline queue, beyond its capacity.",
+                        saved.len()
+                    );
+                }
+                queue.messages.extend(saved);
+                queue.storage = Some(Arc::new(Mutex::new(storage)));
+            }
+            Err(e) => error!(
+                "Failed to load the offline queue, which won't be saved: {}",
+                e
+            ),
+        }
+        queue
+    }
+
+    /// Saves the queue to its storage, if any, after each change. Saves run in the background,
+    /// one after the other.
+    fn save(&mut self) {
+        let Some(ref storage) = self.storage else {
+            return;
+        };
+        let storage = storage.clone();
+        let messages: Vec<Message> = self
+            .unacked
+            .iter()
+            .map(|(message, _)| message)
+            .chain(&self.messages)
+            .cloned()
+            .collect();
+        let previous = self.saving.take();
+        self.saving = Some(rt::spawn(async move {
+            if let Some(previous) = previous {
+                previous.await;
+            }
+            let saved = rt::spawn_blocking(move || {
+                // A storage which panicked while saving can still be given the next queue.
+                let mut storage = storage.lock().unwrap_or_else(PoisonError::into_inner);
+                storage.save(&messages)
+            })
+            .await;
+            if let Err(e) = saved {
+                warn!("Failed to save the offline queue: {}", e);
+            }
+        }));
+    }
+
+    /// Resolves once the last save, if any, is done.
+    pub(crate) async fn saved(&mut self) {
+        if let Some(saving) = self.saving.take() {
+            saving.await;
+        }
+    }
+
+    /// Forgets the messages the server acknowledged, and queues again the ones it may not have
+    /// received before the connection got lost. Ready once any of them changed.
+    fn poll_acked(&mut self, cx: &mut Context) -> Poll<()> {
+        let mut changed = false;
+        while let Some((_, ack)) = self.unacked.front_mut() {
+            match Pin::new(ack).poll(cx) {
+                Poll::Ready(Ok(())) => {
+                    self.unacked.pop_front();
+                    changed = true;
+                }
+                Poll::Ready(Err(_)) => {
+                    while let Some((message, _)) = self.unacked.pop_back() {
+                        self.messages.push_front(message);
+                    }
+                    changed = true;
+                }
+                Poll::Pending => break,
+            }
+        }
+        if changed {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Resolves once acknowledgements changed the queue, saving it.
+    pub(crate) async fn acked(&mut self) {
+        future::poll_fn(|cx| self.poll_acked(cx)).await;
+        self.save();
+    }
+
+    fn enqueue(&mut self, message: Message) {
+        self.push(message);
+        self.save();
+    }
+
+    fn push(&mut self, message: Message) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.messages.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    warn!("Offline queue full, dropping the oldest message.");
+                    self.messages.pop_front();
+                }
+                OverflowPolicy::DropNewest => {
+                    warn!("Offline queue full, dropping the new message.");
+                    return;
+                }
+            }
+        }
+        self.messages.push_back(message);
+    }
+}
+
+/// Sends a message, keeping it until acknowledged with Stream Management, or returns it if it
+/// couldn’t be sent.
+async fn send<C: ServerConnector>(agent: &mut Agent<C>, message: Message) -> Option<Message> {
+    if agent.offline_queue.capacity == 0 && agent.offline_queue.storage.is_none() {
+        return agent
+            .client
+            .send_stanza(message.clone().into())
+            .await
+            .err()
+            .map(|_| message);
+    }
+    match agent
+        .client
+        .send_stanza_tracked(message.clone().into())
+        .await
+    {
+        Ok(ack) => {
+            agent.offline_queue.unacked.push_back((message, ack));
+            None
+        }
+        // Without Stream Management, it leaves the queue once written.
+        Err(Error::InvalidState) => agent
+            .client
+            .send_stanza(message.clone().into())
+            .await
+            .err()
+            .map(|_| message),
+        Err(_) => Some(message),
+    }
+}
+
+/// Sends a message, or queues it if the client isn’t connected.
+pub(crate) async fn send_or_queue<C: ServerConnector>(agent: &mut Agent<C>, message: Message) {
+    // Keep the order of the messages sent while the queue gets flushed.
+    if !agent.offline_queue.messages.is_empty() {
+        agent.offline_queue.enqueue(message);
+        return;
+    }
+    let unacked = agent.offline_queue.unacked.len();
+    match send(agent, message).await {
+        Some(message) => agent.offline_queue.enqueue(message),
+        // Keep it saved until acknowledged.
+        None if agent.offline_queue.unacked.len() > unacked => agent.offline_queue.save(),
+        None => (),
+    }
+}
+
+/// Sends the queued messages, oldest first, once online again, after the ones the server didn’t
+/// acknowledge on the previous connection.
+pub(crate) async fn flush<C: ServerConnector>(agent: &mut Agent<C>) {
+    // Every acknowledgement of the previous connection got resolved once it was lost.
+    let mut changed = future::poll_fn(|cx| agent.offline_queue.poll_acked(cx))
+        .now_or_never()
+        .is_some();
+    while let Some(message) = agent.offline_queue.messages.pop_front() {
+        if let Some(message) = send(agent, message).await {
+            agent.offline_queue.messages.push_front(message);
+            break;
+        }
+        changed = true;
+    }
+    if changed {
+        agent.offline_queue.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientBuilder, Event};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio_xmpp::connect::mock::{MockConnector, MockServer};
+    use tokio_xmpp::parsers::{ns, sm::A};
+    use tokio_xmpp::{BareJid, Jid};
+
+    fn message(body: &str) -> Message {
+        Message::new(Jid::new("juliet@capulet.lit").unwrap())
+            .with_body(String::from("en"), String::from(body))
+    }
+
+    fn bodies(queue: &OfflineQueue) -> Vec<&str> {
+        queue
+            .messages
+            .iter()
+            .map(|message| message.bodies["en"].0.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn overflow() {
+        let mut queue = OfflineQueue::new(0, OverflowPolicy::DropOldest, None);
+        queue.push(message("lost"));
+        assert!(queue.messages.is_empty());
+
+        let mut queue = OfflineQueue::new(2, OverflowPolicy::DropOldest, None);
+        for body in ["one", "two", "three"] {
+            queue.push(message(body));
+        }
+        assert_eq!(bodies(&queue), ["two", "three"]);
+
+        let mut queue = OfflineQueue::new(2, OverflowPolicy::DropNewest, None);
+        for body in ["one", "two", "three"] {
+            queue.push(message(body));
+        }
+        assert_eq!(bodies(&queue), ["one", "two"]);
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("xmpp-rs-{}-{}.xml", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    async fn saved(queue: &mut OfflineQueue) {
+        assert!(queue.saving.is_some());
+        queue.saved().await;
+    }
+
+    #[tokio::test]
+    async fn file_storage() {
+        let path = temporary_path("queue");
+
+        let storage = Box::new(FileQueueStorage::new(&path));
+        let mut queue = OfflineQueue::new(2, OverflowPolicy::DropOldest, Some(storage));
+        assert!(queue.messages.is_empty());
+        for body in ["one", "two", "three"] {
+            queue.enqueue(message(body));
+        }
+        saved(&mut queue).await;
+
+        // After a restart
+        let storage = Box::new(FileQueueStorage::new(&path));
+        let mut queue = OfflineQueue::new(2, OverflowPolicy::DropOldest, Some(storage));
+        assert_eq!(bodies(&queue), ["two", "three"]);
+        queue.messages.pop_front();
+        queue.save();
+        saved(&mut queue).await;
+
+        let mut storage = FileQueueStorage::new(&path);
+        let saved = storage.load().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].bodies["en"].0, "three");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn saved_on_disconnect() {
+        let path = temporary_path("queue-disconnect");
+        let jid = BareJid::new("romeo@montague.lit").unwrap();
+        let server = MockServer::new();
+        let mut agent =
+            ClientBuilder::new_with_connector(jid, "password", MockConnector::new(server))
+                .set_offline_queue(3, OverflowPolicy::DropOldest)
+                .set_queue_storage(Box::new(FileQueueStorage::new(&path)))
+                .build();
+
+        send_or_queue(&mut agent, message("one")).await;
+        let _ = agent.disconnect().await;
+        let saved = FileQueueStorage::new(&path).load().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].bodies["en"].0, "one");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn load_everything() {
+        let path = temporary_path("queue-load");
+        let mut storage = FileQueueStorage::new(&path);
+        storage
+            .save(&[message("one"), message("two"), message("three")])
+            .unwrap();
+
+        // Loaded messages are kept beyond the capacity.
+        let queue = OfflineQueue::new(1, OverflowPolicy::DropOldest, Some(Box::new(storage)));
+        assert_eq!(bodies(&queue), ["one", "two", "three"]);
+
+        // An invalid message only loses itself, with a copy of the file kept.
+        let xml = fs::read_to_string(&path).unwrap();
+        let xml = xml.replacen("type=\"chat\"", "type=\"coucou\"", 1);
+        fs::write(&path, &xml).unwrap();
+        let mut storage = FileQueueStorage::new(&path);
+        let loaded = storage.load().unwrap();
+        assert_eq!(loaded.len(), 2);
+        let mut copy = path.clone().into_os_string();
+        copy.push(".invalid");
+        assert_eq!(fs::read_to_string(&copy).unwrap(), xml);
+
+        // A storage which can't be loaded isn't replaced.
+        fs::write(&path, "<coucou/>").unwrap();
+        let storage = Box::new(FileQueueStorage::new(&path));
+        let mut queue = OfflineQueue::new(1, OverflowPolicy::DropOldest, Some(storage));
+        assert!(queue.storage.is_none());
+        queue.enqueue(message("four"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<coucou/>");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&copy).unwrap();
+    }
+
+    /// A fake server keeping the bodies of the messages it receives, answering the one with
+    /// `last` as its body, and acknowledging every stanza with Stream Management if `sm`.
+    fn server(received: Arc<Mutex<Vec<String>>>, last: &'static str, sm: bool) -> MockServer {
+        let count = AtomicU32::new(0);
+        let server = MockServer::new().with_handler(move |elem| {
+            if elem.is("enable", ns::SM) {
+                return vec![Element::builder("enabled", ns::SM).build()];
+            } else if elem.is("r", ns::SM) {
+                return vec![A::new(count.load(Ordering::Relaxed)).into()];
+            }
+            count.fetch_add(1, Ordering::Relaxed);
+            let Ok(message) = Message::try_from(elem) else {
+                return vec![];
+            };
+            let body = message.bodies["en"].0.clone();
+            received.lock().unwrap().push(body.clone());
+            if body != last {
+                return vec![];
+            }
+            let answer = Message::new(Jid::new("romeo@montague.lit/orchard").unwrap())
+                .with_from(Jid::new("juliet@capulet.lit/balcony").unwrap())
+                .with_body(String::from("en"), String::from("received"));
+            vec![answer.into()]
+        });
+        if sm {
+            server.with_feature(Element::builder("sm", ns::SM).build())
+        } else {
+            server
+        }
+    }
+
+    fn agent(server: MockServer) -> Agent<MockConnector> {
+        let jid = BareJid::new("romeo@montague.lit").unwrap();
+        ClientBuilder::new_with_connector(jid, "password", MockConnector::new(server))
+            .set_offline_queue(3, OverflowPolicy::DropOldest)
+            .build()
+    }
+
+    /// Runs the agent until the server answers.
+    async fn until_answered(agent: &mut Agent<MockConnector>) {
+        while let Some(events) = agent.wait_for_events().await {
+            if events
+                .iter()
+                .any(|event| matches!(event, Event::ChatMessage(..)))
+            {
+                return;
+            }
+        }
+        panic!("Disconnected");
+    }
+
+    #[tokio::test]
+    async fn flush_in_order() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let mut agent = agent(server(received.clone(), "four", false));
+
+        // Sent while offline, then flushed once online, oldest first.
+        for body in ["one", "two", "three"] {
+            send_or_queue(&mut agent, message(body)).await;
+        }
+        assert_eq!(bodies(&agent.offline_queue), ["one", "two", "three"]);
+        while let Some(events) = agent.wait_for_events().await {
+            if events.iter().any(|event| matches!(event, Event::Online)) {
+                break;
+            }
+        }
+        assert!(agent.offline_queue.messages.is_empty());
+
+        // Sent right away once the queue is empty, after the queued ones.
+        send_or_queue(&mut agent, message("four")).await;
+        assert!(agent.offline_queue.messages.is_empty());
+        until_answered(&mut agent).await;
+        assert_eq!(*received.lock().unwrap(), ["one", "two", "three", "four"]);
+        // Without Stream Management, messages leave the queue once written.
+        assert!(agent.offline_queue.unacked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn kept_until_acked() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let mut agent = agent(server(received.clone(), "two", true));
+
+        send_or_queue(&mut agent, message("one")).await;
+        while let Some(events) = agent.wait_for_events().await {
+            if events.iter().any(|event| matches!(event, Event::Online)) {
+                break;
+            }
+        }
+        // Handed from the queue to the server, waiting for its acknowledgement.
+        assert!(agent.offline_queue.messages.is_empty());
+        send_or_queue(&mut agent, message("two")).await;
+        assert!(!agent.offline_queue.unacked.is_empty());
+
+        until_answered(&mut agent).await;
+        assert_eq!(*received.lock().unwrap(), ["one", "two"]);
+        while !agent.offline_queue.unacked.is_empty() {
+            agent.wait_for_events().await.unwrap();
+        }
+        assert!(agent.offline_queue.messages.is_empty());
+    }
+}
@@ -10,7 +10,7 @@ use tokio_xmpp::{
     Jid,
 };
 
-use crate::{delay::StanzaTimeInfo, Agent, Event};
+use crate::{delay::StanzaTimeInfo, encryption::message_encryption, Agent, Event};
 
 pub async fn handle_message_chat<C: ServerConnector>(
     agent: &mut Agent<C>,
@@ -21,38 +21,44 @@ pub async fn handle_message_chat<C: ServerConnector>(
 ) {
     let langs: Vec<&str> = agent.lang.iter().map(String::as_str).collect();
     if let Some((_lang, body)) = message.get_best_body(langs) {
+        let encryption = message_encryption(message);
         let mut found_special_message = false;
 
-        for payload in &message.payloads {
-            if let Ok(_) = MucUser::try_from(payload.clone()) {
-                let event = match from.clone().try_into_full() {
-                    Err(bare) => {
-                        // TODO: Can a service message be of type Chat/Normal and not Groupchat?
-                        warn!("Received misformed MessageType::Chat in muc#user namespace from a bare JID.");
-                        Event::ServiceMessage(
-                            message.id.clone(),
-                            bare,
-                            body.clone(),
-                            time_info.clone(),
-                        )
-                    }
-                    Ok(full) => Event::RoomPrivateMessage(
+        if let Some(Ok(_)) = message.get_payload::<MucUser>() {
+            let event = match from.clone().try_into_full() {
+                Err(bare) => {
+                    // TODO: Can a service message be of type Chat/Normal and not Groupchat?
+                    warn!("Received misformed MessageType::Chat in muc#user namespace from a bare JID.");
+                    Event::ServiceMessage(
                         message.id.clone(),
-                        full.to_bare(),
-                        full.resource().to_string(),
+                        bare,
                         body.clone(),
                         time_info.clone(),
-                    ),
-                };
+                        encryption.clone(),
+                    )
+                }
+                Ok(full) => Event::RoomPrivateMessage(
+                    message.id.clone(),
+                    full.to_bare(),
+                    full.resource().to_string(),
+                    body.clone(),
+                    time_info.clone(),
+                    encryption.clone(),
+                ),
+            };
 
-                found_special_message = true;
-                events.push(event);
-            }
+            found_special_message = true;
+            events.push(event);
         }
 
         if !found_special_message {
-            let event =
-                Event::ChatMessage(message.id.clone(), from.to_bare(), body.clone(), time_info);
+            let event = Event::ChatMessage(
+                message.id.clone(),
+                from.to_bare(),
+                body.clone(),
+                time_info,
+                encryption,
+            );
             events.push(event);
         }
     }
@@ -7,7 +7,7 @@
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{parsers::message::Message, Jid};
 
-use crate::{delay::StanzaTimeInfo, Agent, Event};
+use crate::{delay::StanzaTimeInfo, encryption::message_encryption, Agent, Event};
 
 pub async fn handle_message_group_chat<C: ServerConnector>(
     agent: &mut Agent<C>,
@@ -18,8 +18,15 @@ pub async fn handle_message_group_chat<C: ServerConnector>(
 ) {
     let langs: Vec<&str> = agent.lang.iter().map(String::as_str).collect();
 
-    if let Some((_lang, subject)) = message.get_best_subject(langs.clone()) {
-        events.push(Event::RoomSubject(
+    // Only a message without any body changes the subject, an empty subject clearing it. See
+    // https://xmpp.org/extensions/xep-0045.html#subject-mod
+    let subject = if message.bodies.is_empty() {
+        message.get_best_subject(langs.clone())
+    } else {
+        None
+    };
+    if let Some((_lang, subject)) = subject {
+        events.push(Event::RoomSubjectChanged(
             from.to_bare(),
             from.resource().map(|x| x.to_string()),
             subject.0.clone(),
@@ -28,6 +35,7 @@ pub async fn handle_message_group_chat<C: ServerConnector>(
     }
 
     if let Some((_lang, body)) = message.get_best_body(langs) {
+        let encryption = message_encryption(message);
         let event = match from.clone().try_into_full() {
             Ok(full) => Event::RoomMessage(
                 message.id.clone(),
@@ -35,8 +43,15 @@ pub async fn handle_message_group_chat<C: ServerConnector>(
                 full.resource().to_string(),
                 body.clone(),
                 time_info,
+                encryption,
+            ),
+            Err(bare) => Event::ServiceMessage(
+                message.id.clone(),
+                bare,
+                body.clone(),
+                time_info,
+                encryption,
             ),
-            Err(bare) => Event::ServiceMessage(message.id.clone(), bare, body.clone(), time_info),
         };
         events.push(event)
     }
@@ -5,23 +5,114 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use tokio_xmpp::connect::ServerConnector;
-use tokio_xmpp::parsers::{
-    message::{Message, MessageType},
-    ns,
+use tokio_xmpp::{
+    parsers::{
+        attaching::AttachTo,
+        bob::Data,
+        http_auth::Confirm,
+        mam::Result_ as MamResult,
+        message::{Message, MessageType},
+        ns,
+        rai::Rai,
+        rosterx::Exchange,
+    },
+    BareJid, Element,
 };
 
-use crate::{delay::message_time_info, pubsub, Agent, Event};
+use crate::{
+    bob, call_invite,
+    delay::{message_time_info, StanzaTimeInfo},
+    encryption, http_auth, mam,
+    message::dedup::message_ids,
+    muc, pubsub, roster, Agent, Event,
+};
 
 pub mod chat;
 pub mod group_chat;
 
+/// Translate a `Message` stanza into a list of higher-level `Event`s, `source` being the stanza
+/// as received.
 pub async fn handle_message<C: ServerConnector>(
     agent: &mut Agent<C>,
     message: Message,
+    source: &Element,
 ) -> Vec<Event> {
-    let mut events = vec![];
-    let from = message.from.clone().unwrap();
+    if let Some(Ok(result)) = message.get_payload::<MamResult>() {
+        return handle_archived_message(agent, &message, result, source).await;
+    }
+
+    let own_jid = agent.client.bound_jid().map(|jid| jid.to_bare());
+    agent.archive.record(own_jid.clone(), &message);
+    let stanza_id = mam::message_archive(own_jid, &message).and_then(|archive| {
+        let id = mam::archive_stanza_id(&archive, &message)?;
+        Some((archive, id))
+    });
     let time_info = message_time_info(&message);
+    dispatch_message(agent, message, time_info, stanza_id).await
+}
+
+/// Replays a message retrieved by a catch-up query, see [crate::mam].
+async fn handle_archived_message<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    message: &Message,
+    result: MamResult,
+    source: &Element,
+) -> Vec<Event> {
+    if result
+        .queryid
+        .as_ref()
+        .is_some_and(|queryid| agent.room_history.is_pending(queryid))
+    {
+        agent
+            .room_history
+            .record_result(message.from.as_ref(), result, source);
+        return vec![];
+    }
+    let Some(queryid) = result.queryid else {
+        return vec![];
+    };
+    let own_jid = agent.client.bound_jid().map(|jid| jid.to_bare());
+    if agent
+        .archive
+        .record_result(own_jid.clone(), message.from.as_ref(), &queryid, &result.id)
+        .is_none()
+    {
+        return vec![];
+    }
+    let Some(archived) = result.forwarded.stanza else {
+        return vec![];
+    };
+    // Our own messages, sent from another client.
+    if archived.from.is_none() || archived.from.as_ref().map(|jid| jid.to_bare()) == own_jid {
+        return vec![];
+    }
+    // Results from our own archive come without a from.
+    let archive = message.from.as_ref().map(|jid| jid.to_bare()).or(own_jid);
+    let stanza_id = archive.map(|archive| (archive, result.id));
+
+    let mut time_info = message_time_info(&archived);
+    time_info.delays.extend(result.forwarded.delay);
+    time_info.from_archive = true;
+    dispatch_message(agent, archived, time_info, stanza_id).await
+}
+
+pub(crate) async fn dispatch_message<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    mut message: Message,
+    time_info: StanzaTimeInfo,
+    stanza_id: Option<(BareJid, String)>,
+) -> Vec<Event> {
+    let from = message.from.clone().unwrap();
+    // The same message may reach us several times, e.g. live and from a catch-up query. History
+    // was explicitly asked for though, even if some of it was seen already.
+    let ids = message_ids(&message, stanza_id);
+    if !time_info.historical && agent.seen_messages.is_duplicate(from.to_bare(), ids) {
+        return vec![];
+    }
+
+    encryption::decrypt_message(&mut agent.encryption, &mut message);
+    let mut events = vec![];
+    let from_archive = time_info.from_archive;
 
     match message.type_ {
         MessageType::Groupchat => {
@@ -44,6 +135,38 @@ pub async fn handle_message<C: ServerConnector>(
         if child.is("event", ns::PUBSUB_EVENT) {
             let new_events = pubsub::handle_event(&from, child, agent).await;
             events.extend(new_events);
+        } else if child.is("x", ns::ROSTERX) && !from_archive && roster::exchange_enabled(agent) {
+            if let Ok(exchange) = Exchange::try_from(child) {
+                events.push(Event::ContactsSuggested(from.clone(), exchange.items));
+            }
+        } else if child.is("rai", ns::RAI) && !from_archive {
+            if let Ok(rai) = Rai::try_from(child) {
+                events.extend(muc::activity::handle_activity(agent, &from, rai));
+            }
+        } else if child.is("data", ns::BOB) {
+            if let Ok(data) = Data::try_from(child) {
+                bob::handle_data(agent, data);
+            }
+        } else if child.is("attach-to", ns::MESSAGE_ATTACHING) {
+            if let Ok(attach_to) = AttachTo::try_from(child) {
+                events.push(Event::MessageAttached(
+                    from.clone(),
+                    message.id.clone(),
+                    attach_to.id,
+                ));
+            }
+        } else if child.ns() == ns::CALL_INVITES && !from_archive && agent.calls.enabled {
+            events.extend(call_invite::handle_payload(&from, &message.id, child));
+        } else if child.is("confirm", ns::HTTP_AUTH) && !from_archive {
+            if let Ok(confirm) = Confirm::try_from(child) {
+                let thread = message.thread.clone();
+                events.push(http_auth::handle_message(
+                    agent,
+                    from.clone(),
+                    thread,
+                    confirm,
+                ));
+            }
         }
     }
 
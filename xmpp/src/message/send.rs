@@ -6,11 +6,41 @@
 
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
-    parsers::message::{Body, Message, MessageType},
-    Jid,
+    parsers::{
+        chatstates::ChatState,
+        hints::{NoCopy, NoPermanentStore, NoStore, Store},
+        message::{Message, MessageType},
+        stanza_id::OriginId,
+    },
+    Element, Jid,
 };
 
-use crate::Agent;
+use crate::{encryption, encryption::EncryptionError, message::queue, Agent};
+
+/// Message Processing Hints ([XEP-0334](https://xmpp.org/extensions/xep-0334.html)), telling
+/// servers how to handle a sent message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageHint {
+    /// Don’t archive the message, but it may still be delivered offline.
+    NoPermanentStore,
+    /// Neither archive the message nor deliver it offline.
+    NoStore,
+    /// Don’t copy the message to our other clients.
+    NoCopy,
+    /// Archive the message, even if it would otherwise not be.
+    Store,
+}
+
+impl From<MessageHint> for Element {
+    fn from(hint: MessageHint) -> Element {
+        match hint {
+            MessageHint::NoPermanentStore => NoPermanentStore.into(),
+            MessageHint::NoStore => NoStore.into(),
+            MessageHint::NoCopy => NoCopy.into(),
+            MessageHint::Store => Store.into(),
+        }
+    }
+}
 
 pub async fn send_message<C: ServerConnector>(
     agent: &mut Agent<C>,
@@ -18,11 +48,38 @@ pub async fn send_message<C: ServerConnector>(
     type_: MessageType,
     lang: &str,
     text: &str,
-) {
-    let mut message = Message::new(Some(recipient));
-    message.type_ = type_;
+    hints: &[MessageHint],
+) -> Result<(), EncryptionError> {
+    let origin_id = agent.client.make_id();
+    // Rooms send our messages back to us, which mustn’t be reported as new ones.
+    if type_ == MessageType::Groupchat {
+        let room = recipient.to_bare();
+        if let Some(nick) = agent.joined_rooms.get(&room) {
+            let occupant = room.with_resource_str(nick).unwrap().into();
+            agent.seen_messages.record_sent(room, &occupant, &origin_id);
+        }
+    }
+    let mut message = Message::new_with_type(type_, recipient)
+        .with_body(String::from(lang), String::from(text))
+        .with_payload(OriginId { id: origin_id });
     message
-        .bodies
-        .insert(String::from(lang), Body(String::from(text)));
+        .payloads
+        .extend(hints.iter().copied().map(Element::from));
+    let message = encryption::encrypt_message(&mut agent.encryption, message)?;
+    queue::send_or_queue(agent, message).await;
+    Ok(())
+}
+
+/// Sends a chat state notification, which servers are told not to store since it is only
+/// meaningful right now.
+pub async fn send_chat_state<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    recipient: Jid,
+    type_: MessageType,
+    state: ChatState,
+) {
+    let message = Message::new_with_type(type_, recipient)
+        .with_payload(state)
+        .with_payload(NoStore);
     let _ = agent.client.send_stanza(message.into()).await;
 }
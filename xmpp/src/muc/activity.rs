@@ -0,0 +1,119 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Watching rooms without joining them, with Room Activity Indicators
+//! ([XEP-0437](https://xmpp.org/extensions/xep-0437.html)): a MUC service advertising
+//! [`ns::RAI`](tokio_xmpp::parsers::ns::RAI) tells us which of our rooms got new messages, so a
+//! bot monitoring many rooms only joins them once something happens there, instead of receiving
+//! the presences and messages of all of them. Such a room is best joined with
+//! [Agent::join_room_quietly](crate::Agent::join_room_quietly), skipping its history and
+//! occupants.
+
+use std::collections::HashSet;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        presence::{Presence, Type as PresenceType},
+        rai::Rai,
+    },
+    BareJid, Jid,
+};
+
+use crate::{Agent, Event};
+
+/// The MUC services we subscribed to, their directed presence has to be sent again with every
+/// new session.
+#[derive(Default)]
+pub(crate) struct ActivitySubscriptions {
+    services: HashSet<BareJid>,
+}
+
+impl ActivitySubscriptions {
+    /// The rooms with new activity, if this notification comes from a service we subscribed to.
+    fn rooms(&self, from: &Jid, rai: Rai) -> Vec<BareJid> {
+        if !self.services.contains(&from.to_bare()) {
+            return vec![];
+        }
+        rai.activities
+            .into_iter()
+            .map(|activity| activity.0)
+            .collect()
+    }
+}
+
+async fn send_subscription<C: ServerConnector>(agent: &mut Agent<C>, service: BareJid) {
+    let presence = Presence::available()
+        .with_to(service)
+        .with_payload(Rai::new());
+    let _ = agent.client.send_stanza(presence.into()).await;
+}
+
+/// Subscribes to the activity of our rooms on this MUC service.
+pub(crate) async fn subscribe<C: ServerConnector>(agent: &mut Agent<C>, service: BareJid) {
+    if agent.room_activity.services.insert(service.clone()) {
+        send_subscription(agent, service).await;
+    }
+}
+
+/// Stops the notifications of this MUC service.
+pub(crate) async fn unsubscribe<C: ServerConnector>(agent: &mut Agent<C>, service: BareJid) {
+    if agent.room_activity.services.remove(&service) {
+        let presence = Presence::new(PresenceType::Unavailable).with_to(service);
+        let _ = agent.client.send_stanza(presence.into()).await;
+    }
+}
+
+/// Subscribes again once a new session got established.
+pub(crate) async fn resubscribe<C: ServerConnector>(agent: &mut Agent<C>) {
+    let services: Vec<_> = agent.room_activity.services.iter().cloned().collect();
+    for service in services {
+        send_subscription(agent, service).await;
+    }
+}
+
+/// Reports the rooms with new activity, if this notification comes from a service we subscribed
+/// to.
+pub(crate) fn handle_activity<C: ServerConnector>(
+    agent: &Agent<C>,
+    from: &Jid,
+    rai: Rai,
+) -> Vec<Event> {
+    agent
+        .room_activity
+        .rooms(from, rai)
+        .into_iter()
+        .map(Event::RoomActivity)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::Element;
+
+    #[test]
+    fn rooms() {
+        let elem: Element = "<rai xmlns='urn:xmpp:rai:0'>
+              <activity>coven@chat.shakespeare.lit</activity>
+            </rai>"
+            .parse()
+            .unwrap();
+        let rai = Rai::try_from(elem).unwrap();
+        let service = BareJid::new("chat.shakespeare.lit").unwrap();
+        let mut subscriptions = ActivitySubscriptions::default();
+        assert!(subscriptions
+            .rooms(&Jid::from(service.clone()), rai.clone())
+            .is_empty());
+
+        subscriptions.services.insert(service.clone());
+        assert_eq!(
+            subscriptions.rooms(&Jid::from(service), rai.clone()),
+            [BareJid::new("coven@chat.shakespeare.lit").unwrap()]
+        );
+        let stranger = Jid::new("chat.montague.lit").unwrap();
+        assert!(subscriptions.rooms(&stranger, rai).is_empty());
+    }
+}
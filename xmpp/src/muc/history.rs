@@ -0,0 +1,214 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Fetching older messages of a room from its own archive, page by page, using Message Archive
+//! Management ([XEP-0313](https://xmpp.org/extensions/xep-0313.html#query-muc)).
+
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        iq::Iq,
+        mam::{Complete, Fin, Query, QueryId, Result_ as MamResult},
+        message::Message,
+        rsm::SetQuery,
+        stanza_error::StanzaError,
+    },
+    BareJid, Element, Jid,
+};
+
+use crate::{
+    delay::{message_time_info, StanzaTimeInfo},
+    iq::pending::PendingIqs,
+    message::receive::dispatch_message,
+    Agent, Event,
+};
+
+/// A history query in progress, with the messages received so far, along with the stanza each
+/// one came in.
+struct PendingHistory {
+    room: BareJid,
+    messages: Vec<(Message, StanzaTimeInfo, Element)>,
+}
+
+/// History queries waiting for the end of their page.
+#[derive(Default)]
+pub(crate) struct RoomHistory {
+    pending: PendingIqs<PendingHistory>,
+}
+
+impl RoomHistory {
+    fn make_query(
+        &mut self,
+        id: String,
+        room: BareJid,
+        before: Option<String>,
+        limit: usize,
+    ) -> Iq {
+        let query = Query {
+            queryid: Some(QueryId(id.clone())),
+            node: None,
+            form: None,
+            set: Some(SetQuery {
+                max: Some(limit),
+                after: None,
+                // An empty before asks for the last page.
+                before: Some(before.unwrap_or_default()),
+                index: None,
+            }),
+            flip_page: false,
+        };
+        let iq = Iq::from_set(id.clone(), query).with_to(Jid::from(room.clone()));
+        self.pending.insert(
+            id,
+            room.clone().into(),
+            PendingHistory {
+                room,
+                messages: Vec::new(),
+            },
+        );
+        iq
+    }
+
+    pub(crate) fn is_pending(&self, queryid: &QueryId) -> bool {
+        self.pending.contains(&queryid.0)
+    }
+
+    /// Keeps a result of one of our queries until the end of its page, if it comes from the
+    /// room’s archive.
+    pub(crate) fn record_result(
+        &mut self,
+        from: Option<&Jid>,
+        result: MamResult,
+        source: &Element,
+    ) {
+        let Some(queryid) = result.queryid else {
+            return;
+        };
+        let Some(from) = from else {
+            return;
+        };
+        let Some(query) = self.pending.get_mut(from, &queryid.0) else {
+            return;
+        };
+        let Some(archived) = result.forwarded.stanza else {
+            return;
+        };
+        let mut time_info = message_time_info(&archived);
+        time_info.delays.extend(result.forwarded.delay);
+        time_info.from_archive = true;
+        time_info.historical = true;
+        query.messages.push((archived, time_info, source.clone()));
+    }
+
+    /// Returns the query answered by this iq, if it comes from its room.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<PendingHistory> {
+        self.pending.take(from, id)
+    }
+}
+
+/// Asks the room for up to `limit` messages before the one with this stanza-id, or for its latest
+/// messages if `before` is None.
+pub async fn fetch_history<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    room: BareJid,
+    before: Option<String>,
+    limit: usize,
+) {
+    let id = agent.client.make_id();
+    let iq = agent.room_history.make_query(id, room, before, limit);
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+/// Reports the messages of a page, oldest first, once the archive is done sending them. Their
+/// events get replayed along with the stanza each message came in, not the `<fin/>` ending the
+/// page.
+pub(crate) async fn handle_fin<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    fin: Fin,
+) -> Vec<Event> {
+    let Some(query) = agent.room_history.take(from, id) else {
+        return vec![];
+    };
+    for (message, time_info, source) in query.messages {
+        if message.from.is_some() {
+            for event in dispatch_message(agent, message, time_info, None).await {
+                agent.replayed_events.push((event, source.clone()));
+            }
+        }
+    }
+    vec![Event::RoomHistoryFetched(
+        query.room,
+        fin.set.first,
+        fin.complete == Complete::True,
+    )]
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let query = agent.room_history.take(from, id)?;
+    Some(Event::RoomHistoryFailed(query.room, error.take()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page() {
+        let room = BareJid::new("coven@chat.shakespeare.lit").unwrap();
+        let mut history = RoomHistory::default();
+        let id = String::from("muc-history1");
+        let iq = history.make_query(id, room.clone(), None, 20);
+        assert_eq!(iq.id, "muc-history1");
+        let query = iq.get_payload::<Query>().unwrap().unwrap();
+        let set = query.set.unwrap();
+        assert_eq!(set.before.as_deref(), Some(""));
+        assert_eq!(set.max, Some(20));
+
+        let result = |from: &str, id: &str| {
+            let message: Element = format!(
+                "<message xmlns='jabber:client' from='{}' to='hag66@shakespeare.lit/pda'>
+                  <result xmlns='urn:xmpp:mam:2' queryid='muc-history1' id='{}'>
+                    <forwarded xmlns='urn:xmpp:forward:0'>
+                      <delay xmlns='urn:xmpp:delay' stamp='2010-07-10T23:08:25Z'/>
+                      <message xmlns='jabber:client' from='coven@chat.shakespeare.lit/firstwitch' type='groupchat'>
+                        <body>Thrice the brinded cat hath mew'd.</body>
+                      </message>
+                    </forwarded>
+                  </result>
+                </message>",
+                from, id
+            )
+            .parse()
+            .unwrap();
+            let source = message.clone();
+            let message = Message::try_from(message).unwrap();
+            let from = message.from.clone();
+            let result = message.get_payload::<MamResult>().unwrap().unwrap();
+            (from, result, source)
+        };
+        let queryid = QueryId(String::from("muc-history1"));
+        assert!(history.is_pending(&queryid));
+        let (from, forged, source) = result("darkcave@chat.shakespeare.lit", "forged");
+        history.record_result(from.as_ref(), forged, &source);
+        let (from, first, source) = result("coven@chat.shakespeare.lit", "BgCcdkI4");
+        history.record_result(from.as_ref(), first, &source);
+
+        let other = Jid::new("darkcave@chat.shakespeare.lit").unwrap();
+        assert!(history.take(&other, "muc-history1").is_none());
+        let query = history.take(&Jid::from(room), "muc-history1").unwrap();
+        assert_eq!(query.messages.len(), 1);
+        assert!(query.messages[0].1.historical);
+        assert_eq!(query.messages[0].2, source);
+        assert!(!history.is_pending(&queryid));
+    }
+}
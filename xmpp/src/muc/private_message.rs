@@ -6,14 +6,11 @@
 
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
-    parsers::{
-        message::{Body, Message, MessageType},
-        muc::user::MucUser,
-    },
+    parsers::{message::Message, muc::user::MucUser},
     BareJid, Jid,
 };
 
-use crate::{Agent, RoomNick};
+use crate::{encryption, encryption::EncryptionError, message::queue, Agent, RoomNick};
 
 pub async fn send_room_private_message<C: ServerConnector>(
     agent: &mut Agent<C>,
@@ -21,12 +18,12 @@ pub async fn send_room_private_message<C: ServerConnector>(
     recipient: RoomNick,
     lang: &str,
     text: &str,
-) {
+) -> Result<(), EncryptionError> {
     let recipient: Jid = room.with_resource_str(&recipient).unwrap().into();
-    let mut message = Message::new(recipient).with_payload(MucUser::new());
-    message.type_ = MessageType::Chat;
-    message
-        .bodies
-        .insert(String::from(lang), Body(String::from(text)));
-    let _ = agent.client.send_stanza(message.into()).await;
+    let message = Message::chat(recipient)
+        .with_payload(MucUser::new())
+        .with_body(String::from(lang), String::from(text));
+    let message = encryption::encrypt_message(&mut agent.encryption, message)?;
+    queue::send_or_queue(agent, message).await;
+    Ok(())
 }
@@ -0,0 +1,148 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Registering a reserved nickname with a room, as per
+//! [XEP-0045 §7.10](https://xmpp.org/extensions/xep-0045.html#register).
+
+use std::collections::HashMap;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        data_forms::{DataForm, DataFormType},
+        ibr::Query,
+        iq::Iq,
+        stanza_error::{DefinedCondition, ErrorType, StanzaError},
+    },
+    BareJid, Element, Jid,
+};
+
+use crate::{bob, iq::pending::PendingIqs, Agent, Event};
+
+enum Step {
+    /// Waiting for the registration form.
+    Form,
+    /// Waiting for the outcome of the submitted form.
+    Submit,
+}
+
+/// Registration requests waiting for an answer from their room.
+#[derive(Default)]
+pub(crate) struct Registrations {
+    pending: PendingIqs<(BareJid, Step)>,
+}
+
+impl Registrations {
+    fn insert(&mut self, id: String, room: BareJid, step: Step) {
+        self.pending.insert(id, room.clone().into(), (room, step));
+    }
+
+    /// Returns the room and step of a pending request, if this answer comes from its room.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<(BareJid, Step)> {
+        self.pending.take(from, id)
+    }
+}
+
+fn make_query(form: Option<DataForm>) -> Query {
+    Query {
+        fields: HashMap::new(),
+        registered: false,
+        remove: false,
+        form,
+        data: Vec::new(),
+    }
+}
+
+/// Asks the room for its registration form, see [Event::RoomRegistrationForm].
+pub async fn request_registration_form<C: ServerConnector>(agent: &mut Agent<C>, room: BareJid) {
+    let id = agent.client.make_id();
+    agent
+        .muc_registrations
+        .insert(id.clone(), room.clone(), Step::Form);
+    let iq = Iq::builder().to(room.into()).id(id).get(make_query(None));
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+/// Submits the registration form filled from [Event::RoomRegistrationForm].
+pub async fn register_nick<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    room: BareJid,
+    mut form: DataForm,
+) {
+    form.type_ = DataFormType::Submit;
+    let id = agent.client.make_id();
+    agent
+        .muc_registrations
+        .insert(id.clone(), room.clone(), Step::Submit);
+    let iq = Iq::from_set(id, make_query(Some(form))).with_to(room.into());
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+pub(crate) fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    payload: Option<Element>,
+) -> Option<Event> {
+    let (room, step) = agent.muc_registrations.take(from, id)?;
+    let mut query = payload.map(Query::try_from);
+    // The media of a captcha form reference Bits of Binary sent along.
+    if let Some(Ok(query)) = &mut query {
+        for data in query.data.drain(..) {
+            bob::handle_data(agent, data);
+        }
+    }
+    Some(match (step, query) {
+        (Step::Submit, _) => Event::RoomRegistered(room),
+        (Step::Form, Some(Ok(query))) if query.registered => Event::RoomRegistered(room),
+        (
+            Step::Form,
+            Some(Ok(Query {
+                form: Some(form), ..
+            })),
+        ) => Event::RoomRegistrationForm(room, form),
+        (Step::Form, _) => {
+            let error = StanzaError::new(
+                ErrorType::Cancel,
+                DefinedCondition::UndefinedCondition,
+                "en",
+                "The room sent no registration form.",
+            );
+            Event::RoomRegistrationFailed(room, error)
+        }
+    })
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let (room, _) = agent.muc_registrations.take(from, id)?;
+    Some(Event::RoomRegistrationFailed(room, error.take()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending() {
+        let room = BareJid::new("coven@chat.shakespeare.lit").unwrap();
+        let mut registrations = Registrations::default();
+        let id = String::from("register");
+        registrations.insert(id.clone(), room.clone(), Step::Form);
+
+        let other = Jid::new("darkcave@chat.shakespeare.lit").unwrap();
+        assert!(registrations.take(&other, &id).is_none());
+        let occupant = Jid::new("coven@chat.shakespeare.lit/thirdwitch").unwrap();
+        assert!(registrations.take(&occupant, &id).is_none());
+
+        let (taken, _) = registrations.take(&Jid::from(room.clone()), &id).unwrap();
+        assert_eq!(taken, room);
+        assert!(registrations.take(&Jid::from(room), &id).is_none());
+    }
+}
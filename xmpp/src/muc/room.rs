@@ -7,10 +7,15 @@
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
     parsers::{
-        muc::Muc,
+        message::{Message, MessageType},
+        muc::{
+            muc::History,
+            user::{Invite, MucUser},
+            Muc,
+        },
         presence::{Presence, Type as PresenceType},
     },
-    BareJid,
+    BareJid, Jid,
 };
 
 use crate::{Agent, RoomNick};
@@ -23,16 +28,47 @@ pub async fn join_room<C: ServerConnector>(
     lang: &str,
     status: &str,
 ) {
-    let mut muc = Muc::new();
+    agent.quiet_rooms.remove(&room);
+    send_join(agent, room, nick, password, Muc::new(), lang, status).await
+}
+
+/// Joins a room without receiving its history, and ignoring the presences of its occupants, e.g.
+/// for a bot watching many rooms which only cares about new messages.
+///
+/// The room still sends these presences, but no event, avatar fetch or idle tracking comes out of
+/// them; only our own presence gets reported, with
+/// [Event::RoomJoined](crate::Event::RoomJoined) and [Event::RoomLeft](crate::Event::RoomLeft).
+pub async fn join_room_quietly<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    room: BareJid,
+    nick: Option<String>,
+    password: Option<String>,
+) {
+    agent.quiet_rooms.insert(room.clone());
+    let muc = Muc::new().with_history(History::new().with_maxstanzas(0));
+    send_join(agent, room, nick, password, muc, "", "").await
+}
+
+async fn send_join<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    room: BareJid,
+    nick: Option<String>,
+    password: Option<String>,
+    mut muc: Muc,
+    lang: &str,
+    status: &str,
+) {
     if let Some(password) = password {
+        agent.room_passwords.insert(room.clone(), password.clone());
         muc = muc.with_password(password);
     }
 
     let nick = nick.unwrap_or_else(|| agent.default_nick.read().unwrap().clone());
     let room_jid = room.with_resource_str(&nick).unwrap();
-    let mut presence = Presence::new(PresenceType::None).with_to(room_jid);
-    presence.add_payload(muc);
-    presence.set_status(String::from(lang), String::from(status));
+    let presence = Presence::new(PresenceType::None)
+        .with_to(room_jid)
+        .with_payload(muc)
+        .with_status(lang, status);
     let _ = agent.client.send_stanza(presence.into()).await;
 }
 
@@ -64,7 +100,7 @@ pub async fn leave_room<C: ServerConnector>(
 ) {
     // XEP-0045 specifies that, to leave a room, the client must send a presence stanza
     // with type="unavailable".
-    let mut presence = Presence::new(PresenceType::Unavailable).with_to(
+    let presence = Presence::new(PresenceType::Unavailable).with_to(
         room_jid
             .with_resource_str(nickname.as_str())
             .expect("Invalid room JID after adding resource part."),
@@ -73,7 +109,7 @@ pub async fn leave_room<C: ServerConnector>(
     // Optionally, the client may include a status message in the presence stanza.
     // TODO: Should this be optional? The XEP says "MAY", but the method signature requires the arguments.
     // XEP-0045: "The occupant MAY include normal <status/> information in the unavailable presence stanzas"
-    presence.set_status(lang, status);
+    let presence = presence.with_status(lang, status);
 
     // Send the presence stanza.
     if let Err(e) = agent.client.send_stanza(presence.into()).await {
@@ -81,3 +117,60 @@ pub async fn leave_room<C: ServerConnector>(
         error!("Failed to send leave room presence: {}", e);
     }
 }
+
+/// Leaves a room we are in, after its bookmark got removed or lost its autojoin flag.
+pub(crate) async fn leave_bookmarked_room<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    room: &BareJid,
+) {
+    let Some(nick) = agent.joined_rooms.get(room).cloned() else {
+        return;
+    };
+    let Ok(occupant) = room.with_resource_str(&nick) else {
+        return;
+    };
+    let presence = Presence::new(PresenceType::Unavailable).with_to(occupant);
+    if let Err(e) = agent.client.send_stanza(presence.into()).await {
+        error!("Failed to send leave room presence: {}", e);
+    }
+}
+
+/// Leaves every room we are in, after all bookmarks got removed.
+pub(crate) async fn leave_all_rooms<C: ServerConnector>(agent: &mut Agent<C>) {
+    let rooms: Vec<BareJid> = agent.joined_rooms.keys().cloned().collect();
+    for room in rooms {
+        leave_bookmarked_room(agent, &room).await;
+    }
+}
+
+/// Changes the subject of a room, or clears it when empty
+/// ([XEP-0045 §8.1](https://xmpp.org/extensions/xep-0045.html#subject-mod)).
+///
+/// If allowed, the room reports the new subject with an [Event::RoomSubjectChanged](crate::Event::RoomSubjectChanged).
+pub async fn set_subject<C: ServerConnector>(agent: &mut Agent<C>, room: BareJid, subject: String) {
+    let mut message = Message::new(Jid::from(room)).with_subject(String::new(), subject);
+    message.type_ = MessageType::Groupchat;
+    let _ = agent.client.send_stanza(message.into()).await;
+}
+
+/// Invites someone to a room, through the room which forwards the invitation
+/// ([XEP-0045 §7.8.2](https://xmpp.org/extensions/xep-0045.html#invite-mediated)).
+///
+/// The password the room was joined with, e.g. from its bookmark, is sent along.
+pub async fn invite<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    room: BareJid,
+    invitee: BareJid,
+    reason: Option<String>,
+) {
+    let mut invite = Invite::new(invitee.into());
+    if let Some(reason) = reason {
+        invite = invite.with_reason(reason);
+    }
+    let mut muc_user = MucUser::new().with_invite(invite);
+    if let Some(password) = agent.room_passwords.get(&room) {
+        muc_user = muc_user.with_password(password.as_str());
+    }
+    let message = Message::new(Jid::from(room)).with_payload(muc_user);
+    let _ = agent.client.send_stanza(message.into()).await;
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Pinging our server ([XEP-0199](https://xmpp.org/extensions/xep-0199.html)), to check that the
+//! connection is still alive and measure its round-trip time, also reported in
+//! [ClientStats::last_ping_rtt](tokio_xmpp::ClientStats::last_ping_rtt).
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::rt::Instant;
+use tokio_xmpp::{
+    parsers::{iq::Iq, ping::Ping},
+    IqResponse,
+};
+
+use crate::{disco, Agent, Error};
+
+/// Future resolving to the round-trip time of a ping sent with [Agent::ping](crate::Agent::ping).
+///
+/// The answer only arrives while [Agent::wait_for_events](crate::Agent::wait_for_events) keeps
+/// being called.
+pub struct Pong {
+    response: Result<IqResponse, Option<Error>>,
+    sent: Instant,
+}
+
+impl Future for Pong {
+    type Output = Result<Duration, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let sent = self.sent;
+        match self.response {
+            // Any answer, even an error from a server not supporting pings, proves the connection
+            // alive.
+            Ok(ref mut response) => match Pin::new(response).poll(cx) {
+                Poll::Ready(Ok(_)) => Poll::Ready(Ok(sent.elapsed())),
+                Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+                Poll::Pending => Poll::Pending,
+            },
+            Err(ref mut error) => Poll::Ready(Err(error.take().unwrap_or(Error::InvalidState))),
+        }
+    }
+}
+
+/// Pings our server, see [Agent::ping](crate::Agent::ping).
+pub async fn ping<C: ServerConnector>(agent: &mut Agent<C>) -> Pong {
+    let sent = Instant::now();
+    let Some(server) = disco::server(agent) else {
+        return Pong {
+            response: Err(Some(Error::InvalidState)),
+            sent,
+        };
+    };
+    let iq = Iq::from_get(agent.client.make_id(), Ping).with_to(server.into());
+    Pong {
+        response: agent.client.send_iq(iq).await.map_err(Some),
+        sent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientBuilder, Event};
+    use tokio_xmpp::connect::mock::{MockConnector, MockServer};
+    use tokio_xmpp::parsers::{iq::IqType, ns};
+    use tokio_xmpp::BareJid;
+
+    #[tokio::test]
+    async fn measured() {
+        let server = MockServer::new().with_handler(|elem| match Iq::try_from(elem) {
+            Ok(Iq {
+                to,
+                id,
+                payload: IqType::Get(payload),
+                ..
+            }) if payload.is("ping", ns::PING) => {
+                let pong = Iq {
+                    from: to,
+                    to: None,
+                    id,
+                    payload: IqType::Result(None),
+                };
+                vec![pong.into()]
+            }
+            _ => vec![],
+        });
+        let jid = BareJid::new("romeo@montague.lit").unwrap();
+        let mut agent =
+            ClientBuilder::new_with_connector(jid, "password", MockConnector::new(server)).build();
+        assert!(agent.ping().await.await.is_err());
+        while let Some(events) = agent.wait_for_events().await {
+            if events.iter().any(|event| matches!(event, Event::Online)) {
+                break;
+            }
+        }
+        assert_eq!(agent.stats().last_ping_rtt, None);
+
+        let mut pong = agent.ping().await;
+        let rtt = loop {
+            tokio::select! {
+                rtt = &mut pong => break rtt.unwrap(),
+                events = agent.wait_for_events() => assert!(events.is_some()),
+            }
+        };
+        assert!(agent
+            .stats()
+            .last_ping_rtt
+            .is_some_and(|measured| measured <= rtt));
+    }
+}
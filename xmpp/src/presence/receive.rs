@@ -5,32 +5,54 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use tokio_xmpp::connect::ServerConnector;
+#[cfg(feature = "avatars")]
+use tokio_xmpp::parsers::vcard_update::VCardUpdate;
 use tokio_xmpp::parsers::{
     muc::user::{MucUser, Status},
     presence::{Presence, Type as PresenceType},
 };
 
-use crate::{Agent, Event};
+#[cfg(feature = "avatars")]
+use crate::pubsub::avatar;
+use crate::{idle, Agent, Event};
 
 /// Translate a `Presence` stanza into a list of higher-level `Event`s.
 pub async fn handle_presence<C: ServerConnector>(
-    _agent: &mut Agent<C>,
+    agent: &mut Agent<C>,
     presence: Presence,
 ) -> Vec<Event> {
     // Allocate an empty vector to store the events.
     let mut events = vec![];
 
     // Extract the JID of the sender (i.e. the one whose presence is being sent).
-    let from = presence.from.unwrap().to_bare();
+    let full_from = presence.from.as_ref().unwrap();
+    let from = full_from.to_bare();
 
     // Search through the payloads for a MUC user status.
-
-    if let Some(muc) = presence
-        .payloads
-        .iter()
-        .filter_map(|p| MucUser::try_from(p.clone()).ok())
-        .next()
+    let muc = presence.get_payload::<MucUser>();
+    // Occupants of quietly joined rooms are ignored, only our own presence and errors matter.
+    let self_presence = matches!(&muc, Some(Ok(muc)) if muc.status.contains(&Status::SelfPresence));
+    if agent.quiet_rooms.contains(&from) && !self_presence && presence.type_ != PresenceType::Error
     {
+        return events;
+    }
+
+    #[cfg(feature = "avatars")]
+    if let Some(Ok(update)) = presence.get_payload::<VCardUpdate>() {
+        // Room occupants are told apart by their nickname.
+        let jid = if agent.joined_rooms.contains_key(&from) {
+            full_from.clone()
+        } else {
+            from.clone().into()
+        };
+        events.extend(avatar::handle_vcard_update(agent, jid, update).await);
+    }
+
+    if muc.is_none() {
+        events.extend(idle::handle_presence(agent, full_from, &presence));
+    }
+
+    if let Some(Ok(muc)) = muc {
         // If a MUC user status was found, search through the statuses for a self-presence.
         if muc.status.iter().any(|s| *s == Status::SelfPresence) {
             // If a self-presence was found, then the stanza is about the client's own presence.
@@ -38,10 +60,17 @@ pub async fn handle_presence<C: ServerConnector>(
             match presence.type_ {
                 PresenceType::None => {
                     // According to https://xmpp.org/extensions/xep-0045.html#enter-pres, no type should be seen as "available".
+                    // Remember our nickname, to leave the room when its bookmark goes away.
+                    if let Some(nick) = full_from.resource() {
+                        agent.joined_rooms.insert(from.clone(), nick.to_string());
+                    }
                     events.push(Event::RoomJoined(from.clone()));
                 }
                 PresenceType::Unavailable => {
                     // According to https://xmpp.org/extensions/xep-0045.html#exit, the server will use type "unavailable" to notify the client that it has left the room/
+                    agent.joined_rooms.remove(&from);
+                    agent.quiet_rooms.remove(&from);
+                    agent.room_passwords.remove(&from);
                     events.push(Event::RoomLeft(from.clone()));
                 }
                 _ => unimplemented!("Presence type {:?}", presence.type_), // TODO: What to do here?
@@ -49,6 +78,12 @@ pub async fn handle_presence<C: ServerConnector>(
         }
     }
 
+    // An error from a room we aren't in yet means joining it failed, forget its password.
+    if presence.type_ == PresenceType::Error && !agent.joined_rooms.contains_key(&from) {
+        agent.room_passwords.remove(&from);
+        agent.quiet_rooms.remove(&from);
+    }
+
     // Return the list of events.
     events
 }
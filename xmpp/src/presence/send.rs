@@ -4,19 +4,41 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::parsers::{
     caps::{compute_disco, hash_caps, Caps},
-    disco::DiscoInfoResult,
     hashes::Algo,
     presence::{Presence, Type as PresenceType},
 };
 
-pub(crate) fn make_initial_presence(disco: &DiscoInfoResult, node: &str) -> Presence {
-    let caps_data = compute_disco(disco);
+use crate::Agent;
+
+/// Sends our presence to our contacts, and to every room we are in since rooms don’t get our
+/// broadcast presence, only the directed ones.
+pub(crate) async fn broadcast_presence<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    presence: Presence,
+) {
+    let occupants: Vec<_> = agent
+        .joined_rooms
+        .iter()
+        .filter_map(|(room, nick)| room.with_resource_str(nick).ok())
+        .collect();
+    for occupant in occupants {
+        let directed = presence.clone().with_to(occupant);
+        let _ = agent.client.send_stanza(directed.into()).await;
+    }
+    let _ = agent.client.send_stanza(presence.into()).await;
+}
+
+pub(crate) fn make_initial_presence<C: ServerConnector>(agent: &Agent<C>) -> Presence {
+    let caps_data = compute_disco(&agent.disco);
     let hash = hash_caps(&caps_data, Algo::Sha_1).unwrap();
-    let caps = Caps::new(node, hash);
+    let caps = Caps::new(&agent.node, hash);
 
-    let mut presence = Presence::new(PresenceType::None);
-    presence.add_payload(caps);
+    #[cfg_attr(not(feature = "avatars"), allow(unused_mut))]
+    let mut presence = Presence::new(PresenceType::None).with_payload(caps);
+    #[cfg(feature = "avatars")]
+    crate::pubsub::avatar::add_update(agent, &mut presence);
     presence
 }
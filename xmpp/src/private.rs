@@ -0,0 +1,270 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Storing small private data on the account, e.g. client settings, with Private XML Storage
+//! ([XEP-0049](https://xmpp.org/extensions/xep-0049.html)), which even legacy servers support.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        iq::{Iq, IqType},
+        private::Data,
+        stanza_error::StanzaError,
+    },
+    BareJid, Element, IqResponse, Jid,
+};
+
+use crate::{iq::pending::PendingIqs, Agent, Error, Event};
+
+/// Private XML Storage requests waiting for the confirmation that an element got stored.
+#[derive(Default)]
+pub(crate) struct PrivateStorage {
+    pending: PendingIqs<String>,
+}
+
+impl PrivateStorage {
+    fn insert(&mut self, id: String, own_jid: BareJid, namespace: String) {
+        self.pending.insert(id, own_jid.into(), namespace);
+    }
+
+    pub(crate) fn is_pending(&self, id: &str) -> bool {
+        self.pending.contains(id)
+    }
+
+    /// Returns the namespace of a pending request, if this answer comes from our account.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<String> {
+        self.pending.take(from, id)
+    }
+}
+
+/// Why [Agent::private_get](crate::Agent::private_get) failed.
+#[derive(Debug)]
+pub enum PrivateError {
+    /// The server refused the request.
+    Refused(StanzaError),
+    /// The server sent no element, or one which isn’t of the requested type.
+    Invalid(Box<dyn StdError + Send + Sync>),
+    /// The request couldn’t be sent, or got no answer.
+    Client(Error),
+}
+
+impl fmt::Display for PrivateError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrivateError::Refused(error) => {
+                write!(fmt, "request refused: {:?}", error.defined_condition)
+            }
+            PrivateError::Invalid(error) => write!(fmt, "invalid private data: {}", error),
+            PrivateError::Client(error) => write!(fmt, "{}", error),
+        }
+    }
+}
+
+impl StdError for PrivateError {}
+
+/// Future resolving to the element requested with
+/// [Agent::private_get](crate::Agent::private_get), as a `T`.
+///
+/// The answer only arrives while [Agent::wait_for_events](crate::Agent::wait_for_events) keeps
+/// being called.
+pub struct PrivateData<T> {
+    response: Option<IqResponse>,
+    error: Option<Error>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T> Future for PrivateData<T>
+where
+    T: TryFrom<Element>,
+    T::Error: StdError + Send + Sync + 'static,
+{
+    type Output = Result<T, PrivateError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let Some(ref mut response) = self.response else {
+            let error = self.error.take().unwrap_or(Error::InvalidState);
+            return Poll::Ready(Err(PrivateError::Client(error)));
+        };
+        let iq = match Pin::new(response).poll(cx) {
+            Poll::Ready(Ok(iq)) => iq,
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(PrivateError::Client(error))),
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(match iq.payload {
+            IqType::Result(Some(payload)) => parse(payload).map_err(PrivateError::Invalid),
+            IqType::Error(error) => Err(PrivateError::Refused(error)),
+            _ => Err(PrivateError::Invalid(
+                "The server sent no private data.".into(),
+            )),
+        })
+    }
+}
+
+fn parse<T>(payload: Element) -> Result<T, Box<dyn StdError + Send + Sync>>
+where
+    T: TryFrom<Element>,
+    T::Error: StdError + Send + Sync + 'static,
+{
+    let data = Data::try_from(payload)?;
+    Ok(T::try_from(data.payload)?)
+}
+
+/// Asks for the element of this name and namespace, see
+/// [Agent::private_get](crate::Agent::private_get).
+pub async fn get<C: ServerConnector, T>(
+    agent: &mut Agent<C>,
+    name: &str,
+    namespace: &str,
+) -> PrivateData<T> {
+    let iq = Iq::from_get(agent.client.make_id(), Data::request(name, namespace));
+    let (response, error) = match agent.client.send_iq(iq).await {
+        Ok(response) => (Some(response), None),
+        Err(error) => (None, Some(error)),
+    };
+    PrivateData {
+        response,
+        error,
+        _payload: PhantomData,
+    }
+}
+
+/// Stores this element, replacing the previous one of the same name and namespace, see
+/// [Event::PrivateDataStored].
+pub async fn set<C: ServerConnector>(agent: &mut Agent<C>, payload: Element) {
+    let Some(own_jid) = agent.client.bound_jid().map(Jid::to_bare) else {
+        return;
+    };
+    let id = agent.client.make_id();
+    let namespace = payload.ns();
+    let iq = Iq::from_set(id.clone(), Data::new(payload));
+    agent.private_storage.insert(id, own_jid, namespace);
+    let _ = agent.client.send_stanza(iq.into()).await;
+}
+
+pub(crate) fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+) -> Option<Event> {
+    let namespace = agent.private_storage.take(from, id)?;
+    Some(Event::PrivateDataStored(namespace))
+}
+
+pub(crate) fn handle_error<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    error: &mut Option<StanzaError>,
+) -> Option<Event> {
+    let namespace = agent.private_storage.take(from, id)?;
+    Some(Event::PrivateDataFailed(namespace, error.take()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClientBuilder;
+    use tokio_xmpp::connect::mock::{MockConnector, MockServer};
+    use tokio_xmpp::parsers::{roster::Roster, stanza_error::DefinedCondition};
+
+    #[test]
+    fn pending() {
+        let own_jid = BareJid::new("hamlet@shakespeare.lit").unwrap();
+        let mut storage = PrivateStorage::default();
+        let id = String::from("private");
+        let namespace = String::from("exodus:prefs");
+        storage.insert(id.clone(), own_jid.clone(), namespace);
+        assert!(storage.is_pending(&id));
+
+        let other = Jid::new("horatio@shakespeare.lit").unwrap();
+        assert!(storage.take(&other, &id).is_none());
+        let resource = Jid::new("hamlet@shakespeare.lit/denmark").unwrap();
+        assert!(storage.take(&resource, &id).is_none());
+
+        let namespace = storage.take(&Jid::from(own_jid), &id).unwrap();
+        assert_eq!(namespace, "exodus:prefs");
+        assert!(!storage.is_pending(&id));
+    }
+
+    /// A fake server storing an `<prefs xmlns="exodus:prefs"/>` element, refusing any other.
+    fn server() -> MockServer {
+        MockServer::new().with_handler(|elem| {
+            let Ok(iq) = Iq::try_from(elem) else {
+                return vec![];
+            };
+            let IqType::Get(payload) = iq.payload else {
+                return vec![];
+            };
+            let Ok(data) = Data::try_from(payload) else {
+                return vec![];
+            };
+            let answer = if data.payload.is("prefs", "exodus:prefs") {
+                let prefs = Element::builder("prefs", "exodus:prefs")
+                    .append(Element::builder("defaultnick", "exodus:prefs").append("Hamlet"))
+                    .build();
+                Iq::from_result(iq.id, Some(Data::new(prefs)))
+            } else {
+                Iq::from_error(
+                    iq.id,
+                    StanzaError::from_condition(DefinedCondition::ItemNotFound),
+                )
+            };
+            vec![answer.into()]
+        })
+    }
+
+    async fn get<T>(
+        agent: &mut Agent<MockConnector>,
+        name: &str,
+        ns: &str,
+    ) -> Result<T, PrivateError>
+    where
+        T: TryFrom<Element>,
+        T::Error: StdError + Send + Sync + 'static,
+    {
+        let mut data = agent.private_get::<T>(name, ns).await;
+        loop {
+            tokio::select! {
+                result = &mut data => return result,
+                events = agent.wait_for_events() => assert!(events.is_some()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn typed_get() {
+        let jid = BareJid::new("hamlet@shakespeare.lit").unwrap();
+        let mut agent =
+            ClientBuilder::new_with_connector(jid, "password", MockConnector::new(server()))
+                .build();
+        while let Some(events) = agent.wait_for_events().await {
+            if events.iter().any(|event| matches!(event, Event::Online)) {
+                break;
+            }
+        }
+
+        let prefs: Element = get(&mut agent, "prefs", "exodus:prefs").await.unwrap();
+        assert_eq!(
+            prefs
+                .get_child("defaultnick", "exodus:prefs")
+                .unwrap()
+                .text(),
+            "Hamlet"
+        );
+
+        let result = get::<Roster>(&mut agent, "prefs", "exodus:prefs").await;
+        assert!(matches!(result, Err(PrivateError::Invalid(_))));
+
+        let result = get::<Element>(&mut agent, "storage", "storage:rosternotes").await;
+        assert!(matches!(result, Err(PrivateError::Refused(_))));
+    }
+}
@@ -5,21 +5,206 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use super::Agent;
-use crate::Event;
+use crate::{iq::pending::PendingIqs, Event};
+#[cfg(feature = "image")]
+use image::{imageops::FilterType, DynamicImage, ImageError, ImageFormat};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
+#[cfg(feature = "image")]
+use std::io::Cursor;
 use std::io::{self, Write};
+#[cfg(feature = "image")]
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use tokio_xmpp::connect::ServerConnector;
+#[cfg(feature = "image")]
+use tokio_xmpp::parsers::{
+    avatar::Info,
+    hashes::Sha1HexAttribute,
+    pubsub::{pubsub::Publish, PubSubPayload},
+};
 use tokio_xmpp::parsers::{
     avatar::{Data, Metadata},
+    caps::hash_caps,
+    hashes::{Algo, Hash},
     iq::Iq,
     ns,
+    presence::Presence,
     pubsub::{
         event::Item,
-        pubsub::{Items, PubSub},
-        NodeName,
+        pubsub::{Item as PubSubItem, Items, PubSub},
+        Item as ItemContent, ItemId, NodeName,
     },
-    Jid,
+    vcard::VCard,
+    vcard_update::{Photo, VCardUpdate},
+    Element, Jid,
 };
+use tokio_xmpp::rt;
+
+/// The side of the avatars we retrieve, in pixels, as recommended by XEP-0084.
+const AVATAR_SIZE: u16 = 64;
+
+/// How long to wait for an avatar hosted over HTTP.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The sides of the published avatars, in pixels: XEP-0084 recommends 64, and at most 96.
+#[cfg(feature = "image")]
+const AVATAR_SIZES: [u32; 2] = [64, 96];
+
+/// The avatars advertised in presences, as per
+/// [XEP-0153](https://xmpp.org/extensions/xep-0153.html), for contacts on servers without PEP and
+/// room occupants. Servers implementing [XEP-0398](https://xmpp.org/extensions/xep-0398.html)
+/// advertise the PEP avatar this way, so both share the same hash and the same cached file.
+#[derive(Default)]
+pub(crate) struct VCardAvatars {
+    /// The hash last advertised by each entity, to only fetch new avatars.
+    hashes: HashMap<Jid, String>,
+    /// The vCards requested, and the hash of the avatar we expect in them.
+    pending: PendingIqs<(Jid, String)>,
+    /// The hash of our own avatar, advertised in our presence.
+    own_hash: Option<[u8; 20]>,
+}
+
+impl VCardAvatars {
+    /// Returns the entity and expected hash of a pending request, if this answer comes from it.
+    fn take(&mut self, from: &Jid, id: &str) -> Option<(Jid, String)> {
+        self.pending.take(from, id)
+    }
+
+    /// Records the hash advertised by this entity, returning it if it changed.
+    fn update(&mut self, jid: &Jid, update: VCardUpdate) -> Option<String> {
+        // An empty update means the sender doesn’t know its avatar yet.
+        let photo = update.photo?;
+        let Some(hash) = photo.data else {
+            self.hashes.remove(jid);
+            return None;
+        };
+        let hash = Hash::new(Algo::Sha_1, hash.to_vec()).to_hex();
+        if self.hashes.get(jid) == Some(&hash) {
+            return None;
+        }
+        self.hashes.insert(jid.clone(), hash.clone());
+        Some(hash)
+    }
+}
+
+/// Reported by the tasks downloading avatars hosted over HTTP: the publisher, the avatar id, and
+/// its data.
+pub(crate) type Fetched = (Jid, String, Result<Vec<u8>, String>);
+
+/// The avatars being downloaded over HTTP, in their own tasks so the agent keeps handling events
+/// meanwhile.
+pub(crate) struct AvatarFetches {
+    /// The publisher and id of each avatar being downloaded, to download it only once.
+    running: HashSet<(Jid, String)>,
+    sender: UnboundedSender<Fetched>,
+    pub(crate) receiver: UnboundedReceiver<Fetched>,
+}
+
+impl Default for AvatarFetches {
+    fn default() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        AvatarFetches {
+            running: HashSet::new(),
+            sender,
+            receiver,
+        }
+    }
+}
+
+/// Adds our avatar hash to this presence, once known.
+pub(crate) fn add_update<C: ServerConnector>(agent: &Agent<C>, presence: &mut Presence) {
+    if let Some(hash) = agent.vcard_avatars.own_hash {
+        presence.add_payload(VCardUpdate {
+            photo: Some(Photo { data: Some(hash) }),
+        });
+    }
+}
+
+/// Sets the hash of our own avatar, rebroadcasting our presence if it changed.
+async fn set_own_hash<C: ServerConnector>(agent: &mut Agent<C>, hash: [u8; 20]) {
+    if agent.vcard_avatars.own_hash.replace(hash) != Some(hash) {
+        crate::disco::advertise_changes(agent).await;
+    }
+}
+
+/// Reports the avatar advertised in a presence, from the cache, or requests the vCard holding it.
+/// Room occupants are identified by their full JID, anyone else by their bare JID.
+pub(crate) async fn handle_vcard_update<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    jid: Jid,
+    update: VCardUpdate,
+) -> Vec<Event> {
+    let Some(hash) = agent.vcard_avatars.update(&jid, update) else {
+        return vec![];
+    };
+    let filename = match avatar_path(&jid, &hash) {
+        Ok((_, filename)) => filename,
+        Err(e) => {
+            error!("Invalid avatar of {}: {}", jid, e);
+            return vec![];
+        }
+    };
+    if fs::metadata(&filename).is_ok() {
+        return vec![Event::AvatarRetrieved(jid, filename)];
+    }
+    let id = agent.client.make_id();
+    agent
+        .vcard_avatars
+        .pending
+        .insert(id.clone(), jid.clone(), (jid.clone(), hash));
+    let iq = Iq::builder().to(jid).id(id).get(VCard { photo: None });
+    let _ = agent.client.send_stanza(iq.into()).await;
+    vec![]
+}
+
+/// Saves the avatar of a requested vCard, if it is the one advertised. An empty answer means the
+/// entity has no vCard, hence no avatar.
+pub(crate) fn handle_vcard_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: &Jid,
+    id: &str,
+    payload: Option<Element>,
+) -> Vec<Event> {
+    let Some((jid, hash)) = agent.vcard_avatars.take(from, id) else {
+        return vec![];
+    };
+    let Some(payload) = payload else {
+        return vec![];
+    };
+    let photo = match VCard::try_from(payload) {
+        Ok(VCard { photo: Some(photo) }) => photo,
+        Ok(VCard { photo: None }) => return vec![],
+        Err(e) => {
+            error!("Error parsing the vCard of {}: {}", jid, e);
+            return vec![];
+        }
+    };
+    let data = photo.binval.data;
+    match hash_caps(&data, Algo::Sha_1) {
+        Ok(actual) if actual.to_hex() == hash => (),
+        _ => {
+            error!(
+                "The vCard avatar of {} doesn’t match the announced hash",
+                jid
+            );
+            return vec![];
+        }
+    }
+    match save_avatar(&jid, hash, &data) {
+        Ok(filename) => vec![Event::AvatarRetrieved(jid, filename)],
+        Err(e) => {
+            error!("Error saving avatar: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Forgets a vCard request which failed.
+pub(crate) fn handle_vcard_error<C: ServerConnector>(agent: &mut Agent<C>, from: &Jid, id: &str) {
+    agent.vcard_avatars.take(from, id);
+}
 
 pub(crate) async fn handle_metadata_pubsub_event<C: ServerConnector>(
     from: &Jid,
@@ -27,25 +212,58 @@ pub(crate) async fn handle_metadata_pubsub_event<C: ServerConnector>(
     items: Vec<Item>,
 ) -> Vec<Event> {
     let mut events = Vec::new();
+    let own_jid = agent.client.bound_jid().map(|jid| jid.to_bare());
     for item in items {
         let payload = item.payload.clone().unwrap();
         if payload.is("metadata", ns::AVATAR_METADATA) {
             match Metadata::try_from(payload) {
                 Ok(metadata) => {
-                    for info in metadata.infos {
-                        let filename = format!("data/{}/{}", from, &*info.id.to_hex());
-                        let file_length = match fs::metadata(filename.clone()) {
-                            Ok(metadata) => metadata.len(),
-                            Err(_) => 0,
-                        };
-                        // TODO: Also check the hash.
-                        if info.bytes as u64 == file_length {
-                            events.push(Event::AvatarRetrieved(from.clone(), filename));
-                        } else {
-                            let iq = download_avatar(from);
-                            let _ = agent.client.send_stanza(iq.into()).await;
+                    // Our own avatar, maybe published by another client.
+                    if own_jid.as_ref().is_some_and(|own_jid| from == own_jid) {
+                        let hash = metadata.infos.first().map(|info| info.id.hash.clone());
+                        if let Some(Ok(hash)) = hash.map(<[u8; 20]>::try_from) {
+                            set_own_hash(agent, hash).await;
                         }
                     }
+                    let Some(info) = metadata.best_info(AVATAR_SIZE) else {
+                        continue;
+                    };
+                    let id = info.id.to_hex();
+                    let filename = match avatar_path(from, &id) {
+                        Ok((_, filename)) => filename,
+                        Err(e) => {
+                            error!("Invalid avatar of {}: {}", from, e);
+                            continue;
+                        }
+                    };
+                    let file_length = match fs::metadata(filename.clone()) {
+                        Ok(metadata) => metadata.len(),
+                        Err(_) => 0,
+                    };
+                    // TODO: Also check the hash.
+                    if info.bytes as u64 == file_length {
+                        events.push(Event::AvatarRetrieved(from.clone(), filename));
+                    } else if let Some(ref url) = info.url {
+                        if !agent
+                            .avatar_fetches
+                            .running
+                            .insert((from.clone(), id.clone()))
+                        {
+                            continue;
+                        }
+                        let url = url.clone();
+                        let (bytes, hash) = (info.bytes, info.id.hash.clone());
+                        let (from, sender) = (from.clone(), agent.avatar_fetches.sender.clone());
+                        rt::spawn(async move {
+                            let data = fetch_avatar(&url, bytes, &hash)
+                                .await
+                                .map_err(|e| format!("Error fetching avatar from {}: {}", url, e));
+                            let _ = sender.send((from, id, data));
+                        });
+                    } else {
+                        let iq = download_avatar(agent.client.make_id(), from, id);
+                        let _ = agent.client.send_stanza(iq.into()).await;
+                    }
                 }
 
                 Err(e) => error!("Error parsing avatar metadata: {}", e),
@@ -55,19 +273,94 @@ pub(crate) async fn handle_metadata_pubsub_event<C: ServerConnector>(
     events
 }
 
-fn download_avatar(from: &Jid) -> Iq {
+/// Requests the data of this version of the avatar from the data node.
+fn download_avatar(iq_id: String, from: &Jid, id: String) -> Iq {
+    let item = ItemContent {
+        id: Some(ItemId(id)),
+        publisher: None,
+        payload: None,
+    };
     Iq::from_get(
-        "coucou",
+        iq_id,
         PubSub::Items(Items {
             max_items: None,
             node: NodeName(String::from(ns::AVATAR_DATA)),
             subid: None,
-            items: Vec::new(),
+            items: vec![PubSubItem(item)],
         }),
     )
     .with_to(from.clone())
 }
 
+/// Downloads a version of the avatar hosted over HTTP, checking that it is the one announced, as
+/// the host may be anyone: the download stops as soon as it gets larger than the announced
+/// `bytes`.
+async fn fetch_avatar(url: &str, bytes: u32, hash: &[u8]) -> Result<Vec<u8>, String> {
+    let request = async {
+        let response = reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let mut data = Vec::new();
+        let mut append = |chunk: &[u8]| -> Result<(), String> {
+            if data.len() + chunk.len() > bytes as usize {
+                return Err(String::from("the data is larger than announced"));
+            }
+            data.extend_from_slice(chunk);
+            Ok(())
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut response = response;
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                append(&chunk)?;
+            }
+        }
+        // The browser only gives the whole body, so only its announced length can stop it early.
+        #[cfg(target_arch = "wasm32")]
+        {
+            if response.content_length().unwrap_or(0) > bytes as u64 {
+                return Err(String::from("the data is larger than announced"));
+            }
+            append(&response.bytes().await.map_err(|e| e.to_string())?)?;
+        }
+        Ok::<_, String>(data)
+    };
+    let data = match rt::timeout(FETCH_TIMEOUT, request).await {
+        Ok(data) => data?,
+        Err(_) => return Err(String::from("timed out")),
+    };
+    if hash_caps(&data, Algo::Sha_1)?.hash != hash {
+        return Err(String::from("the data doesn’t match the announced hash"));
+    }
+    Ok(data)
+}
+
+/// Saves an avatar downloaded over HTTP.
+pub(crate) fn handle_fetched<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    (from, id, data): Fetched,
+) -> Vec<Event> {
+    agent
+        .avatar_fetches
+        .running
+        .remove(&(from.clone(), id.clone()));
+    let data = match data {
+        Ok(data) => data,
+        Err(e) => {
+            error!("{}", e);
+            return vec![];
+        }
+    };
+    match save_avatar(&from, id, &data) {
+        Ok(filename) => vec![Event::AvatarRetrieved(from, filename)],
+        Err(e) => {
+            error!("Error saving avatar: {}", e);
+            vec![]
+        }
+    }
+}
+
 // The return value of this function will be simply pushed to a Vec in the caller function,
 // so it makes no sense to allocate a Vec here - we're lazy instead
 pub(crate) fn handle_data_pubsub_iq<'a>(
@@ -80,19 +373,260 @@ pub(crate) fn handle_data_pubsub_iq<'a>(
         .iter()
         .filter_map(move |item| match (&item.id, &item.payload) {
             (Some(id), Some(payload)) => {
-                let data = Data::try_from(payload.clone()).unwrap();
-                let filename = save_avatar(&from, id.0.clone(), &data.data).unwrap();
-                Some(Event::AvatarRetrieved(from.clone(), filename))
+                let data = match Data::try_from(payload.clone()) {
+                    Ok(data) => data.data,
+                    Err(e) => {
+                        error!("Error parsing the avatar data of {}: {}", from, e);
+                        return None;
+                    }
+                };
+                // The id of an avatar is the SHA-1 of its data.
+                if !hash_caps(&data, Algo::Sha_1).is_ok_and(|hash| hash.to_hex() == id.0) {
+                    error!("The avatar data of {} doesn’t match its id", from);
+                    return None;
+                }
+                match save_avatar(&from, id.0.clone(), &data) {
+                    Ok(filename) => Some(Event::AvatarRetrieved(from.clone(), filename)),
+                    Err(e) => {
+                        error!("Error saving avatar: {}", e);
+                        None
+                    }
+                }
             }
             _ => None,
         })
 }
 
+/// Returns the directory caching the avatars of this entity, and the file of this avatar in it.
+///
+/// The JID becomes a single directory name, escaping anything but letters, digits and `@.-_`, as
+/// the nick of a room occupant may contain anything. Avatar ids are hexadecimal SHA-1 hashes.
+fn avatar_path(jid: &Jid, id: &str) -> io::Result<(String, String)> {
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+    if id.len() != 40 || !id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(invalid("the avatar id isn’t a SHA-1 hash"));
+    }
+    let mut directory = String::new();
+    for byte in jid.to_string().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'@' | b'.' | b'-' | b'_' => {
+                directory.push(byte as char)
+            }
+            _ => directory.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    if directory == "." || directory == ".." {
+        return Err(invalid("the JID isn’t a valid directory name"));
+    }
+    let filename = format!("data/{}/{}", directory, id);
+    Ok((format!("data/{}", directory), filename))
+}
+
 fn save_avatar(from: &Jid, id: String, data: &[u8]) -> io::Result<String> {
-    let directory = format!("data/{}", from);
-    let filename = format!("data/{}/{}", from, id);
+    let (directory, filename) = avatar_path(from, &id)?;
     fs::create_dir_all(directory)?;
     let mut file = File::create(&filename)?;
     file.write_all(data)?;
     Ok(filename)
 }
+
+#[cfg(feature = "image")]
+fn encode_avatar(image: &DynamicImage, size: u32) -> Result<(Data, Info), ImageError> {
+    let scaled = image.resize_to_fill(size, size, FilterType::Lanczos3);
+    let mut png = Vec::new();
+    scaled.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    let hash = hash_caps(&png, Algo::Sha_1).unwrap();
+    let info = Info {
+        bytes: png.len() as u32,
+        width: Some(size as u16),
+        height: Some(size as u16),
+        id: Sha1HexAttribute::from_str(&hash.to_hex()).unwrap(),
+        type_: String::from("image/png"),
+        url: None,
+    };
+    Ok((Data { data: png }, info))
+}
+
+/// Crops an image to a square and scales it down to the recommended sizes, as PNG, the 64×64
+/// one first. Images smaller than that are only cropped.
+#[cfg(feature = "image")]
+fn make_avatars(image: &[u8]) -> Result<Vec<(Data, Info)>, ImageError> {
+    let image = image::load_from_memory(image)?;
+    let side = image.width().min(image.height());
+    let mut sizes: Vec<u32> = AVATAR_SIZES
+        .into_iter()
+        .filter(|&size| size <= side)
+        .collect();
+    if sizes.is_empty() {
+        sizes.push(side);
+    }
+    sizes
+        .into_iter()
+        .map(|size| encode_avatar(&image, size))
+        .collect()
+}
+
+#[cfg(feature = "image")]
+fn make_publish<P: PubSubPayload>(iq_id: String, node: &str, id: String, payload: P) -> Iq {
+    let item = ItemContent::new(Some(ItemId(id)), None, Some(payload));
+    Iq::from_set(
+        iq_id,
+        PubSub::Publish {
+            publish: Publish {
+                node: NodeName(String::from(node)),
+                items: vec![PubSubItem(item)],
+            },
+            publish_options: None,
+        },
+    )
+}
+
+/// Publishes every size of the avatar to the data node, then their metadata, which notifies
+/// contacts.
+#[cfg(feature = "image")]
+pub(crate) async fn publish_avatar<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    image: &[u8],
+) -> Result<(), ImageError> {
+    let mut infos = Vec::new();
+    for (data, info) in make_avatars(image)? {
+        let iq = make_publish(
+            agent.client.make_id(),
+            ns::AVATAR_DATA,
+            info.id.to_hex(),
+            data,
+        );
+        let _ = agent.client.send_stanza(iq.into()).await;
+        infos.push(info);
+    }
+    let id = infos[0].id.to_hex();
+    let hash = <[u8; 20]>::try_from(infos[0].id.hash.clone()).unwrap();
+    let iq = make_publish(
+        agent.client.make_id(),
+        ns::AVATAR_METADATA,
+        id,
+        Metadata { infos },
+    );
+    let _ = agent.client.send_stanza(iq.into()).await;
+    set_own_hash(agent, hash).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "image")]
+    use image::RgbImage;
+
+    #[test]
+    fn vcard_updates() {
+        let update = |xml: &str| VCardUpdate::try_from(xml.parse::<Element>().unwrap()).unwrap();
+        let juliet = Jid::new("juliet@capulet.lit").unwrap();
+        let mut avatars = VCardAvatars::default();
+
+        assert_eq!(
+            avatars.update(&juliet, update("<x xmlns='vcard-temp:x:update'/>")),
+            None
+        );
+        let photo = "<x xmlns='vcard-temp:x:update'><photo>01b87fcd030b72895ff8e88db57ec525450f000d</photo></x>";
+        assert_eq!(
+            avatars.update(&juliet, update(photo)).as_deref(),
+            Some("01b87fcd030b72895ff8e88db57ec525450f000d")
+        );
+        // Only a new avatar gets fetched.
+        assert_eq!(avatars.update(&juliet, update(photo)), None);
+        let none = "<x xmlns='vcard-temp:x:update'><photo/></x>";
+        assert_eq!(avatars.update(&juliet, update(none)), None);
+        assert!(avatars.update(&juliet, update(photo)).is_some());
+
+        let id = String::from("vcard");
+        let hash = String::from("hash");
+        avatars
+            .pending
+            .insert(id.clone(), juliet.clone(), (juliet.clone(), hash));
+        let romeo = Jid::new("romeo@montague.lit").unwrap();
+        assert!(avatars.take(&romeo, &id).is_none());
+        assert!(avatars.take(&juliet, &id).is_some());
+        assert!(avatars.take(&juliet, &id).is_none());
+    }
+
+    /// Serves one HTTP response with this body, returning its URL.
+    fn serve(body: &'static [u8]) -> String {
+        use std::io::Read;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/avatar.png", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n");
+            let _ = stream.write_all(body);
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn fetch_checks_size_and_hash() {
+        let data = b"avatar";
+        let hash = hash_caps(data, Algo::Sha_1).unwrap().hash;
+        assert_eq!(
+            fetch_avatar(&serve(data), 6, &hash).await.unwrap(),
+            data.to_vec()
+        );
+        assert_eq!(
+            fetch_avatar(&serve(data), 5, &hash).await,
+            Err(String::from("the data is larger than announced"))
+        );
+        assert_eq!(
+            fetch_avatar(&serve(b"avatar!"), 7, &hash).await,
+            Err(String::from("the data doesn’t match the announced hash"))
+        );
+    }
+
+    #[test]
+    fn paths() {
+        let hash = "01b87fcd030b72895ff8e88db57ec525450f000d";
+        let juliet = Jid::new("juliet@capulet.lit").unwrap();
+        assert_eq!(
+            avatar_path(&juliet, hash).unwrap().1,
+            format!("data/juliet@capulet.lit/{}", hash)
+        );
+        let occupant = Jid::new("coven@chat.shakespeare.lit/../../x y").unwrap();
+        assert_eq!(
+            avatar_path(&occupant, hash).unwrap().0,
+            "data/coven@chat.shakespeare.lit%2F..%2F..%2Fx%20y"
+        );
+        assert!(avatar_path(&juliet, "../../etc/passwd").is_err());
+        assert!(avatar_path(&juliet, &hash[1..]).is_err());
+    }
+
+    #[cfg(feature = "image")]
+    fn encode(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(width, height));
+        let mut data = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn sizes() {
+        let avatars = make_avatars(&encode(200, 150)).unwrap();
+        let sides: Vec<_> = avatars.iter().map(|(_, info)| info.width).collect();
+        assert_eq!(sides, [Some(64), Some(96)]);
+        for (data, info) in avatars {
+            assert_eq!(info.height, info.width);
+            assert_eq!(info.bytes as usize, data.data.len());
+            assert_eq!(info.type_, "image/png");
+            let decoded = image::load_from_memory(&data.data).unwrap();
+            assert_eq!(Some(decoded.width() as u16), info.width);
+        }
+
+        let avatars = make_avatars(&encode(40, 48)).unwrap();
+        assert_eq!(avatars.len(), 1);
+        assert_eq!(avatars[0].1.width, Some(40));
+
+        assert!(make_avatars(b"not an image").is_err());
+    }
+}
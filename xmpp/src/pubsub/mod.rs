@@ -5,7 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use super::Agent;
-use crate::Event;
+use crate::{muc, Event};
 use std::str::FromStr;
 use tokio_xmpp::{
     connect::ServerConnector,
@@ -14,6 +14,7 @@ use tokio_xmpp::{
         ns,
         pubsub::event::PubSubEvent,
         pubsub::pubsub::PubSub,
+        pubsub::NodeName,
         BareJid, Element, Jid,
     },
 };
@@ -21,10 +22,27 @@ use tokio_xmpp::{
 #[cfg(feature = "avatars")]
 pub(crate) mod avatar;
 
+/// The `+notify` feature asking for the notifications of this node, see
+/// [XEP-0163 §4](https://xmpp.org/extensions/xep-0163.html#notify-filter).
+pub(crate) fn notify_feature(node: &str) -> String {
+    format!("{}+notify", node)
+}
+
+/// Whether the application asked for the notifications of this node with
+/// [`Agent::subscribe_pep`](crate::Agent::subscribe_pep).
+fn is_subscribed<C: ServerConnector>(agent: &Agent<C>, node: &str) -> bool {
+    let var = notify_feature(node);
+    agent
+        .disco
+        .features
+        .iter()
+        .any(|feature| feature.var == var)
+}
+
 pub(crate) async fn handle_event<C: ServerConnector>(
-    #[cfg_attr(not(feature = "avatars"), allow(unused_variables))] from: &Jid,
+    from: &Jid,
     elem: Element,
-    #[cfg_attr(not(feature = "avatars"), allow(unused_variables))] agent: &mut Agent<C>,
+    agent: &mut Agent<C>,
 ) -> Vec<Event> {
     let mut events = Vec::new();
     let event = PubSubEvent::try_from(elem);
@@ -49,13 +67,22 @@ pub(crate) async fn handle_event<C: ServerConnector>(
                             if conference.autojoin == Autojoin::True {
                                 events.push(Event::JoinRoom(jid, conference));
                             } else {
+                                muc::room::leave_bookmarked_room(agent, &jid).await;
                                 events.push(Event::LeaveRoom(jid));
                             }
                         }
                         Err(err) => println!("not bookmark: {}", err),
                     }
                 }
-                ref node => unimplemented!("node {}", node),
+                ref node if is_subscribed(agent, node) => {
+                    let items = items.into_iter().map(|item| item.0).collect();
+                    events.push(Event::PepItemsPublished(
+                        from.clone(),
+                        NodeName(node.clone()),
+                        items,
+                    ));
+                }
+                ref node => debug!("Ignoring items published on node {}", node),
             }
         }
         Ok(PubSubEvent::RetractedItems { node, items }) => {
@@ -65,24 +92,36 @@ pub(crate) async fn handle_event<C: ServerConnector>(
                     assert_eq!(items.len(), 1);
                     let item = items.clone().pop().unwrap();
                     let jid = BareJid::from_str(&item.0).unwrap();
+                    muc::room::leave_bookmarked_room(agent, &jid).await;
                     events.push(Event::LeaveRoom(jid));
                 }
-                ref node => unimplemented!("node {}", node),
+                ref node if is_subscribed(agent, node) => {
+                    events.push(Event::PepItemsRetracted(
+                        from.clone(),
+                        NodeName(node.clone()),
+                        items,
+                    ));
+                }
+                ref node => debug!("Ignoring items retracted from node {}", node),
             }
         }
         Ok(PubSubEvent::Purge { node }) => {
             match node.0 {
                 ref node if node == ns::BOOKMARKS2 => {
                     // TODO: Check that our bare JID is the sender.
+                    muc::room::leave_all_rooms(agent).await;
                     events.push(Event::LeaveAllRooms);
                 }
-                ref node => unimplemented!("node {}", node),
+                ref node if is_subscribed(agent, node) => {
+                    events.push(Event::PepNodePurged(from.clone(), NodeName(node.clone())));
+                }
+                ref node => debug!("Ignoring the purge of node {}", node),
             }
         }
         Err(e) => {
             error!("Error parsing PubSub event: {}", e);
         }
-        _ => unimplemented!("PubSub event: {:#?}", event),
+        Ok(event) => debug!("Ignoring PubSub event: {:#?}", event),
     }
     events
 }
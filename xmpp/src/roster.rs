@@ -0,0 +1,196 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The contact list of the account ([RFC 6121 §2](https://xmpp.org/rfcs/rfc6121.html#roster)),
+//! kept from the roster fetched when connecting and updated by the pushes of the server, and
+//! the contacts suggested by other entities with Roster Item Exchange
+//! ([XEP-0144](https://xmpp.org/extensions/xep-0144.html)).
+
+use std::collections::BTreeSet;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        iq::Iq,
+        message::Message,
+        ns,
+        roster::{Item as RosterItem, Roster, Subscription},
+        rosterx::{Exchange, Item as SuggestedItem},
+    },
+    BareJid, Jid,
+};
+
+use crate::{Agent, Event};
+
+/// Returns the contact with this JID, if any.
+pub(crate) fn contact<'a>(roster: &'a Roster, jid: &BareJid) -> Option<&'a RosterItem> {
+    roster.items.iter().find(|item| &item.jid == jid)
+}
+
+/// Returns the contacts in this group.
+pub(crate) fn contacts_in_group<'a>(
+    roster: &'a Roster,
+    group: &'a str,
+) -> impl Iterator<Item = &'a RosterItem> {
+    roster
+        .items
+        .iter()
+        .filter(move |item| item.groups.iter().any(|g| g.0 == group))
+}
+
+/// Returns the names of every group, sorted.
+pub(crate) fn groups(roster: &Roster) -> BTreeSet<&str> {
+    roster
+        .items
+        .iter()
+        .flat_map(|item| item.groups.iter().map(|group| group.0.as_str()))
+        .collect()
+}
+
+/// Replaces the known contacts with the roster fetched when connecting.
+pub(crate) fn handle_result<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    roster: Roster,
+) -> Vec<Event> {
+    let events = roster
+        .items
+        .iter()
+        .cloned()
+        .map(Event::ContactAdded)
+        .collect();
+    agent.roster = roster;
+    events
+}
+
+fn apply_push(roster: &mut Roster, push: Roster) -> Vec<Event> {
+    if push.ver.is_some() {
+        roster.ver = push.ver;
+    }
+    let mut events = Vec::new();
+    for item in push.items {
+        let removed = item.subscription == Subscription::Remove;
+        match (roster.apply_push(item.clone()), removed) {
+            (Some(previous), true) => events.push(Event::ContactRemoved(previous)),
+            (None, true) => (),
+            (Some(_), false) => events.push(Event::ContactChanged(item)),
+            (None, false) => events.push(Event::ContactAdded(item)),
+        }
+    }
+    events
+}
+
+/// Applies a roster push from the server, which has already been checked to come from our own
+/// account, and acknowledges it.
+pub(crate) async fn handle_push<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    push: Roster,
+) -> Vec<Event> {
+    let events = apply_push(&mut agent.roster, push);
+    let iq = Iq::empty_result(from, id);
+    let _ = agent.client.send_stanza(iq.into()).await;
+    events
+}
+
+/// Whether we advertise Roster Item Exchange, see
+/// [`ClientFeature::RosterExchange`](crate::ClientFeature::RosterExchange).
+pub(crate) fn exchange_enabled<C: ServerConnector>(agent: &Agent<C>) -> bool {
+    agent
+        .disco
+        .features
+        .iter()
+        .any(|feature| feature.var == ns::ROSTERX)
+}
+
+/// Acknowledges contacts suggested in an iq, and reports them.
+pub(crate) async fn handle_exchange_iq<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    from: Jid,
+    id: String,
+    exchange: Exchange,
+) -> Event {
+    let iq = Iq::empty_result(from.clone(), id);
+    let _ = agent.client.send_stanza(iq.into()).await;
+    Event::ContactsSuggested(from, exchange.items)
+}
+
+/// Suggests contacts to add, modify or delete to another entity.
+pub(crate) async fn suggest_contacts<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    recipient: Jid,
+    items: Vec<SuggestedItem>,
+) {
+    let message = Message::new(recipient).with_payload(Exchange { items });
+    let _ = agent.client.send_stanza(message.into()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::Element;
+
+    fn parse(xml: &str) -> Roster {
+        let elem: Element = xml.parse().unwrap();
+        Roster::try_from(elem).unwrap()
+    }
+
+    #[test]
+    fn push() {
+        let mut roster = parse(
+            "<query xmlns='jabber:iq:roster' ver='ver7'>
+              <item jid='nurse@example.com'><group>Servants</group></item>
+              <item jid='romeo@example.net' subscription='to'><group>Friends</group></item>
+            </query>",
+        );
+        let romeo = BareJid::new("romeo@example.net").unwrap();
+
+        let events = apply_push(
+            &mut roster,
+            parse(
+                "<query xmlns='jabber:iq:roster' ver='ver8'>
+                  <item jid='romeo@example.net' subscription='both'><group>Friends</group><group>Lovers</group></item>
+                </query>",
+            ),
+        );
+        assert!(matches!(events[..], [Event::ContactChanged(_)]));
+        assert_eq!(roster.ver.as_deref(), Some("ver8"));
+        assert_eq!(
+            contact(&roster, &romeo).unwrap().subscription,
+            Subscription::Both
+        );
+        assert_eq!(
+            groups(&roster).into_iter().collect::<Vec<_>>(),
+            ["Friends", "Lovers", "Servants"]
+        );
+
+        let events = apply_push(
+            &mut roster,
+            parse(
+                "<query xmlns='jabber:iq:roster'>
+                  <item jid='benvolio@example.net' ask='subscribe'><group>Friends</group></item>
+                </query>",
+            ),
+        );
+        assert!(matches!(events[..], [Event::ContactAdded(_)]));
+        assert_eq!(roster.ver.as_deref(), Some("ver8"));
+        assert_eq!(contacts_in_group(&roster, "Friends").count(), 2);
+
+        let events = apply_push(
+            &mut roster,
+            parse(
+                "<query xmlns='jabber:iq:roster' ver='ver9'>
+                  <item jid='nurse@example.com' subscription='remove'/>
+                </query>",
+            ),
+        );
+        match &events[..] {
+            [Event::ContactRemoved(item)] => assert_eq!(item.groups.len(), 1),
+            _ => panic!(),
+        }
+        assert_eq!(contacts_in_group(&roster, "Servants").count(), 0);
+        assert_eq!(roster.items.len(), 2);
+    }
+}
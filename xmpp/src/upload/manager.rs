@@ -0,0 +1,332 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::collections::{HashMap, VecDeque};
+use std::error::Error as StdError;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::{
+    parsers::{
+        bob::Data,
+        date::DateTime,
+        http_upload::{FileTooLarge, Retry},
+        jingle_thumnails::Thumbnail,
+        ns,
+        stanza_error::{DefinedCondition, StanzaError},
+        Error as ParsersError,
+    },
+    Jid,
+};
+
+use crate::{upload::send::request_slots, Agent, Event};
+
+/// Identifies an upload, as returned by [`Agent::upload_file_with`].
+pub type UploadId = String;
+
+/// How failed HTTP PUT requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before each new attempt.
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A file which got uploaded, see [Event::HttpUploadedFile].
+#[derive(Debug, Clone)]
+pub struct UploadedFile {
+    /// The id returned by [`Agent::upload_file_with`].
+    pub id: UploadId,
+    /// The path of the uploaded file.
+    pub path: PathBuf,
+    /// The URL to share, to download the file.
+    pub get_url: String,
+    /// The URL the file got uploaded to.
+    pub put_url: String,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// A thumbnail of the file, to attach when sharing it, if it is an image and
+    /// [ClientFeature::Thumbnails](crate::ClientFeature::Thumbnails) is enabled.
+    pub thumbnail: Option<Thumbnail>,
+}
+
+/// Why an upload failed, see [Event::HttpUploadFailed].
+#[derive(Debug)]
+pub enum UploadError {
+    /// The file is larger than what the service accepts, with its maximum size if it told us.
+    FileTooLarge(Option<u64>),
+    /// A quota was reached, with the time after which a new upload may succeed if the service
+    /// told us.
+    QuotaReached(Option<DateTime>),
+    /// The service refused to give a slot for another reason.
+    SlotRefused(StanzaError),
+    /// The slot given by the service is invalid.
+    InvalidSlot(ParsersError),
+    /// The file couldn’t be read.
+    Io(std::io::Error),
+    /// The HTTP request failed.
+    Http(String),
+    /// The HTTP server answered with an unexpected status code.
+    HttpStatus(u16),
+}
+
+impl From<StanzaError> for UploadError {
+    fn from(error: StanzaError) -> UploadError {
+        match error.other {
+            Some(ref other) if other.is("file-too-large", ns::HTTP_UPLOAD) => {
+                let file_too_large = FileTooLarge::try_from(other.clone()).ok();
+                UploadError::FileTooLarge(file_too_large.and_then(|elem| elem.max_file_size))
+            }
+            Some(ref other) if other.is("retry", ns::HTTP_UPLOAD) => {
+                let retry = Retry::try_from(other.clone()).ok();
+                UploadError::QuotaReached(retry.map(|retry| retry.stamp))
+            }
+            _ if error.defined_condition == DefinedCondition::ResourceConstraint => {
+                UploadError::QuotaReached(None)
+            }
+            _ => UploadError::SlotRefused(error),
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UploadError::FileTooLarge(Some(max)) => {
+                write!(fmt, "file too large, the maximum is {} bytes", max)
+            }
+            UploadError::FileTooLarge(None) => write!(fmt, "file too large"),
+            UploadError::QuotaReached(Some(stamp)) => {
+                write!(fmt, "quota reached, retry after {}", stamp.0)
+            }
+            UploadError::QuotaReached(None) => write!(fmt, "quota reached"),
+            UploadError::SlotRefused(error) => {
+                write!(fmt, "slot refused: {:?}", error.defined_condition)
+            }
+            UploadError::InvalidSlot(error) => write!(fmt, "invalid slot: {}", error),
+            UploadError::Io(error) => write!(fmt, "I/O error: {}", error),
+            UploadError::Http(error) => write!(fmt, "HTTP error: {}", error),
+            UploadError::HttpStatus(status) => write!(fmt, "unexpected HTTP status {}", status),
+        }
+    }
+}
+
+impl StdError for UploadError {}
+
+/// A file waiting for, or being uploaded to, a slot.
+pub(crate) struct Upload {
+    pub(crate) id: UploadId,
+    pub(crate) service: Jid,
+    pub(crate) path: PathBuf,
+    pub(crate) token: CancellationToken,
+}
+
+/// Reported by the tasks performing the HTTP PUT requests.
+pub(crate) enum TaskEvent {
+    // Not reported in the browser, which can't upload files.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    Progress(UploadId, u64, u64),
+    /// The file got uploaded, along with the data of its thumbnail, to share with Bits of Binary.
+    Uploaded(UploadedFile, Option<Data>),
+    Failed(UploadId, PathBuf, UploadError),
+    Cancelled(UploadId),
+}
+
+/// Keeps track of uploads, starting at most `max_concurrent` of them at a time.
+pub(crate) struct UploadManager {
+    max_concurrent: usize,
+    pub(crate) retry: RetryPolicy,
+    queued: VecDeque<Upload>,
+    requesting: Vec<Upload>,
+    running: HashMap<UploadId, CancellationToken>,
+    #[cfg(feature = "image")]
+    pub(crate) thumbnails: bool,
+    pub(crate) sender: UnboundedSender<TaskEvent>,
+    pub(crate) receiver: UnboundedReceiver<TaskEvent>,
+}
+
+impl UploadManager {
+    pub(crate) fn new(max_concurrent: usize, retry: RetryPolicy) -> UploadManager {
+        let (sender, receiver) = unbounded_channel();
+        UploadManager {
+            max_concurrent: max_concurrent.max(1),
+            retry,
+            queued: VecDeque::new(),
+            requesting: Vec::new(),
+            running: HashMap::new(),
+            #[cfg(feature = "image")]
+            thumbnails: false,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Queues an upload, whose id is also the one of its slot request.
+    pub(crate) fn queue(&mut self, id: UploadId, service: Jid, path: PathBuf) -> UploadId {
+        self.queued.push_back(Upload {
+            id: id.clone(),
+            service,
+            path,
+            token: CancellationToken::new(),
+        });
+        id
+    }
+
+    /// Returns the next upload a slot should be requested for, if the concurrency limit allows.
+    pub(crate) fn next_to_request(&mut self) -> Option<(UploadId, Jid, PathBuf)> {
+        if self.requesting.len() + self.running.len() >= self.max_concurrent {
+            return None;
+        }
+        let upload = self.queued.pop_front()?;
+        let next = (
+            upload.id.clone(),
+            upload.service.clone(),
+            upload.path.clone(),
+        );
+        self.requesting.push(upload);
+        Some(next)
+    }
+
+    /// Returns the upload waiting for this slot request, if it wasn’t cancelled meanwhile.
+    pub(crate) fn take_requested(&mut self, service: &Jid, id: &str) -> Option<Upload> {
+        let index = self
+            .requesting
+            .iter()
+            .position(|upload| &upload.service == service && upload.id == id)?;
+        Some(self.requesting.remove(index))
+    }
+
+    pub(crate) fn start(&mut self, upload: &Upload) {
+        self.running.insert(upload.id.clone(), upload.token.clone());
+    }
+
+    pub(crate) fn finish(&mut self, id: &str) {
+        self.running.remove(id);
+    }
+
+    /// Cancels an upload, returns false if it is unknown or already over.
+    pub(crate) fn cancel(&mut self, id: &str) -> bool {
+        if let Some(token) = self.running.get(id) {
+            // The task reports the cancellation itself.
+            token.cancel();
+            return true;
+        }
+        let before = self.queued.len() + self.requesting.len();
+        self.queued.retain(|upload| upload.id != id);
+        self.requesting.retain(|upload| upload.id != id);
+        if self.queued.len() + self.requesting.len() == before {
+            return false;
+        }
+        let _ = self.sender.send(TaskEvent::Cancelled(id.to_owned()));
+        true
+    }
+}
+
+pub(crate) async fn handle_task_event<C: ServerConnector>(
+    agent: &mut Agent<C>,
+    event: TaskEvent,
+) -> Vec<Event> {
+    let events = match event {
+        TaskEvent::Progress(id, sent, total) => {
+            return vec![Event::HttpUploadProgress(id, sent, total)];
+        }
+        TaskEvent::Uploaded(file, thumbnail) => {
+            agent.uploads.finish(&file.id);
+            if let Some(data) = thumbnail {
+                agent.add_bits_of_binary(data);
+            }
+            vec![Event::HttpUploadedFile(file)]
+        }
+        TaskEvent::Failed(id, path, error) => {
+            agent.uploads.finish(&id);
+            vec![Event::HttpUploadFailed(id, path, error)]
+        }
+        TaskEvent::Cancelled(id) => {
+            agent.uploads.finish(&id);
+            vec![Event::HttpUploadCancelled(id)]
+        }
+    };
+    request_slots(agent).await;
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_xmpp::Element;
+
+    #[test]
+    fn concurrency_and_cancellation() {
+        let mut manager = UploadManager::new(2, RetryPolicy::default());
+        let service = Jid::new("upload.example.org").unwrap();
+        let first = manager.queue(String::from("a"), service.clone(), PathBuf::from("a"));
+        let second = manager.queue(String::from("b"), service.clone(), PathBuf::from("b"));
+        let third = manager.queue(String::from("c"), service.clone(), PathBuf::from("c"));
+
+        assert_eq!(manager.next_to_request().unwrap().0, first);
+        assert_eq!(manager.next_to_request().unwrap().0, second);
+        assert!(manager.next_to_request().is_none());
+
+        let upload = manager.take_requested(&service, &first).unwrap();
+        manager.start(&upload);
+        assert!(manager.next_to_request().is_none());
+
+        assert!(manager.cancel(&first));
+        assert!(upload.token.is_cancelled());
+        manager.finish(&first);
+        assert_eq!(manager.next_to_request().unwrap().0, third);
+
+        assert!(manager.cancel(&second));
+        assert!(matches!(
+            manager.receiver.try_recv(),
+            Ok(TaskEvent::Cancelled(id)) if id == second
+        ));
+        assert!(manager.take_requested(&service, &second).is_none());
+        assert!(!manager.cancel(&second));
+    }
+
+    #[test]
+    fn slot_errors() {
+        let elem: Element = "<error xmlns='jabber:client' type='modify'>
+            <not-acceptable xmlns='urn:ietf:params:xml:ns:xmpp-stanzas'/>
+            <file-too-large xmlns='urn:xmpp:http:upload:0'>
+              <max-file-size>20000</max-file-size>
+            </file-too-large>
+          </error>"
+            .parse()
+            .unwrap();
+        let error = StanzaError::try_from(elem).unwrap();
+        assert!(matches!(
+            UploadError::from(error),
+            UploadError::FileTooLarge(Some(20000))
+        ));
+
+        let elem: Element = "<error xmlns='jabber:client' type='wait'>
+            <resource-constraint xmlns='urn:ietf:params:xml:ns:xmpp-stanzas'/>
+            <retry xmlns='urn:xmpp:http:upload:0' stamp='2017-12-03T23:42:01Z'/>
+          </error>"
+            .parse()
+            .unwrap();
+        let error = StanzaError::try_from(elem).unwrap();
+        assert!(matches!(
+            UploadError::from(error),
+            UploadError::QuotaReached(Some(_))
+        ));
+    }
+}
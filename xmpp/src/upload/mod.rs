@@ -4,5 +4,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod manager;
 pub mod receive;
 pub mod send;
+#[cfg(feature = "image")]
+pub(crate) mod thumbnail;
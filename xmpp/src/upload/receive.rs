@@ -7,15 +7,20 @@
 use reqwest::{
     header::HeaderMap as ReqwestHeaderMap, Body as ReqwestBody, Client as ReqwestClient,
 };
-use std::path::PathBuf;
-use tokio::fs::File;
-use tokio_util::codec::{BytesCodec, FramedRead};
+use std::io;
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio_xmpp::connect::ServerConnector;
+use tokio_xmpp::rt;
 use tokio_xmpp::{
-    parsers::http_upload::{Header as HttpUploadHeader, SlotResult},
+    parsers::{
+        http_upload::{Header as HttpUploadHeader, SlotResult},
+        stanza_error::StanzaError,
+    },
     Element, Jid,
 };
 
+use crate::upload::manager::{RetryPolicy, TaskEvent, Upload, UploadError, UploadId, UploadedFile};
 use crate::{Agent, Event};
 
 pub async fn handle_upload_result<C: ServerConnector>(
@@ -24,43 +29,201 @@ pub async fn handle_upload_result<C: ServerConnector>(
     elem: Element,
     agent: &mut Agent<C>,
 ) -> impl IntoIterator<Item = Event> {
-    let mut res: Option<(usize, PathBuf)> = None;
+    let Some(upload) = agent.uploads.take_requested(from, &iqid) else {
+        return vec![];
+    };
 
-    for (i, (id, to, filepath)) in agent.uploads.iter().enumerate() {
-        if to == from && id == &iqid {
-            res = Some((i, filepath.to_path_buf()));
-            break;
+    match SlotResult::try_from(elem) {
+        Ok(slot) => {
+            agent.uploads.start(&upload);
+            let retry = agent.uploads.retry.clone();
+            let sender = agent.uploads.sender.clone();
+            #[cfg(feature = "image")]
+            let thumbnails = agent.uploads.thumbnails;
+            #[cfg(not(feature = "image"))]
+            let thumbnails = false;
+            rt::spawn(upload_to_slot(upload, slot, retry, sender, thumbnails));
         }
+        Err(err) => {
+            let _ = agent.uploads.sender.send(TaskEvent::Failed(
+                upload.id,
+                upload.path,
+                UploadError::InvalidSlot(err),
+            ));
+        }
+    }
+
+    vec![]
+}
+
+pub(crate) fn handle_upload_error<C: ServerConnector>(
+    from: &Jid,
+    iqid: &str,
+    error: StanzaError,
+    agent: &mut Agent<C>,
+) {
+    if let Some(upload) = agent.uploads.take_requested(from, iqid) {
+        let _ = agent.uploads.sender.send(TaskEvent::Failed(
+            upload.id,
+            upload.path,
+            UploadError::from(error),
+        ));
     }
+}
+
+/// Failure of a single HTTP PUT attempt.
+struct PutError {
+    error: UploadError,
+    retryable: bool,
+}
 
-    if let Some((index, file)) = res {
-        agent.uploads.remove(index);
-        let slot = SlotResult::try_from(elem).unwrap();
-
-        let mut headers = ReqwestHeaderMap::new();
-        for header in slot.put.headers {
-            let (attr, val) = match header {
-                HttpUploadHeader::Authorization(val) => ("Authorization", val),
-                HttpUploadHeader::Cookie(val) => ("Cookie", val),
-                HttpUploadHeader::Expires(val) => ("Expires", val),
-            };
-            headers.insert(attr, val.parse().unwrap());
+/// Uploads a file, then makes its thumbnail if `thumbnails` is enabled.
+#[cfg_attr(not(feature = "image"), allow(unused_variables))]
+async fn upload_to_slot(
+    upload: Upload,
+    slot: SlotResult,
+    retry: RetryPolicy,
+    sender: UnboundedSender<TaskEvent>,
+    thumbnails: bool,
+) {
+    let id = upload.id.clone();
+    let event = tokio::select! {
+        _ = upload.token.cancelled() => TaskEvent::Cancelled(id),
+        result = put_with_retries(&upload, &slot, &retry, &sender) => match result {
+            Ok(size) => TaskEvent::Uploaded(UploadedFile {
+                id,
+                path: upload.path.clone(),
+                get_url: slot.get.url.clone(),
+                put_url: slot.put.url.clone(),
+                size,
+                thumbnail: None,
+            }, None),
+            Err(error) => TaskEvent::Failed(id, upload.path.clone(), error),
+        },
+    };
+    #[cfg(feature = "image")]
+    let event = match event {
+        TaskEvent::Uploaded(mut file, _) if thumbnails => {
+            let thumbnail = crate::upload::thumbnail::make_thumbnail_of(file.path.clone()).await;
+            let data = thumbnail.map(|(data, thumbnail)| {
+                file.thumbnail = Some(thumbnail);
+                data
+            });
+            TaskEvent::Uploaded(file, data)
         }
+        event => event,
+    };
+    let _ = sender.send(event);
+}
 
-        let web = ReqwestClient::new();
-        let stream = FramedRead::new(File::open(file).await.unwrap(), BytesCodec::new());
-        let body = ReqwestBody::wrap_stream(stream);
-        let res = web
-            .put(slot.put.url.as_str())
-            .headers(headers)
-            .body(body)
-            .send()
-            .await
-            .unwrap();
-        if res.status() == 201 {
-            return vec![Event::HttpUploadedFile(slot.get.url)];
+async fn put_with_retries(
+    upload: &Upload,
+    slot: &SlotResult,
+    retry: &RetryPolicy,
+    sender: &UnboundedSender<TaskEvent>,
+) -> Result<u64, UploadError> {
+    let mut attempt = 1;
+    loop {
+        match put_file(&upload.id, &upload.path, slot, sender).await {
+            Ok(size) => return Ok(size),
+            Err(err) if err.retryable && attempt < retry.max_attempts => {
+                warn!(
+                    "Upload {} failed (attempt {}/{}), retrying: {}",
+                    upload.id, attempt, retry.max_attempts, err.error
+                );
+                attempt += 1;
+                rt::sleep(retry.delay).await;
+            }
+            Err(err) => return Err(err.error),
         }
     }
+}
+
+async fn put_file(
+    id: &UploadId,
+    path: &Path,
+    slot: &SlotResult,
+    sender: &UnboundedSender<TaskEvent>,
+) -> Result<u64, PutError> {
+    let mut headers = ReqwestHeaderMap::new();
+    for header in slot.put.headers.iter().cloned() {
+        let (attr, val) = match header {
+            HttpUploadHeader::Authorization(val) => ("Authorization", val),
+            HttpUploadHeader::Cookie(val) => ("Cookie", val),
+            HttpUploadHeader::Expires(val) => ("Expires", val),
+        };
+        let val = val.parse().map_err(|_| PutError {
+            error: UploadError::Http(format!("invalid {} header", attr)),
+            retryable: false,
+        })?;
+        headers.insert(attr, val);
+    }
+
+    let (body, total) = file_body(id, path, sender).await.map_err(|err| PutError {
+        error: UploadError::Io(err),
+        retryable: false,
+    })?;
+
+    let web = ReqwestClient::new();
+    let res = web
+        .put(slot.put.url.as_str())
+        .headers(headers)
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| PutError {
+            error: UploadError::Http(err.to_string()),
+            retryable: true,
+        })?;
+    let status = res.status();
+    if status.is_success() {
+        Ok(total)
+    } else {
+        Err(PutError {
+            error: UploadError::HttpStatus(status.as_u16()),
+            retryable: status.is_server_error(),
+        })
+    }
+}
+
+/// Streams the file, reporting progress at most once per percent.
+#[cfg(not(target_arch = "wasm32"))]
+async fn file_body(
+    id: &UploadId,
+    path: &Path,
+    sender: &UnboundedSender<TaskEvent>,
+) -> io::Result<(ReqwestBody, u64)> {
+    use futures::TryStreamExt;
+    use tokio_util::codec::{BytesCodec, FramedRead};
+
+    let file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+
+    let mut sent = 0;
+    let mut reported = None;
+    let progress = sender.clone();
+    let progress_id = id.clone();
+    let stream = FramedRead::new(file, BytesCodec::new()).inspect_ok(move |chunk| {
+        sent += chunk.len() as u64;
+        let percent = sent * 100 / total.max(1);
+        if reported != Some(percent) {
+            reported = Some(percent);
+            let _ = progress.send(TaskEvent::Progress(progress_id.clone(), sent, total));
+        }
+    });
+    Ok((ReqwestBody::wrap_stream(stream), total))
+}
 
-    return vec![];
+/// There are no files to read in the browser.
+#[cfg(target_arch = "wasm32")]
+async fn file_body(
+    _id: &UploadId,
+    path: &Path,
+    _sender: &UnboundedSender<TaskEvent>,
+) -> io::Result<(ReqwestBody, u64)> {
+    std::fs::metadata(path)?;
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "files can't be uploaded from the browser",
+    ))
 }
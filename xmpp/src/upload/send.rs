@@ -4,33 +4,64 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::io;
 use std::path::Path;
-use tokio::fs::File;
 use tokio_xmpp::connect::ServerConnector;
 use tokio_xmpp::{
     parsers::{http_upload::SlotRequest, iq::Iq},
     Jid,
 };
 
+use crate::upload::manager::{TaskEvent, UploadError, UploadId};
 use crate::Agent;
 
 pub async fn upload_file_with<C: ServerConnector>(
     agent: &mut Agent<C>,
     service: &str,
     path: &Path,
-) {
-    let name = path.file_name().unwrap().to_str().unwrap().to_string();
-    let file = File::open(path).await.unwrap();
-    let size = file.metadata().await.unwrap().len();
-    let slot_request = SlotRequest {
-        filename: name,
-        size: size,
-        content_type: None,
-    };
+) -> UploadId {
     let to = service.parse::<Jid>().unwrap();
-    let request = Iq::from_get("upload1", slot_request).with_to(to.clone());
-    agent
-        .uploads
-        .push((String::from("upload1"), to, path.to_path_buf()));
-    agent.client.send_stanza(request.into()).await.unwrap();
+    let id = agent.client.make_id();
+    let id = agent.uploads.queue(id, to, path.to_path_buf());
+    request_slots(agent).await;
+    id
+}
+
+/// Name and size of the file to upload.
+async fn file_info(path: &Path) -> io::Result<(String, u64)> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid file name"))?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let metadata = tokio::fs::metadata(path).await?;
+    // There are no files to read in the browser.
+    #[cfg(target_arch = "wasm32")]
+    let metadata = std::fs::metadata(path)?;
+    Ok((name.to_string(), metadata.len()))
+}
+
+/// Requests slots for as many queued uploads as the concurrency limit allows.
+pub(crate) async fn request_slots<C: ServerConnector>(agent: &mut Agent<C>) {
+    while let Some((id, to, path)) = agent.uploads.next_to_request() {
+        let (name, size) = match file_info(&path).await {
+            Ok(info) => info,
+            Err(err) => {
+                agent.uploads.take_requested(&to, &id);
+                let _ =
+                    agent
+                        .uploads
+                        .sender
+                        .send(TaskEvent::Failed(id, path, UploadError::Io(err)));
+                continue;
+            }
+        };
+        let slot_request = SlotRequest {
+            filename: name,
+            size: size,
+            content_type: None,
+        };
+        let request = Iq::builder().to(to).id(id).get(slot_request);
+        let _ = agent.client.send_stanza(request.into()).await;
+    }
 }
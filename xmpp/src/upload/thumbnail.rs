@@ -0,0 +1,92 @@
+// Copyright (c) 2023 xmpp-rs contributors.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Thumbnails of the uploaded images ([XEP-0264](https://xmpp.org/extensions/xep-0264.html)),
+//! shared with Bits of Binary, see [ClientFeature::Thumbnails](crate::ClientFeature::Thumbnails).
+
+use image::{ImageError, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+use std::path::PathBuf;
+use tokio_xmpp::parsers::{bob::Data, jingle_thumnails::Thumbnail};
+use tokio_xmpp::rt;
+
+/// The largest side of a thumbnail, in pixels.
+const THUMBNAIL_SIZE: u32 = 128;
+
+/// The largest image file to make a thumbnail of, in bytes.
+const MAX_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// The largest side of an image to decode, in pixels.
+const MAX_IMAGE_SIDE: u32 = 16384;
+
+/// How much memory decoding an image may take, in bytes.
+const MAX_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Scales an image down to fit in [THUMBNAIL_SIZE], as PNG, keeping its aspect ratio.
+fn make_thumbnail(image: &[u8]) -> Result<(Data, Thumbnail), ImageError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+    limits.max_alloc = Some(MAX_ALLOC);
+    let mut reader = ImageReader::new(Cursor::new(image)).with_guessed_format()?;
+    reader.limits(limits);
+    let image = reader.decode()?;
+    let scaled = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut png = Vec::new();
+    scaled.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    let data = Data::new(Some(String::from("image/png")), png);
+    let uri = format!("cid:{}", data.cid);
+    let thumbnail = Thumbnail::new(uri, "image/png", scaled.width(), scaled.height());
+    Ok((data, thumbnail))
+}
+
+/// Makes a thumbnail of this file if it is an image, along with its data for the agent to share
+/// with whoever requests its cid. This runs on a blocking thread, not to hold up the upload task
+/// nor the agent.
+pub(crate) async fn make_thumbnail_of(path: PathBuf) -> Option<(Data, Thumbnail)> {
+    // Don’t read files which can’t be images.
+    ImageFormat::from_path(&path).ok()?;
+    let thumbnail = rt::spawn_blocking(move || {
+        if std::fs::metadata(&path).ok()?.len() > MAX_FILE_SIZE {
+            return None;
+        }
+        let image = std::fs::read(&path).ok()?;
+        make_thumbnail(&image)
+            .map_err(|err| warn!("Couldn’t make a thumbnail of {}: {}", path.display(), err))
+            .ok()
+    });
+    thumbnail.await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbImage};
+
+    #[test]
+    fn thumbnail() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(512, 256));
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let (data, thumbnail) = make_thumbnail(&png).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (128, 64));
+        assert_eq!(thumbnail.uri, format!("cid:{}", data.cid));
+        let decoded = image::load_from_memory(&data.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (128, 64));
+
+        assert!(make_thumbnail(b"not an image").is_err());
+
+        // Only the header gets read before failing, not the advertised pixels.
+        let huge = DynamicImage::ImageRgb8(RgbImage::new(MAX_IMAGE_SIDE + 1, 1));
+        let mut png = Vec::new();
+        huge.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert!(matches!(make_thumbnail(&png), Err(ImageError::Limits(_))));
+    }
+}